@@ -1,7 +1,6 @@
 use ig_client::application::services::MarketService;
 use ig_client::application::services::market_service::MarketServiceImpl;
 use ig_client::config::Config;
-use ig_client::presentation::{build_market_hierarchy, extract_markets_from_hierarchy};
 use ig_client::session::auth::IgAuth;
 use ig_client::session::interface::IgAuthenticator;
 use ig_client::transport::http_client::IgHttpClientImpl;
@@ -10,10 +9,7 @@ use ig_client::utils::rate_limiter::RateLimitType;
 use std::error::Error;
 use std::fs;
 use std::sync::Arc;
-use tracing::{debug, error, info, warn};
-
-// Constants for API request handling
-const BATCH_SIZE: usize = 10; // Number of EPICs to process before saving results
+use tracing::{error, info, warn};
 
 /// Structure to hold market summary information
 #[derive(Debug, Clone)]
@@ -139,7 +135,7 @@ async fn get_market_summaries() -> Result<Vec<MarketSummary>, Box<dyn Error>> {
     // Process EPICs one at a time to be extremely conservative with API usage
     for (index, epic) in epics.iter().enumerate() {
         // Process one EPIC at a time
-        let epics_chunk = &[epic.clone()];
+        let epics_chunk = std::slice::from_ref(epic);
 
         info!(
             "Fetching market details for EPIC {}/{}: {}",
@@ -157,17 +153,13 @@ async fn get_market_summaries() -> Result<Vec<MarketSummary>, Box<dyn Error>> {
                 for (i, details) in details_vec.iter().enumerate() {
                     let epic = &epics_chunk[i];
                     info!("✅ Successfully fetched details for {}", epic);
-                    let (mid, spread) =
-                        if details.snapshot.bid.is_none() || details.snapshot.offer.is_none() {
+                    let (mid, spread) = match (details.snapshot.bid, details.snapshot.offer) {
+                        (Some(bid), Some(offer)) => (Some(offer + bid / 2.0), Some(offer - bid)),
+                        _ => {
                             info!("❌ Missing bid or offer for {}", epic);
                             (None, None)
-                        } else {
-                            let mid = details.snapshot.offer.unwrap()
-                                + details.snapshot.bid.unwrap() / 2.0;
-                            let spread =
-                                details.snapshot.offer.unwrap() - details.snapshot.bid.unwrap();
-                            (Some(mid), Some(spread))
-                        };
+                        }
+                    };
                     let last_dealing_date = match details.instrument.expiry_details.clone() {
                         Some(expiry_details) => expiry_details.last_dealing_date,
                         None => details.instrument.expiry.clone(),
@@ -205,17 +197,15 @@ async fn get_market_summaries() -> Result<Vec<MarketSummary>, Box<dyn Error>> {
                     match market_service.get_market_details(&session, epic).await {
                         Ok(details) => {
                             info!("✅ Successfully fetched details for {}", epic);
-                            let (mid, spread) = if details.snapshot.bid.is_none()
-                                || details.snapshot.offer.is_none()
+                            let (mid, spread) = match (details.snapshot.bid, details.snapshot.offer)
                             {
-                                info!("❌ Missing bid or offer for {}", epic);
-                                (None, None)
-                            } else {
-                                let mid = details.snapshot.offer.unwrap()
-                                    + details.snapshot.bid.unwrap() / 2.0;
-                                let spread =
-                                    details.snapshot.offer.unwrap() - details.snapshot.bid.unwrap();
-                                (Some(mid), Some(spread))
+                                (Some(bid), Some(offer)) => {
+                                    (Some(offer + bid / 2.0), Some(offer - bid))
+                                }
+                                _ => {
+                                    info!("❌ Missing bid or offer for {}", epic);
+                                    (None, None)
+                                }
                             };
                             let last_dealing_date = match details.instrument.expiry_details.clone()
                             {
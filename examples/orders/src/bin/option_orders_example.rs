@@ -1,9 +1,8 @@
 use ig_client::application::models::order::{
     ClosePositionRequest, CreateOrderRequest, Direction, Status,
 };
-use ig_client::application::services::market_service::MarketServiceImpl;
+use ig_client::application::services::OrderService;
 use ig_client::application::services::order_service::OrderServiceImpl;
-use ig_client::application::services::{MarketService, OrderService};
 use ig_client::utils::rate_limiter::RateLimitType;
 use ig_client::{
     config::Config, session::auth::IgAuth, session::interface::IgAuthenticator,
@@ -21,10 +20,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         RateLimitType::TradingAccount,
         0.01,
     ));
-    let config_no_trade = Arc::new(Config::with_rate_limit_type(
-        RateLimitType::OnePerSecond,
-        0.5,
-    ));
 
     info!("Configuration loaded");
 
@@ -42,23 +37,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("Session started successfully");
 
     let epic = "DO.D.OTCDDAX.68.IP"; // Example epic for testing
-    let expiry = Some(
-        chrono::Local::now()
-            .format("%d-%b-%y")
-            .to_string()
-            .to_uppercase(),
-    );
+    let expiry = chrono::Local::now()
+        .format("%d-%b-%y")
+        .to_string()
+        .to_uppercase();
     let size = 1.25; // Size of the order
-    let currency_code = Some("EUR".to_string()); // Example currency code
-    let deal_reference = Some(nanoid!(30, &nanoid::alphabet::SAFE));
+    let currency_code = "EUR".to_string(); // Example currency code
+    let deal_reference = nanoid!(30, &nanoid::alphabet::SAFE);
     info!("{:?}", deal_reference);
-    let create_order = CreateOrderRequest::buy_option_to_market(
-        &epic.to_string(),
-        &size,
-        &expiry.clone(),
-        &deal_reference,
-        &currency_code.clone(),
-    );
+    // Options are bought with a limit far above the market so the order fills immediately
+    let mut create_order = CreateOrderRequest::limit(
+        epic.to_string(),
+        Direction::Buy,
+        size,
+        10000.0,
+        currency_code.clone(),
+    )
+    .with_reference(deal_reference);
+    create_order.expiry = expiry;
 
     // Create a market service
     // let market_service = MarketServiceImpl::new(config_no_trade, client.clone());
@@ -117,17 +113,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
 
     info!("Closing position with deal ID: {:?}", deal_id);
-    let close_request = ClosePositionRequest::close_option_to_market_by_id(
-        deal_id.unwrap(),
-        Direction::Sell, // Assuming we are closing a buy position
-        size,
-    );
-    // let close_request = ClosePositionRequest::close_option_to_market_by_epic(
-    //     epic.to_string(),
-    //     expiry.clone().unwrap(),
-    //     Direction::Sell, // Assuming we are closing a buy position
-    //     size,
-    // );
+    // Assuming we are closing a buy position
+    let close_request = ClosePositionRequest::for_deal(&deal_id.unwrap(), &Direction::Buy, size)
+        .with_market(epic, &currency_code)
+        .with_level(0.0);
     let close_result = order_service.close_position(&session, &close_request).await;
 
     match close_result {
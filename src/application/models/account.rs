@@ -3,12 +3,21 @@
    Email: jb@taunais.com
    Date: 13/5/25
 ******************************************************************************/
-use super::order::{Direction, OrderType, Status, TimeInForce};
+use super::order::{
+    CreateOrderRequest, DealFilter, Direction, OrderType, Status, TimeInForce,
+    UpdatePositionRequest,
+};
 use crate::application::models::market::InstrumentType;
+use crate::error::AppError;
 use crate::impl_json_display;
 use crate::presentation::MarketState;
+use crate::utils::currency::CurrencyConverter;
+use crate::utils::money::Money;
+use crate::utils::parsing::parse_instrument_name;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
 use std::ops::Add;
 
 /// Account information
@@ -18,40 +27,384 @@ pub struct AccountInfo {
     pub accounts: Vec<Account>,
 }
 
+/// Type of an IG account
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum AccountType {
+    /// Contract for difference account
+    Cfd,
+    /// Spread betting account
+    Spreadbet,
+    /// Share dealing account
+    Physical,
+    /// A type not known to this client
+    Other(String),
+}
+
+impl AccountType {
+    /// Returns the IG code of the type
+    pub fn as_str(&self) -> &str {
+        match self {
+            AccountType::Cfd => "CFD",
+            AccountType::Spreadbet => "SPREADBET",
+            AccountType::Physical => "PHYSICAL",
+            AccountType::Other(code) => code,
+        }
+    }
+}
+
+impl From<String> for AccountType {
+    fn from(code: String) -> Self {
+        match code.as_str() {
+            "CFD" => AccountType::Cfd,
+            "SPREADBET" => AccountType::Spreadbet,
+            "PHYSICAL" => AccountType::Physical,
+            _ => AccountType::Other(code),
+        }
+    }
+}
+
+impl From<AccountType> for String {
+    fn from(account_type: AccountType) -> Self {
+        account_type.as_str().to_string()
+    }
+}
+
+impl Display for AccountType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Status of an IG account
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum AccountStatus {
+    /// The account can be used
+    Enabled,
+    /// The account is disabled
+    Disabled,
+    /// The account cannot deal
+    SuspendedFromDealing,
+    /// A status not known to this client
+    Other(String),
+}
+
+impl AccountStatus {
+    /// Returns the IG code of the status
+    pub fn as_str(&self) -> &str {
+        match self {
+            AccountStatus::Enabled => "ENABLED",
+            AccountStatus::Disabled => "DISABLED",
+            AccountStatus::SuspendedFromDealing => "SUSPENDED_FROM_DEALING",
+            AccountStatus::Other(code) => code,
+        }
+    }
+}
+
+impl From<String> for AccountStatus {
+    fn from(code: String) -> Self {
+        match code.as_str() {
+            "ENABLED" => AccountStatus::Enabled,
+            "DISABLED" => AccountStatus::Disabled,
+            "SUSPENDED_FROM_DEALING" => AccountStatus::SuspendedFromDealing,
+            _ => AccountStatus::Other(code),
+        }
+    }
+}
+
+impl From<AccountStatus> for String {
+    fn from(status: AccountStatus) -> Self {
+        status.as_str().to_string()
+    }
+}
+
+impl Display for AccountStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Details of a specific account
 #[derive(Debug, Clone, Deserialize)]
+#[serde(from = "AccountResponse")]
 pub struct Account {
     /// Unique identifier for the account
-    #[serde(rename = "accountId")]
     pub account_id: String,
     /// Name of the account
-    #[serde(rename = "accountName")]
     pub account_name: String,
     /// Type of the account (e.g., CFD, Spread bet)
-    #[serde(rename = "accountType")]
-    pub account_type: String,
-    /// Balance information for the account
+    pub account_type: AccountType,
+    /// Balance information for the account, in the account currency
     pub balance: AccountBalance,
     /// Base currency of the account
     pub currency: String,
     /// Current status of the account
-    pub status: String,
+    pub status: AccountStatus,
     /// Whether this is the preferred account
     pub preferred: bool,
 }
 
+/// Account as returned by IG, with the balance amounts apart from their currency
+#[derive(Deserialize)]
+struct AccountResponse {
+    #[serde(rename = "accountId")]
+    account_id: String,
+    #[serde(rename = "accountName")]
+    account_name: String,
+    #[serde(rename = "accountType")]
+    account_type: AccountType,
+    balance: BalanceResponse,
+    currency: String,
+    status: AccountStatus,
+    preferred: bool,
+}
+
+#[derive(Deserialize)]
+struct BalanceResponse {
+    balance: f64,
+    deposit: f64,
+    #[serde(rename = "profitLoss")]
+    profit_loss: f64,
+    available: f64,
+}
+
+impl From<AccountResponse> for Account {
+    fn from(response: AccountResponse) -> Self {
+        let balance = response.balance;
+        Self {
+            balance: AccountBalance::new(
+                &response.currency,
+                balance.balance,
+                balance.deposit,
+                balance.profit_loss,
+                balance.available,
+            ),
+            account_id: response.account_id,
+            account_name: response.account_name,
+            account_type: response.account_type,
+            currency: response.currency,
+            status: response.status,
+            preferred: response.preferred,
+        }
+    }
+}
+
 /// Account balance information
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct AccountBalance {
     /// Total balance of the account
-    pub balance: f64,
+    pub balance: Money,
     /// Deposit amount
-    pub deposit: f64,
+    pub deposit: Money,
     /// Current profit or loss
     #[serde(rename = "profitLoss")]
-    pub profit_loss: f64,
+    pub profit_loss: Money,
     /// Available funds for trading
-    pub available: f64,
+    pub available: Money,
+}
+
+impl AccountBalance {
+    /// Creates a balance whose amounts are all in `currency`
+    pub fn new(
+        currency: &str,
+        balance: f64,
+        deposit: f64,
+        profit_loss: f64,
+        available: f64,
+    ) -> Self {
+        Self {
+            balance: Money::new(balance, currency),
+            deposit: Money::new(deposit, currency),
+            profit_loss: Money::new(profit_loss, currency),
+            available: Money::new(available, currency),
+        }
+    }
+}
+
+/// Margin held by one open position
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionMargin {
+    /// Deal ID of the position
+    pub deal_id: String,
+    /// EPIC of the market
+    pub epic: String,
+    /// Margin in the account currency
+    pub margin: f64,
+}
+
+/// Margin and equity of an account
+///
+/// Amounts are in the account currency. The equity is the balance plus the profit or
+/// loss of the open positions, and the margin level is the equity as a percentage of
+/// the used margin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarginSummary {
+    /// Account the summary is for
+    pub account_id: String,
+    /// Currency of the account
+    pub currency: String,
+    /// Balance of the account
+    pub balance: f64,
+    /// Profit or loss of the open positions
+    pub unrealized_pnl: f64,
+    /// Balance plus the profit or loss of the open positions
+    pub equity: f64,
+    /// Margin held by the open positions
+    pub used_margin: f64,
+    /// Equity not held as margin
+    pub free_margin: f64,
+    /// Equity as a percentage of the used margin, `None` without open positions
+    pub margin_level: Option<f64>,
+    /// Margin of each open position
+    pub positions: Vec<PositionMargin>,
+}
+
+impl MarginSummary {
+    /// Builds the summary of `account` holding the margins of `positions`
+    pub fn new(account: &Account, positions: Vec<PositionMargin>) -> Self {
+        let balance = account.balance.balance.amount;
+        let unrealized_pnl = account.balance.profit_loss.amount;
+        let equity = balance + unrealized_pnl;
+        let used_margin: f64 = positions.iter().map(|position| position.margin).sum();
+        Self {
+            account_id: account.account_id.clone(),
+            currency: account.currency.clone(),
+            balance,
+            unrealized_pnl,
+            equity,
+            used_margin,
+            free_margin: equity - used_margin,
+            margin_level: (used_margin > 0.0).then(|| equity / used_margin * 100.0),
+            positions,
+        }
+    }
+}
+
+/// Exposure of the positions quoted in one currency
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurrencyExposure {
+    /// Currency of the instruments
+    pub currency: String,
+    /// Number of positions
+    pub positions: usize,
+    /// Notional of the long positions, in `currency`
+    pub long_notional: f64,
+    /// Notional of the short positions, in `currency`
+    pub short_notional: f64,
+    /// Long plus short notional, in the base currency
+    pub gross_in_base: f64,
+    /// Long minus short notional, in the base currency
+    pub net_in_base: f64,
+    /// Margin of the positions, in the base currency, when margins were given
+    pub margin: f64,
+}
+
+/// Notional exposure and margin per instrument currency
+///
+/// Notionals are valued at the mid price of each position's market, divided by its
+/// scaling factor. Margins come from
+/// a [`MarginSummary`] and are already in the account currency, so the converter
+/// should have the account currency as base.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExposureReport {
+    /// Currency of the amounts in base currency
+    pub base_currency: String,
+    /// Exposure of each currency, largest gross exposure first
+    pub currencies: Vec<CurrencyExposure>,
+}
+
+impl ExposureReport {
+    /// Aggregates the exposure of `positions` per currency
+    ///
+    /// # Arguments
+    /// * `positions` - Open positions
+    /// * `converter` - Rates of the position currencies into the base currency
+    /// * `margins` - Margins by position, e.g. [`MarginSummary::positions`]; may be empty
+    ///
+    /// # Returns
+    /// The report, or `AppError::InvalidInput` if no rate is known for a currency
+    pub fn new(
+        positions: &Positions,
+        converter: &CurrencyConverter,
+        margins: &[PositionMargin],
+    ) -> Result<Self, AppError> {
+        let margin_of: HashMap<&str, f64> = margins
+            .iter()
+            .map(|margin| (margin.deal_id.as_str(), margin.margin))
+            .collect();
+        let mut currencies: BTreeMap<&str, CurrencyExposure> = BTreeMap::new();
+        for position in &positions.positions {
+            let details = &position.position;
+            let currency = details.currency.as_str();
+            let mid = (position.market.bid + position.market.offer) / 2.0;
+            let notional =
+                details.size * details.contract_size * mid / position.market.price_scale();
+            let notional_in_base = converter.to_base(notional, currency)?;
+            let exposure = currencies
+                .entry(currency)
+                .or_insert_with(|| CurrencyExposure {
+                    currency: currency.to_string(),
+                    positions: 0,
+                    long_notional: 0.0,
+                    short_notional: 0.0,
+                    gross_in_base: 0.0,
+                    net_in_base: 0.0,
+                    margin: 0.0,
+                });
+            exposure.positions += 1;
+            exposure.gross_in_base += notional_in_base;
+            match details.direction {
+                Direction::Buy => {
+                    exposure.long_notional += notional;
+                    exposure.net_in_base += notional_in_base;
+                }
+                Direction::Sell => {
+                    exposure.short_notional += notional;
+                    exposure.net_in_base -= notional_in_base;
+                }
+            }
+            exposure.margin += margin_of
+                .get(details.deal_id.as_str())
+                .copied()
+                .unwrap_or_default();
+        }
+        let mut currencies: Vec<CurrencyExposure> = currencies.into_values().collect();
+        currencies.sort_by(|a, b| b.gross_in_base.total_cmp(&a.gross_in_base));
+        Ok(Self {
+            base_currency: converter.base_currency().to_string(),
+            currencies,
+        })
+    }
+
+    /// Returns the exposure of a currency
+    pub fn currency(&self, currency: &str) -> Option<&CurrencyExposure> {
+        self.currencies
+            .iter()
+            .find(|exposure| exposure.currency == currency)
+    }
+
+    /// Total gross exposure, in the base currency
+    pub fn total_gross(&self) -> f64 {
+        self.currencies
+            .iter()
+            .map(|exposure| exposure.gross_in_base)
+            .sum()
+    }
+
+    /// Total margin, in the base currency
+    pub fn total_margin(&self) -> f64 {
+        self.currencies.iter().map(|exposure| exposure.margin).sum()
+    }
+
+    /// Percentage of the gross exposure in a currency, `None` without exposure
+    pub fn concentration(&self, currency: &str) -> Option<f64> {
+        let total = self.total_gross();
+        (total > 0.0).then(|| {
+            self.currency(currency)
+                .map_or(0.0, |exposure| exposure.gross_in_base / total * 100.0)
+        })
+    }
 }
 
 /// Account activity
@@ -63,6 +416,103 @@ pub struct AccountActivity {
     pub metadata: Option<ActivityMetadata>,
 }
 
+impl AccountActivity {
+    /// Returns the path of the next page, relative to the API root
+    ///
+    /// IG returns the next page as a path carrying the original query and a cursor,
+    /// e.g. `/history/activity?version=3&from=...`.
+    pub fn next_page_path(&self) -> Option<String> {
+        let next = self.metadata.as_ref()?.paging.as_ref()?.next.as_deref()?;
+        let start = next.find("history/activity")?;
+        Some(next[start..].to_string())
+    }
+}
+
+/// Query of the v3 activity history
+///
+/// The filter is a FIQL expression on the fields of the activities, e.g.
+/// `type==POSITION;epic==IX.D.DAX.DAILY.IP`, where `;` is a logical and and `,` a
+/// logical or.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActivityQuery {
+    /// Start date time (`yyyy-MM-ddTHH:mm:ss`)
+    pub from: String,
+    /// End date time (`yyyy-MM-ddTHH:mm:ss`), now when unset
+    pub to: Option<String>,
+    /// FIQL filter
+    pub filter: Option<String>,
+    /// Whether to include the details of every activity
+    pub detailed: bool,
+    /// Number of activities per page
+    pub page_size: Option<u32>,
+}
+
+impl ActivityQuery {
+    /// Creates a query of the activities since `from`
+    pub fn new(from: &str) -> Self {
+        Self {
+            from: from.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Only selects activities up to `to`
+    pub fn with_to(mut self, to: &str) -> Self {
+        self.to = Some(to.to_string());
+        self
+    }
+
+    /// Only selects activities matching the FIQL `filter`
+    pub fn with_filter(mut self, filter: &str) -> Self {
+        self.filter = Some(filter.to_string());
+        self
+    }
+
+    /// Includes the details of every activity
+    pub fn detailed(mut self) -> Self {
+        self.detailed = true;
+        self
+    }
+
+    /// Sets the number of activities per page
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    /// Builds the query string, without the leading `?`
+    pub fn to_query_string(&self) -> String {
+        let mut params = vec![format!("from={}", self.from)];
+        if let Some(to) = &self.to {
+            params.push(format!("to={to}"));
+        }
+        if self.detailed {
+            params.push("detailed=true".to_string());
+        }
+        if let Some(filter) = &self.filter {
+            params.push(format!("filter={}", encode_query_value(filter)));
+        }
+        if let Some(page_size) = self.page_size {
+            params.push(format!("pageSize={page_size}"));
+        }
+        params.join("&")
+    }
+}
+
+/// Percent-encodes every character of `value` but the unreserved ones
+fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
 /// Metadata for activity pagination
 #[derive(Debug, Clone, Deserialize)]
 pub struct ActivityMetadata {
@@ -124,7 +574,7 @@ pub struct Activity {
     pub description: Option<String>,
     /// Additional details about the activity
     /// This is a string when detailed=false, and an object when detailed=true
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_activity_details")]
     pub details: Option<ActivityDetails>,
     /// Channel the activity occurred on (e.g., "WEB" or "Mobile")
     #[serde(default)]
@@ -137,6 +587,40 @@ pub struct Activity {
     pub level: Option<String>,
 }
 
+impl Activity {
+    /// Returns the actions of the activity, empty unless requested with `detailed=true`
+    pub fn actions(&self) -> &[ActivityAction] {
+        self.details
+            .as_ref()
+            .map(|details| details.actions.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns true if the activity concerns the deal, directly or through an action
+    pub fn involves_deal(&self, deal_id: &str) -> bool {
+        self.deal_id.as_deref() == Some(deal_id)
+            || self
+                .actions()
+                .iter()
+                .any(|action| action.affected_deal_id.as_deref() == Some(deal_id))
+    }
+}
+
+/// Reads the details of an activity, which are only an object with `detailed=true`
+fn deserialize_activity_details<'de, D>(
+    deserializer: D,
+) -> Result<Option<ActivityDetails>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match serde_json::Value::deserialize(deserializer)? {
+        value @ serde_json::Value::Object(_) => serde_json::from_value(value)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
 /// Detailed information about an activity
 /// Only available when using the detailed=true parameter
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -189,8 +673,11 @@ pub struct ActivityDetails {
 }
 
 /// Types of actions that can be performed on an activity
-#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub enum ActionType {
+    /// A limit order was amended
+    #[serde(rename = "LIMIT_ORDER_AMENDED")]
+    LimitOrderAmended,
     /// A limit order was deleted
     #[serde(rename = "LIMIT_ORDER_DELETED")]
     LimitOrderDeleted,
@@ -236,16 +723,75 @@ pub enum ActionType {
     /// A stop order was rolled
     #[serde(rename = "STOP_ORDER_ROLLED")]
     StopOrderRolled,
-    /// Unknown action type
-    #[serde(rename = "UNKNOWN")]
-    Unknown,
     /// A working order was deleted
     #[serde(rename = "WORKING_ORDER_DELETED")]
     WorkingOrderDeleted,
+    /// Unknown action type, also used for action types added by IG later
+    #[serde(rename = "UNKNOWN", other)]
+    Unknown,
 }
 
 impl_json_display!(ActionType);
 
+/// Step of the life of a deal marked by an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DealStage {
+    /// The position or working order was created
+    Opened,
+    /// Levels were amended or the deal was rolled to a new expiry
+    Amended,
+    /// Part of the position was closed
+    PartiallyClosed,
+    /// The position was closed, or the working order filled or deleted
+    Closed,
+    /// The action does not tell
+    Unknown,
+}
+
+impl ActionType {
+    /// Returns the step of the life of the affected deal marked by this action
+    pub fn stage(&self) -> DealStage {
+        match self {
+            ActionType::PositionOpened
+            | ActionType::LimitOrderOpened
+            | ActionType::StopOrderOpened => DealStage::Opened,
+            ActionType::StopLimitAmended
+            | ActionType::LimitOrderAmended
+            | ActionType::StopOrderAmended
+            | ActionType::PositionRolled
+            | ActionType::LimitOrderRolled
+            | ActionType::StopOrderRolled => DealStage::Amended,
+            ActionType::PositionPartiallyClosed => DealStage::PartiallyClosed,
+            ActionType::PositionClosed
+            | ActionType::PositionDeleted
+            | ActionType::LimitOrderFilled
+            | ActionType::LimitOrderDeleted
+            | ActionType::StopOrderFilled
+            | ActionType::StopOrderDeleted
+            | ActionType::WorkingOrderDeleted => DealStage::Closed,
+            ActionType::Unknown => DealStage::Unknown,
+        }
+    }
+
+    /// Returns true if the affected deal is a position
+    pub fn affects_position(&self) -> bool {
+        matches!(
+            self,
+            ActionType::PositionOpened
+                | ActionType::PositionClosed
+                | ActionType::PositionDeleted
+                | ActionType::PositionPartiallyClosed
+                | ActionType::PositionRolled
+                | ActionType::StopLimitAmended
+        )
+    }
+
+    /// Returns true if the affected deal is a working order
+    pub fn affects_working_order(&self) -> bool {
+        !self.affects_position() && *self != ActionType::Unknown
+    }
+}
+
 /// Action associated with an activity
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ActivityAction {
@@ -257,6 +803,221 @@ pub struct ActivityAction {
     pub affected_deal_id: Option<String>,
 }
 
+impl ActivityAction {
+    /// Returns the open position affected by this action, if any
+    pub fn affected_position<'a>(&self, positions: &'a Positions) -> Option<&'a Position> {
+        if !self.action_type.affects_position() {
+            return None;
+        }
+        positions.find_by_deal_id(self.affected_deal_id.as_deref()?)
+    }
+
+    /// Returns the pending working order affected by this action, if any
+    pub fn affected_working_order<'a>(
+        &self,
+        working_orders: &'a WorkingOrders,
+    ) -> Option<&'a WorkingOrder> {
+        if !self.action_type.affects_working_order() {
+            return None;
+        }
+        working_orders.find(self.affected_deal_id.as_deref()?)
+    }
+}
+
+/// Action of an activity on a deal, with the activity it belongs to
+#[derive(Debug, Clone)]
+pub struct DealEvent {
+    /// Date of the activity
+    pub date: String,
+    /// Action on the deal
+    pub action_type: ActionType,
+    /// Activity carrying the action
+    pub activity: Activity,
+}
+
+impl DealEvent {
+    /// Step of the life of the deal
+    pub fn stage(&self) -> DealStage {
+        self.action_type.stage()
+    }
+}
+
+/// Life of a deal rebuilt from detailed activities, from opening to closing
+#[derive(Debug, Clone)]
+pub struct DealLifecycle {
+    /// Deal ID of the position or working order
+    pub deal_id: String,
+    /// Actions on the deal, oldest first
+    pub events: Vec<DealEvent>,
+}
+
+impl DealLifecycle {
+    /// Rebuilds the life of a deal from activities in any order
+    ///
+    /// Only the actions of detailed activities (`detailed=true`) affecting `deal_id`
+    /// are kept.
+    pub fn from_activities(deal_id: &str, activities: &[Activity]) -> Self {
+        let mut events: Vec<DealEvent> = activities
+            .iter()
+            .flat_map(|activity| {
+                activity
+                    .actions()
+                    .iter()
+                    .filter(|action| action.affected_deal_id.as_deref() == Some(deal_id))
+                    .map(|action| DealEvent {
+                        date: activity.date.clone(),
+                        action_type: action.action_type,
+                        activity: activity.clone(),
+                    })
+            })
+            .collect();
+        // Dates share the same ISO format, so they sort as strings
+        events.sort_by(|a, b| a.date.cmp(&b.date));
+        Self {
+            deal_id: deal_id.to_string(),
+            events,
+        }
+    }
+
+    /// Returns the event opening the deal
+    pub fn opened(&self) -> Option<&DealEvent> {
+        self.events
+            .iter()
+            .find(|event| event.stage() == DealStage::Opened)
+    }
+
+    /// Returns the amendments of the deal, oldest first
+    pub fn amendments(&self) -> impl Iterator<Item = &DealEvent> {
+        self.events
+            .iter()
+            .filter(|event| event.stage() == DealStage::Amended)
+    }
+
+    /// Returns the partial closes of the deal, oldest first
+    pub fn partial_closes(&self) -> impl Iterator<Item = &DealEvent> {
+        self.events
+            .iter()
+            .filter(|event| event.stage() == DealStage::PartiallyClosed)
+    }
+
+    /// Returns the event closing the deal
+    pub fn closed(&self) -> Option<&DealEvent> {
+        self.events
+            .iter()
+            .find(|event| event.stage() == DealStage::Closed)
+    }
+
+    /// Returns true if the deal was closed, filled or deleted
+    pub fn is_closed(&self) -> bool {
+        self.closed().is_some()
+    }
+}
+
+/// State of a deal reference, as found by [`Reconciliation::new`]
+#[derive(Debug, Clone)]
+pub enum ReconciledDeal {
+    /// The reference opened a position that is still open
+    Open(Box<Position>),
+    /// The reference created a working order that is still pending
+    Pending(Box<WorkingOrder>),
+    /// The reference was accepted but its deal is no longer open
+    Closed {
+        /// Deal ID of the deal, if the activity reports it
+        deal_id: Option<String>,
+        /// Date of the activity closing the deal, if found among the activities
+        closed_at: Option<String>,
+    },
+    /// The reference was rejected
+    Rejected,
+    /// The reference is neither open nor found among the activities
+    Unknown,
+}
+
+/// State of a set of deal references, to recover after losing local state
+///
+/// References are looked up among the open positions, then the working orders, then
+/// the activities, whose `dealReference` is the one of the order that created them.
+#[derive(Debug, Clone, Default)]
+pub struct Reconciliation {
+    /// State of each reference
+    pub deals: BTreeMap<String, ReconciledDeal>,
+}
+
+impl Reconciliation {
+    /// Finds the state of `deal_references`
+    ///
+    /// Closing dates are only found in the actions of detailed activities.
+    pub fn new(
+        deal_references: &[String],
+        positions: &Positions,
+        working_orders: &WorkingOrders,
+        activities: &[Activity],
+    ) -> Self {
+        let deals = deal_references
+            .iter()
+            .map(|reference| {
+                let state = if let Some(position) = positions.find_by_reference(reference) {
+                    ReconciledDeal::Open(Box::new(position.clone()))
+                } else if let Some(order) = working_orders.find_by_reference(reference) {
+                    ReconciledDeal::Pending(Box::new(order.clone()))
+                } else {
+                    Self::from_activities(reference, activities)
+                };
+                (reference.clone(), state)
+            })
+            .collect();
+        Self { deals }
+    }
+
+    fn from_activities(reference: &str, activities: &[Activity]) -> ReconciledDeal {
+        let Some(activity) = activities
+            .iter()
+            .find(|activity| activity.deal_reference.as_deref() == Some(reference))
+        else {
+            return ReconciledDeal::Unknown;
+        };
+        if activity.status == Some(Status::Rejected) {
+            return ReconciledDeal::Rejected;
+        }
+        let closed_at = activity.deal_id.as_deref().and_then(|deal_id| {
+            DealLifecycle::from_activities(deal_id, activities)
+                .closed()
+                .map(|event| event.date.clone())
+        });
+        ReconciledDeal::Closed {
+            deal_id: activity.deal_id.clone(),
+            closed_at,
+        }
+    }
+
+    /// Returns the state of a reference
+    pub fn get(&self, deal_reference: &str) -> Option<&ReconciledDeal> {
+        self.deals.get(deal_reference)
+    }
+
+    /// Returns the references of open positions
+    pub fn open(&self) -> impl Iterator<Item = &str> {
+        self.select(|deal| matches!(deal, ReconciledDeal::Open(_)))
+    }
+
+    /// Returns the references of deals no longer open
+    pub fn closed(&self) -> impl Iterator<Item = &str> {
+        self.select(|deal| matches!(deal, ReconciledDeal::Closed { .. }))
+    }
+
+    /// Returns the references found nowhere
+    pub fn unknown(&self) -> impl Iterator<Item = &str> {
+        self.select(|deal| matches!(deal, ReconciledDeal::Unknown))
+    }
+
+    fn select(&self, keep: fn(&ReconciledDeal) -> bool) -> impl Iterator<Item = &str> {
+        self.deals
+            .iter()
+            .filter(move |(_, deal)| keep(deal))
+            .map(|(reference, _)| reference.as_str())
+    }
+}
+
 /// Open positions
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct Positions {
@@ -290,6 +1051,113 @@ impl Positions {
 
         epic_map.into_values().collect()
     }
+
+    /// Returns the position opened with the given deal reference
+    pub fn find_by_reference(&self, deal_reference: &str) -> Option<&Position> {
+        self.positions
+            .iter()
+            .find(|position| position.position.deal_reference == deal_reference)
+    }
+
+    /// Returns the position with the given deal ID
+    pub fn find_by_deal_id(&self, deal_id: &str) -> Option<&Position> {
+        self.positions
+            .iter()
+            .find(|position| position.position.deal_id == deal_id)
+    }
+
+    /// Returns the net exposure of the positions selected by `filter` after `order`
+    ///
+    /// Long and short positions are both counted whatever the direction of `filter`,
+    /// so a filter on an underlying aggregates its options and the underlying market
+    /// alike. An order on an EPIC not selected by `filter` leaves the exposure as it is.
+    /// Notional values use the level of the order, or else the mid price of the
+    /// selected positions.
+    pub fn exposure_after(&self, filter: &DealFilter, order: &CreateOrderRequest) -> NetExposure {
+        let filter = DealFilter {
+            direction: None,
+            ..filter.clone()
+        };
+        let selected = filter.matches(&order.epic, &order.direction);
+        let mut exposure = NetExposure {
+            long_size: 0.0,
+            short_size: 0.0,
+            order_size: match order.direction {
+                _ if !selected => 0.0,
+                Direction::Buy => order.size,
+                Direction::Sell => -order.size,
+            },
+            price: order.level.filter(|_| selected),
+        };
+        for position in &self.positions {
+            let details = &position.position;
+            if !filter.matches(&position.market.epic, &details.direction) {
+                continue;
+            }
+            match details.direction {
+                Direction::Buy => exposure.long_size += details.size,
+                Direction::Sell => exposure.short_size += details.size,
+            }
+            if exposure.price.is_none() && position.market.epic == order.epic {
+                exposure.price = Some((position.market.bid + position.market.offer) / 2.0);
+            }
+        }
+        exposure
+    }
+}
+
+/// Net exposure on a market or an underlying, before and after a proposed order
+///
+/// Net sizes are signed: positive when long, negative when short.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetExposure {
+    /// Total size of the long positions
+    pub long_size: f64,
+    /// Total size of the short positions
+    pub short_size: f64,
+    /// Signed size of the proposed order
+    pub order_size: f64,
+    /// Price of the market, if known, for notional values
+    pub price: Option<f64>,
+}
+
+impl NetExposure {
+    /// Net size before the order
+    pub fn current(&self) -> f64 {
+        self.long_size - self.short_size
+    }
+
+    /// Net size once the order is filled
+    pub fn projected(&self) -> f64 {
+        self.current() + self.order_size
+    }
+
+    /// Direction of the net exposure once the order is filled, `None` when flat
+    pub fn projected_direction(&self) -> Option<Direction> {
+        let projected = self.projected();
+        if projected.abs() < 1e-9 {
+            None
+        } else if projected > 0.0 {
+            Some(Direction::Buy)
+        } else {
+            Some(Direction::Sell)
+        }
+    }
+
+    /// Returns true if the order reduces the net exposure
+    pub fn is_reducing(&self) -> bool {
+        self.projected().abs() < self.current().abs()
+    }
+
+    /// Returns true if the order turns a long exposure into a short one or the reverse
+    pub fn flips(&self) -> bool {
+        self.current() * self.projected() < 0.0
+    }
+
+    /// Notional value of the net exposure once the order is filled
+    pub fn projected_notional(&self) -> Option<f64> {
+        self.price.map(|price| self.projected().abs() * price)
+    }
 }
 
 /// Individual position
@@ -303,6 +1171,85 @@ pub struct Position {
     pub pnl: Option<f64>,
 }
 
+impl Position {
+    /// Returns the name of the underlying, e.g. `Germany 40` for its weekly options
+    ///
+    /// Parsed from the instrument name with [`parse_instrument_name`], which drops the
+    /// strike, the option type and series prefixes such as `Daily` or `Weekly`.
+    pub fn underlying(&self) -> String {
+        parse_instrument_name(&self.market.instrument_name).asset_name
+    }
+
+    /// Returns the profit or loss of the position if closed at the given quote
+    ///
+    /// Longs close at the bid and shorts at the offer. The points won or lost are
+    /// multiplied by the size and the contract size and divided by the scaling factor of
+    /// the market, giving an amount in the currency of the position.
+    pub fn unrealized_pnl(&self, current_bid: f64, current_offer: f64) -> f64 {
+        let details = &self.position;
+        let points = match details.direction {
+            Direction::Buy => current_bid - details.level,
+            Direction::Sell => details.level - current_offer,
+        };
+        points * details.size * details.contract_size / self.market.price_scale()
+    }
+
+    /// Returns the unrealized profit or loss converted into the account currency
+    ///
+    /// # Returns
+    /// The amount in the base currency of `converter`, or `AppError::InvalidInput` if no
+    /// rate is known for the currency of the position
+    pub fn unrealized_pnl_in(
+        &self,
+        current_bid: f64,
+        current_offer: f64,
+        converter: &CurrencyConverter,
+    ) -> Result<f64, AppError> {
+        converter.to_base(
+            self.unrealized_pnl(current_bid, current_offer),
+            &self.position.currency,
+        )
+    }
+
+    /// Builds the update giving this position a stop and a take profit
+    ///
+    /// IG replaces both levels on every update, so a level left as `None` keeps its
+    /// current value. When only the limit changes, a trailing stop stays trailing.
+    ///
+    /// # Returns
+    /// The update, or `AppError::InvalidInput` if a level is on the wrong side of the
+    /// current closing price of the position
+    pub fn attached_orders_update(
+        &self,
+        stop_level: Option<f64>,
+        limit_level: Option<f64>,
+    ) -> Result<UpdatePositionRequest, AppError> {
+        let details = &self.position;
+        let limit_level = limit_level.or(details.limit_level);
+        let trailing = (
+            details.stop_level,
+            details.trailing_stop_distance,
+            details.trailing_step,
+        );
+        let update = match (stop_level, trailing) {
+            (None, (Some(stop), Some(distance), Some(increment))) => {
+                let mut update = UpdatePositionRequest::trailing_stop(stop, distance, increment);
+                update.limit_level = limit_level;
+                update
+            }
+            (stop_level, _) => {
+                UpdatePositionRequest::levels(stop_level.or(details.stop_level), limit_level)
+            }
+        };
+        let closing_price = match details.direction {
+            Direction::Buy => self.market.bid,
+            Direction::Sell => self.market.offer,
+        };
+        update.validate_levels(&details.direction, closing_price)?;
+        Ok(update)
+    }
+}
+
 impl Add for Position {
     type Output = Position;
 
@@ -455,6 +1402,20 @@ pub struct PositionMarket {
     pub scaling_factor: i64,
 }
 
+impl PositionMarket {
+    /// Returns the factor dividing the quoted prices into prices of the instrument
+    ///
+    /// FX markets quote in pips, e.g. `10850.0` with a scaling factor of `10000` for a
+    /// EUR/USD at `1.085`. A missing or invalid factor is taken as 1.
+    pub fn price_scale(&self) -> f64 {
+        if self.scaling_factor > 0 {
+            self.scaling_factor as f64
+        } else {
+            1.0
+        }
+    }
+}
+
 /// Working orders
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WorkingOrders {
@@ -463,6 +1424,84 @@ pub struct WorkingOrders {
     pub working_orders: Vec<WorkingOrder>,
 }
 
+impl WorkingOrders {
+    /// Returns the working order with the given deal ID
+    pub fn find(&self, deal_id: &str) -> Option<&WorkingOrder> {
+        self.working_orders
+            .iter()
+            .find(|order| order.working_order_data.deal_id == deal_id)
+    }
+
+    /// Returns the working order created with the given deal reference
+    pub fn find_by_reference(&self, deal_reference: &str) -> Option<&WorkingOrder> {
+        self.working_orders.iter().find(|order| {
+            order.working_order_data.deal_reference.as_deref() == Some(deal_reference)
+        })
+    }
+
+    /// Returns the working orders on the given EPIC
+    pub fn by_epic(&self, epic: &str) -> WorkingOrders {
+        self.filtered(|order| order.working_order_data.epic == epic)
+    }
+
+    /// Returns the working orders in the given direction
+    pub fn by_direction(&self, direction: &Direction) -> WorkingOrders {
+        self.filtered(|order| order.working_order_data.direction == *direction)
+    }
+
+    /// Returns the working orders expiring strictly before `date`
+    ///
+    /// Orders without an expiry date, such as good till cancelled orders, are left out.
+    pub fn expiring_before(&self, date: DateTime<Utc>) -> WorkingOrders {
+        self.filtered(|order| {
+            order
+                .working_order_data
+                .expiry()
+                .is_some_and(|expiry| expiry < date)
+        })
+    }
+
+    /// Returns the working orders whose deal reference starts with `prefix`
+    pub fn with_reference(&self, prefix: &str) -> WorkingOrders {
+        self.filtered(|order| {
+            order
+                .working_order_data
+                .deal_reference
+                .as_deref()
+                .is_some_and(|reference| reference.starts_with(prefix))
+        })
+    }
+
+    /// Returns the total size of the working orders in the given direction
+    pub fn total_size(&self, direction: &Direction) -> f64 {
+        self.working_orders
+            .iter()
+            .map(|order| &order.working_order_data)
+            .filter(|data| data.direction == *direction)
+            .map(|data| data.order_size)
+            .sum()
+    }
+
+    /// Returns the total size of the working orders as `(buy, sell)`
+    pub fn totals_by_direction(&self) -> (f64, f64) {
+        (
+            self.total_size(&Direction::Buy),
+            self.total_size(&Direction::Sell),
+        )
+    }
+
+    fn filtered(&self, predicate: impl Fn(&WorkingOrder) -> bool) -> WorkingOrders {
+        WorkingOrders {
+            working_orders: self
+                .working_orders
+                .iter()
+                .filter(|order| predicate(order))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
 /// Working order
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WorkingOrder {
@@ -474,6 +1513,100 @@ pub struct WorkingOrder {
     pub market_data: MarketData,
 }
 
+impl WorkingOrder {
+    /// Returns the name of the underlying, as for [`Position::underlying`]
+    pub fn underlying(&self) -> String {
+        parse_instrument_name(&self.market_data.instrument_name).asset_name
+    }
+}
+
+/// Positions and working orders on one underlying, its options included
+#[derive(Debug, Clone, Default)]
+pub struct UnderlyingGroup {
+    /// Name of the underlying, e.g. `Germany 40`
+    pub underlying: String,
+    /// Open positions on the underlying
+    pub positions: Vec<Position>,
+    /// Working orders on the underlying
+    pub working_orders: Vec<WorkingOrder>,
+}
+
+impl UnderlyingGroup {
+    /// Groups positions and working orders by underlying, sorted by name
+    pub fn group(positions: &Positions, working_orders: &WorkingOrders) -> Vec<UnderlyingGroup> {
+        fn group_of(
+            groups: &mut BTreeMap<String, UnderlyingGroup>,
+            underlying: String,
+        ) -> &mut UnderlyingGroup {
+            groups
+                .entry(underlying.clone())
+                .or_insert_with(|| UnderlyingGroup {
+                    underlying,
+                    ..Default::default()
+                })
+        }
+
+        let mut groups = BTreeMap::new();
+        for position in &positions.positions {
+            group_of(&mut groups, position.underlying())
+                .positions
+                .push(position.clone());
+        }
+        for order in &working_orders.working_orders {
+            group_of(&mut groups, order.underlying())
+                .working_orders
+                .push(order.clone());
+        }
+        groups.into_values().collect()
+    }
+
+    /// Total size of the long positions
+    pub fn long_size(&self) -> f64 {
+        self.size_of(Direction::Buy)
+    }
+
+    /// Total size of the short positions
+    pub fn short_size(&self) -> f64 {
+        self.size_of(Direction::Sell)
+    }
+
+    /// Number of contracts bought minus sold, whatever the instrument
+    ///
+    /// Calls and puts are counted alike, so this is a size balance rather than a delta.
+    pub fn net_size(&self) -> f64 {
+        self.long_size() - self.short_size()
+    }
+
+    /// Total size of the working orders in the given direction
+    pub fn pending_size(&self, direction: &Direction) -> f64 {
+        self.working_orders
+            .iter()
+            .map(|order| &order.working_order_data)
+            .filter(|data| data.direction == *direction)
+            .map(|data| data.order_size)
+            .sum()
+    }
+
+    /// Profit or loss of the positions at their current market quotes
+    ///
+    /// Amounts are added in the currency of each position, see
+    /// [`Position::unrealized_pnl`].
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.positions
+            .iter()
+            .map(|position| position.unrealized_pnl(position.market.bid, position.market.offer))
+            .sum()
+    }
+
+    fn size_of(&self, direction: Direction) -> f64 {
+        self.positions
+            .iter()
+            .filter(|position| position.position.direction == direction)
+            .map(|position| position.position.size)
+            .sum()
+    }
+}
+
 /// Details of a working order
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WorkingOrderData {
@@ -536,6 +1669,26 @@ pub struct WorkingOrderData {
     pub deal_reference: Option<String>,
 }
 
+impl WorkingOrderData {
+    /// Returns the expiry date of a good till date order, in UTC
+    ///
+    /// Reads `goodTillDateISO`, falling back to `goodTillDate`, and returns `None` when
+    /// the order has no expiry or the date cannot be parsed.
+    pub fn expiry(&self) -> Option<DateTime<Utc>> {
+        const ISO_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"];
+        const IG_FORMATS: [&str; 2] = ["%Y/%m/%d %H:%M:%S", "%Y/%m/%d %H:%M"];
+        let parse = |value: &Option<String>, formats: &[&str]| {
+            let value = value.as_deref()?;
+            formats
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        };
+        parse(&self.good_till_date_iso, &ISO_FORMATS)
+            .or_else(|| parse(&self.good_till_date, &IG_FORMATS))
+            .map(|expiry| expiry.and_utc())
+    }
+}
+
 /// Market data for a working order
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct MarketData {
@@ -589,6 +1742,33 @@ pub struct MarketData {
     pub scaling_factor: i64,
 }
 
+/// Type of the transactions requested from the history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionType {
+    /// Every transaction
+    #[default]
+    All,
+    /// Transactions of deals only
+    AllDeal,
+    /// Deposits
+    Deposit,
+    /// Withdrawals
+    Withdrawal,
+}
+
+impl TransactionType {
+    /// Returns the value of the `type` query parameter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionType::All => "ALL",
+            TransactionType::AllDeal => "ALL_DEAL",
+            TransactionType::Deposit => "DEPOSIT",
+            TransactionType::Withdrawal => "WITHDRAWAL",
+        }
+    }
+}
+
 /// Transaction history
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TransactionHistory {
@@ -622,8 +1802,15 @@ pub struct PageData {
     pub total_pages: i32,
 }
 
+impl PageData {
+    /// Returns true if pages follow this one
+    pub fn has_more_pages(&self) -> bool {
+        self.page_number < self.total_pages
+    }
+}
+
 /// Individual transaction
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct AccountTransaction {
     /// Date and time of the transaction
     pub date: String,
@@ -1,6 +1,15 @@
+use crate::application::models::order::{Direction, OrderType};
+use crate::application::models::working_order::CreateWorkingOrderRequest;
+use crate::error::AppError;
 pub(crate) use crate::presentation::InstrumentType;
+use crate::utils::parsing::{ParsedOptionEpic, name_similarity, parse_expiry};
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::fmt::Display;
+use std::str::FromStr;
 
 /// Model for a market instrument with enhanced deserialization
 #[derive(Debug, Clone, Deserialize, PartialEq)]
@@ -29,6 +38,9 @@ pub struct Instrument {
     /// Unit for the margin factor
     #[serde(rename = "marginFactorUnit")]
     pub margin_factor_unit: Option<String>,
+    /// Tiered margin requirements by deal size
+    #[serde(rename = "marginDepositBands", default)]
+    pub margin_deposit_bands: Option<Vec<MarginDepositBand>>,
     /// Available currencies for trading this instrument
     pub currencies: Option<Vec<Currency>>,
     #[serde(rename = "valueOfOnePip")]
@@ -56,6 +68,191 @@ pub struct Instrument {
     #[serde(rename = "chartCode")]
     /// Code used for charting this instrument
     pub chart_code: Option<String>,
+    /// Trading sessions of the instrument, if restricted
+    #[serde(rename = "openingHours", default)]
+    pub opening_hours: Option<OpeningHours>,
+    /// Special information such as holiday or knock-out notices
+    #[serde(rename = "specialInfo", default)]
+    pub special_info: Option<Vec<String>>,
+}
+
+impl Instrument {
+    /// Returns the default currency of the instrument, or the first one listed
+    pub fn default_currency(&self) -> Option<&Currency> {
+        let currencies = self.currencies.as_ref()?;
+        currencies
+            .iter()
+            .find(|currency| currency.is_default == Some(true))
+            .or_else(|| currencies.first())
+    }
+
+    /// Returns whether the instrument is inside one of its trading sessions at `time`
+    ///
+    /// `time` must be expressed in the timezone the opening hours are quoted in. Sessions
+    /// only run on weekdays, and not on the days `specialInfo` announces as closed.
+    ///
+    /// # Returns
+    /// * `None` if the instrument does not publish opening hours
+    pub fn is_open_at<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<bool> {
+        let hours = self.opening_hours.as_ref()?;
+        let closed: Vec<NaiveDate> = self
+            .special_dates()
+            .into_iter()
+            .filter(|special| special.closed)
+            .map(|special| special.date)
+            .collect();
+        Some(
+            hours
+                .session_day(time.naive_local())
+                .is_some_and(|day| is_weekday(day) && !closed.contains(&day)),
+        )
+    }
+
+    /// Returns the dates announced in `specialInfo`, such as holiday closures
+    pub fn special_dates(&self) -> Vec<SpecialDate> {
+        self.special_info
+            .iter()
+            .flatten()
+            .flat_map(|line| SpecialDate::from_special_info(line))
+            .collect()
+    }
+}
+
+/// Returns true if `date` is a Monday to Friday
+fn is_weekday(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Date announced in a `specialInfo` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecialDate {
+    /// Date the line is about
+    pub date: NaiveDate,
+    /// True if the line announces the market closed on that date
+    pub closed: bool,
+    /// The `specialInfo` line the date was read from
+    pub info: String,
+}
+
+impl SpecialDate {
+    /// Extracts the dates of a `specialInfo` line
+    ///
+    /// Dates are read in the `DD/MM/YY`, `DD/MM/YYYY` and `YYYY-MM-DD` formats IG uses.
+    pub fn from_special_info(line: &str) -> Vec<Self> {
+        let closed = line.to_lowercase().contains("closed");
+        line.split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+            .map(|token| token.trim_matches(|c: char| !c.is_ascii_digit()))
+            .filter_map(|token| {
+                ["%d/%m/%y", "%d/%m/%Y", "%Y-%m-%d"]
+                    .iter()
+                    .find_map(|format| NaiveDate::parse_from_str(token, format).ok())
+            })
+            .map(|date| Self {
+                date,
+                closed,
+                info: line.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Trading sessions of an instrument, as returned in `openingHours`
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct OpeningHours {
+    /// Trading sessions of a day
+    #[serde(rename = "marketTimes", default)]
+    pub market_times: Vec<MarketTime>,
+}
+
+impl OpeningHours {
+    /// Returns true if `time` falls inside any of the trading sessions
+    pub fn is_open_at(&self, time: NaiveTime) -> bool {
+        self.market_times
+            .iter()
+            .any(|session| session.contains(time))
+    }
+
+    /// Returns the day the session containing `time` opened on, if `time` is in one
+    ///
+    /// The part of a session spanning midnight that falls after midnight belongs to the
+    /// session opened the day before.
+    pub fn session_day(&self, time: NaiveDateTime) -> Option<NaiveDate> {
+        let session = self
+            .market_times
+            .iter()
+            .find(|session| session.contains(time.time()))?;
+        let date = time.date();
+        if session.open_time > session.close_time && time.time() < session.close_time {
+            date.pred_opt()
+        } else {
+            Some(date)
+        }
+    }
+
+    /// Returns the start of the next session after `time`, if any session is defined
+    pub fn next_open(&self, time: NaiveTime) -> Option<NaiveTime> {
+        let later = self
+            .market_times
+            .iter()
+            .map(|session| session.open_time)
+            .filter(|open| *open > time)
+            .min();
+        later.or_else(|| self.market_times.iter().map(|s| s.open_time).min())
+    }
+}
+
+/// A single trading session
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct MarketTime {
+    /// Session start
+    #[serde(rename = "openTime", with = "hh_mm")]
+    pub open_time: NaiveTime,
+    /// Session end
+    #[serde(rename = "closeTime", with = "hh_mm")]
+    pub close_time: NaiveTime,
+}
+
+impl MarketTime {
+    /// Returns true if `time` is within the session; sessions may span midnight
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.open_time <= self.close_time {
+            time >= self.open_time && time < self.close_time
+        } else {
+            time >= self.open_time || time < self.close_time
+        }
+    }
+}
+
+/// Serde helpers for `HH:MM` times
+mod hh_mm {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.format("%H:%M").to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&value, "%H:%M")
+            .or_else(|_| NaiveTime::parse_from_str(&value, "%H:%M:%S"))
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Margin requirement applied to the part of a deal size that falls within a band
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct MarginDepositBand {
+    /// Lower bound of the band, in contracts
+    pub min: f64,
+    /// Upper bound of the band, `None` for the last band
+    #[serde(default)]
+    pub max: Option<f64>,
+    /// Margin percentage applied within the band
+    pub margin: f64,
+    /// Currency of the band
+    #[serde(default)]
+    pub currency: Option<String>,
 }
 
 /// Model for an instrument's currency
@@ -76,6 +273,19 @@ pub struct Currency {
     pub is_default: Option<bool>,
 }
 
+impl Currency {
+    /// Returns the rate converting amounts in this currency into the account base currency
+    ///
+    /// Uses `exchangeRate` when present, otherwise the inverse of `baseExchangeRate`.
+    pub fn rate_to_base(&self) -> Option<f64> {
+        self.exchange_rate.filter(|rate| *rate > 0.0).or_else(|| {
+            self.base_exchange_rate
+                .filter(|rate| *rate > 0.0)
+                .map(|rate| 1.0 / rate)
+        })
+    }
+}
+
 /// Model for market data with enhanced deserialization
 #[derive(Debug, Clone, Deserialize)]
 pub struct MarketDetails {
@@ -88,8 +298,233 @@ pub struct MarketDetails {
     pub dealing_rules: DealingRules,
 }
 
+impl MarketDetails {
+    /// Returns whether `time` falls inside the trading sessions of the market
+    ///
+    /// Markets that do not publish opening hours are considered open all day.
+    pub fn is_open_at<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> bool {
+        self.instrument.is_open_at(time).unwrap_or(true)
+    }
+
+    /// Checks whether a deal of `size` in `direction` can be placed on this market
+    ///
+    /// Combines the market status, the current price on the dealing side, the
+    /// minimum/maximum deal size and the market order preference.
+    pub fn check_dealable(&self, size: f64, direction: &Direction) -> DealabilityCheck {
+        let mut issues = Vec::new();
+
+        if self.snapshot.market_status != "TRADEABLE" {
+            issues.push(DealabilityIssue::MarketNotTradeable(
+                self.snapshot.market_status.clone(),
+            ));
+        }
+
+        let price = match direction {
+            Direction::Buy => self.snapshot.offer,
+            Direction::Sell => self.snapshot.bid,
+        };
+        if price.is_none() {
+            issues.push(DealabilityIssue::NoPrice(direction.clone()));
+        }
+
+        let min = self.dealing_rules.min_deal_size.value;
+        if let Some(min) = min
+            && size < min
+        {
+            issues.push(DealabilityIssue::BelowMinDealSize { size, min });
+        }
+        if let Some(max) = self.dealing_rules.max_deal_size
+            && size > max
+        {
+            issues.push(DealabilityIssue::AboveMaxDealSize { size, max });
+        }
+
+        if self.dealing_rules.market_order_preference == "NOT_AVAILABLE" {
+            issues.push(DealabilityIssue::MarketOrdersNotAvailable);
+        }
+
+        DealabilityCheck {
+            epic: self.instrument.epic.clone(),
+            size,
+            direction: direction.clone(),
+            issues,
+        }
+    }
+
+    /// Checks a trailing stop against the dealing rules of this market
+    ///
+    /// Percentage rules are converted with the mid price of the snapshot.
+    pub fn validate_trailing_stop(&self, distance: f64, increment: f64) -> Result<(), AppError> {
+        let price = match (self.snapshot.bid, self.snapshot.offer) {
+            (Some(bid), Some(offer)) => Some((bid + offer) / 2.0),
+            (bid, offer) => bid.or(offer),
+        };
+        self.dealing_rules
+            .validate_trailing_stop(distance, increment, price)
+    }
+
+    /// Checks a stop-limit working order against the current price and dealing rules
+    ///
+    /// On top of [`CreateWorkingOrderRequest::validate_levels`], the trigger must be
+    /// beyond the current price on the dealing side (above the offer to buy, below the
+    /// bid to sell) by at least the minimum normal stop distance, and the limit price
+    /// may not be further from the trigger than the maximum stop or limit distance.
+    /// Checks needing a price are skipped when the snapshot has none.
+    ///
+    /// # Returns
+    /// * `AppError::InvalidInput` if the order is not a valid stop-limit order here
+    pub fn validate_stop_limit(&self, order: &CreateWorkingOrderRequest) -> Result<(), AppError> {
+        if order.order_type != OrderType::StopLimit {
+            return Err(AppError::InvalidInput(format!(
+                "{:?} order is not a stop-limit order",
+                order.order_type
+            )));
+        }
+        order.validate_levels()?;
+        let limit = order.limit_price.unwrap_or(order.level);
+        let price = match order.direction {
+            Direction::Buy => self.snapshot.offer,
+            Direction::Sell => self.snapshot.bid,
+        };
+        let rules = &self.dealing_rules;
+
+        if let Some(price) = price {
+            let distance = match order.direction {
+                Direction::Buy => order.level - price,
+                Direction::Sell => price - order.level,
+            };
+            if distance <= 0.0 {
+                return Err(AppError::InvalidInput(format!(
+                    "stop-limit trigger {} is not beyond the current price {price}",
+                    order.level
+                )));
+            }
+            if let Some(min) = rules
+                .min_normal_stop_or_limit_distance
+                .to_points(Some(price))
+                && distance < min
+            {
+                return Err(AppError::InvalidInput(format!(
+                    "stop-limit trigger {} is {distance} from the price, below the minimum distance {min}",
+                    order.level
+                )));
+            }
+        }
+        if let Some(max) = rules.max_stop_or_limit_distance.to_points(price)
+            && max > 0.0
+            && (limit - order.level).abs() > max
+        {
+            return Err(AppError::InvalidInput(format!(
+                "limit price {limit} is more than the maximum distance {max} from the trigger {}",
+                order.level
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Reason why a deal cannot be placed on a market
+#[derive(Debug, Clone, PartialEq)]
+pub enum DealabilityIssue {
+    /// The market status is not `TRADEABLE`; holds the current status
+    MarketNotTradeable(String),
+    /// There is no current price on the dealing side
+    NoPrice(Direction),
+    /// The size is below the minimum deal size
+    BelowMinDealSize {
+        /// Requested size
+        size: f64,
+        /// Minimum deal size
+        min: f64,
+    },
+    /// The size is above the maximum deal size
+    AboveMaxDealSize {
+        /// Requested size
+        size: f64,
+        /// Maximum deal size
+        max: f64,
+    },
+    /// The market does not accept market orders
+    MarketOrdersNotAvailable,
+}
+
+impl Display for DealabilityIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DealabilityIssue::MarketNotTradeable(status) => {
+                write!(f, "market is not tradeable (status {status})")
+            }
+            DealabilityIssue::NoPrice(direction) => {
+                write!(f, "no price available to {direction:?}")
+            }
+            DealabilityIssue::BelowMinDealSize { size, min } => {
+                write!(f, "size {size} is below the minimum deal size {min}")
+            }
+            DealabilityIssue::AboveMaxDealSize { size, max } => {
+                write!(f, "size {size} is above the maximum deal size {max}")
+            }
+            DealabilityIssue::MarketOrdersNotAvailable => {
+                write!(f, "market orders are not available")
+            }
+        }
+    }
+}
+
+/// Verdict of a tradeability pre-check
+#[derive(Debug, Clone, PartialEq)]
+pub struct DealabilityCheck {
+    /// EPIC of the market
+    pub epic: String,
+    /// Requested deal size
+    pub size: f64,
+    /// Requested direction
+    pub direction: Direction,
+    /// Every reason preventing the deal, empty if it can be placed
+    pub issues: Vec<DealabilityIssue>,
+}
+
+impl DealabilityCheck {
+    /// Returns true if no issue prevents the deal, whatever the order type
+    pub fn is_dealable(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Returns true if no issue prevents a deal of the given order type
+    ///
+    /// Unavailable market orders only block `OrderType::Market`.
+    pub fn is_dealable_for(&self, order_type: &OrderType) -> bool {
+        self.issues.iter().all(|issue| {
+            *issue == DealabilityIssue::MarketOrdersNotAvailable && *order_type != OrderType::Market
+        })
+    }
+
+    /// Converts the verdict into a result for a deal of the given order type
+    ///
+    /// # Returns
+    /// * `AppError::InvalidInput` listing every blocking reason if the deal cannot be placed
+    pub fn ensure_dealable(&self, order_type: &OrderType) -> Result<(), AppError> {
+        if self.is_dealable_for(order_type) {
+            return Ok(());
+        }
+        let reasons: Vec<String> = self
+            .issues
+            .iter()
+            .filter(|issue| {
+                **issue != DealabilityIssue::MarketOrdersNotAvailable
+                    || *order_type == OrderType::Market
+            })
+            .map(ToString::to_string)
+            .collect();
+        Err(AppError::InvalidInput(format!(
+            "{} is not dealable: {}",
+            self.epic,
+            reasons.join("; ")
+        )))
+    }
+}
+
 /// Trading rules for a market with enhanced deserialization
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct DealingRules {
     /// Minimum step distance
     #[serde(rename = "minStepDistance")]
@@ -128,8 +563,66 @@ pub struct DealingRules {
     pub max_deal_size: Option<f64>,
 }
 
+impl DealingRules {
+    /// Returns true if the market accepts server-side trailing stops
+    pub fn trailing_stops_available(&self) -> bool {
+        self.trailing_stops_preference == "AVAILABLE"
+    }
+
+    /// Checks a trailing stop distance and increment against the dealing rules
+    ///
+    /// The distance must lie between the minimum normal stop distance and the maximum
+    /// stop distance, and the increment must be at least the minimum step distance.
+    /// `price` converts rules expressed as a percentage into points; those rules are
+    /// skipped without it.
+    ///
+    /// # Returns
+    /// * `AppError::InvalidInput` if the market has no trailing stops or a rule is broken
+    pub fn validate_trailing_stop(
+        &self,
+        distance: f64,
+        increment: f64,
+        price: Option<f64>,
+    ) -> Result<(), AppError> {
+        if !self.trailing_stops_available() {
+            return Err(AppError::InvalidInput(format!(
+                "trailing stops are not available (preference {})",
+                self.trailing_stops_preference
+            )));
+        }
+        if distance <= 0.0 || increment <= 0.0 {
+            return Err(AppError::InvalidInput(format!(
+                "trailing stop distance {distance} and increment {increment} must be positive"
+            )));
+        }
+        if let Some(min) = self.min_normal_stop_or_limit_distance.to_points(price)
+            && distance < min
+        {
+            return Err(AppError::InvalidInput(format!(
+                "trailing stop distance {distance} is below the minimum stop distance {min}"
+            )));
+        }
+        if let Some(max) = self.max_stop_or_limit_distance.to_points(price)
+            && max > 0.0
+            && distance > max
+        {
+            return Err(AppError::InvalidInput(format!(
+                "trailing stop distance {distance} is above the maximum stop distance {max}"
+            )));
+        }
+        if let Some(min_step) = self.min_step_distance.to_points(price)
+            && increment < min_step
+        {
+            return Err(AppError::InvalidInput(format!(
+                "trailing stop increment {increment} is below the minimum step distance {min_step}"
+            )));
+        }
+        Ok(())
+    }
+}
+
 /// Market snapshot with enhanced deserialization
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketSnapshot {
     /// Current status of the market (e.g., "OPEN", "CLOSED")
     #[serde(rename = "marketStatus")]
@@ -180,6 +673,18 @@ pub struct MarketSnapshot {
     pub controlled_risk_extra_spread: Option<f64>,
 }
 
+impl MarketSnapshot {
+    /// Returns the factor dividing the quoted prices into prices of the instrument
+    ///
+    /// See [`PositionMarket::price_scale`](crate::application::models::account::PositionMarket::price_scale).
+    pub fn price_scale(&self) -> f64 {
+        match self.scaling_factor {
+            Some(factor) if factor > 0 => factor as f64,
+            _ => 1.0,
+        }
+    }
+}
+
 /// Model for market search results
 #[derive(Debug, Clone, Deserialize)]
 pub struct MarketSearchResult {
@@ -187,6 +692,115 @@ pub struct MarketSearchResult {
     pub markets: Vec<MarketData>,
 }
 
+impl MarketSearchResult {
+    /// Ranks the markets by similarity of their instrument name to `name`
+    ///
+    /// # Returns
+    /// All markets as `EpicMatch`es, best match first
+    pub fn rank_by_name(&self, name: &str) -> Vec<EpicMatch> {
+        let mut matches: Vec<EpicMatch> = self
+            .markets
+            .iter()
+            .map(|market| EpicMatch {
+                epic: market.epic.clone(),
+                instrument_name: market.instrument_name.clone(),
+                confidence: name_similarity(name, &market.instrument_name),
+            })
+            .collect();
+        matches.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        matches
+    }
+
+    /// Picks the most likely underlying market of an option among the results
+    ///
+    /// Option markets are skipped. Candidates whose EPIC refers to the option's
+    /// underlying code come first, then undated markets, then the closest names.
+    pub fn best_underlying(
+        &self,
+        option: &ParsedOptionEpic,
+        asset_name: &str,
+    ) -> Option<&MarketData> {
+        self.markets
+            .iter()
+            .filter(|market| !market.instrument_type.is_option())
+            .map(|market| {
+                let score = (
+                    option.matches_underlying_epic(&market.epic),
+                    !market.has_expiry(),
+                    name_similarity(asset_name, &market.instrument_name),
+                );
+                (market, score)
+            })
+            .max_by(|(_, a), (_, b)| a.0.cmp(&b.0).then(a.1.cmp(&b.1)).then(a.2.total_cmp(&b.2)))
+            .map(|(market, _)| market)
+    }
+
+    /// Keeps only the markets accepted by the filter
+    pub fn filtered(mut self, filter: &MarketSearchFilter) -> Self {
+        self.markets.retain(|market| filter.matches(market));
+        self
+    }
+}
+
+/// Client-side filter for market search results
+///
+/// The default filter accepts every market.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarketSearchFilter {
+    /// Accepted instrument types, any type if empty
+    pub instrument_types: Vec<InstrumentType>,
+    /// `Some(true)` for dated markets only, `Some(false)` for undated markets only
+    pub has_expiry: Option<bool>,
+    /// Only accept markets whose status is `TRADEABLE`
+    pub tradeable_only: bool,
+}
+
+impl MarketSearchFilter {
+    /// Creates a filter that accepts every market
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts markets of the given instrument type, in addition to those already accepted
+    pub fn with_instrument_type(mut self, instrument_type: InstrumentType) -> Self {
+        self.instrument_types.push(instrument_type);
+        self
+    }
+
+    /// Accepts only dated (`true`) or undated (`false`) markets
+    pub fn with_expiry(mut self, has_expiry: bool) -> Self {
+        self.has_expiry = Some(has_expiry);
+        self
+    }
+
+    /// Accepts only markets that can currently be traded
+    pub fn tradeable_only(mut self) -> Self {
+        self.tradeable_only = true;
+        self
+    }
+
+    /// Returns true if the market passes every criterion of the filter
+    pub fn matches(&self, market: &MarketData) -> bool {
+        (self.instrument_types.is_empty()
+            || self.instrument_types.contains(&market.instrument_type))
+            && self
+                .has_expiry
+                .is_none_or(|has_expiry| market.has_expiry() == has_expiry)
+            && (!self.tradeable_only || market.is_tradeable())
+    }
+}
+
+/// Market matched by instrument name
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EpicMatch {
+    /// EPIC of the matched market
+    pub epic: String,
+    /// Instrument name of the matched market
+    pub instrument_name: String,
+    /// Similarity between the requested and the matched name, from 0.0 to 1.0
+    pub confidence: f64,
+}
+
 /// Basic market data
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct MarketData {
@@ -227,6 +841,47 @@ pub struct MarketData {
     pub offer: Option<f64>,
 }
 
+impl MarketData {
+    /// Returns true for dated markets, false for undated ones (`-` or `DFB` expiry)
+    pub fn has_expiry(&self) -> bool {
+        !matches!(self.expiry.as_str(), "" | "-" | "DFB")
+    }
+
+    /// Returns true if the market status is `TRADEABLE`
+    pub fn is_tradeable(&self) -> bool {
+        self.market_status == "TRADEABLE"
+    }
+
+    /// Returns the difference between offer and bid, if both are available
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.offer? - self.bid?)
+    }
+}
+
+impl From<&MarketDetails> for MarketData {
+    fn from(details: &MarketDetails) -> Self {
+        let instrument = &details.instrument;
+        let snapshot = &details.snapshot;
+        MarketData {
+            epic: instrument.epic.clone(),
+            instrument_name: instrument.name.clone(),
+            instrument_type: instrument
+                .instrument_type
+                .unwrap_or(InstrumentType::Unknown),
+            expiry: instrument.expiry.clone(),
+            high_limit_price: instrument.high_limit_price,
+            low_limit_price: instrument.low_limit_price,
+            market_status: snapshot.market_status.clone(),
+            net_change: snapshot.net_change,
+            percentage_change: snapshot.percentage_change,
+            update_time: snapshot.update_time.clone(),
+            update_time_utc: None,
+            bid: snapshot.bid,
+            offer: snapshot.offer,
+        }
+    }
+}
+
 impl Display for MarketData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let json = serde_json::to_string(self).unwrap_or_else(|_| "Invalid JSON".to_string());
@@ -245,6 +900,300 @@ pub struct HistoricalPricesResponse {
     /// API usage allowance information
     #[serde(rename = "allowance", skip_serializing_if = "Option::is_none", default)]
     pub allowance: Option<PriceAllowance>,
+    /// Paging and allowance metadata returned by the v3 endpoint
+    #[serde(default)]
+    pub metadata: Option<PriceMetadata>,
+}
+
+impl HistoricalPricesResponse {
+    /// Returns the price allowance, whether it came at the top level or inside the metadata
+    pub fn allowance(&self) -> Option<&PriceAllowance> {
+        self.allowance
+            .as_ref()
+            .or_else(|| self.metadata.as_ref()?.allowance.as_ref())
+    }
+
+    /// Returns the paging information of the response, if any
+    pub fn page_data(&self) -> Option<&PricePageData> {
+        self.metadata.as_ref()?.page_data.as_ref()
+    }
+
+    /// Returns true if there are more pages after this one
+    pub fn has_more_pages(&self) -> bool {
+        self.page_data()
+            .is_some_and(|page| page.page_number < page.total_pages)
+    }
+}
+
+/// Metadata of a v3 historical prices response
+#[derive(Debug, Clone, Deserialize)]
+pub struct PriceMetadata {
+    /// API usage allowance information
+    #[serde(default)]
+    pub allowance: Option<PriceAllowance>,
+    /// Number of price points in the response
+    #[serde(default)]
+    pub size: Option<i64>,
+    /// Paging information
+    #[serde(rename = "pageData", default)]
+    pub page_data: Option<PricePageData>,
+}
+
+/// Paging information of a v3 historical prices response
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct PricePageData {
+    /// Number of price points per page
+    #[serde(rename = "pageSize")]
+    pub page_size: i64,
+    /// Current page number, starting at 1
+    #[serde(rename = "pageNumber")]
+    pub page_number: i64,
+    /// Total number of pages
+    #[serde(rename = "totalPages")]
+    pub total_pages: i64,
+}
+
+/// Resolution of historical price candles
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Resolution {
+    /// One second
+    Second,
+    /// One minute
+    Minute,
+    /// Two minutes
+    #[serde(rename = "MINUTE_2")]
+    Minute2,
+    /// Three minutes
+    #[serde(rename = "MINUTE_3")]
+    Minute3,
+    /// Five minutes
+    #[serde(rename = "MINUTE_5")]
+    Minute5,
+    /// Ten minutes
+    #[serde(rename = "MINUTE_10")]
+    Minute10,
+    /// Fifteen minutes
+    #[serde(rename = "MINUTE_15")]
+    Minute15,
+    /// Thirty minutes
+    #[serde(rename = "MINUTE_30")]
+    Minute30,
+    /// One hour
+    Hour,
+    /// Two hours
+    #[serde(rename = "HOUR_2")]
+    Hour2,
+    /// Three hours
+    #[serde(rename = "HOUR_3")]
+    Hour3,
+    /// Four hours
+    #[serde(rename = "HOUR_4")]
+    Hour4,
+    /// One day
+    Day,
+    /// One week
+    Week,
+    /// One month
+    Month,
+}
+
+impl Resolution {
+    /// All resolutions supported by the API, from finest to coarsest
+    pub const ALL: [Resolution; 15] = [
+        Resolution::Second,
+        Resolution::Minute,
+        Resolution::Minute2,
+        Resolution::Minute3,
+        Resolution::Minute5,
+        Resolution::Minute10,
+        Resolution::Minute15,
+        Resolution::Minute30,
+        Resolution::Hour,
+        Resolution::Hour2,
+        Resolution::Hour3,
+        Resolution::Hour4,
+        Resolution::Day,
+        Resolution::Week,
+        Resolution::Month,
+    ];
+
+    /// Returns the name used by the IG API, e.g. `MINUTE_5`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Resolution::Second => "SECOND",
+            Resolution::Minute => "MINUTE",
+            Resolution::Minute2 => "MINUTE_2",
+            Resolution::Minute3 => "MINUTE_3",
+            Resolution::Minute5 => "MINUTE_5",
+            Resolution::Minute10 => "MINUTE_10",
+            Resolution::Minute15 => "MINUTE_15",
+            Resolution::Minute30 => "MINUTE_30",
+            Resolution::Hour => "HOUR",
+            Resolution::Hour2 => "HOUR_2",
+            Resolution::Hour3 => "HOUR_3",
+            Resolution::Hour4 => "HOUR_4",
+            Resolution::Day => "DAY",
+            Resolution::Week => "WEEK",
+            Resolution::Month => "MONTH",
+        }
+    }
+
+    /// Returns the length of one candle
+    ///
+    /// `Month` is approximated as 30 days; use [`Resolution::align`] for exact month boundaries.
+    pub fn duration(&self) -> Duration {
+        match self {
+            Resolution::Second => Duration::seconds(1),
+            Resolution::Minute => Duration::minutes(1),
+            Resolution::Minute2 => Duration::minutes(2),
+            Resolution::Minute3 => Duration::minutes(3),
+            Resolution::Minute5 => Duration::minutes(5),
+            Resolution::Minute10 => Duration::minutes(10),
+            Resolution::Minute15 => Duration::minutes(15),
+            Resolution::Minute30 => Duration::minutes(30),
+            Resolution::Hour => Duration::hours(1),
+            Resolution::Hour2 => Duration::hours(2),
+            Resolution::Hour3 => Duration::hours(3),
+            Resolution::Hour4 => Duration::hours(4),
+            Resolution::Day => Duration::days(1),
+            Resolution::Week => Duration::weeks(1),
+            Resolution::Month => Duration::days(30),
+        }
+    }
+
+    /// Returns the start of the candle containing `time`
+    ///
+    /// Intraday resolutions are aligned to multiples of their duration since midnight UTC,
+    /// weeks start on Monday and months on the first day of the month.
+    pub fn align(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let midnight = Utc
+            .with_ymd_and_hms(time.year(), time.month(), time.day(), 0, 0, 0)
+            .single()
+            .unwrap_or(time);
+        match self {
+            Resolution::Day => midnight,
+            Resolution::Week => {
+                midnight - Duration::days(time.weekday().num_days_from_monday() as i64)
+            }
+            Resolution::Month => Utc
+                .with_ymd_and_hms(time.year(), time.month(), 1, 0, 0, 0)
+                .single()
+                .unwrap_or(midnight),
+            _ => {
+                let step = self.duration().num_seconds();
+                let elapsed = (time - midnight).num_seconds();
+                midnight + Duration::seconds(elapsed - elapsed % step)
+            }
+        }
+    }
+
+    /// Returns the start of the candle following the one containing `time`
+    pub fn next_boundary(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let start = self.align(time);
+        match self {
+            Resolution::Month => {
+                let (year, month) = if start.month() == 12 {
+                    (start.year() + 1, 1)
+                } else {
+                    (start.year(), start.month() + 1)
+                };
+                Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+                    .single()
+                    .unwrap_or(start + self.duration())
+            }
+            _ => start + self.duration(),
+        }
+    }
+}
+
+impl Display for Resolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_uppercase();
+        Resolution::ALL
+            .into_iter()
+            .find(|resolution| resolution.as_str() == name)
+            .ok_or_else(|| AppError::InvalidInput(format!("Unknown resolution: {s}")))
+    }
+}
+
+/// Query parameters for `GET /prices/{epic}` (version 3)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoricalPricesQuery {
+    /// Price resolution
+    pub resolution: Option<Resolution>,
+    /// Start date time (`yyyy-MM-ddTHH:mm:ss`)
+    pub from: Option<String>,
+    /// End date time (`yyyy-MM-ddTHH:mm:ss`)
+    pub to: Option<String>,
+    /// Maximum number of price points to return when no date range is given
+    pub max: Option<u32>,
+    /// Page size, 0 disables paging
+    pub page_size: Option<u32>,
+    /// Page number, starting at 1
+    pub page_number: Option<u32>,
+}
+
+impl HistoricalPricesQuery {
+    /// Creates a query for the given resolution
+    pub fn new(resolution: Resolution) -> Self {
+        Self {
+            resolution: Some(resolution),
+            ..Default::default()
+        }
+    }
+
+    /// Restricts the query to a date range
+    pub fn with_range(mut self, from: &str, to: &str) -> Self {
+        self.from = Some(from.to_string());
+        self.to = Some(to.to_string());
+        self
+    }
+
+    /// Limits the number of price points returned
+    pub fn with_max(mut self, max: u32) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Sets the page size and page number
+    pub fn with_page(mut self, page_size: u32, page_number: u32) -> Self {
+        self.page_size = Some(page_size);
+        self.page_number = Some(page_number);
+        self
+    }
+
+    /// Builds the query string, without the leading `?`
+    pub fn to_query_string(&self) -> String {
+        let mut params = Vec::new();
+        if let Some(resolution) = &self.resolution {
+            params.push(format!("resolution={resolution}"));
+        }
+        if let Some(from) = &self.from {
+            params.push(format!("from={from}"));
+        }
+        if let Some(to) = &self.to {
+            params.push(format!("to={to}"));
+        }
+        if let Some(max) = self.max {
+            params.push(format!("max={max}"));
+        }
+        if let Some(page_size) = self.page_size {
+            params.push(format!("pageSize={page_size}"));
+        }
+        if let Some(page_number) = self.page_number {
+            params.push(format!("pageNumber={page_number}"));
+        }
+        params.join("&")
+    }
 }
 
 /// Historical price data point
@@ -253,6 +1202,9 @@ pub struct HistoricalPrice {
     /// Timestamp of the price data point
     #[serde(rename = "snapshotTime")]
     pub snapshot_time: String,
+    /// UTC timestamp of the price data point (v3 only)
+    #[serde(rename = "snapshotTimeUTC", default)]
+    pub snapshot_time_utc: Option<String>,
     /// Opening price for the period
     #[serde(rename = "openPrice")]
     pub open_price: PricePoint,
@@ -270,6 +1222,26 @@ pub struct HistoricalPrice {
     pub last_traded_volume: Option<i64>,
 }
 
+impl HistoricalPrice {
+    /// Returns the start of the period, in UTC
+    ///
+    /// Reads `snapshotTimeUTC`. v1 and v2 responses only carry `snapshotTime`, in the
+    /// time zone of the account, which cannot be converted without its offset, so the
+    /// timestamp is `None` for them.
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        const FORMATS: [&str; 3] = [
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y/%m/%d %H:%M:%S",
+            "%Y:%m:%d-%H:%M:%S",
+        ];
+        let value = self.snapshot_time_utc.as_deref()?;
+        FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .map(|time| time.and_utc())
+    }
+}
+
 /// Price point with bid, ask and last traded prices
 #[derive(Debug, Clone, Deserialize)]
 pub struct PricePoint {
@@ -282,6 +1254,16 @@ pub struct PricePoint {
     pub last_traded: Option<f64>,
 }
 
+impl PricePoint {
+    /// Returns the mid of the bid and ask, or the last traded price without both
+    pub fn mid(&self) -> Option<f64> {
+        match (self.bid, self.ask) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / 2.0),
+            _ => self.last_traded,
+        }
+    }
+}
+
 /// Information about API usage allowance for price data
 #[derive(Debug, Clone, Deserialize)]
 pub struct PriceAllowance {
@@ -334,7 +1316,7 @@ pub enum StepUnit {
 }
 
 /// A struct to handle the minStepDistance value which can be a complex object
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct StepDistance {
     /// Unit type for the distance
     pub unit: Option<StepUnit>,
@@ -342,6 +1324,19 @@ pub struct StepDistance {
     pub value: Option<f64>,
 }
 
+impl StepDistance {
+    /// Returns the distance in points
+    ///
+    /// Percentage distances are converted with `price`, and give `None` without it.
+    pub fn to_points(&self, price: Option<f64>) -> Option<f64> {
+        let value = self.value?;
+        match self.unit {
+            Some(StepUnit::Percentage) | Some(StepUnit::Pct) => Some(price? * value / 100.0),
+            Some(StepUnit::Points) | None => Some(value),
+        }
+    }
+}
+
 /// Helper function to deserialize null values as empty vectors
 #[allow(dead_code)]
 fn deserialize_null_as_empty_vec<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
@@ -376,3 +1371,327 @@ pub struct MarketNode {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub markets: Vec<MarketData>,
 }
+
+/// Price and identification of a single option contract in a chain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OptionQuote {
+    /// EPIC of the option
+    pub epic: String,
+    /// Instrument name of the option
+    pub instrument_name: String,
+    /// Current bid price
+    pub bid: Option<f64>,
+    /// Current offer price
+    pub offer: Option<f64>,
+    /// Current market status
+    pub market_status: String,
+}
+
+/// Call and put contracts sharing the same strike
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OptionStrike {
+    /// Strike price
+    pub strike: f64,
+    /// Call option at this strike, if listed
+    pub call: Option<OptionQuote>,
+    /// Put option at this strike, if listed
+    pub put: Option<OptionQuote>,
+}
+
+/// Option chain for an underlying and expiry, ordered by strike
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OptionChain {
+    /// Name of the underlying, e.g. "Daily Germany 40"
+    pub underlying: String,
+    /// Expiry of the options, e.g. "22-MAY-25"
+    pub expiry: String,
+    /// Strikes in ascending order
+    pub strikes: Vec<OptionStrike>,
+}
+
+impl OptionChain {
+    /// Returns the strike entry for the given strike price
+    pub fn get(&self, strike: f64) -> Option<&OptionStrike> {
+        self.strikes.iter().find(|s| s.strike == strike)
+    }
+
+    /// Returns the EPICs of all calls and puts in the chain
+    pub fn epics(&self) -> Vec<String> {
+        self.strikes
+            .iter()
+            .flat_map(|s| s.call.iter().chain(s.put.iter()))
+            .map(|quote| quote.epic.clone())
+            .collect()
+    }
+}
+
+/// An expiry date of an underlying together with the markets expiring on it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExpiryDate {
+    /// Parsed expiry date
+    pub date: NaiveDate,
+    /// Expiry as reported by IG, e.g. "22-MAY-25"
+    pub label: String,
+    /// EPICs of the markets expiring on this date
+    pub epics: Vec<String>,
+}
+
+impl ExpiryDate {
+    /// Returns the number of calendar days from `today` to the expiry, negative if past
+    pub fn days_to_expiry(&self, today: NaiveDate) -> i64 {
+        (self.date - today).num_days()
+    }
+}
+
+/// Sorted expiry dates available for an underlying
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExpiryCalendar {
+    /// Expiries in ascending date order
+    pub expiries: Vec<ExpiryDate>,
+}
+
+impl ExpiryCalendar {
+    /// Builds a calendar from dated markets, e.g. search or navigation results
+    ///
+    /// Markets whose expiry cannot be parsed (undated `-` or `DFB` markets) are ignored.
+    pub fn from_markets<'a>(markets: impl IntoIterator<Item = &'a MarketData>) -> Self {
+        let mut expiries: Vec<ExpiryDate> = Vec::new();
+        for market in markets {
+            let Some(date) = parse_expiry(&market.expiry) else {
+                continue;
+            };
+            match expiries.iter_mut().find(|e| e.date == date) {
+                Some(expiry) => expiry.epics.push(market.epic.clone()),
+                None => expiries.push(ExpiryDate {
+                    date,
+                    label: market.expiry.clone(),
+                    epics: vec![market.epic.clone()],
+                }),
+            }
+        }
+        expiries.sort_by_key(|e| e.date);
+        Self { expiries }
+    }
+
+    /// Returns the expiry dates in ascending order
+    pub fn dates(&self) -> Vec<NaiveDate> {
+        self.expiries.iter().map(|e| e.date).collect()
+    }
+
+    /// Returns the expiries on or after `today`
+    pub fn upcoming(&self, today: NaiveDate) -> impl Iterator<Item = &ExpiryDate> {
+        self.expiries.iter().filter(move |e| e.date >= today)
+    }
+
+    /// Returns the first expiry on or after `today`
+    pub fn next(&self, today: NaiveDate) -> Option<&ExpiryDate> {
+        self.upcoming(today).next()
+    }
+
+    /// Returns the first expiry at least `min_days` days after `today`, e.g. to roll
+    /// positions before the front expiry
+    pub fn next_with_min_days(&self, today: NaiveDate, min_days: i64) -> Option<&ExpiryDate> {
+        self.expiries
+            .iter()
+            .find(|e| e.days_to_expiry(today) >= min_days)
+    }
+}
+
+/// A field whose value differs between two market details
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldChange {
+    /// Name of the field as returned by the API, e.g. `minDealSize`
+    pub field: String,
+    /// Previous value
+    pub old: Value,
+    /// New value
+    pub new: Value,
+}
+
+/// Differences between two market details of the same market
+///
+/// Built with [`MarketDetailsDiff::diff`]; persistent catalogs can store it instead of
+/// full snapshots when [`MarketDetailsDiff::has_structural_changes`] is true.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MarketDetailsDiff {
+    /// EPIC of the market
+    pub epic: String,
+    /// Changed dealing rules
+    pub dealing_rules: Vec<FieldChange>,
+    /// Changed margin factor, margin factor unit or deposit bands
+    pub margin: Vec<FieldChange>,
+    /// Changed snapshot fields, including prices
+    pub snapshot: Vec<FieldChange>,
+}
+
+impl MarketDetailsDiff {
+    /// Compares two market details, `old` being the earlier one
+    pub fn diff(old: &MarketDetails, new: &MarketDetails) -> Self {
+        let margin_fields = |details: &MarketDetails| {
+            serde_json::json!({
+                "marginFactor": details.instrument.margin_factor,
+                "marginFactorUnit": details.instrument.margin_factor_unit,
+                "marginDepositBands": details.instrument.margin_deposit_bands,
+            })
+        };
+        Self {
+            epic: new.instrument.epic.clone(),
+            dealing_rules: diff_fields(
+                serde_json::to_value(&old.dealing_rules),
+                serde_json::to_value(&new.dealing_rules),
+            ),
+            margin: diff_fields(Ok(margin_fields(old)), Ok(margin_fields(new))),
+            snapshot: diff_fields(
+                serde_json::to_value(&old.snapshot),
+                serde_json::to_value(&new.snapshot),
+            ),
+        }
+    }
+
+    /// Returns true if nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.dealing_rules.is_empty() && self.margin.is_empty() && self.snapshot.is_empty()
+    }
+
+    /// Returns true if dealing rules, margins or the market status changed
+    ///
+    /// Price-only changes are not structural.
+    pub fn has_structural_changes(&self) -> bool {
+        !self.dealing_rules.is_empty()
+            || !self.margin.is_empty()
+            || self.snapshot.iter().any(|c| c.field == "marketStatus")
+    }
+}
+
+impl MarketDetails {
+    /// Returns the differences between these details and newer ones
+    pub fn diff(&self, newer: &MarketDetails) -> MarketDetailsDiff {
+        MarketDetailsDiff::diff(self, newer)
+    }
+}
+
+/// Compares the top-level fields of two JSON objects, in field name order
+fn diff_fields(
+    old: Result<Value, serde_json::Error>,
+    new: Result<Value, serde_json::Error>,
+) -> Vec<FieldChange> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) = (old, new) else {
+        return Vec::new();
+    };
+    let mut fields: Vec<&String> = old.keys().chain(new.keys()).collect();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let old_value = old.get(field).unwrap_or(&Value::Null);
+            let new_value = new.get(field).unwrap_or(&Value::Null);
+            (old_value != new_value).then(|| FieldChange {
+                field: field.clone(),
+                old: old_value.clone(),
+                new: new_value.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Knock-out terms of a knock-out (turbo) market
+///
+/// IG only publishes these terms as free text in `specialInfo`, so every field is
+/// optional and filled from the lines mentioning it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct KnockoutInfo {
+    /// Level at which positions are knocked out
+    pub knockout_level: Option<f64>,
+    /// Knock-out premium charged when the position is knocked out
+    pub premium: Option<f64>,
+    /// Minimum distance between the price and the knock-out level, in points
+    pub min_knockout_distance: Option<f64>,
+}
+
+impl KnockoutInfo {
+    /// Extracts the knock-out terms from `specialInfo` lines
+    ///
+    /// # Returns
+    /// * `None` if no line carries a knock-out level, premium or distance
+    pub fn from_special_info(lines: &[String]) -> Option<Self> {
+        let mut info = KnockoutInfo::default();
+        for line in lines {
+            let lower = line.to_lowercase();
+            let Some(value) = first_number(line) else {
+                continue;
+            };
+            if lower.contains("distance") {
+                info.min_knockout_distance.get_or_insert(value);
+            } else if lower.contains("premium") {
+                info.premium.get_or_insert(value);
+            } else if ["knock-out", "knock out", "knockout", "ko level"]
+                .iter()
+                .any(|term| lower.contains(term))
+            {
+                info.knockout_level.get_or_insert(value);
+            }
+        }
+        (info != KnockoutInfo::default()).then_some(info)
+    }
+
+    /// Returns the distance in points between `level` and the knock-out level
+    ///
+    /// Long (`Buy`) knock-outs are knocked out below the level and short (`Sell`) ones
+    /// above it, so a negative distance means the position would be knocked out.
+    pub fn distance_to_knockout(&self, level: f64, direction: &Direction) -> Option<f64> {
+        let knockout_level = self.knockout_level?;
+        Some(match direction {
+            Direction::Buy => level - knockout_level,
+            Direction::Sell => knockout_level - level,
+        })
+    }
+
+    /// Returns the distance to the knock-out level in percent of `level`
+    pub fn distance_to_knockout_pct(&self, level: f64, direction: &Direction) -> Option<f64> {
+        if level == 0.0 {
+            return None;
+        }
+        Some(self.distance_to_knockout(level, direction)? / level * 100.0)
+    }
+}
+
+/// Returns the first number of a line, ignoring thousands separators
+fn first_number(line: &str) -> Option<f64> {
+    line.split(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .map(|token| token.replace(',', ""))
+        .find_map(|token| token.trim_matches('.').parse::<f64>().ok())
+}
+
+impl Instrument {
+    /// Returns true if the instrument is a knock-out (turbo)
+    pub fn is_knockout(&self) -> bool {
+        self.instrument_type
+            .as_ref()
+            .is_some_and(InstrumentType::is_knockout)
+    }
+
+    /// Returns the knock-out terms published in `specialInfo`, if any
+    pub fn knockout_info(&self) -> Option<KnockoutInfo> {
+        KnockoutInfo::from_special_info(self.special_info.as_deref()?)
+    }
+}
+
+impl MarketDetails {
+    /// Returns the distance between the current price and the knock-out level
+    ///
+    /// The price on the closing side is used: the bid for `Buy` positions and the
+    /// offer for `Sell` positions.
+    ///
+    /// # Returns
+    /// * `None` if the market has no knock-out level or no price on the closing side
+    pub fn distance_to_knockout(&self, direction: &Direction) -> Option<f64> {
+        let level = match direction {
+            Direction::Buy => self.snapshot.bid?,
+            Direction::Sell => self.snapshot.offer?,
+        };
+        self.instrument
+            .knockout_info()?
+            .distance_to_knockout(level, direction)
+    }
+}
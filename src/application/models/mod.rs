@@ -5,10 +5,15 @@ pub mod account;
 pub mod market;
 /// Order and position data models
 pub mod order;
+/// Typestate builders for orders and working orders
+pub mod order_builder;
 
 /// Transaction data models
 pub mod transaction;
 
+/// Watchlist data models
+pub mod watchlist;
+
 /// Working order data models
 pub mod working_order;
 
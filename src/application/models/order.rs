@@ -3,7 +3,13 @@
    Email: jb@taunais.com
    Date: 13/5/25
 ******************************************************************************/
+use super::account::Position;
+use crate::error::AppError;
 use crate::impl_json_display;
+use crate::utils::finance::{floor_size, size_decimals};
+use crate::utils::parsing::parse_option_epic;
+use serde::de::IntoDeserializer;
+use serde::de::value::StrDeserializer;
 use serde::{Deserialize, Deserializer, Serialize};
 
 //const DEFAULT_ORDER_SELL_SIZE: f64 = 0.0;
@@ -22,6 +28,16 @@ pub enum Direction {
 
 impl_json_display!(Direction);
 
+impl Direction {
+    /// Returns the direction of a deal closing a position opened in this direction
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Buy => Direction::Sell,
+            Direction::Sell => Direction::Buy,
+        }
+    }
+}
+
 /// Order type
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 #[serde(rename_all = "UPPERCASE")]
@@ -36,6 +52,9 @@ pub enum OrderType {
     /// Stop order - becomes market order when price reaches specified level
     Stop,
     /// Stop limit order - becomes limit order when price reaches specified level
+    ///
+    /// Only simulated by the paper order service: IG does not accept it for working orders.
+    #[serde(rename = "STOP_LIMIT", alias = "STOPLIMIT")]
     StopLimit,
 }
 
@@ -199,281 +218,6 @@ impl CreateOrderRequest {
             trailing_stop_increment: None,
         }
     }
-    /*
-    /// Creates a new instance of a market sell option with predefined parameters.
-    ///
-    /// This function sets up a sell option to the market for a given asset (`epic`)
-    /// with the specified size. It configures the order with default values
-    /// for attributes such as direction, order type, and time-in-force.
-    ///
-    /// # Parameters
-    /// - `epic`: A `String` that represents the epic (unique identifier or code) of the instrument
-    ///   being traded.
-    /// - `size`: A `f64` value representing the size or quantity of the order.
-    ///
-    /// # Returns
-    /// An instance of `Self` (the type implementing this function), containing the specified
-    /// `epic` and `size`, along with default values for other parameters:
-    ///
-    /// - `direction`: Set to `Direction::Sell`.
-    /// - `order_type`: Set to `OrderType::Limit`.
-    /// - `time_in_force`: Set to `TimeInForce::FillOrKill`.
-    /// - `level`: Set to `Some(0.1)`.
-    /// - `guaranteed_stop`: Set to `None`.
-    /// - `stop_level`: Set to `None`.
-    /// - `stop_distance`: Set to `None`.
-    /// - `limit_level`: Set to `None`.
-    /// - `limit_distance`: Set to `None`.
-    /// - `expiry`: Set to `None`.
-    /// - `deal_reference`: Set to `None`.
-    /// - `force_open`: Set to `Some(true)`.
-    /// - `currency_code`: Set to `None`.
-    ///
-    /// Note that this function allows for minimal input (the instrument and size),
-    /// while other fields are provided default values. If further customization is required,
-    /// you can modify the returned instance as needed.
-
-    //I dont fully understand this fn so i wont refacotr -FreeDuck
-    #[allow(clippy::ptr_arg)]
-    pub fn sell_option_to_market(
-        epic: &String,
-        size: &f64,
-        expiry: &Option<String>,
-        deal_reference: &Option<String>,
-        currency_code: &Option<String>,
-    ) -> Self {
-        let rounded_size = (size * 100.0).floor() / 100.0;
-        let currency_code = if let Some(code) = currency_code {
-            Some(code.clone())
-        } else {
-            Some("EUR".to_string())
-        };
-
-        let deal_reference = if deal_reference.is_none() {
-            &Some(nanoid::nanoid!(30, &nanoid::alphabet::SAFE))
-        } else {
-            deal_reference
-        };
-
-        Self {
-            epic: epic.clone(),
-            direction: Direction::Sell,
-            size: rounded_size,
-            order_type: OrderType::Limit,
-            time_in_force: TimeInForce::FillOrKill,
-            level: Some(DEFAULT_ORDER_SELL_SIZE),
-            guaranteed_stop: Some(false),
-            stop_level: None,
-            stop_distance: None,
-            limit_level: None,
-            limit_distance: None,
-            expiry: expiry.clone(),
-            deal_reference: deal_reference.clone(),
-            force_open: Some(true),
-            currency_code,
-        }
-    }*/
-
-    /// Constructs and returns a new instance of the `Self` struct representing a sell option
-    /// to the market with specific parameters for execution.
-    ///
-    /// # Parameters
-    /// - `epic`: A reference to a string slice (`&String`) that specifies the EPIC
-    ///   (Exchanged Product Information Code) of the instrument for which the sell order is created.
-    /// - `size`: A reference to a floating-point number (`&f64`) that represents the size of the sell
-    ///   order. The size is rounded to two decimal places.
-    /// - `expiry`: An optional string (`Option<String>`) that indicates the expiry date or period for
-    ///   the sell order. If `None`, no expiry date will be set for the order.
-    /// - `deal_reference`: An optional string (`Option<String>`) that contains a reference or identifier
-    ///   for the deal. Can be used for tracking purposes.
-    /// - `currency_code`: An optional string (`Option<String>`) representing the currency code. Defaults
-    ///   to `"EUR"` if not provided.
-    /// - `force_open`: An optional boolean (`Option<bool>`) that specifies whether to force open the
-    ///   position. When `Some(true)`, a new position is opened even if an existing position for the
-    ///   same instrument and direction is available.
-    ///
-    /// # Returns
-    /// - `Self`: A new instance populated with the provided parameters, including the following default
-    ///   properties:
-    ///   - `direction`: Set to `Direction::Sell` to designate the sell operation.
-    ///   - `order_type`: Set to `OrderType::Limit` to signify the type of the order.
-    ///   - `time_in_force`: Set to `TimeInForce::FillOrKill` indicating the order should be fully
-    ///     executed or canceled.
-    ///   - `level`: Set to a constant value `DEFAULT_ORDER_SELL_SIZE`.
-    ///   - `guaranteed_stop`: Set to `Some(false)` indicating no guaranteed stop.
-    ///   - Other optional levels/distance fields (`stop_level`, `stop_distance`, `limit_level`,
-    ///     `limit_distance`): Set to `None` by default.
-    ///
-    /// # Notes
-    /// - The `#[allow(clippy::ptr_arg)]` attribute suppresses the lint warning for using
-    ///   `&String` instead of `&str`. Consider updating the function signature to use `&str`
-    ///   instead of `&String` for better idiomatic Rust code.
-    /// - The input `size` is automatically rounded down to two decimal places before being stored.
-    ///
-    /*#[allow(clippy::ptr_arg)]
-        pub fn sell_option_to_market_w_force(
-            epic: &String,
-            size: &f64,
-            expiry: &Option<String>,
-            deal_reference: &Option<String>,
-            currency_code: &Option<String>,
-            force_open: Option<bool>, // Compensate position if it is already open
-        ) -> Self {
-            let rounded_size = (size * 100.0).floor() / 100.0;
-            let currency_code = if let Some(code) = currency_code {
-                Some(code.clone())
-            } else {
-                Some("EUR".to_string())
-            };
-            let deal_reference = if deal_reference.is_none() {
-                &Some(nanoid::nanoid!(30, &nanoid::alphabet::SAFE))
-            } else {
-                deal_reference
-            };
-            Self {
-                epic: epic.clone(),
-                direction: Direction::Sell,
-                size: rounded_size,
-                order_type: OrderType::Limit,
-                time_in_force: TimeInForce::FillOrKill,
-                level: Some(DEFAULT_ORDER_SELL_SIZE),
-                guaranteed_stop: Some(false),
-                stop_level: None,
-                stop_distance: None,
-                limit_level: None,
-                limit_distance: None,
-                expiry: expiry.clone(),
-                deal_reference: deal_reference.clone(),
-                force_open,
-                currency_code,
-            }
-        }*/
-    /*
-        /// Creates a new instance of an order to buy an option in the market with specified parameters.
-        ///
-        /// This method initializes an order with the following default values:
-        /// - `direction` is set to `Buy`.
-        /// - `order_type` is set to `Limit`.
-        /// - `time_in_force` is set to `FillOrKill`.
-        /// - `level` is set to `Some(10000.0)`.
-        /// - `force_open` is set to `Some(true)`.
-        ///   Other optional parameters, such as stop levels, distances, expiry, and currency code, are left as `None`.
-        ///
-        /// # Parameters
-        /// - `epic` (`String`): The identifier for the market or instrument to trade.
-        /// - `size` (`f64`): The size or quantity of the order to be executed.
-        ///
-        /// # Returns
-        /// A new instance of `Self` that represents the configured buy option for the given market.
-        ///
-        /// # Note
-        /// Ensure the `epic` and `size` values provided are valid and match required market conditions.
-        #[allow(clippy::ptr_arg)]
-            pub fn buy_option_to_market(
-                epic: &String,
-                size: &f64,
-                expiry: &Option<String>,
-                deal_reference: &Option<String>,
-                currency_code: &Option<String>,
-            ) -> Self {
-                let rounded_size = (size * 100.0).floor() / 100.0;
-                let currency_code = if let Some(code) = currency_code {
-                    Some(code.clone())
-                } else {
-                    Some("EUR".to_string())
-                };
-                let deal_reference = if deal_reference.is_none() {
-                    &Some(nanoid::nanoid!(30, &nanoid::alphabet::SAFE))
-                } else {
-                    deal_reference
-                };
-                Self {
-                    epic: epic.clone(),
-                    direction: Direction::Buy,
-                    size: rounded_size,
-                    order_type: OrderType::Limit,
-                    time_in_force: TimeInForce::FillOrKill,
-                    level: Some(DEFAULT_ORDER_BUY_SIZE),
-                    guaranteed_stop: Some(false),
-                    stop_level: None,
-                    stop_distance: None,
-                    limit_level: None,
-                    limit_distance: None,
-                    expiry: expiry.clone(),
-                    deal_reference: deal_reference.clone(),
-                    force_open: Some(true),
-                    currency_code: currency_code.clone(),
-                }
-            }*/
-        /*
-            /// Constructs a new instance of an order to buy an option in the market with optional force_open behavior.
-            ///
-            /// # Parameters
-            ///
-            /// * `epic` - A string slice representing the unique identifier of the instrument to be traded.
-            /// * `size` - A reference to a 64-bit floating-point number that represents the size of the order.
-            /// * `expiry` - An optional string reference representing the expiry date of the option.
-            /// * `deal_reference` - An optional string reference for the deal reference identifier.
-            /// * `currency_code` - An optional string reference representing the currency in which the order is denominated.
-            ///   Defaults to "EUR" if not provided.
-            /// * `force_open` - An optional boolean indicating whether to force open a new position regardless of existing positions.
-            ///
-            /// # Returns
-            ///
-            /// Returns a new instance of `Self`, representing the constructed order with the provided parameters.
-            ///
-            /// # Behavior
-            ///
-            /// * The size of the order will be rounded down to two decimal places for precision.
-            /// * If a `currency_code` is not provided, the default currency code "EUR" is used.
-            /// * Other parameters are directly mapped into the returned instance.
-            ///
-            /// # Notes
-            ///
-            /// * This function assumes that other order-related fields such as `level`, `stop_level`, `stop_distance`,
-            ///   etc., are set to their defaults or require specific business logic, such as
-            ///   `DEFAULT_ORDER_BUY_SIZE` for the initial buy size.
-            /// * The Clippy lint for `clippy::ptr_arg` is explicitly allowed for the `epic` parameter, where
-            ///   using a reference to `String` is intentional.
-            ///
-            #[allow(clippy::ptr_arg)]
-            pub fn buy_option_to_market_w_force(
-                epic: &String,
-                size: &f64,
-                expiry: &Option<String>,
-                deal_reference: &Option<String>,
-                currency_code: &Option<String>,
-                force_open: Option<bool>,
-            ) -> Self {
-                let rounded_size = (size * 100.0).floor() / 100.0;
-                let currency_code = if let Some(code) = currency_code {
-                    Some(code.clone())
-                } else {
-                    Some("EUR".to_string())
-                };
-                let deal_reference = if deal_reference.is_none() {
-                    &Some(nanoid::nanoid!(30, &nanoid::alphabet::SAFE))
-                } else {
-                    deal_reference
-                };
-                Self {
-                    epic: epic.clone(),
-                    direction: Direction::Buy,
-                    size: rounded_size,
-                    order_type: OrderType::Limit,
-                    time_in_force: TimeInForce::FillOrKill,
-                    level: Some(DEFAULT_ORDER_BUY_SIZE),
-                    guaranteed_stop: Some(false),
-                    stop_level: None,
-                    stop_distance: None,
-                    limit_level: None,
-                    limit_distance: None,
-                    expiry: expiry.clone(),
-                    deal_reference: deal_reference.clone(),
-                    force_open,
-                    currency_code: currency_code.clone(),
-                }
-            }*/
 
     /// Adds a stop loss to the order
     pub fn with_stop_loss(mut self, stop_level: f64) -> Self {
@@ -492,6 +236,20 @@ impl CreateOrderRequest {
         self.deal_reference = Some(reference);
         self
     }
+
+    /// Adds a trailing stop to the order
+    ///
+    /// IG only accepts trailing stops expressed as a distance, so any stop level is
+    /// cleared and the stop cannot be guaranteed. Check the distance and increment
+    /// against the market with `DealingRules::validate_trailing_stop` first.
+    pub fn with_trailing_stop(mut self, distance: f64, increment: f64) -> Self {
+        self.trailing_stop = Some(true);
+        self.stop_distance = Some(distance);
+        self.trailing_stop_increment = Some(increment);
+        self.stop_level = None;
+        self.guaranteed_stop = false;
+        self
+    }
 }
 
 /// Response to order creation
@@ -512,6 +270,163 @@ where
     Ok(opt.unwrap_or(Status::Rejected))
 }
 
+/// Reason given by IG for the outcome of a deal, as found in `OrderConfirmation.reason`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RejectReason {
+    /// The account is not enabled to trade
+    AccountNotEnabledToTrading,
+    /// The level of the attached stop or limit is not valid
+    AttachedOrderLevelError,
+    /// The trailing stop of the attached stop is not valid
+    AttachedOrderTrailingStopError,
+    /// The stop cannot be changed between guaranteed and non-guaranteed
+    CannotChangeStopType,
+    /// The stop cannot be removed
+    CannotRemoveStop,
+    /// Only closing trades are accepted on this market
+    ClosingOnlyTradesAcceptedOnThisMarket,
+    /// The account only accepts closing trades
+    ClosingsOnlyAccount,
+    /// The order conflicts with another order
+    ConflictingOrder,
+    /// The instrument cannot be traded online, contact support
+    ContactSupportInstrumentError,
+    /// The guaranteed stop is too close to another guaranteed stop
+    #[serde(rename = "CR_SPACING")]
+    CrSpacing,
+    /// The order duplicates an existing order
+    DuplicateOrderError,
+    /// The exchange is in manual override
+    ExchangeManualOverride,
+    /// The expiry is below the minimum of the sprint market
+    ExpiryLessThanSprintMarketMinExpiry,
+    /// The finance repeat dealing limit was reached
+    FinanceRepeatDealing,
+    /// A position in another currency is open on the market
+    ForceOpenOnSameMarketDifferentCurrency,
+    /// A general error occurred
+    GeneralError,
+    /// The good-till-date is in the past
+    GoodTillDateInThePast,
+    /// The instrument was not found
+    InstrumentNotFound,
+    /// The instrument cannot be traded in this currency
+    InstrumentNotTradeableInThisCurrency,
+    /// The account has insufficient funds
+    InsufficientFunds,
+    /// The market moved beyond the level tolerance
+    LevelToleranceError,
+    /// The limit order level is on the wrong side of the market
+    LimitOrderWrongSideOfMarket,
+    /// The manual order timed out
+    ManualOrderTimeout,
+    /// The margin requirement is not met
+    MarginError,
+    /// The market is closed
+    MarketClosed,
+    /// The market is closed, only edits are accepted
+    MarketClosedWithEdits,
+    /// The market is closing
+    MarketClosing,
+    /// The market cannot be borrowed to sell short
+    MarketNotBorrowable,
+    /// The market is offline
+    MarketOffline,
+    /// Market orders are not allowed on the instrument
+    MarketOrdersNotAllowedOnInstrument,
+    /// The market can only be traded by phone
+    MarketPhoneOnly,
+    /// The market has rolled to a new contract
+    MarketRolled,
+    /// The market is not available to the client
+    MarketUnavailableToClient,
+    /// The order size exceeds the maximum automatic size
+    MaxAutoSizeExceeded,
+    /// The order size is below the minimum
+    MinimumOrderSizeError,
+    /// The limit can only be moved away from the market
+    MoveAwayOnlyLimit,
+    /// The stop can only be moved away from the market
+    MoveAwayOnlyStop,
+    /// The trigger level can only be moved away from the market
+    MoveAwayOnlyTriggerLevel,
+    /// Positions without guaranteed stops are not allowed on the account
+    #[serde(rename = "NCR_POSITIONS_ON_CR_ACCOUNT")]
+    NcrPositionsOnCrAccount,
+    /// Orders in the opposite direction are not allowed
+    OpposingDirectionOrdersNotAllowed,
+    /// Positions in the opposite direction are not allowed
+    OpposingPositionsNotAllowed,
+    /// The order was declined
+    OrderDeclined,
+    /// The order is locked
+    OrderLocked,
+    /// The order was not found
+    OrderNotFound,
+    /// The order size cannot be filled
+    OrderSizeCannotBeFilled,
+    /// The order size is above the normal market size
+    OverNormalMarketSize,
+    /// The partially closed position was not deleted
+    #[serde(rename = "PARTIALY_CLOSED_POSITION_NOT_DELETED")]
+    PartiallyClosedPositionNotDeleted,
+    /// A position already exists in the opposite direction
+    PositionAlreadyExistsInOppositeDirection,
+    /// The position is not available for closing
+    PositionNotAvailableForClosing,
+    /// The position was not found
+    PositionNotFound,
+    /// CFD orders are rejected on a spread bet account
+    RejectCfdOrderOnSpreadbetAccount,
+    /// Spread bet orders are rejected on a CFD account
+    RejectSpreadbetOrderOnCfdAccount,
+    /// The size is not a multiple of the size increment
+    SizeIncrement,
+    /// The sprint market expiry is after the market close
+    SprintMarketExpiryAfterMarketClose,
+    /// Stops or limits are not allowed on the market
+    StopOrLimitNotAllowed,
+    /// A stop is required
+    StopRequiredError,
+    /// The strike level is beyond the tolerance
+    StrikeLevelTolerance,
+    /// The deal succeeded
+    Success,
+    /// Trailing stops are not allowed on the market
+    TrailingStopNotAllowed,
+    /// The reason is unknown
+    Unknown,
+    /// The level is on the wrong side of the market
+    WrongSideOfMarket,
+    /// A reason not known to this client
+    #[serde(other)]
+    Other,
+}
+
+impl RejectReason {
+    /// Parses an IG reason code, unknown codes giving `RejectReason::Other`
+    pub fn from_code(code: &str) -> Self {
+        let code: StrDeserializer<'_, serde::de::value::Error> = code.into_deserializer();
+        Self::deserialize(code).unwrap_or(RejectReason::Other)
+    }
+
+    /// Returns true if the same deal may succeed when submitted again later
+    ///
+    /// Only transient conditions are retryable: the price moving beyond the tolerance,
+    /// a locked order, a manual order timing out, or a general error. Every other reason
+    /// needs the deal or the account to change first.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            RejectReason::LevelToleranceError
+                | RejectReason::OrderLocked
+                | RejectReason::ManualOrderTimeout
+                | RejectReason::GeneralError
+        )
+    }
+}
+
 /// Details of a confirmed order
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderConfirmation {
@@ -564,8 +479,85 @@ pub struct OrderConfirmation {
     pub direction: Option<Direction>,
 }
 
+impl OrderConfirmation {
+    /// Returns true if the deal was accepted
+    pub fn is_accepted(&self) -> bool {
+        self.deal_status.as_deref() == Some("ACCEPTED")
+    }
+
+    /// Returns the typed reason of a rejected deal
+    ///
+    /// # Returns
+    /// `None` if the deal was accepted or IG gave no reason
+    pub fn reject_reason(&self) -> Option<RejectReason> {
+        if self.is_accepted() {
+            return None;
+        }
+        self.reason.as_deref().map(RejectReason::from_code)
+    }
+}
+
+/// Outcome of one order of a bulk submission
+#[derive(Debug, Clone)]
+pub enum BulkOrderOutcome {
+    /// The deal was confirmed as accepted
+    Accepted(OrderConfirmation),
+    /// The deal was confirmed but rejected; the reason is in the confirmation
+    Rejected(OrderConfirmation),
+    /// The order could not be submitted or confirmed
+    Errored(String),
+}
+
+/// Result of one order of a bulk submission
+#[derive(Debug, Clone)]
+pub struct BulkOrderResult {
+    /// Position of the order in the submitted slice
+    pub index: usize,
+    /// EPIC of the order
+    pub epic: String,
+    /// Deal reference returned on submission, if the order reached IG
+    pub deal_reference: Option<String>,
+    /// What happened to the order
+    pub outcome: BulkOrderOutcome,
+}
+
+/// Report of a bulk order submission, one result per order in submission order
+#[derive(Debug, Clone, Default)]
+pub struct BulkOrderReport {
+    /// Result of every order
+    pub results: Vec<BulkOrderResult>,
+}
+
+impl BulkOrderReport {
+    /// Returns the orders confirmed as accepted
+    pub fn accepted(&self) -> impl Iterator<Item = &BulkOrderResult> {
+        self.results
+            .iter()
+            .filter(|result| matches!(result.outcome, BulkOrderOutcome::Accepted(_)))
+    }
+
+    /// Returns the orders confirmed as rejected
+    pub fn rejected(&self) -> impl Iterator<Item = &BulkOrderResult> {
+        self.results
+            .iter()
+            .filter(|result| matches!(result.outcome, BulkOrderOutcome::Rejected(_)))
+    }
+
+    /// Returns the orders that could not be submitted or confirmed
+    pub fn errored(&self) -> impl Iterator<Item = &BulkOrderResult> {
+        self.results
+            .iter()
+            .filter(|result| matches!(result.outcome, BulkOrderOutcome::Errored(_)))
+    }
+
+    /// Returns true if every order was accepted
+    pub fn all_accepted(&self) -> bool {
+        self.accepted().count() == self.results.len()
+    }
+}
+
 /// Model for updating an existing position
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UpdatePositionRequest {
     /// New price level for stop loss
     #[serde(rename = "stopLevel", skip_serializing_if = "Option::is_none")]
@@ -582,6 +574,78 @@ pub struct UpdatePositionRequest {
         skip_serializing_if = "Option::is_none"
     )]
     pub trailing_stop_distance: Option<f64>,
+    /// Step by which the trailing stop follows the price
+    #[serde(
+        rename = "trailingStopIncrement",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub trailing_stop_increment: Option<f64>,
+}
+
+impl UpdatePositionRequest {
+    /// Creates an update moving the stop loss and take profit to the given levels
+    pub fn levels(stop_level: Option<f64>, limit_level: Option<f64>) -> Self {
+        Self {
+            stop_level,
+            limit_level,
+            trailing_stop: Some(false),
+            ..Default::default()
+        }
+    }
+
+    /// Creates an update turning the stop into a trailing stop
+    ///
+    /// IG requires the current stop level along with the trailing distance and
+    /// increment. Check them against the market with
+    /// `DealingRules::validate_trailing_stop` first.
+    pub fn trailing_stop(stop_level: f64, distance: f64, increment: f64) -> Self {
+        Self {
+            stop_level: Some(stop_level),
+            trailing_stop: Some(true),
+            trailing_stop_distance: Some(distance),
+            trailing_stop_increment: Some(increment),
+            ..Default::default()
+        }
+    }
+
+    /// Keeps the take profit at the given level
+    pub fn with_take_profit(mut self, limit_level: f64) -> Self {
+        self.limit_level = Some(limit_level);
+        self
+    }
+
+    /// Checks that the levels suit a position in `direction` closing at `closing_price`
+    ///
+    /// The stop must be on the losing side of the price and the limit on the winning
+    /// side: below and above the bid for a long, above and below the offer for a short.
+    ///
+    /// # Returns
+    /// * `AppError::InvalidInput` naming the misplaced level
+    pub fn validate_levels(
+        &self,
+        direction: &Direction,
+        closing_price: f64,
+    ) -> Result<(), AppError> {
+        let sign = match direction {
+            Direction::Buy => 1.0,
+            Direction::Sell => -1.0,
+        };
+        if let Some(stop) = self.stop_level
+            && (closing_price - stop) * sign <= 0.0
+        {
+            return Err(AppError::InvalidInput(format!(
+                "stop level {stop} of a {direction:?} position must be beyond the price {closing_price} on the losing side"
+            )));
+        }
+        if let Some(limit) = self.limit_level
+            && (limit - closing_price) * sign <= 0.0
+        {
+            return Err(AppError::InvalidInput(format!(
+                "limit level {limit} of a {direction:?} position must be beyond the price {closing_price} on the winning side"
+            )));
+        }
+        Ok(())
+    }
 }
 
 /// Model for closing an existing position
@@ -610,7 +674,62 @@ pub struct ClosePositionRequest {
 }
 
 impl ClosePositionRequest {
+    /// Creates a request closing the whole of `position` at market price
+    pub fn for_position(position: &Position) -> Self {
+        let details = &position.position;
+        Self::for_deal(&details.deal_id, &details.direction, details.size)
+            .with_market(&position.market.epic, &details.currency)
+    }
+
+    /// Creates a request closing `size` of the deal `deal_id` at market price
+    ///
+    /// `position_direction` is the direction the position was opened in; the request
+    /// is sent in the opposite direction.
+    pub fn for_deal(deal_id: &str, position_direction: &Direction, size: f64) -> Self {
+        Self {
+            deal_id: Some(deal_id.to_string()),
+            direction: position_direction.opposite(),
+            size,
+            order_type: OrderType::Market,
+            time_in_force: TimeInForce::ExecuteAndEliminate,
+            level: None,
+            expiry: "-".into(),
+            epic: String::new(),
+            quote_id: None,
+            currency_code: String::new(),
+            force_open: false,
+            guaranteed_stop: false,
+        }
+    }
+
+    /// Sets the EPIC and currency of the position
+    pub fn with_market(mut self, epic: &str, currency_code: &str) -> Self {
+        self.epic = epic.to_string();
+        self.currency_code = currency_code.to_string();
+        self
+    }
+
+    /// Closes `size` instead of the whole position
+    pub fn with_size(mut self, size: f64) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Closes at `level` with a fill-or-kill limit order instead of at market price
+    ///
+    /// This is useful for instruments that don't support market orders
+    pub fn with_level(mut self, level: f64) -> Self {
+        self.order_type = OrderType::Limit;
+        self.time_in_force = TimeInForce::FillOrKill;
+        self.level = Some(level);
+        self
+    }
+
     /// Creates a request to close a position at market price
+    #[deprecated(
+        since = "0.1.20",
+        note = "use `ClosePositionRequest::for_position` or `ClosePositionRequest::for_deal`"
+    )]
     pub fn market(
         deal_id: String,
         direction: Direction,
@@ -637,6 +756,10 @@ impl ClosePositionRequest {
     /// Creates a request to close a position at a specific price level
     ///
     /// This is useful for instruments that don't support market orders
+    #[deprecated(
+        since = "0.1.20",
+        note = "use `ClosePositionRequest::for_deal` with `ClosePositionRequest::with_level`"
+    )]
     pub fn limit(
         deal_id: String,
         direction: Direction,
@@ -723,6 +846,269 @@ impl ClosePositionRequest {
     }*/
 }
 
+/// Amount of a position to close
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PartialCloseSize {
+    /// Fraction of the position size, between 0 and 1
+    Fraction(f64),
+    /// Size to close, in the units of the position
+    Size(f64),
+}
+
+impl PartialCloseSize {
+    /// Computes the size to close and the size left open
+    ///
+    /// The requested size is rounded down to the precision of `min_deal_size` (a
+    /// minimum of 0.5 allows steps of 0.1). Neither the closed nor the remaining size
+    /// may fall below the minimum deal size: the closed size is reduced to leave the
+    /// minimum open when needed, and a request larger than the position closes it all.
+    ///
+    /// # Returns
+    /// * `(close_size, remaining_size)`
+    /// * `AppError::InvalidInput` if no valid size can be closed
+    pub fn resolve(
+        &self,
+        position_size: f64,
+        min_deal_size: Option<f64>,
+    ) -> Result<(f64, f64), AppError> {
+        let requested = match *self {
+            PartialCloseSize::Fraction(fraction) if fraction > 0.0 && fraction <= 1.0 => {
+                position_size * fraction
+            }
+            PartialCloseSize::Size(size) if size > 0.0 => size,
+            other => {
+                return Err(AppError::InvalidInput(format!(
+                    "invalid close amount {other:?}"
+                )));
+            }
+        };
+        if requested >= position_size {
+            return Ok((position_size, 0.0));
+        }
+
+        let min = min_deal_size.filter(|min| *min > 0.0).unwrap_or(0.0);
+        let floor = |size: f64| floor_size(size, size_decimals(min));
+
+        let mut close = floor(requested);
+        if position_size - close < min {
+            close = floor(position_size - min);
+        }
+        if close <= 0.0 || close < min {
+            return Err(AppError::InvalidInput(format!(
+                "cannot close {requested} of a position of {position_size} with a minimum deal size of {min}"
+            )));
+        }
+        let remaining = floor(position_size - close);
+        Ok((close, remaining))
+    }
+}
+
+/// Outcome of a partial close
+#[derive(Debug, Clone)]
+pub struct PartialCloseResult {
+    /// Confirmation of the closing deal
+    pub confirmation: OrderConfirmation,
+    /// Size that was closed
+    pub closed_size: f64,
+    /// Size left open
+    pub remaining_size: f64,
+}
+
+/// Selects the positions or working orders affected by a close-all or cancel-all
+///
+/// An empty filter selects the whole account; every criterion set must match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DealFilter {
+    /// Exact EPIC of the market
+    pub epic: Option<String>,
+    /// Direction of the deal
+    pub direction: Option<Direction>,
+    /// Underlying code, e.g. `DAX`, matching options and the underlying market alike
+    ///
+    /// The code of the EPIC must equal it, or be one of its series codes (`DDAX` for
+    /// dailies, `DAXWK` for weeklies), so `DAX` does not select `MDAX` or `TECDAX`.
+    pub underlying: Option<String>,
+}
+
+impl DealFilter {
+    /// Creates a filter selecting every deal of the account
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Only selects deals on this EPIC
+    pub fn with_epic(mut self, epic: &str) -> Self {
+        self.epic = Some(epic.to_string());
+        self
+    }
+
+    /// Only selects deals in this direction
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    /// Only selects deals on this underlying, including its options
+    pub fn with_underlying(mut self, underlying: &str) -> Self {
+        self.underlying = Some(underlying.to_string());
+        self
+    }
+
+    /// Returns true if a deal on `epic` in `direction` is selected
+    pub fn matches(&self, epic: &str, direction: &Direction) -> bool {
+        if self.epic.as_deref().is_some_and(|wanted| wanted != epic) {
+            return false;
+        }
+        if self
+            .direction
+            .as_ref()
+            .is_some_and(|wanted| wanted != direction)
+        {
+            return false;
+        }
+        match &self.underlying {
+            Some(underlying) => {
+                let code = parse_option_epic(epic)
+                    .map(|parsed| parsed.underlying_code)
+                    .or_else(|| epic.split('.').nth(2).map(str::to_string))
+                    .unwrap_or_default();
+                !code.is_empty()
+                    && (code == *underlying
+                        || code.strip_prefix('D') == Some(underlying.as_str())
+                        || code.strip_suffix("WK") == Some(underlying.as_str()))
+            }
+            None => true,
+        }
+    }
+}
+
+/// Outcome of a position reversal
+///
+/// Once the close is accepted the account is flat whatever happens to the opposite
+/// order, so a failed opening is reported here rather than as the error of the call.
+#[derive(Debug)]
+pub struct ReversalResult {
+    /// Confirmation of the close of the original position
+    pub close: OrderConfirmation,
+    /// Confirmation of the opposite position, or the error that kept it from being sent
+    /// or confirmed; `None` if the close was not accepted
+    pub open: Option<Result<OrderConfirmation, AppError>>,
+}
+
+impl ReversalResult {
+    /// Returns true if the position was closed and the opposite one opened
+    pub fn is_complete(&self) -> bool {
+        self.close.is_accepted() && matches!(&self.open, Some(Ok(open)) if open.is_accepted())
+    }
+
+    /// Returns true if the position was closed but the opposite one was not opened
+    pub fn is_flat(&self) -> bool {
+        self.close.is_accepted() && !self.is_complete()
+    }
+}
+
+/// How the account treats an order opposite to an open position
+///
+/// On a netting account an opposite order reduces or closes the open position, unless
+/// it is sent with `forceOpen`, which IG requires whenever a stop or a limit is attached.
+/// On a hedging account every order opens its own position and positions are only
+/// closed explicitly, by deal ID.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PositionMode {
+    /// Opposite orders net the open position
+    #[default]
+    Netting,
+    /// Opposite orders open positions of their own
+    Hedging,
+}
+
+impl PositionMode {
+    /// Infers the mode of the account from its open positions
+    ///
+    /// Opposite positions open on the same EPIC reveal a hedging account. Without them
+    /// both modes look alike, so the mode is undetermined.
+    ///
+    /// # Returns
+    /// `Some(PositionMode::Hedging)` or `None` when nothing tells the modes apart
+    pub fn detect(positions: &[Position]) -> Option<Self> {
+        let hedged = positions.iter().any(|a| {
+            positions.iter().any(|b| {
+                a.market.epic == b.market.epic && a.position.direction != b.position.direction
+            })
+        });
+        hedged.then_some(PositionMode::Hedging)
+    }
+
+    /// Returns the `forceOpen` flag to send with `order` in this mode
+    ///
+    /// Hedging always forces a new position. Netting only forces it when the order
+    /// attaches a stop or a limit, so plain opposite orders close the open position.
+    pub fn force_open(self, order: &CreateOrderRequest) -> bool {
+        match self {
+            PositionMode::Hedging => true,
+            PositionMode::Netting => {
+                order.stop_level.is_some()
+                    || order.stop_distance.is_some()
+                    || order.limit_level.is_some()
+                    || order.limit_distance.is_some()
+                    || order.trailing_stop == Some(true)
+            }
+        }
+    }
+
+    /// Checks that `close_request` designates a single position in this mode
+    ///
+    /// # Returns
+    /// * `AppError::InvalidInput` for a close by EPIC on a hedging account, where
+    ///   several positions may be open in the same direction
+    pub fn validate_close(self, close_request: &ClosePositionRequest) -> Result<(), AppError> {
+        if self == PositionMode::Hedging && close_request.deal_id.is_none() {
+            return Err(AppError::InvalidInput(
+                "closes on a hedging account need a deal ID".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Result of the action on one deal of a close-all or cancel-all
+#[derive(Debug, Clone, PartialEq)]
+pub struct DealActionResult {
+    /// Deal ID of the position or working order
+    pub deal_id: String,
+    /// EPIC of the market
+    pub epic: String,
+    /// Deal reference of the close or deletion, if it was accepted for processing
+    pub deal_reference: Option<String>,
+    /// Error returned by the API, if the request failed
+    pub error: Option<String>,
+}
+
+/// Summary of a close-all or cancel-all, one result per selected deal
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BulkActionSummary {
+    /// Result of every selected deal
+    pub results: Vec<DealActionResult>,
+}
+
+impl BulkActionSummary {
+    /// Returns the deals whose request was sent successfully
+    pub fn succeeded(&self) -> impl Iterator<Item = &DealActionResult> {
+        self.results.iter().filter(|result| result.error.is_none())
+    }
+
+    /// Returns the deals whose request failed
+    pub fn failed(&self) -> impl Iterator<Item = &DealActionResult> {
+        self.results.iter().filter(|result| result.error.is_some())
+    }
+
+    /// Returns true if no request failed
+    pub fn is_complete(&self) -> bool {
+        self.failed().next().is_none()
+    }
+}
+
 /// Response to closing a position
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClosePositionResponse {
@@ -0,0 +1,376 @@
+//! Typestate builders for orders
+//!
+//! The kind of order is part of the builder type, so invalid requests do not compile:
+//! a market order has no way to receive a level, a limit order cannot be created
+//! without one, and a good-till-date working order can only be obtained by giving
+//! its date.
+//!
+//! ```
+//! use ig_client::application::models::order::Direction;
+//! use ig_client::application::models::order_builder::{OrderBuilder, WorkingOrderBuilder};
+//!
+//! let order = OrderBuilder::market("IX.D.DAX.DAILY.IP", Direction::Buy, 1.0, "EUR")
+//!     .with_stop_distance(50.0)
+//!     .with_limit_distance(100.0)
+//!     .build();
+//! assert_eq!(order.level, None);
+//!
+//! let working_order = WorkingOrderBuilder::limit("IX.D.DAX.DAILY.IP", Direction::Buy, 1.0, 18000.0)
+//!     .good_till_date("2025/12/31 23:59:59")
+//!     .build();
+//! assert_eq!(working_order.good_till_date.as_deref(), Some("2025/12/31 23:59:59"));
+//! ```
+use crate::application::models::market::DealingRules;
+use crate::application::models::order::{CreateOrderRequest, Direction, TimeInForce};
+use crate::application::models::working_order::CreateWorkingOrderRequest;
+use crate::error::AppError;
+use crate::utils::finance::round_size_to_rules;
+use std::marker::PhantomData;
+
+/// Marker for orders filled at the current market price
+#[derive(Debug, Clone, Copy)]
+pub struct Market;
+
+/// Marker for orders filled at a given level or better
+#[derive(Debug, Clone, Copy)]
+pub struct Limit;
+
+/// Marker for working orders triggered when the price crosses a level
+#[derive(Debug, Clone, Copy)]
+pub struct Stop;
+
+/// Marker for working orders triggered at a level, then filled at a limit price or better
+#[derive(Debug, Clone, Copy)]
+pub struct StopLimit;
+
+/// Marker for working orders valid until cancelled
+#[derive(Debug, Clone, Copy)]
+pub struct GoodTillCancelled;
+
+/// Marker for working orders valid until a date
+#[derive(Debug, Clone, Copy)]
+pub struct GoodTillDate;
+
+/// Stop or limit attached to an order, as a level or as a distance but never both
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Attached {
+    None,
+    Level(f64),
+    Distance(f64),
+}
+
+impl Attached {
+    fn level(self) -> Option<f64> {
+        match self {
+            Attached::Level(level) => Some(level),
+            _ => None,
+        }
+    }
+
+    fn distance(self) -> Option<f64> {
+        match self {
+            Attached::Distance(distance) => Some(distance),
+            _ => None,
+        }
+    }
+}
+
+/// Builder of [`CreateOrderRequest`] for market (`OrderBuilder<Market>`) and limit
+/// (`OrderBuilder<Limit>`) orders
+#[derive(Debug, Clone)]
+pub struct OrderBuilder<K> {
+    request: CreateOrderRequest,
+    stop: Attached,
+    limit: Attached,
+    trailing_increment: Option<f64>,
+    kind: PhantomData<K>,
+}
+
+impl OrderBuilder<Market> {
+    /// Starts a market order, executed or eliminated at the current price
+    pub fn market(epic: &str, direction: Direction, size: f64, currency_code: &str) -> Self {
+        Self::from_request(CreateOrderRequest::market(
+            epic.to_string(),
+            direction,
+            size,
+            currency_code.to_string(),
+        ))
+    }
+}
+
+impl OrderBuilder<Limit> {
+    /// Starts a limit order, filled entirely at `level` or better or not at all
+    pub fn limit(
+        epic: &str,
+        direction: Direction,
+        size: f64,
+        level: f64,
+        currency_code: &str,
+    ) -> Self {
+        let mut request = CreateOrderRequest::limit(
+            epic.to_string(),
+            direction,
+            size,
+            level,
+            currency_code.to_string(),
+        );
+        request.time_in_force = TimeInForce::FillOrKill;
+        request.force_open = false;
+        Self::from_request(request)
+    }
+
+    /// Lets the order be partially filled, the rest being eliminated
+    pub fn execute_and_eliminate(mut self) -> Self {
+        self.request.time_in_force = TimeInForce::ExecuteAndEliminate;
+        self
+    }
+}
+
+impl<K> OrderBuilder<K> {
+    fn from_request(request: CreateOrderRequest) -> Self {
+        Self {
+            request,
+            stop: Attached::None,
+            limit: Attached::None,
+            trailing_increment: None,
+            kind: PhantomData,
+        }
+    }
+
+    /// Sets the expiry of the instrument, `-` (the default) for undated markets
+    pub fn with_expiry(mut self, expiry: &str) -> Self {
+        self.request.expiry = expiry.to_string();
+        self
+    }
+
+    /// Sets the client deal reference
+    pub fn with_reference(mut self, reference: &str) -> Self {
+        self.request.deal_reference = Some(reference.to_string());
+        self
+    }
+
+    /// Rounds the size to the step and maximum deal size of the market
+    ///
+    /// # Returns
+    /// * `AppError::InvalidInput` if the size is below the minimum deal size
+    pub fn round_size_to(mut self, rules: &DealingRules) -> Result<Self, AppError> {
+        self.request.size = round_size_to_rules(self.request.size, rules)?;
+        Ok(self)
+    }
+
+    /// Opens a new position even if an opposite one exists on the market
+    pub fn force_open(mut self) -> Self {
+        self.request.force_open = true;
+        self
+    }
+
+    /// Places the stop loss at a level, replacing any stop distance or trailing stop
+    pub fn with_stop_level(mut self, level: f64) -> Self {
+        self.stop = Attached::Level(level);
+        self.trailing_increment = None;
+        self
+    }
+
+    /// Places the stop loss at a distance, replacing any stop level
+    pub fn with_stop_distance(mut self, distance: f64) -> Self {
+        self.stop = Attached::Distance(distance);
+        self
+    }
+
+    /// Makes the stop a guaranteed stop, dropping any trailing stop
+    pub fn guaranteed_stop(mut self) -> Self {
+        self.request.guaranteed_stop = true;
+        self.trailing_increment = None;
+        self
+    }
+
+    /// Makes the stop a trailing stop at a distance, which cannot be guaranteed
+    pub fn with_trailing_stop(mut self, distance: f64, increment: f64) -> Self {
+        self.stop = Attached::Distance(distance);
+        self.trailing_increment = Some(increment);
+        self.request.guaranteed_stop = false;
+        self
+    }
+
+    /// Places the take profit at a level, replacing any limit distance
+    pub fn with_limit_level(mut self, level: f64) -> Self {
+        self.limit = Attached::Level(level);
+        self
+    }
+
+    /// Places the take profit at a distance, replacing any limit level
+    pub fn with_limit_distance(mut self, distance: f64) -> Self {
+        self.limit = Attached::Distance(distance);
+        self
+    }
+
+    /// Builds the request
+    ///
+    /// Attaching a stop or a limit sets `force_open`, which IG requires for them.
+    pub fn build(self) -> CreateOrderRequest {
+        let mut request = self.request;
+        request.stop_level = self.stop.level();
+        request.stop_distance = self.stop.distance();
+        request.limit_level = self.limit.level();
+        request.limit_distance = self.limit.distance();
+        request.trailing_stop = self.trailing_increment.map(|_| true);
+        request.trailing_stop_increment = self.trailing_increment;
+        request.guaranteed_stop &= self.stop != Attached::None;
+        // IG only attaches a stop or a limit to a new position, never to a netting deal
+        if self.stop != Attached::None || self.limit != Attached::None {
+            request.force_open = true;
+        }
+        request
+    }
+}
+
+/// Builder of [`CreateWorkingOrderRequest`] for limit and stop working orders
+///
+/// The time in force is part of the type: orders start good till cancelled and
+/// [`WorkingOrderBuilder::good_till_date`] is the only way to get a dated order.
+#[derive(Debug, Clone)]
+pub struct WorkingOrderBuilder<K, T = GoodTillCancelled> {
+    request: CreateWorkingOrderRequest,
+    stop: Attached,
+    limit: Attached,
+    kind: PhantomData<(K, T)>,
+}
+
+impl WorkingOrderBuilder<Limit> {
+    /// Starts a limit working order, filled at `level` or better
+    pub fn limit(epic: &str, direction: Direction, size: f64, level: f64) -> Self {
+        Self::from_request(CreateWorkingOrderRequest::limit(
+            epic.to_string(),
+            direction,
+            size,
+            level,
+        ))
+    }
+}
+
+impl WorkingOrderBuilder<Stop> {
+    /// Starts a stop working order, triggered when the price crosses `level`
+    pub fn stop(epic: &str, direction: Direction, size: f64, level: f64) -> Self {
+        Self::from_request(CreateWorkingOrderRequest::stop(
+            epic.to_string(),
+            direction,
+            size,
+            level,
+        ))
+    }
+}
+
+impl WorkingOrderBuilder<StopLimit> {
+    /// Starts a stop-limit working order, triggered when the price crosses
+    /// `stop_level` and then filled at `limit_price` or better
+    ///
+    /// Only the paper order service accepts it, IG having no stop-limit working orders.
+    pub fn stop_limit(
+        epic: &str,
+        direction: Direction,
+        size: f64,
+        stop_level: f64,
+        limit_price: f64,
+    ) -> Self {
+        Self::from_request(CreateWorkingOrderRequest::stop_limit(
+            epic.to_string(),
+            direction,
+            size,
+            stop_level,
+            limit_price,
+        ))
+    }
+}
+
+impl<K> WorkingOrderBuilder<K, GoodTillCancelled> {
+    fn from_request(request: CreateWorkingOrderRequest) -> Self {
+        Self {
+            request,
+            stop: Attached::None,
+            limit: Attached::None,
+            kind: PhantomData,
+        }
+    }
+
+    /// Keeps the order until `date` (`yyyy/mm/dd hh:mm:ss`) instead of until cancelled
+    pub fn good_till_date(mut self, date: &str) -> WorkingOrderBuilder<K, GoodTillDate> {
+        self.request.time_in_force = TimeInForce::GoodTillDate;
+        self.request.good_till_date = Some(date.to_string());
+        WorkingOrderBuilder {
+            request: self.request,
+            stop: self.stop,
+            limit: self.limit,
+            kind: PhantomData,
+        }
+    }
+}
+
+impl<K, T> WorkingOrderBuilder<K, T> {
+    /// Sets the expiry of the instrument, `DFB` by default
+    pub fn with_expiry(mut self, expiry: &str) -> Self {
+        self.request.expiry = expiry.to_string();
+        self
+    }
+
+    /// Sets the currency of the order
+    pub fn with_currency(mut self, currency_code: &str) -> Self {
+        self.request.currency_code = Some(currency_code.to_string());
+        self
+    }
+
+    /// Sets the client deal reference
+    pub fn with_reference(mut self, reference: &str) -> Self {
+        self.request.deal_reference = Some(reference.to_string());
+        self
+    }
+
+    /// Rounds the size to the step and maximum deal size of the market
+    ///
+    /// # Returns
+    /// * `AppError::InvalidInput` if the size is below the minimum deal size
+    pub fn round_size_to(mut self, rules: &DealingRules) -> Result<Self, AppError> {
+        self.request.size = round_size_to_rules(self.request.size, rules)?;
+        Ok(self)
+    }
+
+    /// Places the stop loss at a level, replacing any stop distance
+    pub fn with_stop_level(mut self, level: f64) -> Self {
+        self.stop = Attached::Level(level);
+        self
+    }
+
+    /// Places the stop loss at a distance, replacing any stop level
+    pub fn with_stop_distance(mut self, distance: f64) -> Self {
+        self.stop = Attached::Distance(distance);
+        self
+    }
+
+    /// Makes the stop a guaranteed stop
+    pub fn guaranteed_stop(mut self) -> Self {
+        self.request.guaranteed_stop = true;
+        self
+    }
+
+    /// Places the take profit at a level, replacing any limit distance
+    pub fn with_limit_level(mut self, level: f64) -> Self {
+        self.limit = Attached::Level(level);
+        self
+    }
+
+    /// Places the take profit at a distance, replacing any limit level
+    pub fn with_limit_distance(mut self, distance: f64) -> Self {
+        self.limit = Attached::Distance(distance);
+        self
+    }
+
+    /// Builds the request
+    pub fn build(self) -> CreateWorkingOrderRequest {
+        let mut request = self.request;
+        request.stop_level = self.stop.level();
+        request.stop_distance = self.stop.distance();
+        request.limit_level = self.limit.level();
+        request.limit_distance = self.limit.distance();
+        request.guaranteed_stop &= self.stop != Attached::None;
+        request
+    }
+}
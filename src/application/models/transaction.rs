@@ -1,10 +1,90 @@
 use crate::application::models::account::AccountTransaction;
+use crate::error::AppError;
 use crate::impl_json_display;
-use crate::utils::parsing::{ParsedOptionInfo, parse_instrument_name};
+use crate::utils::currency::CurrencyConverter;
+use crate::utils::parsing::{
+    AdminEntry, ParsedOptionInfo, parse_admin_entry, parse_amount, parse_exchange_rate,
+    parse_instrument_name,
+};
 use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc, Weekday};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
+/// Cash-flow category of an account transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionCategory {
+    /// Realized profit or loss of a deal
+    Trade,
+    /// Charges, commissions and admin fees
+    Fee,
+    /// Overnight funding, interest and rollover adjustments
+    Funding,
+    /// Dividend adjustments
+    Dividend,
+    /// Funds paid into the account
+    Deposit,
+    /// Funds taken out of the account
+    Withdrawal,
+    /// Transfers between accounts
+    Transfer,
+    /// Anything else
+    Other,
+}
+
+impl TransactionCategory {
+    /// Classifies a transaction from its type and instrument name
+    ///
+    /// Deals are trades. Cash transactions (`DEPO`, `WITH` and the like) are
+    /// classified by the administrative entry detected in their instrument name, see
+    /// [`parse_admin_entry`], and otherwise by the sign of their type.
+    pub fn of(transaction: &AccountTransaction) -> Self {
+        let transaction_type = transaction.transaction_type.to_ascii_uppercase();
+        if transaction_type == "DEAL" {
+            return TransactionCategory::Trade;
+        }
+        match parse_admin_entry(&transaction.instrument_name) {
+            Some(AdminEntry::Fee) => return TransactionCategory::Fee,
+            Some(AdminEntry::Funding) => return TransactionCategory::Funding,
+            Some(AdminEntry::Dividend) => return TransactionCategory::Dividend,
+            Some(AdminEntry::Transfer) => return TransactionCategory::Transfer,
+            None => {}
+        }
+        match transaction_type.as_str() {
+            "DEPO" | "DEPOSIT" => TransactionCategory::Deposit,
+            "WITH" | "WITHDRAWAL" => TransactionCategory::Withdrawal,
+            "DIVIDEND" => TransactionCategory::Dividend,
+            "TRANSFER" | "EXCHANGE" => TransactionCategory::Transfer,
+            "CHRG" | "CHARGE" | "FEE" => TransactionCategory::Fee,
+            _ => TransactionCategory::Other,
+        }
+    }
+
+    /// Returns true for movements of funds rather than trading results
+    pub fn is_external_flow(&self) -> bool {
+        matches!(
+            self,
+            TransactionCategory::Deposit
+                | TransactionCategory::Withdrawal
+                | TransactionCategory::Transfer
+        )
+    }
+
+    /// Sums the amounts of transactions per category
+    ///
+    /// Amounts that cannot be parsed count as zero.
+    pub fn totals(transactions: &[AccountTransaction]) -> BTreeMap<TransactionCategory, f64> {
+        let mut totals = BTreeMap::new();
+        for transaction in transactions {
+            *totals
+                .entry(TransactionCategory::of(transaction))
+                .or_default() += parse_amount(&transaction.profit_and_loss).unwrap_or_default();
+        }
+        totals
+    }
+}
+
 /// Represents a processed transaction from IG Markets with parsed fields
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StoreTransaction {
@@ -32,61 +112,64 @@ pub struct StoreTransaction {
 
 impl_json_display!(StoreTransaction);
 
+/// Parses the expiry of an option or future from the period of a transaction
+///
+/// `DD-MON-YY` periods give the exact day; `MON-YY` periods give the last Wednesday of
+/// the previous month.
+fn parse_period(period: &str) -> Option<NaiveDate> {
+    // For format "DD-MON-YY"
+    if let Some((day_str, rest)) = period.split_once('-')
+        && let Some((mon_str, year_str)) = rest.split_once('-')
+        // Try to parse the day
+        && let Ok(day) = day_str.parse::<u32>()
+    {
+        let month = chrono::Month::from_str(mon_str).ok()?;
+        let year = 2000 + year_str.parse::<i32>().ok()?;
+
+        // Return the exact date
+        return NaiveDate::from_ymd_opt(year, month.number_from_month(), day);
+    }
+
+    // For format "MON-YY"
+    if let Some((mon_str, year_str)) = period.split_once('-') {
+        let month = chrono::Month::from_str(mon_str).ok()?;
+        let year = 2000 + year_str.parse::<i32>().ok()?;
+
+        // Get the first day of the month
+        let first_of_month = NaiveDate::from_ymd_opt(year, month.number_from_month(), 1)?;
+
+        // Get the first day of the previous month
+        let prev_month = if month.number_from_month() == 1 {
+            // If January, go to December of previous year
+            NaiveDate::from_ymd_opt(year - 1, 12, 1)?
+        } else {
+            // Otherwise, just go to previous month
+            NaiveDate::from_ymd_opt(year, month.number_from_month() - 1, 1)?
+        };
+
+        // Find the last day of the previous month
+        let last_day_of_prev_month = if prev_month.month() == 12 {
+            // December has 31 days
+            NaiveDate::from_ymd_opt(prev_month.year(), 12, 31)?
+        } else {
+            // For other months, the last day is one day before the first of current month
+            first_of_month - Duration::days(1)
+        };
+
+        // Calculate how many days to go back to find the last Wednesday
+        let days_back = (last_day_of_prev_month.weekday().num_days_from_monday() + 7
+            - Weekday::Wed.num_days_from_monday())
+            % 7;
+
+        // Get the last Wednesday
+        return Some(last_day_of_prev_month - Duration::days(days_back as i64));
+    }
+
+    None
+}
+
 impl From<AccountTransaction> for StoreTransaction {
     fn from(raw: AccountTransaction) -> Self {
-        fn parse_period(period: &str) -> Option<NaiveDate> {
-            // For format "DD-MON-YY"
-            if let Some((day_str, rest)) = period.split_once('-') {
-                if let Some((mon_str, year_str)) = rest.split_once('-') {
-                    // Try to parse the day
-                    if let Ok(day) = day_str.parse::<u32>() {
-                        let month = chrono::Month::from_str(mon_str).ok()?;
-                        let year = 2000 + year_str.parse::<i32>().ok()?;
-
-                        // Return the exact date
-                        return NaiveDate::from_ymd_opt(year, month.number_from_month(), day);
-                    }
-                }
-            }
-
-            // For format "MON-YY"
-            if let Some((mon_str, year_str)) = period.split_once('-') {
-                let month = chrono::Month::from_str(mon_str).ok()?;
-                let year = 2000 + year_str.parse::<i32>().ok()?;
-
-                // Get the first day of the month
-                let first_of_month = NaiveDate::from_ymd_opt(year, month.number_from_month(), 1)?;
-
-                // Get the first day of the previous month
-                let prev_month = if month.number_from_month() == 1 {
-                    // If January, go to December of previous year
-                    NaiveDate::from_ymd_opt(year - 1, 12, 1)?
-                } else {
-                    // Otherwise, just go to previous month
-                    NaiveDate::from_ymd_opt(year, month.number_from_month() - 1, 1)?
-                };
-
-                // Find the last day of the previous month
-                let last_day_of_prev_month = if prev_month.month() == 12 {
-                    // December has 31 days
-                    NaiveDate::from_ymd_opt(prev_month.year(), 12, 31)?
-                } else {
-                    // For other months, the last day is one day before the first of current month
-                    first_of_month - Duration::days(1)
-                };
-
-                // Calculate how many days to go back to find the last Wednesday
-                let days_back = (last_day_of_prev_month.weekday().num_days_from_monday() + 7
-                    - Weekday::Wed.num_days_from_monday())
-                    % 7;
-
-                // Get the last Wednesday
-                return Some(last_day_of_prev_month - Duration::days(days_back as i64));
-            }
-
-            None
-        }
-
         let instrument_info: ParsedOptionInfo = parse_instrument_name(&raw.instrument_name);
         let underlying = Some(instrument_info.asset_name);
         let strike = match instrument_info {
@@ -151,3 +234,144 @@ impl From<&Vec<AccountTransaction>> for TransactionList {
         )
     }
 }
+
+/// Origin of the rate converting a transaction into the base currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RateSource {
+    /// The transaction is already in the base currency
+    Base,
+    /// Rate noted by IG in the instrument name, see [`parse_exchange_rate`]
+    InstrumentName,
+    /// Rate of the [`CurrencyConverter`]
+    Converter,
+}
+
+/// Profit or loss of a transaction converted into the base currency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BasePnl {
+    /// Profit or loss, in `currency`
+    pub amount: f64,
+    /// Base currency of the account
+    pub currency: String,
+    /// Rate applied to the profit or loss in the transaction currency
+    pub rate: f64,
+    /// Origin of the rate
+    pub source: RateSource,
+}
+
+/// Account transaction with its instrument parsed, for options accounting
+///
+/// The underlying, strike and option type come from [`parse_instrument_name`], and
+/// the expiry from the period of the transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrichedTransaction {
+    /// Transaction as returned by IG
+    pub transaction: AccountTransaction,
+    /// Underlying, strike and option type parsed from the instrument name
+    pub instrument: ParsedOptionInfo,
+    /// Cash-flow category of the transaction
+    pub category: TransactionCategory,
+    /// Expiry parsed from the period, for options and futures
+    pub expiry: Option<NaiveDate>,
+    /// Profit or loss in the base currency, once [`EnrichedTransaction::normalize`]d
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_pnl: Option<BasePnl>,
+}
+
+impl EnrichedTransaction {
+    /// Parses the instrument of a transaction
+    pub fn new(transaction: AccountTransaction) -> Self {
+        Self {
+            instrument: parse_instrument_name(&transaction.instrument_name),
+            category: TransactionCategory::of(&transaction),
+            expiry: parse_period(&transaction.period),
+            base_pnl: None,
+            transaction,
+        }
+    }
+
+    /// Parses the instruments of several transactions
+    pub fn enrich(transactions: &[AccountTransaction]) -> Vec<Self> {
+        transactions.iter().map(Self::from).collect()
+    }
+
+    /// Name of the underlying, e.g. `US Tech 100`
+    pub fn underlying(&self) -> &str {
+        &self.instrument.asset_name
+    }
+
+    /// Strike of an option, `None` for other instruments
+    pub fn strike(&self) -> Option<f64> {
+        self.instrument.strike.as_deref()?.parse().ok()
+    }
+
+    /// Returns true for options
+    pub fn is_option(&self) -> bool {
+        self.instrument.option_type.is_some()
+    }
+
+    /// Profit or loss of the transaction, `None` if it cannot be parsed
+    pub fn pnl(&self) -> Option<f64> {
+        parse_amount(&self.transaction.profit_and_loss)
+    }
+
+    /// Exchange rate noted by IG in the instrument name, if any
+    pub fn exchange_rate_hint(&self) -> Option<f64> {
+        parse_exchange_rate(&self.transaction.instrument_name)
+    }
+
+    /// Converts the profit or loss into the base currency of `converter`
+    ///
+    /// A rate noted in the instrument name is the one IG applied, so it takes
+    /// precedence over the rates of the converter.
+    ///
+    /// # Returns
+    /// The converted profit or loss, or `AppError::InvalidInput` if it cannot be parsed
+    /// or no rate is known for the transaction currency
+    pub fn to_base(&self, converter: &CurrencyConverter) -> Result<BasePnl, AppError> {
+        let pnl = self.pnl().ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "invalid profit and loss: {}",
+                self.transaction.profit_and_loss
+            ))
+        })?;
+        let base = converter.base_currency();
+        let (rate, source) = if self.transaction.currency == base {
+            (1.0, RateSource::Base)
+        } else if let Some(rate) = self.exchange_rate_hint() {
+            (rate, RateSource::InstrumentName)
+        } else {
+            let rate = converter.to_base(1.0, &self.transaction.currency)?;
+            (rate, RateSource::Converter)
+        };
+        Ok(BasePnl {
+            amount: pnl * rate,
+            currency: base.to_string(),
+            rate,
+            source,
+        })
+    }
+
+    /// Converts the profit or loss into the base currency and keeps it in `base_pnl`
+    ///
+    /// See [`EnrichedTransaction::to_base`].
+    pub fn normalize(&mut self, converter: &CurrencyConverter) -> Result<&BasePnl, AppError> {
+        let base_pnl = self.to_base(converter)?;
+        Ok(self.base_pnl.insert(base_pnl))
+    }
+}
+
+impl From<AccountTransaction> for EnrichedTransaction {
+    fn from(transaction: AccountTransaction) -> Self {
+        Self::new(transaction)
+    }
+}
+
+impl From<&AccountTransaction> for EnrichedTransaction {
+    fn from(transaction: &AccountTransaction) -> Self {
+        Self::new(transaction.clone())
+    }
+}
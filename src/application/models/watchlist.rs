@@ -0,0 +1,101 @@
+use crate::application::models::market::MarketData;
+use crate::impl_json_display;
+use serde::{Deserialize, Serialize};
+
+/// Summary of a watchlist as returned by `GET /watchlists`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Watchlist {
+    /// Watchlist identifier
+    pub id: String,
+    /// Watchlist name
+    pub name: String,
+    /// Whether markets can be added to or removed from the watchlist
+    pub editable: bool,
+    /// Whether the watchlist can be deleted
+    pub deleteable: bool,
+    /// Whether this is one of the default system watchlists
+    #[serde(rename = "defaultSystemWatchlist")]
+    pub default_system_watchlist: bool,
+}
+
+/// Response to `GET /watchlists`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Watchlists {
+    /// All watchlists of the account
+    pub watchlists: Vec<Watchlist>,
+}
+
+/// Response to `GET /watchlists/{watchlistId}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchlistMarkets {
+    /// Markets in the watchlist
+    pub markets: Vec<MarketData>,
+}
+
+impl WatchlistMarkets {
+    /// Returns the EPICs of all markets in the watchlist
+    pub fn epics(&self) -> Vec<String> {
+        self.markets.iter().map(|m| m.epic.clone()).collect()
+    }
+}
+
+/// Request body of `POST /watchlists`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWatchlistRequest {
+    /// Watchlist name
+    pub name: String,
+    /// EPICs to add to the new watchlist
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub epics: Option<Vec<String>>,
+}
+
+/// Status returned when creating a watchlist
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CreateWatchlistStatus {
+    /// All instruments were added
+    Success,
+    /// The watchlist was created but some instruments could not be added
+    SuccessNotAllInstrumentsAdded,
+}
+
+/// Response to `POST /watchlists`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWatchlistResponse {
+    /// Identifier of the new watchlist
+    #[serde(rename = "watchlistId")]
+    pub watchlist_id: String,
+    /// Outcome of the creation
+    pub status: CreateWatchlistStatus,
+}
+
+/// Request body of `PUT /watchlists/{watchlistId}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddToWatchlistRequest {
+    /// EPIC of the market to add
+    pub epic: String,
+}
+
+/// Status returned by watchlist update and delete operations
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchlistStatusResponse {
+    /// Operation status, `SUCCESS` when the operation succeeded
+    pub status: String,
+}
+
+impl WatchlistStatusResponse {
+    /// Returns true if the operation succeeded
+    pub fn is_success(&self) -> bool {
+        self.status == "SUCCESS"
+    }
+}
+
+impl_json_display!(
+    Watchlist,
+    Watchlists,
+    WatchlistMarkets,
+    CreateWatchlistRequest,
+    CreateWatchlistResponse,
+    AddToWatchlistRequest,
+    WatchlistStatusResponse
+);
@@ -1,8 +1,10 @@
+use crate::application::models::account::WorkingOrderData;
 use crate::application::models::order::{Direction, OrderType, TimeInForce};
+use crate::error::AppError;
 use serde::{Deserialize, Serialize};
 
 /// Model for creating a new working order
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateWorkingOrderRequest {
     /// Instrument EPIC identifier
     pub epic: String,
@@ -10,9 +12,15 @@ pub struct CreateWorkingOrderRequest {
     pub direction: Direction,
     /// Order size/quantity
     pub size: f64,
-    /// Price level for the order
+    /// Price level for the order, the trigger level of a stop-limit order
     pub level: f64,
-    /// Type of working order (LIMIT or STOP)
+    /// Limit price of a stop-limit order
+    ///
+    /// Once the price reaches `level`, the order only fills at this price or better.
+    /// Not part of the IG schema: only the paper order service reads it.
+    #[serde(rename = "limitPrice", skip_serializing_if = "Option::is_none")]
+    pub limit_price: Option<f64>,
+    /// Type of working order (LIMIT, STOP or STOP_LIMIT)
     #[serde(rename = "type")]
     pub order_type: OrderType,
     /// Order duration (how long the order remains valid)
@@ -55,6 +63,7 @@ impl CreateWorkingOrderRequest {
             direction,
             size,
             level,
+            limit_price: None,
             order_type: OrderType::Limit,
             time_in_force: TimeInForce::GoodTillCancelled,
             guaranteed_stop: false,
@@ -76,6 +85,7 @@ impl CreateWorkingOrderRequest {
             direction,
             size,
             level,
+            limit_price: None,
             order_type: OrderType::Stop,
             time_in_force: TimeInForce::GoodTillCancelled,
             guaranteed_stop: false,
@@ -90,6 +100,57 @@ impl CreateWorkingOrderRequest {
         }
     }
 
+    /// Creates a new stop-limit working order
+    ///
+    /// The order is triggered when the price reaches `stop_level`, then fills at
+    /// `limit_price` or better: at or below it for a buy, at or above it for a sell.
+    /// IG has no stop-limit working orders, so only the paper order service accepts
+    /// them; the live order service rejects them with `AppError::InvalidInput`.
+    pub fn stop_limit(
+        epic: String,
+        direction: Direction,
+        size: f64,
+        stop_level: f64,
+        limit_price: f64,
+    ) -> Self {
+        let mut order = Self::stop(epic, direction, size, stop_level);
+        order.order_type = OrderType::StopLimit;
+        order.limit_price = Some(limit_price);
+        order
+    }
+
+    /// Checks that the levels are consistent with the type of the order
+    ///
+    /// A stop-limit order needs a limit price no worse than its trigger level, which
+    /// other types must not have.
+    ///
+    /// # Returns
+    /// * `AppError::InvalidInput` describing the inconsistency
+    pub fn validate_levels(&self) -> Result<(), AppError> {
+        match (&self.order_type, self.limit_price) {
+            (OrderType::StopLimit, None) => Err(AppError::InvalidInput(
+                "a stop-limit order needs a limit price".to_string(),
+            )),
+            (OrderType::StopLimit, Some(limit)) => {
+                let worse = match self.direction {
+                    Direction::Buy => limit < self.level,
+                    Direction::Sell => limit > self.level,
+                };
+                if worse {
+                    return Err(AppError::InvalidInput(format!(
+                        "limit price {limit} of a {:?} stop-limit cannot fill once triggered at {}",
+                        self.direction, self.level
+                    )));
+                }
+                Ok(())
+            }
+            (order_type, Some(limit)) => Err(AppError::InvalidInput(format!(
+                "limit price {limit} is only valid for stop-limit orders, not {order_type:?}"
+            ))),
+            (_, None) => Ok(()),
+        }
+    }
+
     /// Adds a stop loss to the working order
     pub fn with_stop_loss(mut self, stop_level: f64) -> Self {
         self.stop_level = Some(stop_level);
@@ -123,9 +184,135 @@ impl CreateWorkingOrderRequest {
 }
 
 /// Response to working order creation
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateWorkingOrderResponse {
     /// Client-generated reference for the deal
     #[serde(rename = "dealReference")]
     pub deal_reference: String,
 }
+
+/// Model for amending an existing working order
+///
+/// IG requires the level, type and duration of the order on every amendment; the
+/// other fields left as `None` are not sent.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UpdateWorkingOrderRequest {
+    /// New price level for the order
+    pub level: f64,
+    /// Type of working order (LIMIT or STOP)
+    #[serde(rename = "type")]
+    pub order_type: OrderType,
+    /// Order duration
+    #[serde(rename = "timeInForce")]
+    pub time_in_force: TimeInForce,
+    /// New expiry date for GTD orders
+    #[serde(rename = "goodTillDate", skip_serializing_if = "Option::is_none")]
+    pub good_till_date: Option<String>,
+    /// Whether to use a guaranteed stop
+    #[serde(rename = "guaranteedStop", skip_serializing_if = "Option::is_none")]
+    pub guaranteed_stop: Option<bool>,
+    /// New price level for stop loss
+    #[serde(rename = "stopLevel", skip_serializing_if = "Option::is_none")]
+    pub stop_level: Option<f64>,
+    /// New distance for stop loss
+    #[serde(rename = "stopDistance", skip_serializing_if = "Option::is_none")]
+    pub stop_distance: Option<f64>,
+    /// New price level for take profit
+    #[serde(rename = "limitLevel", skip_serializing_if = "Option::is_none")]
+    pub limit_level: Option<f64>,
+    /// New distance for take profit
+    #[serde(rename = "limitDistance", skip_serializing_if = "Option::is_none")]
+    pub limit_distance: Option<f64>,
+}
+
+impl UpdateWorkingOrderRequest {
+    /// Creates an amendment moving an order of the given type and duration to a new level
+    pub fn new(level: f64, order_type: OrderType, time_in_force: TimeInForce) -> Self {
+        Self {
+            level,
+            order_type,
+            time_in_force,
+            good_till_date: None,
+            guaranteed_stop: None,
+            stop_level: None,
+            stop_distance: None,
+            limit_level: None,
+            limit_distance: None,
+        }
+    }
+
+    /// Creates an amendment moving a working order to a new level
+    ///
+    /// The type, duration and expiry date are kept from the current order.
+    pub fn for_order(order: &WorkingOrderData, level: f64) -> Self {
+        let mut update = Self::new(level, order.order_type.clone(), order.time_in_force.clone());
+        update.good_till_date = order.good_till_date.clone();
+        update
+    }
+
+    /// Changes the type of the working order
+    pub fn with_order_type(mut self, order_type: OrderType) -> Self {
+        self.order_type = order_type;
+        self
+    }
+
+    /// Sets the stop loss at a distance from the order level
+    pub fn with_stop_distance(mut self, stop_distance: f64) -> Self {
+        self.stop_distance = Some(stop_distance);
+        self
+    }
+
+    /// Sets the take profit at a distance from the order level
+    pub fn with_limit_distance(mut self, limit_distance: f64) -> Self {
+        self.limit_distance = Some(limit_distance);
+        self
+    }
+
+    /// Sets the stop loss at a price level
+    pub fn with_stop_loss(mut self, stop_level: f64) -> Self {
+        self.stop_level = Some(stop_level);
+        self
+    }
+
+    /// Sets the take profit at a price level
+    pub fn with_take_profit(mut self, limit_level: f64) -> Self {
+        self.limit_level = Some(limit_level);
+        self
+    }
+
+    /// Enables or disables the guaranteed stop
+    pub fn with_guaranteed_stop(mut self, guaranteed_stop: bool) -> Self {
+        self.guaranteed_stop = Some(guaranteed_stop);
+        self
+    }
+
+    /// Keeps the order until it is cancelled
+    pub fn good_till_cancelled(mut self) -> Self {
+        self.time_in_force = TimeInForce::GoodTillCancelled;
+        self.good_till_date = None;
+        self
+    }
+
+    /// Sets the order to expire at a specific date
+    pub fn expires_at(mut self, date: String) -> Self {
+        self.time_in_force = TimeInForce::GoodTillDate;
+        self.good_till_date = Some(date);
+        self
+    }
+}
+
+/// Response to working order amendment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateWorkingOrderResponse {
+    /// Client-generated reference for the amendment deal
+    #[serde(rename = "dealReference")]
+    pub deal_reference: String,
+}
+
+/// Response to working order deletion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteWorkingOrderResponse {
+    /// Client-generated reference for the deletion deal
+    #[serde(rename = "dealReference")]
+    pub deal_reference: String,
+}
@@ -1,17 +1,25 @@
 use crate::application::services::AccountService;
 use crate::{
     application::models::account::{
-        AccountActivity, AccountInfo, Positions, TransactionHistory, WorkingOrders,
+        AccountActivity, AccountInfo, AccountTransaction, Activity, ActivityQuery, MarginSummary,
+        Position, PositionMargin, Positions, TransactionHistory, TransactionType, WorkingOrders,
     },
+    application::models::market::MarketDetails,
+    application::models::order::Direction,
     config::Config,
+    constants::DEFAULT_PAGE_SIZE,
     error::AppError,
     session::interface::IgSession,
     transport::http_client::IgHttpClient,
+    utils::currency::CurrencyConverter,
+    utils::finance::calculate_margin,
 };
 use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use reqwest::Method;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 /// Implementation of the account service
 pub struct AccountServiceImpl<T: IgHttpClient> {
@@ -71,6 +79,92 @@ impl<T: IgHttpClient + 'static> AccountService for AccountServiceImpl<T> {
         Ok(result)
     }
 
+    async fn positions_with_pnl(
+        &self,
+        session: &IgSession,
+        converter: &CurrencyConverter,
+    ) -> Result<Positions, AppError> {
+        let mut positions = self.get_positions(session).await?;
+        for position in &mut positions.positions {
+            let (bid, offer) = (position.market.bid, position.market.offer);
+            position.pnl = match position.unrealized_pnl_in(bid, offer, converter) {
+                Ok(pnl) => Some(pnl),
+                Err(e) => {
+                    warn!("No P&L for position {}: {}", position.position.deal_id, e);
+                    None
+                }
+            };
+        }
+        Ok(positions)
+    }
+
+    async fn get_margin_summary(
+        &self,
+        session: &IgSession,
+        converter: &CurrencyConverter,
+    ) -> Result<MarginSummary, AppError> {
+        let accounts = self.get_accounts(session).await?.accounts;
+        let account = accounts
+            .iter()
+            .find(|account| account.account_id == session.account_id)
+            .ok_or(AppError::NotFound)?;
+        let positions = self.get_positions(session).await?.positions;
+
+        let mut converter = converter.clone();
+        let mut markets: HashMap<String, MarketDetails> = HashMap::new();
+        let mut margins = Vec::with_capacity(positions.len());
+        for position in &positions {
+            let epic = &position.market.epic;
+            if !markets.contains_key(epic) {
+                let path = format!("markets/{epic}");
+                let details = self
+                    .client
+                    .request::<(), MarketDetails>(Method::GET, &path, session, None, "3")
+                    .await?;
+                converter.add_market_details(&details);
+                markets.insert(epic.clone(), details);
+            }
+            let details = &position.position;
+            let level = match details.direction {
+                Direction::Buy => position.market.bid,
+                Direction::Sell => position.market.offer,
+            };
+            let margin = calculate_margin(&markets[epic], details.size, level, 1.0)?;
+            margins.push(PositionMargin {
+                deal_id: details.deal_id.clone(),
+                epic: epic.clone(),
+                margin: converter.to_base(margin, &details.currency)?,
+            });
+        }
+
+        let summary = MarginSummary::new(account, margins);
+        debug!(
+            "Margin of {}: {} used, {} free",
+            summary.account_id, summary.used_margin, summary.free_margin
+        );
+        Ok(summary)
+    }
+
+    async fn find_position_by_reference(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+    ) -> Result<Option<Position>, AppError> {
+        let positions = self.get_positions(session).await?;
+        let position = positions.find_by_reference(deal_reference).cloned();
+
+        debug!(
+            "Position with reference {} {}",
+            deal_reference,
+            if position.is_some() {
+                "found"
+            } else {
+                "not found"
+            }
+        );
+        Ok(position)
+    }
+
     async fn get_working_orders(&self, session: &IgSession) -> Result<WorkingOrders, AppError> {
         info!("Getting working orders");
 
@@ -128,6 +222,33 @@ impl<T: IgHttpClient + 'static> AccountService for AccountServiceImpl<T> {
         Ok(result)
     }
 
+    fn stream_activity<'a>(
+        &'a self,
+        session: &'a IgSession,
+        query: &ActivityQuery,
+    ) -> BoxStream<'a, Result<Activity, AppError>> {
+        let first = format!("history/activity?{}", query.to_query_string());
+        info!("Streaming account activity");
+
+        stream::try_unfold(Some(first), move |next_path| async move {
+            let Some(path) = next_path else {
+                return Ok::<_, AppError>(None);
+            };
+            let page = self
+                .client
+                .request::<(), AccountActivity>(Method::GET, &path, session, None, "3")
+                .await?;
+            let next_path = page.next_page_path();
+            debug!("Page of {} activities streamed", page.activities.len());
+            Ok(Some((
+                stream::iter(page.activities.into_iter().map(Ok)),
+                next_path,
+            )))
+        })
+        .try_flatten()
+        .boxed()
+    }
+
     async fn get_transactions(
         &self,
         session: &IgSession,
@@ -152,6 +273,46 @@ impl<T: IgHttpClient + 'static> AccountService for AccountServiceImpl<T> {
         );
         Ok(result)
     }
+
+    fn stream_transactions<'a>(
+        &'a self,
+        session: &'a IgSession,
+        from: &str,
+        to: &str,
+        tx_type: TransactionType,
+    ) -> BoxStream<'a, Result<AccountTransaction, AppError>> {
+        let query = format!(
+            "history/transactions?from={from}&to={to}&type={}&pageSize={DEFAULT_PAGE_SIZE}",
+            tx_type.as_str()
+        );
+        info!("Streaming {} transactions", tx_type.as_str());
+
+        stream::try_unfold(Some(1u32), move |next_page| {
+            let query = query.clone();
+            async move {
+                let Some(page_number) = next_page else {
+                    return Ok::<_, AppError>(None);
+                };
+                let path = format!("{query}&pageNumber={page_number}");
+                let page = self
+                    .client
+                    .request::<(), TransactionHistory>(Method::GET, &path, session, None, "2")
+                    .await?;
+                let next_page = page
+                    .metadata
+                    .page_data
+                    .has_more_pages()
+                    .then_some(page_number + 1);
+                debug!("Page {} of transactions streamed", page_number);
+                Ok(Some((
+                    stream::iter(page.transactions.into_iter().map(Ok)),
+                    next_page,
+                )))
+            }
+        })
+        .try_flatten()
+        .boxed()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,106 @@
+use crate::application::services::AccountService;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::storage::balance_history::{BalanceHistory, BalanceSnapshot, BalanceStore};
+use crate::utils::periodic::{PeriodicTask, ShutdownToken};
+use crate::utils::rate_limiter::{RateLimiter, wait_if_set};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// Default interval between two balance snapshots
+pub const DEFAULT_BALANCE_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Records the balance of the session account over time
+///
+/// IG only returns the current balance, so a task running [`BalanceTracker::run`]
+/// stores a snapshot on an interval, and [`BalanceTracker::history`] reads them back
+/// for the drawdown, daily profit and loss and equity curve of [`BalanceHistory`].
+pub struct BalanceTracker<A: AccountService, S: BalanceStore> {
+    account_service: A,
+    store: S,
+    interval: Duration,
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl<A: AccountService, S: BalanceStore> BalanceTracker<A, S> {
+    /// Creates a tracker reading balances from `account_service` into `store`
+    pub fn new(account_service: A, store: S) -> Self {
+        Self {
+            account_service,
+            store,
+            interval: DEFAULT_BALANCE_SNAPSHOT_INTERVAL,
+            rate_limiter: None,
+        }
+    }
+
+    /// Sets the interval between two snapshots in [`BalanceTracker::run`]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Uses a specific rate limiter for balance requests
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Returns the configured rate limiter, or the account limiter of `session`
+    fn rate_limiter(&self, session: &IgSession) -> Arc<RateLimiter> {
+        self.rate_limiter
+            .clone()
+            .unwrap_or_else(|| session.rate_limiters().account_non_trading.clone())
+    }
+
+    /// Returns the store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Reads the balance of the session account and stores a snapshot
+    ///
+    /// # Returns
+    /// The stored snapshot, or `AppError::NotFound` if the session account is not
+    /// among the accounts of the client
+    pub async fn record(&self, session: &IgSession) -> Result<BalanceSnapshot, AppError> {
+        wait_if_set(self.rate_limiter.as_deref()).await;
+        let accounts = self.account_service.get_accounts(session).await?.accounts;
+        let account = accounts
+            .into_iter()
+            .find(|account| account.account_id == session.account_id)
+            .ok_or(AppError::NotFound)?;
+        let snapshot =
+            BalanceSnapshot::new(&account.account_id, &account.currency, account.balance);
+        self.store.append(&snapshot)?;
+        debug!(
+            "Balance of {} recorded: equity {}",
+            snapshot.account_id,
+            snapshot.equity()
+        );
+        Ok(snapshot)
+    }
+
+    /// Records a snapshot on the configured interval until `shutdown` is cancelled
+    ///
+    /// Runs as a [`PeriodicTask`] notifying the rate limiter of the tracker.
+    pub async fn run(&self, session: &IgSession, shutdown: &ShutdownToken) {
+        PeriodicTask::new("record the balance", self.interval)
+            .with_rate_limiter(self.rate_limiter(session))
+            .run(shutdown, || async {
+                self.record(session).await.map(|_| ())
+            })
+            .await
+    }
+
+    /// Returns the snapshots of `account_id` taken in `[from, to)`
+    pub fn history(
+        &self,
+        account_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<BalanceHistory, AppError> {
+        Ok(BalanceHistory::new(self.store.query(account_id, from, to)?))
+    }
+}
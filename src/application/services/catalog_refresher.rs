@@ -0,0 +1,193 @@
+use crate::application::models::market::MarketNode;
+use crate::application::services::MarketService;
+use crate::application::services::navigation_cache::NavigationCache;
+use crate::application::services::navigation_crawler::NavigationCrawler;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::storage::Store;
+use crate::storage::instrument_catalog::{CatalogEntry, CatalogQuery, catalog_entries};
+use crate::utils::periodic::{PeriodicTask, ShutdownToken};
+use chrono::{Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, info};
+
+/// Default age after which the details of a catalog entry are fetched again, in days
+pub const DEFAULT_CATALOG_DETAILS_MAX_AGE_DAYS: i64 = 7;
+
+/// Outcome of an [`InstrumentCatalogRefresher::refresh`] run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CatalogRefreshReport {
+    /// Markets listed in the navigation tree
+    pub listed: usize,
+    /// Markets new to the catalog
+    pub added: usize,
+    /// Markets whose name, type, expiry, path or status changed
+    pub updated: usize,
+    /// Markets whose details were fetched
+    pub details_fetched: usize,
+    /// Markets whose details could not be fetched
+    pub details_failed: usize,
+    /// Catalog entries no longer listed in the navigation tree
+    pub unlisted: usize,
+}
+
+/// Keeps the instrument catalog of a [`Store`] in line with the navigation tree
+///
+/// Each run lists the markets of the tree, writes the new and changed ones, and
+/// fetches the details of those never fetched or older than
+/// [`DEFAULT_CATALOG_DETAILS_MAX_AGE_DAYS`] days, so a run after the first one only
+/// requests what changed. Entries no longer listed are kept, since expired markets
+/// still identify past deals.
+pub struct InstrumentCatalogRefresher<'a, S: MarketService, St: Store> {
+    market_service: &'a S,
+    store: St,
+    details_max_age: Duration,
+    max_details: Option<usize>,
+}
+
+impl<'a, S: MarketService, St: Store> InstrumentCatalogRefresher<'a, S, St> {
+    /// Creates a refresher fetching details from `market_service` into `store`
+    pub fn new(market_service: &'a S, store: St) -> Self {
+        Self {
+            market_service,
+            store,
+            details_max_age: Duration::days(DEFAULT_CATALOG_DETAILS_MAX_AGE_DAYS),
+            max_details: None,
+        }
+    }
+
+    /// Sets the age after which the details of an entry are fetched again
+    pub fn with_details_max_age(mut self, max_age: Duration) -> Self {
+        self.details_max_age = max_age;
+        self
+    }
+
+    /// Fetches the details of at most `max_details` markets per run, the oldest first
+    ///
+    /// The remaining markets are fetched by the next runs, which spreads a first
+    /// refresh of a large tree over time.
+    pub fn with_max_details(mut self, max_details: usize) -> Self {
+        self.max_details = Some(max_details);
+        self
+    }
+
+    /// Returns the store
+    pub fn store(&self) -> &St {
+        &self.store
+    }
+
+    /// Updates the catalog from a navigation tree
+    ///
+    /// # Returns
+    /// The report of the run, or the first store error; details that cannot be fetched
+    /// are counted in the report and retried on the next run
+    pub async fn refresh(
+        &self,
+        session: &IgSession,
+        tree: &[MarketNode],
+    ) -> Result<CatalogRefreshReport, AppError> {
+        let now = Utc::now();
+        let mut stored: HashMap<String, CatalogEntry> = self
+            .store
+            .search_instruments(&CatalogQuery::new())
+            .await?
+            .into_iter()
+            .map(|entry| (entry.epic.clone(), entry))
+            .collect();
+        let listed = catalog_entries(tree, now);
+        let mut report = CatalogRefreshReport {
+            listed: listed.len(),
+            ..Default::default()
+        };
+
+        let mut current: HashMap<String, CatalogEntry> = HashMap::with_capacity(listed.len());
+        let mut changed = HashSet::new();
+        let mut stale = Vec::new();
+        for mut entry in listed {
+            match stored.remove(&entry.epic) {
+                Some(previous) => {
+                    let differs = previous.listing_differs(&entry);
+                    // Details are kept until fetched again
+                    entry.listed_at = previous.listed_at;
+                    entry.currency = previous.currency;
+                    entry.dealing_rules = previous.dealing_rules;
+                    entry.details_at = previous.details_at;
+                    if differs {
+                        report.updated += 1;
+                        changed.insert(entry.epic.clone());
+                    }
+                }
+                None => {
+                    report.added += 1;
+                    changed.insert(entry.epic.clone());
+                }
+            }
+            if entry.details_stale(self.details_max_age, now) {
+                stale.push(entry.epic.clone());
+            }
+            current.insert(entry.epic.clone(), entry);
+        }
+        // Entries left are the ones no longer listed
+        report.unlisted = stored.len();
+
+        // Never fetched first, then the oldest
+        stale.sort_by_key(|epic| current[epic].details_at);
+        if let Some(max_details) = self.max_details {
+            stale.truncate(max_details);
+        }
+        if !stale.is_empty() {
+            info!("Fetching the details of {} catalog markets", stale.len());
+            let details = self
+                .market_service
+                .get_market_details_bulk(session, &stale)
+                .await;
+            for epic in &stale {
+                match details.get(epic) {
+                    Some(Ok(details)) => {
+                        let entry = current.get_mut(epic).expect("listed entry");
+                        entry.apply_details(details, now);
+                        report.details_fetched += 1;
+                        changed.insert(epic.clone());
+                    }
+                    Some(Err(e)) => {
+                        debug!("Failed to fetch the details of {}: {}", epic, e);
+                        report.details_failed += 1;
+                    }
+                    None => report.details_failed += 1,
+                }
+            }
+        }
+
+        let entries: Vec<CatalogEntry> = changed
+            .iter()
+            .filter_map(|epic| current.get(epic).cloned())
+            .collect();
+        self.store.put_instruments(&entries).await?;
+        info!(
+            "Catalog refreshed: {} listed, {} added, {} updated, {} details fetched",
+            report.listed, report.added, report.updated, report.details_fetched
+        );
+        Ok(report)
+    }
+
+    /// Refreshes the catalog on `interval`, from the tree of a navigation cache, until
+    /// `shutdown` is cancelled
+    ///
+    /// Runs as a [`PeriodicTask`]; crawls paused by the rate limit are retried on the
+    /// next tick.
+    pub async fn run(
+        &self,
+        session: &IgSession,
+        cache: &NavigationCache,
+        crawler: &NavigationCrawler<'_, S>,
+        interval: std::time::Duration,
+        shutdown: &ShutdownToken,
+    ) {
+        PeriodicTask::new("refresh the instrument catalog", interval)
+            .run(shutdown, || async {
+                let tree = cache.tree(crawler, session).await?;
+                self.refresh(session, &tree).await.map(|_| ())
+            })
+            .await
+    }
+}
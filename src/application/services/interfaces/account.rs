@@ -1,9 +1,13 @@
 use crate::application::models::account::{
-    AccountActivity, AccountInfo, Positions, TransactionHistory, WorkingOrders,
+    AccountActivity, AccountInfo, AccountTransaction, Activity, ActivityQuery, MarginSummary,
+    Position, Positions, Reconciliation, TransactionHistory, TransactionType, WorkingOrders,
 };
 use crate::error::AppError;
 use crate::session::interface::IgSession;
+use crate::utils::currency::CurrencyConverter;
 use async_trait::async_trait;
+use futures::TryStreamExt;
+use futures::stream::BoxStream;
 
 /// Interface for the account service
 #[async_trait]
@@ -14,6 +18,43 @@ pub trait AccountService: Send + Sync {
     /// Gets open positions
     async fn get_positions(&self, session: &IgSession) -> Result<Positions, AppError>;
 
+    /// Gets open positions with `pnl` filled in the account currency
+    ///
+    /// The P&L of each position is computed with
+    /// [`Position::unrealized_pnl_in`] at the bid and offer returned with the position.
+    /// Positions in a currency without a known rate keep `pnl` empty.
+    async fn positions_with_pnl(
+        &self,
+        session: &IgSession,
+        converter: &CurrencyConverter,
+    ) -> Result<Positions, AppError>;
+
+    /// Gets the used and free margin and the margin level of the session account
+    ///
+    /// The margin of each open position is computed with
+    /// [`calculate_margin`](crate::utils::finance::calculate_margin) from the details of
+    /// its market at its closing price, then converted into the account currency with
+    /// `converter`, completed with the exchange rates of the market details.
+    ///
+    /// # Returns
+    /// The summary, `AppError::NotFound` if the session account is not listed, or
+    /// `AppError::InvalidInput` if the margin of a position cannot be computed
+    async fn get_margin_summary(
+        &self,
+        session: &IgSession,
+        converter: &CurrencyConverter,
+    ) -> Result<MarginSummary, AppError>;
+
+    /// Finds an open position by the deal reference it was opened with
+    ///
+    /// # Returns
+    /// The position, or `None` if no open position has this deal reference
+    async fn find_position_by_reference(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+    ) -> Result<Option<Position>, AppError>;
+
     /// Gets working orders
     async fn get_working_orders(&self, session: &IgSession) -> Result<WorkingOrders, AppError>;
 
@@ -52,6 +93,17 @@ pub trait AccountService: Send + Sync {
         to: &str,
     ) -> Result<AccountActivity, AppError>;
 
+    /// Streams the v3 activity history selected by `query`, one activity at a time
+    ///
+    /// Pages are requested lazily as the stream is consumed, following the cursor IG
+    /// returns in `metadata.paging.next`. The stream ends after the last page or after
+    /// the first error.
+    fn stream_activity<'a>(
+        &'a self,
+        session: &'a IgSession,
+        query: &ActivityQuery,
+    ) -> BoxStream<'a, Result<Activity, AppError>>;
+
     /// Gets transaction history
     async fn get_transactions(
         &self,
@@ -61,4 +113,54 @@ pub trait AccountService: Send + Sync {
         page_size: u32,
         page_number: u32,
     ) -> Result<TransactionHistory, AppError>;
+
+    /// Streams the transactions of `tx_type` between `from` and `to`, one at a time
+    ///
+    /// Pages of [`DEFAULT_PAGE_SIZE`](crate::constants::DEFAULT_PAGE_SIZE) transactions
+    /// are requested lazily as the stream is consumed, up to the total number of pages
+    /// reported by IG. The stream ends after the last page or after the first error.
+    ///
+    /// # Arguments
+    /// * `session` - The current session
+    /// * `from` - Start date in ISO format (e.g. "2023-01-01T00:00:00")
+    /// * `to` - End date in ISO format (e.g. "2023-02-01T00:00:00")
+    /// * `tx_type` - Type of the transactions
+    fn stream_transactions<'a>(
+        &'a self,
+        session: &'a IgSession,
+        from: &str,
+        to: &str,
+        tx_type: TransactionType,
+    ) -> BoxStream<'a, Result<AccountTransaction, AppError>>;
+
+    /// Reports whether deal references match open positions, pending working orders,
+    /// closed deals or nothing
+    ///
+    /// Meant for trading bots recovering from a crash: the references they submitted are
+    /// looked up among the open positions and working orders, then among the detailed
+    /// activities since `from`, which must cover the submission of every reference.
+    ///
+    /// # Arguments
+    /// * `session` - The current session
+    /// * `deal_references` - References the orders were submitted with
+    /// * `from` - Start date of the activities searched (e.g. "2023-01-01T00:00:00")
+    async fn reconcile(
+        &self,
+        session: &IgSession,
+        deal_references: &[String],
+        from: &str,
+    ) -> Result<Reconciliation, AppError> {
+        let positions = self.get_positions(session).await?;
+        let working_orders = self.get_working_orders(session).await?;
+        let activities: Vec<Activity> = self
+            .stream_activity(session, &ActivityQuery::new(from).detailed())
+            .try_collect()
+            .await?;
+        Ok(Reconciliation::new(
+            deal_references,
+            &positions,
+            &working_orders,
+            &activities,
+        ))
+    }
 }
@@ -1,9 +1,15 @@
 use crate::application::models::market::{
-    HistoricalPricesResponse, MarketDetails, MarketNavigationResponse, MarketSearchResult,
+    DealabilityCheck, EpicMatch, ExpiryCalendar, HistoricalPrice, HistoricalPricesQuery,
+    HistoricalPricesResponse, MarketData, MarketDetails, MarketNavigationResponse,
+    MarketSearchFilter, MarketSearchResult, OptionChain, Resolution,
 };
+use crate::application::models::order::Direction;
 use crate::error::AppError;
 use crate::session::interface::IgSession;
 use async_trait::async_trait;
+use futures::stream::BoxStream;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Interface for the market service
 #[async_trait]
@@ -15,6 +21,32 @@ pub trait MarketService: Send + Sync {
         search_term: &str,
     ) -> Result<MarketSearchResult, AppError>;
 
+    /// Searches markets by search term and keeps only those accepted by the filter
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `search_term` - Term to search for
+    /// * `filter` - Instrument type, expiry and tradeability criteria
+    async fn search_markets_filtered(
+        &self,
+        session: &IgSession,
+        search_term: &str,
+        filter: &MarketSearchFilter,
+    ) -> Result<MarketSearchResult, AppError>;
+
+    /// Resolves an instrument name to the best matching EPIC
+    ///
+    /// Searches markets with the given name and ranks the results by fuzzy
+    /// similarity of their instrument names.
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `name` - Human readable instrument name, e.g. "FTSE 100 daily"
+    ///
+    /// # Returns
+    /// The best match with its confidence score, or `AppError::NotFound` if the search is empty
+    async fn resolve_epic(&self, session: &IgSession, name: &str) -> Result<EpicMatch, AppError>;
+
     /// Gets details of a specific market by its EPIC
     async fn get_market_details(
         &self,
@@ -22,10 +54,30 @@ pub trait MarketService: Send + Sync {
         epic: &str,
     ) -> Result<MarketDetails, AppError>;
 
+    /// Fetches the market details and checks whether a deal can be placed
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `epic` - The EPIC of the market
+    /// * `size` - Proposed deal size
+    /// * `direction` - Proposed deal direction
+    ///
+    /// # Returns
+    /// A verdict listing every reason preventing the deal
+    async fn check_dealable(
+        &self,
+        session: &IgSession,
+        epic: &str,
+        size: f64,
+        direction: Direction,
+    ) -> Result<DealabilityCheck, AppError>;
+
     /// Gets details of multiple markets by their EPICs in a single request
     ///
     /// This method accepts a vector of EPICs and returns a vector of market details.
-    /// The EPICs are sent as a comma-separated list in a single API request.
+    /// The EPICs are sent as a comma-separated list in a single API request, the only
+    /// batch of [`MarketService::get_markets`]; more than `MAX_EPICS_PER_REQUEST` EPICs
+    /// are refused with `AppError::InvalidInput`.
     ///
     /// # Arguments
     /// * `session` - The active IG session
@@ -39,16 +91,155 @@ pub trait MarketService: Send + Sync {
         epics: &[String],
     ) -> Result<Vec<MarketDetails>, AppError>;
 
+    /// Gets details of any number of markets, chunking the request as needed
+    ///
+    /// Uses `GET /markets?epics=...&filter=ALL`, splitting the EPICs into batches of
+    /// at most `MAX_EPICS_PER_REQUEST` and concatenating the results.
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `epics` - A slice of EPICs to get details for
+    ///
+    /// # Returns
+    /// A vector of market details in the same order as the input EPICs, without the
+    /// markets the API did not return
+    async fn get_markets(
+        &self,
+        session: &IgSession,
+        epics: &[String],
+    ) -> Result<Vec<MarketDetails>, AppError>;
+
+    /// Gets details of many markets, keyed by EPIC
+    ///
+    /// The EPICs are split into batches of `MAX_EPICS_PER_REQUEST` that are requested
+    /// concurrently (at most `MAX_CONCURRENT_MARKET_REQUESTS` at a time) through the
+    /// rate-limited HTTP client. A failed batch does not fail the whole call: each EPIC
+    /// maps to its own result, the error of its batch, or `AppError::NotFound` if the
+    /// API did not return it.
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `epics` - EPICs of the markets; duplicates are requested once
+    async fn get_market_details_bulk(
+        &self,
+        session: &IgSession,
+        epics: &[String],
+    ) -> HashMap<String, Result<MarketDetails, Arc<AppError>>>;
+
+    /// Gets the expiry calendar of an option or future underlying
+    ///
+    /// Searches the markets of the underlying and keeps the dated ones whose name
+    /// starts with `underlying`.
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `underlying` - Name prefix of the markets, e.g. "Germany 40"
+    ///
+    /// # Returns
+    /// The available expiries sorted by date
+    async fn get_expiry_calendar(
+        &self,
+        session: &IgSession,
+        underlying: &str,
+    ) -> Result<ExpiryCalendar, AppError>;
+
+    /// Gets the option chain of an underlying for a given expiry
+    ///
+    /// Searches the option markets of the underlying, fetches the details of every
+    /// strike in batches and groups calls and puts by strike.
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `underlying` - Name prefix of the options, e.g. "Daily Germany 40"
+    /// * `expiry` - Expiry of the options as reported by IG, e.g. "22-MAY-25"
+    ///
+    /// # Returns
+    /// The option chain ordered by strike
+    async fn get_option_chain(
+        &self,
+        session: &IgSession,
+        underlying: &str,
+        expiry: &str,
+    ) -> Result<OptionChain, AppError>;
+
+    /// Resolves the underlying market of an option EPIC
+    ///
+    /// Parses the EPIC, reads the option's instrument name to get the asset name and
+    /// searches for the non-option market of that asset, preferring EPICs sharing the
+    /// option's underlying code and undated markets.
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `option_epic` - EPIC of the option, e.g. "OP.D.OTCDAX1.021100P.IP"
+    ///
+    /// # Returns
+    /// The underlying market, `AppError::InvalidInput` if the EPIC is not an option or
+    /// `AppError::NotFound` if no underlying market is found
+    async fn resolve_underlying(
+        &self,
+        session: &IgSession,
+        option_epic: &str,
+    ) -> Result<MarketData, AppError>;
+
     /// Gets historical prices for a market
     async fn get_historical_prices(
         &self,
         session: &IgSession,
         epic: &str,
-        resolution: &str,
+        resolution: Resolution,
         from: &str,
         to: &str,
     ) -> Result<HistoricalPricesResponse, AppError>;
 
+    /// Gets historical prices for a market using the full v3 query
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `epic` - The EPIC of the market
+    /// * `query` - Resolution, date range, max points and paging parameters
+    ///
+    /// # Returns
+    /// The requested page of prices together with allowance and paging metadata
+    async fn get_historical_prices_query(
+        &self,
+        session: &IgSession,
+        epic: &str,
+        query: &HistoricalPricesQuery,
+    ) -> Result<HistoricalPricesResponse, AppError>;
+
+    /// Gets all pages of historical prices for a market
+    ///
+    /// Starts at the page in `query` (or the first page) and keeps requesting
+    /// until the last page announced by the first response, or an empty page, merging
+    /// the prices into a single response.
+    async fn get_all_historical_prices(
+        &self,
+        session: &IgSession,
+        epic: &str,
+        query: &HistoricalPricesQuery,
+    ) -> Result<HistoricalPricesResponse, AppError>;
+
+    /// Streams historical prices for a market, one price point at a time
+    ///
+    /// Pages are requested lazily as the stream is consumed, so long histories can be
+    /// processed without holding every page in memory. The stream ends after the last
+    /// page, an empty page or the first error.
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `epic` - The EPIC of the market
+    /// * `resolution` - Resolution of the prices
+    /// * `from` - Start date in `yyyy-MM-ddTHH:mm:ss` format
+    /// * `to` - End date in `yyyy-MM-ddTHH:mm:ss` format
+    fn stream_prices<'a>(
+        &'a self,
+        session: &'a IgSession,
+        epic: &'a str,
+        resolution: Resolution,
+        from: &str,
+        to: &str,
+    ) -> BoxStream<'a, Result<HistoricalPrice, AppError>>;
+
     /// Gets the top-level market navigation nodes
     ///
     /// This method returns the root nodes of the market hierarchy, which can be used
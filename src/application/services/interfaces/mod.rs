@@ -1,3 +1,4 @@
 pub(crate) mod account;
 pub(crate) mod market;
 pub(crate) mod order;
+pub(crate) mod watchlist;
@@ -1,14 +1,20 @@
-use crate::application::models::account::WorkingOrders;
+use crate::application::models::account::{Position, WorkingOrder, WorkingOrders};
 use crate::application::models::order::{
-    ClosePositionRequest, ClosePositionResponse, CreateOrderRequest, CreateOrderResponse,
-    OrderConfirmation, UpdatePositionRequest, UpdatePositionResponse,
+    BulkActionSummary, BulkOrderOutcome, BulkOrderReport, BulkOrderResult, ClosePositionRequest,
+    ClosePositionResponse, CreateOrderRequest, CreateOrderResponse, DealFilter, OrderConfirmation,
+    PartialCloseResult, PartialCloseSize, ReversalResult, UpdatePositionRequest,
+    UpdatePositionResponse,
 };
 use crate::application::models::working_order::{
-    CreateWorkingOrderRequest, CreateWorkingOrderResponse,
+    CreateWorkingOrderRequest, CreateWorkingOrderResponse, DeleteWorkingOrderResponse,
+    UpdateWorkingOrderRequest, UpdateWorkingOrderResponse,
 };
+use crate::constants::ORDER_CONFIRMATION_TIMEOUT_MS;
 use crate::error::AppError;
 use crate::session::interface::IgSession;
 use async_trait::async_trait;
+use std::time::Duration;
+use tracing::{debug, info, warn};
 
 #[async_trait]
 /// Service for creating, updating, and managing trading orders with the IG Markets API
@@ -24,6 +30,63 @@ pub trait OrderService: Send + Sync {
         order: &CreateOrderRequest,
     ) -> Result<CreateOrderResponse, AppError>;
 
+    /// Submits several orders one after the other and confirms each of them
+    ///
+    /// Orders are sent sequentially through [`OrderService::create_order`], so legs of
+    /// a strategy are placed in the given order and every leg goes through the same
+    /// checks as a single order. Each one is confirmed within
+    /// [`OrderService::confirmation_timeout`]. A failing order does not stop the
+    /// following ones; its error is recorded in the report instead.
+    ///
+    /// # Returns
+    /// One result per order: accepted, rejected (with the confirmation) or errored
+    async fn create_orders(
+        &self,
+        session: &IgSession,
+        orders: &[CreateOrderRequest],
+    ) -> Result<BulkOrderReport, AppError> {
+        info!("Submitting {} orders", orders.len());
+        let timeout = self.confirmation_timeout();
+        let mut report = BulkOrderReport::default();
+        for (index, order) in orders.iter().enumerate() {
+            let mut deal_reference = None;
+            let outcome = match self.create_order(session, order).await {
+                Ok(response) => {
+                    deal_reference = Some(response.deal_reference.clone());
+                    match self
+                        .await_confirmation(session, &response.deal_reference, timeout)
+                        .await
+                    {
+                        Ok(confirmation) if confirmation.is_accepted() => {
+                            BulkOrderOutcome::Accepted(confirmation)
+                        }
+                        Ok(confirmation) => BulkOrderOutcome::Rejected(confirmation),
+                        Err(e) => BulkOrderOutcome::Errored(e.to_string()),
+                    }
+                }
+                Err(e) => BulkOrderOutcome::Errored(e.to_string()),
+            };
+            report.results.push(BulkOrderResult {
+                index,
+                epic: order.epic.clone(),
+                deal_reference,
+                outcome,
+            });
+        }
+        debug!(
+            "Bulk submission done: {} accepted, {} rejected, {} errored",
+            report.accepted().count(),
+            report.rejected().count(),
+            report.errored().count()
+        );
+        Ok(report)
+    }
+
+    /// Time the helpers of the service wait for the confirmation of a deal
+    fn confirmation_timeout(&self) -> Duration {
+        Duration::from_millis(ORDER_CONFIRMATION_TIMEOUT_MS)
+    }
+
     /// Gets the confirmation of an order
     async fn get_order_confirmation(
         &self,
@@ -31,6 +94,22 @@ pub trait OrderService: Send + Sync {
         deal_reference: &str,
     ) -> Result<OrderConfirmation, AppError>;
 
+    /// Waits for the confirmation of a deal
+    ///
+    /// A confirmation can answer 404 for a moment after the deal is submitted, so
+    /// `/confirms/{dealReference}` is polled with a bounded exponential backoff until
+    /// it is available. Clients connected to the trade stream can read the same
+    /// confirmation with [`TradeFields::confirmation`](crate::presentation::trade::TradeFields::confirmation).
+    ///
+    /// # Returns
+    /// The confirmation, or `AppError::Timeout` if it is still missing after `timeout`
+    async fn await_confirmation(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+        timeout: Duration,
+    ) -> Result<OrderConfirmation, AppError>;
+
     /// Updates an existing position
     async fn update_position(
         &self,
@@ -39,20 +118,195 @@ pub trait OrderService: Send + Sync {
         update: &UpdatePositionRequest,
     ) -> Result<UpdatePositionResponse, AppError>;
 
+    /// Sets the stop loss and take profit of an open position and confirms the change
+    ///
+    /// Deals placed without `forceOpen`, which IG requires to attach a stop or a limit
+    /// to the order, get them here once filled. The position is read first: a level
+    /// left as `None` keeps its current value (see
+    /// [`Position::attached_orders_update`]), and a level on the wrong side of the price
+    /// is refused with `AppError::InvalidInput` before anything is sent.
+    ///
+    /// # Returns
+    /// The confirmation of the amendment, read from `/confirms`
+    async fn set_attached_orders(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        stop_level: Option<f64>,
+        limit_level: Option<f64>,
+    ) -> Result<OrderConfirmation, AppError>;
+
     /// Closes an existing position
+    ///
+    /// Sends the documented `DELETE /positions/otc` (as a `POST` with the `_method: DELETE`
+    /// header), so the request can never be processed as a new opening. Requests with
+    /// `force_open` set, or with neither a deal ID nor an EPIC, are rejected with
+    /// `AppError::InvalidInput`.
     async fn close_position(
         &self,
         session: &IgSession,
         close_request: &ClosePositionRequest,
     ) -> Result<ClosePositionResponse, AppError>;
 
+    /// Closes part of an open position at market
+    ///
+    /// The size is checked against the minimum deal size of the market (see
+    /// [`PartialCloseSize::resolve`]) before the close is sent, then the close is
+    /// confirmed.
+    ///
+    /// # Returns
+    /// The confirmation of the close with the closed and remaining sizes
+    async fn partial_close(
+        &self,
+        session: &IgSession,
+        position: &Position,
+        amount: PartialCloseSize,
+    ) -> Result<PartialCloseResult, AppError>;
+
+    /// Returns the orders turning the position `deal_id` around at market
+    ///
+    /// The close of the whole position and the market order of the same size in the
+    /// opposite direction, as sent by [`OrderService::reverse_position`]. Stops and
+    /// limits of the original position are not carried over.
+    async fn reversal_orders(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<(ClosePositionRequest, CreateOrderRequest), AppError>;
+
+    /// Turns the position `deal_id` around at market
+    ///
+    /// The orders of [`OrderService::reversal_orders`] go through
+    /// [`OrderService::close_position`] and [`OrderService::create_order`], so both legs
+    /// get the same checks and records as any close and order. The opposite order is
+    /// only sent once the close is confirmed as accepted.
+    ///
+    /// # Returns
+    /// Both confirmations; a close that is not accepted is returned without opening
+    async fn reverse_position(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<ReversalResult, AppError> {
+        let (close, order) = self.reversal_orders(session, deal_id).await?;
+        send_reversal(self, session, &close, &order).await
+    }
+
+    /// Closes every open position selected by `filter` at market
+    ///
+    /// Positions are closed one after the other under the trading account rate limiter.
+    /// A failing close does not stop the following ones. Closes are not confirmed, so
+    /// check the deal references of the summary when the outcome matters.
+    async fn close_all_positions(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError>;
+
+    /// Deletes every working order selected by `filter`
+    ///
+    /// Orders are deleted one after the other under the trading account rate limiter.
+    /// A failing deletion does not stop the following ones.
+    async fn cancel_all_working_orders(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError>;
+
     /// Gets all working orders
     async fn get_working_orders(&self, session: &IgSession) -> Result<WorkingOrders, AppError>;
 
+    /// Finds a working order by its deal ID
+    ///
+    /// # Returns
+    /// The working order, or `None` if no pending order has this deal ID
+    async fn find_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<Option<WorkingOrder>, AppError>;
+
     /// Creates a new working order
     async fn create_working_order(
         &self,
         session: &IgSession,
         order: &CreateWorkingOrderRequest,
     ) -> Result<CreateWorkingOrderResponse, AppError>;
+
+    /// Amends a working order without deleting and recreating it
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `deal_id` - Deal ID of the working order
+    /// * `update` - New level, stop/limit, type and time in force
+    ///
+    /// # Returns
+    /// The deal reference of the amendment, to be confirmed with
+    /// [`OrderService::get_order_confirmation`]
+    async fn update_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        update: &UpdateWorkingOrderRequest,
+    ) -> Result<UpdateWorkingOrderResponse, AppError>;
+
+    /// Deletes (cancels) a working order
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `deal_id` - Deal ID of the working order
+    ///
+    /// # Returns
+    /// The deal reference of the deletion, to be confirmed with
+    /// [`OrderService::get_order_confirmation`]
+    async fn delete_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<DeleteWorkingOrderResponse, AppError>;
+}
+
+/// Sends the close then, once it is accepted, the opposite order of a reversal
+///
+/// A failure of the opposite order is returned in [`ReversalResult::open`], since the
+/// position is closed by then.
+pub(crate) async fn send_reversal<S: OrderService + ?Sized>(
+    service: &S,
+    session: &IgSession,
+    close: &ClosePositionRequest,
+    order: &CreateOrderRequest,
+) -> Result<ReversalResult, AppError> {
+    let deal_id = close.deal_id.as_deref().unwrap_or_default();
+    info!(
+        "Reversing position {} into a {:?} of {} on {}",
+        deal_id, order.direction, order.size, order.epic
+    );
+    let timeout = service.confirmation_timeout();
+    let response = service.close_position(session, close).await?;
+    let close = service
+        .await_confirmation(session, &response.deal_reference, timeout)
+        .await?;
+    if !close.is_accepted() {
+        warn!("Close of {} was not accepted, not reversing", deal_id);
+        return Ok(ReversalResult { close, open: None });
+    }
+
+    let open = match service.create_order(session, order).await {
+        Ok(response) => {
+            service
+                .await_confirmation(session, &response.deal_reference, timeout)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = &open {
+        warn!(
+            "Closed {} but failed to open the opposite position: {}",
+            deal_id, e
+        );
+    }
+    Ok(ReversalResult {
+        close,
+        open: Some(open),
+    })
 }
@@ -0,0 +1,59 @@
+use crate::application::models::watchlist::{
+    CreateWatchlistResponse, WatchlistMarkets, WatchlistStatusResponse, Watchlists,
+};
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use async_trait::async_trait;
+
+/// Interface for the watchlist service
+#[async_trait]
+pub trait WatchlistService: Send + Sync {
+    /// Gets all watchlists of the active account
+    async fn get_watchlists(&self, session: &IgSession) -> Result<Watchlists, AppError>;
+
+    /// Creates a new watchlist
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `name` - Name of the new watchlist
+    /// * `epics` - EPICs to add to the watchlist
+    ///
+    /// # Returns
+    /// * The ID of the new watchlist and whether all EPICs were added
+    async fn create_watchlist(
+        &self,
+        session: &IgSession,
+        name: &str,
+        epics: &[String],
+    ) -> Result<CreateWatchlistResponse, AppError>;
+
+    /// Gets the markets of a watchlist
+    async fn get_watchlist(
+        &self,
+        session: &IgSession,
+        watchlist_id: &str,
+    ) -> Result<WatchlistMarkets, AppError>;
+
+    /// Deletes a watchlist
+    async fn delete_watchlist(
+        &self,
+        session: &IgSession,
+        watchlist_id: &str,
+    ) -> Result<WatchlistStatusResponse, AppError>;
+
+    /// Adds a market to a watchlist
+    async fn add_to_watchlist(
+        &self,
+        session: &IgSession,
+        watchlist_id: &str,
+        epic: &str,
+    ) -> Result<WatchlistStatusResponse, AppError>;
+
+    /// Removes a market from a watchlist
+    async fn remove_from_watchlist(
+        &self,
+        session: &IgSession,
+        watchlist_id: &str,
+        epic: &str,
+    ) -> Result<WatchlistStatusResponse, AppError>;
+}
@@ -0,0 +1,570 @@
+use crate::application::models::account::{Position, WorkingOrder, WorkingOrders};
+use crate::application::models::order::{
+    BulkActionSummary, ClosePositionRequest, ClosePositionResponse, CreateOrderRequest,
+    CreateOrderResponse, DealFilter, OrderConfirmation, PartialCloseResult, PartialCloseSize,
+    UpdatePositionRequest, UpdatePositionResponse,
+};
+use crate::application::models::working_order::{
+    CreateWorkingOrderRequest, CreateWorkingOrderResponse, DeleteWorkingOrderResponse,
+    UpdateWorkingOrderRequest, UpdateWorkingOrderResponse,
+};
+use crate::application::services::OrderService;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::storage::order_journal::{JournalEntry, JournalEntryKind, JournalQuery, OrderJournal};
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+/// Order service wrapper recording every request, response, confirmation and amendment
+///
+/// Each operation gets a correlation ID shared by all of its entries. Confirmations
+/// are attached to the correlation ID of the order that returned their deal reference,
+/// looked up in the journal if the order was sent by a previous run.
+///
+/// Recording never fails an operation: if the journal cannot be written, a warning is
+/// logged and the result of the inner service is returned unchanged.
+pub struct JournaledOrderService<S: OrderService, J: OrderJournal> {
+    order_service: S,
+    journal: J,
+    correlations: Mutex<HashMap<String, String>>,
+}
+
+impl<S: OrderService, J: OrderJournal> JournaledOrderService<S, J> {
+    /// Wraps `order_service`, recording to `journal`
+    pub fn new(order_service: S, journal: J) -> Self {
+        Self {
+            order_service,
+            journal,
+            correlations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the journal
+    pub fn journal(&self) -> &J {
+        &self.journal
+    }
+
+    /// Returns the wrapped order service
+    pub fn inner(&self) -> &S {
+        &self.order_service
+    }
+
+    fn new_correlation_id() -> String {
+        nanoid::nanoid!(16)
+    }
+
+    /// Correlation ID of the operation that returned `deal_reference`
+    fn correlation_of(&self, deal_reference: &str) -> String {
+        if let Some(id) = self.correlations.lock().unwrap().get(deal_reference) {
+            return id.clone();
+        }
+        let query = JournalQuery::all()
+            .with_deal_reference(deal_reference)
+            .with_kind(JournalEntryKind::Response);
+        let id = match self.journal.query(&query) {
+            Ok(entries) => entries.into_iter().next().map(|entry| entry.correlation_id),
+            Err(e) => {
+                warn!("Failed to query the order journal: {}", e);
+                None
+            }
+        }
+        .unwrap_or_else(Self::new_correlation_id);
+        self.correlations
+            .lock()
+            .unwrap()
+            .insert(deal_reference.to_string(), id.clone());
+        id
+    }
+
+    fn record(&self, entry: JournalEntry) {
+        if let Err(e) = self.journal.append(&entry) {
+            warn!(
+                "Failed to journal {:?} of {} ({}): {}",
+                entry.kind, entry.operation, entry.correlation_id, e
+            );
+        }
+    }
+
+    fn record_request(
+        &self,
+        correlation_id: &str,
+        kind: JournalEntryKind,
+        operation: &str,
+        epic: Option<&str>,
+        deal_id: Option<&str>,
+        payload: Value,
+    ) {
+        self.record(
+            JournalEntry::new(correlation_id, kind, operation, payload)
+                .with_epic(epic)
+                .with_deal_id(deal_id),
+        );
+    }
+
+    /// Records the response or the error of an operation
+    ///
+    /// The deal reference of a successful response is linked to `correlation_id`, so
+    /// its confirmation joins the same trail.
+    fn record_result<R: Serialize>(
+        &self,
+        correlation_id: &str,
+        operation: &str,
+        epic: Option<&str>,
+        deal_id: Option<&str>,
+        result: &Result<R, AppError>,
+        deal_reference: impl Fn(&R) -> Option<&str>,
+    ) {
+        let entry = match result {
+            Ok(response) => {
+                let reference = deal_reference(response);
+                if let Some(reference) = reference {
+                    self.correlations
+                        .lock()
+                        .unwrap()
+                        .insert(reference.to_string(), correlation_id.to_string());
+                }
+                JournalEntry::new(
+                    correlation_id,
+                    JournalEntryKind::Response,
+                    operation,
+                    to_payload(response),
+                )
+                .with_deal_reference(reference)
+            }
+            Err(e) => JournalEntry::new(
+                correlation_id,
+                JournalEntryKind::Error,
+                operation,
+                json!({ "error": e.to_string() }),
+            ),
+        };
+        self.record(entry.with_epic(epic).with_deal_id(deal_id));
+    }
+
+    fn record_confirmation(&self, operation: &str, confirmation: &OrderConfirmation) {
+        let correlation_id = self.correlation_of(&confirmation.deal_reference);
+        self.record(
+            JournalEntry::new(
+                &correlation_id,
+                JournalEntryKind::Confirmation,
+                operation,
+                to_payload(confirmation),
+            )
+            .with_epic(confirmation.epic.as_deref())
+            .with_deal_reference(Some(&confirmation.deal_reference))
+            .with_deal_id(confirmation.deal_id.as_deref()),
+        );
+    }
+
+    /// Records the request of a close-all or cancel-all and one response per deal
+    fn record_bulk_action(
+        &self,
+        operation: &str,
+        filter: &DealFilter,
+        result: &Result<BulkActionSummary, AppError>,
+    ) {
+        let correlation_id = Self::new_correlation_id();
+        self.record_request(
+            &correlation_id,
+            JournalEntryKind::Request,
+            operation,
+            filter.epic.as_deref(),
+            None,
+            json!({
+                "epic": filter.epic,
+                "direction": filter.direction,
+                "underlying": filter.underlying,
+            }),
+        );
+        match result {
+            Ok(summary) => {
+                for action in &summary.results {
+                    let payload = json!({
+                        "dealId": action.deal_id,
+                        "epic": action.epic,
+                        "dealReference": action.deal_reference,
+                        "error": action.error,
+                    });
+                    let kind = if action.error.is_some() {
+                        JournalEntryKind::Error
+                    } else {
+                        JournalEntryKind::Response
+                    };
+                    if let Some(reference) = &action.deal_reference {
+                        self.correlations
+                            .lock()
+                            .unwrap()
+                            .insert(reference.clone(), correlation_id.clone());
+                    }
+                    self.record(
+                        JournalEntry::new(&correlation_id, kind, operation, payload)
+                            .with_epic(Some(&action.epic))
+                            .with_deal_reference(action.deal_reference.as_deref())
+                            .with_deal_id(Some(&action.deal_id)),
+                    );
+                }
+            }
+            Err(e) => self.record(JournalEntry::new(
+                &correlation_id,
+                JournalEntryKind::Error,
+                operation,
+                json!({ "error": e.to_string() }),
+            )),
+        }
+    }
+}
+
+fn to_payload<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or_else(|e| json!({ "serializationError": e.to_string() }))
+}
+
+#[async_trait]
+impl<S: OrderService, J: OrderJournal> OrderService for JournaledOrderService<S, J> {
+    async fn create_order(
+        &self,
+        session: &IgSession,
+        order: &CreateOrderRequest,
+    ) -> Result<CreateOrderResponse, AppError> {
+        let correlation_id = Self::new_correlation_id();
+        let epic = Some(order.epic.as_str());
+        self.record_request(
+            &correlation_id,
+            JournalEntryKind::Request,
+            "create_order",
+            epic,
+            None,
+            to_payload(order),
+        );
+        let result = self.order_service.create_order(session, order).await;
+        self.record_result(&correlation_id, "create_order", epic, None, &result, |r| {
+            Some(&r.deal_reference)
+        });
+        result
+    }
+
+    fn confirmation_timeout(&self) -> Duration {
+        self.order_service.confirmation_timeout()
+    }
+
+    async fn get_order_confirmation(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+    ) -> Result<OrderConfirmation, AppError> {
+        let result = self
+            .order_service
+            .get_order_confirmation(session, deal_reference)
+            .await;
+        if let Ok(confirmation) = &result {
+            self.record_confirmation("get_order_confirmation", confirmation);
+        }
+        result
+    }
+
+    async fn await_confirmation(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+        timeout: Duration,
+    ) -> Result<OrderConfirmation, AppError> {
+        let result = self
+            .order_service
+            .await_confirmation(session, deal_reference, timeout)
+            .await;
+        if let Ok(confirmation) = &result {
+            self.record_confirmation("await_confirmation", confirmation);
+        }
+        result
+    }
+
+    async fn update_position(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        update: &UpdatePositionRequest,
+    ) -> Result<UpdatePositionResponse, AppError> {
+        let correlation_id = Self::new_correlation_id();
+        self.record_request(
+            &correlation_id,
+            JournalEntryKind::Amendment,
+            "update_position",
+            None,
+            Some(deal_id),
+            to_payload(update),
+        );
+        let result = self
+            .order_service
+            .update_position(session, deal_id, update)
+            .await;
+        self.record_result(
+            &correlation_id,
+            "update_position",
+            None,
+            Some(deal_id),
+            &result,
+            |r| Some(&r.deal_reference),
+        );
+        result
+    }
+
+    async fn set_attached_orders(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        stop_level: Option<f64>,
+        limit_level: Option<f64>,
+    ) -> Result<OrderConfirmation, AppError> {
+        let correlation_id = Self::new_correlation_id();
+        self.record_request(
+            &correlation_id,
+            JournalEntryKind::Amendment,
+            "set_attached_orders",
+            None,
+            Some(deal_id),
+            json!({ "stopLevel": stop_level, "limitLevel": limit_level }),
+        );
+        let result = self
+            .order_service
+            .set_attached_orders(session, deal_id, stop_level, limit_level)
+            .await;
+        match &result {
+            Ok(confirmation) => {
+                self.correlations
+                    .lock()
+                    .unwrap()
+                    .insert(confirmation.deal_reference.clone(), correlation_id.clone());
+                self.record_confirmation("set_attached_orders", confirmation);
+            }
+            Err(e) => self.record(
+                JournalEntry::new(
+                    &correlation_id,
+                    JournalEntryKind::Error,
+                    "set_attached_orders",
+                    json!({ "error": e.to_string() }),
+                )
+                .with_deal_id(Some(deal_id)),
+            ),
+        }
+        result
+    }
+
+    async fn close_position(
+        &self,
+        session: &IgSession,
+        close_request: &ClosePositionRequest,
+    ) -> Result<ClosePositionResponse, AppError> {
+        let correlation_id = Self::new_correlation_id();
+        let epic = Some(close_request.epic.as_str());
+        let deal_id = close_request.deal_id.as_deref();
+        self.record_request(
+            &correlation_id,
+            JournalEntryKind::Request,
+            "close_position",
+            epic,
+            deal_id,
+            to_payload(close_request),
+        );
+        let result = self
+            .order_service
+            .close_position(session, close_request)
+            .await;
+        self.record_result(
+            &correlation_id,
+            "close_position",
+            epic,
+            deal_id,
+            &result,
+            |r| Some(&r.deal_reference),
+        );
+        result
+    }
+
+    async fn partial_close(
+        &self,
+        session: &IgSession,
+        position: &Position,
+        amount: PartialCloseSize,
+    ) -> Result<PartialCloseResult, AppError> {
+        let correlation_id = Self::new_correlation_id();
+        let epic = Some(position.market.epic.as_str());
+        let deal_id = Some(position.position.deal_id.as_str());
+        let (fraction, size) = match amount {
+            PartialCloseSize::Fraction(fraction) => (Some(fraction), None),
+            PartialCloseSize::Size(size) => (None, Some(size)),
+        };
+        self.record_request(
+            &correlation_id,
+            JournalEntryKind::Request,
+            "partial_close",
+            epic,
+            deal_id,
+            json!({ "fraction": fraction, "size": size, "positionSize": position.position.size }),
+        );
+        let result = self
+            .order_service
+            .partial_close(session, position, amount)
+            .await;
+        match &result {
+            Ok(close) => {
+                self.correlations.lock().unwrap().insert(
+                    close.confirmation.deal_reference.clone(),
+                    correlation_id.clone(),
+                );
+                self.record_confirmation("partial_close", &close.confirmation);
+            }
+            Err(e) => self.record(
+                JournalEntry::new(
+                    &correlation_id,
+                    JournalEntryKind::Error,
+                    "partial_close",
+                    json!({ "error": e.to_string() }),
+                )
+                .with_epic(epic)
+                .with_deal_id(deal_id),
+            ),
+        }
+        result
+    }
+
+    async fn reversal_orders(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<(ClosePositionRequest, CreateOrderRequest), AppError> {
+        self.order_service.reversal_orders(session, deal_id).await
+    }
+
+    async fn close_all_positions(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError> {
+        let result = self
+            .order_service
+            .close_all_positions(session, filter)
+            .await;
+        self.record_bulk_action("close_all_positions", filter, &result);
+        result
+    }
+
+    async fn cancel_all_working_orders(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError> {
+        let result = self
+            .order_service
+            .cancel_all_working_orders(session, filter)
+            .await;
+        self.record_bulk_action("cancel_all_working_orders", filter, &result);
+        result
+    }
+
+    async fn get_working_orders(&self, session: &IgSession) -> Result<WorkingOrders, AppError> {
+        self.order_service.get_working_orders(session).await
+    }
+
+    async fn find_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<Option<WorkingOrder>, AppError> {
+        self.order_service
+            .find_working_order(session, deal_id)
+            .await
+    }
+
+    async fn create_working_order(
+        &self,
+        session: &IgSession,
+        order: &CreateWorkingOrderRequest,
+    ) -> Result<CreateWorkingOrderResponse, AppError> {
+        let correlation_id = Self::new_correlation_id();
+        let epic = Some(order.epic.as_str());
+        self.record_request(
+            &correlation_id,
+            JournalEntryKind::Request,
+            "create_working_order",
+            epic,
+            None,
+            to_payload(order),
+        );
+        let result = self
+            .order_service
+            .create_working_order(session, order)
+            .await;
+        self.record_result(
+            &correlation_id,
+            "create_working_order",
+            epic,
+            None,
+            &result,
+            |r| Some(&r.deal_reference),
+        );
+        result
+    }
+
+    async fn update_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        update: &UpdateWorkingOrderRequest,
+    ) -> Result<UpdateWorkingOrderResponse, AppError> {
+        let correlation_id = Self::new_correlation_id();
+        self.record_request(
+            &correlation_id,
+            JournalEntryKind::Amendment,
+            "update_working_order",
+            None,
+            Some(deal_id),
+            to_payload(update),
+        );
+        let result = self
+            .order_service
+            .update_working_order(session, deal_id, update)
+            .await;
+        self.record_result(
+            &correlation_id,
+            "update_working_order",
+            None,
+            Some(deal_id),
+            &result,
+            |r| Some(&r.deal_reference),
+        );
+        result
+    }
+
+    async fn delete_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<DeleteWorkingOrderResponse, AppError> {
+        let correlation_id = Self::new_correlation_id();
+        self.record_request(
+            &correlation_id,
+            JournalEntryKind::Request,
+            "delete_working_order",
+            None,
+            Some(deal_id),
+            json!({ "dealId": deal_id }),
+        );
+        let result = self
+            .order_service
+            .delete_working_order(session, deal_id)
+            .await;
+        self.record_result(
+            &correlation_id,
+            "delete_working_order",
+            None,
+            Some(deal_id),
+            &result,
+            |r| Some(&r.deal_reference),
+        );
+        result
+    }
+}
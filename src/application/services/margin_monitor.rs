@@ -0,0 +1,298 @@
+use crate::application::models::account::Position;
+use crate::application::services::AccountService;
+use crate::application::services::positions_cache::PositionsCache;
+use crate::storage::balance_history::BalanceSnapshot;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
+
+/// Number of alerts kept for slow subscribers before they start missing some
+const ALERT_CAPACITY: usize = 64;
+
+/// Callback run by [`MarginMonitor`] when a threshold is breached
+pub type AlertCallback = Box<dyn Fn(&MarginAlert) + Send + Sync>;
+
+/// Thresholds watched by [`MarginMonitor`]
+///
+/// Every threshold is optional; an unset threshold is not checked.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarginThresholds {
+    /// Maximum margin usage, as a percentage of the equity
+    pub max_margin_usage: Option<f64>,
+    /// Minimum funds available for trading
+    pub min_available: Option<f64>,
+    /// Maximum loss of a single open position, as a positive amount
+    pub max_position_loss: Option<f64>,
+}
+
+impl MarginThresholds {
+    /// Creates thresholds with no threshold set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Alerts when the margin exceeds `percent` of the equity
+    pub fn with_max_margin_usage(mut self, percent: f64) -> Self {
+        self.max_margin_usage = Some(percent);
+        self
+    }
+
+    /// Alerts when the available funds fall below `amount`
+    pub fn with_min_available(mut self, amount: f64) -> Self {
+        self.min_available = Some(amount);
+        self
+    }
+
+    /// Alerts when a single position loses more than `amount`
+    pub fn with_max_position_loss(mut self, amount: f64) -> Self {
+        self.max_position_loss = Some(amount);
+        self
+    }
+}
+
+/// Threshold breached, as reported by [`MarginMonitor`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarginAlert {
+    /// The margin uses too large a share of the equity
+    MarginUsage {
+        /// Account of the balance
+        account_id: String,
+        /// Margin as a percentage of the equity
+        usage: f64,
+        /// Configured maximum
+        max: f64,
+    },
+    /// The funds available for trading are below the floor
+    AvailableFunds {
+        /// Account of the balance
+        account_id: String,
+        /// Funds available for trading
+        available: f64,
+        /// Configured minimum
+        min: f64,
+    },
+    /// An open position loses more than allowed
+    PositionLoss {
+        /// Deal ID of the position
+        deal_id: String,
+        /// EPIC of the market
+        epic: String,
+        /// Loss of the position in its currency, as a positive amount
+        loss: f64,
+        /// Configured maximum
+        max: f64,
+    },
+}
+
+impl MarginAlert {
+    /// Identifies the breached threshold, so that an alert fires once per breach
+    fn key(&self) -> String {
+        match self {
+            MarginAlert::MarginUsage { account_id, .. } => format!("usage:{account_id}"),
+            MarginAlert::AvailableFunds { account_id, .. } => format!("available:{account_id}"),
+            MarginAlert::PositionLoss { deal_id, .. } => format!("loss:{deal_id}"),
+        }
+    }
+}
+
+impl Display for MarginAlert {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MarginAlert::MarginUsage {
+                account_id,
+                usage,
+                max,
+            } => write!(
+                f,
+                "margin usage of {account_id} is {usage:.2}%, above the maximum {max}%"
+            ),
+            MarginAlert::AvailableFunds {
+                account_id,
+                available,
+                min,
+            } => write!(
+                f,
+                "available funds of {account_id} are {available}, below the minimum {min}"
+            ),
+            MarginAlert::PositionLoss {
+                deal_id,
+                epic,
+                loss,
+                max,
+            } => write!(
+                f,
+                "position {deal_id} on {epic} loses {loss}, above the maximum {max}"
+            ),
+        }
+    }
+}
+
+/// Margin used as a percentage of the equity of a snapshot
+///
+/// An account holding margin without positive equity is fully used.
+fn margin_usage(snapshot: &BalanceSnapshot) -> f64 {
+    let equity = snapshot.equity();
+    let margin = snapshot.balance.deposit.amount;
+    if equity > 0.0 {
+        margin / equity * 100.0
+    } else if margin > 0.0 {
+        f64::INFINITY
+    } else {
+        0.0
+    }
+}
+
+/// Watches balance snapshots and open positions against [`MarginThresholds`]
+///
+/// Snapshots recorded by
+/// [`BalanceTracker`](crate::application::services::balance_tracker::BalanceTracker)
+/// are passed to [`MarginMonitor::check_balance`], and the positions of a
+/// [`PositionsCache`] to [`MarginMonitor::check_positions`], or followed by a task
+/// running [`MarginMonitor::watch_positions`]. Each breach runs the registered
+/// callbacks and is sent to the subscribers once; it fires again only after the value
+/// came back within the threshold.
+pub struct MarginMonitor {
+    thresholds: MarginThresholds,
+    callbacks: Vec<AlertCallback>,
+    breached: Mutex<HashSet<String>>,
+    alerts: broadcast::Sender<MarginAlert>,
+}
+
+impl MarginMonitor {
+    /// Creates a monitor watching `thresholds`
+    pub fn new(thresholds: MarginThresholds) -> Self {
+        let (alerts, _) = broadcast::channel(ALERT_CAPACITY);
+        Self {
+            thresholds,
+            callbacks: Vec::new(),
+            breached: Mutex::new(HashSet::new()),
+            alerts,
+        }
+    }
+
+    /// Runs `callback` on every alert
+    pub fn on_alert<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&MarginAlert) + Send + Sync + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Returns the watched thresholds
+    pub fn thresholds(&self) -> &MarginThresholds {
+        &self.thresholds
+    }
+
+    /// Subscribes to the following alerts
+    pub fn subscribe(&self) -> broadcast::Receiver<MarginAlert> {
+        self.alerts.subscribe()
+    }
+
+    /// Checks the margin usage and the available funds of a balance snapshot
+    ///
+    /// # Returns
+    /// The alerts fired by this check, leaving out thresholds already breached
+    pub fn check_balance(&self, snapshot: &BalanceSnapshot) -> Vec<MarginAlert> {
+        let mut alerts = Vec::new();
+        if let Some(max) = self.thresholds.max_margin_usage {
+            let usage = margin_usage(snapshot);
+            let alert = MarginAlert::MarginUsage {
+                account_id: snapshot.account_id.clone(),
+                usage,
+                max,
+            };
+            self.update(alert, usage > max, &mut alerts);
+        }
+        if let Some(min) = self.thresholds.min_available {
+            let available = snapshot.balance.available.amount;
+            let alert = MarginAlert::AvailableFunds {
+                account_id: snapshot.account_id.clone(),
+                available,
+                min,
+            };
+            self.update(alert, available < min, &mut alerts);
+        }
+        alerts
+    }
+
+    /// Checks the loss of each open position at its last known quote
+    ///
+    /// Positions missing from `positions` are considered closed, so they fire again
+    /// if reopened with the same deal ID.
+    ///
+    /// # Returns
+    /// The alerts fired by this check, leaving out positions already breaching
+    pub fn check_positions(&self, positions: &[Position]) -> Vec<MarginAlert> {
+        let mut alerts = Vec::new();
+        let Some(max) = self.thresholds.max_position_loss else {
+            return alerts;
+        };
+        let open: HashSet<String> = positions
+            .iter()
+            .map(|position| format!("loss:{}", position.position.deal_id))
+            .collect();
+        self.breached
+            .lock()
+            .unwrap()
+            .retain(|key| !key.starts_with("loss:") || open.contains(key));
+        for position in positions {
+            let loss = -position.unrealized_pnl(position.market.bid, position.market.offer);
+            let alert = MarginAlert::PositionLoss {
+                deal_id: position.position.deal_id.clone(),
+                epic: position.market.epic.clone(),
+                loss,
+                max,
+            };
+            self.update(alert, loss > max, &mut alerts);
+        }
+        alerts
+    }
+
+    /// Checks the positions of `cache` after each of its refreshes reporting a change
+    ///
+    /// Never returns while the cache lives, so run it in its own task and abort the task
+    /// to stop it.
+    pub async fn watch_positions<A: AccountService>(&self, cache: &PositionsCache<A>) {
+        info!("Watching positions for margin alerts");
+        let mut events = cache.subscribe();
+        self.check_positions(&cache.current().positions);
+        loop {
+            match events.recv().await {
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => {
+                    debug!("Margin monitor missed {} position events", missed)
+                }
+                Err(RecvError::Closed) => {
+                    warn!("Positions cache closed, no more margin alerts");
+                    return;
+                }
+            }
+            self.check_positions(&cache.current().positions);
+        }
+    }
+
+    /// Fires `alert` if newly breached, or clears it once back within the threshold
+    fn update(&self, alert: MarginAlert, breached: bool, fired: &mut Vec<MarginAlert>) {
+        let key = alert.key();
+        let mut active = self.breached.lock().unwrap();
+        if !breached {
+            active.remove(&key);
+            return;
+        }
+        if !active.insert(key) {
+            return;
+        }
+        drop(active);
+        warn!("Margin alert: {}", alert);
+        for callback in &self.callbacks {
+            callback(&alert);
+        }
+        // Sending only fails when nobody is subscribed
+        let _ = self.alerts.send(alert.clone());
+        fired.push(alert);
+    }
+}
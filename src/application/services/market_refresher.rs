@@ -0,0 +1,240 @@
+use crate::application::models::market::MarketData;
+use crate::application::services::MarketService;
+use crate::constants::MAX_EPICS_PER_REQUEST;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::utils::rate_limiter::{RateLimiter, wait_if_set};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+/// Default interval between two refreshes
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Change detected between two refreshes of a market
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketChange {
+    /// The mid price moved by more than the threshold since it was last reported
+    PriceMoved {
+        /// EPIC of the market
+        epic: String,
+        /// Mid price when last reported
+        previous: f64,
+        /// Current mid price
+        current: f64,
+        /// Move in percent of the previous price
+        change_pct: f64,
+    },
+    /// The market status changed
+    StatusChanged {
+        /// EPIC of the market
+        epic: String,
+        /// Previous status
+        previous: String,
+        /// Current status
+        current: String,
+    },
+}
+
+impl MarketChange {
+    /// Returns the EPIC of the changed market
+    pub fn epic(&self) -> &str {
+        match self {
+            MarketChange::PriceMoved { epic, .. } | MarketChange::StatusChanged { epic, .. } => {
+                epic
+            }
+        }
+    }
+}
+
+fn mid_price(market: &MarketData) -> Option<f64> {
+    Some((market.bid? + market.offer?) / 2.0)
+}
+
+/// Polls selected markets on an interval and reports what changed
+///
+/// Meant for users who cannot rely on streaming. The first refresh only records the
+/// baseline; later refreshes compare against it. Price moves are measured against the
+/// last reported price, so slow drifts are reported once they exceed the threshold.
+pub struct MarketRefresher<'a, S: MarketService> {
+    market_service: &'a S,
+    epics: Vec<String>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    interval: Duration,
+    price_threshold_pct: f64,
+    max_age: Option<Duration>,
+    baseline: HashMap<String, MarketData>,
+    latest: HashMap<String, (MarketData, Instant)>,
+}
+
+impl<'a, S: MarketService> MarketRefresher<'a, S> {
+    /// Creates a refresher for the given markets
+    ///
+    /// Any price move is reported until a threshold is set with
+    /// [`MarketRefresher::with_price_threshold`].
+    pub fn new(market_service: &'a S, epics: Vec<String>) -> Self {
+        Self {
+            market_service,
+            epics,
+            rate_limiter: None,
+            interval: DEFAULT_REFRESH_INTERVAL,
+            price_threshold_pct: 0.0,
+            max_age: None,
+            baseline: HashMap::new(),
+            latest: HashMap::new(),
+        }
+    }
+
+    /// Uses a specific rate limiter for refresh requests
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Returns the configured rate limiter, or the account limiter of `session`
+    fn rate_limiter(&self, session: &IgSession) -> Arc<RateLimiter> {
+        self.rate_limiter
+            .clone()
+            .unwrap_or_else(|| session.rate_limiters().account_non_trading.clone())
+    }
+
+    /// Sets the interval between two refreshes in [`MarketRefresher::run`]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Only reports price moves strictly larger than `threshold_pct` percent
+    pub fn with_price_threshold(mut self, threshold_pct: f64) -> Self {
+        self.price_threshold_pct = threshold_pct;
+        self
+    }
+
+    /// Sets the age after which [`MarketRefresher::snapshot`] treats a snapshot as stale
+    ///
+    /// Defaults to twice the refresh interval.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Returns the snapshot of a market fetched by the last successful refresh
+    ///
+    /// # Returns
+    /// * `None` if the market was never fetched, or if its snapshot is older than the
+    ///   max age because the later refreshes failed
+    pub fn snapshot(&self, epic: &str) -> Option<&MarketData> {
+        let max_age = self.max_age.unwrap_or(self.interval * 2);
+        self.latest
+            .get(epic)
+            .filter(|(_, fetched_at)| fetched_at.elapsed() <= max_age)
+            .map(|(market, _)| market)
+    }
+
+    /// Fetches the markets once and returns the changes since the previous refresh
+    pub async fn refresh(&mut self, session: &IgSession) -> Result<Vec<MarketChange>, AppError> {
+        let mut markets = Vec::with_capacity(self.epics.len());
+        for chunk in self.epics.chunks(MAX_EPICS_PER_REQUEST) {
+            wait_if_set(self.rate_limiter.as_deref()).await;
+            let details = self.market_service.get_markets(session, chunk).await?;
+            markets.extend(details.iter().map(MarketData::from));
+        }
+
+        let fetched_at = Instant::now();
+        let mut changes = Vec::new();
+        for market in markets {
+            self.latest
+                .insert(market.epic.clone(), (market.clone(), fetched_at));
+            self.update(market, &mut changes);
+        }
+
+        debug!(
+            "{} changes detected on {} markets",
+            changes.len(),
+            self.epics.len()
+        );
+        Ok(changes)
+    }
+
+    fn update(&mut self, mut market: MarketData, changes: &mut Vec<MarketChange>) {
+        let Some(previous) = self.baseline.get(&market.epic) else {
+            self.baseline.insert(market.epic.clone(), market);
+            return;
+        };
+
+        if previous.market_status != market.market_status {
+            changes.push(MarketChange::StatusChanged {
+                epic: market.epic.clone(),
+                previous: previous.market_status.clone(),
+                current: market.market_status.clone(),
+            });
+        }
+
+        match (mid_price(previous), mid_price(&market)) {
+            (Some(old), Some(new)) if old != 0.0 => {
+                let change_pct = (new - old) / old * 100.0;
+                if change_pct.abs() > self.price_threshold_pct {
+                    changes.push(MarketChange::PriceMoved {
+                        epic: market.epic.clone(),
+                        previous: old,
+                        current: new,
+                        change_pct,
+                    });
+                } else {
+                    // Keep measuring from the last reported price
+                    market.bid = previous.bid;
+                    market.offer = previous.offer;
+                }
+            }
+            (Some(_), None) => {
+                market.bid = previous.bid;
+                market.offer = previous.offer;
+            }
+            _ => {}
+        }
+
+        self.baseline.insert(market.epic.clone(), market);
+    }
+
+    /// Refreshes the markets on the configured interval and sends every change
+    ///
+    /// Failed refreshes are logged and retried on the next tick; a rate limit error
+    /// also notifies the rate limiter. Returns when the receiver is dropped.
+    pub async fn run(&mut self, session: &IgSession, sender: Sender<MarketChange>) {
+        info!(
+            "Refreshing {} markets every {:?}",
+            self.epics.len(),
+            self.interval
+        );
+        let mut interval = tokio::time::interval(self.interval);
+        loop {
+            interval.tick().await;
+            if sender.is_closed() {
+                break;
+            }
+            let changes = match self.refresh(session).await {
+                Ok(changes) => changes,
+                Err(AppError::RateLimitExceeded) => {
+                    warn!("Rate limit exceeded while refreshing markets");
+                    self.rate_limiter(session)
+                        .notify_rate_limit_exceeded()
+                        .await;
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Failed to refresh markets: {}", e);
+                    continue;
+                }
+            };
+            for change in changes {
+                if sender.send(change).await.is_err() {
+                    debug!("Market change receiver dropped, stopping refresher");
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::application::models::market::MarketData;
+use crate::application::services::MarketService;
+use crate::application::services::navigation_crawler::{
+    CrawlCheckpoint, CrawlStatus, NavigationCrawler,
+};
+use crate::constants::MAX_EPICS_PER_REQUEST;
+use crate::error::AppError;
+use crate::presentation::extract_markets_from_hierarchy;
+use crate::session::interface::IgSession;
+use crate::utils::rate_limiter::{RateLimiter, wait_if_set};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Predicate deciding whether a market is kept by the scanner
+pub type MarketPredicate = Box<dyn Fn(&MarketData) -> bool + Send + Sync>;
+
+/// Scoring function used to rank the markets kept by the scanner, higher first
+pub type MarketScore = Box<dyn Fn(&MarketData) -> f64 + Send + Sync>;
+
+/// Market kept by a scan
+#[derive(Debug, Clone)]
+pub struct ScanResult {
+    /// Snapshot of the market
+    pub market: MarketData,
+    /// Ranking score of the market
+    pub score: f64,
+}
+
+/// Screener that fetches market snapshots and keeps those matching every predicate
+///
+/// Markets are ranked by score, the absolute percentage change by default. Requests
+/// go through a rate limiter (the global non-trading account limiter by default).
+pub struct MarketScanner<'a, S: MarketService> {
+    market_service: &'a S,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    predicates: Vec<MarketPredicate>,
+    score: MarketScore,
+    limit: Option<usize>,
+}
+
+impl<'a, S: MarketService> MarketScanner<'a, S> {
+    /// Creates a scanner without predicates using the given market service
+    pub fn new(market_service: &'a S) -> Self {
+        Self {
+            market_service,
+            rate_limiter: None,
+            predicates: Vec::new(),
+            score: Box::new(|market| market.percentage_change.unwrap_or(0.0).abs()),
+            limit: None,
+        }
+    }
+
+    /// Uses a specific rate limiter for snapshot requests
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Only keeps markets for which the predicate returns true
+    pub fn with_predicate<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&MarketData) -> bool + Send + Sync + 'static,
+    {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    /// Only keeps markets with a known spread of at most `max_spread`
+    pub fn with_max_spread(self, max_spread: f64) -> Self {
+        self.with_predicate(move |market| market.spread().is_some_and(|s| s <= max_spread))
+    }
+
+    /// Only keeps markets that moved at least `min_change` percent, up or down
+    pub fn with_min_percentage_change(self, min_change: f64) -> Self {
+        self.with_predicate(move |market| {
+            market
+                .percentage_change
+                .is_some_and(|change| change.abs() >= min_change)
+        })
+    }
+
+    /// Only keeps markets with the given status, e.g. `TRADEABLE`
+    pub fn with_status(self, status: &str) -> Self {
+        let status = status.to_string();
+        self.with_predicate(move |market| market.market_status == status)
+    }
+
+    /// Ranks markets with a custom score, higher first
+    pub fn rank_by<F>(mut self, score: F) -> Self
+    where
+        F: Fn(&MarketData) -> f64 + Send + Sync + 'static,
+    {
+        self.score = Box::new(score);
+        self
+    }
+
+    /// Keeps only the best `limit` markets
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Filters and ranks markets that have already been fetched
+    pub fn scan_markets(&self, markets: impl IntoIterator<Item = MarketData>) -> Vec<ScanResult> {
+        let mut results: Vec<ScanResult> = markets
+            .into_iter()
+            .filter(|market| self.predicates.iter().all(|predicate| predicate(market)))
+            .map(|market| ScanResult {
+                score: (self.score)(&market),
+                market,
+            })
+            .collect();
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        if let Some(limit) = self.limit {
+            results.truncate(limit);
+        }
+        results
+    }
+
+    /// Fetches the snapshots of the given markets and scans them
+    ///
+    /// # Arguments
+    /// * `session` - The active IG session
+    /// * `epics` - EPICs of the markets to scan
+    pub async fn scan_epics(
+        &self,
+        session: &IgSession,
+        epics: &[String],
+    ) -> Result<Vec<ScanResult>, AppError> {
+        let mut markets = Vec::with_capacity(epics.len());
+        for chunk in epics.chunks(MAX_EPICS_PER_REQUEST) {
+            wait_if_set(self.rate_limiter.as_deref()).await;
+            let details = self.market_service.get_markets(session, chunk).await?;
+            markets.extend(details.iter().map(MarketData::from));
+        }
+
+        let results = self.scan_markets(markets);
+        info!(
+            "Market scan kept {} of {} markets",
+            results.len(),
+            epics.len()
+        );
+        Ok(results)
+    }
+
+    /// Scans every market below a navigation node
+    ///
+    /// The subtree is crawled with a [`NavigationCrawler`] sharing this scanner's rate limiter.
+    ///
+    /// # Returns
+    /// * The ranked markets, or `AppError::RateLimitExceeded` if the crawl had to pause
+    pub async fn scan_node(
+        &self,
+        session: &IgSession,
+        node_id: &str,
+    ) -> Result<Vec<ScanResult>, AppError> {
+        let mut crawler = NavigationCrawler::new(self.market_service);
+        if let Some(rate_limiter) = &self.rate_limiter {
+            crawler = crawler.with_rate_limiter(rate_limiter.clone());
+        }
+        let mut checkpoint = CrawlCheckpoint::from_node(node_id, node_id);
+        if let CrawlStatus::Paused { pending } = crawler.run(session, &mut checkpoint).await? {
+            warn!(
+                "Scan of node {} paused with {} nodes pending",
+                node_id, pending
+            );
+            return Err(AppError::RateLimitExceeded);
+        }
+
+        let markets = extract_markets_from_hierarchy(&checkpoint.tree());
+        debug!("{} markets found below node {}", markets.len(), node_id);
+        Ok(self.scan_markets(markets))
+    }
+}
@@ -1,17 +1,25 @@
 use crate::application::services::MarketService;
 use crate::{
     application::models::market::{
-        HistoricalPricesResponse, MarketDetails, MarketNavigationResponse, MarketSearchResult,
+        DealabilityCheck, EpicMatch, ExpiryCalendar, HistoricalPrice, HistoricalPricesQuery,
+        HistoricalPricesResponse, MarketData, MarketDetails, MarketNavigationResponse,
+        MarketSearchFilter, MarketSearchResult, OptionChain, OptionQuote, OptionStrike, Resolution,
     },
+    application::models::order::Direction,
     config::Config,
+    constants::{MAX_CONCURRENT_MARKET_REQUESTS, MAX_EPICS_PER_REQUEST},
     error::AppError,
     session::interface::IgSession,
     transport::http_client::IgHttpClient,
+    utils::parsing::{parse_instrument_name, parse_option_epic},
 };
 use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use reqwest::Method;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tracing::{debug, info};
+use std::time::Duration;
+use tracing::{debug, info, warn};
 
 /// Implementation of the market service
 pub struct MarketServiceImpl<T: IgHttpClient> {
@@ -42,6 +50,13 @@ impl<T: IgHttpClient> MarketServiceImpl<T> {
     }
 }
 
+/// Response of `GET /markets?epics=...`, wrapping the details in a `marketDetails` array
+#[derive(serde::Deserialize)]
+struct MarketDetailsResponse {
+    #[serde(rename = "marketDetails")]
+    market_details: Vec<MarketDetails>,
+}
+
 #[async_trait]
 impl<T: IgHttpClient + 'static> MarketService for MarketServiceImpl<T> {
     async fn search_markets(
@@ -61,6 +76,36 @@ impl<T: IgHttpClient + 'static> MarketService for MarketServiceImpl<T> {
         Ok(result)
     }
 
+    async fn search_markets_filtered(
+        &self,
+        session: &IgSession,
+        search_term: &str,
+        filter: &MarketSearchFilter,
+    ) -> Result<MarketSearchResult, AppError> {
+        let result = self
+            .search_markets(session, search_term)
+            .await?
+            .filtered(filter);
+
+        debug!("{} markets left after filtering", result.markets.len());
+        Ok(result)
+    }
+
+    async fn resolve_epic(&self, session: &IgSession, name: &str) -> Result<EpicMatch, AppError> {
+        let result = self.search_markets(session, name).await?;
+        let best = result
+            .rank_by_name(name)
+            .into_iter()
+            .next()
+            .ok_or(AppError::NotFound)?;
+
+        debug!(
+            "Resolved '{}' to {} ({}) with confidence {:.2}",
+            name, best.epic, best.instrument_name, best.confidence
+        );
+        Ok(best)
+    }
+
     async fn get_market_details(
         &self,
         session: &IgSession,
@@ -78,68 +123,390 @@ impl<T: IgHttpClient + 'static> MarketService for MarketServiceImpl<T> {
         Ok(result)
     }
 
+    async fn check_dealable(
+        &self,
+        session: &IgSession,
+        epic: &str,
+        size: f64,
+        direction: Direction,
+    ) -> Result<DealabilityCheck, AppError> {
+        let details = self.get_market_details(session, epic).await?;
+        let check = details.check_dealable(size, &direction);
+
+        if !check.is_dealable() {
+            debug!(
+                "{} dealability issues for {}: {:?}",
+                check.issues.len(),
+                epic,
+                check.issues
+            );
+        }
+        Ok(check)
+    }
+
     async fn get_multiple_market_details(
         &self,
         session: &IgSession,
         epics: &[String],
     ) -> Result<Vec<MarketDetails>, AppError> {
-        if epics.is_empty() {
-            return Ok(Vec::new());
-        } else if epics.len() > 50 {
-            return Err(AppError::InvalidInput(
-                "The maximum number of EPICs is 50".to_string(),
-            ));
+        if epics.len() > MAX_EPICS_PER_REQUEST {
+            return Err(AppError::InvalidInput(format!(
+                "The maximum number of EPICs is {MAX_EPICS_PER_REQUEST}"
+            )));
         }
+        // Fits in a single request of `get_markets`
+        self.get_markets(session, epics).await
+    }
 
-        // Join the EPICs with commas to create a single request
-        let epics_str = epics.join(",");
-        let path = format!("markets?epics={epics_str}");
+    async fn get_markets(
+        &self,
+        session: &IgSession,
+        epics: &[String],
+    ) -> Result<Vec<MarketDetails>, AppError> {
+        let mut markets = Vec::with_capacity(epics.len());
+        info!(
+            "Getting {} markets in batches of {}",
+            epics.len(),
+            MAX_EPICS_PER_REQUEST
+        );
+
+        for chunk in epics.chunks(MAX_EPICS_PER_REQUEST) {
+            let path = format!("markets?epics={}&filter=ALL", chunk.join(","));
+            let response = self
+                .client
+                .request::<(), MarketDetailsResponse>(Method::GET, &path, session, None, "2")
+                .await?;
+            debug!(
+                "Batch of {} EPICs returned {} markets",
+                chunk.len(),
+                response.market_details.len()
+            );
+            markets.extend(response.market_details);
+        }
+
+        // The API does not keep the order of the requested EPICs
+        let positions: HashMap<&str, usize> = epics
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, epic)| (epic.as_str(), index))
+            .collect();
+        markets.sort_by_key(|details| {
+            positions
+                .get(details.instrument.epic.as_str())
+                .copied()
+                .unwrap_or(usize::MAX)
+        });
+        Ok(markets)
+    }
+
+    async fn get_market_details_bulk(
+        &self,
+        session: &IgSession,
+        epics: &[String],
+    ) -> HashMap<String, Result<MarketDetails, Arc<AppError>>> {
+        let mut seen = HashSet::new();
+        let unique: Vec<String> = epics
+            .iter()
+            .filter(|epic| seen.insert(epic.as_str()))
+            .cloned()
+            .collect();
+        info!(
+            "Getting {} markets in batches of {}, {} at a time",
+            unique.len(),
+            MAX_EPICS_PER_REQUEST,
+            MAX_CONCURRENT_MARKET_REQUESTS
+        );
+
+        let chunks: Vec<Vec<String>> = unique
+            .chunks(MAX_EPICS_PER_REQUEST)
+            .map(<[String]>::to_vec)
+            .collect();
+        let batches: Vec<_> = stream::iter(chunks)
+            .map(|chunk| async move {
+                // Chunks fit in a single request of `get_markets`
+                let response = self.get_markets(session, &chunk).await;
+                (chunk, response)
+            })
+            .buffer_unordered(MAX_CONCURRENT_MARKET_REQUESTS)
+            .collect()
+            .await;
+
+        let mut results = HashMap::with_capacity(unique.len());
+        for (chunk, response) in batches {
+            match response {
+                Ok(markets) => {
+                    for details in markets {
+                        results.insert(details.instrument.epic.clone(), Ok(details));
+                    }
+                    for epic in chunk {
+                        results
+                            .entry(epic)
+                            .or_insert_with(|| Err(Arc::new(AppError::NotFound)));
+                    }
+                }
+                Err(e) => {
+                    warn!("Batch of {} EPICs failed: {}", chunk.len(), e);
+                    let error = Arc::new(e);
+                    for epic in chunk {
+                        results.insert(epic, Err(error.clone()));
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    async fn get_expiry_calendar(
+        &self,
+        session: &IgSession,
+        underlying: &str,
+    ) -> Result<ExpiryCalendar, AppError> {
+        let prefix = underlying.to_lowercase();
+        let search = self.search_markets(session, underlying).await?;
+        let calendar = ExpiryCalendar::from_markets(
+            search
+                .markets
+                .iter()
+                .filter(|m| m.instrument_name.to_lowercase().starts_with(&prefix)),
+        );
 
         debug!(
-            "Getting market details for {} EPICs in a batch: {}",
-            epics.len(),
-            epics_str
+            "{} expiries found for {}",
+            calendar.expiries.len(),
+            underlying
+        );
+        Ok(calendar)
+    }
+
+    async fn get_option_chain(
+        &self,
+        session: &IgSession,
+        underlying: &str,
+        expiry: &str,
+    ) -> Result<OptionChain, AppError> {
+        info!(
+            "Getting option chain for {} expiring {}",
+            underlying, expiry
         );
+        let prefix = underlying.to_lowercase();
 
-        // The API returns an object with un array de MarketDetails en la propiedad marketDetails
-        #[derive(serde::Deserialize)]
-        struct MarketDetailsResponse {
-            #[serde(rename = "marketDetails")]
-            market_details: Vec<MarketDetails>,
+        let search = self.search_markets(session, underlying).await?;
+        let epics: Vec<String> = search
+            .markets
+            .iter()
+            .filter(|m| m.instrument_type.is_option())
+            .filter(|m| m.expiry.eq_ignore_ascii_case(expiry))
+            .filter(|m| m.instrument_name.to_lowercase().starts_with(&prefix))
+            .map(|m| m.epic.clone())
+            .collect();
+        debug!("{} option markets found for {}", epics.len(), underlying);
+
+        let mut strikes: Vec<OptionStrike> = Vec::new();
+        for details in self.get_markets(session, &epics).await? {
+            let parsed = parse_instrument_name(&details.instrument.name);
+            let (Some(strike), Some(option_type)) = (
+                parsed.strike.and_then(|s| s.parse::<f64>().ok()),
+                parsed.option_type,
+            ) else {
+                debug!("Skipping non option market: {}", details.instrument.name);
+                continue;
+            };
+
+            let quote = OptionQuote {
+                epic: details.instrument.epic.clone(),
+                instrument_name: details.instrument.name.clone(),
+                bid: details.snapshot.bid,
+                offer: details.snapshot.offer,
+                market_status: details.snapshot.market_status.clone(),
+            };
+            let entry = match strikes.iter_mut().find(|s| s.strike == strike) {
+                Some(entry) => entry,
+                None => {
+                    strikes.push(OptionStrike {
+                        strike,
+                        call: None,
+                        put: None,
+                    });
+                    strikes.last_mut().expect("strike just pushed")
+                }
+            };
+            if option_type == "CALL" {
+                entry.call = Some(quote);
+            } else {
+                entry.put = Some(quote);
+            }
         }
+        strikes.sort_by(|a, b| a.strike.total_cmp(&b.strike));
 
-        let response = self
-            .client
-            .request::<(), MarketDetailsResponse>(Method::GET, &path, session, None, "2")
-            .await?;
+        debug!(
+            "Option chain for {} has {} strikes",
+            underlying,
+            strikes.len()
+        );
+        Ok(OptionChain {
+            underlying: underlying.to_string(),
+            expiry: expiry.to_string(),
+            strikes,
+        })
+    }
+
+    async fn resolve_underlying(
+        &self,
+        session: &IgSession,
+        option_epic: &str,
+    ) -> Result<MarketData, AppError> {
+        let option = parse_option_epic(option_epic).ok_or_else(|| {
+            AppError::InvalidInput(format!("{option_epic} is not an option EPIC"))
+        })?;
+        info!("Resolving underlying of {}", option_epic);
+
+        let details = self.get_market_details(session, option_epic).await?;
+        let asset_name = parse_instrument_name(&details.instrument.name).asset_name;
+
+        let search = self.search_markets(session, &asset_name).await?;
+        let underlying = search
+            .best_underlying(&option, &asset_name)
+            .cloned()
+            .ok_or(AppError::NotFound)?;
 
         debug!(
-            "Market details obtained for {} EPICs",
-            response.market_details.len()
+            "Resolved underlying of {} to {} ({})",
+            option_epic, underlying.epic, underlying.instrument_name
         );
-        Ok(response.market_details)
+        Ok(underlying)
     }
 
     async fn get_historical_prices(
         &self,
         session: &IgSession,
         epic: &str,
-        resolution: &str,
+        resolution: Resolution,
         from: &str,
         to: &str,
     ) -> Result<HistoricalPricesResponse, AppError> {
-        let path = format!("prices/{epic}?resolution={resolution}&from={from}&to={to}");
+        let query = HistoricalPricesQuery::new(resolution).with_range(from, to);
+        self.get_historical_prices_query(session, epic, &query)
+            .await
+    }
+
+    async fn get_historical_prices_query(
+        &self,
+        session: &IgSession,
+        epic: &str,
+        query: &HistoricalPricesQuery,
+    ) -> Result<HistoricalPricesResponse, AppError> {
+        let path = format!("prices/{epic}?{}", query.to_query_string());
         info!("Getting historical prices for: {}", epic);
 
+        // Reserve a point of the weekly allowance, waiting for it once spent
+        let limiter = &session.rate_limiters().historical_price;
+        limiter.wait().await;
+
         let result = self
             .client
             .request::<(), HistoricalPricesResponse>(Method::GET, &path, session, None, "3")
             .await?;
 
-        debug!("Historical prices obtained for: {}", epic);
+        // The weekly allowance is counted in data points, one per price returned
+        limiter.consume(result.prices.len().saturating_sub(1)).await;
+        if let Some(allowance) = result.allowance() {
+            let used = allowance.total_allowance - allowance.remaining_allowance;
+            limiter
+                .sync_usage(
+                    used.max(0) as usize,
+                    Duration::from_secs(allowance.allowance_expiry.max(0) as u64),
+                )
+                .await;
+        }
+        debug!(
+            "{} historical prices obtained for: {}",
+            result.prices.len(),
+            epic
+        );
         Ok(result)
     }
 
+    async fn get_all_historical_prices(
+        &self,
+        session: &IgSession,
+        epic: &str,
+        query: &HistoricalPricesQuery,
+    ) -> Result<HistoricalPricesResponse, AppError> {
+        let mut page_query = query.clone();
+        let mut page_number = query.page_number.unwrap_or(1);
+        page_query.page_number = Some(page_number);
+        let mut result = self
+            .get_historical_prices_query(session, epic, &page_query)
+            .await?;
+
+        // The page count of the first response bounds the walk, so paging metadata
+        // that keeps announcing more pages cannot spend the whole allowance
+        let last_page = result
+            .page_data()
+            .map_or(page_number, |page| page.total_pages.max(0) as u32);
+        let mut pages = 1;
+        while page_number < last_page {
+            page_number += 1;
+            page_query.page_number = Some(page_number);
+            let page = self
+                .get_historical_prices_query(session, epic, &page_query)
+                .await?;
+            pages += 1;
+            let empty = page.prices.is_empty();
+            result.prices.extend(page.prices);
+            result.metadata = page.metadata;
+            if empty {
+                break;
+            }
+        }
+
+        debug!(
+            "{} historical prices obtained in {} pages for: {}",
+            result.prices.len(),
+            pages,
+            epic
+        );
+        Ok(result)
+    }
+
+    fn stream_prices<'a>(
+        &'a self,
+        session: &'a IgSession,
+        epic: &'a str,
+        resolution: Resolution,
+        from: &str,
+        to: &str,
+    ) -> BoxStream<'a, Result<HistoricalPrice, AppError>> {
+        let query = HistoricalPricesQuery::new(resolution).with_range(from, to);
+        info!("Streaming historical prices for: {}", epic);
+
+        stream::try_unfold(Some(1u32), move |next_page| {
+            let mut query = query.clone();
+            async move {
+                let Some(page_number) = next_page else {
+                    return Ok::<_, AppError>(None);
+                };
+                query.page_number = Some(page_number);
+                let page = self
+                    .get_historical_prices_query(session, epic, &query)
+                    .await?;
+                let next_page =
+                    (page.has_more_pages() && !page.prices.is_empty()).then_some(page_number + 1);
+                debug!(
+                    "Page {} of historical prices streamed for: {}",
+                    page_number, epic
+                );
+                Ok(Some((
+                    stream::iter(page.prices.into_iter().map(Ok)),
+                    next_page,
+                )))
+            }
+        })
+        .try_flatten()
+        .boxed()
+    }
+
     async fn get_market_navigation(
         &self,
         session: &IgSession,
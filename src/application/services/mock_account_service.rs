@@ -0,0 +1,361 @@
+use crate::application::models::account::{
+    Account, AccountActivity, AccountBalance, AccountInfo, AccountTransaction, Activity,
+    ActivityQuery, MarginSummary, PageData, Position, PositionMargin, Positions,
+    TransactionHistory, TransactionMetadata, TransactionType, WorkingOrder, WorkingOrders,
+};
+use crate::application::models::transaction::TransactionCategory;
+use crate::application::services::AccountService;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::utils::currency::CurrencyConverter;
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Fixtures served by [`MockAccountService`]
+#[derive(Default)]
+struct Fixtures {
+    accounts: Vec<Account>,
+    positions: Vec<Position>,
+    margins: HashMap<String, f64>,
+    working_orders: Vec<WorkingOrder>,
+    activities: Vec<Activity>,
+    transactions: Vec<AccountTransaction>,
+    failures: VecDeque<AppError>,
+}
+
+/// Returns true if an API date falls within `[from, to]`
+///
+/// Dates are compared as ISO 8601 strings, ignoring a trailing `Z`.
+fn in_range(date: &str, from: &str, to: Option<&str>) -> bool {
+    let date = date.trim_end_matches('Z');
+    date >= from.trim_end_matches('Z') && to.is_none_or(|to| date <= to.trim_end_matches('Z'))
+}
+
+/// Returns true if a transaction is of the type requested from the API
+fn of_type(transaction: &AccountTransaction, tx_type: TransactionType) -> bool {
+    let category = TransactionCategory::of(transaction);
+    match tx_type {
+        TransactionType::All => true,
+        TransactionType::AllDeal => category == TransactionCategory::Trade,
+        TransactionType::Deposit => category == TransactionCategory::Deposit,
+        TransactionType::Withdrawal => category == TransactionCategory::Withdrawal,
+    }
+}
+
+/// Account service serving programmable fixtures, for tests needing no IG account
+///
+/// Accounts, positions, working orders, activities and transactions are set with the
+/// `with_*` builders and changed between calls with the `set_*` methods. The same
+/// fixtures are served to every session, except that
+/// [`AccountService::get_margin_summary`] looks up the session account. Activities and
+/// transactions are filtered by date, and transactions by type and page; activity
+/// filters of [`ActivityQuery`] are ignored.
+///
+/// Errors queued with [`MockAccountService::fail_next`] are returned by the following
+/// calls, one per call, whatever the method.
+#[derive(Default)]
+pub struct MockAccountService {
+    fixtures: Mutex<Fixtures>,
+    calls: AtomicUsize,
+}
+
+impl MockAccountService {
+    /// Creates a service with no account and nothing open
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an account
+    pub fn with_account(self, account: Account) -> Self {
+        self.fixtures.lock().unwrap().accounts.push(account);
+        self
+    }
+
+    /// Sets the open positions
+    pub fn with_positions(self, positions: Vec<Position>) -> Self {
+        self.set_positions(positions);
+        self
+    }
+
+    /// Sets the margin held by the position with the given deal ID
+    ///
+    /// Positions without a margin hold none in [`AccountService::get_margin_summary`].
+    pub fn with_margin(self, deal_id: &str, margin: f64) -> Self {
+        self.fixtures
+            .lock()
+            .unwrap()
+            .margins
+            .insert(deal_id.to_string(), margin);
+        self
+    }
+
+    /// Sets the working orders
+    pub fn with_working_orders(self, working_orders: Vec<WorkingOrder>) -> Self {
+        self.set_working_orders(working_orders);
+        self
+    }
+
+    /// Sets the activity history
+    pub fn with_activities(self, activities: Vec<Activity>) -> Self {
+        self.set_activities(activities);
+        self
+    }
+
+    /// Sets the transaction history
+    pub fn with_transactions(self, transactions: Vec<AccountTransaction>) -> Self {
+        self.set_transactions(transactions);
+        self
+    }
+
+    /// Replaces the balance of an account
+    ///
+    /// # Returns
+    /// `AppError::NotFound` if no account has this ID
+    pub fn set_balance(&self, account_id: &str, balance: AccountBalance) -> Result<(), AppError> {
+        let mut fixtures = self.fixtures.lock().unwrap();
+        let account = fixtures
+            .accounts
+            .iter_mut()
+            .find(|account| account.account_id == account_id)
+            .ok_or(AppError::NotFound)?;
+        account.balance = balance;
+        Ok(())
+    }
+
+    /// Replaces the open positions
+    pub fn set_positions(&self, positions: Vec<Position>) {
+        self.fixtures.lock().unwrap().positions = positions;
+    }
+
+    /// Replaces the working orders
+    pub fn set_working_orders(&self, working_orders: Vec<WorkingOrder>) {
+        self.fixtures.lock().unwrap().working_orders = working_orders;
+    }
+
+    /// Replaces the activity history
+    pub fn set_activities(&self, activities: Vec<Activity>) {
+        self.fixtures.lock().unwrap().activities = activities;
+    }
+
+    /// Replaces the transaction history
+    pub fn set_transactions(&self, transactions: Vec<AccountTransaction>) {
+        self.fixtures.lock().unwrap().transactions = transactions;
+    }
+
+    /// Makes the next call fail with `error`
+    pub fn fail_next(&self, error: AppError) {
+        self.fixtures.lock().unwrap().failures.push_back(error);
+    }
+
+    /// Returns the number of calls made to the service
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// Counts a call and returns the fixtures, or the next queued error
+    fn fixtures(&self) -> Result<std::sync::MutexGuard<'_, Fixtures>, AppError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let mut fixtures = self.fixtures.lock().unwrap();
+        match fixtures.failures.pop_front() {
+            Some(error) => Err(error),
+            None => Ok(fixtures),
+        }
+    }
+
+    fn activities(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        detailed: bool,
+    ) -> Result<Vec<Activity>, AppError> {
+        Ok(self
+            .fixtures()?
+            .activities
+            .iter()
+            .filter(|activity| in_range(&activity.date, from, to))
+            .map(|activity| Activity {
+                details: if detailed {
+                    activity.details.clone()
+                } else {
+                    None
+                },
+                ..activity.clone()
+            })
+            .collect())
+    }
+
+    fn transactions(
+        &self,
+        from: &str,
+        to: &str,
+        tx_type: TransactionType,
+    ) -> Result<Vec<AccountTransaction>, AppError> {
+        Ok(self
+            .fixtures()?
+            .transactions
+            .iter()
+            .filter(|transaction| in_range(&transaction.date_utc, from, Some(to)))
+            .filter(|transaction| of_type(transaction, tx_type))
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl AccountService for MockAccountService {
+    async fn get_accounts(&self, _session: &IgSession) -> Result<AccountInfo, AppError> {
+        Ok(AccountInfo {
+            accounts: self.fixtures()?.accounts.clone(),
+        })
+    }
+
+    async fn get_positions(&self, _session: &IgSession) -> Result<Positions, AppError> {
+        Ok(Positions {
+            positions: self.fixtures()?.positions.clone(),
+        })
+    }
+
+    async fn positions_with_pnl(
+        &self,
+        session: &IgSession,
+        converter: &CurrencyConverter,
+    ) -> Result<Positions, AppError> {
+        let mut positions = self.get_positions(session).await?;
+        for position in &mut positions.positions {
+            let (bid, offer) = (position.market.bid, position.market.offer);
+            position.pnl = position.unrealized_pnl_in(bid, offer, converter).ok();
+        }
+        Ok(positions)
+    }
+
+    async fn get_margin_summary(
+        &self,
+        session: &IgSession,
+        _converter: &CurrencyConverter,
+    ) -> Result<MarginSummary, AppError> {
+        let fixtures = self.fixtures()?;
+        let account = fixtures
+            .accounts
+            .iter()
+            .find(|account| account.account_id == session.account_id)
+            .ok_or(AppError::NotFound)?;
+        let margins = fixtures
+            .positions
+            .iter()
+            .map(|position| PositionMargin {
+                deal_id: position.position.deal_id.clone(),
+                epic: position.market.epic.clone(),
+                margin: fixtures
+                    .margins
+                    .get(&position.position.deal_id)
+                    .copied()
+                    .unwrap_or_default(),
+            })
+            .collect();
+        Ok(MarginSummary::new(account, margins))
+    }
+
+    async fn find_position_by_reference(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+    ) -> Result<Option<Position>, AppError> {
+        Ok(self
+            .get_positions(session)
+            .await?
+            .find_by_reference(deal_reference)
+            .cloned())
+    }
+
+    async fn get_working_orders(&self, _session: &IgSession) -> Result<WorkingOrders, AppError> {
+        Ok(WorkingOrders {
+            working_orders: self.fixtures()?.working_orders.clone(),
+        })
+    }
+
+    async fn get_activity(
+        &self,
+        _session: &IgSession,
+        from: &str,
+        to: &str,
+    ) -> Result<AccountActivity, AppError> {
+        Ok(AccountActivity {
+            activities: self.activities(from, Some(to), false)?,
+            metadata: None,
+        })
+    }
+
+    async fn get_activity_with_details(
+        &self,
+        _session: &IgSession,
+        from: &str,
+        to: &str,
+    ) -> Result<AccountActivity, AppError> {
+        Ok(AccountActivity {
+            activities: self.activities(from, Some(to), true)?,
+            metadata: None,
+        })
+    }
+
+    fn stream_activity<'a>(
+        &'a self,
+        _session: &'a IgSession,
+        query: &ActivityQuery,
+    ) -> BoxStream<'a, Result<Activity, AppError>> {
+        match self.activities(&query.from, query.to.as_deref(), query.detailed) {
+            Ok(activities) => stream::iter(activities.into_iter().map(Ok)).boxed(),
+            Err(error) => stream::once(async { Err(error) }).boxed(),
+        }
+    }
+
+    async fn get_transactions(
+        &self,
+        _session: &IgSession,
+        from: &str,
+        to: &str,
+        page_size: u32,
+        page_number: u32,
+    ) -> Result<TransactionHistory, AppError> {
+        let transactions = self.transactions(from, to, TransactionType::All)?;
+        let size = transactions.len();
+        // A page size of zero disables paging, as on IG
+        let page_size = if page_size == 0 {
+            size.max(1)
+        } else {
+            page_size as usize
+        };
+        let total_pages = size.div_ceil(page_size).max(1);
+        let start = (page_number.max(1) as usize - 1) * page_size;
+        Ok(TransactionHistory {
+            transactions: transactions
+                .into_iter()
+                .skip(start)
+                .take(page_size)
+                .collect(),
+            metadata: TransactionMetadata {
+                page_data: PageData {
+                    page_number: page_number.max(1) as i32,
+                    page_size: page_size as i32,
+                    total_pages: total_pages as i32,
+                },
+                size: size as i32,
+            },
+        })
+    }
+
+    fn stream_transactions<'a>(
+        &'a self,
+        _session: &'a IgSession,
+        from: &str,
+        to: &str,
+        tx_type: TransactionType,
+    ) -> BoxStream<'a, Result<AccountTransaction, AppError>> {
+        match self.transactions(from, to, tx_type) {
+            Ok(transactions) => stream::iter(transactions.into_iter().map(Ok)).boxed(),
+            Err(error) => stream::once(async { Err(error) }).boxed(),
+        }
+    }
+}
@@ -1,17 +1,68 @@
 /// Module containing account service for retrieving account information
 pub mod account_service;
+/// Module containing a tracker recording the account balance over time
+pub mod balance_tracker;
+/// Module containing a job keeping the offline instrument catalog up to date
+pub mod catalog_refresher;
 mod interfaces;
+/// Module containing an order service wrapper recording an audit journal of orders
+pub mod journaled_order_service;
 mod listener;
+/// Module containing threshold monitors firing margin and position loss alerts
+pub mod margin_monitor;
+/// Module containing a polling market refresher emitting change events
+pub mod market_refresher;
+/// Module containing a market scanner filtering and ranking snapshots
+pub mod market_scanner;
 /// Module containing market update listener implementation
 /// Module containing market service for retrieving market information
 pub mod market_service;
+/// Module containing an account service test double serving programmable fixtures
+pub mod mock_account_service;
+/// Module containing a disk cache of the market navigation tree
+pub mod navigation_cache;
+/// Module containing a resumable, rate-limited crawler of the market navigation tree
+pub mod navigation_crawler;
+/// Module containing registries of submitted deal references for idempotent orders
+pub mod order_idempotency;
+/// Module containing an order service wrapper collecting deal statistics
+pub mod order_metrics;
+/// Module containing a scheduler submitting orders at a time or at market open
+pub mod order_scheduler;
 /// Module containing order service for creating and managing orders
 pub mod order_service;
+/// Module containing a tracker correlating order submissions, confirmations and updates
+pub mod order_tracker;
+/// Module containing a paper-trading order service simulating fills locally
+pub mod paper_order_service;
+/// Module containing a combined view of the positions, orders and balances of several accounts
+pub mod portfolio_service;
+/// Module containing a shared view of the open positions refreshed in the background
+pub mod positions_cache;
+/// Module containing a resumable historical price backfill job
+pub mod price_backfill;
+/// Module containing a background task applying storage retention policies
+pub mod retention_compactor;
+/// Module containing an order service wrapper enforcing client-side risk limits
+pub mod risk_guard;
+/// Module containing a recorder storing snapshots of the account over time
+pub mod snapshot_recorder;
+/// Module containing a recorder writing streamed prices to storage as ticks
+pub mod tick_recorder;
+/// Module containing client-side trailing stops for markets without server support
+pub mod trailing_stop_manager;
+/// Module containing a fetch of long transaction histories in bounded date windows
+pub mod transaction_fetcher;
+/// Module containing an incremental, checkpointed sync of the transaction history
+pub mod transaction_sync;
 /// Module containing common types used by services
 mod types;
+/// Module containing watchlist service for managing IG watchlists
+pub mod watchlist_service;
 
 pub use interfaces::account::AccountService;
 pub use interfaces::market::MarketService;
 pub use interfaces::order::OrderService;
+pub use interfaces::watchlist::WatchlistService;
 pub use listener::Listener;
 pub use types::ListenerResult;
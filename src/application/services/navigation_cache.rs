@@ -0,0 +1,128 @@
+use crate::application::models::market::MarketNode;
+use crate::application::services::MarketService;
+use crate::application::services::navigation_crawler::{
+    CrawlCheckpoint, CrawlStatus, NavigationCrawler,
+};
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Default time after which a cached navigation tree is crawled again
+pub const DEFAULT_NAVIGATION_CACHE_TTL_HOURS: i64 = 24;
+
+/// Navigation tree stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedNavigation {
+    /// When the crawl that produced the tree started
+    pub fetched_at: DateTime<Utc>,
+    /// Crawl state, complete or paused
+    pub checkpoint: CrawlCheckpoint,
+}
+
+impl CachedNavigation {
+    /// Returns true if the crawl finished and is younger than `max_age`
+    pub fn is_fresh(&self, max_age: Duration, now: DateTime<Utc>) -> bool {
+        self.checkpoint.is_complete() && now - self.fetched_at < max_age
+    }
+}
+
+/// JSON file cache of the `/marketnavigation` tree
+///
+/// A fresh cache is served without any request. A stale cache is crawled again from
+/// scratch, and a paused crawl is resumed, the cache being saved after every run so
+/// that the next startup continues where the previous one stopped.
+pub struct NavigationCache {
+    path: PathBuf,
+    max_age: Duration,
+}
+
+impl NavigationCache {
+    /// Creates a cache stored at `path` with the default time to live
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            max_age: Duration::hours(DEFAULT_NAVIGATION_CACHE_TTL_HOURS),
+        }
+    }
+
+    /// Sets the age after which the tree is crawled again
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Loads the cached tree, `None` if nothing has been cached yet
+    pub fn load(&self) -> Result<Option<CachedNavigation>, AppError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let json = std::fs::read_to_string(&self.path)?;
+        Ok(Some(serde_json::from_str(&json)?))
+    }
+
+    /// Writes the tree to the cache file
+    pub fn save(&self, cached: &CachedNavigation) -> Result<(), AppError> {
+        let json = serde_json::to_string(cached)?;
+        std::fs::write(&self.path, json)?;
+        Ok(())
+    }
+
+    /// Returns the cached tree if it is fresh, crawling (or resuming) it otherwise
+    ///
+    /// # Returns
+    /// * The cached navigation, or `AppError::RateLimitExceeded` if the crawl had to
+    ///   pause; the partial crawl is saved and resumed on the next call
+    pub async fn get_or_crawl<S: MarketService>(
+        &self,
+        crawler: &NavigationCrawler<'_, S>,
+        session: &IgSession,
+    ) -> Result<CachedNavigation, AppError> {
+        let now = Utc::now();
+        let mut cached = match self.load() {
+            Ok(Some(cached)) if cached.is_fresh(self.max_age, now) => {
+                debug!("Serving navigation tree from {}", self.path.display());
+                return Ok(cached);
+            }
+            Ok(Some(cached)) if !cached.checkpoint.is_complete() => {
+                info!(
+                    "Resuming navigation crawl cached at {}",
+                    self.path.display()
+                );
+                cached
+            }
+            Ok(_) => CachedNavigation {
+                fetched_at: now,
+                checkpoint: CrawlCheckpoint::new(),
+            },
+            Err(e) => {
+                warn!("Ignoring unreadable navigation cache: {}", e);
+                CachedNavigation {
+                    fetched_at: now,
+                    checkpoint: CrawlCheckpoint::new(),
+                }
+            }
+        };
+
+        let status = crawler.run(session, &mut cached.checkpoint).await;
+        self.save(&cached)?;
+        match status? {
+            CrawlStatus::Complete => Ok(cached),
+            CrawlStatus::Paused { pending } => {
+                warn!("Navigation crawl paused with {} nodes pending", pending);
+                Err(AppError::RateLimitExceeded)
+            }
+        }
+    }
+
+    /// Returns the instrument tree, from the cache when fresh
+    pub async fn tree<S: MarketService>(
+        &self,
+        crawler: &NavigationCrawler<'_, S>,
+        session: &IgSession,
+    ) -> Result<Vec<MarketNode>, AppError> {
+        Ok(self.get_or_crawl(crawler, session).await?.checkpoint.tree())
+    }
+}
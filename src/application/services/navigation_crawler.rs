@@ -0,0 +1,407 @@
+use crate::application::models::market::{
+    MarketData, MarketNavigationNode, MarketNavigationResponse, MarketNode,
+};
+use crate::application::services::MarketService;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::utils::rate_limiter::{RateLimiter, wait_if_set};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Default maximum depth explored by the crawler
+pub const DEFAULT_MAX_CRAWL_DEPTH: usize = 8;
+
+/// Predicate deciding whether a navigation node (and its subtree) should be crawled.
+/// Receives the node and its depth, the top-level nodes having depth 1.
+pub type NodeFilter = Box<dyn Fn(&MarketNavigationNode, usize) -> bool + Send + Sync>;
+
+/// Navigation node waiting to be fetched
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PendingNode {
+    /// Node ID, `None` for the navigation root
+    pub id: Option<String>,
+    /// Node name
+    pub name: String,
+    /// ID of the parent node, `None` for top-level nodes
+    pub parent_id: Option<String>,
+    /// Depth of the node, 0 for the root
+    pub depth: usize,
+}
+
+/// Navigation node that has already been fetched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawledNode {
+    /// Node ID
+    pub id: String,
+    /// Node name
+    pub name: String,
+    /// ID of the parent node, `None` for top-level nodes
+    pub parent_id: Option<String>,
+    /// IDs of the child nodes, in API order
+    pub children: Vec<String>,
+    /// Markets listed directly under this node
+    pub markets: Vec<MarketData>,
+}
+
+/// Resumable state of a navigation crawl
+///
+/// The checkpoint can be serialized (see [`CrawlCheckpoint::save`]) when a crawl is paused
+/// and loaded later to continue where it stopped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlCheckpoint {
+    /// Nodes still to be fetched, in breadth-first order
+    pub pending: VecDeque<PendingNode>,
+    /// Nodes already fetched, by ID
+    pub crawled: HashMap<String, CrawledNode>,
+    /// IDs of the top-level nodes, in API order
+    pub roots: Vec<String>,
+    /// Markets listed at the root of the navigation
+    pub root_markets: Vec<MarketData>,
+    /// Number of navigation requests made so far
+    pub requests: usize,
+}
+
+impl Default for CrawlCheckpoint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrawlCheckpoint {
+    /// Creates a checkpoint that starts at the navigation root
+    pub fn new() -> Self {
+        Self {
+            pending: VecDeque::from([PendingNode {
+                id: None,
+                name: "root".to_string(),
+                parent_id: None,
+                depth: 0,
+            }]),
+            crawled: HashMap::new(),
+            roots: Vec::new(),
+            root_markets: Vec::new(),
+            requests: 0,
+        }
+    }
+
+    /// Creates a checkpoint that only crawls the subtree below the given node
+    ///
+    /// The node becomes the single top-level node of the resulting tree.
+    pub fn from_node(id: &str, name: &str) -> Self {
+        Self {
+            pending: VecDeque::from([PendingNode {
+                id: Some(id.to_string()),
+                name: name.to_string(),
+                parent_id: None,
+                depth: 1,
+            }]),
+            ..Self::new()
+        }
+    }
+
+    /// Returns true if there are no nodes left to fetch
+    pub fn is_complete(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Saves the checkpoint as JSON
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AppError> {
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Loads a checkpoint previously written with [`CrawlCheckpoint::save`]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Builds the instrument tree from the nodes crawled so far
+    ///
+    /// Markets are represented as leaf nodes, as in `build_market_hierarchy`.
+    pub fn tree(&self) -> Vec<MarketNode> {
+        let mut nodes: Vec<MarketNode> = self
+            .roots
+            .iter()
+            .filter_map(|id| self.build_node(id))
+            .collect();
+        nodes.extend(self.root_markets.iter().map(market_leaf));
+        nodes
+    }
+
+    /// Returns a crawled node by ID
+    pub fn node(&self, id: &str) -> Option<&CrawledNode> {
+        self.crawled.get(id)
+    }
+
+    /// Returns the crawled children of a node, in API order
+    pub fn children(&self, id: &str) -> Vec<&CrawledNode> {
+        self.crawled
+            .get(id)
+            .map(|node| {
+                node.children
+                    .iter()
+                    .filter_map(|child| self.crawled.get(child))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns every market below a node, including those of its descendants
+    pub fn markets_under(&self, id: &str) -> Vec<&MarketData> {
+        let mut markets = Vec::new();
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            if let Some(node) = self.crawled.get(current) {
+                markets.extend(node.markets.iter());
+                stack.extend(node.children.iter().rev().map(String::as_str));
+            }
+        }
+        markets
+    }
+
+    /// Returns every crawled market matching the predicate
+    pub fn find_markets<F>(&self, predicate: F) -> Vec<&MarketData>
+    where
+        F: Fn(&MarketData) -> bool,
+    {
+        self.root_markets
+            .iter()
+            .chain(self.crawled.values().flat_map(|node| node.markets.iter()))
+            .filter(|market| predicate(market))
+            .collect()
+    }
+
+    fn build_node(&self, id: &str) -> Option<MarketNode> {
+        let crawled = self.crawled.get(id)?;
+        let mut children: Vec<MarketNode> = crawled
+            .children
+            .iter()
+            .filter_map(|child| self.build_node(child))
+            .collect();
+        children.extend(crawled.markets.iter().map(market_leaf));
+        Some(MarketNode {
+            id: crawled.id.clone(),
+            name: crawled.name.clone(),
+            children,
+            markets: Vec::new(),
+        })
+    }
+}
+
+fn market_leaf(market: &MarketData) -> MarketNode {
+    MarketNode {
+        id: market.epic.clone(),
+        name: market.instrument_name.clone(),
+        children: Vec::new(),
+        markets: vec![market.clone()],
+    }
+}
+
+/// Outcome of a crawl run
+#[derive(Debug, Clone, PartialEq)]
+pub enum CrawlStatus {
+    /// Every reachable node has been fetched
+    Complete,
+    /// The crawl stopped early; the checkpoint holds the remaining nodes
+    Paused {
+        /// Number of nodes still to be fetched
+        pending: usize,
+    },
+}
+
+/// Breadth-first crawler of the `/marketnavigation` tree
+///
+/// Every request goes through a rate limiter (the global non-trading account limiter
+/// by default), the crawl can be limited to a number of requests per run and resumed
+/// from a [`CrawlCheckpoint`], and subtrees can be skipped with a node filter.
+pub struct NavigationCrawler<'a, S: MarketService> {
+    market_service: &'a S,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    filter: Option<NodeFilter>,
+    max_depth: usize,
+    max_requests: Option<usize>,
+}
+
+impl<'a, S: MarketService> NavigationCrawler<'a, S> {
+    /// Creates a crawler using the given market service
+    pub fn new(market_service: &'a S) -> Self {
+        Self {
+            market_service,
+            rate_limiter: None,
+            filter: None,
+            max_depth: DEFAULT_MAX_CRAWL_DEPTH,
+            max_requests: None,
+        }
+    }
+
+    /// Uses a specific rate limiter for navigation requests
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Returns the configured rate limiter, or the account limiter of `session`
+    fn rate_limiter(&self, session: &IgSession) -> Arc<RateLimiter> {
+        self.rate_limiter
+            .clone()
+            .unwrap_or_else(|| session.rate_limiters().account_non_trading.clone())
+    }
+
+    /// Only crawls nodes for which the filter returns true
+    pub fn with_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&MarketNavigationNode, usize) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Sets the maximum depth to crawl
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Pauses the crawl after the given number of requests in a single run
+    pub fn with_max_requests(mut self, max_requests: usize) -> Self {
+        self.max_requests = Some(max_requests);
+        self
+    }
+
+    /// Crawls the whole navigation tree from scratch
+    ///
+    /// # Returns
+    /// * The full instrument tree, or `AppError::RateLimitExceeded` if the crawl had to pause
+    pub async fn crawl(&self, session: &IgSession) -> Result<Vec<MarketNode>, AppError> {
+        let mut checkpoint = CrawlCheckpoint::new();
+        match self.run(session, &mut checkpoint).await? {
+            CrawlStatus::Complete => Ok(checkpoint.tree()),
+            CrawlStatus::Paused { pending } => {
+                warn!("Navigation crawl paused with {} nodes pending", pending);
+                Err(AppError::RateLimitExceeded)
+            }
+        }
+    }
+
+    /// Continues a crawl from the given checkpoint
+    ///
+    /// The checkpoint is updated in place. If the API reports a rate limit or the
+    /// request budget is spent, the crawl pauses and the checkpoint can be resumed later.
+    /// On any other error the failed node is kept pending and the error is returned.
+    /// Every node is fetched once, even if the checkpoint lists it several times.
+    pub async fn run(
+        &self,
+        session: &IgSession,
+        checkpoint: &mut CrawlCheckpoint,
+    ) -> Result<CrawlStatus, AppError> {
+        let mut requests = 0;
+        let mut visited: HashSet<String> = checkpoint.crawled.keys().cloned().collect();
+        // A restored checkpoint may list a node twice, or one that was crawled already
+        checkpoint.pending.retain(|node| match &node.id {
+            Some(id) => visited.insert(id.clone()),
+            None => true,
+        });
+
+        while let Some(item) = checkpoint.pending.pop_front() {
+            if self.max_requests.is_some_and(|max| requests >= max) {
+                checkpoint.pending.push_front(item);
+                break;
+            }
+
+            wait_if_set(self.rate_limiter.as_deref()).await;
+            let response = match self.fetch(session, item.id.as_deref()).await {
+                Ok(response) => response,
+                Err(AppError::RateLimitExceeded) => {
+                    warn!("Rate limit exceeded while crawling, pausing");
+                    self.rate_limiter(session)
+                        .notify_rate_limit_exceeded()
+                        .await;
+                    checkpoint.pending.push_front(item);
+                    break;
+                }
+                Err(e) => {
+                    checkpoint.pending.push_front(item);
+                    return Err(e);
+                }
+            };
+            requests += 1;
+            checkpoint.requests += 1;
+
+            let child_depth = item.depth + 1;
+            let mut children = Vec::new();
+            for node in response.nodes {
+                if child_depth > self.max_depth || visited.contains(&node.id) {
+                    continue;
+                }
+                if let Some(filter) = &self.filter
+                    && !filter(&node, child_depth)
+                {
+                    debug!("Skipping filtered node {} ({})", node.name, node.id);
+                    continue;
+                }
+                visited.insert(node.id.clone());
+                children.push(node.id.clone());
+                checkpoint.pending.push_back(PendingNode {
+                    id: Some(node.id),
+                    name: node.name,
+                    parent_id: item.id.clone(),
+                    depth: child_depth,
+                });
+            }
+
+            match item.id {
+                Some(id) => {
+                    if item.parent_id.is_none() {
+                        checkpoint.roots.push(id.clone());
+                    }
+                    checkpoint.crawled.insert(
+                        id.clone(),
+                        CrawledNode {
+                            id,
+                            name: item.name,
+                            parent_id: item.parent_id,
+                            children,
+                            markets: response.markets,
+                        },
+                    );
+                }
+                None => checkpoint.root_markets = response.markets,
+            }
+        }
+
+        info!(
+            "Navigation crawl: {} requests this run, {} nodes crawled, {} pending",
+            requests,
+            checkpoint.crawled.len(),
+            checkpoint.pending.len()
+        );
+
+        if checkpoint.is_complete() {
+            Ok(CrawlStatus::Complete)
+        } else {
+            Ok(CrawlStatus::Paused {
+                pending: checkpoint.pending.len(),
+            })
+        }
+    }
+
+    async fn fetch(
+        &self,
+        session: &IgSession,
+        node_id: Option<&str>,
+    ) -> Result<MarketNavigationResponse, AppError> {
+        match node_id {
+            Some(id) => {
+                self.market_service
+                    .get_market_navigation_node(session, id)
+                    .await
+            }
+            None => self.market_service.get_market_navigation(session).await,
+        }
+    }
+}
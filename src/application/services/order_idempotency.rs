@@ -0,0 +1,182 @@
+use crate::error::AppError;
+use crate::utils::atomic_file::write_atomic;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Default time the stores remember a deal reference
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Registry of the deal references of submitted orders
+///
+/// When an [`OrderServiceImpl`](crate::application::services::order_service::OrderServiceImpl)
+/// has a store, an order carrying a deal reference that was already submitted is first
+/// looked up on `/confirms/{dealReference}`. If IG knows the deal, the order is not sent
+/// again, so retrying after a network failure cannot open a duplicate position.
+pub trait OrderIdempotencyStore: Send + Sync {
+    /// Returns true if an order with this deal reference was already submitted
+    fn contains(&self, deal_reference: &str) -> bool;
+
+    /// Records that an order with this deal reference is being submitted
+    fn record(&self, deal_reference: &str) -> Result<(), AppError>;
+
+    /// Forgets a deal reference, e.g. once its confirmation has been processed
+    fn remove(&self, deal_reference: &str) -> Result<(), AppError>;
+}
+
+/// Deal references with the time they were recorded, as saved by [`FileIdempotencyStore`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredReferences {
+    deal_references: HashMap<String, DateTime<Utc>>,
+}
+
+impl StoredReferences {
+    /// Returns true if `deal_reference` was recorded less than `ttl` ago
+    fn contains(&self, deal_reference: &str, ttl: Duration) -> bool {
+        self.deal_references
+            .get(deal_reference)
+            .is_some_and(|recorded_at| !expired(*recorded_at, ttl))
+    }
+
+    /// Drops the references older than `ttl`, returning true if any was dropped
+    fn prune(&mut self, ttl: Duration) -> bool {
+        let count = self.deal_references.len();
+        self.deal_references
+            .retain(|_, recorded_at| !expired(*recorded_at, ttl));
+        self.deal_references.len() != count
+    }
+}
+
+/// Returns true if a reference recorded at `recorded_at` is older than `ttl`
+fn expired(recorded_at: DateTime<Utc>, ttl: Duration) -> bool {
+    (Utc::now() - recorded_at)
+        .to_std()
+        .is_ok_and(|age| age >= ttl)
+}
+
+/// In-memory registry of deal references, lost when the process exits
+#[derive(Debug)]
+pub struct MemoryIdempotencyStore {
+    references: Mutex<StoredReferences>,
+    ttl: Duration,
+}
+
+impl Default for MemoryIdempotencyStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryIdempotencyStore {
+    /// Creates an empty store remembering references for [`DEFAULT_IDEMPOTENCY_TTL`]
+    pub fn new() -> Self {
+        Self {
+            references: Mutex::new(StoredReferences::default()),
+            ttl: DEFAULT_IDEMPOTENCY_TTL,
+        }
+    }
+
+    /// Forgets the references recorded more than `ttl` ago
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+impl OrderIdempotencyStore for MemoryIdempotencyStore {
+    fn contains(&self, deal_reference: &str) -> bool {
+        self.references
+            .lock()
+            .unwrap()
+            .contains(deal_reference, self.ttl)
+    }
+
+    fn record(&self, deal_reference: &str) -> Result<(), AppError> {
+        let mut references = self.references.lock().unwrap();
+        references.prune(self.ttl);
+        references
+            .deal_references
+            .insert(deal_reference.to_string(), Utc::now());
+        Ok(())
+    }
+
+    fn remove(&self, deal_reference: &str) -> Result<(), AppError> {
+        self.references
+            .lock()
+            .unwrap()
+            .deal_references
+            .remove(deal_reference);
+        Ok(())
+    }
+}
+
+/// Registry of deal references persisted to a JSON file
+///
+/// The file is rewritten on every change so that a restarted process still knows
+/// which orders may already have reached IG. References older than the TTL are dropped
+/// from the file on the next change, so it does not grow without bound.
+#[derive(Debug)]
+pub struct FileIdempotencyStore {
+    path: PathBuf,
+    references: Mutex<StoredReferences>,
+    ttl: Duration,
+}
+
+impl FileIdempotencyStore {
+    /// Opens the store at `path`, starting empty if the file does not exist
+    ///
+    /// References are remembered for [`DEFAULT_IDEMPOTENCY_TTL`].
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let path = path.as_ref().to_path_buf();
+        let references = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&path)?)?
+        } else {
+            StoredReferences::default()
+        };
+        Ok(Self {
+            path,
+            references: Mutex::new(references),
+            ttl: DEFAULT_IDEMPOTENCY_TTL,
+        })
+    }
+
+    /// Forgets the references recorded more than `ttl` ago
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn save(&self, references: &StoredReferences) -> Result<(), AppError> {
+        write_atomic(&self.path, &serde_json::to_vec(references)?)?;
+        Ok(())
+    }
+}
+
+impl OrderIdempotencyStore for FileIdempotencyStore {
+    fn contains(&self, deal_reference: &str) -> bool {
+        self.references
+            .lock()
+            .unwrap()
+            .contains(deal_reference, self.ttl)
+    }
+
+    fn record(&self, deal_reference: &str) -> Result<(), AppError> {
+        let mut references = self.references.lock().unwrap();
+        references.prune(self.ttl);
+        references
+            .deal_references
+            .insert(deal_reference.to_string(), Utc::now());
+        self.save(&references)
+    }
+
+    fn remove(&self, deal_reference: &str) -> Result<(), AppError> {
+        let mut references = self.references.lock().unwrap();
+        if references.deal_references.remove(deal_reference).is_some() {
+            self.save(&references)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,382 @@
+use crate::application::models::account::{Position, WorkingOrder, WorkingOrders};
+use crate::application::models::order::{
+    BulkActionSummary, ClosePositionRequest, ClosePositionResponse, CreateOrderRequest,
+    CreateOrderResponse, DealFilter, OrderConfirmation, PartialCloseResult, PartialCloseSize,
+    UpdatePositionRequest, UpdatePositionResponse,
+};
+use crate::application::models::working_order::{
+    CreateWorkingOrderRequest, CreateWorkingOrderResponse, DeleteWorkingOrderResponse,
+    UpdateWorkingOrderRequest, UpdateWorkingOrderResponse,
+};
+use crate::application::services::OrderService;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::utils::metrics::{increment_counter, record_histogram};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Counter of orders and working orders sent to IG
+pub const ORDERS_SUBMITTED: &str = "ig_orders_submitted_total";
+/// Counter of confirmations with an `ACCEPTED` deal status
+pub const ORDERS_ACCEPTED: &str = "ig_orders_accepted_total";
+/// Counter of confirmations with a `REJECTED` deal status, labelled by `reason`
+pub const ORDERS_REJECTED: &str = "ig_orders_rejected_total";
+/// Counter of positions closed, fully or partially
+pub const POSITIONS_CLOSED: &str = "ig_positions_closed_total";
+/// Counter of order operations that returned an error, labelled by `operation`
+pub const ORDER_ERRORS: &str = "ig_order_errors_total";
+/// Histogram of the seconds between the submission and the confirmation of an order
+pub const CONFIRMATION_LATENCY: &str = "ig_order_confirmation_latency_seconds";
+
+/// Statistics of the deals of an order service at a point in time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderMetricsSnapshot {
+    /// Orders and working orders sent to IG
+    pub submitted: u64,
+    /// Confirmations with an `ACCEPTED` deal status
+    pub accepted: u64,
+    /// Confirmations with a `REJECTED` deal status
+    pub rejected: u64,
+    /// Positions closed, fully or partially
+    pub closed: u64,
+    /// Operations that returned an error
+    pub errors: u64,
+    /// Number of rejections by IG reason code
+    pub rejection_reasons: HashMap<String, u64>,
+    /// Average time between the submission and the confirmation of an order
+    pub average_confirmation_latency: Option<Duration>,
+}
+
+impl OrderMetricsSnapshot {
+    /// Share of confirmed deals that were rejected, `None` before any confirmation
+    pub fn reject_rate(&self) -> Option<f64> {
+        let confirmed = self.accepted + self.rejected;
+        (confirmed > 0).then(|| self.rejected as f64 / confirmed as f64)
+    }
+}
+
+/// Order service wrapper counting submitted, accepted, rejected and closed deals
+///
+/// Statistics are kept in process and read with [`MeteredOrderService::snapshot`].
+/// With the `metrics` feature they are also reported through the `metrics` facade
+/// under the names of the constants of this module.
+///
+/// Accepted and rejected deals are counted from the confirmations of orders submitted
+/// through this service, once per deal reference, which is also when the confirmation
+/// latency is measured. Deals not confirmed within the confirmation timeout of the
+/// wrapped service are forgotten and no longer counted.
+pub struct MeteredOrderService<S: OrderService> {
+    order_service: S,
+    stats: Mutex<OrderMetricsSnapshot>,
+    latency_total: Mutex<(Duration, u32)>,
+    pending: Mutex<HashMap<String, Instant>>,
+}
+
+impl<S: OrderService> MeteredOrderService<S> {
+    /// Wraps `order_service`
+    pub fn new(order_service: S) -> Self {
+        Self {
+            order_service,
+            stats: Mutex::new(OrderMetricsSnapshot::default()),
+            latency_total: Mutex::new((Duration::ZERO, 0)),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the wrapped order service
+    pub fn inner(&self) -> &S {
+        &self.order_service
+    }
+
+    /// Returns the statistics collected so far
+    pub fn snapshot(&self) -> OrderMetricsSnapshot {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Clears the statistics, e.g. at the start of a reporting window
+    pub fn reset(&self) {
+        *self.stats.lock().unwrap() = OrderMetricsSnapshot::default();
+        *self.latency_total.lock().unwrap() = (Duration::ZERO, 0);
+    }
+
+    fn submitted(&self, deal_reference: &str) {
+        self.stats.lock().unwrap().submitted += 1;
+        increment_counter(ORDERS_SUBMITTED, 1, &[]);
+        let timeout = self.order_service.confirmation_timeout();
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, submitted_at| submitted_at.elapsed() <= timeout);
+        pending.insert(deal_reference.to_string(), Instant::now());
+    }
+
+    fn closed(&self, count: u64) {
+        if count > 0 {
+            self.stats.lock().unwrap().closed += count;
+            increment_counter(POSITIONS_CLOSED, count, &[]);
+        }
+    }
+
+    fn errored(&self, operation: &'static str) {
+        self.stats.lock().unwrap().errors += 1;
+        increment_counter(ORDER_ERRORS, 1, &[("operation", operation.to_string())]);
+    }
+
+    fn confirmed(&self, confirmation: &OrderConfirmation) {
+        let Some(submitted_at) = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&confirmation.deal_reference)
+        else {
+            return;
+        };
+        let latency = submitted_at.elapsed();
+        if latency > self.order_service.confirmation_timeout() {
+            return;
+        }
+        record_histogram(CONFIRMATION_LATENCY, latency.as_secs_f64(), &[]);
+        let average = {
+            let mut total = self.latency_total.lock().unwrap();
+            total.0 += latency;
+            total.1 += 1;
+            total.0 / total.1
+        };
+
+        let mut stats = self.stats.lock().unwrap();
+        stats.average_confirmation_latency = Some(average);
+        if confirmation.is_accepted() {
+            stats.accepted += 1;
+            increment_counter(ORDERS_ACCEPTED, 1, &[]);
+        } else {
+            let reason = confirmation
+                .reason
+                .clone()
+                .unwrap_or_else(|| "UNKNOWN".to_string());
+            stats.rejected += 1;
+            *stats.rejection_reasons.entry(reason.clone()).or_insert(0) += 1;
+            increment_counter(ORDERS_REJECTED, 1, &[("reason", reason)]);
+        }
+    }
+
+    fn observe<R>(&self, operation: &'static str, result: &Result<R, AppError>) {
+        if result.is_err() {
+            self.errored(operation);
+        }
+    }
+}
+
+#[async_trait]
+impl<S: OrderService> OrderService for MeteredOrderService<S> {
+    async fn create_order(
+        &self,
+        session: &IgSession,
+        order: &CreateOrderRequest,
+    ) -> Result<CreateOrderResponse, AppError> {
+        let result = self.order_service.create_order(session, order).await;
+        match &result {
+            Ok(response) => self.submitted(&response.deal_reference),
+            Err(_) => self.errored("create_order"),
+        }
+        result
+    }
+
+    fn confirmation_timeout(&self) -> Duration {
+        self.order_service.confirmation_timeout()
+    }
+
+    async fn get_order_confirmation(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+    ) -> Result<OrderConfirmation, AppError> {
+        let result = self
+            .order_service
+            .get_order_confirmation(session, deal_reference)
+            .await;
+        if let Ok(confirmation) = &result {
+            self.confirmed(confirmation);
+        }
+        result
+    }
+
+    async fn await_confirmation(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+        timeout: Duration,
+    ) -> Result<OrderConfirmation, AppError> {
+        let result = self
+            .order_service
+            .await_confirmation(session, deal_reference, timeout)
+            .await;
+        match &result {
+            Ok(confirmation) => self.confirmed(confirmation),
+            Err(_) => self.errored("await_confirmation"),
+        }
+        result
+    }
+
+    async fn update_position(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        update: &UpdatePositionRequest,
+    ) -> Result<UpdatePositionResponse, AppError> {
+        let result = self
+            .order_service
+            .update_position(session, deal_id, update)
+            .await;
+        self.observe("update_position", &result);
+        result
+    }
+
+    async fn set_attached_orders(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        stop_level: Option<f64>,
+        limit_level: Option<f64>,
+    ) -> Result<OrderConfirmation, AppError> {
+        let result = self
+            .order_service
+            .set_attached_orders(session, deal_id, stop_level, limit_level)
+            .await;
+        self.observe("set_attached_orders", &result);
+        result
+    }
+
+    async fn close_position(
+        &self,
+        session: &IgSession,
+        close_request: &ClosePositionRequest,
+    ) -> Result<ClosePositionResponse, AppError> {
+        let result = self
+            .order_service
+            .close_position(session, close_request)
+            .await;
+        match &result {
+            Ok(_) => self.closed(1),
+            Err(_) => self.errored("close_position"),
+        }
+        result
+    }
+
+    async fn partial_close(
+        &self,
+        session: &IgSession,
+        position: &Position,
+        amount: PartialCloseSize,
+    ) -> Result<PartialCloseResult, AppError> {
+        let result = self
+            .order_service
+            .partial_close(session, position, amount)
+            .await;
+        match &result {
+            Ok(close) if close.confirmation.is_accepted() => self.closed(1),
+            Ok(_) => {}
+            Err(_) => self.errored("partial_close"),
+        }
+        result
+    }
+
+    async fn reversal_orders(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<(ClosePositionRequest, CreateOrderRequest), AppError> {
+        self.order_service.reversal_orders(session, deal_id).await
+    }
+
+    async fn close_all_positions(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError> {
+        let result = self
+            .order_service
+            .close_all_positions(session, filter)
+            .await;
+        match &result {
+            Ok(summary) => {
+                self.closed(summary.succeeded().count() as u64);
+                for _ in summary.failed() {
+                    self.errored("close_all_positions");
+                }
+            }
+            Err(_) => self.errored("close_all_positions"),
+        }
+        result
+    }
+
+    async fn cancel_all_working_orders(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError> {
+        let result = self
+            .order_service
+            .cancel_all_working_orders(session, filter)
+            .await;
+        self.observe("cancel_all_working_orders", &result);
+        result
+    }
+
+    async fn get_working_orders(&self, session: &IgSession) -> Result<WorkingOrders, AppError> {
+        self.order_service.get_working_orders(session).await
+    }
+
+    async fn find_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<Option<WorkingOrder>, AppError> {
+        self.order_service
+            .find_working_order(session, deal_id)
+            .await
+    }
+
+    async fn create_working_order(
+        &self,
+        session: &IgSession,
+        order: &CreateWorkingOrderRequest,
+    ) -> Result<CreateWorkingOrderResponse, AppError> {
+        let result = self
+            .order_service
+            .create_working_order(session, order)
+            .await;
+        match &result {
+            Ok(response) => self.submitted(&response.deal_reference),
+            Err(_) => self.errored("create_working_order"),
+        }
+        result
+    }
+
+    async fn update_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        update: &UpdateWorkingOrderRequest,
+    ) -> Result<UpdateWorkingOrderResponse, AppError> {
+        let result = self
+            .order_service
+            .update_working_order(session, deal_id, update)
+            .await;
+        self.observe("update_working_order", &result);
+        result
+    }
+
+    async fn delete_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<DeleteWorkingOrderResponse, AppError> {
+        let result = self
+            .order_service
+            .delete_working_order(session, deal_id)
+            .await;
+        self.observe("delete_working_order", &result);
+        result
+    }
+}
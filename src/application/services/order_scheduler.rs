@@ -0,0 +1,278 @@
+use crate::application::models::order::CreateOrderRequest;
+use crate::application::models::working_order::CreateWorkingOrderRequest;
+use crate::application::services::{MarketService, OrderService};
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::utils::atomic_file::write_atomic;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Condition that makes a scheduled order due
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScheduleTrigger {
+    /// Due from the given time
+    At(DateTime<Utc>),
+    /// Due as soon as the market is `TRADEABLE`, optionally not before a time
+    ///
+    /// Use `not_before` to wait for the next session of a market that is already open.
+    MarketOpen {
+        /// Time before which the market status is not checked
+        not_before: Option<DateTime<Utc>>,
+    },
+}
+
+/// Order held by the scheduler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScheduledRequest {
+    /// Order sent with [`OrderService::create_order`]
+    Order(CreateOrderRequest),
+    /// Working order sent with [`OrderService::create_working_order`]
+    WorkingOrder(CreateWorkingOrderRequest),
+}
+
+impl ScheduledRequest {
+    /// EPIC of the market of the order
+    pub fn epic(&self) -> &str {
+        match self {
+            ScheduledRequest::Order(order) => &order.epic,
+            ScheduledRequest::WorkingOrder(order) => &order.epic,
+        }
+    }
+}
+
+/// Pending order with its activation condition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledOrder {
+    /// Identifier given by the scheduler
+    pub id: String,
+    /// Order to submit
+    pub request: ScheduledRequest,
+    /// Condition that makes the order due
+    pub trigger: ScheduleTrigger,
+    /// Time the order was scheduled
+    pub scheduled_at: DateTime<Utc>,
+}
+
+/// Outcome of the submission of a due order
+#[derive(Debug, Clone)]
+pub struct ScheduledExecution {
+    /// The scheduled order, no longer pending
+    pub order: ScheduledOrder,
+    /// Deal reference returned by IG if the order was sent
+    pub deal_reference: Option<String>,
+    /// Error returned by the submission, if it failed
+    pub error: Option<String>,
+}
+
+/// Pending orders saved by [`OrderScheduler::with_persistence`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredSchedule {
+    next_id: u64,
+    orders: Vec<ScheduledOrder>,
+}
+
+/// Holds orders until a time or the opening of their market, then submits them
+///
+/// Call [`OrderScheduler::run_due`] periodically, or let [`OrderScheduler::run`] poll
+/// until nothing is left. With [`OrderScheduler::with_persistence`] the pending orders
+/// are written to a JSON file on every change, so a restarted process picks them up.
+///
+/// A due order is submitted once: if the submission fails it is dropped and the error
+/// is reported in its [`ScheduledExecution`], as resending it blindly could open a
+/// duplicate position.
+pub struct OrderScheduler<'a, S: OrderService, M: MarketService> {
+    order_service: &'a S,
+    market_service: &'a M,
+    schedule: StoredSchedule,
+    path: Option<PathBuf>,
+}
+
+impl<'a, S: OrderService, M: MarketService> OrderScheduler<'a, S, M> {
+    /// Creates a scheduler submitting orders through `order_service`
+    ///
+    /// `market_service` is used to check the status of markets for
+    /// [`ScheduleTrigger::MarketOpen`].
+    pub fn new(order_service: &'a S, market_service: &'a M) -> Self {
+        Self {
+            order_service,
+            market_service,
+            schedule: StoredSchedule::default(),
+            path: None,
+        }
+    }
+
+    /// Persists pending orders to `path`, loading those left by a previous run
+    pub fn with_persistence(mut self, path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            self.schedule = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            info!(
+                "Loaded {} scheduled orders from {}",
+                self.schedule.orders.len(),
+                path.display()
+            );
+        }
+        self.path = Some(path);
+        Ok(self)
+    }
+
+    /// Returns the orders waiting for their trigger
+    pub fn pending(&self) -> &[ScheduledOrder] {
+        &self.schedule.orders
+    }
+
+    /// Schedules an order
+    ///
+    /// # Returns
+    /// The identifier of the scheduled order
+    pub fn schedule(
+        &mut self,
+        request: ScheduledRequest,
+        trigger: ScheduleTrigger,
+    ) -> Result<String, AppError> {
+        self.schedule.next_id += 1;
+        let id = format!("SCHED-{}", self.schedule.next_id);
+        debug!("Scheduling {} on {} for {:?}", id, request.epic(), trigger);
+        self.schedule.orders.push(ScheduledOrder {
+            id: id.clone(),
+            request,
+            trigger,
+            scheduled_at: Utc::now(),
+        });
+        self.save()?;
+        Ok(id)
+    }
+
+    /// Removes a pending order
+    pub fn cancel(&mut self, id: &str) -> Result<Option<ScheduledOrder>, AppError> {
+        let Some(index) = self.schedule.orders.iter().position(|order| order.id == id) else {
+            return Ok(None);
+        };
+        let order = self.schedule.orders.remove(index);
+        self.save()?;
+        Ok(Some(order))
+    }
+
+    /// Submits the orders that are due now
+    pub async fn run_due(
+        &mut self,
+        session: &IgSession,
+    ) -> Result<Vec<ScheduledExecution>, AppError> {
+        self.run_due_at(session, Utc::now()).await
+    }
+
+    /// Submits the orders that are due at `now`
+    ///
+    /// The status of each market waiting for its opening is fetched once. If it cannot
+    /// be fetched, the orders on that market stay pending.
+    pub async fn run_due_at(
+        &mut self,
+        session: &IgSession,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<ScheduledExecution>, AppError> {
+        let mut tradeable: HashMap<String, bool> = HashMap::new();
+        let mut due = Vec::new();
+        for (index, order) in self.schedule.orders.iter().enumerate() {
+            let is_due = match &order.trigger {
+                ScheduleTrigger::At(time) => *time <= now,
+                ScheduleTrigger::MarketOpen { not_before } => {
+                    if not_before.is_some_and(|time| time > now) {
+                        false
+                    } else {
+                        let epic = order.request.epic();
+                        if !tradeable.contains_key(epic) {
+                            let status =
+                                match self.market_service.get_market_details(session, epic).await {
+                                    Ok(details) => details.snapshot.market_status == "TRADEABLE",
+                                    Err(e) => {
+                                        warn!("Failed to get the status of {}: {}", epic, e);
+                                        false
+                                    }
+                                };
+                            tradeable.insert(epic.to_string(), status);
+                        }
+                        tradeable[epic]
+                    }
+                }
+            };
+            if is_due {
+                due.push(index);
+            }
+        }
+        if due.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut orders = Vec::with_capacity(due.len());
+        for index in due.into_iter().rev() {
+            orders.push(self.schedule.orders.remove(index));
+        }
+        orders.reverse();
+        // Saved before submitting, so that a crash cannot send an order twice
+        self.save()?;
+
+        let mut executions = Vec::with_capacity(orders.len());
+        for order in orders {
+            let result = match &order.request {
+                ScheduledRequest::Order(request) => self
+                    .order_service
+                    .create_order(session, request)
+                    .await
+                    .map(|response| response.deal_reference),
+                ScheduledRequest::WorkingOrder(request) => self
+                    .order_service
+                    .create_working_order(session, request)
+                    .await
+                    .map(|response| response.deal_reference),
+            };
+            let (deal_reference, error) = match result {
+                Ok(reference) => {
+                    info!("Submitted scheduled order {}: {}", order.id, reference);
+                    (Some(reference), None)
+                }
+                Err(e) => {
+                    warn!("Scheduled order {} failed: {}", order.id, e);
+                    (None, Some(e.to_string()))
+                }
+            };
+            executions.push(ScheduledExecution {
+                order,
+                deal_reference,
+                error,
+            });
+        }
+        Ok(executions)
+    }
+
+    /// Polls every `interval` until no order is pending
+    ///
+    /// # Returns
+    /// The executions of every submitted order
+    pub async fn run(
+        &mut self,
+        session: &IgSession,
+        interval: Duration,
+    ) -> Result<Vec<ScheduledExecution>, AppError> {
+        let mut executions = Vec::new();
+        loop {
+            executions.extend(self.run_due(session).await?);
+            if self.schedule.orders.is_empty() {
+                return Ok(executions);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    fn save(&self) -> Result<(), AppError> {
+        if let Some(path) = &self.path {
+            write_atomic(path, &serde_json::to_vec(&self.schedule)?)?;
+        }
+        Ok(())
+    }
+}
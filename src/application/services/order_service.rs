@@ -1,31 +1,216 @@
-use crate::application::models::account::WorkingOrders;
+use crate::application::models::account::{Position, Positions, WorkingOrder, WorkingOrders};
+use crate::application::models::market::MarketDetails;
 use crate::application::models::order::{
-    ClosePositionRequest, ClosePositionResponse, CreateOrderRequest, CreateOrderResponse,
-    OrderConfirmation, UpdatePositionRequest, UpdatePositionResponse,
+    BulkActionSummary, ClosePositionRequest, ClosePositionResponse, CreateOrderRequest,
+    CreateOrderResponse, DealActionResult, DealFilter, OrderConfirmation, OrderType,
+    PartialCloseResult, PartialCloseSize, PositionMode, UpdatePositionRequest,
+    UpdatePositionResponse,
 };
 use crate::application::models::working_order::{
-    CreateWorkingOrderRequest, CreateWorkingOrderResponse,
+    CreateWorkingOrderRequest, CreateWorkingOrderResponse, DeleteWorkingOrderResponse,
+    UpdateWorkingOrderRequest, UpdateWorkingOrderResponse,
 };
 use crate::application::services::interfaces::order::OrderService;
+use crate::application::services::order_idempotency::OrderIdempotencyStore;
 use crate::config::Config;
+use crate::constants::{
+    CONFIRMATION_INITIAL_BACKOFF_MS, CONFIRMATION_MAX_BACKOFF_MS, ORDER_CONFIRMATION_TIMEOUT_MS,
+};
 use crate::error::AppError;
 use crate::session::interface::IgSession;
 use crate::transport::http_client::IgHttpClient;
 use async_trait::async_trait;
 use reqwest::Method;
-use std::sync::Arc;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
 use tracing::{debug, info};
 
+/// Default time the market details used by [`OrderServiceImpl::with_market_defaults`]
+/// are kept before being fetched again
+pub const DEFAULT_MARKET_DETAILS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Market details cached by EPIC, with the time they were fetched
+struct MarketDetailsCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, MarketDetails)>>,
+}
+
 /// Implementation of the order service
 pub struct OrderServiceImpl<T: IgHttpClient> {
     config: Arc<Config>,
     client: Arc<T>,
+    idempotency_store: Option<Arc<dyn OrderIdempotencyStore>>,
+    market_defaults: Option<MarketDetailsCache>,
+    position_mode: Option<PositionMode>,
+    confirmation_timeout: Duration,
 }
 
 impl<T: IgHttpClient> OrderServiceImpl<T> {
     /// Creates a new instance of the order service
     pub fn new(config: Arc<Config>, client: Arc<T>) -> Self {
-        Self { config, client }
+        Self {
+            config,
+            client,
+            idempotency_store: None,
+            market_defaults: None,
+            position_mode: None,
+            confirmation_timeout: Duration::from_millis(ORDER_CONFIRMATION_TIMEOUT_MS),
+        }
+    }
+
+    /// Sets how long to wait for the confirmation of a deal before giving up
+    ///
+    /// Defaults to [`ORDER_CONFIRMATION_TIMEOUT_MS`]; applies to the operations that
+    /// wait for confirmations, such as bulk submissions and partial closes.
+    pub fn with_confirmation_timeout(mut self, timeout: Duration) -> Self {
+        self.confirmation_timeout = timeout;
+        self
+    }
+
+    /// Makes order submission idempotent for orders carrying a deal reference
+    ///
+    /// Before an order whose deal reference is already in `store` is sent again, its
+    /// confirmation is polled for up to the confirmation timeout; if IG knows the deal,
+    /// the existing reference is returned instead of submitting a duplicate. The order
+    /// is only sent again once IG still does not know it after the timeout.
+    pub fn with_idempotency_store(mut self, store: Arc<dyn OrderIdempotencyStore>) -> Self {
+        self.idempotency_store = Some(store);
+        self
+    }
+
+    /// Fills in the currency and expiry missing from orders using the market details
+    ///
+    /// An order with an empty currency gets the default currency of the instrument, and
+    /// an order with an empty or `-` expiry gets the expiry of the instrument. Market
+    /// details are fetched once per EPIC and cached for [`DEFAULT_MARKET_DETAILS_TTL`].
+    pub fn with_market_defaults(self) -> Self {
+        self.with_market_defaults_ttl(DEFAULT_MARKET_DETAILS_TTL)
+    }
+
+    /// Same as [`Self::with_market_defaults`], fetching the market details again once
+    /// they are older than `ttl`, so changes of dealing rules are picked up
+    pub fn with_market_defaults_ttl(mut self, ttl: Duration) -> Self {
+        self.market_defaults = Some(MarketDetailsCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        });
+        self
+    }
+
+    /// Sends orders and closes following the position mode of the account
+    ///
+    /// The `forceOpen` flag of every order is set from `mode` (see
+    /// [`PositionMode::force_open`]) and closes by EPIC are refused on hedging accounts.
+    /// Without a mode, orders are sent as built.
+    pub fn with_position_mode(mut self, mode: PositionMode) -> Self {
+        self.position_mode = Some(mode);
+        self
+    }
+
+    /// Returns the position mode set with [`Self::with_position_mode`]
+    pub fn position_mode(&self) -> Option<PositionMode> {
+        self.position_mode
+    }
+
+    /// Returns the market details of `epic`, from the cache when possible
+    async fn cached_market_details(
+        &self,
+        session: &IgSession,
+        epic: &str,
+    ) -> Result<Option<MarketDetails>, AppError> {
+        let Some(cache) = &self.market_defaults else {
+            return Ok(None);
+        };
+        if let Some((fetched_at, details)) = cache.entries.lock().unwrap().get(epic)
+            && fetched_at.elapsed() < cache.ttl
+        {
+            return Ok(Some(details.clone()));
+        }
+        let path = format!("markets/{epic}");
+        let details = self
+            .client
+            .request::<(), MarketDetails>(Method::GET, &path, session, None, "3")
+            .await?;
+        cache
+            .entries
+            .lock()
+            .unwrap()
+            .insert(epic.to_string(), (Instant::now(), details.clone()));
+        Ok(Some(details))
+    }
+
+    /// Returns `order` with its `forceOpen` set by the position mode and its missing
+    /// currency and expiry resolved
+    async fn resolve_order<'o>(
+        &self,
+        session: &IgSession,
+        order: &'o CreateOrderRequest,
+    ) -> Result<Cow<'o, CreateOrderRequest>, AppError> {
+        let force_open = self
+            .position_mode
+            .map_or(order.force_open, |mode| mode.force_open(order));
+        let order = if force_open == order.force_open {
+            Cow::Borrowed(order)
+        } else {
+            debug!(
+                "Sending order on {} with forceOpen {}",
+                order.epic, force_open
+            );
+            let mut order = order.clone();
+            order.force_open = force_open;
+            Cow::Owned(order)
+        };
+        let missing_expiry = is_missing_expiry(&order.expiry);
+        if !missing_expiry && !order.currency_code.is_empty() {
+            return Ok(order);
+        }
+        let Some(details) = self.cached_market_details(session, &order.epic).await? else {
+            return Ok(order);
+        };
+        let mut order = order.into_owned();
+        if missing_expiry {
+            order.expiry = details.instrument.expiry.clone();
+        }
+        if order.currency_code.is_empty()
+            && let Some(currency) = details.instrument.default_currency()
+        {
+            order.currency_code = currency.code.clone();
+        }
+        debug!(
+            "Resolved order on {} to expiry {} and currency {}",
+            order.epic, order.expiry, order.currency_code
+        );
+        Ok(Cow::Owned(order))
+    }
+
+    /// Returns the working order `order` with its missing currency and expiry resolved
+    async fn resolve_working_order<'o>(
+        &self,
+        session: &IgSession,
+        order: &'o CreateWorkingOrderRequest,
+    ) -> Result<Cow<'o, CreateWorkingOrderRequest>, AppError> {
+        let missing_expiry = is_missing_expiry(&order.expiry);
+        let missing_currency = order.currency_code.as_deref().is_none_or(str::is_empty);
+        if !missing_expiry && !missing_currency {
+            return Ok(Cow::Borrowed(order));
+        }
+        let Some(details) = self.cached_market_details(session, &order.epic).await? else {
+            return Ok(Cow::Borrowed(order));
+        };
+        let mut order = order.clone();
+        if missing_expiry {
+            order.expiry = details.instrument.expiry.clone();
+        }
+        if missing_currency {
+            order.currency_code = details
+                .instrument
+                .default_currency()
+                .map(|currency| currency.code.clone());
+        }
+        Ok(Cow::Owned(order))
     }
 
     /// Gets the current configuration
@@ -54,13 +239,42 @@ impl<T: IgHttpClient + 'static> OrderService for OrderServiceImpl<T> {
     ) -> Result<CreateOrderResponse, AppError> {
         info!("Creating order for: {}", order.epic);
 
+        if let (Some(store), Some(deal_reference)) =
+            (&self.idempotency_store, &order.deal_reference)
+        {
+            if store.contains(deal_reference) {
+                // IG may take a moment to know the deal, so only a confirmation still
+                // missing after the timeout shows that the order never reached it
+                match self
+                    .await_confirmation(session, deal_reference, self.confirmation_timeout)
+                    .await
+                {
+                    Ok(_) => {
+                        info!(
+                            "Order {} was already submitted, not sending it again",
+                            deal_reference
+                        );
+                        return Ok(CreateOrderResponse {
+                            deal_reference: deal_reference.clone(),
+                        });
+                    }
+                    Err(AppError::Timeout(_)) => {
+                        debug!("Order {} never reached IG, resubmitting", deal_reference);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            store.record(deal_reference)?;
+        }
+
+        let order = self.resolve_order(session, order).await?;
         let result = self
             .client
             .request::<CreateOrderRequest, CreateOrderResponse>(
                 Method::POST,
                 "positions/otc",
                 session,
-                Some(order),
+                Some(order.as_ref()),
                 "2",
             )
             .await?;
@@ -69,6 +283,10 @@ impl<T: IgHttpClient + 'static> OrderService for OrderServiceImpl<T> {
         Ok(result)
     }
 
+    fn confirmation_timeout(&self) -> Duration {
+        self.confirmation_timeout
+    }
+
     async fn get_order_confirmation(
         &self,
         session: &IgSession,
@@ -86,6 +304,37 @@ impl<T: IgHttpClient + 'static> OrderService for OrderServiceImpl<T> {
         Ok(result)
     }
 
+    async fn await_confirmation(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+        timeout: Duration,
+    ) -> Result<OrderConfirmation, AppError> {
+        let deadline = Instant::now() + timeout;
+        let mut backoff = Duration::from_millis(CONFIRMATION_INITIAL_BACKOFF_MS);
+
+        loop {
+            match self.get_order_confirmation(session, deal_reference).await {
+                Ok(confirmation) => return Ok(confirmation),
+                Err(AppError::NotFound) => {}
+                Err(e) => return Err(e),
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(AppError::Timeout(format!(
+                    "no confirmation for {deal_reference} after {timeout:?}"
+                )));
+            }
+            debug!(
+                "Confirmation for {} not available yet, retrying in {:?}",
+                deal_reference, backoff
+            );
+            tokio::time::sleep(backoff.min(deadline - now)).await;
+            backoff = (backoff * 2).min(Duration::from_millis(CONFIRMATION_MAX_BACKOFF_MS));
+        }
+    }
+
     async fn update_position(
         &self,
         session: &IgSession,
@@ -113,19 +362,56 @@ impl<T: IgHttpClient + 'static> OrderService for OrderServiceImpl<T> {
         Ok(result)
     }
 
+    async fn set_attached_orders(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        stop_level: Option<f64>,
+        limit_level: Option<f64>,
+    ) -> Result<OrderConfirmation, AppError> {
+        let path = format!("positions/{deal_id}");
+        let position = self
+            .client
+            .request::<(), Position>(Method::GET, &path, session, None, "2")
+            .await?;
+        let update = position.attached_orders_update(stop_level, limit_level)?;
+        info!(
+            "Setting stop {:?} and limit {:?} on position {}",
+            update.stop_level, update.limit_level, deal_id
+        );
+        let response = self.update_position(session, deal_id, &update).await?;
+        self.await_confirmation(session, &response.deal_reference, self.confirmation_timeout)
+            .await
+    }
+
     async fn close_position(
         &self,
         session: &IgSession,
         close_request: &ClosePositionRequest,
     ) -> Result<ClosePositionResponse, AppError> {
+        // A close sent with forceOpen would open an opposite position instead
+        if close_request.force_open {
+            return Err(AppError::InvalidInput(
+                "close requests must not set forceOpen".to_string(),
+            ));
+        }
+        if let Some(mode) = self.position_mode {
+            mode.validate_close(close_request)?;
+        }
+        if close_request.deal_id.is_none() && close_request.epic.is_empty() {
+            return Err(AppError::InvalidInput(
+                "close requests need a deal ID or an EPIC".to_string(),
+            ));
+        }
         info!("{}", serde_json::to_string(close_request)?);
+
+        // IG closes positions with DELETE /positions/otc, sent as POST + `_method: DELETE`
         let result = self
             .client
-            .request::<ClosePositionRequest, ClosePositionResponse>(
-                Method::POST,
+            .request_delete_with_body::<ClosePositionRequest, ClosePositionResponse>(
                 "positions/otc",
                 session,
-                Some(close_request),
+                close_request,
                 "1",
             )
             .await?;
@@ -134,6 +420,137 @@ impl<T: IgHttpClient + 'static> OrderService for OrderServiceImpl<T> {
         Ok(result)
     }
 
+    async fn partial_close(
+        &self,
+        session: &IgSession,
+        position: &Position,
+        amount: PartialCloseSize,
+    ) -> Result<PartialCloseResult, AppError> {
+        let details = &position.position;
+        let path = format!("markets/{}", position.market.epic);
+        let market = self
+            .client
+            .request::<(), MarketDetails>(Method::GET, &path, session, None, "3")
+            .await?;
+        let (closed_size, remaining_size) =
+            amount.resolve(details.size, market.dealing_rules.min_deal_size.value)?;
+        info!(
+            "Closing {} of {} on position {}",
+            closed_size, details.size, details.deal_id
+        );
+
+        let close = ClosePositionRequest::for_position(position).with_size(closed_size);
+        let response = self.close_position(session, &close).await?;
+        let confirmation = self
+            .await_confirmation(session, &response.deal_reference, self.confirmation_timeout)
+            .await?;
+
+        Ok(PartialCloseResult {
+            confirmation,
+            closed_size,
+            remaining_size,
+        })
+    }
+
+    async fn reversal_orders(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<(ClosePositionRequest, CreateOrderRequest), AppError> {
+        let path = format!("positions/{deal_id}");
+        let position = self
+            .client
+            .request::<(), Position>(Method::GET, &path, session, None, "2")
+            .await?;
+        let details = &position.position;
+        let mut order = CreateOrderRequest::market(
+            position.market.epic.clone(),
+            details.direction.opposite(),
+            details.size,
+            details.currency.clone(),
+        );
+        order.expiry = position.market.expiry.clone();
+        Ok((ClosePositionRequest::for_position(&position), order))
+    }
+
+    async fn close_all_positions(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError> {
+        let positions = self
+            .client
+            .request::<(), Positions>(Method::GET, "positions", session, None, "2")
+            .await?;
+        let selected: Vec<&Position> = positions
+            .positions
+            .iter()
+            .filter(|p| filter.matches(&p.market.epic, &p.position.direction))
+            .collect();
+        info!(
+            "Closing {} of {} positions",
+            selected.len(),
+            positions.positions.len()
+        );
+
+        let mut summary = BulkActionSummary::default();
+        for position in selected {
+            let details = &position.position;
+            let close = ClosePositionRequest::for_position(position);
+            let result = self.close_position(session, &close).await;
+            summary.results.push(DealActionResult {
+                deal_id: details.deal_id.clone(),
+                epic: position.market.epic.clone(),
+                deal_reference: result.as_ref().ok().map(|r| r.deal_reference.clone()),
+                error: result.err().map(|e| e.to_string()),
+            });
+        }
+
+        debug!(
+            "Close-all done: {} closed, {} failed",
+            summary.succeeded().count(),
+            summary.failed().count()
+        );
+        Ok(summary)
+    }
+
+    async fn cancel_all_working_orders(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError> {
+        let orders = self.get_working_orders(session).await?;
+        let selected: Vec<&WorkingOrder> = orders
+            .working_orders
+            .iter()
+            .filter(|o| filter.matches(&o.working_order_data.epic, &o.working_order_data.direction))
+            .collect();
+        info!(
+            "Deleting {} of {} working orders",
+            selected.len(),
+            orders.working_orders.len()
+        );
+
+        let mut summary = BulkActionSummary::default();
+        for order in selected {
+            let data = &order.working_order_data;
+            let result = self.delete_working_order(session, &data.deal_id).await;
+            summary.results.push(DealActionResult {
+                deal_id: data.deal_id.clone(),
+                epic: data.epic.clone(),
+                deal_reference: result.as_ref().ok().map(|r| r.deal_reference.clone()),
+                error: result.err().map(|e| e.to_string()),
+            });
+        }
+
+        debug!(
+            "Cancel-all done: {} deleted, {} failed",
+            summary.succeeded().count(),
+            summary.failed().count()
+        );
+        Ok(summary)
+    }
+
     async fn get_working_orders(&self, session: &IgSession) -> Result<WorkingOrders, AppError> {
         info!("Getting all working orders");
 
@@ -146,20 +563,49 @@ impl<T: IgHttpClient + 'static> OrderService for OrderServiceImpl<T> {
         Ok(result)
     }
 
+    async fn find_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<Option<WorkingOrder>, AppError> {
+        let orders = self.get_working_orders(session).await?;
+        let order = orders.find(deal_id).cloned();
+
+        debug!(
+            "Working order {} {}",
+            deal_id,
+            if order.is_some() {
+                "found"
+            } else {
+                "not found"
+            }
+        );
+        Ok(order)
+    }
+
     async fn create_working_order(
         &self,
         session: &IgSession,
         order: &CreateWorkingOrderRequest,
     ) -> Result<CreateWorkingOrderResponse, AppError> {
         info!("Creating working order for: {}", order.epic);
+        order.validate_levels()?;
+        // The v2 working order schema of IG only has LIMIT and STOP orders
+        if order.order_type == OrderType::StopLimit {
+            return Err(AppError::InvalidInput(
+                "stop-limit working orders are only simulated by the paper order service"
+                    .to_string(),
+            ));
+        }
 
+        let order = self.resolve_working_order(session, order).await?;
         let result = self
             .client
             .request::<CreateWorkingOrderRequest, CreateWorkingOrderResponse>(
                 Method::POST,
                 "workingorders/otc",
                 session,
-                Some(order),
+                Some(order.as_ref()),
                 "2",
             )
             .await?;
@@ -170,6 +616,58 @@ impl<T: IgHttpClient + 'static> OrderService for OrderServiceImpl<T> {
         );
         Ok(result)
     }
+
+    async fn update_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        update: &UpdateWorkingOrderRequest,
+    ) -> Result<UpdateWorkingOrderResponse, AppError> {
+        let path = format!("workingorders/otc/{deal_id}");
+        info!("Updating working order: {}", deal_id);
+
+        let result = self
+            .client
+            .request::<UpdateWorkingOrderRequest, UpdateWorkingOrderResponse>(
+                Method::PUT,
+                &path,
+                session,
+                Some(update),
+                "2",
+            )
+            .await?;
+
+        debug!(
+            "Working order updated: {} with deal reference: {}",
+            deal_id, result.deal_reference
+        );
+        Ok(result)
+    }
+
+    async fn delete_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<DeleteWorkingOrderResponse, AppError> {
+        let path = format!("workingorders/otc/{deal_id}");
+        info!("Deleting working order: {}", deal_id);
+
+        let result = self
+            .client
+            .request::<(), DeleteWorkingOrderResponse>(Method::DELETE, &path, session, None, "2")
+            .await?;
+
+        debug!(
+            "Working order {} deleted with reference: {}",
+            deal_id, result.deal_reference
+        );
+        Ok(result)
+    }
+}
+
+/// Returns true if an order expiry must be taken from the instrument
+fn is_missing_expiry(expiry: &str) -> bool {
+    expiry.is_empty() || expiry == "-"
 }
 
 #[cfg(test)]
@@ -0,0 +1,286 @@
+use crate::application::models::order::{OrderConfirmation, Status};
+use crate::presentation::trade::{OpenPositionUpdate, TradeFields, WorkingOrderUpdate};
+use std::collections::HashMap;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, warn};
+
+/// Stage of the lifecycle of an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderState {
+    /// Sent to IG, waiting for its confirmation
+    Submitted,
+    /// Confirmed as accepted, not yet seen as a position or working order
+    Accepted,
+    /// Resting as a working order
+    Working,
+    /// Filled, the position is open
+    Filled,
+    /// Rejected by IG
+    Rejected,
+    /// The working order was deleted or expired without being filled
+    Cancelled,
+    /// The position was closed
+    Closed,
+}
+
+impl OrderState {
+    /// Returns true if no further transition is expected
+    ///
+    /// `Cancelled` is not terminal: the fill of a working order may be reported after
+    /// its deletion.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, OrderState::Rejected | OrderState::Closed)
+    }
+
+    /// Progress of the state; updates never move an order back
+    ///
+    /// Stream updates and confirmations can arrive in any order, so a late
+    /// confirmation does not undo a fill, and the deletion of a filled working order
+    /// does not cancel it.
+    fn rank(&self) -> u8 {
+        match self {
+            OrderState::Submitted => 0,
+            OrderState::Accepted => 1,
+            OrderState::Working => 2,
+            OrderState::Cancelled => 3,
+            OrderState::Filled => 4,
+            OrderState::Rejected | OrderState::Closed => 5,
+        }
+    }
+}
+
+/// Everything known about one order, from submission to close
+#[derive(Debug, Clone)]
+pub struct OrderLifecycle {
+    /// Deal reference returned on submission
+    pub deal_reference: String,
+    /// Deal ID of the working order or position, once known
+    pub deal_id: Option<String>,
+    /// EPIC of the market, once known
+    pub epic: Option<String>,
+    /// Current state
+    pub state: OrderState,
+    /// Every state the order went through, in order
+    pub history: Vec<OrderState>,
+    /// Deal confirmation, once received
+    pub confirmation: Option<OrderConfirmation>,
+}
+
+/// Change of state of a tracked order
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderEvent {
+    /// Deal reference of the order
+    pub deal_reference: String,
+    /// Deal ID of the working order or position, if known
+    pub deal_id: Option<String>,
+    /// State before the change
+    pub previous: OrderState,
+    /// State after the change
+    pub state: OrderState,
+}
+
+/// Correlates submissions, confirmations and trade stream updates of orders
+///
+/// Orders are registered with [`OrderTracker::submitted`] using the deal reference of
+/// `CreateOrderResponse`. Confirmations and `OPU`/`WOU` updates of the trade stream are
+/// then matched by deal reference, or by deal ID for updates that only carry it, such
+/// as the position opened when a working order fills (`dealIdOrigin`).
+///
+/// Every state change is returned to the caller and, with
+/// [`OrderTracker::with_sender`], pushed to a channel that can be consumed as a stream.
+#[derive(Debug, Default)]
+pub struct OrderTracker {
+    orders: HashMap<String, OrderLifecycle>,
+    deal_ids: HashMap<String, String>,
+    sender: Option<Sender<OrderEvent>>,
+}
+
+impl OrderTracker {
+    /// Creates an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pushes every state change to `sender`
+    ///
+    /// Events are dropped with a warning if the channel is full or closed, so that the
+    /// trade stream is never blocked by a slow consumer.
+    pub fn with_sender(mut self, sender: Sender<OrderEvent>) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    /// Returns the lifecycle of an order by deal reference
+    pub fn get(&self, deal_reference: &str) -> Option<&OrderLifecycle> {
+        self.orders.get(deal_reference)
+    }
+
+    /// Returns the lifecycle of an order by the deal ID of its working order or position
+    pub fn get_by_deal_id(&self, deal_id: &str) -> Option<&OrderLifecycle> {
+        self.orders.get(self.deal_ids.get(deal_id)?)
+    }
+
+    /// Returns every tracked order
+    pub fn orders(&self) -> impl Iterator<Item = &OrderLifecycle> {
+        self.orders.values()
+    }
+
+    /// Stops tracking orders that reached a terminal state
+    pub fn prune_terminal(&mut self) {
+        self.orders.retain(|_, order| !order.state.is_terminal());
+        let orders = &self.orders;
+        self.deal_ids
+            .retain(|_, reference| orders.contains_key(reference));
+    }
+
+    /// Registers a submitted order
+    ///
+    /// The registration is pushed to the channel as an event from and to `Submitted`.
+    pub fn submitted(&mut self, deal_reference: &str, epic: Option<&str>) {
+        let order = OrderLifecycle {
+            deal_reference: deal_reference.to_string(),
+            deal_id: None,
+            epic: epic.map(str::to_string),
+            state: OrderState::Submitted,
+            history: vec![OrderState::Submitted],
+            confirmation: None,
+        };
+        self.orders.insert(deal_reference.to_string(), order);
+        let event = OrderEvent {
+            deal_reference: deal_reference.to_string(),
+            deal_id: None,
+            previous: OrderState::Submitted,
+            state: OrderState::Submitted,
+        };
+        self.emit(&event);
+    }
+
+    /// Applies a deal confirmation, from `/confirms` or the `CONFIRMS` stream field
+    pub fn on_confirmation(&mut self, confirmation: &OrderConfirmation) -> Option<OrderEvent> {
+        let reference = confirmation.deal_reference.clone();
+        let order = self.orders.get_mut(&reference)?;
+        order.confirmation = Some(confirmation.clone());
+        if order.epic.is_none() {
+            order.epic = confirmation.epic.clone();
+        }
+        if let Some(deal_id) = &confirmation.deal_id {
+            order.deal_id = Some(deal_id.clone());
+            self.deal_ids.insert(deal_id.clone(), reference.clone());
+        }
+        let state = if confirmation.is_accepted() {
+            OrderState::Accepted
+        } else {
+            OrderState::Rejected
+        };
+        self.transition(&reference, state)
+    }
+
+    /// Applies an update of the trade stream
+    ///
+    /// # Returns
+    /// The state changes caused by the confirmation, position and working order parts
+    pub fn on_trade_update(&mut self, fields: &TradeFields) -> Vec<OrderEvent> {
+        let mut events = Vec::new();
+        if let Some(confirmation) = fields.confirmation() {
+            events.extend(self.on_confirmation(&confirmation));
+        }
+        if let Some(wou) = &fields.wou {
+            events.extend(self.on_working_order_update(wou));
+        }
+        if let Some(opu) = &fields.opu {
+            events.extend(self.on_position_update(opu));
+        }
+        events
+    }
+
+    /// Applies a working order update (`WOU`)
+    pub fn on_working_order_update(&mut self, update: &WorkingOrderUpdate) -> Option<OrderEvent> {
+        let reference = self.correlate(update.deal_reference.as_deref(), &[&update.deal_id])?;
+        self.link_deal_id(&reference, update.deal_id.as_deref());
+        let state = match update.status {
+            Some(Status::Deleted) | Some(Status::Cancelled) | Some(Status::Expired) => {
+                OrderState::Cancelled
+            }
+            _ => OrderState::Working,
+        };
+        self.transition(&reference, state)
+    }
+
+    /// Applies an open position update (`OPU`)
+    pub fn on_position_update(&mut self, update: &OpenPositionUpdate) -> Option<OrderEvent> {
+        let reference = self.correlate(
+            update.deal_reference.as_deref(),
+            &[&update.deal_id, &update.deal_id_origin],
+        )?;
+        self.link_deal_id(&reference, update.deal_id.as_deref());
+        let state = match update.status {
+            Some(Status::Deleted) | Some(Status::Closed) | Some(Status::FullyClosed) => {
+                OrderState::Closed
+            }
+            _ => OrderState::Filled,
+        };
+        self.transition(&reference, state)
+    }
+
+    /// Finds the tracked order an update belongs to
+    fn correlate(
+        &self,
+        deal_reference: Option<&str>,
+        deal_ids: &[&Option<String>],
+    ) -> Option<String> {
+        if let Some(reference) = deal_reference
+            && self.orders.contains_key(reference)
+        {
+            return Some(reference.to_string());
+        }
+        deal_ids
+            .iter()
+            .filter_map(|deal_id| deal_id.as_deref())
+            .find_map(|deal_id| self.deal_ids.get(deal_id).cloned())
+    }
+
+    fn link_deal_id(&mut self, reference: &str, deal_id: Option<&str>) {
+        if let (Some(deal_id), Some(order)) = (deal_id, self.orders.get_mut(reference)) {
+            order.deal_id = Some(deal_id.to_string());
+            self.deal_ids
+                .insert(deal_id.to_string(), reference.to_string());
+        }
+    }
+
+    fn transition(&mut self, reference: &str, state: OrderState) -> Option<OrderEvent> {
+        let order = self.orders.get_mut(reference)?;
+        let previous = order.state;
+        if previous == state {
+            return None;
+        }
+        if state.rank() <= previous.rank() {
+            debug!(
+                "Ignoring {:?} for order {} already {:?}",
+                state, reference, previous
+            );
+            return None;
+        }
+        order.state = state;
+        order.history.push(state);
+        let event = OrderEvent {
+            deal_reference: reference.to_string(),
+            deal_id: order.deal_id.clone(),
+            previous,
+            state,
+        };
+        debug!(
+            "Order {} moved from {:?} to {:?}",
+            reference, previous, state
+        );
+        self.emit(&event);
+        Some(event)
+    }
+
+    fn emit(&self, event: &OrderEvent) {
+        if let Some(sender) = &self.sender
+            && let Err(e) = sender.try_send(event.clone())
+        {
+            warn!("Dropping order event for {}: {}", event.deal_reference, e);
+        }
+    }
+}
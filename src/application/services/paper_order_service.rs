@@ -0,0 +1,834 @@
+use crate::application::models::account::{
+    MarketData, Position, WorkingOrder, WorkingOrderData, WorkingOrders,
+};
+use crate::application::models::order::{
+    BulkActionSummary, ClosePositionRequest, ClosePositionResponse, CreateOrderRequest,
+    CreateOrderResponse, DealActionResult, DealFilter, Direction, OrderConfirmation, OrderType,
+    PartialCloseResult, PartialCloseSize, PositionMode, Status, UpdatePositionRequest,
+    UpdatePositionResponse,
+};
+use crate::application::models::working_order::{
+    CreateWorkingOrderRequest, CreateWorkingOrderResponse, DeleteWorkingOrderResponse,
+    UpdateWorkingOrderRequest, UpdateWorkingOrderResponse,
+};
+use crate::application::services::{MarketService, OrderService};
+use crate::error::AppError;
+use crate::presentation::{InstrumentType, MarketState};
+use crate::session::interface::IgSession;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Position opened by [`PaperOrderService`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaperPosition {
+    /// Simulated deal ID
+    pub deal_id: String,
+    /// Deal reference of the opening order
+    pub deal_reference: String,
+    /// EPIC of the market
+    pub epic: String,
+    /// Direction of the position
+    pub direction: Direction,
+    /// Open size
+    pub size: f64,
+    /// Opening level
+    pub level: f64,
+    /// Stop loss level
+    pub stop_level: Option<f64>,
+    /// Take profit level
+    pub limit_level: Option<f64>,
+    /// Currency of the position
+    pub currency: String,
+}
+
+impl PaperPosition {
+    /// Profit or loss per unit of size when closed at `price`
+    fn pnl_per_unit(&self, price: f64) -> f64 {
+        match self.direction {
+            Direction::Buy => price - self.level,
+            Direction::Sell => self.level - price,
+        }
+    }
+}
+
+/// Everything simulated by a [`PaperOrderService`]
+#[derive(Debug, Default)]
+struct PaperBook {
+    quotes: HashMap<String, (f64, f64)>,
+    streamed: HashSet<String>,
+    positions: Vec<PaperPosition>,
+    working_orders: Vec<WorkingOrder>,
+    stop_limits: HashMap<String, f64>,
+    confirmations: HashMap<String, OrderConfirmation>,
+    realized_pnl: f64,
+    next_id: u64,
+}
+
+impl PaperBook {
+    fn next_deal_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("PAPER{:010}", self.next_id)
+    }
+
+    fn confirm(&mut self, confirmation: OrderConfirmation) -> OrderConfirmation {
+        self.confirmations
+            .insert(confirmation.deal_reference.clone(), confirmation.clone());
+        confirmation
+    }
+
+    /// Fills `order` at `price`, netting opposite positions unless it forces a new one
+    fn execute(&mut self, order: &CreateOrderRequest, price: f64) -> OrderConfirmation {
+        let deal_reference = order.deal_reference.clone().unwrap_or_else(new_reference);
+        let mut remaining = order.size;
+        if !order.force_open {
+            let opposite: Vec<String> = self
+                .positions
+                .iter()
+                .filter(|position| {
+                    position.epic == order.epic && position.direction != order.direction
+                })
+                .map(|position| position.deal_id.clone())
+                .collect();
+            for deal_id in opposite {
+                if remaining <= 0.0 {
+                    break;
+                }
+                remaining -= self.close(&deal_id, remaining, price);
+            }
+        }
+
+        let mut deal_id = None;
+        let status = if remaining > 1e-9 {
+            let id = self.next_deal_id();
+            let (stop_level, limit_level) = attached_levels(&order.direction, price, order);
+            debug!("Paper position {} opened at {}", id, price);
+            self.positions.push(PaperPosition {
+                deal_id: id.clone(),
+                deal_reference: deal_reference.clone(),
+                epic: order.epic.clone(),
+                direction: order.direction.clone(),
+                size: remaining,
+                level: price,
+                stop_level,
+                limit_level,
+                currency: order.currency_code.clone(),
+            });
+            deal_id = Some(id);
+            Status::Open
+        } else {
+            Status::Closed
+        };
+
+        let mut confirmation = confirmation(&deal_reference, &order.epic, Some(price), true);
+        confirmation.status = status;
+        confirmation.deal_id = deal_id;
+        confirmation.size = Some(order.size);
+        confirmation.direction = Some(order.direction.clone());
+        self.confirm(confirmation)
+    }
+
+    /// Closes up to `size` of a position at `price`
+    ///
+    /// # Returns
+    /// The size actually closed
+    fn close(&mut self, deal_id: &str, size: f64, price: f64) -> f64 {
+        let Some(index) = self.positions.iter().position(|p| p.deal_id == deal_id) else {
+            return 0.0;
+        };
+        let position = &mut self.positions[index];
+        let closed = size.min(position.size);
+        self.realized_pnl += position.pnl_per_unit(price) * closed;
+        position.size -= closed;
+        debug!("Paper position {} closed {} at {}", deal_id, closed, price);
+        if position.size <= 1e-9 {
+            self.positions.remove(index);
+        }
+        closed
+    }
+
+    /// Applies a quote to the working orders and the stops and limits of positions
+    fn on_price(&mut self, epic: &str, bid: f64, offer: f64) -> Vec<OrderConfirmation> {
+        self.quotes.insert(epic.to_string(), (bid, offer));
+        self.streamed.insert(epic.to_string());
+        let mut confirmations = Vec::new();
+
+        let (triggered, waiting): (Vec<WorkingOrder>, Vec<WorkingOrder>) =
+            std::mem::take(&mut self.working_orders)
+                .into_iter()
+                .partition(|order| {
+                    let data = &order.working_order_data;
+                    data.epic == epic && is_triggered(data, bid, offer)
+                });
+        self.working_orders = waiting;
+        for mut order in triggered {
+            let price = entry_price(&order.working_order_data.direction, bid, offer);
+            if let Some(limit) = self.stop_limits.remove(&order.working_order_data.deal_id) {
+                let data = &mut order.working_order_data;
+                let fillable = match data.direction {
+                    Direction::Buy => price <= limit,
+                    Direction::Sell => price >= limit,
+                };
+                if !fillable {
+                    // The triggered stop-limit now rests as a limit order at its limit price
+                    debug!("Paper stop-limit {} rests at {}", data.deal_id, limit);
+                    data.order_type = OrderType::Limit;
+                    data.order_level = limit;
+                    self.working_orders.push(order);
+                    continue;
+                }
+            }
+            let data = order.working_order_data;
+            info!("Paper working order {} triggered", data.deal_id);
+            let mut request = CreateOrderRequest::market(
+                data.epic.clone(),
+                data.direction.clone(),
+                data.order_size,
+                data.currency_code.clone(),
+            );
+            request.stop_level = data.stop_level;
+            request.stop_distance = data.stop_distance;
+            request.limit_level = data.limit_level;
+            request.limit_distance = data.limit_distance;
+            confirmations.push(self.execute(&request, price));
+        }
+
+        let hit: Vec<(String, f64)> = self
+            .positions
+            .iter()
+            .filter(|position| position.epic == epic)
+            .filter_map(|position| {
+                let price = entry_price(&position.direction.opposite(), bid, offer);
+                let (stop_hit, limit_hit) = match position.direction {
+                    Direction::Buy => (
+                        position.stop_level.is_some_and(|stop| price <= stop),
+                        position.limit_level.is_some_and(|limit| price >= limit),
+                    ),
+                    Direction::Sell => (
+                        position.stop_level.is_some_and(|stop| price >= stop),
+                        position.limit_level.is_some_and(|limit| price <= limit),
+                    ),
+                };
+                (stop_hit || limit_hit).then(|| (position.deal_id.clone(), price))
+            })
+            .collect();
+        for (deal_id, price) in hit {
+            info!(
+                "Paper position {} hit its stop or limit at {}",
+                deal_id, price
+            );
+            confirmations.push(self.close_confirmed(&deal_id, None, price));
+        }
+        confirmations
+    }
+
+    /// Closes a position, or `size` of it, and confirms the close
+    fn close_confirmed(
+        &mut self,
+        deal_id: &str,
+        size: Option<f64>,
+        price: f64,
+    ) -> OrderConfirmation {
+        let position = self
+            .positions
+            .iter()
+            .find(|position| position.deal_id == deal_id)
+            .cloned();
+        let Some(position) = position else {
+            return confirmation(&new_reference(), "", None, false);
+        };
+        let closed = self.close(deal_id, size.unwrap_or(position.size), price);
+        let mut confirmation = confirmation(&new_reference(), &position.epic, Some(price), true);
+        confirmation.status = if closed < position.size {
+            Status::PartiallyClosed
+        } else {
+            Status::FullyClosed
+        };
+        confirmation.deal_id = Some(position.deal_id);
+        confirmation.size = Some(closed);
+        confirmation.direction = Some(position.direction.opposite());
+        self.confirm(confirmation)
+    }
+}
+
+/// Order service simulating fills locally, for strategies tested with no live risk
+///
+/// Market orders fill at the offer when buying and at the bid when selling, so the
+/// spread is paid as it would be on IG. Prices come from [`PaperOrderService::on_price`],
+/// typically fed by the price stream, or otherwise from the market snapshot returned by
+/// `market_service`. Working orders and the stops and limits of positions are only
+/// triggered by `on_price`.
+///
+/// Orders net opposite positions on the same EPIC unless `force_open` is set, or the
+/// position mode set with [`PaperOrderService::with_position_mode`] forces it, and every
+/// order, close or amendment gets a confirmation available from
+/// [`OrderService::get_order_confirmation`]. Nothing is ever sent to IG.
+pub struct PaperOrderService<M: MarketService> {
+    market_service: M,
+    book: Mutex<PaperBook>,
+    position_mode: Option<PositionMode>,
+}
+
+impl<M: MarketService> PaperOrderService<M> {
+    /// Creates an empty paper account reading snapshots from `market_service`
+    pub fn new(market_service: M) -> Self {
+        Self {
+            market_service,
+            book: Mutex::new(PaperBook::default()),
+            position_mode: None,
+        }
+    }
+
+    /// Simulates an account netting or hedging positions
+    ///
+    /// The `forceOpen` flag of orders is set from `mode` as
+    /// [`OrderServiceImpl`](crate::application::services::order_service::OrderServiceImpl)
+    /// does, and closes by EPIC are refused in hedging mode.
+    pub fn with_position_mode(mut self, mode: PositionMode) -> Self {
+        self.position_mode = Some(mode);
+        self
+    }
+
+    /// Applies a streamed quote
+    ///
+    /// Triggered working orders are filled and positions whose stop or limit is reached
+    /// are closed.
+    ///
+    /// # Returns
+    /// The confirmations of the fills and closes
+    pub fn on_price(&self, epic: &str, bid: f64, offer: f64) -> Vec<OrderConfirmation> {
+        self.book.lock().unwrap().on_price(epic, bid, offer)
+    }
+
+    /// Returns the simulated open positions
+    pub fn positions(&self) -> Vec<PaperPosition> {
+        self.book.lock().unwrap().positions.clone()
+    }
+
+    /// Returns the profit or loss realized by closed positions
+    pub fn realized_pnl(&self) -> f64 {
+        self.book.lock().unwrap().realized_pnl
+    }
+
+    /// Returns the bid and offer last used for `epic`
+    pub fn quote(&self, epic: &str) -> Option<(f64, f64)> {
+        self.book.lock().unwrap().quotes.get(epic).copied()
+    }
+
+    /// Returns the bid and offer of `epic`
+    ///
+    /// The last streamed quote is used once `on_price` has been called for `epic`; until
+    /// then the snapshot is fetched again for every order rather than reusing a stale one.
+    async fn current_quote(&self, session: &IgSession, epic: &str) -> Result<(f64, f64), AppError> {
+        {
+            let book = self.book.lock().unwrap();
+            if book.streamed.contains(epic)
+                && let Some(quote) = book.quotes.get(epic)
+            {
+                return Ok(*quote);
+            }
+        }
+        let details = self
+            .market_service
+            .get_market_details(session, epic)
+            .await?;
+        match (details.snapshot.bid, details.snapshot.offer) {
+            (Some(bid), Some(offer)) => {
+                self.book
+                    .lock()
+                    .unwrap()
+                    .quotes
+                    .insert(epic.to_string(), (bid, offer));
+                Ok((bid, offer))
+            }
+            _ => Err(AppError::InvalidInput(format!(
+                "no price available for {epic}"
+            ))),
+        }
+    }
+
+    fn find_position(&self, close_request: &ClosePositionRequest) -> Option<PaperPosition> {
+        let book = self.book.lock().unwrap();
+        book.positions
+            .iter()
+            .find(|position| match &close_request.deal_id {
+                Some(deal_id) => position.deal_id == *deal_id,
+                None => {
+                    position.epic == close_request.epic
+                        && position.direction == close_request.direction.opposite()
+                }
+            })
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl<M: MarketService> OrderService for PaperOrderService<M> {
+    async fn create_order(
+        &self,
+        session: &IgSession,
+        order: &CreateOrderRequest,
+    ) -> Result<CreateOrderResponse, AppError> {
+        let (bid, offer) = self.current_quote(session, &order.epic).await?;
+        let price = entry_price(&order.direction, bid, offer);
+        let mut book = self.book.lock().unwrap();
+        let limit_missed = order.order_type == OrderType::Limit
+            && order.level.is_some_and(|level| match order.direction {
+                Direction::Buy => price > level,
+                Direction::Sell => price < level,
+            });
+        let confirmation = if limit_missed {
+            let deal_reference = order.deal_reference.clone().unwrap_or_else(new_reference);
+            book.confirm(confirmation(
+                &deal_reference,
+                &order.epic,
+                order.level,
+                false,
+            ))
+        } else {
+            match self.position_mode {
+                Some(mode) => {
+                    let mut order = order.clone();
+                    order.force_open = mode.force_open(&order);
+                    book.execute(&order, price)
+                }
+                None => book.execute(order, price),
+            }
+        };
+        info!(
+            "Paper order {} on {}: {:?}",
+            confirmation.deal_reference, order.epic, confirmation.deal_status
+        );
+        Ok(CreateOrderResponse {
+            deal_reference: confirmation.deal_reference,
+        })
+    }
+
+    async fn get_order_confirmation(
+        &self,
+        _session: &IgSession,
+        deal_reference: &str,
+    ) -> Result<OrderConfirmation, AppError> {
+        self.book
+            .lock()
+            .unwrap()
+            .confirmations
+            .get(deal_reference)
+            .cloned()
+            .ok_or(AppError::NotFound)
+    }
+
+    async fn await_confirmation(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+        _timeout: Duration,
+    ) -> Result<OrderConfirmation, AppError> {
+        // Paper deals are confirmed as soon as they are submitted
+        self.get_order_confirmation(session, deal_reference).await
+    }
+
+    async fn update_position(
+        &self,
+        _session: &IgSession,
+        deal_id: &str,
+        update: &UpdatePositionRequest,
+    ) -> Result<UpdatePositionResponse, AppError> {
+        let mut book = self.book.lock().unwrap();
+        let position = book
+            .positions
+            .iter_mut()
+            .find(|position| position.deal_id == deal_id)
+            .ok_or(AppError::NotFound)?;
+        position.stop_level = update.stop_level;
+        position.limit_level = update.limit_level;
+        let mut confirmation = confirmation(&new_reference(), &position.epic, None, true);
+        confirmation.status = Status::Amended;
+        confirmation.deal_id = Some(deal_id.to_string());
+        confirmation.stop_level = update.stop_level;
+        confirmation.limit_level = update.limit_level;
+        let confirmation = book.confirm(confirmation);
+        Ok(UpdatePositionResponse {
+            deal_reference: confirmation.deal_reference,
+        })
+    }
+
+    async fn set_attached_orders(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        stop_level: Option<f64>,
+        limit_level: Option<f64>,
+    ) -> Result<OrderConfirmation, AppError> {
+        let position = self
+            .positions()
+            .into_iter()
+            .find(|position| position.deal_id == deal_id)
+            .ok_or(AppError::NotFound)?;
+        let (bid, offer) = self.current_quote(session, &position.epic).await?;
+        let update = UpdatePositionRequest::levels(
+            stop_level.or(position.stop_level),
+            limit_level.or(position.limit_level),
+        );
+        let closing_price = entry_price(&position.direction.opposite(), bid, offer);
+        update.validate_levels(&position.direction, closing_price)?;
+        let response = self.update_position(session, deal_id, &update).await?;
+        self.get_order_confirmation(session, &response.deal_reference)
+            .await
+    }
+
+    async fn close_position(
+        &self,
+        session: &IgSession,
+        close_request: &ClosePositionRequest,
+    ) -> Result<ClosePositionResponse, AppError> {
+        if close_request.force_open {
+            return Err(AppError::InvalidInput(
+                "close requests must not set forceOpen".to_string(),
+            ));
+        }
+        if let Some(mode) = self.position_mode {
+            mode.validate_close(close_request)?;
+        }
+        let position = self
+            .find_position(close_request)
+            .ok_or(AppError::NotFound)?;
+        let (bid, offer) = self.current_quote(session, &position.epic).await?;
+        let price = entry_price(&close_request.direction, bid, offer);
+        let confirmation = self.book.lock().unwrap().close_confirmed(
+            &position.deal_id,
+            Some(close_request.size),
+            price,
+        );
+        Ok(ClosePositionResponse {
+            deal_reference: confirmation.deal_reference,
+        })
+    }
+
+    async fn partial_close(
+        &self,
+        session: &IgSession,
+        position: &Position,
+        amount: PartialCloseSize,
+    ) -> Result<PartialCloseResult, AppError> {
+        let details = self
+            .market_service
+            .get_market_details(session, &position.market.epic)
+            .await?;
+        let (closed_size, remaining_size) = amount.resolve(
+            position.position.size,
+            details.dealing_rules.min_deal_size.value,
+        )?;
+        let close = ClosePositionRequest::for_position(position).with_size(closed_size);
+        let response = self.close_position(session, &close).await?;
+        let confirmation = self
+            .get_order_confirmation(session, &response.deal_reference)
+            .await?;
+        Ok(PartialCloseResult {
+            confirmation,
+            closed_size,
+            remaining_size,
+        })
+    }
+
+    async fn reversal_orders(
+        &self,
+        _session: &IgSession,
+        deal_id: &str,
+    ) -> Result<(ClosePositionRequest, CreateOrderRequest), AppError> {
+        let position = self
+            .positions()
+            .into_iter()
+            .find(|position| position.deal_id == deal_id)
+            .ok_or(AppError::NotFound)?;
+        let close =
+            ClosePositionRequest::for_deal(&position.deal_id, &position.direction, position.size)
+                .with_market(&position.epic, &position.currency);
+        let order = CreateOrderRequest::market(
+            position.epic,
+            position.direction.opposite(),
+            position.size,
+            position.currency,
+        );
+        Ok((close, order))
+    }
+
+    async fn close_all_positions(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError> {
+        let mut summary = BulkActionSummary::default();
+        for position in self.positions() {
+            if !filter.matches(&position.epic, &position.direction) {
+                continue;
+            }
+            let close = ClosePositionRequest::for_deal(
+                &position.deal_id,
+                &position.direction,
+                position.size,
+            )
+            .with_market(&position.epic, &position.currency);
+            let result = self.close_position(session, &close).await;
+            summary.results.push(DealActionResult {
+                deal_id: position.deal_id,
+                epic: position.epic,
+                deal_reference: result.as_ref().ok().map(|r| r.deal_reference.clone()),
+                error: result.err().map(|e| e.to_string()),
+            });
+        }
+        Ok(summary)
+    }
+
+    async fn cancel_all_working_orders(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError> {
+        let mut summary = BulkActionSummary::default();
+        for order in self.get_working_orders(session).await?.working_orders {
+            let data = order.working_order_data;
+            if !filter.matches(&data.epic, &data.direction) {
+                continue;
+            }
+            let result = self.delete_working_order(session, &data.deal_id).await;
+            summary.results.push(DealActionResult {
+                deal_id: data.deal_id,
+                epic: data.epic,
+                deal_reference: result.as_ref().ok().map(|r| r.deal_reference.clone()),
+                error: result.err().map(|e| e.to_string()),
+            });
+        }
+        Ok(summary)
+    }
+
+    async fn get_working_orders(&self, _session: &IgSession) -> Result<WorkingOrders, AppError> {
+        Ok(WorkingOrders {
+            working_orders: self.book.lock().unwrap().working_orders.clone(),
+        })
+    }
+
+    async fn find_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<Option<WorkingOrder>, AppError> {
+        Ok(self
+            .get_working_orders(session)
+            .await?
+            .find(deal_id)
+            .cloned())
+    }
+
+    async fn create_working_order(
+        &self,
+        session: &IgSession,
+        order: &CreateWorkingOrderRequest,
+    ) -> Result<CreateWorkingOrderResponse, AppError> {
+        order.validate_levels()?;
+        let (bid, offer) = self.current_quote(session, &order.epic).await?;
+        let mut book = self.book.lock().unwrap();
+        let deal_id = book.next_deal_id();
+        let deal_reference = order.deal_reference.clone().unwrap_or_else(new_reference);
+        let now = Utc::now();
+        let data = WorkingOrderData {
+            deal_id: deal_id.clone(),
+            direction: order.direction.clone(),
+            epic: order.epic.clone(),
+            order_size: order.size,
+            order_level: order.level,
+            time_in_force: order.time_in_force.clone(),
+            good_till_date: order.good_till_date.clone(),
+            good_till_date_iso: None,
+            created_date: now.format("%Y/%m/%d %H:%M:%S:%3f").to_string(),
+            created_date_utc: now.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            guaranteed_stop: order.guaranteed_stop,
+            order_type: order.order_type.clone(),
+            stop_distance: order.stop_distance,
+            limit_distance: order.limit_distance,
+            currency_code: order.currency_code.clone().unwrap_or_default(),
+            dma: false,
+            limited_risk_premium: None,
+            limit_level: order.limit_level,
+            stop_level: order.stop_level,
+            deal_reference: Some(deal_reference.clone()),
+        };
+        if let Some(limit) = order.limit_price {
+            book.stop_limits.insert(deal_id.clone(), limit);
+        }
+        book.working_orders.push(WorkingOrder {
+            working_order_data: data,
+            market_data: paper_market_data(&order.epic, &order.expiry, bid, offer),
+        });
+        let mut confirmation = confirmation(&deal_reference, &order.epic, Some(order.level), true);
+        confirmation.deal_id = Some(deal_id);
+        confirmation.size = Some(order.size);
+        confirmation.direction = Some(order.direction.clone());
+        book.confirm(confirmation);
+        // A working order already marketable fills on the next quote, as it would on IG
+        Ok(CreateWorkingOrderResponse { deal_reference })
+    }
+
+    async fn update_working_order(
+        &self,
+        _session: &IgSession,
+        deal_id: &str,
+        update: &UpdateWorkingOrderRequest,
+    ) -> Result<UpdateWorkingOrderResponse, AppError> {
+        let mut book = self.book.lock().unwrap();
+        let order = book
+            .working_orders
+            .iter_mut()
+            .find(|order| order.working_order_data.deal_id == deal_id)
+            .ok_or(AppError::NotFound)?;
+        let data = &mut order.working_order_data;
+        data.order_level = update.level;
+        data.order_type = update.order_type.clone();
+        data.time_in_force = update.time_in_force.clone();
+        data.good_till_date = update.good_till_date.clone();
+        data.stop_level = update.stop_level;
+        data.stop_distance = update.stop_distance;
+        data.limit_level = update.limit_level;
+        data.limit_distance = update.limit_distance;
+        let epic = data.epic.clone();
+        let mut confirmation = confirmation(&new_reference(), &epic, Some(update.level), true);
+        confirmation.status = Status::Amended;
+        confirmation.deal_id = Some(deal_id.to_string());
+        let confirmation = book.confirm(confirmation);
+        Ok(UpdateWorkingOrderResponse {
+            deal_reference: confirmation.deal_reference,
+        })
+    }
+
+    async fn delete_working_order(
+        &self,
+        _session: &IgSession,
+        deal_id: &str,
+    ) -> Result<DeleteWorkingOrderResponse, AppError> {
+        let mut book = self.book.lock().unwrap();
+        let index = book
+            .working_orders
+            .iter()
+            .position(|order| order.working_order_data.deal_id == deal_id)
+            .ok_or(AppError::NotFound)?;
+        let order = book.working_orders.remove(index);
+        book.stop_limits.remove(deal_id);
+        let mut confirmation =
+            confirmation(&new_reference(), &order.working_order_data.epic, None, true);
+        confirmation.status = Status::Deleted;
+        confirmation.deal_id = Some(deal_id.to_string());
+        let confirmation = book.confirm(confirmation);
+        Ok(DeleteWorkingOrderResponse {
+            deal_reference: confirmation.deal_reference,
+        })
+    }
+}
+
+/// Price at which a deal in `direction` is filled: the offer to buy, the bid to sell
+fn entry_price(direction: &Direction, bid: f64, offer: f64) -> f64 {
+    match direction {
+        Direction::Buy => offer,
+        Direction::Sell => bid,
+    }
+}
+
+/// Returns true if a working order is triggered by the quote
+///
+/// Limit orders fill at their level or better, stop and stop-limit orders once the
+/// price crosses it.
+fn is_triggered(data: &WorkingOrderData, bid: f64, offer: f64) -> bool {
+    let price = entry_price(&data.direction, bid, offer);
+    let level = data.order_level;
+    match (&data.order_type, &data.direction) {
+        (OrderType::Stop | OrderType::StopLimit, Direction::Buy) => price >= level,
+        (OrderType::Stop | OrderType::StopLimit, Direction::Sell) => price <= level,
+        (_, Direction::Buy) => price <= level,
+        (_, Direction::Sell) => price >= level,
+    }
+}
+
+/// Stop and limit levels of a position in `direction` opened at `price`
+///
+/// Levels are kept as given, distances are converted into levels.
+fn attached_levels(
+    direction: &Direction,
+    price: f64,
+    order: &CreateOrderRequest,
+) -> (Option<f64>, Option<f64>) {
+    let sign = match direction {
+        Direction::Buy => 1.0,
+        Direction::Sell => -1.0,
+    };
+    let stop = order
+        .stop_level
+        .or(order.stop_distance.map(|distance| price - sign * distance));
+    let limit = order
+        .limit_level
+        .or(order.limit_distance.map(|distance| price + sign * distance));
+    (stop, limit)
+}
+
+fn new_reference() -> String {
+    nanoid::nanoid!(30, &nanoid::alphabet::SAFE)
+}
+
+/// Builds the confirmation of a paper deal
+fn confirmation(
+    deal_reference: &str,
+    epic: &str,
+    level: Option<f64>,
+    accepted: bool,
+) -> OrderConfirmation {
+    OrderConfirmation {
+        date: Utc::now().format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
+        status: if accepted {
+            Status::Open
+        } else {
+            Status::Rejected
+        },
+        reason: Some(if accepted { "SUCCESS" } else { "UNKNOWN" }.to_string()),
+        deal_id: None,
+        deal_reference: deal_reference.to_string(),
+        deal_status: Some(if accepted { "ACCEPTED" } else { "REJECTED" }.to_string()),
+        epic: (!epic.is_empty()).then(|| epic.to_string()),
+        expiry: None,
+        guaranteed_stop: Some(false),
+        level,
+        limit_distance: None,
+        limit_level: None,
+        size: None,
+        stop_distance: None,
+        stop_level: None,
+        trailing_stop: Some(false),
+        direction: None,
+    }
+}
+
+/// Market data attached to paper working orders
+fn paper_market_data(epic: &str, expiry: &str, bid: f64, offer: f64) -> MarketData {
+    let now = Utc::now();
+    MarketData {
+        instrument_name: epic.to_string(),
+        exchange_id: String::new(),
+        expiry: expiry.to_string(),
+        market_status: MarketState::Tradeable,
+        epic: epic.to_string(),
+        instrument_type: InstrumentType::Unknown,
+        lot_size: 1.0,
+        high: offer,
+        low: bid,
+        percentage_change: 0.0,
+        net_change: 0.0,
+        bid,
+        offer,
+        update_time: now.format("%H:%M:%S").to_string(),
+        update_time_utc: now.format("%H:%M:%S").to_string(),
+        delay_time: 0,
+        streaming_prices_available: true,
+        scaling_factor: 1,
+    }
+}
@@ -0,0 +1,181 @@
+use crate::application::models::account::{Account, Position, WorkingOrder};
+use crate::application::services::AccountService;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::utils::currency::CurrencyConverter;
+use crate::utils::money::Money;
+use std::collections::BTreeMap;
+use tracing::debug;
+
+/// Positions, working orders and balance of one account
+///
+/// Amounts are in the base currency of the report; the account itself keeps the
+/// figures in its own currency.
+#[derive(Debug, Clone)]
+pub struct AccountPortfolio {
+    /// Account as returned by IG
+    pub account: Account,
+    /// Open positions of the account
+    pub positions: Vec<Position>,
+    /// Working orders of the account
+    pub working_orders: Vec<WorkingOrder>,
+    /// Balance of the account
+    pub balance: f64,
+    /// Profit or loss of the open positions
+    pub profit_loss: f64,
+    /// Balance plus the profit or loss of the open positions
+    pub equity: f64,
+    /// Funds available for trading
+    pub available: f64,
+}
+
+impl AccountPortfolio {
+    fn new(
+        account: Account,
+        positions: Vec<Position>,
+        working_orders: Vec<WorkingOrder>,
+        converter: &CurrencyConverter,
+    ) -> Result<Self, AppError> {
+        let to_base = |money: &Money| money.to_base(converter).map(|money| money.amount);
+        let balance = to_base(&account.balance.balance)?;
+        let profit_loss = to_base(&account.balance.profit_loss)?;
+        let available = to_base(&account.balance.available)?;
+        Ok(Self {
+            balance,
+            profit_loss,
+            equity: balance + profit_loss,
+            available,
+            account,
+            positions,
+            working_orders,
+        })
+    }
+}
+
+/// Combined view of several accounts, keyed by account ID
+#[derive(Debug, Clone)]
+pub struct PortfolioReport {
+    /// Currency of every amount of the report
+    pub base_currency: String,
+    /// Portfolio of each account
+    pub accounts: BTreeMap<String, AccountPortfolio>,
+}
+
+impl PortfolioReport {
+    /// Total balance of the accounts
+    pub fn total_balance(&self) -> f64 {
+        self.accounts.values().map(|account| account.balance).sum()
+    }
+
+    /// Total profit or loss of the open positions
+    pub fn total_profit_loss(&self) -> f64 {
+        self.accounts
+            .values()
+            .map(|account| account.profit_loss)
+            .sum()
+    }
+
+    /// Total equity of the accounts
+    pub fn total_equity(&self) -> f64 {
+        self.accounts.values().map(|account| account.equity).sum()
+    }
+
+    /// Total funds available for trading
+    pub fn total_available(&self) -> f64 {
+        self.accounts
+            .values()
+            .map(|account| account.available)
+            .sum()
+    }
+
+    /// Open positions of every account, with the ID of their account
+    pub fn positions(&self) -> impl Iterator<Item = (&str, &Position)> {
+        self.accounts.iter().flat_map(|(account_id, portfolio)| {
+            portfolio
+                .positions
+                .iter()
+                .map(move |position| (account_id.as_str(), position))
+        })
+    }
+
+    /// Working orders of every account, with the ID of their account
+    pub fn working_orders(&self) -> impl Iterator<Item = (&str, &WorkingOrder)> {
+        self.accounts.iter().flat_map(|(account_id, portfolio)| {
+            portfolio
+                .working_orders
+                .iter()
+                .map(move |order| (account_id.as_str(), order))
+        })
+    }
+}
+
+/// Gathers the positions, working orders and balances of several accounts
+///
+/// IG sessions are bound to one account, so the report takes one session per account.
+/// Balances are converted into the base currency of the converter, which needs a rate
+/// for the currency of every account.
+pub struct PortfolioService<A: AccountService> {
+    account_service: A,
+    converter: CurrencyConverter,
+}
+
+impl<A: AccountService> PortfolioService<A> {
+    /// Creates a service reading accounts from `account_service` and reporting in the
+    /// base currency of `converter`
+    pub fn new(account_service: A, converter: CurrencyConverter) -> Self {
+        Self {
+            account_service,
+            converter,
+        }
+    }
+
+    /// Returns the converter used to normalize balances
+    pub fn converter(&self) -> &CurrencyConverter {
+        &self.converter
+    }
+
+    /// Builds the report of the accounts of `sessions`
+    ///
+    /// # Returns
+    /// The report, `AppError::NotFound` if the account of a session is not among its
+    /// accounts, or `AppError::InvalidInput` if no rate is known for an account currency
+    pub async fn report(&self, sessions: &[IgSession]) -> Result<PortfolioReport, AppError> {
+        let mut accounts = BTreeMap::new();
+        for session in sessions {
+            let portfolio = self.account_portfolio(session).await?;
+            debug!(
+                "Account {}: {} positions, {} working orders",
+                session.account_id,
+                portfolio.positions.len(),
+                portfolio.working_orders.len()
+            );
+            accounts.insert(session.account_id.clone(), portfolio);
+        }
+        Ok(PortfolioReport {
+            base_currency: self.converter.base_currency().to_string(),
+            accounts,
+        })
+    }
+
+    /// Builds the portfolio of the account of `session`
+    pub async fn account_portfolio(
+        &self,
+        session: &IgSession,
+    ) -> Result<AccountPortfolio, AppError> {
+        let account = self
+            .account_service
+            .get_accounts(session)
+            .await?
+            .accounts
+            .into_iter()
+            .find(|account| account.account_id == session.account_id)
+            .ok_or(AppError::NotFound)?;
+        let positions = self.account_service.get_positions(session).await?.positions;
+        let working_orders = self
+            .account_service
+            .get_working_orders(session)
+            .await?
+            .working_orders;
+        AccountPortfolio::new(account, positions, working_orders, &self.converter)
+    }
+}
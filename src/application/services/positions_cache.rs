@@ -0,0 +1,221 @@
+use crate::application::models::account::{Position, Positions};
+use crate::application::services::AccountService;
+use crate::error::AppError;
+use crate::presentation::trade::OpenPositionUpdate;
+use crate::session::interface::IgSession;
+use crate::utils::periodic::{PeriodicTask, ShutdownToken};
+use crate::utils::rate_limiter::{RateLimiter, wait_if_set};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{Notify, broadcast};
+use tokio::time::Instant;
+use tracing::debug;
+
+/// Default interval between two refreshes of the positions
+pub const DEFAULT_POSITIONS_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Number of events kept for slow subscribers before they start missing some
+const EVENT_CAPACITY: usize = 256;
+
+/// Change of the open positions between two refreshes
+#[derive(Debug, Clone)]
+pub enum PositionEvent {
+    /// A position was opened
+    Opened(Position),
+    /// The size, level, stop or limit of a position changed
+    Updated {
+        /// Position before the change
+        previous: Box<Position>,
+        /// Position after the change
+        current: Position,
+    },
+    /// A position was closed
+    Closed(Position),
+}
+
+impl PositionEvent {
+    /// Returns the deal ID of the position
+    pub fn deal_id(&self) -> &str {
+        match self {
+            PositionEvent::Opened(position)
+            | PositionEvent::Updated {
+                current: position, ..
+            }
+            | PositionEvent::Closed(position) => &position.position.deal_id,
+        }
+    }
+}
+
+/// Returns true if the fields a refresh reports as an update differ
+fn position_changed(previous: &Position, current: &Position) -> bool {
+    let (a, b) = (&previous.position, &current.position);
+    a.size != b.size
+        || a.level != b.level
+        || a.stop_level != b.stop_level
+        || a.limit_level != b.limit_level
+        || a.trailing_stop_distance != b.trailing_stop_distance
+}
+
+/// Shared view of the open positions, refreshed in the background
+///
+/// Components read the last known positions with [`PositionsCache::current`] and
+/// follow their changes with [`PositionsCache::subscribe`], while a single task running
+/// [`PositionsCache::run`] calls `get_positions` on an interval. Position updates of the
+/// trade stream passed to [`PositionsCache::on_position_update`] trigger an early
+/// refresh, still paced by the rate limiter, so one refresh serves every reader.
+pub struct PositionsCache<A: AccountService> {
+    account_service: A,
+    positions: Mutex<Option<Positions>>,
+    last_refresh: Mutex<Option<Instant>>,
+    interval: Duration,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    stale: AtomicBool,
+    wake: Notify,
+    events: broadcast::Sender<PositionEvent>,
+}
+
+impl<A: AccountService> PositionsCache<A> {
+    /// Creates an empty cache reading positions from `account_service`
+    pub fn new(account_service: A) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            account_service,
+            positions: Mutex::new(None),
+            last_refresh: Mutex::new(None),
+            interval: DEFAULT_POSITIONS_REFRESH_INTERVAL,
+            rate_limiter: None,
+            stale: AtomicBool::new(false),
+            wake: Notify::new(),
+            events,
+        }
+    }
+
+    /// Sets the interval between two refreshes in [`PositionsCache::run`]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Uses a specific rate limiter for refresh requests
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Returns the configured rate limiter, or the account limiter of `session`
+    fn rate_limiter(&self, session: &IgSession) -> Arc<RateLimiter> {
+        self.rate_limiter
+            .clone()
+            .unwrap_or_else(|| session.rate_limiters().account_non_trading.clone())
+    }
+
+    /// Returns the positions of the last refresh, empty before the first one
+    pub fn current(&self) -> Positions {
+        self.positions.lock().unwrap().clone().unwrap_or_default()
+    }
+
+    /// Returns the position with the given deal ID, as of the last refresh
+    pub fn get(&self, deal_id: &str) -> Option<Position> {
+        self.positions
+            .lock()
+            .unwrap()
+            .as_ref()?
+            .find_by_deal_id(deal_id)
+            .cloned()
+    }
+
+    /// Returns the time of the last successful refresh
+    pub fn last_refresh(&self) -> Option<Instant> {
+        *self.last_refresh.lock().unwrap()
+    }
+
+    /// Subscribes to the changes found by the following refreshes
+    ///
+    /// The first refresh only loads the positions and reports no change.
+    pub fn subscribe(&self) -> broadcast::Receiver<PositionEvent> {
+        self.events.subscribe()
+    }
+
+    /// Marks the positions as outdated so that [`PositionsCache::run`] refreshes early
+    pub fn invalidate(&self) {
+        self.stale.store(true, Ordering::SeqCst);
+        self.wake.notify_one();
+    }
+
+    /// Applies an open position update (OPU) of the trade stream
+    ///
+    /// The update only tells that positions changed; the cache is refreshed from the
+    /// REST API so that every field stays consistent.
+    pub fn on_position_update(&self, update: &OpenPositionUpdate) {
+        debug!(
+            "Position update for {:?}, refreshing positions",
+            update.deal_id
+        );
+        self.invalidate();
+    }
+
+    /// Fetches the positions once and returns the changes since the previous refresh
+    ///
+    /// The changes are also sent to the subscribers.
+    pub async fn refresh(&self, session: &IgSession) -> Result<Vec<PositionEvent>, AppError> {
+        self.stale.store(false, Ordering::SeqCst);
+        wait_if_set(self.rate_limiter.as_deref()).await;
+        let fresh = self.account_service.get_positions(session).await?;
+        *self.last_refresh.lock().unwrap() = Some(Instant::now());
+
+        let previous = self.positions.lock().unwrap().replace(fresh.clone());
+        let Some(previous) = previous else {
+            debug!("Positions loaded: {}", fresh.positions.len());
+            return Ok(Vec::new());
+        };
+
+        let mut previous: HashMap<&str, &Position> = previous
+            .positions
+            .iter()
+            .map(|position| (position.position.deal_id.as_str(), position))
+            .collect();
+        let mut changes = Vec::new();
+        for position in &fresh.positions {
+            match previous.remove(position.position.deal_id.as_str()) {
+                None => changes.push(PositionEvent::Opened(position.clone())),
+                Some(old) if position_changed(old, position) => {
+                    changes.push(PositionEvent::Updated {
+                        previous: Box::new(old.clone()),
+                        current: position.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        let mut closed: Vec<&Position> = previous.into_values().collect();
+        closed.sort_by(|a, b| a.position.deal_id.cmp(&b.position.deal_id));
+        changes.extend(
+            closed
+                .into_iter()
+                .map(|position| PositionEvent::Closed(position.clone())),
+        );
+
+        for change in &changes {
+            // Sending only fails when nobody is subscribed
+            let _ = self.events.send(change.clone());
+        }
+        debug!("{} position changes detected", changes.len());
+        Ok(changes)
+    }
+
+    /// Refreshes the positions on the configured interval, or earlier when invalidated,
+    /// until `shutdown` is cancelled
+    ///
+    /// Runs as a [`PeriodicTask`] notifying the rate limiter of the cache.
+    pub async fn run(&self, session: &IgSession, shutdown: &ShutdownToken) {
+        PeriodicTask::new("refresh positions", self.interval)
+            .with_rate_limiter(self.rate_limiter(session))
+            .with_wake(&self.wake)
+            .run(shutdown, || async {
+                self.refresh(session).await.map(|_| ())
+            })
+            .await
+    }
+}
@@ -0,0 +1,294 @@
+use crate::application::models::market::{HistoricalPrice, HistoricalPricesQuery, Resolution};
+use crate::application::services::MarketService;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::utils::rate_limiter::{RateLimiter, wait_if_set};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Default number of candles requested per window
+pub const DEFAULT_BACKFILL_WINDOW_POINTS: i64 = 1000;
+
+/// Default number of historical points kept in reserve before the backfill pauses
+pub const DEFAULT_MIN_PRICE_ALLOWANCE: i64 = 0;
+
+const QUERY_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// History still to be downloaded for one market and resolution
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackfillTask {
+    /// EPIC of the market
+    pub epic: String,
+    /// Resolution of the candles
+    pub resolution: Resolution,
+    /// Start of the next window to download
+    pub cursor: DateTime<Utc>,
+    /// End of the requested range
+    pub to: DateTime<Utc>,
+    /// Number of candles downloaded so far
+    pub points: u64,
+}
+
+impl BackfillTask {
+    /// Returns true once the whole range has been downloaded
+    pub fn is_complete(&self) -> bool {
+        self.cursor >= self.to
+    }
+}
+
+/// Resumable state of a price backfill
+///
+/// The checkpoint can be serialized (see [`BackfillCheckpoint::save`]) when a backfill is
+/// paused and loaded later to continue where it stopped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackfillCheckpoint {
+    /// One task per market and resolution, downloaded in order
+    pub tasks: Vec<BackfillTask>,
+    /// Historical points left in the allowance, as last reported by the API
+    pub remaining_allowance: Option<i64>,
+    /// When the reported allowance resets
+    pub allowance_resets_at: Option<DateTime<Utc>>,
+    /// Number of price requests made so far
+    pub requests: usize,
+}
+
+impl BackfillCheckpoint {
+    /// Creates a checkpoint downloading every resolution of every market over a date range
+    pub fn new(
+        epics: &[String],
+        resolutions: &[Resolution],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Self {
+        let tasks = epics
+            .iter()
+            .flat_map(|epic| {
+                resolutions.iter().map(move |resolution| BackfillTask {
+                    epic: epic.clone(),
+                    resolution: *resolution,
+                    cursor: from,
+                    to,
+                    points: 0,
+                })
+            })
+            .collect();
+        Self {
+            tasks,
+            remaining_allowance: None,
+            allowance_resets_at: None,
+            requests: 0,
+        }
+    }
+
+    /// Returns true if every task has been downloaded
+    pub fn is_complete(&self) -> bool {
+        self.tasks.iter().all(BackfillTask::is_complete)
+    }
+
+    /// Returns the number of tasks not yet complete
+    pub fn pending(&self) -> usize {
+        self.tasks.iter().filter(|task| !task.is_complete()).count()
+    }
+
+    /// Returns true if the allowance known at `now` is at or below `min_allowance`
+    pub fn is_allowance_exhausted(&self, min_allowance: i64, now: DateTime<Utc>) -> bool {
+        let reset = self.allowance_resets_at.is_some_and(|reset| now >= reset);
+        !reset
+            && self
+                .remaining_allowance
+                .is_some_and(|remaining| remaining <= min_allowance)
+    }
+
+    /// Saves the checkpoint as JSON
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AppError> {
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Loads a checkpoint previously written with [`BackfillCheckpoint::save`]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// Outcome of a backfill run
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackfillStatus {
+    /// Every task has been downloaded
+    Complete,
+    /// The backfill stopped early; the checkpoint holds the remaining tasks
+    Paused {
+        /// Number of tasks still to be downloaded
+        pending: usize,
+    },
+}
+
+/// Downloads historical prices for several markets and resolutions over a date range
+///
+/// The range is fetched in windows of a fixed number of candles. The backfill pauses
+/// when the API reports a rate limit, when the historical points allowance falls to
+/// the configured reserve or is spent, when the historical points limiter of the
+/// session has no budget left or when the request budget of the run is spent, and
+/// resumes from the [`BackfillCheckpoint`].
+pub struct PriceBackfill<'a, S: MarketService> {
+    market_service: &'a S,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    window_points: i64,
+    min_allowance: i64,
+    max_requests: Option<usize>,
+}
+
+impl<'a, S: MarketService> PriceBackfill<'a, S> {
+    /// Creates a backfill using the given market service
+    pub fn new(market_service: &'a S) -> Self {
+        Self {
+            market_service,
+            rate_limiter: None,
+            window_points: DEFAULT_BACKFILL_WINDOW_POINTS,
+            min_allowance: DEFAULT_MIN_PRICE_ALLOWANCE,
+            max_requests: None,
+        }
+    }
+
+    /// Uses a specific rate limiter for price requests
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Sets the number of candles requested per window
+    pub fn with_window_points(mut self, window_points: i64) -> Self {
+        self.window_points = window_points.max(1);
+        self
+    }
+
+    /// Pauses once the remaining historical points allowance is at or below `min_allowance`
+    pub fn with_min_allowance(mut self, min_allowance: i64) -> Self {
+        self.min_allowance = min_allowance;
+        self
+    }
+
+    /// Pauses the backfill after the given number of requests in a single run
+    pub fn with_max_requests(mut self, max_requests: usize) -> Self {
+        self.max_requests = Some(max_requests);
+        self
+    }
+
+    /// Continues a backfill from the given checkpoint
+    ///
+    /// Every downloaded window is handed to `sink` before the checkpoint moves past it,
+    /// so persisting the checkpoint after the sink succeeds never loses prices. If the
+    /// sink or a request fails, the window stays pending and the error is returned.
+    pub async fn run<F>(
+        &self,
+        session: &IgSession,
+        checkpoint: &mut BackfillCheckpoint,
+        mut sink: F,
+    ) -> Result<BackfillStatus, AppError>
+    where
+        F: FnMut(&BackfillTask, Vec<HistoricalPrice>) -> Result<(), AppError>,
+    {
+        let mut requests = 0;
+
+        'tasks: for index in 0..checkpoint.tasks.len() {
+            while !checkpoint.tasks[index].is_complete() {
+                if self.max_requests.is_some_and(|max| requests >= max) {
+                    break 'tasks;
+                }
+                if checkpoint.is_allowance_exhausted(self.min_allowance, Utc::now()) {
+                    warn!(
+                        "Historical price allowance down to {:?}, pausing backfill",
+                        checkpoint.remaining_allowance
+                    );
+                    break 'tasks;
+                }
+                // Pause rather than wait for the weekly budget to free up
+                let budget = &session.rate_limiters().historical_price;
+                if budget.current_request_count().await >= budget.effective_limit() {
+                    warn!("Historical price points budget spent, pausing backfill");
+                    break 'tasks;
+                }
+
+                let task = &checkpoint.tasks[index];
+                let span = task.resolution.duration() * self.window_points as i32;
+                let window_end = (task.cursor + span).min(task.to);
+                // Windows are inclusive, so stop just before the next window starts
+                let query_end = if window_end < task.to {
+                    window_end - Duration::seconds(1)
+                } else {
+                    window_end
+                };
+                let query = HistoricalPricesQuery::new(task.resolution).with_range(
+                    &task.cursor.format(QUERY_DATE_FORMAT).to_string(),
+                    &query_end.format(QUERY_DATE_FORMAT).to_string(),
+                );
+
+                wait_if_set(self.rate_limiter.as_deref()).await;
+                let response = match self
+                    .market_service
+                    .get_all_historical_prices(session, &task.epic, &query)
+                    .await
+                {
+                    Ok(response) => response,
+                    Err(AppError::RateLimitExceeded) => {
+                        warn!("Rate limit exceeded while backfilling, pausing");
+                        if let Some(rate_limiter) = &self.rate_limiter {
+                            rate_limiter.notify_rate_limit_exceeded().await;
+                        }
+                        break 'tasks;
+                    }
+                    Err(AppError::HistoricalAllowanceExceeded) => {
+                        warn!("Historical price allowance spent while backfilling, pausing");
+                        checkpoint.remaining_allowance = Some(0);
+                        // Unless known, wait for the longest the allowance can take to reset
+                        let now = Utc::now();
+                        if checkpoint
+                            .allowance_resets_at
+                            .is_none_or(|reset| reset <= now)
+                        {
+                            checkpoint.allowance_resets_at = Some(now + Duration::weeks(1));
+                        }
+                        break 'tasks;
+                    }
+                    Err(e) => return Err(e),
+                };
+                requests += 1;
+                checkpoint.requests += 1;
+
+                if let Some(allowance) = response.allowance() {
+                    checkpoint.remaining_allowance = Some(allowance.remaining_allowance);
+                    checkpoint.allowance_resets_at =
+                        Some(Utc::now() + Duration::seconds(allowance.allowance_expiry));
+                }
+
+                let points = response.prices.len();
+                sink(&checkpoint.tasks[index], response.prices)?;
+
+                let task = &mut checkpoint.tasks[index];
+                task.cursor = window_end;
+                task.points += points as u64;
+                debug!(
+                    "Backfilled {} {} candles of {} up to {}",
+                    points, task.resolution, task.epic, window_end
+                );
+            }
+        }
+
+        let pending = checkpoint.pending();
+        info!(
+            "Price backfill: {} requests this run, {} tasks pending",
+            requests, pending
+        );
+
+        if pending == 0 {
+            Ok(BackfillStatus::Complete)
+        } else {
+            Ok(BackfillStatus::Paused { pending })
+        }
+    }
+}
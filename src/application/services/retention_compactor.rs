@@ -0,0 +1,150 @@
+use crate::application::models::market::Resolution;
+use crate::error::AppError;
+use crate::storage::Store;
+use crate::storage::retention::{RetentionPolicy, RetentionRule};
+use crate::storage::store::{Candle, StoreQuery, candles_from_ticks, downsample};
+use crate::utils::periodic::{PeriodicTask, ShutdownToken};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Outcome of a [`RetentionCompactor::compact`] run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactionReport {
+    /// Candles written by downsampling
+    pub candles_written: usize,
+    /// Candles pruned
+    pub candles_deleted: usize,
+    /// Ticks pruned
+    pub ticks_deleted: usize,
+}
+
+/// Applies a [`RetentionPolicy`] to a [`Store`]
+///
+/// Ticks are handled first, then candles from the finest resolution to the coarsest,
+/// so ticks compacted to one minute candles can be compacted again to hours by a later
+/// rule of the same run. Periods already stored at the target resolution, for
+/// instance candles fetched from the price history, are kept as they are.
+///
+/// Data older than its cutoff is read at once: a first run on a large store holds it
+/// all in memory, later runs only the data aged since the previous one.
+pub struct RetentionCompactor<S: Store> {
+    store: S,
+    policy: RetentionPolicy,
+}
+
+impl<S: Store> RetentionCompactor<S> {
+    /// Creates a compactor applying `policy` to `store`
+    pub fn new(store: S, policy: RetentionPolicy) -> Self {
+        Self { store, policy }
+    }
+
+    /// Returns the store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the policy
+    pub fn policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    /// Downsamples and prunes the data older than the policy allows
+    ///
+    /// # Returns
+    /// The report of the run, or the first store error; a rule compacting candles to a
+    /// finer resolution fails with `AppError::InvalidInput`
+    pub async fn compact(&self) -> Result<CompactionReport, AppError> {
+        self.compact_at(Utc::now()).await
+    }
+
+    /// Applies the policy as if run at `now`
+    ///
+    /// See [`RetentionCompactor::compact`].
+    pub async fn compact_at(&self, now: DateTime<Utc>) -> Result<CompactionReport, AppError> {
+        let mut report = CompactionReport::default();
+        if let Some(rule) = &self.policy.ticks {
+            let cutoff = rule.cutoff(now);
+            let old = StoreQuery::new().before(cutoff);
+            if let Some(target) = rule.compact_to {
+                let ticks = self.store.query_ticks(&old).await?;
+                let candles = candles_from_ticks(&ticks, target);
+                report.candles_written += self.write_missing(target, candles, cutoff).await?;
+            }
+            report.ticks_deleted += self.store.delete_ticks(&old).await?;
+            debug!("Ticks before {} pruned", cutoff);
+        }
+        for (&resolution, rule) in &self.policy.candles {
+            let (written, deleted) = self.compact_candles(resolution, rule, now).await?;
+            report.candles_written += written;
+            report.candles_deleted += deleted;
+        }
+        info!(
+            "Retention applied: {} candles written, {} candles and {} ticks pruned",
+            report.candles_written, report.candles_deleted, report.ticks_deleted
+        );
+        Ok(report)
+    }
+
+    /// Compacts and prunes the candles of one resolution
+    ///
+    /// # Returns
+    /// The number of candles written and deleted
+    async fn compact_candles(
+        &self,
+        resolution: Resolution,
+        rule: &RetentionRule,
+        now: DateTime<Utc>,
+    ) -> Result<(usize, usize), AppError> {
+        let cutoff = rule.cutoff(now);
+        let old = StoreQuery::new().before(cutoff);
+        let mut written = 0;
+        if let Some(target) = rule.compact_to {
+            if target.duration() <= resolution.duration() {
+                return Err(AppError::InvalidInput(format!(
+                    "cannot compact {} candles to {}",
+                    resolution.as_str(),
+                    target.as_str()
+                )));
+            }
+            let candles = downsample(&self.store.query_candles(resolution, &old).await?, target)?;
+            written = self.write_missing(target, candles, cutoff).await?;
+        }
+        let deleted = self.store.delete_candles(resolution, &old).await?;
+        debug!("{} candles before {} pruned", resolution.as_str(), cutoff);
+        Ok((written, deleted))
+    }
+
+    /// Writes the candles of the periods not stored yet at `resolution`
+    async fn write_missing(
+        &self,
+        resolution: Resolution,
+        candles: Vec<Candle>,
+        cutoff: DateTime<Utc>,
+    ) -> Result<usize, AppError> {
+        let Some(first) = candles.iter().map(|candle| candle.timestamp).min() else {
+            return Ok(0);
+        };
+        let stored: HashSet<(String, DateTime<Utc>)> = self
+            .store
+            .query_candles(resolution, &StoreQuery::new().between(first, cutoff))
+            .await?
+            .into_iter()
+            .map(|candle| (candle.epic, candle.timestamp))
+            .collect();
+        let missing: Vec<Candle> = candles
+            .into_iter()
+            .filter(|candle| !stored.contains(&(candle.epic.clone(), candle.timestamp)))
+            .collect();
+        Ok(self.store.put_candles(&missing).await?.total())
+    }
+
+    /// Applies the policy on `interval` until `shutdown` is cancelled, as a
+    /// [`PeriodicTask`]
+    pub async fn run(&self, interval: Duration, shutdown: &ShutdownToken) {
+        PeriodicTask::new("apply the retention policy", interval)
+            .run(shutdown, || async { self.compact().await.map(|_| ()) })
+            .await
+    }
+}
@@ -0,0 +1,460 @@
+use crate::application::models::account::{Position, WorkingOrder, WorkingOrders};
+use crate::application::models::order::{
+    BulkActionSummary, ClosePositionRequest, ClosePositionResponse, CreateOrderRequest,
+    CreateOrderResponse, DealFilter, Direction, OrderConfirmation, PartialCloseResult,
+    PartialCloseSize, ReversalResult, UpdatePositionRequest, UpdatePositionResponse,
+};
+use crate::application::models::working_order::{
+    CreateWorkingOrderRequest, CreateWorkingOrderResponse, DeleteWorkingOrderResponse,
+    UpdateWorkingOrderRequest, UpdateWorkingOrderResponse,
+};
+use crate::application::services::interfaces::order::send_reversal;
+use crate::application::services::{AccountService, OrderService};
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::warn;
+
+/// Client-side limits enforced by [`RiskGuard`]
+///
+/// Every limit is optional; an unset limit is not checked.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RiskLimits {
+    /// Maximum number of open positions
+    pub max_open_positions: Option<usize>,
+    /// Maximum total size on a single EPIC, open positions included
+    pub max_size_per_epic: Option<f64>,
+    /// Maximum notional exposure (size times price) over all positions
+    pub max_notional_exposure: Option<f64>,
+    /// Maximum number of orders submitted within a minute
+    pub max_orders_per_minute: Option<usize>,
+}
+
+impl RiskLimits {
+    /// Creates limits with no limit set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of open positions
+    pub fn with_max_open_positions(mut self, max: usize) -> Self {
+        self.max_open_positions = Some(max);
+        self
+    }
+
+    /// Limits the total size on a single EPIC
+    pub fn with_max_size_per_epic(mut self, max: f64) -> Self {
+        self.max_size_per_epic = Some(max);
+        self
+    }
+
+    /// Limits the notional exposure over all positions
+    pub fn with_max_notional_exposure(mut self, max: f64) -> Self {
+        self.max_notional_exposure = Some(max);
+        self
+    }
+
+    /// Limits the number of orders submitted within a minute
+    pub fn with_max_orders_per_minute(mut self, max: usize) -> Self {
+        self.max_orders_per_minute = Some(max);
+        self
+    }
+}
+
+/// Limit broken by an order rejected by [`RiskGuard`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskViolation {
+    /// The order would open one position too many
+    MaxOpenPositions {
+        /// Positions currently open
+        open: usize,
+        /// Configured maximum
+        max: usize,
+    },
+    /// The order would take the size on its EPIC above the maximum
+    MaxSizePerEpic {
+        /// EPIC of the order
+        epic: String,
+        /// Size on the EPIC after the order
+        size: f64,
+        /// Configured maximum
+        max: f64,
+    },
+    /// The order would take the notional exposure above the maximum
+    MaxNotionalExposure {
+        /// Exposure after the order
+        exposure: f64,
+        /// Configured maximum
+        max: f64,
+    },
+    /// The order has no level and no open position gives a price for its EPIC, so its
+    /// notional exposure cannot be checked
+    UnknownPrice {
+        /// EPIC of the order
+        epic: String,
+    },
+    /// Too many orders were submitted within the last minute
+    MaxOrdersPerMinute {
+        /// Configured maximum
+        max: usize,
+    },
+}
+
+impl Display for RiskViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RiskViolation::MaxOpenPositions { open, max } => {
+                write!(f, "{open} positions open, the maximum is {max}")
+            }
+            RiskViolation::MaxSizePerEpic { epic, size, max } => {
+                write!(f, "size {size} on {epic} is above the maximum {max}")
+            }
+            RiskViolation::MaxNotionalExposure { exposure, max } => {
+                write!(f, "notional exposure {exposure} is above the maximum {max}")
+            }
+            RiskViolation::UnknownPrice { epic } => {
+                write!(f, "no price to check the notional exposure of {epic}")
+            }
+            RiskViolation::MaxOrdersPerMinute { max } => {
+                write!(f, "more than {max} orders within a minute")
+            }
+        }
+    }
+}
+
+/// Order service enforcing client-side risk limits before orders reach IG
+///
+/// New orders and working orders are checked against the open positions of the account,
+/// fetched from `account_service`, as if they all opened new exposure. An order breaking
+/// a limit is rejected locally with `AppError::RiskLimit` and never sent. The opposite
+/// order of a reversal is checked before the close, without the reversed position.
+/// Every other operation, closes included, is passed through unchanged.
+pub struct RiskGuard<S: OrderService, A: AccountService> {
+    order_service: S,
+    account_service: A,
+    limits: RiskLimits,
+    submissions: Mutex<VecDeque<Instant>>,
+}
+
+impl<S: OrderService, A: AccountService> RiskGuard<S, A> {
+    /// Wraps `order_service`, reading open positions from `account_service`
+    pub fn new(order_service: S, account_service: A, limits: RiskLimits) -> Self {
+        Self {
+            order_service,
+            account_service,
+            limits,
+            submissions: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Returns the enforced limits
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    /// Returns the wrapped order service
+    pub fn inner(&self) -> &S {
+        &self.order_service
+    }
+
+    /// Checks an order of `size` on `epic` at `level` against the limits
+    ///
+    /// The position `reversed`, closed before the order is sent, is left out of the open
+    /// positions. The order counts towards the orders per minute only if every limit is
+    /// met.
+    async fn check(
+        &self,
+        session: &IgSession,
+        epic: &str,
+        size: f64,
+        level: Option<f64>,
+        reversed: Option<&str>,
+    ) -> Result<(), AppError> {
+        let result = self
+            .check_positions(session, epic, size, level, reversed)
+            .await;
+        let result = result.and_then(|_| self.record_submission());
+        if let Err(AppError::RiskLimit(violation)) = &result {
+            warn!(
+                "Order on {} rejected by the risk guard: {}",
+                epic, violation
+            );
+        }
+        result
+    }
+
+    async fn check_positions(
+        &self,
+        session: &IgSession,
+        epic: &str,
+        size: f64,
+        level: Option<f64>,
+        reversed: Option<&str>,
+    ) -> Result<(), AppError> {
+        let limits = &self.limits;
+        if limits.max_open_positions.is_none()
+            && limits.max_size_per_epic.is_none()
+            && limits.max_notional_exposure.is_none()
+        {
+            return Ok(());
+        }
+        let mut positions = self.account_service.get_positions(session).await?.positions;
+        // The reversed position still quotes its market
+        let price = level.or_else(|| {
+            positions
+                .iter()
+                .find(|position| position.market.epic == epic)
+                .map(|position| (position.market.bid + position.market.offer) / 2.0)
+        });
+        if let Some(deal_id) = reversed {
+            positions.retain(|position| position.position.deal_id != deal_id);
+        }
+
+        if let Some(max) = limits.max_open_positions
+            && positions.len() >= max
+        {
+            return Err(AppError::RiskLimit(RiskViolation::MaxOpenPositions {
+                open: positions.len(),
+                max,
+            }));
+        }
+
+        if let Some(max) = limits.max_size_per_epic {
+            let size = size
+                + positions
+                    .iter()
+                    .filter(|position| position.market.epic == epic)
+                    .map(|position| position.position.size)
+                    .sum::<f64>();
+            if size > max {
+                return Err(AppError::RiskLimit(RiskViolation::MaxSizePerEpic {
+                    epic: epic.to_string(),
+                    size,
+                    max,
+                }));
+            }
+        }
+
+        if let Some(max) = limits.max_notional_exposure {
+            let Some(price) = price else {
+                return Err(AppError::RiskLimit(RiskViolation::UnknownPrice {
+                    epic: epic.to_string(),
+                }));
+            };
+            let exposure = size * price + positions.iter().map(notional).sum::<f64>();
+            if exposure > max {
+                return Err(AppError::RiskLimit(RiskViolation::MaxNotionalExposure {
+                    exposure,
+                    max,
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts a submission, unless the orders per minute are exhausted
+    fn record_submission(&self) -> Result<(), AppError> {
+        let Some(max) = self.limits.max_orders_per_minute else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let mut submissions = self.submissions.lock().unwrap();
+        while submissions
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= Duration::from_secs(60))
+        {
+            submissions.pop_front();
+        }
+        if submissions.len() >= max {
+            return Err(AppError::RiskLimit(RiskViolation::MaxOrdersPerMinute {
+                max,
+            }));
+        }
+        submissions.push_back(now);
+        Ok(())
+    }
+}
+
+/// Notional value of a position at its closing price
+fn notional(position: &Position) -> f64 {
+    let price = match position.position.direction {
+        Direction::Buy => position.market.bid,
+        Direction::Sell => position.market.offer,
+    };
+    position.position.size * price
+}
+
+#[async_trait]
+impl<S: OrderService, A: AccountService> OrderService for RiskGuard<S, A> {
+    async fn create_order(
+        &self,
+        session: &IgSession,
+        order: &CreateOrderRequest,
+    ) -> Result<CreateOrderResponse, AppError> {
+        self.check(session, &order.epic, order.size, order.level, None)
+            .await?;
+        self.order_service.create_order(session, order).await
+    }
+
+    fn confirmation_timeout(&self) -> Duration {
+        self.order_service.confirmation_timeout()
+    }
+
+    async fn get_order_confirmation(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+    ) -> Result<OrderConfirmation, AppError> {
+        self.order_service
+            .get_order_confirmation(session, deal_reference)
+            .await
+    }
+
+    async fn await_confirmation(
+        &self,
+        session: &IgSession,
+        deal_reference: &str,
+        timeout: Duration,
+    ) -> Result<OrderConfirmation, AppError> {
+        self.order_service
+            .await_confirmation(session, deal_reference, timeout)
+            .await
+    }
+
+    async fn update_position(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        update: &UpdatePositionRequest,
+    ) -> Result<UpdatePositionResponse, AppError> {
+        self.order_service
+            .update_position(session, deal_id, update)
+            .await
+    }
+
+    async fn set_attached_orders(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        stop_level: Option<f64>,
+        limit_level: Option<f64>,
+    ) -> Result<OrderConfirmation, AppError> {
+        self.order_service
+            .set_attached_orders(session, deal_id, stop_level, limit_level)
+            .await
+    }
+
+    async fn close_position(
+        &self,
+        session: &IgSession,
+        close_request: &ClosePositionRequest,
+    ) -> Result<ClosePositionResponse, AppError> {
+        self.order_service
+            .close_position(session, close_request)
+            .await
+    }
+
+    async fn partial_close(
+        &self,
+        session: &IgSession,
+        position: &Position,
+        amount: PartialCloseSize,
+    ) -> Result<PartialCloseResult, AppError> {
+        self.order_service
+            .partial_close(session, position, amount)
+            .await
+    }
+
+    async fn reversal_orders(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<(ClosePositionRequest, CreateOrderRequest), AppError> {
+        self.order_service.reversal_orders(session, deal_id).await
+    }
+
+    /// Checks the opposite order before anything is sent, so a reversal breaking a
+    /// limit leaves the position open
+    async fn reverse_position(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<ReversalResult, AppError> {
+        let (close, order) = self.reversal_orders(session, deal_id).await?;
+        self.check(session, &order.epic, order.size, order.level, Some(deal_id))
+            .await?;
+        send_reversal(&self.order_service, session, &close, &order).await
+    }
+
+    async fn close_all_positions(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError> {
+        self.order_service
+            .close_all_positions(session, filter)
+            .await
+    }
+
+    async fn cancel_all_working_orders(
+        &self,
+        session: &IgSession,
+        filter: &DealFilter,
+    ) -> Result<BulkActionSummary, AppError> {
+        self.order_service
+            .cancel_all_working_orders(session, filter)
+            .await
+    }
+
+    async fn get_working_orders(&self, session: &IgSession) -> Result<WorkingOrders, AppError> {
+        self.order_service.get_working_orders(session).await
+    }
+
+    async fn find_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<Option<WorkingOrder>, AppError> {
+        self.order_service
+            .find_working_order(session, deal_id)
+            .await
+    }
+
+    async fn create_working_order(
+        &self,
+        session: &IgSession,
+        order: &CreateWorkingOrderRequest,
+    ) -> Result<CreateWorkingOrderResponse, AppError> {
+        self.check(session, &order.epic, order.size, Some(order.level), None)
+            .await?;
+        self.order_service
+            .create_working_order(session, order)
+            .await
+    }
+
+    async fn update_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+        update: &UpdateWorkingOrderRequest,
+    ) -> Result<UpdateWorkingOrderResponse, AppError> {
+        self.order_service
+            .update_working_order(session, deal_id, update)
+            .await
+    }
+
+    async fn delete_working_order(
+        &self,
+        session: &IgSession,
+        deal_id: &str,
+    ) -> Result<DeleteWorkingOrderResponse, AppError> {
+        self.order_service
+            .delete_working_order(session, deal_id)
+            .await
+    }
+}
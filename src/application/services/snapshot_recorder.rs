@@ -0,0 +1,104 @@
+use crate::application::services::AccountService;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::storage::account_snapshots::{AccountSnapshot, AccountSnapshotStore};
+use crate::utils::periodic::{PeriodicTask, ShutdownToken};
+use crate::utils::rate_limiter::{RateLimiter, wait_if_set};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// Default interval between two account snapshots
+pub const DEFAULT_ACCOUNT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Records the positions, working orders and balance of the session account over time
+///
+/// A task running [`AccountSnapshotRecorder::run`] stores a snapshot on an interval,
+/// giving a history to reconcile a live strategy against or to look at when debugging
+/// it. Each snapshot takes three requests, all paced by the rate limiter.
+pub struct AccountSnapshotRecorder<A: AccountService, S: AccountSnapshotStore> {
+    account_service: A,
+    store: S,
+    interval: Duration,
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl<A: AccountService, S: AccountSnapshotStore> AccountSnapshotRecorder<A, S> {
+    /// Creates a recorder reading the account from `account_service` into `store`
+    pub fn new(account_service: A, store: S) -> Self {
+        Self {
+            account_service,
+            store,
+            interval: DEFAULT_ACCOUNT_SNAPSHOT_INTERVAL,
+            rate_limiter: None,
+        }
+    }
+
+    /// Sets the interval between two snapshots in [`AccountSnapshotRecorder::run`]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Uses a specific rate limiter for account requests
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Returns the configured rate limiter, or the account limiter of `session`
+    fn rate_limiter(&self, session: &IgSession) -> Arc<RateLimiter> {
+        self.rate_limiter
+            .clone()
+            .unwrap_or_else(|| session.rate_limiters().account_non_trading.clone())
+    }
+
+    /// Returns the store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Reads the session account and stores a snapshot
+    ///
+    /// # Returns
+    /// The stored snapshot, or `AppError::NotFound` if the session account is not
+    /// among the accounts of the client
+    pub async fn record(&self, session: &IgSession) -> Result<AccountSnapshot, AppError> {
+        wait_if_set(self.rate_limiter.as_deref()).await;
+        let accounts = self.account_service.get_accounts(session).await?.accounts;
+        let account = accounts
+            .iter()
+            .find(|account| account.account_id == session.account_id)
+            .ok_or(AppError::NotFound)?;
+        wait_if_set(self.rate_limiter.as_deref()).await;
+        let positions = self.account_service.get_positions(session).await?.positions;
+        wait_if_set(self.rate_limiter.as_deref()).await;
+        let working_orders = self
+            .account_service
+            .get_working_orders(session)
+            .await?
+            .working_orders;
+
+        let snapshot = AccountSnapshot::new(account, positions, working_orders);
+        self.store.append(&snapshot)?;
+        debug!(
+            "Account {} recorded: {} positions, {} working orders",
+            snapshot.account_id,
+            snapshot.positions.len(),
+            snapshot.working_orders.len()
+        );
+        Ok(snapshot)
+    }
+
+    /// Records a snapshot on the configured interval until `shutdown` is cancelled
+    ///
+    /// Runs as a [`PeriodicTask`] notifying the rate limiter of the recorder.
+    pub async fn run(&self, session: &IgSession, shutdown: &ShutdownToken) {
+        PeriodicTask::new("record the account", self.interval)
+            .with_rate_limiter(self.rate_limiter(session))
+            .run(shutdown, || async {
+                self.record(session).await.map(|_| ())
+            })
+            .await
+    }
+}
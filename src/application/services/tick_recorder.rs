@@ -0,0 +1,259 @@
+use crate::error::AppError;
+use crate::presentation::PriceData;
+use crate::storage::Store;
+use crate::storage::batch_writer::{
+    BatchSink, BatchWriter, BatchWriterConfig, BatchWriterStats, OverflowPolicy,
+};
+use crate::storage::store::{Tick, WriteReport};
+use crate::utils::metrics::set_gauge;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Default number of queued ticks written at once
+pub const DEFAULT_TICK_BATCH_SIZE: usize = 500;
+
+/// Default longest time a tick waits before it is written
+pub const DEFAULT_TICK_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default number of ticks queued while the store is failing or falling behind
+pub const DEFAULT_TICK_CAPACITY: usize = 50_000;
+
+/// Gauge of the ticks queued and not written yet
+pub const TICKS_PENDING: &str = "ig_tick_recorder_pending_ticks";
+
+/// Returns the EPIC of a subscription item, such as `PRICE:<account>:<epic>` or
+/// `MARKET:<epic>`
+///
+/// Names without a prefix are returned unchanged.
+pub fn epic_of_item(item_name: &str) -> &str {
+    item_name.rsplit(':').next().unwrap_or(item_name)
+}
+
+/// Converts a price update into a tick
+///
+/// The tick is timed by the `TIMESTAMP` field of the update, in milliseconds since the
+/// epoch, or by `received` when the update has none.
+///
+/// # Returns
+/// The tick, or `None` if the update carries neither a bid nor an offer
+pub fn tick_from_price(price: &PriceData, received: DateTime<Utc>) -> Option<Tick> {
+    let bid = price.fields.bid_price1();
+    let offer = price.fields.ask_price1();
+    if bid.is_none() && offer.is_none() {
+        return None;
+    }
+    let timestamp = price
+        .fields
+        .timestamp()
+        .and_then(|millis| DateTime::from_timestamp_millis(millis as i64))
+        .unwrap_or(received);
+    Some(Tick {
+        epic: epic_of_item(&price.item_name).to_string(),
+        timestamp,
+        bid,
+        offer,
+    })
+}
+
+/// Sink of a [`TickRecorder`], writing the ticks of each market of a batch apart
+struct EpicPartitions<S>(Arc<S>);
+
+#[async_trait]
+impl<S: Store + 'static> BatchSink<Tick> for EpicPartitions<S> {
+    async fn write_batch(&self, batch: &[Tick]) -> Result<WriteReport, AppError> {
+        let mut partitions: BTreeMap<&str, Vec<Tick>> = BTreeMap::new();
+        for tick in batch {
+            partitions
+                .entry(tick.epic.as_str())
+                .or_default()
+                .push(tick.clone());
+        }
+        // Writes are idempotent, so a retried batch may rewrite the markets written
+        let mut report = WriteReport::default();
+        for (epic, ticks) in partitions {
+            let written = self.0.put_ticks(&ticks).await?;
+            debug!(
+                "{} ticks of {} written, {} already stored",
+                written.total(),
+                epic,
+                written.conflicts()
+            );
+            report += written;
+        }
+        Ok(report)
+    }
+}
+
+/// Records streamed prices as ticks in a [`Store`]
+///
+/// Ticks are queued in a [`BatchWriter`], which writes them once
+/// [`DEFAULT_TICK_BATCH_SIZE`] are queued and on the flush interval, so quiet markets
+/// reach the store too. Each write holds the ticks of a single market. Failed writes
+/// are retried with backoff as configured by [`TickRecorder::with_writer_config`].
+///
+/// The queue holds at most [`DEFAULT_TICK_CAPACITY`] ticks, see
+/// [`TickRecorder::with_capacity`]. While the store is down the queue fills up and the
+/// following ticks are dropped rather than stalling the price stream; they are counted
+/// in [`BatchWriterStats::dropped`].
+///
+/// With the `metrics` feature, the ticks queued and not written yet are reported under
+/// [`TICKS_PENDING`]: a backlog that keeps growing means the writes fail or fall
+/// behind. Wrap the store in a
+/// [`MeteredStore`](crate::storage::metered_store::MeteredStore) to measure the
+/// writes themselves.
+pub struct TickRecorder<S: Store> {
+    store: Arc<S>,
+    config: BatchWriterConfig,
+    epics: Option<HashSet<String>>,
+    writer: Option<BatchWriter<Tick>>,
+    closed: BatchWriterStats,
+}
+
+impl<S: Store + 'static> TickRecorder<S> {
+    /// Creates a recorder writing ticks into `store`
+    pub fn new(store: S) -> Self {
+        Self {
+            store: Arc::new(store),
+            config: BatchWriterConfig::new()
+                .with_batch_size(DEFAULT_TICK_BATCH_SIZE)
+                .with_flush_interval(DEFAULT_TICK_FLUSH_INTERVAL)
+                .with_capacity(DEFAULT_TICK_CAPACITY)
+                .with_overflow(OverflowPolicy::Drop),
+            epics: None,
+            writer: None,
+            closed: BatchWriterStats::default(),
+        }
+    }
+
+    /// Sets the number of queued ticks written at once
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.config = self.config.with_batch_size(batch_size);
+        self
+    }
+
+    /// Sets the longest time a tick waits in a partial batch
+    pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.config = self.config.with_flush_interval(flush_interval);
+        self
+    }
+
+    /// Sets the number of ticks queued before the following ones are dropped
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.config = self.config.with_capacity(capacity);
+        self
+    }
+
+    /// Replaces the settings of the batch writer, including its queue and retries
+    pub fn with_writer_config(mut self, config: BatchWriterConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Records only the ticks of `epics`, ignoring the other markets of the stream
+    pub fn with_epics<I, E>(mut self, epics: I) -> Self
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<String>,
+    {
+        self.epics = Some(epics.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Returns the store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the counters of the ticks recorded so far
+    pub fn stats(&self) -> BatchWriterStats {
+        let mut stats = self.closed;
+        if let Some(writer) = &self.writer {
+            stats += writer.stats();
+        }
+        stats
+    }
+
+    /// Returns the number of ticks queued and not written yet
+    pub fn pending(&self) -> usize {
+        let stats = self.stats();
+        let done = stats.written.total() as u64 + stats.discarded;
+        stats.accepted.saturating_sub(done) as usize
+    }
+
+    /// Queues the tick of a price update
+    ///
+    /// Starts the batch writer on the first tick, so it must be called within a Tokio
+    /// runtime.
+    ///
+    /// # Returns
+    /// True if a tick was queued, false when the update is skipped or the tick dropped
+    pub async fn record(&mut self, price: &PriceData) -> bool {
+        let Some(tick) = tick_from_price(price, Utc::now()) else {
+            return false;
+        };
+        if let Some(epics) = &self.epics
+            && !epics.contains(&tick.epic)
+        {
+            return false;
+        }
+        let writer = self.writer.get_or_insert_with(|| {
+            BatchWriter::spawn(EpicPartitions(self.store.clone()), self.config.clone())
+        });
+        let queued = writer.write(tick).await;
+        set_gauge(TICKS_PENDING, self.pending() as f64, &[]);
+        queued
+    }
+
+    /// Writes the queued ticks, waiting until their batch is written or discarded
+    pub async fn flush(&self) {
+        if let Some(writer) = &self.writer {
+            writer.flush().await;
+            set_gauge(TICKS_PENDING, self.pending() as f64, &[]);
+        }
+    }
+
+    /// Writes the queued ticks and stops the batch writer
+    ///
+    /// Recording again starts a new one.
+    ///
+    /// # Returns
+    /// The counters of the ticks recorded so far
+    pub async fn close(&mut self) -> BatchWriterStats {
+        if let Some(writer) = self.writer.take() {
+            self.closed += writer.close().await;
+            set_gauge(TICKS_PENDING, self.pending() as f64, &[]);
+        }
+        self.closed
+    }
+
+    /// Records the prices of `prices` until it ends, then writes the queued ticks
+    ///
+    /// # Returns
+    /// The counters of the ticks recorded so far
+    pub async fn run<P>(&mut self, prices: P) -> BatchWriterStats
+    where
+        P: Stream<Item = PriceData>,
+    {
+        info!(
+            "Recording ticks, flushing every {:?}",
+            self.config.flush_interval
+        );
+        let mut prices = std::pin::pin!(prices);
+        while let Some(price) = prices.next().await {
+            self.record(&price).await;
+        }
+        let stats = self.close().await;
+        info!(
+            "Tick recording done: {} ticks written, {} dropped, {} discarded",
+            stats.written.total(),
+            stats.dropped,
+            stats.discarded
+        );
+        stats
+    }
+}
@@ -0,0 +1,287 @@
+use crate::application::models::account::Position;
+use crate::application::models::order::{ClosePositionRequest, Direction, UpdatePositionRequest};
+use crate::application::services::OrderService;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{debug, info, warn};
+
+/// Trailing stop maintained on the client side for a single position
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SyntheticTrailingStop {
+    /// Deal ID of the protected position
+    pub deal_id: String,
+    /// EPIC of the market
+    pub epic: String,
+    /// Direction of the position
+    pub direction: Direction,
+    /// Size of the position
+    pub size: f64,
+    /// Currency of the position
+    pub currency: String,
+    /// Distance in points kept between the price and the stop
+    pub distance: f64,
+    /// Step by which the stop follows the price
+    pub increment: f64,
+    /// Current stop level
+    pub stop_level: f64,
+    /// Take profit level of the position, kept when the stop is sent to the server
+    #[serde(default)]
+    pub limit_level: Option<f64>,
+}
+
+/// What happened to a synthetic trailing stop on a price update
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrailingStopEvent {
+    /// The price did not move far enough to move the stop
+    Unchanged,
+    /// The stop followed the price to a new level
+    Moved {
+        /// Previous stop level
+        from: f64,
+        /// New stop level
+        to: f64,
+    },
+    /// The closing price reached the stop
+    Triggered {
+        /// Stop level that was hit
+        stop_level: f64,
+        /// Closing price that hit it
+        price: f64,
+    },
+}
+
+impl SyntheticTrailingStop {
+    /// Creates a trailing stop `distance` points away from `price`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        deal_id: String,
+        epic: String,
+        direction: Direction,
+        size: f64,
+        currency: String,
+        distance: f64,
+        increment: f64,
+        price: f64,
+    ) -> Self {
+        let stop_level = match direction {
+            Direction::Buy => price - distance,
+            Direction::Sell => price + distance,
+        };
+        Self {
+            deal_id,
+            epic,
+            direction,
+            size,
+            currency,
+            distance,
+            increment,
+            stop_level,
+            limit_level: None,
+        }
+    }
+
+    /// Sets the take profit level of the position
+    pub fn with_limit_level(mut self, limit_level: Option<f64>) -> Self {
+        self.limit_level = limit_level;
+        self
+    }
+
+    /// Creates a trailing stop for an open position
+    ///
+    /// The stop starts `distance` points away from the current closing price, or at
+    /// the existing stop of the position if that one is tighter.
+    pub fn from_position(position: &Position, distance: f64, increment: f64) -> Self {
+        let details = &position.position;
+        let mut stop = Self::new(
+            details.deal_id.clone(),
+            position.market.epic.clone(),
+            details.direction.clone(),
+            details.size,
+            details.currency.clone(),
+            distance,
+            increment,
+            Self::closing_price(
+                &details.direction,
+                position.market.bid,
+                position.market.offer,
+            ),
+        )
+        .with_limit_level(details.limit_level);
+        if let Some(level) = details.stop_level {
+            stop.stop_level = match details.direction {
+                Direction::Buy => stop.stop_level.max(level),
+                Direction::Sell => stop.stop_level.min(level),
+            };
+        }
+        stop
+    }
+
+    /// Price at which a position in `direction` would be closed
+    fn closing_price(direction: &Direction, bid: f64, offer: f64) -> f64 {
+        match direction {
+            Direction::Buy => bid,
+            Direction::Sell => offer,
+        }
+    }
+
+    /// Updates the stop with a new quote
+    ///
+    /// The stop only moves in the favourable direction, by whole increments, and is
+    /// triggered once the closing price (bid for longs, offer for shorts) reaches it.
+    pub fn update(&mut self, bid: f64, offer: f64) -> TrailingStopEvent {
+        let price = Self::closing_price(&self.direction, bid, offer);
+        let triggered = match self.direction {
+            Direction::Buy => price <= self.stop_level,
+            Direction::Sell => price >= self.stop_level,
+        };
+        if triggered {
+            return TrailingStopEvent::Triggered {
+                stop_level: self.stop_level,
+                price,
+            };
+        }
+
+        let gap = match self.direction {
+            Direction::Buy => price - self.distance - self.stop_level,
+            Direction::Sell => self.stop_level - (price + self.distance),
+        };
+        // A non-positive increment lets the stop follow the price continuously
+        let shift = if self.increment > 0.0 {
+            (gap / self.increment).floor() * self.increment
+        } else {
+            gap
+        };
+        if shift <= 0.0 {
+            return TrailingStopEvent::Unchanged;
+        }
+
+        let from = self.stop_level;
+        self.stop_level = match self.direction {
+            Direction::Buy => from + shift,
+            Direction::Sell => from - shift,
+        };
+        TrailingStopEvent::Moved {
+            from,
+            to: self.stop_level,
+        }
+    }
+}
+
+/// Maintains synthetic trailing stops for markets without server-side trailing stops
+///
+/// Feed it quotes with [`TrailingStopManager::on_price`]: positions whose stop is hit
+/// are closed at market. With [`TrailingStopManager::with_server_stops`] every move is
+/// also sent as a plain stop level, so the position stays protected if the client
+/// stops running.
+pub struct TrailingStopManager<'a, S: OrderService> {
+    order_service: &'a S,
+    stops: HashMap<String, SyntheticTrailingStop>,
+    server_stops: bool,
+}
+
+impl<'a, S: OrderService> TrailingStopManager<'a, S> {
+    /// Creates a manager closing positions through `order_service`
+    pub fn new(order_service: &'a S) -> Self {
+        Self {
+            order_service,
+            stops: HashMap::new(),
+            server_stops: false,
+        }
+    }
+
+    /// Sends every stop move to the server as the stop level of the position
+    pub fn with_server_stops(mut self, server_stops: bool) -> Self {
+        self.server_stops = server_stops;
+        self
+    }
+
+    /// Starts maintaining a trailing stop, replacing any stop on the same deal
+    pub fn track(&mut self, stop: SyntheticTrailingStop) {
+        debug!(
+            "Tracking trailing stop for {} at {}",
+            stop.deal_id, stop.stop_level
+        );
+        self.stops.insert(stop.deal_id.clone(), stop);
+    }
+
+    /// Stops maintaining the trailing stop of a deal
+    pub fn untrack(&mut self, deal_id: &str) -> Option<SyntheticTrailingStop> {
+        self.stops.remove(deal_id)
+    }
+
+    /// Returns the trailing stop of a deal
+    pub fn get(&self, deal_id: &str) -> Option<&SyntheticTrailingStop> {
+        self.stops.get(deal_id)
+    }
+
+    /// Returns every tracked trailing stop
+    pub fn stops(&self) -> impl Iterator<Item = &SyntheticTrailingStop> {
+        self.stops.values()
+    }
+
+    /// Applies a quote to every trailing stop on `epic`
+    ///
+    /// Triggered positions are closed at market and no longer tracked. A failed
+    /// request is logged without stopping the other stops: a position that could not
+    /// be closed stays tracked, so the next quote hitting its stop retries the close.
+    ///
+    /// # Returns
+    /// The deal IDs whose stop moved or triggered, with the corresponding event
+    pub async fn on_price(
+        &mut self,
+        session: &IgSession,
+        epic: &str,
+        bid: f64,
+        offer: f64,
+    ) -> Result<Vec<(String, TrailingStopEvent)>, AppError> {
+        let mut events = Vec::new();
+        for stop in self.stops.values_mut().filter(|stop| stop.epic == epic) {
+            let event = stop.update(bid, offer);
+            if event != TrailingStopEvent::Unchanged {
+                events.push((stop.deal_id.clone(), event));
+            }
+        }
+
+        for (deal_id, event) in &events {
+            match event {
+                TrailingStopEvent::Moved { to, .. } => {
+                    debug!("Trailing stop for {} moved to {}", deal_id, to);
+                    if self.server_stops {
+                        // Without the limit level, IG would remove the take profit
+                        let limit_level = self.stops.get(deal_id).and_then(|stop| stop.limit_level);
+                        let update = UpdatePositionRequest::levels(Some(*to), limit_level);
+                        if let Err(e) = self
+                            .order_service
+                            .update_position(session, deal_id, &update)
+                            .await
+                        {
+                            warn!("Failed to move the stop of {} to {}: {}", deal_id, to, e);
+                        }
+                    }
+                }
+                TrailingStopEvent::Triggered { stop_level, price } => {
+                    info!(
+                        "Trailing stop for {} hit at {} (stop {}), closing",
+                        deal_id, price, stop_level
+                    );
+                    let Some(stop) = self.stops.get(deal_id) else {
+                        continue;
+                    };
+                    let close =
+                        ClosePositionRequest::for_deal(&stop.deal_id, &stop.direction, stop.size)
+                            .with_market(&stop.epic, &stop.currency);
+                    match self.order_service.close_position(session, &close).await {
+                        Ok(_) => {
+                            self.stops.remove(deal_id);
+                        }
+                        Err(e) => warn!("Failed to close {} on its trailing stop: {}", deal_id, e),
+                    }
+                }
+                TrailingStopEvent::Unchanged => {}
+            }
+        }
+        Ok(events)
+    }
+}
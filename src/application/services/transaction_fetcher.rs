@@ -0,0 +1,223 @@
+use crate::application::models::account::{AccountTransaction, TransactionType};
+use crate::application::models::transaction::EnrichedTransaction;
+use crate::application::services::AccountService;
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::utils::rate_limiter::{RateLimiter, wait_if_set};
+use chrono::{DateTime, Duration, Utc};
+use futures::future;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Default length of the date windows requested by [`TransactionFetcher`], in days
+pub const DEFAULT_FETCH_WINDOW_DAYS: i64 = 90;
+
+/// Date format of the transaction history requests
+const REQUEST_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Callback run by [`TransactionFetcher`] after each window
+pub type ProgressCallback = Box<dyn Fn(&FetchProgress) + Send + Sync>;
+
+/// Splits `[from, to]` into consecutive windows of at most `window`
+///
+/// History requests include both of their dates, so every window but the last ends one
+/// second before the next starts and no transaction is returned twice. The last window
+/// ends at `to`; no window is returned when `from` is not before `to`.
+pub fn date_windows(
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    window: Duration,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut windows = Vec::new();
+    let mut start = from;
+    while start < to {
+        let end = (start + window).min(to);
+        if end < to {
+            windows.push((start, end - Duration::seconds(1)));
+        } else {
+            windows.push((start, end));
+        }
+        start = end;
+    }
+    windows
+}
+
+/// Progress of a windowed fetch, reported after each window
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchProgress {
+    /// Number of the window just fetched, from 1
+    pub window: usize,
+    /// Number of windows of the fetch
+    pub windows: usize,
+    /// Start of the window
+    pub from: DateTime<Utc>,
+    /// End of the window
+    pub to: DateTime<Utc>,
+    /// Transactions returned for the window
+    pub fetched: usize,
+    /// Transactions returned since the first window
+    pub total_fetched: usize,
+}
+
+impl FetchProgress {
+    /// Share of the windows fetched, from 0 to 1
+    pub fn fraction(&self) -> f64 {
+        if self.windows == 0 {
+            1.0
+        } else {
+            self.window as f64 / self.windows as f64
+        }
+    }
+}
+
+/// Fetches long transaction histories in bounded date windows
+///
+/// IG times out or pages for a long time on requests covering years, so the range is
+/// split into windows of [`DEFAULT_FETCH_WINDOW_DAYS`] days, requested one after the
+/// other, each waiting for the non-trading rate limiter. The registered callbacks are
+/// run after every window with the [`FetchProgress`] of the fetch.
+pub struct TransactionFetcher<A: AccountService> {
+    account_service: A,
+    window: Duration,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    callbacks: Vec<ProgressCallback>,
+}
+
+impl<A: AccountService> TransactionFetcher<A> {
+    /// Creates a fetcher reading transactions from `account_service`
+    pub fn new(account_service: A) -> Self {
+        Self {
+            account_service,
+            window: Duration::days(DEFAULT_FETCH_WINDOW_DAYS),
+            rate_limiter: None,
+            callbacks: Vec::new(),
+        }
+    }
+
+    /// Sets the length of the date windows, in days
+    pub fn with_window_days(mut self, days: i64) -> Self {
+        self.window = Duration::days(days.max(1));
+        self
+    }
+
+    /// Uses a specific rate limiter for transaction requests
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Runs `callback` after every window
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&FetchProgress) + Send + Sync + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Returns the length of the date windows
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Fetches the transactions dated within `[from, to]`, window by window
+    ///
+    /// # Returns
+    /// The transactions in the order IG returned them, window after window, or the
+    /// first error
+    pub async fn fetch(
+        &self,
+        session: &IgSession,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<AccountTransaction>, AppError> {
+        let windows = date_windows(from, to, self.window);
+        info!(
+            "Fetching transactions from {} to {} in {} windows",
+            from,
+            to,
+            windows.len()
+        );
+        let mut transactions = Vec::new();
+        for (index, (start, end)) in windows.iter().enumerate() {
+            let fetched = self.fetch_window(session, *start, *end).await?;
+            let count = fetched.len();
+            transactions.extend(fetched);
+            self.report(&FetchProgress {
+                window: index + 1,
+                windows: windows.len(),
+                from: *start,
+                to: *end,
+                fetched: count,
+                total_fetched: transactions.len(),
+            });
+        }
+        Ok(transactions)
+    }
+
+    /// Fetches the transactions of a single window, after waiting for the rate limiter
+    pub async fn fetch_window(
+        &self,
+        session: &IgSession,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<AccountTransaction>, AppError> {
+        wait_if_set(self.rate_limiter.as_deref()).await;
+        self.account_service
+            .stream_transactions(
+                session,
+                &from.format(REQUEST_DATE_FORMAT).to_string(),
+                &to.format(REQUEST_DATE_FORMAT).to_string(),
+                TransactionType::All,
+            )
+            .try_collect()
+            .await
+    }
+
+    /// Streams the transactions dated within `[from, to]`, window by window
+    ///
+    /// Transactions are parsed as they arrive, page after page, so a history is never
+    /// held in memory as a whole; each window waits for the rate limiter when the
+    /// stream reaches it. Progress callbacks are not run. The stream ends after the
+    /// first error.
+    pub fn stream<'a>(
+        &'a self,
+        session: &'a IgSession,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxStream<'a, Result<EnrichedTransaction, AppError>> {
+        stream::iter(date_windows(from, to, self.window))
+            .then(move |(start, end)| async move {
+                wait_if_set(self.rate_limiter.as_deref()).await;
+                debug!("Streaming transactions from {} to {}", start, end);
+                self.account_service.stream_transactions(
+                    session,
+                    &start.format(REQUEST_DATE_FORMAT).to_string(),
+                    &end.format(REQUEST_DATE_FORMAT).to_string(),
+                    TransactionType::All,
+                )
+            })
+            .flatten()
+            .map_ok(EnrichedTransaction::new)
+            .scan(false, |failed, item| {
+                if *failed {
+                    return future::ready(None);
+                }
+                *failed = item.is_err();
+                future::ready(Some(item))
+            })
+            .boxed()
+    }
+
+    /// Runs the progress callbacks
+    pub(crate) fn report(&self, progress: &FetchProgress) {
+        debug!(
+            "Transaction window {}/{} fetched: {} transactions until {}",
+            progress.window, progress.windows, progress.fetched, progress.to
+        );
+        for callback in &self.callbacks {
+            callback(progress);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::application::models::account::AccountTransaction;
+use crate::application::services::AccountService;
+use crate::application::services::transaction_fetcher::{
+    FetchProgress, TransactionFetcher, date_windows,
+};
+use crate::error::AppError;
+use crate::session::interface::IgSession;
+use crate::storage::transaction_store::{SyncCheckpoint, TransactionStore, transaction_date};
+use crate::utils::rate_limiter::RateLimiter;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Default length of the date windows requested by [`TransactionSync`], in days
+pub const DEFAULT_SYNC_WINDOW_DAYS: i64 = 30;
+
+/// Outcome of a [`TransactionSync::sync`] run
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncReport {
+    /// Date windows requested
+    pub windows: usize,
+    /// Transactions returned by IG
+    pub fetched: usize,
+    /// New transactions stored
+    pub stored: usize,
+    /// Checkpoint saved at the end of the run
+    pub checkpoint: SyncCheckpoint,
+}
+
+/// Mirrors the transaction history of the session account into a store, incrementally
+///
+/// The first run fetches from the start date; later runs resume from the checkpoint of
+/// the store, so only newer transactions are requested. The range is split into windows
+/// of [`DEFAULT_SYNC_WINDOW_DAYS`] days fetched by a [`TransactionFetcher`], and the
+/// checkpoint is saved after every window so an interrupted run resumes where it
+/// stopped. IG may still add transactions to the last window of a run, so it only
+/// moves the checkpoint up to its last transaction and the next run requests it again
+/// from there.
+pub struct TransactionSync<A: AccountService, S: TransactionStore> {
+    fetcher: TransactionFetcher<A>,
+    store: S,
+    start: DateTime<Utc>,
+}
+
+impl<A: AccountService, S: TransactionStore> TransactionSync<A, S> {
+    /// Creates a sync reading transactions from `account_service` into `store`
+    ///
+    /// `start` is the date the first run fetches from.
+    pub fn new(account_service: A, store: S, start: DateTime<Utc>) -> Self {
+        Self {
+            fetcher: TransactionFetcher::new(account_service)
+                .with_window_days(DEFAULT_SYNC_WINDOW_DAYS),
+            store,
+            start,
+        }
+    }
+
+    /// Sets the length of the date windows, in days
+    pub fn with_window_days(mut self, days: i64) -> Self {
+        self.fetcher = self.fetcher.with_window_days(days);
+        self
+    }
+
+    /// Uses a specific rate limiter for transaction requests
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.fetcher = self.fetcher.with_rate_limiter(rate_limiter);
+        self
+    }
+
+    /// Runs `callback` after every window, once its transactions are stored
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&FetchProgress) + Send + Sync + 'static,
+    {
+        self.fetcher = self.fetcher.on_progress(callback);
+        self
+    }
+
+    /// Returns the store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Fetches the transactions newer than the checkpoint, up to now
+    pub async fn sync(&self, session: &IgSession) -> Result<SyncReport, AppError> {
+        self.sync_until(session, Utc::now()).await
+    }
+
+    /// Fetches the transactions newer than the checkpoint, up to `to`
+    ///
+    /// # Returns
+    /// The report of the run, or the first error; windows stored before the error are
+    /// kept and covered by the checkpoint
+    pub async fn sync_until(
+        &self,
+        session: &IgSession,
+        to: DateTime<Utc>,
+    ) -> Result<SyncReport, AppError> {
+        let mut checkpoint = self
+            .store
+            .checkpoint()
+            .await?
+            .unwrap_or_else(|| SyncCheckpoint::new(self.start));
+        let mut report = SyncReport {
+            windows: 0,
+            fetched: 0,
+            stored: 0,
+            checkpoint: checkpoint.clone(),
+        };
+        info!(
+            "Syncing transactions from {} to {}",
+            checkpoint.last_date_utc, to
+        );
+
+        let windows = date_windows(checkpoint.last_date_utc, to, self.fetcher.window());
+        for (index, (from, until)) in windows.iter().copied().enumerate() {
+            let transactions = self.fetcher.fetch_window(session, from, until).await?;
+            report.windows += 1;
+            report.fetched += transactions.len();
+            let fetched = transactions.len();
+
+            let mut fresh = Vec::with_capacity(transactions.len());
+            for transaction in transactions {
+                let date = transaction_date(&transaction)?;
+                if !checkpoint.covers(date, &transaction.reference) {
+                    fresh.push((date, transaction));
+                }
+            }
+            fresh.sort_by_key(|(date, _)| *date);
+            for (date, transaction) in &fresh {
+                checkpoint.advance(*date, &transaction.reference);
+            }
+            let fresh: Vec<AccountTransaction> = fresh
+                .into_iter()
+                .map(|(_, transaction)| transaction)
+                .collect();
+            report.stored += self.store.append(&fresh).await?;
+            // Past windows are complete up to the start of the next one; the last one may
+            // still receive transactions dated before `to`, so it only moves up to its
+            // last transaction
+            if let Some((next, _)) = windows.get(index + 1)
+                && *next > checkpoint.last_date_utc
+            {
+                checkpoint = SyncCheckpoint::new(*next);
+            }
+            self.store.save_checkpoint(&checkpoint).await?;
+            debug!("Transactions synced until {}: {} new", until, fresh.len());
+            self.fetcher.report(&FetchProgress {
+                window: index + 1,
+                windows: windows.len(),
+                from,
+                to: until,
+                fetched,
+                total_fetched: report.fetched,
+            });
+        }
+
+        report.checkpoint = checkpoint;
+        info!(
+            "Transaction sync done: {} fetched, {} stored",
+            report.fetched, report.stored
+        );
+        Ok(report)
+    }
+}
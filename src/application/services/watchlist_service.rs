@@ -0,0 +1,203 @@
+use crate::application::models::watchlist::{
+    AddToWatchlistRequest, CreateWatchlistRequest, CreateWatchlistResponse, WatchlistMarkets,
+    WatchlistStatusResponse, Watchlists,
+};
+use crate::application::services::WatchlistService;
+use crate::{
+    config::Config, error::AppError, session::interface::IgSession,
+    transport::http_client::IgHttpClient,
+};
+use async_trait::async_trait;
+use reqwest::Method;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Implementation of the watchlist service
+pub struct WatchlistServiceImpl<T: IgHttpClient> {
+    config: Arc<Config>,
+    client: Arc<T>,
+}
+
+impl<T: IgHttpClient> WatchlistServiceImpl<T> {
+    /// Creates a new instance of the watchlist service
+    pub fn new(config: Arc<Config>, client: Arc<T>) -> Self {
+        Self { config, client }
+    }
+
+    /// Gets the current configuration
+    ///
+    /// # Returns
+    /// * Reference to the current configuration
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Sets a new configuration
+    ///
+    /// # Arguments
+    /// * `config` - The new configuration to use
+    pub fn set_config(&mut self, config: Arc<Config>) {
+        self.config = config;
+    }
+}
+
+#[async_trait]
+impl<T: IgHttpClient + 'static> WatchlistService for WatchlistServiceImpl<T> {
+    async fn get_watchlists(&self, session: &IgSession) -> Result<Watchlists, AppError> {
+        info!("Getting watchlists");
+
+        let result = self
+            .client
+            .request::<(), Watchlists>(Method::GET, "watchlists", session, None, "1")
+            .await?;
+
+        debug!("{} watchlists found", result.watchlists.len());
+        Ok(result)
+    }
+
+    async fn create_watchlist(
+        &self,
+        session: &IgSession,
+        name: &str,
+        epics: &[String],
+    ) -> Result<CreateWatchlistResponse, AppError> {
+        info!("Creating watchlist: {}", name);
+
+        let body = CreateWatchlistRequest {
+            name: name.to_string(),
+            epics: if epics.is_empty() {
+                None
+            } else {
+                Some(epics.to_vec())
+            },
+        };
+        let result = self
+            .client
+            .request::<CreateWatchlistRequest, CreateWatchlistResponse>(
+                Method::POST,
+                "watchlists",
+                session,
+                Some(&body),
+                "1",
+            )
+            .await?;
+
+        debug!(
+            "Watchlist created with ID: {} ({:?})",
+            result.watchlist_id, result.status
+        );
+        Ok(result)
+    }
+
+    async fn get_watchlist(
+        &self,
+        session: &IgSession,
+        watchlist_id: &str,
+    ) -> Result<WatchlistMarkets, AppError> {
+        let path = format!("watchlists/{watchlist_id}");
+        info!("Getting watchlist: {}", watchlist_id);
+
+        let result = self
+            .client
+            .request::<(), WatchlistMarkets>(Method::GET, &path, session, None, "1")
+            .await?;
+
+        debug!(
+            "{} markets found in watchlist {}",
+            result.markets.len(),
+            watchlist_id
+        );
+        Ok(result)
+    }
+
+    async fn delete_watchlist(
+        &self,
+        session: &IgSession,
+        watchlist_id: &str,
+    ) -> Result<WatchlistStatusResponse, AppError> {
+        let path = format!("watchlists/{watchlist_id}");
+        info!("Deleting watchlist: {}", watchlist_id);
+
+        let result = self
+            .client
+            .request::<(), WatchlistStatusResponse>(Method::DELETE, &path, session, None, "1")
+            .await?;
+
+        debug!("Watchlist {} deleted: {}", watchlist_id, result.status);
+        Ok(result)
+    }
+
+    async fn add_to_watchlist(
+        &self,
+        session: &IgSession,
+        watchlist_id: &str,
+        epic: &str,
+    ) -> Result<WatchlistStatusResponse, AppError> {
+        let path = format!("watchlists/{watchlist_id}");
+        info!("Adding {} to watchlist {}", epic, watchlist_id);
+
+        let body = AddToWatchlistRequest {
+            epic: epic.to_string(),
+        };
+        let result = self
+            .client
+            .request::<AddToWatchlistRequest, WatchlistStatusResponse>(
+                Method::PUT,
+                &path,
+                session,
+                Some(&body),
+                "1",
+            )
+            .await?;
+
+        debug!(
+            "Added {} to watchlist {}: {}",
+            epic, watchlist_id, result.status
+        );
+        Ok(result)
+    }
+
+    async fn remove_from_watchlist(
+        &self,
+        session: &IgSession,
+        watchlist_id: &str,
+        epic: &str,
+    ) -> Result<WatchlistStatusResponse, AppError> {
+        let path = format!("watchlists/{watchlist_id}/{epic}");
+        info!("Removing {} from watchlist {}", epic, watchlist_id);
+
+        let result = self
+            .client
+            .request::<(), WatchlistStatusResponse>(Method::DELETE, &path, session, None, "1")
+            .await?;
+
+        debug!(
+            "Removed {} from watchlist {}: {}",
+            epic, watchlist_id, result.status
+        );
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::transport::http_client::IgHttpClientImpl;
+    use crate::utils::rate_limiter::RateLimitType;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_get_and_set_config() {
+        let config = Arc::new(Config::with_rate_limit_type(
+            RateLimitType::NonTradingAccount,
+            0.7,
+        ));
+        let client = Arc::new(IgHttpClientImpl::new(config.clone()));
+        let mut service = WatchlistServiceImpl::new(config.clone(), client.clone());
+        assert!(std::ptr::eq(service.get_config(), &*config));
+        let new_cfg = Arc::new(Config::default());
+        service.set_config(new_cfg.clone());
+        assert!(std::ptr::eq(service.get_config(), &*new_cfg));
+    }
+}
@@ -8,6 +8,16 @@ pub const ERROR_COOLDOWN_SECONDS: u64 = 300;
 pub const DEFAULT_SLEEP_TIME: u64 = 24;
 /// Default page size for API requests
 pub const DEFAULT_PAGE_SIZE: u32 = 50;
+/// Maximum number of EPICs accepted by a single `GET /markets?epics=` request
+pub const MAX_EPICS_PER_REQUEST: usize = 50;
+/// Maximum number of market detail batches requested concurrently
+pub const MAX_CONCURRENT_MARKET_REQUESTS: usize = 4;
+/// First delay in milliseconds between two polls of a deal confirmation
+pub const CONFIRMATION_INITIAL_BACKOFF_MS: u64 = 100;
+/// Maximum delay in milliseconds between two polls of a deal confirmation
+pub const CONFIRMATION_MAX_BACKOFF_MS: u64 = 2000;
+/// Time in milliseconds to wait for the confirmation of an order sent by a helper
+pub const ORDER_CONFIRMATION_TIMEOUT_MS: u64 = 5000;
 
 // Constants for rate limiter configuration
 /// Base delay in milliseconds used for proximity-based delays in the rate limiter
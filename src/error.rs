@@ -3,6 +3,7 @@
    Email: jb@taunais.com
    Date: 12/5/25
 ******************************************************************************/
+use crate::application::services::risk_guard::RiskViolation;
 use reqwest::StatusCode;
 use std::fmt::{Display, Formatter};
 use std::{fmt, io};
@@ -141,6 +142,9 @@ pub enum AppError {
     NotFound,
     /// API rate limit exceeded
     RateLimitExceeded,
+    /// The weekly allowance of historical price points is spent; unlike
+    /// [`AppError::RateLimitExceeded`], retrying before it resets fails again
+    HistoricalAllowanceExceeded,
     /// Error during serialization or deserialization
     SerializationError(String),
     /// WebSocket communication error
@@ -159,6 +163,18 @@ pub enum AppError {
     ///     that were violated.
     ///
     InvalidInput(String),
+    /// An operation did not complete within its deadline
+    Timeout(String),
+    /// An order was rejected locally for breaking a client-side risk limit
+    RiskLimit(RiskViolation),
+    /// A database was written by a later version of the crate, with a schema this
+    /// version cannot use
+    IncompatibleSchema {
+        /// Schema version of the database
+        found: i64,
+        /// Latest schema version known to this version of the crate
+        supported: i64,
+    },
 }
 
 impl Display for AppError {
@@ -172,10 +188,20 @@ impl Display for AppError {
             AppError::Unauthorized => write!(f, "unauthorized"),
             AppError::NotFound => write!(f, "not found"),
             AppError::RateLimitExceeded => write!(f, "rate limit exceeded"),
+            AppError::HistoricalAllowanceExceeded => {
+                write!(f, "historical price data allowance exceeded")
+            }
             AppError::SerializationError(s) => write!(f, "serialization error: {s}"),
             AppError::WebSocketError(s) => write!(f, "websocket error: {s}"),
             AppError::Deserialization(s) => write!(f, "deserialization error: {s}"),
             AppError::InvalidInput(s) => write!(f, "invalid input: {s}"),
+            AppError::Timeout(s) => write!(f, "timeout: {s}"),
+            AppError::RiskLimit(v) => write!(f, "risk limit: {v}"),
+            AppError::IncompatibleSchema { found, supported } => write!(
+                f,
+                "incompatible schema: database is at version {found}, newer than the \
+                 version {supported} supported; upgrade the crate to use it"
+            ),
         }
     }
 }
@@ -374,6 +374,11 @@ impl ChartData {
         !self.is_tick()
     }
 
+    /// Gets the name of the subscribed item, `CHART:{epic}:{scale}`
+    pub fn item_name(&self) -> &str {
+        &self.item_name
+    }
+
     /// Gets the time scale of the data
     pub fn get_scale(&self) -> &ChartScale {
         &self.scale
@@ -68,3 +68,29 @@ pub enum InstrumentType {
     /// Options
     Options,
 }
+
+impl InstrumentType {
+    /// Returns true for option instrument types
+    pub fn is_option(&self) -> bool {
+        matches!(
+            self,
+            InstrumentType::OptCommodities
+                | InstrumentType::OptCurrencies
+                | InstrumentType::OptIndices
+                | InstrumentType::OptRates
+                | InstrumentType::OptShares
+                | InstrumentType::Options
+        )
+    }
+
+    /// Returns true for knock-out (turbo) instrument types
+    pub fn is_knockout(&self) -> bool {
+        matches!(
+            self,
+            InstrumentType::KnockoutsCommodities
+                | InstrumentType::KnockoutsCurrencies
+                | InstrumentType::KnockoutsIndices
+                | InstrumentType::KnockoutsShares
+        )
+    }
+}
@@ -9,7 +9,7 @@ pub mod serialization;
 pub mod trade;
 
 pub use account::AccountData;
-pub use chart::ChartData;
+pub use chart::{ChartData, ChartScale};
 pub use instrument::InstrumentType;
 pub use market::{
     MarketData, MarketFields, MarketState, build_market_hierarchy, extract_markets_from_hierarchy,
@@ -1,4 +1,6 @@
-use crate::application::models::order::{Direction, OrderType, Status, TimeInForce};
+use crate::application::models::order::{
+    Direction, OrderConfirmation, OrderType, Status, TimeInForce,
+};
 use crate::presentation::serialization::{option_string_empty_as_none, string_as_float_opt};
 use lightstreamer_rs::subscription::ItemUpdate;
 use serde::{Deserialize, Serialize};
@@ -40,6 +42,16 @@ pub struct TradeFields {
     pub wou: Option<WorkingOrderUpdate>,
 }
 
+impl TradeFields {
+    /// Parses the deal confirmation carried by the `CONFIRMS` field, if any
+    ///
+    /// The trade stream pushes the same confirmation as `GET /confirms/{dealReference}`,
+    /// so a connected stream can confirm deals without polling.
+    pub fn confirmation(&self) -> Option<OrderConfirmation> {
+        serde_json::from_str(self.confirms.as_deref()?).ok()
+    }
+}
+
 /// Structure representing details of an open position update.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct OpenPositionUpdate {
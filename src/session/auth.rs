@@ -6,7 +6,7 @@ use crate::{
     error::AuthError,
     session::interface::{IgAuthenticator, IgSession},
     session::response::{AccountSwitchRequest, AccountSwitchResponse, SessionResp},
-    utils::rate_limiter::app_non_trading_limiter,
+    utils::rate_limiter::rate_limiter_registry,
 };
 use async_trait::async_trait;
 use rand;
@@ -74,8 +74,8 @@ impl IgAuthenticator for IgAuth<'_> {
         let mut retry_delay_ms = INITIAL_RETRY_DELAY_MS;
 
         loop {
-            // Use the global app rate limiter for unauthenticated requests
-            let limiter = app_non_trading_limiter();
+            // Use the app rate limiter of the API key for unauthenticated requests
+            let limiter = rate_limiter_registry().app_non_trading(&self.cfg.credentials.api_key);
             limiter.wait().await;
 
             // Following the exact approach from trading-ig Python library
@@ -1,11 +1,10 @@
 use crate::config::Config;
 use crate::error::{AppError, AuthError};
 use crate::utils::rate_limiter::{
-    RateLimitType, RateLimiter, RateLimiterStats, app_non_trading_limiter, create_rate_limiter,
+    RateLimitType, RateLimiterStats, SessionRateLimiters, rate_limiter_registry,
 };
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use tracing::debug;
 
 /// Session information for IG Markets API authentication
 #[derive(Debug, Clone)]
@@ -24,8 +23,10 @@ pub struct IgSession {
     pub lightstreamer_endpoint: String,
     /// API key for API requests
     pub api_key: String,
-    /// Rate limiter for controlling request rates
-    pub(crate) rate_limiter: Option<Arc<RateLimiter>>,
+    /// Type of the limit reported by [`IgSession::get_rate_limit_stats`]
+    pub(crate) rate_limit_type: RateLimitType,
+    /// Rate limiters of the account and API key of the session
+    pub(crate) rate_limiters: SessionRateLimiters,
     /// Flag to indicate if the session is being used in a concurrent context
     pub(crate) concurrent_mode: Arc<AtomicBool>,
 }
@@ -34,8 +35,10 @@ impl IgSession {
     /// Creates a new session with the given credentials
     ///
     /// This is a simplified version for tests and basic usage.
-    /// Uses default values for most fields and a default rate limiter.
+    /// Uses default values for most fields. Without an API key, the app limiter is the
+    /// session's own; see [`IgSession::with_api_key`].
     pub fn new(cst: String, token: String, account_id: String) -> Self {
+        let rate_limiters = rate_limiter_registry().for_account("", &account_id);
         Self {
             base_url: String::new(),
             cst,
@@ -44,10 +47,8 @@ impl IgSession {
             account_id,
             lightstreamer_endpoint: String::new(),
             api_key: String::new(),
-            rate_limiter: Some(create_rate_limiter(
-                RateLimitType::NonTradingAccount,
-                Some(0.8),
-            )),
+            rate_limit_type: RateLimitType::NonTradingAccount,
+            rate_limiters,
             concurrent_mode: Arc::new(AtomicBool::new(false)),
         }
     }
@@ -55,7 +56,10 @@ impl IgSession {
     /// Creates a new session with the given parameters
     ///
     /// This creates a thread-safe session that can be shared across multiple threads.
-    /// The rate limiter is wrapped in an Arc to ensure proper synchronization.
+    /// Requests wait on the limiters of the account and API key from the default
+    /// [`RateLimiterRegistry`](crate::utils::rate_limiter::RateLimiterRegistry), which
+    /// sets their safety margin; `rate_limit_type` selects the limiter reported by
+    /// [`IgSession::get_rate_limit_stats`].
     #[allow(clippy::too_many_arguments)]
     pub fn new_with_config(
         base_url: String,
@@ -66,10 +70,8 @@ impl IgSession {
         lightstreamer_endpoint: String,
         api_key: String,
         rate_limit_type: RateLimitType,
-        rate_limit_safety_margin: f64,
     ) -> Self {
-        // Create a rate limiter with the specified type and safety margin
-        let rate_limiter = create_rate_limiter(rate_limit_type, Some(rate_limit_safety_margin));
+        let rate_limiters = rate_limiter_registry().for_account(&api_key, &account_id);
 
         Self {
             base_url,
@@ -79,7 +81,8 @@ impl IgSession {
             account_id,
             lightstreamer_endpoint,
             api_key,
-            rate_limiter: Some(rate_limiter),
+            rate_limit_type,
+            rate_limiters,
             concurrent_mode: Arc::new(AtomicBool::new(false)),
         }
     }
@@ -87,12 +90,15 @@ impl IgSession {
     /// Creates a new session with the given credentials and a rate limiter
     ///
     /// This creates a thread-safe session that can be shared across multiple threads.
+    /// Without an API key, the app limiter is the session's own; see
+    /// [`IgSession::with_api_key`].
     pub fn with_rate_limiter(
         cst: String,
         token: String,
         account_id: String,
         limit_type: RateLimitType,
     ) -> Self {
+        let rate_limiters = rate_limiter_registry().for_account("", &account_id);
         Self {
             cst,
             token,
@@ -101,13 +107,16 @@ impl IgSession {
             client_id: String::new(),
             lightstreamer_endpoint: String::new(),
             api_key: String::new(),
-            rate_limiter: Some(create_rate_limiter(limit_type, Some(0.8))),
+            rate_limit_type: limit_type,
+            rate_limiters,
             concurrent_mode: Arc::new(AtomicBool::new(false)),
         }
     }
 
     /// Creates a new session with the given credentials and rate limiter configuration from Config
     pub fn from_config(cst: String, token: String, account_id: String, config: &Config) -> Self {
+        let api_key = config.credentials.api_key.clone();
+        let rate_limiters = rate_limiter_registry().for_account(&api_key, &account_id);
         Self {
             cst,
             token,
@@ -115,19 +124,17 @@ impl IgSession {
             base_url: String::new(),
             client_id: String::new(),
             lightstreamer_endpoint: String::new(),
-            api_key: String::new(),
-            rate_limiter: Some(create_rate_limiter(
-                config.rate_limit_type,
-                Some(config.rate_limit_safety_margin),
-            )),
+            api_key,
+            rate_limit_type: config.rate_limit_type,
+            rate_limiters,
             concurrent_mode: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    /// Waits if necessary to respect rate limits before making a request
+    /// Waits if necessary to respect the rate limits of a non-trading request
     ///
-    /// This method will always use a rate limiter - either the one configured in the session,
-    /// or a default one if none is configured.
+    /// The request counts against the non-trading limits of the account and of the
+    /// API key of the session, see [`IgSession::rate_limiters`].
     ///
     /// This method is thread-safe and can be called from multiple threads concurrently.
     ///
@@ -135,31 +142,58 @@ impl IgSession {
     /// * `Ok(())` - If the rate limit is respected
     /// * `Err(AppError::RateLimitExceeded)` - If the rate limit has been exceeded and cannot be respected
     pub async fn respect_rate_limit(&self) -> Result<(), AppError> {
+        self.respect_rate_limit_for(false).await
+    }
+
+    /// Waits if necessary to respect the rate limits of a trading or non-trading request
+    ///
+    /// Trading requests, which create, amend or close positions and working orders,
+    /// count against the trading limit of the account; other requests against the
+    /// non-trading limits of the account and of the API key.
+    pub async fn respect_rate_limit_for(&self, trading: bool) -> Result<(), AppError> {
         // Mark that this session is being used in a concurrent context
         self.concurrent_mode.store(true, Ordering::SeqCst);
 
-        // Get the rate limiter from the session or use a default one
-        let limiter = match &self.rate_limiter {
-            Some(limiter) => limiter.clone(),
-            None => {
-                // This should never happen since we always initialize with a default limiter,
-                // but just in case, use the global app non-trading limiter
-                debug!("No rate limiter configured in session, using default");
-                app_non_trading_limiter()
-            }
-        };
-
-        // Wait if necessary to respect the rate limit
-        limiter.wait().await;
+        if trading {
+            self.rate_limiters.account_trading.wait().await;
+        } else {
+            self.rate_limiters.account_non_trading.wait().await;
+            self.rate_limiters.app_non_trading.wait().await;
+        }
         Ok(())
     }
 
-    /// Gets statistics about the current rate limit usage
+    /// Returns the rate limiters of the account and API key of the session
+    ///
+    /// Sessions of the same account or API key share them, including the sessions
+    /// returned by a refresh or an account switch.
+    pub fn rate_limiters(&self) -> &SessionRateLimiters {
+        &self.rate_limiters
+    }
+
+    /// Sets the API key of the session and uses the limiters of its account and key
+    /// from the default
+    /// [`RateLimiterRegistry`](crate::utils::rate_limiter::RateLimiterRegistry)
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.rate_limiters = rate_limiter_registry().for_account(api_key, &self.account_id);
+        self.api_key = api_key.to_string();
+        self
+    }
+
+    /// Uses the given rate limiters, e.g. from a dedicated
+    /// [`RateLimiterRegistry`](crate::utils::rate_limiter::RateLimiterRegistry)
+    pub fn with_rate_limiters(mut self, rate_limiters: SessionRateLimiters) -> Self {
+        self.rate_limiters = rate_limiters;
+        self
+    }
+
+    /// Gets statistics about the usage of the limit of the configured type
+    ///
+    /// Returns `None` for limits that sessions do not track, such as
+    /// [`RateLimitType::OnePerSecond`].
     pub async fn get_rate_limit_stats(&self) -> Option<RateLimiterStats> {
-        match &self.rate_limiter {
-            Some(limiter) => Some(limiter.get_stats().await),
-            None => None,
-        }
+        let limiter = self.rate_limiters.for_type(self.rate_limit_type)?;
+        Some(limiter.get_stats().await)
     }
 }
 
@@ -0,0 +1,139 @@
+use crate::application::models::account::{Account, AccountBalance, Position, WorkingOrder};
+use crate::error::AppError;
+use crate::storage::balance_history::BalanceSnapshot;
+use crate::storage::json_lines::{AccountRecord, JsonLinesFile, MemoryRecords};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Positions, working orders and balance of an account at a point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSnapshot {
+    /// Time the account was read
+    pub timestamp: DateTime<Utc>,
+    /// Account the snapshot belongs to
+    pub account_id: String,
+    /// Currency of the account
+    pub currency: String,
+    /// Balance as returned by IG
+    pub balance: AccountBalance,
+    /// Open positions
+    pub positions: Vec<Position>,
+    /// Working orders
+    pub working_orders: Vec<WorkingOrder>,
+}
+
+impl AccountSnapshot {
+    /// Creates a snapshot of `account` timestamped now
+    pub fn new(
+        account: &Account,
+        positions: Vec<Position>,
+        working_orders: Vec<WorkingOrder>,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            account_id: account.account_id.clone(),
+            currency: account.currency.clone(),
+            balance: account.balance.clone(),
+            positions,
+            working_orders,
+        }
+    }
+
+    /// Returns the balance part of the snapshot
+    pub fn balance_snapshot(&self) -> BalanceSnapshot {
+        BalanceSnapshot {
+            timestamp: self.timestamp,
+            account_id: self.account_id.clone(),
+            currency: self.currency.clone(),
+            balance: self.balance.clone(),
+        }
+    }
+
+    /// Returns the position with the given deal ID
+    pub fn find_position(&self, deal_id: &str) -> Option<&Position> {
+        self.positions
+            .iter()
+            .find(|position| position.position.deal_id == deal_id)
+    }
+
+    /// Returns the working order with the given deal ID
+    pub fn find_working_order(&self, deal_id: &str) -> Option<&WorkingOrder> {
+        self.working_orders
+            .iter()
+            .find(|order| order.working_order_data.deal_id == deal_id)
+    }
+}
+
+impl AccountRecord for AccountSnapshot {
+    fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+}
+
+/// Store of account snapshots
+///
+/// Used by [`AccountSnapshotRecorder`](crate::application::services::snapshot_recorder::AccountSnapshotRecorder)
+/// to keep a history of the account for reconciliation and debugging.
+pub trait AccountSnapshotStore: Send + Sync {
+    /// Appends a snapshot
+    fn append(&self, snapshot: &AccountSnapshot) -> Result<(), AppError>;
+
+    /// Returns the snapshots of `account_id` taken in `[from, to)`, oldest first
+    fn query(
+        &self,
+        account_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<AccountSnapshot>, AppError>;
+
+    /// Returns the last snapshot of `account_id` taken before `at`
+    fn latest_before(
+        &self,
+        account_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Option<AccountSnapshot>, AppError> {
+        Ok(self.query(account_id, DateTime::<Utc>::MIN_UTC, at)?.pop())
+    }
+}
+
+/// In-memory account snapshot store, lost when the process exits
+pub type MemoryAccountSnapshotStore = MemoryRecords<AccountSnapshot>;
+
+impl AccountSnapshotStore for MemoryRecords<AccountSnapshot> {
+    fn append(&self, snapshot: &AccountSnapshot) -> Result<(), AppError> {
+        self.push(snapshot);
+        Ok(())
+    }
+
+    fn query(
+        &self,
+        account_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<AccountSnapshot>, AppError> {
+        Ok(self.query_account(account_id, from, to))
+    }
+}
+
+/// Account snapshot store keeping one snapshot per line of a [`JsonLinesFile`]
+pub type FileAccountSnapshotStore = JsonLinesFile<AccountSnapshot>;
+
+impl AccountSnapshotStore for JsonLinesFile<AccountSnapshot> {
+    fn append(&self, snapshot: &AccountSnapshot) -> Result<(), AppError> {
+        JsonLinesFile::append(self, snapshot)
+    }
+
+    fn query(
+        &self,
+        account_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<AccountSnapshot>, AppError> {
+        self.query_account(account_id, from, to)
+    }
+}
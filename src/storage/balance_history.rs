@@ -0,0 +1,176 @@
+use crate::application::models::account::AccountBalance;
+use crate::error::AppError;
+use crate::storage::json_lines::{AccountRecord, JsonLinesFile, MemoryRecords};
+pub use crate::utils::performance::Drawdown;
+use crate::utils::performance::max_drawdown;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Balance of an account at a point in time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceSnapshot {
+    /// Time the balance was read
+    pub timestamp: DateTime<Utc>,
+    /// Account the balance belongs to
+    pub account_id: String,
+    /// Currency of the account
+    pub currency: String,
+    /// Balance as returned by IG
+    pub balance: AccountBalance,
+}
+
+impl BalanceSnapshot {
+    /// Creates a snapshot timestamped now
+    pub fn new(account_id: &str, currency: &str, balance: AccountBalance) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            account_id: account_id.to_string(),
+            currency: currency.to_string(),
+            balance,
+        }
+    }
+
+    /// Balance including the profit or loss of the open positions
+    pub fn equity(&self) -> f64 {
+        self.balance.balance.amount + self.balance.profit_loss.amount
+    }
+}
+
+impl AccountRecord for BalanceSnapshot {
+    fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+}
+
+/// Store of balance snapshots
+///
+/// Used by [`BalanceTracker`](crate::application::services::balance_tracker::BalanceTracker)
+/// to keep the balance over time, which IG does not provide.
+pub trait BalanceStore: Send + Sync {
+    /// Appends a snapshot
+    fn append(&self, snapshot: &BalanceSnapshot) -> Result<(), AppError>;
+
+    /// Returns the snapshots of `account_id` taken in `[from, to)`, oldest first
+    fn query(
+        &self,
+        account_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<BalanceSnapshot>, AppError>;
+}
+
+/// In-memory balance store, lost when the process exits
+pub type MemoryBalanceStore = MemoryRecords<BalanceSnapshot>;
+
+impl BalanceStore for MemoryRecords<BalanceSnapshot> {
+    fn append(&self, snapshot: &BalanceSnapshot) -> Result<(), AppError> {
+        self.push(snapshot);
+        Ok(())
+    }
+
+    fn query(
+        &self,
+        account_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<BalanceSnapshot>, AppError> {
+        Ok(self.query_account(account_id, from, to))
+    }
+}
+
+/// Balance store keeping one snapshot per line of a [`JsonLinesFile`]
+pub type FileBalanceStore = JsonLinesFile<BalanceSnapshot>;
+
+impl BalanceStore for JsonLinesFile<BalanceSnapshot> {
+    fn append(&self, snapshot: &BalanceSnapshot) -> Result<(), AppError> {
+        JsonLinesFile::append(self, snapshot)
+    }
+
+    fn query(
+        &self,
+        account_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<BalanceSnapshot>, AppError> {
+        self.query_account(account_id, from, to)
+    }
+}
+
+/// Change of the equity over one UTC day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DailyPnl {
+    /// Day of the change
+    pub date: NaiveDate,
+    /// Equity at the end of the previous recorded day, or first equity of the day
+    pub open_equity: f64,
+    /// Last equity recorded on the day
+    pub close_equity: f64,
+}
+
+impl DailyPnl {
+    /// Profit or loss of the day
+    pub fn pnl(&self) -> f64 {
+        self.close_equity - self.open_equity
+    }
+}
+
+/// Balance snapshots of one account, oldest first
+///
+/// Equity is the balance plus the profit or loss of the open positions. Deposits and
+/// withdrawals are not told apart from trading results, so they show in the daily
+/// profit and loss and in drawdowns.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BalanceHistory {
+    /// Snapshots sorted by timestamp
+    pub snapshots: Vec<BalanceSnapshot>,
+}
+
+impl BalanceHistory {
+    /// Creates a history from snapshots in any order
+    pub fn new(mut snapshots: Vec<BalanceSnapshot>) -> Self {
+        snapshots.sort_by_key(|snapshot| snapshot.timestamp);
+        Self { snapshots }
+    }
+
+    /// Returns the equity at each snapshot
+    pub fn equity_curve(&self) -> Vec<(DateTime<Utc>, f64)> {
+        self.snapshots
+            .iter()
+            .map(|snapshot| (snapshot.timestamp, snapshot.equity()))
+            .collect()
+    }
+
+    /// Returns the largest fall of the equity from a previous peak
+    ///
+    /// # Returns
+    /// The drawdown, or `None` if the equity never fell below a previous peak
+    pub fn max_drawdown(&self) -> Option<Drawdown> {
+        max_drawdown(&self.equity_curve())
+    }
+
+    /// Returns the change of the equity on each UTC day with a snapshot
+    pub fn daily_pnl(&self) -> Vec<DailyPnl> {
+        let mut days: Vec<DailyPnl> = Vec::new();
+        for snapshot in &self.snapshots {
+            let date = snapshot.timestamp.date_naive();
+            let equity = snapshot.equity();
+            match days.last_mut() {
+                Some(day) if day.date == date => day.close_equity = equity,
+                last => {
+                    let open_equity = last.map_or(equity, |day| day.close_equity);
+                    days.push(DailyPnl {
+                        date,
+                        open_equity,
+                        close_equity: equity,
+                    });
+                }
+            }
+        }
+        days
+    }
+}
@@ -0,0 +1,351 @@
+use crate::application::models::account::AccountTransaction;
+use crate::error::AppError;
+use crate::storage::Store;
+use crate::storage::store::{Candle, Tick, WriteReport};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn};
+
+/// Default number of records written at once
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Default longest time a record waits in a partial batch
+pub const DEFAULT_BATCH_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Shortest time a record waits in a partial batch
+pub const MIN_BATCH_FLUSH_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Default number of records queued for the writer task
+pub const DEFAULT_BATCH_CAPACITY: usize = 10_000;
+
+/// Default delay before retrying a failed write, doubled on each new failure
+pub const DEFAULT_BATCH_RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Destination of the batches of a [`BatchWriter`]
+///
+/// Every [`Store`] is a sink of ticks, candles and account transactions.
+#[async_trait]
+pub trait BatchSink<T>: Send + Sync + 'static {
+    /// Writes a batch of records
+    async fn write_batch(&self, batch: &[T]) -> Result<WriteReport, AppError>;
+}
+
+#[async_trait]
+impl<S: Store + 'static> BatchSink<Tick> for S {
+    async fn write_batch(&self, batch: &[Tick]) -> Result<WriteReport, AppError> {
+        self.put_ticks(batch).await
+    }
+}
+
+#[async_trait]
+impl<S: Store + 'static> BatchSink<Candle> for S {
+    async fn write_batch(&self, batch: &[Candle]) -> Result<WriteReport, AppError> {
+        self.put_candles(batch).await
+    }
+}
+
+#[async_trait]
+impl<S: Store + 'static> BatchSink<AccountTransaction> for S {
+    async fn write_batch(&self, batch: &[AccountTransaction]) -> Result<WriteReport, AppError> {
+        self.put_transactions(batch).await
+    }
+}
+
+/// What [`BatchWriter::write`] does when the queue is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Waits for room, slowing the producer down to the pace of the sink
+    #[default]
+    Wait,
+    /// Drops the record, so the producer never waits
+    Drop,
+}
+
+/// Settings of a [`BatchWriter`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchWriterConfig {
+    /// Number of records written at once
+    pub batch_size: usize,
+    /// Longest time a record waits in a partial batch
+    pub flush_interval: Duration,
+    /// Number of records queued for the writer task; with the batch being written, it
+    /// bounds the memory held by the writer
+    pub capacity: usize,
+    /// What to do with records written while the queue is full
+    pub overflow: OverflowPolicy,
+    /// Delay before retrying a failed write, doubled on each new failure
+    pub retry_backoff: Duration,
+    /// Longest delay between two attempts
+    pub max_backoff: Duration,
+    /// Retries of a failed batch before it is discarded
+    pub max_retries: u32,
+}
+
+impl Default for BatchWriterConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: DEFAULT_BATCH_SIZE,
+            flush_interval: DEFAULT_BATCH_FLUSH_INTERVAL,
+            capacity: DEFAULT_BATCH_CAPACITY,
+            overflow: OverflowPolicy::default(),
+            retry_backoff: DEFAULT_BATCH_RETRY_BACKOFF,
+            max_backoff: Duration::from_secs(30),
+            max_retries: 5,
+        }
+    }
+}
+
+impl BatchWriterConfig {
+    /// Creates the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of records written at once
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets the longest time a record waits in a partial batch
+    ///
+    /// An interval below [`MIN_BATCH_FLUSH_INTERVAL`] is raised to it.
+    pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval.max(MIN_BATCH_FLUSH_INTERVAL);
+        self
+    }
+
+    /// Sets the number of records queued for the writer task
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets what to do with records written while the queue is full
+    pub fn with_overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Sets the retries of a failed batch and the delays between them
+    pub fn with_retries(
+        mut self,
+        max_retries: u32,
+        backoff: Duration,
+        max_backoff: Duration,
+    ) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self.max_backoff = max_backoff.max(backoff);
+        self
+    }
+}
+
+/// Counters of a [`BatchWriter`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchWriterStats {
+    /// Records queued
+    pub accepted: u64,
+    /// Records dropped because the queue was full
+    pub dropped: u64,
+    /// Batches written
+    pub batches: u64,
+    /// Records of the batches written, by outcome
+    pub written: WriteReport,
+    /// Failed write attempts, retried or not
+    pub failed_attempts: u64,
+    /// Records of the batches discarded after their last retry
+    pub discarded: u64,
+}
+
+impl std::ops::AddAssign for BatchWriterStats {
+    fn add_assign(&mut self, other: Self) {
+        self.accepted += other.accepted;
+        self.dropped += other.dropped;
+        self.batches += other.batches;
+        self.written += other.written;
+        self.failed_attempts += other.failed_attempts;
+        self.discarded += other.discarded;
+    }
+}
+
+enum Command<T> {
+    Record(T),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Buffered writer grouping records into batches written by a background task
+///
+/// A batch is written once it holds `batch_size` records, and a partial batch every
+/// `flush_interval`, so busy streams are written in large batches and quiet ones still
+/// reach the sink. Records are queued up to `capacity`; when the sink falls behind, the
+/// [`OverflowPolicy`] either slows the producers down or drops records. A failed batch
+/// is retried with exponential backoff, during which the queue fills up, and discarded
+/// after its last retry.
+///
+/// ```no_run
+/// use ig_client::storage::batch_writer::{BatchWriter, BatchWriterConfig};
+/// use ig_client::storage::memory_store::MemoryStore;
+/// use ig_client::storage::store::Tick;
+///
+/// # async fn example(store: MemoryStore, tick: Tick) {
+/// let writer: BatchWriter<Tick> = BatchWriter::spawn(store, BatchWriterConfig::new());
+/// writer.write(tick).await;
+/// let stats = writer.close().await;
+/// # }
+/// ```
+pub struct BatchWriter<T> {
+    sender: mpsc::Sender<Command<T>>,
+    overflow: OverflowPolicy,
+    stats: Arc<Mutex<BatchWriterStats>>,
+    task: JoinHandle<()>,
+}
+
+impl<T: Send + 'static> BatchWriter<T> {
+    /// Starts the task writing batches to `sink`
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn spawn<S: BatchSink<T>>(sink: S, config: BatchWriterConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(config.capacity.max(1));
+        let stats = Arc::new(Mutex::new(BatchWriterStats::default()));
+        let overflow = config.overflow;
+        let task = tokio::spawn(write_batches(sink, config, receiver, stats.clone()));
+        Self {
+            sender,
+            overflow,
+            stats,
+            task,
+        }
+    }
+
+    /// Queues a record
+    ///
+    /// # Returns
+    /// True if the record was queued, false if it was dropped because the queue was
+    /// full under [`OverflowPolicy::Drop`]
+    pub async fn write(&self, record: T) -> bool {
+        let queued = match self.overflow {
+            OverflowPolicy::Wait => self.sender.send(Command::Record(record)).await.is_ok(),
+            OverflowPolicy::Drop => self.sender.try_send(Command::Record(record)).is_ok(),
+        };
+        let mut stats = self.stats.lock().unwrap();
+        if queued {
+            stats.accepted += 1;
+        } else {
+            stats.dropped += 1;
+        }
+        queued
+    }
+
+    /// Writes the records queued so far, waiting until their batch is written or
+    /// discarded
+    pub async fn flush(&self) {
+        let (done, written) = oneshot::channel();
+        if self.sender.send(Command::Flush(done)).await.is_ok() {
+            let _ = written.await;
+        }
+    }
+
+    /// Returns the counters of the writer
+    pub fn stats(&self) -> BatchWriterStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// Writes the queued records and stops the task
+    ///
+    /// # Returns
+    /// The final counters of the writer
+    pub async fn close(self) -> BatchWriterStats {
+        drop(self.sender);
+        if let Err(e) = self.task.await {
+            warn!("Batch writer task failed: {}", e);
+        }
+        *self.stats.lock().unwrap()
+    }
+}
+
+/// Loop of the writer task, until every sender is dropped
+async fn write_batches<T, S: BatchSink<T>>(
+    sink: S,
+    config: BatchWriterConfig,
+    mut receiver: mpsc::Receiver<Command<T>>,
+    stats: Arc<Mutex<BatchWriterStats>>,
+) {
+    let mut batch = Vec::with_capacity(config.batch_size);
+    // A zero period makes the interval panic
+    let mut interval = tokio::time::interval(config.flush_interval.max(MIN_BATCH_FLUSH_INTERVAL));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // The first tick of an interval completes immediately
+    interval.tick().await;
+    loop {
+        tokio::select! {
+            command = receiver.recv() => match command {
+                Some(Command::Record(record)) => {
+                    batch.push(record);
+                    if batch.len() >= config.batch_size {
+                        write_batch(&sink, &config, &mut batch, &stats).await;
+                        interval.reset();
+                    }
+                }
+                Some(Command::Flush(done)) => {
+                    write_batch(&sink, &config, &mut batch, &stats).await;
+                    let _ = done.send(());
+                }
+                None => break,
+            },
+            _ = interval.tick() => write_batch(&sink, &config, &mut batch, &stats).await,
+        }
+    }
+    write_batch(&sink, &config, &mut batch, &stats).await;
+}
+
+/// Writes `batch`, retrying with backoff, then clears it
+async fn write_batch<T, S: BatchSink<T>>(
+    sink: &S,
+    config: &BatchWriterConfig,
+    batch: &mut Vec<T>,
+    stats: &Mutex<BatchWriterStats>,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let mut backoff = config.retry_backoff;
+    let mut retries = 0;
+    loop {
+        match sink.write_batch(batch).await {
+            Ok(report) => {
+                debug!(
+                    "Batch of {} records written, {} already stored",
+                    batch.len(),
+                    report.conflicts()
+                );
+                let mut stats = stats.lock().unwrap();
+                stats.batches += 1;
+                stats.written += report;
+                break;
+            }
+            Err(e) => {
+                stats.lock().unwrap().failed_attempts += 1;
+                if retries >= config.max_retries {
+                    warn!(
+                        "Discarding a batch of {} records after {} retries: {}",
+                        batch.len(),
+                        retries,
+                        e
+                    );
+                    stats.lock().unwrap().discarded += batch.len() as u64;
+                    break;
+                }
+                warn!("Failed to write a batch, retrying in {:?}: {}", backoff, e);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(config.max_backoff);
+                retries += 1;
+            }
+        }
+    }
+    batch.clear();
+}
@@ -1,6 +1,13 @@
 use crate::impl_json_display;
 use serde::{Deserialize, Serialize};
 
+/// Default number of seconds to wait for a pooled connection
+pub const DEFAULT_ACQUIRE_TIMEOUT: u64 = 30;
+
+fn default_acquire_timeout() -> u64 {
+    DEFAULT_ACQUIRE_TIMEOUT
+}
+
 /// Configuration for database connections
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DatabaseConfig {
@@ -8,6 +15,12 @@ pub struct DatabaseConfig {
     pub url: String,
     /// Maximum number of connections in the connection pool
     pub max_connections: u32,
+    /// Number of connections the pool keeps open when idle
+    #[serde(default)]
+    pub min_connections: u32,
+    /// Seconds to wait for a connection of the pool before failing
+    #[serde(default = "default_acquire_timeout")]
+    pub acquire_timeout: u64,
 }
 
 impl_json_display!(DatabaseConfig);
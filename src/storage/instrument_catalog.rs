@@ -0,0 +1,212 @@
+use crate::application::models::market::{DealingRules, MarketData, MarketDetails, MarketNode};
+use crate::error::AppError;
+use crate::presentation::InstrumentType;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Instrument of the offline catalog
+///
+/// Entries are listed from the navigation tree, and completed with the currency and
+/// dealing rules of the market details when those are fetched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogEntry {
+    /// EPIC of the market
+    pub epic: String,
+    /// Name of the instrument
+    pub name: String,
+    /// Type of the instrument
+    pub instrument_type: InstrumentType,
+    /// Expiry of the instrument, `-` or `DFB` for undated markets
+    pub expiry: String,
+    /// Names of the navigation nodes above the market, from the top
+    pub path: Vec<String>,
+    /// Status of the market when last listed
+    pub market_status: String,
+    /// Default currency, known once the details are fetched
+    pub currency: Option<String>,
+    /// Dealing rules, known once the details are fetched
+    pub dealing_rules: Option<DealingRules>,
+    /// Time the market was first listed in the navigation tree
+    pub listed_at: DateTime<Utc>,
+    /// Time the details were last fetched
+    pub details_at: Option<DateTime<Utc>>,
+}
+
+impl CatalogEntry {
+    /// Creates the entry of a market listed under the navigation nodes `path`
+    pub fn from_market(market: &MarketData, path: &[String], listed_at: DateTime<Utc>) -> Self {
+        Self {
+            epic: market.epic.clone(),
+            name: market.instrument_name.clone(),
+            instrument_type: market.instrument_type,
+            expiry: market.expiry.clone(),
+            path: path.to_vec(),
+            market_status: market.market_status.clone(),
+            currency: None,
+            dealing_rules: None,
+            listed_at,
+            details_at: None,
+        }
+    }
+
+    /// Completes the entry with the currency and dealing rules of the market details
+    pub fn apply_details(&mut self, details: &MarketDetails, fetched_at: DateTime<Utc>) {
+        self.currency = details
+            .instrument
+            .default_currency()
+            .map(|currency| currency.code.clone());
+        self.dealing_rules = Some(details.dealing_rules.clone());
+        self.market_status = details.snapshot.market_status.clone();
+        self.details_at = Some(fetched_at);
+    }
+
+    /// Returns true if the details were never fetched or are older than `max_age`
+    pub fn details_stale(&self, max_age: Duration, now: DateTime<Utc>) -> bool {
+        self.details_at
+            .is_none_or(|fetched| now - fetched > max_age)
+    }
+
+    /// Returns true if a listing changed the name, type, expiry or status of the market
+    pub fn listing_differs(&self, other: &CatalogEntry) -> bool {
+        self.name != other.name
+            || self.instrument_type != other.instrument_type
+            || self.expiry != other.expiry
+            || self.path != other.path
+            || self.market_status != other.market_status
+    }
+
+    /// Returns the text searched by [`CatalogQuery::text`], in lowercase
+    pub fn search_text(&self) -> String {
+        format!("{} {} {}", self.epic, self.name, self.path.join(" ")).to_lowercase()
+    }
+}
+
+/// Returns the name of an instrument type used by the IG API, e.g. `INDICES`
+pub(crate) fn instrument_type_name(instrument_type: InstrumentType) -> Result<String, AppError> {
+    Ok(serde_json::to_value(instrument_type)?
+        .as_str()
+        .unwrap_or_default()
+        .to_string())
+}
+
+/// Returns the entries of every market of a navigation tree
+///
+/// A market listed under several nodes keeps the first path met, depth first in API
+/// order.
+pub fn catalog_entries(tree: &[MarketNode], listed_at: DateTime<Utc>) -> Vec<CatalogEntry> {
+    fn walk(
+        nodes: &[MarketNode],
+        path: &mut Vec<String>,
+        listed_at: DateTime<Utc>,
+        seen: &mut HashSet<String>,
+        entries: &mut Vec<CatalogEntry>,
+    ) {
+        for node in nodes {
+            path.push(node.name.clone());
+            for market in &node.markets {
+                if seen.insert(market.epic.clone()) {
+                    entries.push(CatalogEntry::from_market(market, path, listed_at));
+                }
+            }
+            walk(&node.children, path, listed_at, seen, entries);
+            path.pop();
+        }
+    }
+
+    let mut entries = Vec::new();
+    walk(
+        tree,
+        &mut Vec::new(),
+        listed_at,
+        &mut HashSet::new(),
+        &mut entries,
+    );
+    entries
+}
+
+/// Search of the instrument catalog
+///
+/// Every filter is optional. Entries are returned by name, at most `limit` of them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CatalogQuery {
+    /// Words that must all appear, case-insensitively, in the EPIC, name or path
+    pub text: Option<String>,
+    /// Type of the instruments
+    pub instrument_type: Option<InstrumentType>,
+    /// Default currency of the instruments
+    pub currency: Option<String>,
+    /// Maximum number of entries returned
+    pub limit: Option<usize>,
+}
+
+impl CatalogQuery {
+    /// Creates a query selecting every entry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects the entries containing every word of `text`
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    /// Selects the instruments of a type
+    pub fn of_type(mut self, instrument_type: InstrumentType) -> Self {
+        self.instrument_type = Some(instrument_type);
+        self
+    }
+
+    /// Selects the instruments with a default currency
+    pub fn in_currency(mut self, currency: &str) -> Self {
+        self.currency = Some(currency.to_string());
+        self
+    }
+
+    /// Returns at most `limit` entries
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns true if `entry` is selected, ignoring the limit
+    pub fn matches(&self, entry: &CatalogEntry) -> bool {
+        if self
+            .instrument_type
+            .is_some_and(|instrument_type| instrument_type != entry.instrument_type)
+        {
+            return false;
+        }
+        if self
+            .currency
+            .as_ref()
+            .is_some_and(|currency| entry.currency.as_ref() != Some(currency))
+        {
+            return false;
+        }
+        match &self.text {
+            Some(text) => {
+                let searched = entry.search_text();
+                text.to_lowercase()
+                    .split_whitespace()
+                    .all(|word| searched.contains(word))
+            }
+            None => true,
+        }
+    }
+
+    /// Keeps the entries selected by the query, ordered by name then EPIC, up to the limit
+    pub fn select(&self, entries: impl IntoIterator<Item = CatalogEntry>) -> Vec<CatalogEntry> {
+        let mut selected: Vec<CatalogEntry> = entries
+            .into_iter()
+            .filter(|entry| self.matches(entry))
+            .collect();
+        selected.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.epic.cmp(&b.epic)));
+        if let Some(limit) = self.limit {
+            selected.truncate(limit);
+        }
+        selected
+    }
+}
@@ -0,0 +1,169 @@
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Record of an account taken at a point in time
+pub trait AccountRecord {
+    /// Account the record belongs to
+    fn account_id(&self) -> &str;
+
+    /// Time the record was taken
+    fn timestamp(&self) -> DateTime<Utc>;
+}
+
+/// Returns true if `record` belongs to `account_id` and was taken in `[from, to)`
+fn taken_between<T: AccountRecord>(
+    record: &T,
+    account_id: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> bool {
+    let timestamp = record.timestamp();
+    record.account_id() == account_id && timestamp >= from && timestamp < to
+}
+
+/// Records kept in memory, lost when the process exits
+#[derive(Debug)]
+pub struct MemoryRecords<T> {
+    records: Mutex<Vec<T>>,
+}
+
+impl<T> Default for MemoryRecords<T> {
+    fn default() -> Self {
+        Self {
+            records: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl<T: Clone> MemoryRecords<T> {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a record
+    pub fn push(&self, record: &T) {
+        self.records.lock().unwrap().push(record.clone());
+    }
+
+    /// Returns the records kept by `keep`, in the order they were appended
+    pub fn filter(&self, mut keep: impl FnMut(&T) -> bool) -> Vec<T> {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|record| keep(record))
+            .cloned()
+            .collect()
+    }
+}
+
+impl<T: AccountRecord + Clone> MemoryRecords<T> {
+    /// Returns the records of `account_id` taken in `[from, to)`, oldest first
+    pub fn query_account(
+        &self,
+        account_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Vec<T> {
+        let mut records = self.filter(|record| taken_between(record, account_id, from, to));
+        records.sort_by_key(AccountRecord::timestamp);
+        records
+    }
+}
+
+/// Records appended to a newline-delimited JSON file
+///
+/// Each record is written as one line as soon as it is appended, so nothing recorded
+/// is lost in a crash. Reads go through the whole file.
+#[derive(Debug)]
+pub struct JsonLinesFile<T> {
+    path: PathBuf,
+    lock: Mutex<()>,
+    records: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> JsonLinesFile<T> {
+    /// Opens the file at `path`, created on the first append
+    pub fn open(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lock: Mutex::new(()),
+            records: PhantomData,
+        }
+    }
+
+    /// Returns the path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a record
+    pub fn append(&self, record: &T) -> Result<(), AppError> {
+        self.append_all([record])
+    }
+
+    /// Appends records, one line each
+    pub fn append_all<'a>(&self, records: impl IntoIterator<Item = &'a T>) -> Result<(), AppError>
+    where
+        T: 'a,
+    {
+        let _guard = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        for record in records {
+            writeln!(file, "{}", serde_json::to_string(record)?)?;
+        }
+        Ok(())
+    }
+
+    /// Returns every record, in the order they were appended
+    pub fn read(&self) -> Result<Vec<T>, AppError> {
+        self.filter(|_| true)
+    }
+
+    /// Returns the records kept by `keep`, in the order they were appended
+    ///
+    /// A missing file holds no record; blank lines are skipped.
+    pub fn filter(&self, mut keep: impl FnMut(&T) -> bool) -> Result<Vec<T>, AppError> {
+        let _guard = self.lock.lock().unwrap();
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut records = Vec::new();
+        for line in BufReader::new(std::fs::File::open(&self.path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: T = serde_json::from_str(&line)?;
+            if keep(&record) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+}
+
+impl<T: AccountRecord + Serialize + DeserializeOwned> JsonLinesFile<T> {
+    /// Returns the records of `account_id` taken in `[from, to)`, oldest first
+    pub fn query_account(
+        &self,
+        account_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<T>, AppError> {
+        let mut records = self.filter(|record| taken_between(record, account_id, from, to))?;
+        records.sort_by_key(AccountRecord::timestamp);
+        Ok(records)
+    }
+}
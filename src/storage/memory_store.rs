@@ -0,0 +1,377 @@
+use crate::application::models::account::AccountTransaction;
+use crate::application::models::market::Resolution;
+use crate::error::AppError;
+use crate::storage::instrument_catalog::{CatalogEntry, CatalogQuery};
+use crate::storage::order_journal::{DealRecord, JournalEntry, JournalEntryKind};
+use crate::storage::store::{Candle, Store, StoreQuery, Tick, WriteReport};
+use crate::storage::transaction_store::{TransactionKey, transaction_date, transaction_key};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Records of a [`MemoryStore`], keyed like the tables of the database backends
+#[derive(Debug, Default)]
+struct MemoryTables {
+    candles: BTreeMap<(Resolution, String, DateTime<Utc>), Candle>,
+    ticks: BTreeMap<(String, DateTime<Utc>), Tick>,
+    transactions: BTreeMap<TransactionKey, (DateTime<Utc>, AccountTransaction)>,
+    orders: BTreeMap<(String, DateTime<Utc>, JournalEntryKind), JournalEntry>,
+    deals: BTreeMap<String, DealRecord>,
+    instruments: BTreeMap<String, CatalogEntry>,
+}
+
+/// Writes `record` at `key` unless the same record is stored there
+fn upsert<K: Ord, V: PartialEq>(
+    table: &mut BTreeMap<K, V>,
+    key: K,
+    record: V,
+    report: &mut WriteReport,
+) {
+    let stored = table.get(&key).map(|stored| *stored == record);
+    if report.count(stored) {
+        table.insert(key, record);
+    }
+}
+
+/// Keeps the first `limit` records of a query
+fn take<T>(mut records: Vec<T>, query: &StoreQuery) -> Vec<T> {
+    if let Some(limit) = query.limit {
+        records.truncate(limit);
+    }
+    records
+}
+
+/// [`Store`] in memory, lost when the process exits
+///
+/// Selects and orders records like the database backends, so tests of code writing
+/// to a store can run without a file or a server. Clones share the same records, as
+/// clones of the database backends share their connection pool.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    tables: Arc<Mutex<MemoryTables>>,
+}
+
+impl MemoryStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of candles stored, at every resolution
+    pub fn candle_count(&self) -> usize {
+        self.tables.lock().unwrap().candles.len()
+    }
+
+    /// Returns the number of ticks stored
+    pub fn tick_count(&self) -> usize {
+        self.tables.lock().unwrap().ticks.len()
+    }
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn put_candles(&self, candles: &[Candle]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tables = self.tables.lock().unwrap();
+        for candle in candles {
+            upsert(
+                &mut tables.candles,
+                (candle.resolution, candle.epic.clone(), candle.timestamp),
+                candle.clone(),
+                &mut report,
+            );
+        }
+        Ok(report)
+    }
+
+    async fn get_candle(
+        &self,
+        epic: &str,
+        resolution: Resolution,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Candle>, AppError> {
+        Ok(self
+            .tables
+            .lock()
+            .unwrap()
+            .candles
+            .get(&(resolution, epic.to_string(), timestamp))
+            .cloned())
+    }
+
+    async fn query_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<Vec<Candle>, AppError> {
+        let mut candles: Vec<Candle> = self
+            .tables
+            .lock()
+            .unwrap()
+            .candles
+            .values()
+            .filter(|candle| {
+                candle.resolution == resolution && query.matches(&candle.epic, candle.timestamp)
+            })
+            .cloned()
+            .collect();
+        candles.sort_by(|a, b| (a.timestamp, &a.epic).cmp(&(b.timestamp, &b.epic)));
+        Ok(take(candles, query))
+    }
+
+    async fn delete_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<usize, AppError> {
+        let mut tables = self.tables.lock().unwrap();
+        let before = tables.candles.len();
+        tables.candles.retain(|_, candle| {
+            candle.resolution != resolution || !query.matches(&candle.epic, candle.timestamp)
+        });
+        Ok(before - tables.candles.len())
+    }
+
+    async fn put_ticks(&self, ticks: &[Tick]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tables = self.tables.lock().unwrap();
+        for tick in ticks {
+            upsert(
+                &mut tables.ticks,
+                (tick.epic.clone(), tick.timestamp),
+                tick.clone(),
+                &mut report,
+            );
+        }
+        Ok(report)
+    }
+
+    async fn get_tick(
+        &self,
+        epic: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Tick>, AppError> {
+        Ok(self
+            .tables
+            .lock()
+            .unwrap()
+            .ticks
+            .get(&(epic.to_string(), timestamp))
+            .cloned())
+    }
+
+    async fn query_ticks(&self, query: &StoreQuery) -> Result<Vec<Tick>, AppError> {
+        let mut ticks: Vec<Tick> = self
+            .tables
+            .lock()
+            .unwrap()
+            .ticks
+            .values()
+            .filter(|tick| query.matches(&tick.epic, tick.timestamp))
+            .cloned()
+            .collect();
+        ticks.sort_by(|a, b| (a.timestamp, &a.epic).cmp(&(b.timestamp, &b.epic)));
+        Ok(take(ticks, query))
+    }
+
+    async fn delete_ticks(&self, query: &StoreQuery) -> Result<usize, AppError> {
+        let mut tables = self.tables.lock().unwrap();
+        let before = tables.ticks.len();
+        tables
+            .ticks
+            .retain(|_, tick| !query.matches(&tick.epic, tick.timestamp));
+        Ok(before - tables.ticks.len())
+    }
+
+    async fn put_transactions(
+        &self,
+        transactions: &[AccountTransaction],
+    ) -> Result<WriteReport, AppError> {
+        // Every date is parsed before writing, so a bad one writes nothing
+        let dated = transactions
+            .iter()
+            .map(|transaction| Ok((transaction_date(transaction)?, transaction.clone())))
+            .collect::<Result<Vec<_>, AppError>>()?;
+        let mut report = WriteReport::default();
+        let mut tables = self.tables.lock().unwrap();
+        for (date, transaction) in dated {
+            upsert(
+                &mut tables.transactions,
+                transaction_key(&transaction),
+                (date, transaction),
+                &mut report,
+            );
+        }
+        Ok(report)
+    }
+
+    async fn get_transactions(&self, reference: &str) -> Result<Vec<AccountTransaction>, AppError> {
+        let mut transactions: Vec<(DateTime<Utc>, AccountTransaction)> = self
+            .tables
+            .lock()
+            .unwrap()
+            .transactions
+            .values()
+            .filter(|(_, transaction)| transaction.reference == reference)
+            .cloned()
+            .collect();
+        transactions.sort_by_key(|(date, _)| *date);
+        Ok(transactions
+            .into_iter()
+            .map(|(_, transaction)| transaction)
+            .collect())
+    }
+
+    async fn query_transactions(
+        &self,
+        query: &StoreQuery,
+    ) -> Result<Vec<AccountTransaction>, AppError> {
+        let mut transactions: Vec<(DateTime<Utc>, AccountTransaction)> = self
+            .tables
+            .lock()
+            .unwrap()
+            .transactions
+            .values()
+            .filter(|(date, transaction)| query.matches(&transaction.instrument_name, *date))
+            .cloned()
+            .collect();
+        transactions.sort_by(|(a_date, a), (b_date, b)| {
+            (a_date, &a.reference).cmp(&(b_date, &b.reference))
+        });
+        Ok(take(
+            transactions
+                .into_iter()
+                .map(|(_, transaction)| transaction)
+                .collect(),
+            query,
+        ))
+    }
+
+    async fn put_orders(&self, entries: &[JournalEntry]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tables = self.tables.lock().unwrap();
+        for entry in entries {
+            upsert(
+                &mut tables.orders,
+                (entry.correlation_id.clone(), entry.timestamp, entry.kind),
+                entry.clone(),
+                &mut report,
+            );
+        }
+        Ok(report)
+    }
+
+    async fn get_orders(&self, correlation_id: &str) -> Result<Vec<JournalEntry>, AppError> {
+        // Keys are ordered by correlation ID, then time
+        Ok(self
+            .tables
+            .lock()
+            .unwrap()
+            .orders
+            .values()
+            .filter(|entry| entry.correlation_id == correlation_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn query_orders(&self, query: &StoreQuery) -> Result<Vec<JournalEntry>, AppError> {
+        let mut entries: Vec<JournalEntry> = self
+            .tables
+            .lock()
+            .unwrap()
+            .orders
+            .values()
+            .filter(|entry| selects_optional_epic(query, entry.epic.as_deref(), entry.timestamp))
+            .cloned()
+            .collect();
+        entries.sort_by(|a, b| {
+            (a.timestamp, &a.correlation_id).cmp(&(b.timestamp, &b.correlation_id))
+        });
+        Ok(take(entries, query))
+    }
+
+    async fn put_deals(&self, deals: &[DealRecord]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tables = self.tables.lock().unwrap();
+        for deal in deals {
+            upsert(
+                &mut tables.deals,
+                deal.deal_reference.clone(),
+                deal.clone(),
+                &mut report,
+            );
+        }
+        Ok(report)
+    }
+
+    async fn get_deal(&self, deal_reference: &str) -> Result<Option<DealRecord>, AppError> {
+        Ok(self
+            .tables
+            .lock()
+            .unwrap()
+            .deals
+            .get(deal_reference)
+            .cloned())
+    }
+
+    async fn query_deals(&self, query: &StoreQuery) -> Result<Vec<DealRecord>, AppError> {
+        let mut deals: Vec<DealRecord> = self
+            .tables
+            .lock()
+            .unwrap()
+            .deals
+            .values()
+            .filter(|deal| selects_optional_epic(query, deal.epic.as_deref(), deal.timestamp))
+            .cloned()
+            .collect();
+        deals.sort_by(|a, b| {
+            (a.timestamp, &a.deal_reference).cmp(&(b.timestamp, &b.deal_reference))
+        });
+        Ok(take(deals, query))
+    }
+
+    async fn put_instruments(&self, entries: &[CatalogEntry]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tables = self.tables.lock().unwrap();
+        for entry in entries {
+            upsert(
+                &mut tables.instruments,
+                entry.epic.clone(),
+                entry.clone(),
+                &mut report,
+            );
+        }
+        Ok(report)
+    }
+
+    async fn get_instrument(&self, epic: &str) -> Result<Option<CatalogEntry>, AppError> {
+        Ok(self.tables.lock().unwrap().instruments.get(epic).cloned())
+    }
+
+    async fn search_instruments(
+        &self,
+        query: &CatalogQuery,
+    ) -> Result<Vec<CatalogEntry>, AppError> {
+        let entries: Vec<CatalogEntry> = self
+            .tables
+            .lock()
+            .unwrap()
+            .instruments
+            .values()
+            .cloned()
+            .collect();
+        Ok(query.select(entries))
+    }
+}
+
+/// Returns true if a record with an optional EPIC is selected
+///
+/// Records without an EPIC are only selected by queries without one, as in SQL where
+/// a null never equals a value.
+fn selects_optional_epic(query: &StoreQuery, epic: Option<&str>, timestamp: DateTime<Utc>) -> bool {
+    match (query.epic.as_deref(), epic) {
+        (Some(_), None) => false,
+        (_, epic) => query.matches(epic.unwrap_or_default(), timestamp),
+    }
+}
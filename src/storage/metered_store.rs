@@ -0,0 +1,345 @@
+use crate::application::models::account::AccountTransaction;
+use crate::application::models::market::Resolution;
+use crate::error::AppError;
+use crate::storage::Store;
+use crate::storage::instrument_catalog::{CatalogEntry, CatalogQuery};
+use crate::storage::order_journal::{DealRecord, JournalEntry};
+use crate::storage::store::{Candle, StoreQuery, Tick, WriteReport};
+use crate::utils::metrics::{increment_counter, record_histogram};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Counter of records written, labelled by `table` and `outcome` (`inserted`,
+/// `updated` or `unchanged`)
+pub const STORE_RECORDS_WRITTEN: &str = "ig_store_records_written_total";
+/// Counter of records deleted, labelled by `table`
+pub const STORE_RECORDS_DELETED: &str = "ig_store_records_deleted_total";
+/// Histogram of the number of records of a write, labelled by `table`
+pub const STORE_BATCH_SIZE: &str = "ig_store_batch_size";
+/// Histogram of the seconds taken by a write, labelled by `table`
+pub const STORE_WRITE_LATENCY: &str = "ig_store_write_latency_seconds";
+/// Counter of store operations that returned an error, labelled by `operation`
+pub const STORE_ERRORS: &str = "ig_store_errors_total";
+
+/// Statistics of the writes to one table of a store
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableWriteStats {
+    /// Successful writes
+    pub batches: u64,
+    /// Records of the successful writes, by outcome
+    pub records: WriteReport,
+    /// Largest number of records written at once
+    pub max_batch_size: usize,
+    /// Time spent in successful writes
+    pub write_time: Duration,
+}
+
+impl TableWriteStats {
+    /// Average number of records of a write, `None` before any write
+    pub fn average_batch_size(&self) -> Option<f64> {
+        (self.batches > 0).then(|| self.records.total() as f64 / self.batches as f64)
+    }
+
+    /// Average time of a write, `None` before any write
+    pub fn average_latency(&self) -> Option<Duration> {
+        (self.batches > 0).then(|| self.write_time / self.batches as u32)
+    }
+
+    /// Records written per second of write time, `None` before any write
+    pub fn throughput(&self) -> Option<f64> {
+        let seconds = self.write_time.as_secs_f64();
+        (seconds > 0.0).then(|| self.records.total() as f64 / seconds)
+    }
+}
+
+/// Statistics of a store at a point in time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreMetricsSnapshot {
+    /// Write statistics by table: `candles`, `ticks`, `transactions`, `orders`,
+    /// `deals` or `instruments`
+    pub writes: HashMap<&'static str, TableWriteStats>,
+    /// Records deleted by table
+    pub deleted: HashMap<&'static str, u64>,
+    /// Operations that returned an error, reads included
+    pub errors: u64,
+}
+
+impl StoreMetricsSnapshot {
+    /// Returns the write statistics of a table, empty if it was never written
+    pub fn table(&self, table: &str) -> TableWriteStats {
+        self.writes.get(table).cloned().unwrap_or_default()
+    }
+}
+
+/// Store wrapper measuring the writes of the storage writers
+///
+/// Wrap the store given to a [`TickRecorder`], a [`RetentionCompactor`] or any other
+/// writer to follow its health: records written per table and outcome, batch sizes,
+/// write latencies, deletions and errors. Statistics are kept in process and read
+/// with [`MeteredStore::snapshot`]; with the `metrics` feature they are also reported
+/// through the `metrics` facade under the names of the constants of this module.
+///
+/// [`TickRecorder`]: crate::application::services::tick_recorder::TickRecorder
+/// [`RetentionCompactor`]: crate::application::services::retention_compactor::RetentionCompactor
+pub struct MeteredStore<S: Store> {
+    store: S,
+    stats: Mutex<StoreMetricsSnapshot>,
+}
+
+impl<S: Store> MeteredStore<S> {
+    /// Wraps `store`
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            stats: Mutex::new(StoreMetricsSnapshot::default()),
+        }
+    }
+
+    /// Returns the wrapped store
+    pub fn inner(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the statistics collected so far
+    pub fn snapshot(&self) -> StoreMetricsSnapshot {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Clears the statistics, e.g. at the start of a reporting window
+    pub fn reset(&self) {
+        *self.stats.lock().unwrap() = StoreMetricsSnapshot::default();
+    }
+
+    fn written(&self, table: &'static str, report: &WriteReport, latency: Duration) {
+        let labels = [("table", table.to_string())];
+        record_histogram(STORE_BATCH_SIZE, report.total() as f64, &labels);
+        record_histogram(STORE_WRITE_LATENCY, latency.as_secs_f64(), &labels);
+        for (outcome, count) in [
+            ("inserted", report.inserted),
+            ("updated", report.updated),
+            ("unchanged", report.unchanged),
+        ] {
+            if count > 0 {
+                increment_counter(
+                    STORE_RECORDS_WRITTEN,
+                    count as u64,
+                    &[
+                        ("table", table.to_string()),
+                        ("outcome", outcome.to_string()),
+                    ],
+                );
+            }
+        }
+
+        let mut stats = self.stats.lock().unwrap();
+        let table = stats.writes.entry(table).or_default();
+        table.batches += 1;
+        table.records += *report;
+        table.max_batch_size = table.max_batch_size.max(report.total());
+        table.write_time += latency;
+    }
+
+    fn deleted(&self, table: &'static str, count: usize) {
+        if count > 0 {
+            *self.stats.lock().unwrap().deleted.entry(table).or_insert(0) += count as u64;
+            increment_counter(
+                STORE_RECORDS_DELETED,
+                count as u64,
+                &[("table", table.to_string())],
+            );
+        }
+    }
+
+    fn errored(&self, operation: &'static str) {
+        self.stats.lock().unwrap().errors += 1;
+        increment_counter(STORE_ERRORS, 1, &[("operation", operation.to_string())]);
+    }
+
+    fn observe<R>(
+        &self,
+        operation: &'static str,
+        result: Result<R, AppError>,
+    ) -> Result<R, AppError> {
+        if result.is_err() {
+            self.errored(operation);
+        }
+        result
+    }
+
+    /// Measures a write to `table`
+    fn observe_write(
+        &self,
+        table: &'static str,
+        operation: &'static str,
+        started: Instant,
+        result: Result<WriteReport, AppError>,
+    ) -> Result<WriteReport, AppError> {
+        match &result {
+            Ok(report) => self.written(table, report, started.elapsed()),
+            Err(_) => self.errored(operation),
+        }
+        result
+    }
+
+    /// Counts the records deleted from `table`
+    fn observe_delete(
+        &self,
+        table: &'static str,
+        operation: &'static str,
+        result: Result<usize, AppError>,
+    ) -> Result<usize, AppError> {
+        match &result {
+            Ok(count) => self.deleted(table, *count),
+            Err(_) => self.errored(operation),
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<S: Store> Store for MeteredStore<S> {
+    async fn put_candles(&self, candles: &[Candle]) -> Result<WriteReport, AppError> {
+        let started = Instant::now();
+        let result = self.store.put_candles(candles).await;
+        self.observe_write("candles", "put_candles", started, result)
+    }
+
+    async fn get_candle(
+        &self,
+        epic: &str,
+        resolution: Resolution,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Candle>, AppError> {
+        let result = self.store.get_candle(epic, resolution, timestamp).await;
+        self.observe("get_candle", result)
+    }
+
+    async fn query_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<Vec<Candle>, AppError> {
+        let result = self.store.query_candles(resolution, query).await;
+        self.observe("query_candles", result)
+    }
+
+    async fn latest_candles(
+        &self,
+        epic: &str,
+        resolution: Resolution,
+        count: usize,
+    ) -> Result<Vec<Candle>, AppError> {
+        let result = self.store.latest_candles(epic, resolution, count).await;
+        self.observe("latest_candles", result)
+    }
+
+    async fn delete_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<usize, AppError> {
+        let result = self.store.delete_candles(resolution, query).await;
+        self.observe_delete("candles", "delete_candles", result)
+    }
+
+    async fn put_ticks(&self, ticks: &[Tick]) -> Result<WriteReport, AppError> {
+        let started = Instant::now();
+        let result = self.store.put_ticks(ticks).await;
+        self.observe_write("ticks", "put_ticks", started, result)
+    }
+
+    async fn get_tick(
+        &self,
+        epic: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Tick>, AppError> {
+        let result = self.store.get_tick(epic, timestamp).await;
+        self.observe("get_tick", result)
+    }
+
+    async fn query_ticks(&self, query: &StoreQuery) -> Result<Vec<Tick>, AppError> {
+        let result = self.store.query_ticks(query).await;
+        self.observe("query_ticks", result)
+    }
+
+    async fn delete_ticks(&self, query: &StoreQuery) -> Result<usize, AppError> {
+        let result = self.store.delete_ticks(query).await;
+        self.observe_delete("ticks", "delete_ticks", result)
+    }
+
+    async fn put_transactions(
+        &self,
+        transactions: &[AccountTransaction],
+    ) -> Result<WriteReport, AppError> {
+        let started = Instant::now();
+        let result = self.store.put_transactions(transactions).await;
+        self.observe_write("transactions", "put_transactions", started, result)
+    }
+
+    async fn get_transactions(&self, reference: &str) -> Result<Vec<AccountTransaction>, AppError> {
+        let result = self.store.get_transactions(reference).await;
+        self.observe("get_transactions", result)
+    }
+
+    async fn query_transactions(
+        &self,
+        query: &StoreQuery,
+    ) -> Result<Vec<AccountTransaction>, AppError> {
+        let result = self.store.query_transactions(query).await;
+        self.observe("query_transactions", result)
+    }
+
+    async fn put_orders(&self, entries: &[JournalEntry]) -> Result<WriteReport, AppError> {
+        let started = Instant::now();
+        let result = self.store.put_orders(entries).await;
+        self.observe_write("orders", "put_orders", started, result)
+    }
+
+    async fn get_orders(&self, correlation_id: &str) -> Result<Vec<JournalEntry>, AppError> {
+        let result = self.store.get_orders(correlation_id).await;
+        self.observe("get_orders", result)
+    }
+
+    async fn query_orders(&self, query: &StoreQuery) -> Result<Vec<JournalEntry>, AppError> {
+        let result = self.store.query_orders(query).await;
+        self.observe("query_orders", result)
+    }
+
+    async fn put_deals(&self, deals: &[DealRecord]) -> Result<WriteReport, AppError> {
+        let started = Instant::now();
+        let result = self.store.put_deals(deals).await;
+        self.observe_write("deals", "put_deals", started, result)
+    }
+
+    async fn get_deal(&self, deal_reference: &str) -> Result<Option<DealRecord>, AppError> {
+        let result = self.store.get_deal(deal_reference).await;
+        self.observe("get_deal", result)
+    }
+
+    async fn query_deals(&self, query: &StoreQuery) -> Result<Vec<DealRecord>, AppError> {
+        let result = self.store.query_deals(query).await;
+        self.observe("query_deals", result)
+    }
+
+    async fn put_instruments(&self, entries: &[CatalogEntry]) -> Result<WriteReport, AppError> {
+        let started = Instant::now();
+        let result = self.store.put_instruments(entries).await;
+        self.observe_write("instruments", "put_instruments", started, result)
+    }
+
+    async fn get_instrument(&self, epic: &str) -> Result<Option<CatalogEntry>, AppError> {
+        let result = self.store.get_instrument(epic).await;
+        self.observe("get_instrument", result)
+    }
+
+    async fn search_instruments(
+        &self,
+        query: &CatalogQuery,
+    ) -> Result<Vec<CatalogEntry>, AppError> {
+        let result = self.store.search_instruments(query).await;
+        self.observe("search_instruments", result)
+    }
+}
@@ -1,4 +1,40 @@
+/// Module containing snapshots of the positions, orders and balance of an account
+pub mod account_snapshots;
+/// Module containing balance snapshots recorded over time and their analysis
+pub mod balance_history;
+/// Module containing a buffered writer of record batches
+pub mod batch_writer;
 /// Module containing database configuration structures
 pub mod config;
+/// Module containing the offline catalog of instruments and its search
+pub mod instrument_catalog;
+/// Module containing generic stores of records kept in memory or in JSON lines files
+pub mod json_lines;
+/// Module containing an in-memory implementation of the storage trait
+pub mod memory_store;
+/// Module containing a storage wrapper reporting metrics of the writes
+pub mod metered_store;
+/// Module containing journals recording the audit trail of orders
+pub mod order_journal;
+/// Module containing writers of partitioned Parquet datasets of stored data
+#[cfg(feature = "parquet")]
+pub mod parquet_dataset;
+/// Module containing a PostgreSQL implementation of the storage trait
+pub mod pg_store;
+/// Module containing time-ordered streams of stored prices with their gaps
+pub mod price_series;
+/// Module containing retention policies of stored market data
+pub mod retention;
+/// Module containing portable snapshots of stored data
+pub mod snapshot;
+/// Module containing a SQLite implementation of the storage trait
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+/// Module containing the storage trait for market data, transactions and orders
+pub mod store;
+/// Module containing local mirrors of the transaction history with sync checkpoints
+pub mod transaction_store;
 /// Module containing utility functions for database operations
 pub mod utils;
+
+pub use store::Store;
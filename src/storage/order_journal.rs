@@ -0,0 +1,366 @@
+use crate::error::AppError;
+use crate::storage::Store;
+use crate::storage::json_lines::{JsonLinesFile, MemoryRecords};
+use crate::storage::store::WriteReport;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Kind of event recorded in an order journal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum JournalEntryKind {
+    /// Request sent to IG: new order, working order, close or deletion
+    Request,
+    /// Amendment of a position or a working order
+    Amendment,
+    /// Response of IG to a request or an amendment
+    Response,
+    /// Deal confirmation
+    Confirmation,
+    /// Error returned instead of a response
+    Error,
+}
+
+/// One event of the audit trail of orders
+///
+/// Every entry of the same order carries the same correlation ID, from the request to
+/// the confirmation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    /// Time the event was recorded
+    pub timestamp: DateTime<Utc>,
+    /// Identifier shared by the entries of one order
+    pub correlation_id: String,
+    /// Kind of event
+    pub kind: JournalEntryKind,
+    /// Order service operation, e.g. `create_order`
+    pub operation: String,
+    /// EPIC of the market, if known
+    pub epic: Option<String>,
+    /// Deal reference, once known
+    pub deal_reference: Option<String>,
+    /// Deal ID of the position or working order, if known
+    pub deal_id: Option<String>,
+    /// Request, response, confirmation or error as sent or received
+    pub payload: Value,
+}
+
+impl JournalEntry {
+    /// Creates an entry timestamped now
+    pub fn new(
+        correlation_id: &str,
+        kind: JournalEntryKind,
+        operation: &str,
+        payload: Value,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            correlation_id: correlation_id.to_string(),
+            kind,
+            operation: operation.to_string(),
+            epic: None,
+            deal_reference: None,
+            deal_id: None,
+            payload,
+        }
+    }
+
+    /// Sets the EPIC of the market
+    pub fn with_epic(mut self, epic: Option<&str>) -> Self {
+        self.epic = epic.map(str::to_string);
+        self
+    }
+
+    /// Sets the deal reference
+    pub fn with_deal_reference(mut self, deal_reference: Option<&str>) -> Self {
+        self.deal_reference = deal_reference.map(str::to_string);
+        self
+    }
+
+    /// Sets the deal ID
+    pub fn with_deal_id(mut self, deal_id: Option<&str>) -> Self {
+        self.deal_id = deal_id.map(str::to_string);
+        self
+    }
+}
+
+/// Selects journal entries; every criterion set must match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JournalQuery {
+    /// Correlation ID of the order
+    pub correlation_id: Option<String>,
+    /// Deal reference
+    pub deal_reference: Option<String>,
+    /// Deal ID
+    pub deal_id: Option<String>,
+    /// EPIC of the market
+    pub epic: Option<String>,
+    /// Kind of event
+    pub kind: Option<JournalEntryKind>,
+    /// Earliest timestamp, inclusive
+    pub from: Option<DateTime<Utc>>,
+    /// Latest timestamp, exclusive
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl JournalQuery {
+    /// Creates a query selecting every entry
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Only selects entries of this correlation ID
+    pub fn with_correlation_id(mut self, correlation_id: &str) -> Self {
+        self.correlation_id = Some(correlation_id.to_string());
+        self
+    }
+
+    /// Only selects entries with this deal reference
+    pub fn with_deal_reference(mut self, deal_reference: &str) -> Self {
+        self.deal_reference = Some(deal_reference.to_string());
+        self
+    }
+
+    /// Only selects entries with this deal ID
+    pub fn with_deal_id(mut self, deal_id: &str) -> Self {
+        self.deal_id = Some(deal_id.to_string());
+        self
+    }
+
+    /// Only selects entries on this EPIC
+    pub fn with_epic(mut self, epic: &str) -> Self {
+        self.epic = Some(epic.to_string());
+        self
+    }
+
+    /// Only selects entries of this kind
+    pub fn with_kind(mut self, kind: JournalEntryKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Only selects entries recorded in `[from, to)`
+    pub fn between(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self.to = Some(to);
+        self
+    }
+
+    /// Returns true if `entry` is selected
+    pub fn matches(&self, entry: &JournalEntry) -> bool {
+        fn field_matches(wanted: &Option<String>, value: Option<&str>) -> bool {
+            wanted.as_deref().is_none_or(|wanted| Some(wanted) == value)
+        }
+        field_matches(&self.correlation_id, Some(&entry.correlation_id))
+            && field_matches(&self.deal_reference, entry.deal_reference.as_deref())
+            && field_matches(&self.deal_id, entry.deal_id.as_deref())
+            && field_matches(&self.epic, entry.epic.as_deref())
+            && self.kind.is_none_or(|kind| kind == entry.kind)
+            && self.from.is_none_or(|from| entry.timestamp >= from)
+            && self.to.is_none_or(|to| entry.timestamp < to)
+    }
+}
+
+/// Append-only store of order events
+///
+/// Used by [`JournaledOrderService`](crate::application::services::journaled_order_service::JournaledOrderService)
+/// to keep a local audit trail of orders, independent of the activity history of IG.
+pub trait OrderJournal: Send + Sync {
+    /// Appends an entry
+    fn append(&self, entry: &JournalEntry) -> Result<(), AppError>;
+
+    /// Returns the entries selected by `query`, in the order they were appended
+    fn query(&self, query: &JournalQuery) -> Result<Vec<JournalEntry>, AppError>;
+}
+
+/// In-memory journal, lost when the process exits
+pub type MemoryOrderJournal = MemoryRecords<JournalEntry>;
+
+impl OrderJournal for MemoryRecords<JournalEntry> {
+    fn append(&self, entry: &JournalEntry) -> Result<(), AppError> {
+        self.push(entry);
+        Ok(())
+    }
+
+    fn query(&self, query: &JournalQuery) -> Result<Vec<JournalEntry>, AppError> {
+        Ok(self.filter(|entry| query.matches(entry)))
+    }
+}
+
+/// Journal keeping one entry per line of a [`JsonLinesFile`], so the trail survives a
+/// crash
+pub type FileOrderJournal = JsonLinesFile<JournalEntry>;
+
+impl OrderJournal for JsonLinesFile<JournalEntry> {
+    fn append(&self, entry: &JournalEntry) -> Result<(), AppError> {
+        JsonLinesFile::append(self, entry)
+    }
+
+    fn query(&self, query: &JournalQuery) -> Result<Vec<JournalEntry>, AppError> {
+        self.filter(|entry| query.matches(entry))
+    }
+}
+
+/// One deal of the journal, from its request to its confirmation
+///
+/// Deals are linked by deal reference: the response returning the reference and the
+/// confirmation carrying it are joined with the request of the same correlation ID.
+/// A close-all or cancel-all gives one deal per reference returned. The confirmation
+/// tells whether the deal was filled, and at which level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DealRecord {
+    /// Deal reference returned by IG
+    pub deal_reference: String,
+    /// Correlation ID of the order in the journal
+    pub correlation_id: String,
+    /// Order service operation, e.g. `create_order`
+    pub operation: String,
+    /// Time the deal reference was returned, or confirmed when no response was recorded
+    pub timestamp: DateTime<Utc>,
+    /// EPIC of the market, if known
+    pub epic: Option<String>,
+    /// Direction of the deal, `BUY` or `SELL`, if known
+    pub direction: Option<String>,
+    /// Size requested, or filled once confirmed
+    pub size: Option<f64>,
+    /// Time the request was recorded
+    pub requested_at: Option<DateTime<Utc>>,
+    /// Time the confirmation was recorded
+    pub confirmed_at: Option<DateTime<Utc>>,
+    /// Deal status of the confirmation, e.g. `ACCEPTED` or `REJECTED`
+    pub deal_status: Option<String>,
+    /// Reason given by the confirmation
+    pub reason: Option<String>,
+    /// Deal ID of the position or working order
+    pub deal_id: Option<String>,
+    /// Level the deal was filled at
+    pub level: Option<f64>,
+}
+
+impl DealRecord {
+    /// Returns true if the confirmation accepted the deal
+    pub fn is_filled(&self) -> bool {
+        self.deal_status.as_deref() == Some("ACCEPTED")
+    }
+
+    /// Completes the deal with the fields of a request or confirmation payload
+    fn apply_payload(&mut self, payload: &Value) {
+        if let Some(epic) = payload_text(payload, "epic") {
+            self.epic = Some(epic);
+        }
+        if let Some(direction) = payload_text(payload, "direction") {
+            self.direction = Some(direction);
+        }
+        if let Some(size) = payload.get("size").and_then(Value::as_f64) {
+            self.size = Some(size);
+        }
+    }
+}
+
+fn payload_text(payload: &Value, field: &str) -> Option<String> {
+    payload
+        .get(field)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// Returns the deals of journal entries, oldest first
+///
+/// Entries of one correlation ID should all be given, so each deal is joined with its
+/// request; see [`Store::record_orders`](crate::storage::Store::record_orders).
+pub fn deal_records(entries: &[JournalEntry]) -> Vec<DealRecord> {
+    let mut sorted: Vec<&JournalEntry> = entries.iter().collect();
+    sorted.sort_by_key(|entry| entry.timestamp);
+
+    let mut deals: Vec<DealRecord> = Vec::new();
+    for entry in &sorted {
+        let Some(reference) = entry.deal_reference.as_deref() else {
+            continue;
+        };
+        if !matches!(
+            entry.kind,
+            JournalEntryKind::Response | JournalEntryKind::Confirmation
+        ) {
+            continue;
+        }
+        let index = match deals
+            .iter()
+            .position(|deal| deal.deal_reference == reference)
+        {
+            Some(index) => index,
+            None => {
+                let request = sorted.iter().find(|request| {
+                    request.correlation_id == entry.correlation_id
+                        && matches!(
+                            request.kind,
+                            JournalEntryKind::Request | JournalEntryKind::Amendment
+                        )
+                });
+                let mut deal = DealRecord {
+                    deal_reference: reference.to_string(),
+                    correlation_id: entry.correlation_id.clone(),
+                    operation: request
+                        .map_or(&entry.operation, |request| &request.operation)
+                        .clone(),
+                    timestamp: entry.timestamp,
+                    epic: None,
+                    direction: None,
+                    size: None,
+                    requested_at: request.map(|request| request.timestamp),
+                    confirmed_at: None,
+                    deal_status: None,
+                    reason: None,
+                    deal_id: None,
+                    level: None,
+                };
+                if let Some(request) = request {
+                    deal.epic = request.epic.clone();
+                    deal.deal_id = request.deal_id.clone();
+                    deal.apply_payload(&request.payload);
+                }
+                deals.push(deal);
+                deals.len() - 1
+            }
+        };
+        let deal = &mut deals[index];
+        if entry.epic.is_some() {
+            deal.epic = entry.epic.clone();
+        }
+        if entry.deal_id.is_some() {
+            deal.deal_id = entry.deal_id.clone();
+        }
+        if entry.kind == JournalEntryKind::Confirmation {
+            deal.apply_payload(&entry.payload);
+            deal.confirmed_at = Some(entry.timestamp);
+            deal.deal_status = payload_text(&entry.payload, "dealStatus");
+            deal.reason = payload_text(&entry.payload, "reason");
+            deal.level = entry.payload.get("level").and_then(Value::as_f64);
+        }
+    }
+    deals
+}
+
+/// Copies the entries of a journal selected by `query` into a [`Store`], with their
+/// deals
+///
+/// Copying again is safe, entries and deals replace the stored ones. Use it to keep a
+/// database in line with a [`FileOrderJournal`].
+///
+/// # Returns
+/// The report of the write of the entries, counting those already copied as
+/// unchanged
+pub async fn copy_journal<J, S>(
+    journal: &J,
+    store: &S,
+    query: &JournalQuery,
+) -> Result<WriteReport, AppError>
+where
+    J: OrderJournal + ?Sized,
+    S: Store + ?Sized,
+{
+    let entries = journal.query(query)?;
+    store.record_orders(&entries).await
+}
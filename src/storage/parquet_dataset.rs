@@ -0,0 +1,313 @@
+use crate::application::models::account::AccountTransaction;
+use crate::application::models::market::Resolution;
+use crate::application::models::transaction::EnrichedTransaction;
+use crate::error::AppError;
+use crate::storage::Store;
+use crate::storage::store::{Candle, StoreQuery, Tick};
+use crate::storage::transaction_store::transaction_date;
+use crate::utils::atomic_file::AtomicFile;
+use crate::utils::export::{parquet_error, write_column, write_transactions_parquet};
+use chrono::NaiveDate;
+use parquet::column::writer::ColumnWriter;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Name of the file written in each partition
+pub const PARTITION_FILE_NAME: &str = "part-0.parquet";
+
+/// Parquet schema of a candle partition; EPIC, resolution and date are in the path
+const CANDLE_SCHEMA: &str = "
+message candle {
+    OPTIONAL INT64 timestamp (TIMESTAMP(MICROS, true));
+    OPTIONAL DOUBLE open;
+    OPTIONAL DOUBLE high;
+    OPTIONAL DOUBLE low;
+    OPTIONAL DOUBLE close;
+    OPTIONAL INT64 volume;
+}";
+
+/// Parquet schema of a tick partition; EPIC and date are in the path
+const TICK_SCHEMA: &str = "
+message tick {
+    OPTIONAL INT64 timestamp (TIMESTAMP(MICROS, true));
+    OPTIONAL DOUBLE bid;
+    OPTIONAL DOUBLE offer;
+}";
+
+/// Values of one column of a partition, in schema order
+enum ColumnValues {
+    Int64(Vec<Option<i64>>),
+    Double(Vec<Option<f64>>),
+}
+
+/// Partition file written by a [`ParquetDatasetWriter`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatasetPartition {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Number of rows in the file
+    pub rows: usize,
+}
+
+/// Writes stored candles, ticks and transactions as partitioned Parquet datasets
+///
+/// Datasets use Hive-style partitions under the root directory:
+///
+/// - `candles/resolution=<RESOLUTION>/epic=<EPIC>/date=<YYYY-MM-DD>/part-0.parquet`
+/// - `ticks/epic=<EPIC>/date=<YYYY-MM-DD>/part-0.parquet`
+/// - `transactions/date=<YYYY-MM-DD>/part-0.parquet`
+///
+/// so they can be read directly, for instance with
+/// `read_parquet('<root>/candles/**/*.parquet', hive_partitioning = true)` in DuckDB or
+/// `pyarrow.dataset.dataset(path, partitioning = "hive")` in Python. Partition values
+/// are not repeated inside the files. Transactions carry an instrument name rather than
+/// an EPIC, so they are only partitioned by date.
+///
+/// Dates are UTC. Writing a partition replaces its file, so export whole days: a
+/// partial day replaces the rows written before. Files are written next to their
+/// final path and renamed, so readers never see a partial file.
+#[derive(Debug, Clone)]
+pub struct ParquetDatasetWriter {
+    root: PathBuf,
+}
+
+impl ParquetDatasetWriter {
+    /// Creates a writer of datasets under `root`, created on the first write
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the root directory of the datasets
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the directory of the candles of a market on a day
+    ///
+    /// # Returns
+    /// The directory, or `AppError::InvalidInput` if the EPIC cannot be a path component
+    pub fn candle_partition(
+        &self,
+        epic: &str,
+        resolution: Resolution,
+        date: NaiveDate,
+    ) -> Result<PathBuf, AppError> {
+        Ok(self
+            .root
+            .join("candles")
+            .join(format!("resolution={}", resolution.as_str()))
+            .join(format!("epic={}", partition_value(epic)?))
+            .join(format!("date={date}")))
+    }
+
+    /// Returns the directory of the ticks of a market on a day
+    ///
+    /// # Returns
+    /// The directory, or `AppError::InvalidInput` if the EPIC cannot be a path component
+    pub fn tick_partition(&self, epic: &str, date: NaiveDate) -> Result<PathBuf, AppError> {
+        Ok(self
+            .root
+            .join("ticks")
+            .join(format!("epic={}", partition_value(epic)?))
+            .join(format!("date={date}")))
+    }
+
+    /// Returns the directory of the transactions of a day
+    pub fn transaction_partition(&self, date: NaiveDate) -> PathBuf {
+        self.root.join("transactions").join(format!("date={date}"))
+    }
+
+    /// Writes candles, one file per resolution, market and day
+    ///
+    /// # Returns
+    /// The files written, in path order
+    pub fn write_candles(&self, candles: &[Candle]) -> Result<Vec<DatasetPartition>, AppError> {
+        let mut partitions: BTreeMap<PathBuf, Vec<&Candle>> = BTreeMap::new();
+        for candle in candles {
+            let directory = self.candle_partition(
+                &candle.epic,
+                candle.resolution,
+                candle.timestamp.date_naive(),
+            )?;
+            partitions.entry(directory).or_default().push(candle);
+        }
+        partitions
+            .into_iter()
+            .map(|(directory, mut candles)| {
+                candles.sort_by_key(|candle| candle.timestamp);
+                let columns = vec![
+                    ColumnValues::Int64(
+                        candles
+                            .iter()
+                            .map(|candle| Some(candle.timestamp.timestamp_micros()))
+                            .collect(),
+                    ),
+                    ColumnValues::Double(candles.iter().map(|candle| Some(candle.open)).collect()),
+                    ColumnValues::Double(candles.iter().map(|candle| Some(candle.high)).collect()),
+                    ColumnValues::Double(candles.iter().map(|candle| Some(candle.low)).collect()),
+                    ColumnValues::Double(candles.iter().map(|candle| Some(candle.close)).collect()),
+                    ColumnValues::Int64(candles.iter().map(|candle| candle.volume).collect()),
+                ];
+                write_partition(&directory, candles.len(), |writer| {
+                    write_rows(writer, CANDLE_SCHEMA, columns)
+                })
+            })
+            .collect()
+    }
+
+    /// Writes ticks, one file per market and day
+    ///
+    /// # Returns
+    /// The files written, in path order
+    pub fn write_ticks(&self, ticks: &[Tick]) -> Result<Vec<DatasetPartition>, AppError> {
+        let mut partitions: BTreeMap<PathBuf, Vec<&Tick>> = BTreeMap::new();
+        for tick in ticks {
+            let directory = self.tick_partition(&tick.epic, tick.timestamp.date_naive())?;
+            partitions.entry(directory).or_default().push(tick);
+        }
+        partitions
+            .into_iter()
+            .map(|(directory, mut ticks)| {
+                ticks.sort_by_key(|tick| tick.timestamp);
+                let columns = vec![
+                    ColumnValues::Int64(
+                        ticks
+                            .iter()
+                            .map(|tick| Some(tick.timestamp.timestamp_micros()))
+                            .collect(),
+                    ),
+                    ColumnValues::Double(ticks.iter().map(|tick| tick.bid).collect()),
+                    ColumnValues::Double(ticks.iter().map(|tick| tick.offer).collect()),
+                ];
+                write_partition(&directory, ticks.len(), |writer| {
+                    write_rows(writer, TICK_SCHEMA, columns)
+                })
+            })
+            .collect()
+    }
+
+    /// Writes transactions, one file per day, with the columns of
+    /// [`write_transactions_parquet`]
+    ///
+    /// # Returns
+    /// The files written, in path order, or `AppError::InvalidInput` if a date cannot
+    /// be parsed
+    pub fn write_transactions(
+        &self,
+        transactions: &[AccountTransaction],
+    ) -> Result<Vec<DatasetPartition>, AppError> {
+        let mut partitions: BTreeMap<PathBuf, Vec<AccountTransaction>> = BTreeMap::new();
+        for transaction in transactions {
+            let date = transaction_date(transaction)?.date_naive();
+            partitions
+                .entry(self.transaction_partition(date))
+                .or_default()
+                .push(transaction.clone());
+        }
+        partitions
+            .into_iter()
+            .map(|(directory, mut transactions)| {
+                transactions.sort_by(|a, b| a.date_utc.cmp(&b.date_utc));
+                let enriched = EnrichedTransaction::enrich(&transactions);
+                write_partition(&directory, enriched.len(), |writer| {
+                    write_transactions_parquet(writer, &enriched)
+                })
+            })
+            .collect()
+    }
+
+    /// Writes the candles at `resolution` of `store` selected by `query`
+    ///
+    /// See [`ParquetDatasetWriter::write_candles`].
+    pub async fn export_candles<S: Store + ?Sized>(
+        &self,
+        store: &S,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<Vec<DatasetPartition>, AppError> {
+        self.write_candles(&store.query_candles(resolution, query).await?)
+    }
+
+    /// Writes the ticks of `store` selected by `query`
+    ///
+    /// See [`ParquetDatasetWriter::write_ticks`].
+    pub async fn export_ticks<S: Store + ?Sized>(
+        &self,
+        store: &S,
+        query: &StoreQuery,
+    ) -> Result<Vec<DatasetPartition>, AppError> {
+        self.write_ticks(&store.query_ticks(query).await?)
+    }
+
+    /// Writes the transactions of `store` selected by `query`
+    ///
+    /// See [`ParquetDatasetWriter::write_transactions`].
+    pub async fn export_transactions<S: Store + ?Sized>(
+        &self,
+        store: &S,
+        query: &StoreQuery,
+    ) -> Result<Vec<DatasetPartition>, AppError> {
+        self.write_transactions(&store.query_transactions(query).await?)
+    }
+}
+
+/// Checks that an EPIC can name a partition directory
+fn partition_value(epic: &str) -> Result<&str, AppError> {
+    if epic.is_empty() || epic == "." || epic == ".." || epic.contains(['/', '\\']) {
+        return Err(AppError::InvalidInput(format!(
+            "EPIC cannot name a partition: {epic:?}"
+        )));
+    }
+    Ok(epic)
+}
+
+/// Writes the file of a partition through an [`AtomicFile`]
+fn write_partition(
+    directory: &Path,
+    rows: usize,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), AppError>,
+) -> Result<DatasetPartition, AppError> {
+    std::fs::create_dir_all(directory)?;
+    let path = directory.join(PARTITION_FILE_NAME);
+    let mut file = AtomicFile::create(&path)?;
+    write(file.writer())?;
+    file.commit()?;
+    Ok(DatasetPartition { path, rows })
+}
+
+/// Writes `columns` as one row group of a file with `schema`
+fn write_rows<W: Write + Send>(
+    writer: W,
+    schema: &str,
+    columns: Vec<ColumnValues>,
+) -> Result<(), AppError> {
+    let schema = Arc::new(parse_message_type(schema).map_err(parquet_error)?);
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut file = SerializedFileWriter::new(writer, schema, properties).map_err(parquet_error)?;
+    let mut group = file.next_row_group().map_err(parquet_error)?;
+    let mut columns = columns.into_iter();
+    while let Some(mut column) = group.next_column().map_err(parquet_error)? {
+        match (column.untyped(), columns.next()) {
+            (ColumnWriter::Int64ColumnWriter(writer), Some(ColumnValues::Int64(values))) => {
+                write_column(writer, values)?
+            }
+            (ColumnWriter::DoubleColumnWriter(writer), Some(ColumnValues::Double(values))) => {
+                write_column(writer, values)?
+            }
+            _ => unreachable!("columns follow the schema"),
+        }
+        column.close().map_err(parquet_error)?;
+    }
+    group.close().map_err(parquet_error)?;
+    let mut writer = file.into_inner().map_err(parquet_error)?;
+    writer.flush()?;
+    Ok(())
+}
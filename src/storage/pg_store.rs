@@ -0,0 +1,665 @@
+use crate::application::models::account::AccountTransaction;
+use crate::application::models::market::Resolution;
+use crate::config::Config;
+use crate::error::AppError;
+use crate::storage::instrument_catalog::{CatalogEntry, CatalogQuery, instrument_type_name};
+use crate::storage::order_journal::{DealRecord, JournalEntry};
+use crate::storage::store::{Candle, Store, StoreQuery, Tick, WriteReport};
+use crate::storage::transaction_store::transaction_date;
+use crate::storage::utils::{PgMigration, apply_pg_migrations, pg_schema_version};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgRow;
+use sqlx::types::Json;
+use sqlx::{PgPool, Row};
+
+/// Schema migrations of [`PgStore`]: version, description and SQL
+///
+/// Applied versions are recorded in `ig_store_migrations`; new migrations are appended
+/// with the next version and existing ones never change.
+const PG_STORE_MIGRATIONS: [PgMigration; 5] = [
+    (
+        1,
+        "create candles and ticks",
+        r#"
+        CREATE TABLE IF NOT EXISTS ig_candles (
+            epic TEXT NOT NULL,
+            resolution TEXT NOT NULL,
+            timestamp TIMESTAMPTZ NOT NULL,
+            open DOUBLE PRECISION NOT NULL,
+            high DOUBLE PRECISION NOT NULL,
+            low DOUBLE PRECISION NOT NULL,
+            close DOUBLE PRECISION NOT NULL,
+            volume BIGINT,
+            PRIMARY KEY (epic, resolution, timestamp)
+        );
+        CREATE INDEX IF NOT EXISTS ig_candles_timestamp ON ig_candles (resolution, timestamp);
+        CREATE TABLE IF NOT EXISTS ig_ticks (
+            epic TEXT NOT NULL,
+            timestamp TIMESTAMPTZ NOT NULL,
+            bid DOUBLE PRECISION,
+            offer DOUBLE PRECISION,
+            PRIMARY KEY (epic, timestamp)
+        );
+        CREATE INDEX IF NOT EXISTS ig_ticks_timestamp ON ig_ticks (timestamp);
+        "#,
+    ),
+    (
+        2,
+        "create store transactions and orders",
+        r#"
+        CREATE TABLE IF NOT EXISTS ig_store_transactions (
+            reference TEXT NOT NULL,
+            date_utc TEXT NOT NULL,
+            timestamp TIMESTAMPTZ NOT NULL,
+            instrument_name TEXT NOT NULL,
+            raw JSONB NOT NULL,
+            PRIMARY KEY (reference, date_utc)
+        );
+        CREATE INDEX IF NOT EXISTS ig_store_transactions_timestamp
+            ON ig_store_transactions (timestamp);
+        CREATE TABLE IF NOT EXISTS ig_orders (
+            correlation_id TEXT NOT NULL,
+            timestamp TIMESTAMPTZ NOT NULL,
+            kind TEXT NOT NULL,
+            epic TEXT,
+            deal_reference TEXT,
+            raw JSONB NOT NULL,
+            PRIMARY KEY (correlation_id, timestamp, kind)
+        );
+        CREATE INDEX IF NOT EXISTS ig_orders_timestamp ON ig_orders (timestamp);
+        "#,
+    ),
+    (
+        3,
+        "create instrument catalog",
+        r#"
+        CREATE TABLE IF NOT EXISTS ig_instruments (
+            epic TEXT PRIMARY KEY,
+            instrument_type TEXT NOT NULL,
+            currency TEXT,
+            raw JSONB NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS ig_instruments_type ON ig_instruments (instrument_type);
+        "#,
+    ),
+    (
+        4,
+        "create deals",
+        r#"
+        CREATE INDEX IF NOT EXISTS ig_orders_deal_reference ON ig_orders (deal_reference);
+        CREATE TABLE IF NOT EXISTS ig_deals (
+            deal_reference TEXT PRIMARY KEY,
+            correlation_id TEXT NOT NULL,
+            timestamp TIMESTAMPTZ NOT NULL,
+            epic TEXT,
+            raw JSONB NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS ig_deals_epic_timestamp ON ig_deals (epic, timestamp);
+        "#,
+    ),
+    (
+        5,
+        "key store transactions on type, instrument and amount",
+        r#"
+        ALTER TABLE ig_store_transactions
+            ADD COLUMN IF NOT EXISTS transaction_type TEXT NOT NULL DEFAULT '',
+            ADD COLUMN IF NOT EXISTS amount TEXT NOT NULL DEFAULT '';
+        UPDATE ig_store_transactions SET
+            transaction_type = raw->>'transactionType',
+            amount = raw->>'profitAndLoss';
+        ALTER TABLE ig_store_transactions DROP CONSTRAINT IF EXISTS ig_store_transactions_pkey;
+        ALTER TABLE ig_store_transactions
+            ADD PRIMARY KEY (reference, date_utc, transaction_type, instrument_name, amount);
+        "#,
+    ),
+];
+
+/// Latest schema version of [`PgStore`]
+pub const PG_STORE_SCHEMA_VERSION: i64 = PG_STORE_MIGRATIONS[PG_STORE_MIGRATIONS.len() - 1].0;
+
+/// Table recording the applied migrations
+const PG_STORE_MIGRATIONS_TABLE: &str = "ig_store_migrations";
+
+/// Key of the advisory lock serializing migrations across processes
+const PG_STORE_MIGRATION_LOCK: i64 = 0x6967_7374;
+
+/// Postgres binds no limit as a null one
+fn limit(query: &StoreQuery) -> Option<i64> {
+    query.limit.map(|limit| limit as i64)
+}
+
+fn candle_from_row(row: &PgRow) -> Result<Candle, AppError> {
+    let resolution: String = row.try_get("resolution")?;
+    Ok(Candle {
+        epic: row.try_get("epic")?,
+        resolution: resolution.parse()?,
+        timestamp: row.try_get("timestamp")?,
+        open: row.try_get("open")?,
+        high: row.try_get("high")?,
+        low: row.try_get("low")?,
+        close: row.try_get("close")?,
+        volume: row.try_get("volume")?,
+    })
+}
+
+fn tick_from_row(row: &PgRow) -> Result<Tick, AppError> {
+    Ok(Tick {
+        epic: row.try_get("epic")?,
+        timestamp: row.try_get("timestamp")?,
+        bid: row.try_get("bid")?,
+        offer: row.try_get("offer")?,
+    })
+}
+
+/// Unwraps the `raw` JSON column of each row
+fn raw_rows<T>(rows: Vec<Json<T>>) -> Vec<T> {
+    rows.into_iter().map(|Json(value)| value).collect()
+}
+
+/// [`Store`] in a PostgreSQL database
+///
+/// Several processes can share the database, for instance recorders writing market
+/// data and dashboards reading it. Transactions, order journal entries and deals are
+/// kept as JSON next to the columns they are queried on. Call [`PgStore::migrate`] once
+/// before use to create or update the schema.
+#[derive(Debug, Clone)]
+pub struct PgStore {
+    pool: PgPool,
+}
+
+impl PgStore {
+    /// Creates a store on `pool`, see [`Config::pg_pool`]
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Connects to the database of `config` and applies the pending migrations
+    pub async fn connect(config: &Config) -> Result<Self, AppError> {
+        let store = Self::new(config.pg_pool().await?);
+        store.migrate().await?;
+        Ok(store)
+    }
+
+    /// Returns the connection pool of the store
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Applies the migrations not applied yet, each in its own database transaction
+    ///
+    /// Safe to call from several processes at once.
+    ///
+    /// # Returns
+    /// The number of migrations applied, or `AppError::IncompatibleSchema` if the
+    /// database is at a later version than [`PG_STORE_SCHEMA_VERSION`]
+    pub async fn migrate(&self) -> Result<usize, AppError> {
+        apply_pg_migrations(
+            &self.pool,
+            PG_STORE_MIGRATIONS_TABLE,
+            PG_STORE_MIGRATION_LOCK,
+            &PG_STORE_MIGRATIONS,
+        )
+        .await
+    }
+
+    /// Returns the latest migration applied, `None` before the first one
+    pub async fn schema_version(&self) -> Result<Option<i64>, AppError> {
+        pg_schema_version(&self.pool, PG_STORE_MIGRATIONS_TABLE).await
+    }
+}
+
+#[async_trait]
+impl Store for PgStore {
+    async fn put_candles(&self, candles: &[Candle]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for candle in candles {
+            let inserted: Option<bool> = sqlx::query_scalar(
+                r#"
+                INSERT INTO ig_candles (epic, resolution, timestamp, open, high, low, close, volume)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (epic, resolution, timestamp) DO UPDATE SET
+                    open = EXCLUDED.open,
+                    high = EXCLUDED.high,
+                    low = EXCLUDED.low,
+                    close = EXCLUDED.close,
+                    volume = EXCLUDED.volume
+                WHERE (ig_candles.open, ig_candles.high, ig_candles.low, ig_candles.close,
+                    ig_candles.volume) IS DISTINCT FROM (EXCLUDED.open, EXCLUDED.high,
+                    EXCLUDED.low, EXCLUDED.close, EXCLUDED.volume)
+                RETURNING (xmax = 0)
+                "#,
+            )
+            .bind(&candle.epic)
+            .bind(candle.resolution.as_str())
+            .bind(candle.timestamp)
+            .bind(candle.open)
+            .bind(candle.high)
+            .bind(candle.low)
+            .bind(candle.close)
+            .bind(candle.volume)
+            .fetch_optional(&mut *tx)
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_candle(
+        &self,
+        epic: &str,
+        resolution: Resolution,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Candle>, AppError> {
+        sqlx::query(
+            "SELECT * FROM ig_candles WHERE epic = $1 AND resolution = $2 AND timestamp = $3",
+        )
+        .bind(epic)
+        .bind(resolution.as_str())
+        .bind(timestamp)
+        .fetch_optional(&self.pool)
+        .await?
+        .as_ref()
+        .map(candle_from_row)
+        .transpose()
+    }
+
+    async fn query_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<Vec<Candle>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM ig_candles
+            WHERE resolution = $1
+                AND ($2::TEXT IS NULL OR epic = $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR timestamp >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR timestamp < $4)
+            ORDER BY timestamp, epic
+            LIMIT $5
+            "#,
+        )
+        .bind(resolution.as_str())
+        .bind(query.epic.as_deref())
+        .bind(query.from)
+        .bind(query.to)
+        .bind(limit(query))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(candle_from_row).collect()
+    }
+
+    async fn delete_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<usize, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM ig_candles
+            WHERE resolution = $1
+                AND ($2::TEXT IS NULL OR epic = $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR timestamp >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR timestamp < $4)
+            "#,
+        )
+        .bind(resolution.as_str())
+        .bind(query.epic.as_deref())
+        .bind(query.from)
+        .bind(query.to)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn latest_candles(
+        &self,
+        epic: &str,
+        resolution: Resolution,
+        count: usize,
+    ) -> Result<Vec<Candle>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM ig_candles
+            WHERE epic = $1 AND resolution = $2
+            ORDER BY timestamp DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(epic)
+        .bind(resolution.as_str())
+        .bind(count as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().rev().map(candle_from_row).collect()
+    }
+
+    async fn put_ticks(&self, ticks: &[Tick]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for tick in ticks {
+            let inserted: Option<bool> = sqlx::query_scalar(
+                r#"
+                INSERT INTO ig_ticks (epic, timestamp, bid, offer) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (epic, timestamp) DO UPDATE SET
+                    bid = EXCLUDED.bid,
+                    offer = EXCLUDED.offer
+                WHERE (ig_ticks.bid, ig_ticks.offer)
+                    IS DISTINCT FROM (EXCLUDED.bid, EXCLUDED.offer)
+                RETURNING (xmax = 0)
+                "#,
+            )
+            .bind(&tick.epic)
+            .bind(tick.timestamp)
+            .bind(tick.bid)
+            .bind(tick.offer)
+            .fetch_optional(&mut *tx)
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_tick(
+        &self,
+        epic: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Tick>, AppError> {
+        sqlx::query("SELECT * FROM ig_ticks WHERE epic = $1 AND timestamp = $2")
+            .bind(epic)
+            .bind(timestamp)
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(tick_from_row)
+            .transpose()
+    }
+
+    async fn query_ticks(&self, query: &StoreQuery) -> Result<Vec<Tick>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM ig_ticks
+            WHERE ($1::TEXT IS NULL OR epic = $1)
+                AND ($2::TIMESTAMPTZ IS NULL OR timestamp >= $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR timestamp < $3)
+            ORDER BY timestamp, epic
+            LIMIT $4
+            "#,
+        )
+        .bind(query.epic.as_deref())
+        .bind(query.from)
+        .bind(query.to)
+        .bind(limit(query))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(tick_from_row).collect()
+    }
+
+    async fn delete_ticks(&self, query: &StoreQuery) -> Result<usize, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM ig_ticks
+            WHERE ($1::TEXT IS NULL OR epic = $1)
+                AND ($2::TIMESTAMPTZ IS NULL OR timestamp >= $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR timestamp < $3)
+            "#,
+        )
+        .bind(query.epic.as_deref())
+        .bind(query.from)
+        .bind(query.to)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn put_transactions(
+        &self,
+        transactions: &[AccountTransaction],
+    ) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for transaction in transactions {
+            let inserted: Option<bool> = sqlx::query_scalar(
+                r#"
+                INSERT INTO ig_store_transactions (
+                    reference, date_utc, timestamp, instrument_name, raw, transaction_type, amount
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (reference, date_utc, transaction_type, instrument_name, amount)
+                DO UPDATE SET
+                    timestamp = EXCLUDED.timestamp,
+                    raw = EXCLUDED.raw
+                WHERE ig_store_transactions.raw IS DISTINCT FROM EXCLUDED.raw
+                RETURNING (xmax = 0)
+                "#,
+            )
+            .bind(&transaction.reference)
+            .bind(&transaction.date_utc)
+            .bind(transaction_date(transaction)?)
+            .bind(&transaction.instrument_name)
+            .bind(Json(transaction))
+            .bind(&transaction.transaction_type)
+            .bind(&transaction.profit_and_loss)
+            .fetch_optional(&mut *tx)
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_transactions(&self, reference: &str) -> Result<Vec<AccountTransaction>, AppError> {
+        let rows = sqlx::query_scalar(
+            "SELECT raw FROM ig_store_transactions WHERE reference = $1 ORDER BY timestamp",
+        )
+        .bind(reference)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(raw_rows(rows))
+    }
+
+    async fn query_transactions(
+        &self,
+        query: &StoreQuery,
+    ) -> Result<Vec<AccountTransaction>, AppError> {
+        let rows = sqlx::query_scalar(
+            r#"
+            SELECT raw FROM ig_store_transactions
+            WHERE ($1::TEXT IS NULL OR instrument_name = $1)
+                AND ($2::TIMESTAMPTZ IS NULL OR timestamp >= $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR timestamp < $3)
+            ORDER BY timestamp, reference
+            LIMIT $4
+            "#,
+        )
+        .bind(query.epic.as_deref())
+        .bind(query.from)
+        .bind(query.to)
+        .bind(limit(query))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(raw_rows(rows))
+    }
+
+    async fn put_orders(&self, entries: &[JournalEntry]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for entry in entries {
+            let kind = serde_json::to_value(entry.kind)?;
+            let inserted: Option<bool> = sqlx::query_scalar(
+                r#"
+                INSERT INTO ig_orders (correlation_id, timestamp, kind, epic, deal_reference, raw)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (correlation_id, timestamp, kind) DO UPDATE SET
+                    epic = EXCLUDED.epic,
+                    deal_reference = EXCLUDED.deal_reference,
+                    raw = EXCLUDED.raw
+                WHERE ig_orders.raw IS DISTINCT FROM EXCLUDED.raw
+                RETURNING (xmax = 0)
+                "#,
+            )
+            .bind(&entry.correlation_id)
+            .bind(entry.timestamp)
+            .bind(kind.as_str())
+            .bind(entry.epic.as_deref())
+            .bind(entry.deal_reference.as_deref())
+            .bind(Json(entry))
+            .fetch_optional(&mut *tx)
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_orders(&self, correlation_id: &str) -> Result<Vec<JournalEntry>, AppError> {
+        let rows = sqlx::query_scalar(
+            "SELECT raw FROM ig_orders WHERE correlation_id = $1 ORDER BY timestamp",
+        )
+        .bind(correlation_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(raw_rows(rows))
+    }
+
+    async fn query_orders(&self, query: &StoreQuery) -> Result<Vec<JournalEntry>, AppError> {
+        let rows = sqlx::query_scalar(
+            r#"
+            SELECT raw FROM ig_orders
+            WHERE ($1::TEXT IS NULL OR epic = $1)
+                AND ($2::TIMESTAMPTZ IS NULL OR timestamp >= $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR timestamp < $3)
+            ORDER BY timestamp, correlation_id
+            LIMIT $4
+            "#,
+        )
+        .bind(query.epic.as_deref())
+        .bind(query.from)
+        .bind(query.to)
+        .bind(limit(query))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(raw_rows(rows))
+    }
+
+    async fn put_deals(&self, deals: &[DealRecord]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for deal in deals {
+            let inserted: Option<bool> = sqlx::query_scalar(
+                r#"
+                INSERT INTO ig_deals (deal_reference, correlation_id, timestamp, epic, raw)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (deal_reference) DO UPDATE SET
+                    correlation_id = EXCLUDED.correlation_id,
+                    timestamp = EXCLUDED.timestamp,
+                    epic = EXCLUDED.epic,
+                    raw = EXCLUDED.raw
+                WHERE ig_deals.raw IS DISTINCT FROM EXCLUDED.raw
+                RETURNING (xmax = 0)
+                "#,
+            )
+            .bind(&deal.deal_reference)
+            .bind(&deal.correlation_id)
+            .bind(deal.timestamp)
+            .bind(deal.epic.as_deref())
+            .bind(Json(deal))
+            .fetch_optional(&mut *tx)
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_deal(&self, deal_reference: &str) -> Result<Option<DealRecord>, AppError> {
+        let rows = sqlx::query_scalar("SELECT raw FROM ig_deals WHERE deal_reference = $1")
+            .bind(deal_reference)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(raw_rows::<DealRecord>(rows).pop())
+    }
+
+    async fn query_deals(&self, query: &StoreQuery) -> Result<Vec<DealRecord>, AppError> {
+        let rows = sqlx::query_scalar(
+            r#"
+            SELECT raw FROM ig_deals
+            WHERE ($1::TEXT IS NULL OR epic = $1)
+                AND ($2::TIMESTAMPTZ IS NULL OR timestamp >= $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR timestamp < $3)
+            ORDER BY timestamp, deal_reference
+            LIMIT $4
+            "#,
+        )
+        .bind(query.epic.as_deref())
+        .bind(query.from)
+        .bind(query.to)
+        .bind(limit(query))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(raw_rows(rows))
+    }
+
+    async fn put_instruments(&self, entries: &[CatalogEntry]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for entry in entries {
+            let inserted: Option<bool> = sqlx::query_scalar(
+                r#"
+                INSERT INTO ig_instruments (epic, instrument_type, currency, raw)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (epic) DO UPDATE SET
+                    instrument_type = EXCLUDED.instrument_type,
+                    currency = EXCLUDED.currency,
+                    raw = EXCLUDED.raw
+                WHERE ig_instruments.raw IS DISTINCT FROM EXCLUDED.raw
+                RETURNING (xmax = 0)
+                "#,
+            )
+            .bind(&entry.epic)
+            .bind(instrument_type_name(entry.instrument_type)?)
+            .bind(entry.currency.as_deref())
+            .bind(Json(entry))
+            .fetch_optional(&mut *tx)
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_instrument(&self, epic: &str) -> Result<Option<CatalogEntry>, AppError> {
+        let rows = sqlx::query_scalar("SELECT raw FROM ig_instruments WHERE epic = $1")
+            .bind(epic)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(raw_rows::<CatalogEntry>(rows).pop())
+    }
+
+    async fn search_instruments(
+        &self,
+        query: &CatalogQuery,
+    ) -> Result<Vec<CatalogEntry>, AppError> {
+        let instrument_type = query
+            .instrument_type
+            .map(instrument_type_name)
+            .transpose()?;
+        let rows = sqlx::query_scalar(
+            r#"
+            SELECT raw FROM ig_instruments
+            WHERE ($1::TEXT IS NULL OR instrument_type = $1)
+                AND ($2::TEXT IS NULL OR currency = $2)
+            "#,
+        )
+        .bind(instrument_type)
+        .bind(query.currency.as_deref())
+        .fetch_all(&self.pool)
+        .await?;
+        // Words are matched here, the same way for every backend
+        Ok(query.select(raw_rows::<CatalogEntry>(rows)))
+    }
+}
@@ -0,0 +1,246 @@
+use crate::application::models::market::Resolution;
+use crate::error::AppError;
+use crate::storage::Store;
+use crate::storage::store::{Candle, StoreQuery, Tick};
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+
+/// Number of records read from the store at once by a price stream
+pub const SERIES_PAGE_SIZE: usize = 10_000;
+
+/// Default silence after which a tick stream reports a gap, in seconds
+pub const DEFAULT_TICK_GAP_SECONDS: i64 = 60;
+
+/// Range of a price stream without stored data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceGap {
+    /// Start of the first missing candle, or time of the last tick before the gap
+    pub start: DateTime<Utc>,
+    /// Time of the next record, or the end of the range when no record follows
+    pub end: DateTime<Utc>,
+    /// Number of candles missing; `None` for ticks
+    pub missing: Option<u64>,
+}
+
+impl PriceGap {
+    /// Returns the length of the gap
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// Event of a price stream: a stored record, or a gap before the next one
+///
+/// Markets closed overnight or on weekends give gaps too; compare them with the
+/// trading hours of the market to tell missing data from closed sessions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PriceEvent<T> {
+    /// Stored candle or tick
+    Price(T),
+    /// No data stored over a range
+    Gap(PriceGap),
+}
+
+impl<T> PriceEvent<T> {
+    /// Returns the record, or `None` for a gap
+    pub fn price(self) -> Option<T> {
+        match self {
+            PriceEvent::Price(price) => Some(price),
+            PriceEvent::Gap(_) => None,
+        }
+    }
+
+    /// Returns the gap, or `None` for a record
+    pub fn gap(&self) -> Option<&PriceGap> {
+        match self {
+            PriceEvent::Price(_) => None,
+            PriceEvent::Gap(gap) => Some(gap),
+        }
+    }
+}
+
+/// Returns the number of periods of `resolution` starting within `[start, end)`
+fn periods_between(resolution: Resolution, start: DateTime<Utc>, end: DateTime<Utc>) -> u64 {
+    if end <= start {
+        return 0;
+    }
+    match resolution {
+        // Months have different lengths
+        Resolution::Month => {
+            let mut count = 0;
+            let mut period = start;
+            while period < end {
+                count += 1;
+                period = resolution.next_boundary(period);
+            }
+            count
+        }
+        _ => {
+            let step = resolution.duration().num_microseconds().unwrap_or(1).max(1) as u64;
+            let elapsed = (end - start).num_microseconds().unwrap_or(i64::MAX) as u64;
+            elapsed.div_ceil(step)
+        }
+    }
+}
+
+/// Rule telling where data is missing
+#[derive(Debug, Clone, Copy)]
+enum GapRule {
+    /// A candle is expected at every period of the resolution
+    Periods(Resolution),
+    /// Ticks are expected at most this long apart
+    Silence(Duration),
+}
+
+/// Finds the gaps of a stream, record after record
+#[derive(Debug, Clone, Copy)]
+struct GapTracker {
+    rule: GapRule,
+    /// Start of the next candle expected, or time of the last tick
+    next: DateTime<Utc>,
+    to: DateTime<Utc>,
+}
+
+impl GapTracker {
+    fn new(rule: GapRule, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        let next = match rule {
+            // Only candles starting within the range are read
+            GapRule::Periods(resolution) if resolution.align(from) != from => {
+                resolution.next_boundary(from)
+            }
+            _ => from,
+        };
+        Self { rule, next, to }
+    }
+
+    /// Returns the gap before a record at `time`, and moves past it
+    fn advance(&mut self, time: DateTime<Utc>) -> Option<PriceGap> {
+        let gap = self.gap_until(time);
+        self.next = match self.rule {
+            GapRule::Periods(resolution) => resolution.next_boundary(time),
+            GapRule::Silence(_) => time,
+        };
+        gap
+    }
+
+    /// Returns the gap between the last record and `end`
+    fn gap_until(&self, end: DateTime<Utc>) -> Option<PriceGap> {
+        match self.rule {
+            GapRule::Periods(resolution) => {
+                let missing = periods_between(resolution, self.next, end);
+                (missing > 0).then_some(PriceGap {
+                    start: self.next,
+                    end,
+                    missing: Some(missing),
+                })
+            }
+            GapRule::Silence(max_silence) => (end - self.next > max_silence).then_some(PriceGap {
+                start: self.next,
+                end,
+                missing: None,
+            }),
+        }
+    }
+
+    /// Returns the events of a page of records, followed by the trailing gap if it is
+    /// the last page
+    fn events<T>(
+        &mut self,
+        records: Vec<T>,
+        time: impl Fn(&T) -> DateTime<Utc>,
+        last_page: bool,
+    ) -> Vec<PriceEvent<T>> {
+        let mut events = Vec::with_capacity(records.len() + 1);
+        for record in records {
+            if let Some(gap) = self.advance(time(&record)) {
+                events.push(PriceEvent::Gap(gap));
+            }
+            events.push(PriceEvent::Price(record));
+        }
+        if last_page && let Some(gap) = self.gap_until(self.to) {
+            events.push(PriceEvent::Gap(gap));
+        }
+        events
+    }
+}
+
+/// Streams the candles of a market within `[from, to)` with the gaps between them
+///
+/// See [`Store::candles`].
+pub fn candle_stream<'a, S: Store + ?Sized>(
+    store: &'a S,
+    epic: &'a str,
+    resolution: Resolution,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> BoxStream<'a, Result<PriceEvent<Candle>, AppError>> {
+    let tracker = GapTracker::new(GapRule::Periods(resolution), from, to);
+    stream::try_unfold(
+        (Some(from), tracker),
+        move |(cursor, mut tracker)| async move {
+            let Some(cursor) = cursor else {
+                return Ok::<_, AppError>(None);
+            };
+            let query = StoreQuery::new()
+                .for_epic(epic)
+                .between(cursor, to)
+                .with_limit(SERIES_PAGE_SIZE);
+            let candles = store.query_candles(resolution, &query).await?;
+            let next = next_cursor(&candles, |candle| candle.timestamp);
+            let events = tracker.events(candles, |candle| candle.timestamp, next.is_none());
+            Ok(Some((
+                stream::iter(events.into_iter().map(Ok)),
+                (next, tracker),
+            )))
+        },
+    )
+    .try_flatten()
+    .boxed()
+}
+
+/// Streams the ticks of a market within `[from, to)`, reporting a gap wherever no
+/// tick was stored for longer than `max_silence`
+///
+/// See [`Store::ticks`].
+pub fn tick_stream<'a, S: Store + ?Sized>(
+    store: &'a S,
+    epic: &'a str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    max_silence: Duration,
+) -> BoxStream<'a, Result<PriceEvent<Tick>, AppError>> {
+    let tracker = GapTracker::new(GapRule::Silence(max_silence), from, to);
+    stream::try_unfold(
+        (Some(from), tracker),
+        move |(cursor, mut tracker)| async move {
+            let Some(cursor) = cursor else {
+                return Ok::<_, AppError>(None);
+            };
+            let query = StoreQuery::new()
+                .for_epic(epic)
+                .between(cursor, to)
+                .with_limit(SERIES_PAGE_SIZE);
+            let ticks = store.query_ticks(&query).await?;
+            let next = next_cursor(&ticks, |tick| tick.timestamp);
+            let events = tracker.events(ticks, |tick| tick.timestamp, next.is_none());
+            Ok(Some((
+                stream::iter(events.into_iter().map(Ok)),
+                (next, tracker),
+            )))
+        },
+    )
+    .try_flatten()
+    .boxed()
+}
+
+/// Returns the start of the next page, `None` after a partial page
+///
+/// Records of a market have distinct times, stored to the microsecond, so the next
+/// page starts one microsecond after the last record.
+fn next_cursor<T>(page: &[T], time: impl Fn(&T) -> DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if page.len() < SERIES_PAGE_SIZE {
+        return None;
+    }
+    page.last()
+        .map(|record| time(record) + Duration::microseconds(1))
+}
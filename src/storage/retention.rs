@@ -0,0 +1,112 @@
+use crate::application::models::market::Resolution;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+
+/// How long one kind of market data is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionRule {
+    /// Age after which records are pruned
+    pub max_age: Duration,
+    /// Resolution of the candles the records are downsampled to before being pruned
+    pub compact_to: Option<Resolution>,
+}
+
+impl RetentionRule {
+    /// Returns the time before which records are pruned at `now`
+    ///
+    /// When the records are compacted, the cutoff is moved back to the start of the
+    /// period of the target resolution containing it, so only whole periods are
+    /// downsampled.
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let cutoff = now - self.max_age;
+        match self.compact_to {
+            Some(resolution) => resolution.align(cutoff),
+            None => cutoff,
+        }
+    }
+}
+
+/// Retention of the ticks and candles of a store
+///
+/// Data without a rule is kept forever, so a policy keeping raw ticks for 30 days and
+/// one minute candles forever is:
+///
+/// ```
+/// use chrono::Duration;
+/// use ig_client::application::models::market::Resolution;
+/// use ig_client::storage::retention::RetentionPolicy;
+///
+/// let policy = RetentionPolicy::new().compact_ticks(Duration::days(30), Resolution::Minute);
+/// assert!(policy.candles.is_empty());
+/// ```
+///
+/// See [`RetentionCompactor`](crate::application::services::retention_compactor::RetentionCompactor)
+/// for the task applying it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionPolicy {
+    /// Retention of the ticks
+    pub ticks: Option<RetentionRule>,
+    /// Retention of the candles, per resolution
+    pub candles: BTreeMap<Resolution, RetentionRule>,
+}
+
+impl RetentionPolicy {
+    /// Creates a policy keeping everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prunes ticks older than `max_age`
+    pub fn keep_ticks(mut self, max_age: Duration) -> Self {
+        self.ticks = Some(RetentionRule {
+            max_age,
+            compact_to: None,
+        });
+        self
+    }
+
+    /// Downsamples ticks older than `max_age` to candles at `resolution`, then prunes
+    /// them
+    pub fn compact_ticks(mut self, max_age: Duration, resolution: Resolution) -> Self {
+        self.ticks = Some(RetentionRule {
+            max_age,
+            compact_to: Some(resolution),
+        });
+        self
+    }
+
+    /// Prunes candles at `resolution` older than `max_age`
+    pub fn keep_candles(mut self, resolution: Resolution, max_age: Duration) -> Self {
+        self.candles.insert(
+            resolution,
+            RetentionRule {
+                max_age,
+                compact_to: None,
+            },
+        );
+        self
+    }
+
+    /// Downsamples candles at `resolution` older than `max_age` to `target`, then
+    /// prunes them
+    pub fn compact_candles(
+        mut self,
+        resolution: Resolution,
+        max_age: Duration,
+        target: Resolution,
+    ) -> Self {
+        self.candles.insert(
+            resolution,
+            RetentionRule {
+                max_age,
+                compact_to: Some(target),
+            },
+        );
+        self
+    }
+
+    /// Returns true if the policy keeps everything
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_none() && self.candles.is_empty()
+    }
+}
@@ -0,0 +1,450 @@
+use crate::application::models::account::AccountTransaction;
+use crate::application::models::market::Resolution;
+use crate::error::AppError;
+use crate::storage::Store;
+use crate::storage::instrument_catalog::{CatalogEntry, CatalogQuery};
+use crate::storage::order_journal::{DealRecord, JournalEntry};
+use crate::storage::store::{Candle, StoreQuery, Tick, WriteReport};
+use crate::storage::transaction_store::{transaction_date, transaction_key};
+use crate::utils::atomic_file::AtomicFile;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::fs::File;
+use std::future::Future;
+use std::hash::Hash;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Format named in the header of a snapshot
+pub const SNAPSHOT_FORMAT: &str = "ig-client-store-snapshot";
+
+/// Version of the snapshot format written by this crate
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Number of records of a table written to the store at once on import
+pub const SNAPSHOT_IMPORT_BATCH_SIZE: usize = 1_000;
+
+/// Number of records of a table read from the store at once on export
+pub const SNAPSHOT_PAGE_SIZE: usize = 10_000;
+
+/// Data of a store selected for a snapshot
+///
+/// Candles, ticks, orders, deals and instruments are selected by EPIC, every market
+/// when no EPIC is given; candles, ticks, orders and deals by time too. Transactions
+/// carry no EPIC, so they are only exported with every market.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotSelection {
+    /// Markets exported, every market when empty
+    pub epics: Vec<String>,
+    /// Start of the range exported, inclusive
+    pub from: Option<DateTime<Utc>>,
+    /// End of the range exported, exclusive
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl SnapshotSelection {
+    /// Creates a selection of every record
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a market to the selection
+    pub fn for_epic(mut self, epic: &str) -> Self {
+        self.epics.push(epic.to_string());
+        self
+    }
+
+    /// Adds markets to the selection
+    pub fn with_epics<I, E>(mut self, epics: I) -> Self
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<String>,
+    {
+        self.epics.extend(epics.into_iter().map(Into::into));
+        self
+    }
+
+    /// Selects the records within `[from, to)`
+    pub fn between(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self.to = Some(to);
+        self
+    }
+
+    /// Returns the store queries of the selection, one per market
+    fn queries(&self) -> Vec<StoreQuery> {
+        let query = StoreQuery {
+            epic: None,
+            from: self.from,
+            to: self.to,
+            limit: None,
+        };
+        if self.epics.is_empty() {
+            return vec![query];
+        }
+        let epics: BTreeSet<&String> = self.epics.iter().collect();
+        epics
+            .into_iter()
+            .map(|epic| StoreQuery {
+                epic: Some(epic.clone()),
+                ..query.clone()
+            })
+            .collect()
+    }
+
+    /// Returns true if a record at `timestamp` is within the range
+    fn contains(&self, timestamp: DateTime<Utc>) -> bool {
+        self.from.is_none_or(|from| timestamp >= from) && self.to.is_none_or(|to| timestamp < to)
+    }
+}
+
+/// First line of a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotHeader {
+    /// Always [`SNAPSHOT_FORMAT`]
+    pub format: String,
+    /// Version of the format
+    pub version: u32,
+    /// Time of the export
+    pub created_at: DateTime<Utc>,
+    /// Data exported
+    pub selection: SnapshotSelection,
+}
+
+/// Number of records of each table in a snapshot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SnapshotCounts {
+    /// Candles, at every resolution
+    pub candles: usize,
+    /// Ticks
+    pub ticks: usize,
+    /// Account transactions
+    pub transactions: usize,
+    /// Order journal entries
+    pub orders: usize,
+    /// Deals
+    pub deals: usize,
+    /// Instrument catalog entries
+    pub instruments: usize,
+}
+
+impl SnapshotCounts {
+    /// Returns the number of records of every table
+    pub fn total(&self) -> usize {
+        self.candles + self.ticks + self.transactions + self.orders + self.deals + self.instruments
+    }
+}
+
+/// Line of a snapshot after the header, written by [`SnapshotWriter::write`]
+#[derive(Debug, Deserialize)]
+#[serde(tag = "table", content = "record", rename_all = "camelCase")]
+enum SnapshotRecord {
+    Candle(Candle),
+    Tick(Tick),
+    Transaction(AccountTransaction),
+    Order(JournalEntry),
+    Deal(DealRecord),
+    Instrument(CatalogEntry),
+}
+
+/// Writes the lines of a snapshot
+struct SnapshotWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    fn header(&mut self, header: &SnapshotHeader) -> Result<(), AppError> {
+        serde_json::to_writer(&mut self.writer, header)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Writes `records` of `table`, a variant name of [`SnapshotRecord`]
+    fn write<T: Serialize>(&mut self, table: &str, records: &[T]) -> Result<usize, AppError> {
+        #[derive(Serialize)]
+        struct Line<'a, T> {
+            table: &'a str,
+            record: &'a T,
+        }
+        for record in records {
+            serde_json::to_writer(&mut self.writer, &Line { table, record })?;
+            self.writer.write_all(b"\n")?;
+        }
+        Ok(records.len())
+    }
+}
+
+/// Exports the records of `store` selected by `selection` to a snapshot at `path`
+///
+/// See [`Store::export_snapshot`].
+pub async fn export_snapshot<S: Store + ?Sized>(
+    store: &S,
+    path: &Path,
+    selection: &SnapshotSelection,
+) -> Result<SnapshotCounts, AppError> {
+    if let Some(directory) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(directory)?;
+    }
+    let mut file = AtomicFile::create(path)?;
+    let mut writer = SnapshotWriter {
+        writer: file.writer(),
+    };
+    let counts = write_snapshot(store, &mut writer, selection).await?;
+    file.commit()?;
+    Ok(counts)
+}
+
+/// Writes the records selected by `query`, read from the store page after page
+///
+/// `position` gives the time of a record, which the store orders records by, and a
+/// key telling apart the records of the same time. Records of several markets can
+/// share a time, so each page starts at the time of the last record of the previous
+/// one, skipping the records of that time already written, and grows by their number
+/// so that it always brings new records.
+async fn write_pages<T, K, W, F, Fut>(
+    writer: &mut SnapshotWriter<W>,
+    table: &str,
+    query: &StoreQuery,
+    position: impl Fn(&T) -> Result<(DateTime<Utc>, K), AppError>,
+    fetch: F,
+) -> Result<usize, AppError>
+where
+    T: Serialize,
+    K: Eq + Hash,
+    W: Write,
+    F: Fn(StoreQuery) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, AppError>>,
+{
+    let mut written = 0;
+    let mut cursor = query.from;
+    let mut seen: HashSet<K> = HashSet::new();
+    loop {
+        let limit = SNAPSHOT_PAGE_SIZE + seen.len();
+        let page_query = StoreQuery {
+            from: cursor,
+            limit: Some(limit),
+            ..query.clone()
+        };
+        let page = fetch(page_query).await?;
+        let full = page.len() >= limit;
+        let mut last = None;
+        let mut fresh = Vec::with_capacity(page.len());
+        let mut at_last = HashSet::new();
+        for record in page {
+            let (time, key) = position(&record)?;
+            if Some(time) == cursor && seen.contains(&key) {
+                continue;
+            }
+            if last != Some(time) {
+                at_last.clear();
+                last = Some(time);
+            }
+            at_last.insert(key);
+            fresh.push(record);
+        }
+        written += writer.write(table, &fresh)?;
+        let Some(last) = last.filter(|_| full) else {
+            return Ok(written);
+        };
+        if cursor == Some(last) {
+            seen.extend(at_last);
+        } else {
+            seen = at_last;
+        }
+        cursor = Some(last);
+    }
+}
+
+async fn write_snapshot<S: Store + ?Sized, W: Write>(
+    store: &S,
+    writer: &mut SnapshotWriter<W>,
+    selection: &SnapshotSelection,
+) -> Result<SnapshotCounts, AppError> {
+    writer.header(&SnapshotHeader {
+        format: SNAPSHOT_FORMAT.to_string(),
+        version: SNAPSHOT_VERSION,
+        created_at: Utc::now(),
+        selection: selection.clone(),
+    })?;
+    let mut counts = SnapshotCounts::default();
+    let queries = selection.queries();
+    for query in &queries {
+        for resolution in Resolution::ALL {
+            counts.candles += write_pages(
+                writer,
+                "candle",
+                query,
+                |candle: &Candle| Ok((candle.timestamp, candle.epic.clone())),
+                |page| async move { store.query_candles(resolution, &page).await },
+            )
+            .await?;
+        }
+        counts.ticks += write_pages(
+            writer,
+            "tick",
+            query,
+            |tick: &Tick| Ok((tick.timestamp, tick.epic.clone())),
+            |page| async move { store.query_ticks(&page).await },
+        )
+        .await?;
+
+        // Entries without an EPIC, such as confirmations, follow the orders they belong to
+        let correlation_ids: BTreeSet<String> = store
+            .query_orders(query)
+            .await?
+            .into_iter()
+            .map(|entry| entry.correlation_id)
+            .collect();
+        for correlation_id in correlation_ids {
+            let entries: Vec<JournalEntry> = store
+                .get_orders(&correlation_id)
+                .await?
+                .into_iter()
+                .filter(|entry| selection.contains(entry.timestamp))
+                .collect();
+            counts.orders += writer.write("order", &entries)?;
+        }
+        counts.deals += write_pages(
+            writer,
+            "deal",
+            query,
+            |deal: &DealRecord| Ok((deal.timestamp, deal.deal_reference.clone())),
+            |page| async move { store.query_deals(&page).await },
+        )
+        .await?;
+    }
+
+    if selection.epics.is_empty() {
+        counts.transactions += write_pages(
+            writer,
+            "transaction",
+            &queries[0],
+            |transaction: &AccountTransaction| {
+                Ok((transaction_date(transaction)?, transaction_key(transaction)))
+            },
+            |page| async move { store.query_transactions(&page).await },
+        )
+        .await?;
+        counts.instruments += writer.write(
+            "instrument",
+            &store.search_instruments(&CatalogQuery::new()).await?,
+        )?;
+    } else {
+        for query in &queries {
+            let epic = query.epic.as_deref().unwrap_or_default();
+            if let Some(entry) = store.get_instrument(epic).await? {
+                counts.instruments += writer.write("instrument", &[entry])?;
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// Records of a snapshot waiting to be written to a store
+#[derive(Default)]
+struct ImportBatches {
+    candles: Vec<Candle>,
+    ticks: Vec<Tick>,
+    transactions: Vec<AccountTransaction>,
+    orders: Vec<JournalEntry>,
+    deals: Vec<DealRecord>,
+    instruments: Vec<CatalogEntry>,
+}
+
+impl ImportBatches {
+    /// Adds a record, returning true once a table holds a full batch
+    fn push(&mut self, record: SnapshotRecord) -> bool {
+        let len = match record {
+            SnapshotRecord::Candle(candle) => push(&mut self.candles, candle),
+            SnapshotRecord::Tick(tick) => push(&mut self.ticks, tick),
+            SnapshotRecord::Transaction(transaction) => push(&mut self.transactions, transaction),
+            SnapshotRecord::Order(entry) => push(&mut self.orders, entry),
+            SnapshotRecord::Deal(deal) => push(&mut self.deals, deal),
+            SnapshotRecord::Instrument(entry) => push(&mut self.instruments, entry),
+        };
+        len >= SNAPSHOT_IMPORT_BATCH_SIZE
+    }
+
+    /// Writes every waiting record to `store`
+    async fn flush<S: Store + ?Sized>(&mut self, store: &S) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        report += store
+            .put_candles(&std::mem::take(&mut self.candles))
+            .await?;
+        report += store.put_ticks(&std::mem::take(&mut self.ticks)).await?;
+        report += store
+            .put_transactions(&std::mem::take(&mut self.transactions))
+            .await?;
+        report += store.put_orders(&std::mem::take(&mut self.orders)).await?;
+        report += store.put_deals(&std::mem::take(&mut self.deals)).await?;
+        report += store
+            .put_instruments(&std::mem::take(&mut self.instruments))
+            .await?;
+        Ok(report)
+    }
+}
+
+fn push<T>(records: &mut Vec<T>, record: T) -> usize {
+    records.push(record);
+    records.len()
+}
+
+/// Reads the header of the snapshot at `path`
+///
+/// # Returns
+/// The header, or `AppError::InvalidInput` if the file is not a snapshot or was
+/// written by a later version of the format
+pub fn read_snapshot_header(path: &Path) -> Result<SnapshotHeader, AppError> {
+    let mut line = String::new();
+    BufReader::new(File::open(path)?).read_line(&mut line)?;
+    parse_header(&line, path)
+}
+
+fn parse_header(line: &str, path: &Path) -> Result<SnapshotHeader, AppError> {
+    let header: SnapshotHeader = serde_json::from_str(line).map_err(|_| {
+        AppError::InvalidInput(format!("{} is not a store snapshot", path.display()))
+    })?;
+    if header.format != SNAPSHOT_FORMAT {
+        return Err(AppError::InvalidInput(format!(
+            "{} is not a store snapshot",
+            path.display()
+        )));
+    }
+    if header.version > SNAPSHOT_VERSION {
+        return Err(AppError::InvalidInput(format!(
+            "{} has snapshot version {}, only {} is supported",
+            path.display(),
+            header.version,
+            SNAPSHOT_VERSION
+        )));
+    }
+    Ok(header)
+}
+
+/// Imports the snapshot at `path` into `store`
+///
+/// See [`Store::import_snapshot`].
+pub async fn import_snapshot<S: Store + ?Sized>(
+    store: &S,
+    path: &Path,
+) -> Result<WriteReport, AppError> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header = lines.next().transpose()?.unwrap_or_default();
+    parse_header(&header, path)?;
+    let mut batches = ImportBatches::default();
+    let mut report = WriteReport::default();
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if batches.push(serde_json::from_str(&line)?) {
+            report += batches.flush(store).await?;
+        }
+    }
+    report += batches.flush(store).await?;
+    Ok(report)
+}
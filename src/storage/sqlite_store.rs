@@ -0,0 +1,754 @@
+use crate::application::models::account::AccountTransaction;
+use crate::application::models::market::Resolution;
+use crate::error::AppError;
+use crate::storage::instrument_catalog::{CatalogEntry, CatalogQuery, instrument_type_name};
+use crate::storage::order_journal::{DealRecord, JournalEntry};
+use crate::storage::store::{Candle, Store, StoreQuery, Tick, WriteReport};
+use crate::storage::transaction_store::transaction_date;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::query::Query;
+use sqlx::sqlite::{
+    SqliteArguments, SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePool,
+    SqlitePoolOptions, SqliteRow,
+};
+use sqlx::{Row, Sqlite};
+use std::path::Path;
+use tracing::debug;
+
+/// Schema migrations of [`SqliteStore`]: version, description and SQL
+///
+/// The version applied is kept in the `user_version` of the database; new migrations
+/// are appended with the next version and existing ones never change. The first one
+/// creates the tables if missing, so databases created before versioning are adopted.
+const SQLITE_STORE_MIGRATIONS: [(i64, &str, &str); 2] = [
+    (
+        1,
+        "create tables",
+        r#"
+CREATE TABLE IF NOT EXISTS candles (
+    epic TEXT NOT NULL,
+    resolution TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    open REAL NOT NULL,
+    high REAL NOT NULL,
+    low REAL NOT NULL,
+    close REAL NOT NULL,
+    volume INTEGER,
+    PRIMARY KEY (epic, resolution, timestamp)
+);
+CREATE TABLE IF NOT EXISTS ticks (
+    epic TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    bid REAL,
+    offer REAL,
+    PRIMARY KEY (epic, timestamp)
+);
+CREATE TABLE IF NOT EXISTS transactions (
+    reference TEXT NOT NULL,
+    date_utc TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    instrument_name TEXT NOT NULL,
+    raw TEXT NOT NULL,
+    PRIMARY KEY (reference, date_utc)
+);
+CREATE INDEX IF NOT EXISTS transactions_timestamp ON transactions (timestamp);
+CREATE TABLE IF NOT EXISTS orders (
+    correlation_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    epic TEXT,
+    deal_reference TEXT,
+    raw TEXT NOT NULL,
+    PRIMARY KEY (correlation_id, timestamp, kind)
+);
+CREATE INDEX IF NOT EXISTS orders_timestamp ON orders (timestamp);
+CREATE INDEX IF NOT EXISTS orders_deal_reference ON orders (deal_reference);
+CREATE TABLE IF NOT EXISTS deals (
+    deal_reference TEXT PRIMARY KEY,
+    correlation_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    epic TEXT,
+    raw TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS deals_epic_timestamp ON deals (epic, timestamp);
+CREATE TABLE IF NOT EXISTS instruments (
+    epic TEXT PRIMARY KEY,
+    instrument_type TEXT NOT NULL,
+    currency TEXT,
+    raw TEXT NOT NULL
+);
+"#,
+    ),
+    (
+        2,
+        "key transactions on type, instrument and amount",
+        // SQLite cannot change a primary key, so the table is copied into a new one
+        r#"
+CREATE TABLE transactions_keyed (
+    reference TEXT NOT NULL,
+    date_utc TEXT NOT NULL,
+    transaction_type TEXT NOT NULL,
+    instrument_name TEXT NOT NULL,
+    amount TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    raw TEXT NOT NULL,
+    PRIMARY KEY (reference, date_utc, transaction_type, instrument_name, amount)
+);
+INSERT OR REPLACE INTO transactions_keyed
+    SELECT reference, date_utc, json_extract(raw, '$.transactionType'), instrument_name,
+        json_extract(raw, '$.profitAndLoss'), timestamp, raw
+    FROM transactions;
+DROP TABLE transactions;
+ALTER TABLE transactions_keyed RENAME TO transactions;
+CREATE INDEX IF NOT EXISTS transactions_timestamp ON transactions (timestamp);
+"#,
+    ),
+];
+
+/// Latest schema version of [`SqliteStore`]
+pub const SQLITE_STORE_SCHEMA_VERSION: i64 =
+    SQLITE_STORE_MIGRATIONS[SQLITE_STORE_MIGRATIONS.len() - 1].0;
+
+/// Times are stored as microseconds since the epoch, so they sort as integers
+fn to_micros(timestamp: DateTime<Utc>) -> i64 {
+    timestamp.timestamp_micros()
+}
+
+fn from_micros(micros: i64) -> Result<DateTime<Utc>, AppError> {
+    DateTime::from_timestamp_micros(micros)
+        .ok_or_else(|| AppError::Deserialization(format!("invalid stored time: {micros}")))
+}
+
+/// SQLite binds no limit as a negative one
+fn limit(query: &StoreQuery) -> i64 {
+    query.limit.map_or(-1, |limit| limit as i64)
+}
+
+fn candle_from_row(row: &SqliteRow) -> Result<Candle, AppError> {
+    let resolution: String = row.try_get("resolution")?;
+    Ok(Candle {
+        epic: row.try_get("epic")?,
+        resolution: resolution.parse()?,
+        timestamp: from_micros(row.try_get("timestamp")?)?,
+        open: row.try_get("open")?,
+        high: row.try_get("high")?,
+        low: row.try_get("low")?,
+        close: row.try_get("close")?,
+        volume: row.try_get("volume")?,
+    })
+}
+
+fn tick_from_row(row: &SqliteRow) -> Result<Tick, AppError> {
+    Ok(Tick {
+        epic: row.try_get("epic")?,
+        timestamp: from_micros(row.try_get("timestamp")?)?,
+        bid: row.try_get("bid")?,
+        offer: row.try_get("offer")?,
+    })
+}
+
+/// Deserializes the `raw` JSON column of each row
+fn raw_rows<T: serde::de::DeserializeOwned>(rows: &[SqliteRow]) -> Result<Vec<T>, AppError> {
+    rows.iter()
+        .map(|row| {
+            let raw: String = row.try_get("raw")?;
+            Ok(serde_json::from_str(&raw)?)
+        })
+        .collect()
+}
+
+type SqliteQuery<'q> = Query<'q, Sqlite, SqliteArguments<'q>>;
+
+/// Writes a record with `insert`, which leaves a stored key as is, then with `update`
+/// when the key is stored, which only changes a row holding other values
+///
+/// SQLite cannot tell an upsert that inserted from one that updated, so the rows each
+/// statement affects classify the record instead of reading it first. Both statements
+/// take the parameters bound by `bind`.
+///
+/// # Returns
+/// `None` if the stored record was identical, or whether the key was new
+async fn upsert<'q>(
+    connection: &mut SqliteConnection,
+    insert: &'q str,
+    update: &'q str,
+    bind: impl Fn(SqliteQuery<'q>) -> SqliteQuery<'q>,
+) -> Result<Option<bool>, AppError> {
+    let inserted = bind(sqlx::query(insert))
+        .execute(&mut *connection)
+        .await?
+        .rows_affected();
+    if inserted > 0 {
+        return Ok(Some(true));
+    }
+    let updated = bind(sqlx::query(update))
+        .execute(&mut *connection)
+        .await?
+        .rows_affected();
+    Ok((updated > 0).then_some(false))
+}
+
+/// [`Store`] in a SQLite database file
+///
+/// The schema is created or upgraded when the store is opened. Transactions, order
+/// journal entries and deals are kept as JSON next to the columns they are queried on.
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it if missing
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+        Self::with_pool(pool).await
+    }
+
+    /// Opens a database held in memory, lost when the store is dropped
+    pub async fn in_memory() -> Result<Self, AppError> {
+        // Every connection to `:memory:` opens its own database, so keep a single one
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await?;
+        Self::with_pool(pool).await
+    }
+
+    /// Creates a store on an existing pool, applying the pending migrations
+    ///
+    /// # Returns
+    /// The store, or `AppError::IncompatibleSchema` if the database was written by a
+    /// later version of the crate
+    pub async fn with_pool(pool: SqlitePool) -> Result<Self, AppError> {
+        let store = Self { pool };
+        store.migrate().await?;
+        Ok(store)
+    }
+
+    /// Returns the connection pool of the store
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// Applies the migrations not applied yet, each in its own database transaction
+    ///
+    /// Called when the store is opened.
+    ///
+    /// # Returns
+    /// The number of migrations applied, or `AppError::IncompatibleSchema` if the
+    /// database is at a later version than [`SQLITE_STORE_SCHEMA_VERSION`]
+    pub async fn migrate(&self) -> Result<usize, AppError> {
+        if let Some(found) = self.schema_version().await?
+            && found > SQLITE_STORE_SCHEMA_VERSION
+        {
+            return Err(AppError::IncompatibleSchema {
+                found,
+                supported: SQLITE_STORE_SCHEMA_VERSION,
+            });
+        }
+        let mut applied = 0;
+        for (version, description, sql) in SQLITE_STORE_MIGRATIONS {
+            let mut tx = self.pool.begin().await?;
+            let current: i64 = sqlx::query_scalar("PRAGMA user_version")
+                .fetch_one(&mut *tx)
+                .await?;
+            if current >= version {
+                continue;
+            }
+            sqlx::raw_sql(sql).execute(&mut *tx).await?;
+            // Pragmas take no bound parameters
+            sqlx::raw_sql(&format!("PRAGMA user_version = {version}"))
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            debug!(
+                "SQLite store migrated to version {}: {}",
+                version, description
+            );
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    /// Returns the latest migration applied, `None` before the first one
+    pub async fn schema_version(&self) -> Result<Option<i64>, AppError> {
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok((version > 0).then_some(version))
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn put_candles(&self, candles: &[Candle]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for candle in candles {
+            let inserted = upsert(
+                &mut tx,
+                r#"
+                INSERT INTO candles (epic, resolution, timestamp, open, high, low, close, volume)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                ON CONFLICT (epic, resolution, timestamp) DO NOTHING
+                "#,
+                r#"
+                UPDATE candles SET open = ?4, high = ?5, low = ?6, close = ?7, volume = ?8
+                WHERE epic = ?1 AND resolution = ?2 AND timestamp = ?3
+                    AND (open, high, low, close, volume) IS NOT (?4, ?5, ?6, ?7, ?8)
+                "#,
+                |query| {
+                    query
+                        .bind(&candle.epic)
+                        .bind(candle.resolution.as_str())
+                        .bind(to_micros(candle.timestamp))
+                        .bind(candle.open)
+                        .bind(candle.high)
+                        .bind(candle.low)
+                        .bind(candle.close)
+                        .bind(candle.volume)
+                },
+            )
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_candle(
+        &self,
+        epic: &str,
+        resolution: Resolution,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Candle>, AppError> {
+        sqlx::query("SELECT * FROM candles WHERE epic = ?1 AND resolution = ?2 AND timestamp = ?3")
+            .bind(epic)
+            .bind(resolution.as_str())
+            .bind(to_micros(timestamp))
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(candle_from_row)
+            .transpose()
+    }
+
+    async fn query_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<Vec<Candle>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM candles
+            WHERE resolution = ?1
+                AND (?2 IS NULL OR epic = ?2)
+                AND (?3 IS NULL OR timestamp >= ?3)
+                AND (?4 IS NULL OR timestamp < ?4)
+            ORDER BY timestamp, epic
+            LIMIT ?5
+            "#,
+        )
+        .bind(resolution.as_str())
+        .bind(query.epic.as_deref())
+        .bind(query.from.map(to_micros))
+        .bind(query.to.map(to_micros))
+        .bind(limit(query))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(candle_from_row).collect()
+    }
+
+    async fn delete_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<usize, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM candles
+            WHERE resolution = ?1
+                AND (?2 IS NULL OR epic = ?2)
+                AND (?3 IS NULL OR timestamp >= ?3)
+                AND (?4 IS NULL OR timestamp < ?4)
+            "#,
+        )
+        .bind(resolution.as_str())
+        .bind(query.epic.as_deref())
+        .bind(query.from.map(to_micros))
+        .bind(query.to.map(to_micros))
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn latest_candles(
+        &self,
+        epic: &str,
+        resolution: Resolution,
+        count: usize,
+    ) -> Result<Vec<Candle>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM candles
+            WHERE epic = ?1 AND resolution = ?2
+            ORDER BY timestamp DESC
+            LIMIT ?3
+            "#,
+        )
+        .bind(epic)
+        .bind(resolution.as_str())
+        .bind(count as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().rev().map(candle_from_row).collect()
+    }
+
+    async fn put_ticks(&self, ticks: &[Tick]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for tick in ticks {
+            let inserted = upsert(
+                &mut tx,
+                r#"
+                INSERT INTO ticks (epic, timestamp, bid, offer) VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (epic, timestamp) DO NOTHING
+                "#,
+                r#"
+                UPDATE ticks SET bid = ?3, offer = ?4
+                WHERE epic = ?1 AND timestamp = ?2 AND (bid, offer) IS NOT (?3, ?4)
+                "#,
+                |query| {
+                    query
+                        .bind(&tick.epic)
+                        .bind(to_micros(tick.timestamp))
+                        .bind(tick.bid)
+                        .bind(tick.offer)
+                },
+            )
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_tick(
+        &self,
+        epic: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Tick>, AppError> {
+        sqlx::query("SELECT * FROM ticks WHERE epic = ?1 AND timestamp = ?2")
+            .bind(epic)
+            .bind(to_micros(timestamp))
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(tick_from_row)
+            .transpose()
+    }
+
+    async fn query_ticks(&self, query: &StoreQuery) -> Result<Vec<Tick>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM ticks
+            WHERE (?1 IS NULL OR epic = ?1)
+                AND (?2 IS NULL OR timestamp >= ?2)
+                AND (?3 IS NULL OR timestamp < ?3)
+            ORDER BY timestamp, epic
+            LIMIT ?4
+            "#,
+        )
+        .bind(query.epic.as_deref())
+        .bind(query.from.map(to_micros))
+        .bind(query.to.map(to_micros))
+        .bind(limit(query))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(tick_from_row).collect()
+    }
+
+    async fn delete_ticks(&self, query: &StoreQuery) -> Result<usize, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM ticks
+            WHERE (?1 IS NULL OR epic = ?1)
+                AND (?2 IS NULL OR timestamp >= ?2)
+                AND (?3 IS NULL OR timestamp < ?3)
+            "#,
+        )
+        .bind(query.epic.as_deref())
+        .bind(query.from.map(to_micros))
+        .bind(query.to.map(to_micros))
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn put_transactions(
+        &self,
+        transactions: &[AccountTransaction],
+    ) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for transaction in transactions {
+            let raw = serde_json::to_string(transaction)?;
+            let timestamp = to_micros(transaction_date(transaction)?);
+            let inserted = upsert(
+                &mut tx,
+                r#"
+                INSERT INTO transactions (
+                    reference, date_utc, timestamp, instrument_name, raw, transaction_type, amount
+                )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                ON CONFLICT (reference, date_utc, transaction_type, instrument_name, amount)
+                DO NOTHING
+                "#,
+                r#"
+                UPDATE transactions SET timestamp = ?3, raw = ?5
+                WHERE reference = ?1 AND date_utc = ?2 AND transaction_type = ?6
+                    AND instrument_name = ?4 AND amount = ?7 AND raw IS NOT ?5
+                "#,
+                |query| {
+                    query
+                        .bind(&transaction.reference)
+                        .bind(&transaction.date_utc)
+                        .bind(timestamp)
+                        .bind(&transaction.instrument_name)
+                        .bind(&raw)
+                        .bind(&transaction.transaction_type)
+                        .bind(&transaction.profit_and_loss)
+                },
+            )
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_transactions(&self, reference: &str) -> Result<Vec<AccountTransaction>, AppError> {
+        let rows =
+            sqlx::query("SELECT raw FROM transactions WHERE reference = ?1 ORDER BY timestamp")
+                .bind(reference)
+                .fetch_all(&self.pool)
+                .await?;
+        raw_rows(&rows)
+    }
+
+    async fn query_transactions(
+        &self,
+        query: &StoreQuery,
+    ) -> Result<Vec<AccountTransaction>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT raw FROM transactions
+            WHERE (?1 IS NULL OR instrument_name = ?1)
+                AND (?2 IS NULL OR timestamp >= ?2)
+                AND (?3 IS NULL OR timestamp < ?3)
+            ORDER BY timestamp, reference
+            LIMIT ?4
+            "#,
+        )
+        .bind(query.epic.as_deref())
+        .bind(query.from.map(to_micros))
+        .bind(query.to.map(to_micros))
+        .bind(limit(query))
+        .fetch_all(&self.pool)
+        .await?;
+        raw_rows(&rows)
+    }
+
+    async fn put_orders(&self, entries: &[JournalEntry]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for entry in entries {
+            let kind = serde_json::to_value(entry.kind)?;
+            let raw = serde_json::to_string(entry)?;
+            let inserted = upsert(
+                &mut tx,
+                r#"
+                INSERT INTO orders (correlation_id, timestamp, kind, epic, deal_reference, raw)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                ON CONFLICT (correlation_id, timestamp, kind) DO NOTHING
+                "#,
+                r#"
+                UPDATE orders SET epic = ?4, deal_reference = ?5, raw = ?6
+                WHERE correlation_id = ?1 AND timestamp = ?2 AND kind = ?3 AND raw IS NOT ?6
+                "#,
+                |query| {
+                    query
+                        .bind(&entry.correlation_id)
+                        .bind(to_micros(entry.timestamp))
+                        .bind(kind.as_str())
+                        .bind(entry.epic.as_deref())
+                        .bind(entry.deal_reference.as_deref())
+                        .bind(&raw)
+                },
+            )
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_orders(&self, correlation_id: &str) -> Result<Vec<JournalEntry>, AppError> {
+        let rows =
+            sqlx::query("SELECT raw FROM orders WHERE correlation_id = ?1 ORDER BY timestamp")
+                .bind(correlation_id)
+                .fetch_all(&self.pool)
+                .await?;
+        raw_rows(&rows)
+    }
+
+    async fn query_orders(&self, query: &StoreQuery) -> Result<Vec<JournalEntry>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT raw FROM orders
+            WHERE (?1 IS NULL OR epic = ?1)
+                AND (?2 IS NULL OR timestamp >= ?2)
+                AND (?3 IS NULL OR timestamp < ?3)
+            ORDER BY timestamp, correlation_id
+            LIMIT ?4
+            "#,
+        )
+        .bind(query.epic.as_deref())
+        .bind(query.from.map(to_micros))
+        .bind(query.to.map(to_micros))
+        .bind(limit(query))
+        .fetch_all(&self.pool)
+        .await?;
+        raw_rows(&rows)
+    }
+
+    async fn put_deals(&self, deals: &[DealRecord]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for deal in deals {
+            let raw = serde_json::to_string(deal)?;
+            let inserted = upsert(
+                &mut tx,
+                r#"
+                INSERT INTO deals (deal_reference, correlation_id, timestamp, epic, raw)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (deal_reference) DO NOTHING
+                "#,
+                r#"
+                UPDATE deals SET correlation_id = ?2, timestamp = ?3, epic = ?4, raw = ?5
+                WHERE deal_reference = ?1 AND raw IS NOT ?5
+                "#,
+                |query| {
+                    query
+                        .bind(&deal.deal_reference)
+                        .bind(&deal.correlation_id)
+                        .bind(to_micros(deal.timestamp))
+                        .bind(deal.epic.as_deref())
+                        .bind(&raw)
+                },
+            )
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_deal(&self, deal_reference: &str) -> Result<Option<DealRecord>, AppError> {
+        let rows = sqlx::query("SELECT raw FROM deals WHERE deal_reference = ?1")
+            .bind(deal_reference)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(raw_rows::<DealRecord>(&rows)?.pop())
+    }
+
+    async fn query_deals(&self, query: &StoreQuery) -> Result<Vec<DealRecord>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT raw FROM deals
+            WHERE (?1 IS NULL OR epic = ?1)
+                AND (?2 IS NULL OR timestamp >= ?2)
+                AND (?3 IS NULL OR timestamp < ?3)
+            ORDER BY timestamp, deal_reference
+            LIMIT ?4
+            "#,
+        )
+        .bind(query.epic.as_deref())
+        .bind(query.from.map(to_micros))
+        .bind(query.to.map(to_micros))
+        .bind(limit(query))
+        .fetch_all(&self.pool)
+        .await?;
+        raw_rows(&rows)
+    }
+
+    async fn put_instruments(&self, entries: &[CatalogEntry]) -> Result<WriteReport, AppError> {
+        let mut report = WriteReport::default();
+        let mut tx = self.pool.begin().await?;
+        for entry in entries {
+            let instrument_type = instrument_type_name(entry.instrument_type)?;
+            let raw = serde_json::to_string(entry)?;
+            let inserted = upsert(
+                &mut tx,
+                r#"
+                INSERT INTO instruments (epic, instrument_type, currency, raw)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (epic) DO NOTHING
+                "#,
+                r#"
+                UPDATE instruments SET instrument_type = ?2, currency = ?3, raw = ?4
+                WHERE epic = ?1 AND raw IS NOT ?4
+                "#,
+                |query| {
+                    query
+                        .bind(&entry.epic)
+                        .bind(&instrument_type)
+                        .bind(entry.currency.as_deref())
+                        .bind(&raw)
+                },
+            )
+            .await?;
+            report.count_upsert(inserted);
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn get_instrument(&self, epic: &str) -> Result<Option<CatalogEntry>, AppError> {
+        let rows = sqlx::query("SELECT raw FROM instruments WHERE epic = ?1")
+            .bind(epic)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(raw_rows::<CatalogEntry>(&rows)?.pop())
+    }
+
+    async fn search_instruments(
+        &self,
+        query: &CatalogQuery,
+    ) -> Result<Vec<CatalogEntry>, AppError> {
+        let instrument_type = query
+            .instrument_type
+            .map(instrument_type_name)
+            .transpose()?;
+        let rows = sqlx::query(
+            r#"
+            SELECT raw FROM instruments
+            WHERE (?1 IS NULL OR instrument_type = ?1)
+                AND (?2 IS NULL OR currency = ?2)
+            "#,
+        )
+        .bind(instrument_type)
+        .bind(query.currency.as_deref())
+        .fetch_all(&self.pool)
+        .await?;
+        // Words are matched here, the same way for every backend
+        Ok(query.select(raw_rows::<CatalogEntry>(&rows)?))
+    }
+}
@@ -0,0 +1,595 @@
+use crate::application::models::account::AccountTransaction;
+use crate::application::models::market::{HistoricalPrice, Resolution};
+use crate::error::AppError;
+use crate::presentation::{ChartData, ChartScale};
+use crate::storage::instrument_catalog::{CatalogEntry, CatalogQuery};
+use crate::storage::order_journal::{DealRecord, JournalEntry, deal_records};
+use crate::storage::price_series::{
+    DEFAULT_TICK_GAP_SECONDS, PriceEvent, candle_stream, tick_stream,
+};
+use crate::storage::snapshot::{
+    SnapshotCounts, SnapshotSelection, export_snapshot, import_snapshot,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// OHLC candle of a market at a resolution
+///
+/// Prices are mid prices, or last traded prices for markets quoting them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candle {
+    /// EPIC of the market
+    pub epic: String,
+    /// Resolution of the candle
+    pub resolution: Resolution,
+    /// Start of the candle
+    pub timestamp: DateTime<Utc>,
+    /// Opening price
+    pub open: f64,
+    /// Highest price
+    pub high: f64,
+    /// Lowest price
+    pub low: f64,
+    /// Closing price
+    pub close: f64,
+    /// Volume traded, if reported
+    pub volume: Option<i64>,
+}
+
+impl Candle {
+    /// Builds the mid price candle of a price returned by a history request
+    ///
+    /// # Returns
+    /// The candle, or `None` if the time or a price of the period is missing
+    pub fn from_historical(
+        epic: &str,
+        resolution: Resolution,
+        price: &HistoricalPrice,
+    ) -> Option<Self> {
+        Some(Self {
+            epic: epic.to_string(),
+            resolution,
+            timestamp: price.timestamp()?,
+            open: price.open_price.mid()?,
+            high: price.high_price.mid()?,
+            low: price.low_price.mid()?,
+            close: price.close_price.mid()?,
+            volume: price.last_traded_volume,
+        })
+    }
+
+    /// Builds the mid price candle of a chart update, `CHART:{epic}:{scale}`
+    ///
+    /// Candles still open are returned too; storing the next update of the same
+    /// candle replaces them.
+    ///
+    /// # Returns
+    /// The candle, or `None` for tick updates and updates missing a price
+    pub fn from_chart(chart: &ChartData) -> Option<Self> {
+        let resolution = match chart.get_scale() {
+            ChartScale::Second => Resolution::Second,
+            ChartScale::OneMinute => Resolution::Minute,
+            ChartScale::FiveMinute => Resolution::Minute5,
+            ChartScale::Hour => Resolution::Hour,
+            ChartScale::Tick => return None,
+        };
+        let fields = chart.get_fields();
+        let mid = |bid: Option<f64>, offer: Option<f64>| Some((bid? + offer?) / 2.0);
+        Some(Self {
+            epic: chart.item_name().split(':').nth(1)?.to_string(),
+            resolution,
+            timestamp: DateTime::from_timestamp_millis(fields.update_time()? as i64)?,
+            open: mid(fields.bid_open(), fields.offer_open())?,
+            high: mid(fields.bid_high(), fields.offer_high())?,
+            low: mid(fields.bid_low(), fields.offer_low())?,
+            close: mid(fields.bid_close(), fields.offer_close())?,
+            volume: fields.last_traded_volume().map(|volume| volume as i64),
+        })
+    }
+}
+
+/// Aggregates candles into coarser ones, such as one minute candles into five minutes
+///
+/// Candles are grouped per market into the periods of `resolution`, see
+/// [`Resolution::align`]. A period missing some of its candles is aggregated from the
+/// ones present; its volume is the sum of the volumes reported, `None` if none is.
+///
+/// # Returns
+/// The candles ordered by time then EPIC, or `AppError::InvalidInput` if a candle is
+/// coarser than `resolution`
+pub fn downsample(candles: &[Candle], resolution: Resolution) -> Result<Vec<Candle>, AppError> {
+    if let Some(coarser) = candles
+        .iter()
+        .find(|candle| candle.resolution.duration() > resolution.duration())
+    {
+        return Err(AppError::InvalidInput(format!(
+            "cannot downsample {} candles to {}",
+            coarser.resolution.as_str(),
+            resolution.as_str()
+        )));
+    }
+    let mut sorted: Vec<&Candle> = candles.iter().collect();
+    sorted.sort_by_key(|candle| candle.timestamp);
+
+    let mut periods: BTreeMap<(DateTime<Utc>, &str), Candle> = BTreeMap::new();
+    for candle in sorted {
+        let start = resolution.align(candle.timestamp);
+        periods
+            .entry((start, &candle.epic))
+            .and_modify(|period| {
+                period.high = period.high.max(candle.high);
+                period.low = period.low.min(candle.low);
+                period.close = candle.close;
+                period.volume = match (period.volume, candle.volume) {
+                    (Some(total), Some(volume)) => Some(total + volume),
+                    (total, volume) => total.or(volume),
+                };
+            })
+            .or_insert_with(|| Candle {
+                resolution,
+                timestamp: start,
+                ..candle.clone()
+            });
+    }
+    Ok(periods.into_values().collect())
+}
+
+/// Quote of a market at a point in time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tick {
+    /// EPIC of the market
+    pub epic: String,
+    /// Time of the quote
+    pub timestamp: DateTime<Utc>,
+    /// Bid price, if quoted
+    pub bid: Option<f64>,
+    /// Offer price, if quoted
+    pub offer: Option<f64>,
+}
+
+/// Aggregates ticks into candles of their mid prices
+///
+/// A tick quoting a single side is priced at that side. The volume of a candle is its
+/// number of ticks.
+///
+/// # Returns
+/// The candles ordered by time then EPIC; ticks without a price are skipped
+pub fn candles_from_ticks(ticks: &[Tick], resolution: Resolution) -> Vec<Candle> {
+    let mut sorted: Vec<&Tick> = ticks.iter().collect();
+    sorted.sort_by_key(|tick| tick.timestamp);
+
+    let mut periods: BTreeMap<(DateTime<Utc>, &str), Candle> = BTreeMap::new();
+    for tick in sorted {
+        let price = match (tick.bid, tick.offer) {
+            (Some(bid), Some(offer)) => (bid + offer) / 2.0,
+            (Some(price), None) | (None, Some(price)) => price,
+            (None, None) => continue,
+        };
+        let start = resolution.align(tick.timestamp);
+        periods
+            .entry((start, &tick.epic))
+            .and_modify(|period| {
+                period.high = period.high.max(price);
+                period.low = period.low.min(price);
+                period.close = price;
+                period.volume = period.volume.map(|count| count + 1);
+            })
+            .or_insert_with(|| Candle {
+                epic: tick.epic.clone(),
+                resolution,
+                timestamp: start,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume: Some(1),
+            });
+    }
+    periods.into_values().collect()
+}
+
+/// Selection of stored records
+///
+/// Every filter is optional. Records are selected within `[from, to)` and returned
+/// oldest first, at most `limit` of them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreQuery {
+    /// EPIC of the market; for transactions, the instrument name
+    pub epic: Option<String>,
+    /// First time selected
+    pub from: Option<DateTime<Utc>>,
+    /// Time after the last one selected
+    pub to: Option<DateTime<Utc>>,
+    /// Maximum number of records returned
+    pub limit: Option<usize>,
+}
+
+impl StoreQuery {
+    /// Creates a query selecting every record
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects the records of a market
+    pub fn for_epic(mut self, epic: &str) -> Self {
+        self.epic = Some(epic.to_string());
+        self
+    }
+
+    /// Selects the records within `[from, to)`
+    pub fn between(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self.to = Some(to);
+        self
+    }
+
+    /// Selects the records before `to`
+    pub fn before(mut self, to: DateTime<Utc>) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    /// Returns at most `limit` records
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns true if a record of `epic` at `timestamp` is selected, ignoring the limit
+    pub fn matches(&self, epic: &str, timestamp: DateTime<Utc>) -> bool {
+        self.epic.as_deref().is_none_or(|selected| selected == epic)
+            && self.from.is_none_or(|from| timestamp >= from)
+            && self.to.is_none_or(|to| timestamp < to)
+    }
+}
+
+/// Outcome of a write to a [`Store`]
+///
+/// Records whose key is already stored are conflicts: they replace the stored record,
+/// and are counted as unchanged when it holds the same values. Replaying a stream or
+/// re-running a backfill only gives conflicts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteReport {
+    /// Records with a new key
+    pub inserted: usize,
+    /// Records replacing a stored one with different values
+    pub updated: usize,
+    /// Records identical to the stored one
+    pub unchanged: usize,
+}
+
+impl WriteReport {
+    /// Returns the number of records of the write
+    pub fn total(&self) -> usize {
+        self.inserted + self.updated + self.unchanged
+    }
+
+    /// Returns the number of records whose key was already stored
+    pub fn conflicts(&self) -> usize {
+        self.updated + self.unchanged
+    }
+
+    /// Counts a record from the lookup of its key
+    ///
+    /// `stored` is `None` if the key is new, or whether the stored record is identical.
+    ///
+    /// # Returns
+    /// True if the record must be written
+    pub(crate) fn count(&mut self, stored: Option<bool>) -> bool {
+        match stored {
+            None => self.inserted += 1,
+            Some(false) => self.updated += 1,
+            Some(true) => {
+                self.unchanged += 1;
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Counts a record from the outcome of an upsert
+    ///
+    /// `inserted` is `None` if the stored record was identical and left as is, or whether
+    /// the key was new.
+    pub(crate) fn count_upsert(&mut self, inserted: Option<bool>) {
+        match inserted {
+            Some(true) => self.inserted += 1,
+            Some(false) => self.updated += 1,
+            None => self.unchanged += 1,
+        }
+    }
+}
+
+impl std::ops::AddAssign for WriteReport {
+    fn add_assign(&mut self, other: Self) {
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.unchanged += other.unchanged;
+    }
+}
+
+/// Persistence of market data, transactions, orders and the instrument catalog
+///
+/// Every record has a key and writing a record with the key of a stored one replaces
+/// it, so writes can be repeated safely; each write returns a [`WriteReport`] counting
+/// the conflicts:
+///
+/// - candles: EPIC, resolution and start time
+/// - ticks: EPIC and time
+/// - transactions: reference, UTC date, type, instrument and amount, see
+///   [`transaction_key`](crate::storage::transaction_store::transaction_key)
+/// - orders: correlation ID, time and kind of the journal entry
+/// - deals: deal reference
+/// - instruments: EPIC
+///
+/// Queries return records oldest first.
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// Writes candles
+    ///
+    /// # Returns
+    /// The report of the write
+    async fn put_candles(&self, candles: &[Candle]) -> Result<WriteReport, AppError>;
+
+    /// Returns the candle of a market starting at `timestamp`
+    async fn get_candle(
+        &self,
+        epic: &str,
+        resolution: Resolution,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Candle>, AppError>;
+
+    /// Returns the candles at `resolution` selected by `query`
+    async fn query_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<Vec<Candle>, AppError>;
+
+    /// Returns the latest `count` candles of a market at `resolution`, oldest first
+    ///
+    /// The default implementation reads every candle of the market; backends override
+    /// it with an indexed query.
+    async fn latest_candles(
+        &self,
+        epic: &str,
+        resolution: Resolution,
+        count: usize,
+    ) -> Result<Vec<Candle>, AppError> {
+        let mut candles = self
+            .query_candles(resolution, &StoreQuery::new().for_epic(epic))
+            .await?;
+        let skipped = candles.len().saturating_sub(count);
+        candles.drain(..skipped);
+        Ok(candles)
+    }
+
+    /// Returns the candles selected by `query`, read at `source` and downsampled to
+    /// `target`
+    ///
+    /// The limit applies to the downsampled candles. When the range of the query does
+    /// not start or end on a period of `target`, the first or last candle only covers
+    /// the part of its period within the range.
+    ///
+    /// # Returns
+    /// The candles, or `AppError::InvalidInput` if `target` is finer than `source`
+    async fn query_candles_downsampled(
+        &self,
+        source: Resolution,
+        target: Resolution,
+        query: &StoreQuery,
+    ) -> Result<Vec<Candle>, AppError> {
+        if target.duration() < source.duration() {
+            return Err(AppError::InvalidInput(format!(
+                "cannot downsample {} candles to {}",
+                source.as_str(),
+                target.as_str()
+            )));
+        }
+        let source_query = StoreQuery {
+            limit: None,
+            ..query.clone()
+        };
+        let mut candles = downsample(&self.query_candles(source, &source_query).await?, target)?;
+        if let Some(limit) = query.limit {
+            candles.truncate(limit);
+        }
+        Ok(candles)
+    }
+
+    /// Streams the candles of a market at `resolution` within `[from, to)`, oldest first
+    ///
+    /// A [`PriceEvent::Gap`] precedes each candle not starting the period after the
+    /// previous one, or `from`, and one ends the stream if the last candle is not the
+    /// last period before `to`. Candles are read in pages of
+    /// [`SERIES_PAGE_SIZE`](crate::storage::price_series::SERIES_PAGE_SIZE) as the
+    /// stream is consumed; the stream ends after the first error.
+    fn candles<'a>(
+        &'a self,
+        epic: &'a str,
+        resolution: Resolution,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxStream<'a, Result<PriceEvent<Candle>, AppError>> {
+        candle_stream(self, epic, resolution, from, to)
+    }
+
+    /// Deletes the candles at `resolution` selected by `query`, ignoring its limit
+    ///
+    /// # Returns
+    /// The number of candles deleted
+    async fn delete_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<usize, AppError>;
+
+    /// Writes ticks
+    ///
+    /// # Returns
+    /// The report of the write
+    async fn put_ticks(&self, ticks: &[Tick]) -> Result<WriteReport, AppError>;
+
+    /// Returns the tick of a market at `timestamp`
+    async fn get_tick(
+        &self,
+        epic: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Tick>, AppError>;
+
+    /// Returns the ticks selected by `query`
+    async fn query_ticks(&self, query: &StoreQuery) -> Result<Vec<Tick>, AppError>;
+
+    /// Streams the ticks of a market within `[from, to)`, oldest first
+    ///
+    /// A [`PriceEvent::Gap`] is reported wherever no tick was stored for more than
+    /// [`DEFAULT_TICK_GAP_SECONDS`], including from `from` to the first tick and from
+    /// the last one to `to`; use [`tick_stream`] for another threshold. Ticks are read
+    /// in pages as the stream is consumed; the stream ends after the first error.
+    fn ticks<'a>(
+        &'a self,
+        epic: &'a str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxStream<'a, Result<PriceEvent<Tick>, AppError>> {
+        tick_stream(
+            self,
+            epic,
+            from,
+            to,
+            Duration::seconds(DEFAULT_TICK_GAP_SECONDS),
+        )
+    }
+
+    /// Deletes the ticks selected by `query`, ignoring its limit
+    ///
+    /// # Returns
+    /// The number of ticks deleted
+    async fn delete_ticks(&self, query: &StoreQuery) -> Result<usize, AppError>;
+
+    /// Writes transactions
+    ///
+    /// # Returns
+    /// The report of the write, or `AppError::InvalidInput` if a date
+    /// cannot be parsed
+    async fn put_transactions(
+        &self,
+        transactions: &[AccountTransaction],
+    ) -> Result<WriteReport, AppError>;
+
+    /// Returns the transactions with a reference, such as a deal and its charges
+    async fn get_transactions(&self, reference: &str) -> Result<Vec<AccountTransaction>, AppError>;
+
+    /// Returns the transactions selected by `query`, matching its EPIC against the
+    /// instrument name
+    async fn query_transactions(
+        &self,
+        query: &StoreQuery,
+    ) -> Result<Vec<AccountTransaction>, AppError>;
+
+    /// Writes order journal entries
+    ///
+    /// # Returns
+    /// The report of the write
+    async fn put_orders(&self, entries: &[JournalEntry]) -> Result<WriteReport, AppError>;
+
+    /// Returns the journal entries of one order, oldest first
+    async fn get_orders(&self, correlation_id: &str) -> Result<Vec<JournalEntry>, AppError>;
+
+    /// Returns the journal entries selected by `query`
+    ///
+    /// Entries without an EPIC are only selected by queries without one.
+    async fn query_orders(&self, query: &StoreQuery) -> Result<Vec<JournalEntry>, AppError>;
+
+    /// Writes order journal entries and the deals they complete
+    ///
+    /// The deals are rebuilt from every stored entry of the orders written, so a
+    /// confirmation recorded after its request joins the same deal.
+    ///
+    /// # Returns
+    /// The report of the write of the entries; deals are not counted
+    async fn record_orders(&self, entries: &[JournalEntry]) -> Result<WriteReport, AppError> {
+        let report = self.put_orders(entries).await?;
+        let mut correlation_ids: Vec<&str> = entries
+            .iter()
+            .filter(|entry| entry.deal_reference.is_some())
+            .map(|entry| entry.correlation_id.as_str())
+            .collect();
+        correlation_ids.sort_unstable();
+        correlation_ids.dedup();
+        let mut deals = Vec::new();
+        for correlation_id in correlation_ids {
+            deals.extend(deal_records(&self.get_orders(correlation_id).await?));
+        }
+        self.put_deals(&deals).await?;
+        Ok(report)
+    }
+
+    /// Writes deals
+    ///
+    /// # Returns
+    /// The report of the write
+    async fn put_deals(&self, deals: &[DealRecord]) -> Result<WriteReport, AppError>;
+
+    /// Returns the deal with a deal reference
+    async fn get_deal(&self, deal_reference: &str) -> Result<Option<DealRecord>, AppError>;
+
+    /// Returns the deals selected by `query`, such as every deal on a market between
+    /// two dates
+    ///
+    /// Deals are timed by [`DealRecord::timestamp`]. Deals without an EPIC are only
+    /// selected by queries without one.
+    async fn query_deals(&self, query: &StoreQuery) -> Result<Vec<DealRecord>, AppError>;
+
+    /// Writes instrument catalog entries
+    ///
+    /// # Returns
+    /// The report of the write
+    async fn put_instruments(&self, entries: &[CatalogEntry]) -> Result<WriteReport, AppError>;
+
+    /// Returns the catalog entry of a market
+    async fn get_instrument(&self, epic: &str) -> Result<Option<CatalogEntry>, AppError>;
+
+    /// Returns the catalog entries selected by `query`, by name
+    async fn search_instruments(&self, query: &CatalogQuery)
+    -> Result<Vec<CatalogEntry>, AppError>;
+
+    /// Exports the records selected by `selection` to a snapshot file at `path`
+    ///
+    /// A snapshot is a JSON Lines file: a
+    /// [`SnapshotHeader`](crate::storage::snapshot::SnapshotHeader) followed by one
+    /// record per line, readable by any backend, so data can move between machines and
+    /// stores. The file is written through a temporary file renamed once complete.
+    /// Each table is read in pages of
+    /// [`SNAPSHOT_PAGE_SIZE`](crate::storage::snapshot::SNAPSHOT_PAGE_SIZE) records,
+    /// written as they come, so the export does not hold a whole table in memory.
+    ///
+    /// # Returns
+    /// The number of records exported per table
+    async fn export_snapshot(
+        &self,
+        path: &Path,
+        selection: &SnapshotSelection,
+    ) -> Result<SnapshotCounts, AppError> {
+        export_snapshot(self, path, selection).await
+    }
+
+    /// Imports a snapshot written by [`Store::export_snapshot`]
+    ///
+    /// Records are written in batches of
+    /// [`SNAPSHOT_IMPORT_BATCH_SIZE`](crate::storage::snapshot::SNAPSHOT_IMPORT_BATCH_SIZE)
+    /// and replace the stored ones with the same key, so importing twice is safe.
+    ///
+    /// # Returns
+    /// The report of the writes, or `AppError::InvalidInput` if the file is not a
+    /// snapshot this version can read
+    async fn import_snapshot(&self, path: &Path) -> Result<WriteReport, AppError> {
+        import_snapshot(self, path).await
+    }
+}
@@ -0,0 +1,478 @@
+use crate::application::models::account::AccountTransaction;
+use crate::application::models::transaction::EnrichedTransaction;
+use crate::config::Config;
+use crate::error::AppError;
+use crate::storage::json_lines::JsonLinesFile;
+use crate::storage::utils::{PgMigration, apply_pg_migrations, pg_schema_version};
+use crate::utils::atomic_file::write_atomic;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Parses the UTC date of a transaction
+///
+/// # Returns
+/// The date, or `AppError::InvalidInput` if it cannot be parsed
+pub fn transaction_date(transaction: &AccountTransaction) -> Result<DateTime<Utc>, AppError> {
+    NaiveDateTime::parse_from_str(&transaction.date_utc, "%Y-%m-%dT%H:%M:%S%.f")
+        .map(|date| date.and_utc())
+        .map_err(|_| {
+            AppError::InvalidInput(format!(
+                "invalid transaction date: {}",
+                transaction.date_utc
+            ))
+        })
+}
+
+/// Identity of a transaction in a store
+///
+/// IG reuses a reference for the transactions of one deal, such as a close and its
+/// charges, which may be booked in the same second. The type, instrument and amount
+/// tell them apart.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TransactionKey {
+    /// Reference of the deal
+    pub reference: String,
+    /// UTC date of the transaction
+    pub date_utc: String,
+    /// Type of the transaction, e.g. `DEAL` or `CHARGE`
+    pub transaction_type: String,
+    /// Name of the instrument
+    pub instrument_name: String,
+    /// Profit or loss, as formatted by IG
+    pub amount: String,
+}
+
+/// Returns the [`TransactionKey`] of a transaction
+pub fn transaction_key(transaction: &AccountTransaction) -> TransactionKey {
+    TransactionKey {
+        reference: transaction.reference.clone(),
+        date_utc: transaction.date_utc.clone(),
+        transaction_type: transaction.transaction_type.clone(),
+        instrument_name: transaction.instrument_name.clone(),
+        amount: transaction.profit_and_loss.clone(),
+    }
+}
+
+/// Position of an incremental transaction sync
+///
+/// IG dates have a one-second resolution, so the references of the transactions at
+/// the last date are kept to skip them when the next sync fetches that second again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCheckpoint {
+    /// Date of the last transaction fetched, or end of the last window synced
+    pub last_date_utc: DateTime<Utc>,
+    /// References of the transactions fetched at `last_date_utc`
+    pub references: Vec<String>,
+}
+
+impl SyncCheckpoint {
+    /// Creates a checkpoint at `last_date_utc` with no transaction
+    pub fn new(last_date_utc: DateTime<Utc>) -> Self {
+        Self {
+            last_date_utc,
+            references: Vec::new(),
+        }
+    }
+
+    /// Returns true if a transaction dated `date` was fetched before this checkpoint
+    pub fn covers(&self, date: DateTime<Utc>, reference: &str) -> bool {
+        date < self.last_date_utc
+            || (date == self.last_date_utc && self.references.iter().any(|seen| seen == reference))
+    }
+
+    /// Moves the checkpoint past a transaction fetched at `date`
+    pub fn advance(&mut self, date: DateTime<Utc>, reference: &str) {
+        if date > self.last_date_utc {
+            self.last_date_utc = date;
+            self.references.clear();
+        }
+        if date == self.last_date_utc && !self.references.iter().any(|seen| seen == reference) {
+            self.references.push(reference.to_string());
+        }
+    }
+}
+
+/// Local mirror of the transaction history
+///
+/// Used by [`TransactionSync`](crate::application::services::transaction_sync::TransactionSync)
+/// to keep the transactions fetched so far and the checkpoint to resume from.
+#[async_trait]
+pub trait TransactionStore: Send + Sync {
+    /// Appends the transactions not stored yet
+    ///
+    /// Transactions with the [`transaction_key`] of a stored transaction, or of an
+    /// earlier one of the batch, are skipped, so overlapping fetch windows never store a
+    /// transaction twice.
+    ///
+    /// # Returns
+    /// The number of transactions stored
+    async fn append(&self, transactions: &[AccountTransaction]) -> Result<usize, AppError>;
+
+    /// Returns every stored transaction, in the order they were stored
+    async fn load(&self) -> Result<Vec<AccountTransaction>, AppError>;
+
+    /// Returns every stored transaction with its instrument parsed
+    async fn load_enriched(&self) -> Result<Vec<EnrichedTransaction>, AppError> {
+        Ok(self
+            .load()
+            .await?
+            .into_iter()
+            .map(EnrichedTransaction::new)
+            .collect())
+    }
+
+    /// Returns the checkpoint of the last sync, `None` before the first one
+    async fn checkpoint(&self) -> Result<Option<SyncCheckpoint>, AppError>;
+
+    /// Replaces the checkpoint
+    async fn save_checkpoint(&self, checkpoint: &SyncCheckpoint) -> Result<(), AppError>;
+}
+
+/// In-memory transaction store, lost when the process exits
+#[derive(Debug, Default)]
+pub struct MemoryTransactionStore {
+    transactions: Mutex<Vec<AccountTransaction>>,
+    keys: Mutex<HashSet<TransactionKey>>,
+    checkpoint: Mutex<Option<SyncCheckpoint>>,
+}
+
+impl MemoryTransactionStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TransactionStore for MemoryTransactionStore {
+    async fn append(&self, transactions: &[AccountTransaction]) -> Result<usize, AppError> {
+        let mut keys = self.keys.lock().unwrap();
+        let mut stored = self.transactions.lock().unwrap();
+        let before = stored.len();
+        stored.extend(
+            transactions
+                .iter()
+                .filter(|transaction| keys.insert(transaction_key(transaction)))
+                .cloned(),
+        );
+        Ok(stored.len() - before)
+    }
+
+    async fn load(&self) -> Result<Vec<AccountTransaction>, AppError> {
+        Ok(self.transactions.lock().unwrap().clone())
+    }
+
+    async fn checkpoint(&self) -> Result<Option<SyncCheckpoint>, AppError> {
+        Ok(self.checkpoint.lock().unwrap().clone())
+    }
+
+    async fn save_checkpoint(&self, checkpoint: &SyncCheckpoint) -> Result<(), AppError> {
+        *self.checkpoint.lock().unwrap() = Some(checkpoint.clone());
+        Ok(())
+    }
+}
+
+/// Transaction store keeping one transaction per line of a [`JsonLinesFile`]
+///
+/// The checkpoint is kept next to it, in a file with the `checkpoint.json` extension.
+/// Loading reads the whole file, and so does the first append, to learn the keys
+/// already stored.
+#[derive(Debug)]
+pub struct FileTransactionStore {
+    file: JsonLinesFile<AccountTransaction>,
+    checkpoint_path: PathBuf,
+    keys: Mutex<Option<HashSet<TransactionKey>>>,
+}
+
+impl FileTransactionStore {
+    /// Opens the store at `path`, creating the files on the first write
+    pub fn open(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        Self {
+            checkpoint_path: path.with_extension("checkpoint.json"),
+            file: JsonLinesFile::open(path),
+            keys: Mutex::new(None),
+        }
+    }
+
+    /// Returns the path of the checkpoint file
+    pub fn checkpoint_path(&self) -> &Path {
+        &self.checkpoint_path
+    }
+}
+
+#[async_trait]
+impl TransactionStore for FileTransactionStore {
+    async fn append(&self, transactions: &[AccountTransaction]) -> Result<usize, AppError> {
+        let mut guard = self.keys.lock().unwrap();
+        let keys = match guard.as_mut() {
+            Some(keys) => keys,
+            None => guard.insert(
+                self.file
+                    .filter(|_| true)?
+                    .iter()
+                    .map(transaction_key)
+                    .collect(),
+            ),
+        };
+        let fresh: Vec<&AccountTransaction> = transactions
+            .iter()
+            .filter(|transaction| keys.insert(transaction_key(transaction)))
+            .collect();
+        self.file.append_all(fresh.iter().copied())?;
+        Ok(fresh.len())
+    }
+
+    async fn load(&self) -> Result<Vec<AccountTransaction>, AppError> {
+        let _guard = self.keys.lock().unwrap();
+        self.file.read()
+    }
+
+    async fn checkpoint(&self) -> Result<Option<SyncCheckpoint>, AppError> {
+        let _guard = self.keys.lock().unwrap();
+        if !self.checkpoint_path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&self.checkpoint_path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    async fn save_checkpoint(&self, checkpoint: &SyncCheckpoint) -> Result<(), AppError> {
+        let _guard = self.keys.lock().unwrap();
+        write_atomic(&self.checkpoint_path, &serde_json::to_vec(checkpoint)?)?;
+        Ok(())
+    }
+}
+
+/// Schema migrations of [`PgTransactionStore`]: version, description and SQL
+///
+/// Applied versions are recorded in `ig_transaction_migrations`; new migrations are
+/// appended with the next version and existing ones never change.
+const PG_MIGRATIONS: [PgMigration; 3] = [
+    (
+        1,
+        "create transactions",
+        r#"
+        CREATE TABLE IF NOT EXISTS ig_transactions (
+            reference TEXT NOT NULL,
+            date_utc TEXT NOT NULL,
+            deal_date TIMESTAMPTZ NOT NULL,
+            transaction_type TEXT NOT NULL,
+            instrument_name TEXT NOT NULL,
+            category TEXT NOT NULL,
+            profit_and_loss DOUBLE PRECISION,
+            currency TEXT NOT NULL,
+            raw JSONB NOT NULL,
+            inserted_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (reference, date_utc)
+        );
+        CREATE INDEX IF NOT EXISTS ig_transactions_deal_date ON ig_transactions (deal_date);
+        "#,
+    ),
+    (
+        2,
+        "create sync checkpoints",
+        r#"
+        CREATE TABLE IF NOT EXISTS ig_transaction_checkpoints (
+            name TEXT PRIMARY KEY,
+            checkpoint JSONB NOT NULL,
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        "#,
+    ),
+    (
+        3,
+        "key transactions on type, instrument and amount",
+        r#"
+        ALTER TABLE ig_transactions ADD COLUMN IF NOT EXISTS amount TEXT NOT NULL DEFAULT '';
+        UPDATE ig_transactions SET amount = raw->>'profitAndLoss';
+        ALTER TABLE ig_transactions DROP CONSTRAINT IF EXISTS ig_transactions_pkey;
+        ALTER TABLE ig_transactions
+            ADD PRIMARY KEY (reference, date_utc, transaction_type, instrument_name, amount);
+        "#,
+    ),
+];
+
+/// Table recording the applied migrations
+const PG_MIGRATIONS_TABLE: &str = "ig_transaction_migrations";
+
+/// Key of the advisory lock serializing migrations across processes
+const PG_MIGRATION_LOCK: i64 = 0x6967_7478;
+
+/// Transaction store in a PostgreSQL database
+///
+/// Transactions are upserted on their [`transaction_key`]: a transaction fetched again
+/// replaces the stored row if IG changed it, and is otherwise left alone. The raw
+/// transaction is kept as JSON next to columns for the date, type, instrument,
+/// category and profit, so the mirror can be queried in SQL.
+///
+/// As a [`TransactionStore`] it backs a
+/// [`TransactionSync`](crate::application::services::transaction_sync::TransactionSync)
+/// directly. Its checkpoint is kept per account, so the syncs of several accounts can
+/// share a database. Open it with [`PgTransactionStore::connect`], or call
+/// [`PgTransactionStore::migrate`] once before use, to create or update the schema.
+#[derive(Debug, Clone)]
+pub struct PgTransactionStore {
+    pool: PgPool,
+    account_id: String,
+}
+
+impl PgTransactionStore {
+    /// Creates a store on `pool` keeping the checkpoint of `account_id`, see
+    /// [`Config::pg_pool`](crate::config::Config::pg_pool)
+    pub fn new(pool: PgPool, account_id: &str) -> Self {
+        Self {
+            pool,
+            account_id: account_id.to_string(),
+        }
+    }
+
+    /// Connects to the database of `config`, for the account of its credentials, and
+    /// applies the pending migrations
+    pub async fn connect(config: &Config) -> Result<Self, AppError> {
+        let store = Self::new(config.pg_pool().await?, &config.credentials.account_id);
+        store.migrate().await?;
+        Ok(store)
+    }
+
+    /// Returns the account the checkpoint belongs to
+    pub fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    /// Returns the connection pool of the store
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Applies the migrations not applied yet, each in its own database transaction
+    ///
+    /// Safe to call from several processes at once: an advisory lock lets one apply
+    /// each migration while the others wait and skip it.
+    ///
+    /// # Returns
+    /// The number of migrations applied, or `AppError::IncompatibleSchema` if the
+    /// database records a migration this version of the crate does not know
+    pub async fn migrate(&self) -> Result<usize, AppError> {
+        apply_pg_migrations(
+            &self.pool,
+            PG_MIGRATIONS_TABLE,
+            PG_MIGRATION_LOCK,
+            &PG_MIGRATIONS,
+        )
+        .await
+    }
+
+    /// Returns the latest migration applied, `None` before the first one
+    pub async fn schema_version(&self) -> Result<Option<i64>, AppError> {
+        pg_schema_version(&self.pool, PG_MIGRATIONS_TABLE).await
+    }
+
+    /// Inserts new transactions and updates the stored ones IG changed since
+    ///
+    /// # Returns
+    /// The number of rows inserted or updated, or `AppError::InvalidInput` if a date
+    /// cannot be parsed, in which case nothing is written
+    pub async fn upsert(&self, transactions: &[AccountTransaction]) -> Result<usize, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let mut written = 0;
+        for transaction in transactions {
+            written += Self::upsert_one(&mut tx, transaction).await?;
+        }
+        tx.commit().await?;
+        Ok(written)
+    }
+
+    async fn upsert_one(
+        tx: &mut Transaction<'_, Postgres>,
+        transaction: &AccountTransaction,
+    ) -> Result<usize, AppError> {
+        let enriched = EnrichedTransaction::from(transaction);
+        let category = serde_json::to_value(enriched.category)?;
+        let result = sqlx::query(
+            r#"
+            INSERT INTO ig_transactions (
+                reference, date_utc, deal_date, transaction_type, instrument_name,
+                category, profit_and_loss, currency, raw, amount
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (reference, date_utc, transaction_type, instrument_name, amount)
+            DO UPDATE SET
+                deal_date = EXCLUDED.deal_date,
+                category = EXCLUDED.category,
+                profit_and_loss = EXCLUDED.profit_and_loss,
+                currency = EXCLUDED.currency,
+                raw = EXCLUDED.raw,
+                updated_at = now()
+            WHERE ig_transactions.raw IS DISTINCT FROM EXCLUDED.raw
+            "#,
+        )
+        .bind(&transaction.reference)
+        .bind(&transaction.date_utc)
+        .bind(transaction_date(transaction)?)
+        .bind(&transaction.transaction_type)
+        .bind(&transaction.instrument_name)
+        .bind(category.as_str())
+        .bind(enriched.pnl())
+        .bind(&transaction.currency)
+        .bind(Json(transaction))
+        .bind(&transaction.profit_and_loss)
+        .execute(&mut **tx)
+        .await?;
+        Ok(result.rows_affected() as usize)
+    }
+}
+
+#[async_trait]
+impl TransactionStore for PgTransactionStore {
+    /// Upserts the transactions, see [`PgTransactionStore::upsert`]
+    ///
+    /// # Returns
+    /// The number of rows inserted, or updated because IG changed them since
+    async fn append(&self, transactions: &[AccountTransaction]) -> Result<usize, AppError> {
+        self.upsert(transactions).await
+    }
+
+    /// Returns every stored transaction, oldest first
+    async fn load(&self) -> Result<Vec<AccountTransaction>, AppError> {
+        let rows: Vec<Json<AccountTransaction>> =
+            sqlx::query_scalar("SELECT raw FROM ig_transactions ORDER BY deal_date, reference")
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rows
+            .into_iter()
+            .map(|Json(transaction)| transaction)
+            .collect())
+    }
+
+    async fn checkpoint(&self) -> Result<Option<SyncCheckpoint>, AppError> {
+        let row: Option<Json<SyncCheckpoint>> =
+            sqlx::query_scalar("SELECT checkpoint FROM ig_transaction_checkpoints WHERE name = $1")
+                .bind(&self.account_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.map(|Json(checkpoint)| checkpoint))
+    }
+
+    async fn save_checkpoint(&self, checkpoint: &SyncCheckpoint) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO ig_transaction_checkpoints (name, checkpoint) VALUES ($1, $2)
+            ON CONFLICT (name) DO UPDATE SET checkpoint = EXCLUDED.checkpoint, updated_at = now()
+            "#,
+        )
+        .bind(&self.account_id)
+        .bind(Json(checkpoint))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
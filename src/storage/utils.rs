@@ -3,7 +3,7 @@ use crate::error::AppError;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 use serde_json;
-use sqlx::Executor;
+use sqlx::{Executor, PgPool};
 
 /// Stores a list of transactions in the database
 ///
@@ -62,3 +62,81 @@ pub fn serialize_to_json<T: Serialize>(value: &T) -> Result<String, serde_json::
 pub fn deserialize_from_json<T: DeserializeOwned>(s: &str) -> Result<T, serde_json::Error> {
     serde_json::from_str(s)
 }
+
+/// Schema migration of a PostgreSQL store: version, description and SQL
+pub(crate) type PgMigration = (i64, &'static str, &'static str);
+
+/// Applies the migrations not recorded in `table` yet, each in its own database transaction
+///
+/// Safe to call from several processes at once: the advisory lock `lock` lets one apply
+/// each migration while the others wait and skip it.
+///
+/// # Returns
+/// The number of migrations applied, or `AppError::IncompatibleSchema` if the database
+/// records a later migration than `migrations`, so a downgraded crate never writes to a
+/// schema it does not know
+pub(crate) async fn apply_pg_migrations(
+    pool: &PgPool,
+    table: &str,
+    lock: i64,
+    migrations: &[PgMigration],
+) -> Result<usize, AppError> {
+    sqlx::query(&format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            version BIGINT PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )
+        "#
+    ))
+    .execute(pool)
+    .await?;
+    let supported = migrations.iter().map(|(version, _, _)| *version).max();
+    if let Some(found) = pg_schema_version(pool, table).await?
+        && supported.is_none_or(|supported| found > supported)
+    {
+        return Err(AppError::IncompatibleSchema {
+            found,
+            supported: supported.unwrap_or_default(),
+        });
+    }
+
+    let mut applied = 0;
+    for (version, description, sql) in migrations {
+        let mut tx = pool.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(lock)
+            .execute(&mut *tx)
+            .await?;
+        let done: bool = sqlx::query_scalar(&format!(
+            "SELECT EXISTS (SELECT 1 FROM {table} WHERE version = $1)"
+        ))
+        .bind(version)
+        .fetch_one(&mut *tx)
+        .await?;
+        if done {
+            continue;
+        }
+        sqlx::raw_sql(sql).execute(&mut *tx).await?;
+        sqlx::query(&format!(
+            "INSERT INTO {table} (version, description) VALUES ($1, $2)"
+        ))
+        .bind(version)
+        .bind(description)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+/// Returns the latest migration recorded in `table`, `None` before the first one
+pub(crate) async fn pg_schema_version(pool: &PgPool, table: &str) -> Result<Option<i64>, AppError> {
+    Ok(
+        sqlx::query_scalar(&format!("SELECT MAX(version) FROM {table}"))
+            .fetch_one(pool)
+            .await?,
+    )
+}
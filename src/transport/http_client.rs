@@ -9,7 +9,7 @@ use tokio::sync::Semaphore;
 use tracing::{debug, error, info, warn};
 
 use crate::constants::USER_AGENT;
-use crate::utils::rate_limiter::app_non_trading_limiter;
+use crate::utils::rate_limiter::{RateLimiter, rate_limiter_registry};
 use crate::{config::Config, error::AppError, session::interface::IgSession};
 
 // Global semaphore to limit concurrent API requests
@@ -28,6 +28,16 @@ const DEFAULT_INITIAL_BACKOFF_MS: u64 = 1000; // 1 second
 const DEFAULT_MAX_BACKOFF_MS: u64 = 60000; // 60 seconds max backoff
 const DEFAULT_BACKOFF_FACTOR: f64 = 2.0; // Exponential backoff factor
 
+/// Returns whether a request counts against the trading limit of the account
+///
+/// Trading requests create, amend or close positions and working orders; reading
+/// them, e.g. the confirmation of a deal, is a non-trading request.
+fn is_trading_request(method: &Method, path: &str) -> bool {
+    let path = path.trim_start_matches('/');
+    *method != Method::GET
+        && (path.starts_with("positions/otc") || path.starts_with("workingorders/otc"))
+}
+
 /// Interface for the IG HTTP client
 #[async_trait]
 pub trait IgHttpClient: Send + Sync {
@@ -44,6 +54,26 @@ pub trait IgHttpClient: Send + Sync {
         for<'de> R: DeserializeOwned + 'static,
         T: Serialize + Send + Sync + 'static;
 
+    /// Makes a DELETE request carrying a JSON body
+    ///
+    /// IG documents such requests (e.g. closing positions) as a `POST` with the
+    /// `_method: DELETE` header, since many HTTP stacks drop the body of a `DELETE`.
+    /// The default implementation sends a plain `DELETE` through [`IgHttpClient::request`].
+    async fn request_delete_with_body<T, R>(
+        &self,
+        path: &str,
+        session: &IgSession,
+        body: &T,
+        version: &str,
+    ) -> Result<R, AppError>
+    where
+        for<'de> R: DeserializeOwned + 'static,
+        T: Serialize + Send + Sync + 'static,
+    {
+        self.request(Method::DELETE, path, session, Some(body), version)
+            .await
+    }
+
     /// Makes an unauthenticated HTTP request (for login)
     async fn request_no_auth<T, R>(
         &self,
@@ -198,6 +228,9 @@ impl IgHttpClientImpl {
                     )
                     .await;
                     Err(AppError::RateLimitExceeded)
+                } else if body.contains("exceeded-account-historical-data-allowance") {
+                    error!("Historical data allowance exceeded for request to {}", url);
+                    Err(AppError::HistoricalAllowanceExceeded)
                 } else {
                     error!("Forbidden access to {}: {}", url, body);
                     Err(AppError::Unauthorized)
@@ -214,37 +247,18 @@ impl IgHttpClientImpl {
         }
     }
 
-    /// Helper method to handle rate limiting
-    async fn handle_rate_limit(&self, url: &str, reason: &str) {
-        // Set the rate limited flag
-        RATE_LIMITED.store(true, Ordering::SeqCst);
-        error!("Rate limit exceeded for request to {} ({})", url, reason);
-
-        // Notify all rate limiters about the exceeded limit
-        // This will cause them to enforce a mandatory cooldown period
-        let non_trading_limiter = app_non_trading_limiter();
-        non_trading_limiter.notify_rate_limit_exceeded().await;
-
-        // Schedule a task to reset the flag after a delay
-        // Increased from 30 to 60 seconds to give more time for rate limit to reset
-        let rate_limited = RATE_LIMITED.clone();
-        tokio::spawn(async move {
-            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
-            rate_limited.store(false, Ordering::SeqCst);
-            info!("Rate limit flag reset after 60 second cooldown");
-        });
-    }
-}
-
-#[async_trait]
-impl IgHttpClient for IgHttpClientImpl {
-    async fn request<T, R>(
+    /// Sends an authenticated request with retries, optionally overriding the method
+    ///
+    /// With `method_override`, the request is sent as `method` and carries the
+    /// `_method` header that IG reads as the actual method.
+    async fn send_authenticated<T, R>(
         &self,
         method: Method,
         path: &str,
         session: &IgSession,
         body: Option<&T>,
         version: &str,
+        method_override: Option<Method>,
     ) -> Result<R, AppError>
     where
         for<'de> R: DeserializeOwned + 'static,
@@ -253,6 +267,7 @@ impl IgHttpClient for IgHttpClientImpl {
         let url = self.build_url(path);
         let method_str = method.as_str().to_string(); // Store method as string for logging
         debug!("Making {} request to {}", method_str, url);
+        let trading = is_trading_request(&method, path);
 
         let mut retry_count = 0;
 
@@ -296,7 +311,7 @@ impl IgHttpClient for IgHttpClientImpl {
 
             // Respect rate limits before making the request
             // This will handle the actual rate limiting based on request history
-            match session.respect_rate_limit().await {
+            match session.respect_rate_limit_for(trading).await {
                 Ok(()) => {}
                 Err(e) => {
                     drop(permit);
@@ -311,6 +326,9 @@ impl IgHttpClient for IgHttpClientImpl {
             let mut builder = self.client.request(method.clone(), &url);
             builder = self.add_common_headers(builder, version);
             builder = self.add_auth_headers(builder, session);
+            if let Some(override_method) = &method_override {
+                builder = builder.header("_method", override_method.as_str());
+            }
 
             if let Some(data) = body {
                 builder = builder.json(data);
@@ -370,11 +388,14 @@ impl IgHttpClient for IgHttpClientImpl {
         let permit = API_SEMAPHORE.acquire().await.unwrap();
 
         // Respect rate limits
-        session.respect_rate_limit().await?;
+        session.respect_rate_limit_for(trading).await?;
 
         let mut builder = self.client.request(method, &url);
         builder = self.add_common_headers(builder, version);
         builder = self.add_auth_headers(builder, session);
+        if let Some(override_method) = &method_override {
+            builder = builder.header("_method", override_method.as_str());
+        }
 
         if let Some(data) = body {
             builder = builder.json(data);
@@ -387,6 +408,73 @@ impl IgHttpClient for IgHttpClientImpl {
         result
     }
 
+    /// Returns the limiter of the non-trading requests of the API key of the client
+    fn app_limiter(&self) -> Arc<RateLimiter> {
+        rate_limiter_registry().app_non_trading(&self.config.credentials.api_key)
+    }
+
+    /// Helper method to handle rate limiting
+    async fn handle_rate_limit(&self, url: &str, reason: &str) {
+        // Set the rate limited flag
+        RATE_LIMITED.store(true, Ordering::SeqCst);
+        error!("Rate limit exceeded for request to {} ({})", url, reason);
+
+        // Notify all rate limiters about the exceeded limit
+        // This will cause them to enforce a mandatory cooldown period
+        let non_trading_limiter = self.app_limiter();
+        non_trading_limiter.notify_rate_limit_exceeded().await;
+
+        // Schedule a task to reset the flag after a delay
+        // Increased from 30 to 60 seconds to give more time for rate limit to reset
+        let rate_limited = RATE_LIMITED.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+            rate_limited.store(false, Ordering::SeqCst);
+            info!("Rate limit flag reset after 60 second cooldown");
+        });
+    }
+}
+
+#[async_trait]
+impl IgHttpClient for IgHttpClientImpl {
+    async fn request<T, R>(
+        &self,
+        method: Method,
+        path: &str,
+        session: &IgSession,
+        body: Option<&T>,
+        version: &str,
+    ) -> Result<R, AppError>
+    where
+        for<'de> R: DeserializeOwned + 'static,
+        T: Serialize + Send + Sync + 'static,
+    {
+        self.send_authenticated(method, path, session, body, version, None)
+            .await
+    }
+
+    async fn request_delete_with_body<T, R>(
+        &self,
+        path: &str,
+        session: &IgSession,
+        body: &T,
+        version: &str,
+    ) -> Result<R, AppError>
+    where
+        for<'de> R: DeserializeOwned + 'static,
+        T: Serialize + Send + Sync + 'static,
+    {
+        self.send_authenticated(
+            Method::POST,
+            path,
+            session,
+            Some(body),
+            version,
+            Some(Method::DELETE),
+        )
+        .await
+    }
+
     async fn request_no_auth<T, R>(
         &self,
         method: Method,
@@ -443,9 +531,9 @@ impl IgHttpClient for IgHttpClientImpl {
                 method_str, url
             );
 
-            // Use the global app rate limiter for unauthenticated requests
+            // Use the app rate limiter of the API key for unauthenticated requests
             // This is thread-safe and can be called from multiple threads concurrently
-            let limiter = app_non_trading_limiter();
+            let limiter = self.app_limiter();
             limiter.wait().await;
 
             let mut builder = self.client.request(method.clone(), &url);
@@ -513,8 +601,8 @@ impl IgHttpClient for IgHttpClientImpl {
         // Acquire a permit from the semaphore
         let permit = API_SEMAPHORE.acquire().await.unwrap();
 
-        // Use the global app rate limiter
-        let limiter = app_non_trading_limiter();
+        // Use the app rate limiter of the API key
+        let limiter = self.app_limiter();
         limiter.wait().await;
 
         let mut builder = self.client.request(method, &url);
@@ -1,2 +1,4 @@
 /// Module containing the HTTP client for making API requests to IG Markets
 pub mod http_client;
+/// Module tracking Lightstreamer subscriptions across account switches
+pub mod streaming;
@@ -0,0 +1,306 @@
+use crate::error::AppError;
+use crate::session::interface::{IgAuthenticator, IgSession};
+use lightstreamer_rs::client::LightstreamerClient;
+use lightstreamer_rs::client::SubscriptionRequest;
+use lightstreamer_rs::subscription::{
+    Snapshot, Subscription, SubscriptionListener, SubscriptionMode,
+};
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, info};
+
+/// Item prefixes whose subscriptions are bound to a specific account ID
+const ACCOUNT_SCOPED_PREFIXES: [&str; 2] = ["ACCOUNT:", "TRADE:"];
+
+/// Factory used to build a fresh listener each time a subscription is (re)created
+pub type ListenerFactory = Arc<dyn Fn() -> Box<dyn SubscriptionListener> + Send + Sync>;
+
+/// Description of a Lightstreamer subscription that can be recreated on demand
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionSpec {
+    /// Subscription mode (MERGE, DISTINCT, RAW or COMMAND)
+    pub mode: StreamingMode,
+    /// Items to subscribe to, e.g. `MARKET:{epic}` or `TRADE:{account_id}`
+    pub items: Vec<String>,
+    /// Fields requested for every item
+    pub fields: Vec<String>,
+    /// Optional data adapter name
+    pub data_adapter: Option<String>,
+    /// Whether the initial snapshot should be requested
+    pub snapshot: bool,
+}
+
+/// Cloneable mirror of Lightstreamer's `SubscriptionMode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamingMode {
+    /// MERGE mode
+    Merge,
+    /// DISTINCT mode
+    Distinct,
+    /// RAW mode
+    Raw,
+    /// COMMAND mode
+    Command,
+}
+
+impl From<StreamingMode> for SubscriptionMode {
+    fn from(mode: StreamingMode) -> Self {
+        match mode {
+            StreamingMode::Merge => SubscriptionMode::Merge,
+            StreamingMode::Distinct => SubscriptionMode::Distinct,
+            StreamingMode::Raw => SubscriptionMode::Raw,
+            StreamingMode::Command => SubscriptionMode::Command,
+        }
+    }
+}
+
+impl SubscriptionSpec {
+    /// Creates a new subscription spec with snapshot enabled and no data adapter
+    ///
+    /// # Arguments
+    /// * `mode` - Subscription mode
+    /// * `items` - Items to subscribe to
+    /// * `fields` - Fields to request for every item
+    pub fn new(mode: StreamingMode, items: Vec<String>, fields: Vec<String>) -> Self {
+        Self {
+            mode,
+            items,
+            fields,
+            data_adapter: None,
+            snapshot: true,
+        }
+    }
+
+    /// Returns true if any of the items is bound to the given account ID
+    pub fn is_account_scoped(&self, account_id: &str) -> bool {
+        self.items
+            .iter()
+            .any(|item| is_account_scoped_item(item, account_id))
+    }
+
+    /// Returns a copy of this spec with every account-scoped item rewritten
+    /// from `old_account_id` to `new_account_id`. Other items are kept as is.
+    pub fn rescoped(&self, old_account_id: &str, new_account_id: &str) -> Self {
+        let items = self
+            .items
+            .iter()
+            .map(|item| rescope_item(item, old_account_id, new_account_id))
+            .collect();
+        Self {
+            items,
+            ..self.clone()
+        }
+    }
+
+    /// Builds a Lightstreamer subscription from this spec
+    fn build(&self, listener: Box<dyn SubscriptionListener>) -> Result<Subscription, AppError> {
+        let mut subscription = Subscription::new(
+            self.mode.into(),
+            Some(self.items.clone()),
+            Some(self.fields.clone()),
+        )
+        .map_err(|e| AppError::WebSocketError(e.to_string()))?;
+        subscription
+            .set_data_adapter(self.data_adapter.clone())
+            .map_err(AppError::WebSocketError)?;
+        let snapshot = if self.snapshot {
+            Snapshot::Yes
+        } else {
+            Snapshot::No
+        };
+        subscription
+            .set_requested_snapshot(Some(snapshot))
+            .map_err(AppError::WebSocketError)?;
+        subscription.add_listener(listener);
+        Ok(subscription)
+    }
+}
+
+/// Returns true if the item is an ACCOUNT or TRADE item for the given account ID
+pub fn is_account_scoped_item(item: &str, account_id: &str) -> bool {
+    ACCOUNT_SCOPED_PREFIXES
+        .iter()
+        .any(|prefix| item.strip_prefix(prefix) == Some(account_id))
+}
+
+/// Rewrites an account-scoped item for a new account ID
+///
+/// Items that are not bound to `old_account_id` are returned unchanged.
+pub fn rescope_item(item: &str, old_account_id: &str, new_account_id: &str) -> String {
+    for prefix in ACCOUNT_SCOPED_PREFIXES {
+        if item.strip_prefix(prefix) == Some(old_account_id) {
+            return format!("{prefix}{new_account_id}");
+        }
+    }
+    item.to_string()
+}
+
+/// A subscription currently registered with the Lightstreamer client
+struct TrackedSubscription {
+    id: usize,
+    spec: SubscriptionSpec,
+    listener_factory: ListenerFactory,
+}
+
+/// Keeps track of active Lightstreamer subscriptions so that account-scoped
+/// ones (TRADE/ACCOUNT items) can be recreated when the session switches account.
+///
+/// Market, chart and price subscriptions are left untouched on a switch.
+/// The Lightstreamer client must be connected for subscription IDs to be returned.
+pub struct StreamingSubscriptions {
+    sender: Sender<SubscriptionRequest>,
+    account_id: String,
+    subscriptions: Vec<TrackedSubscription>,
+}
+
+impl StreamingSubscriptions {
+    /// Creates a new subscription registry
+    ///
+    /// # Arguments
+    /// * `sender` - Subscription sender of the `LightstreamerClient`
+    /// * `account_id` - Account ID the streaming connection is currently using
+    pub fn new(sender: Sender<SubscriptionRequest>, account_id: &str) -> Self {
+        Self {
+            sender,
+            account_id: account_id.to_string(),
+            subscriptions: Vec::new(),
+        }
+    }
+
+    /// Returns the account ID account-scoped subscriptions are bound to
+    pub fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    /// Returns the specs of all tracked subscriptions
+    pub fn specs(&self) -> Vec<&SubscriptionSpec> {
+        self.subscriptions.iter().map(|s| &s.spec).collect()
+    }
+
+    /// Subscribes and starts tracking a subscription
+    ///
+    /// # Arguments
+    /// * `spec` - Description of the subscription
+    /// * `listener_factory` - Builds the listener; called again on every resubscription
+    ///
+    /// # Returns
+    /// * The Lightstreamer subscription ID
+    pub async fn subscribe(
+        &mut self,
+        spec: SubscriptionSpec,
+        listener_factory: ListenerFactory,
+    ) -> Result<usize, AppError> {
+        let id = send_subscription(&self.sender, &spec, &listener_factory).await?;
+        debug!("Subscribed {:?} with id {}", spec.items, id);
+        self.subscriptions.push(TrackedSubscription {
+            id,
+            spec,
+            listener_factory,
+        });
+        Ok(id)
+    }
+
+    /// Starts tracking a subscription already made with the given ID
+    ///
+    /// # Arguments
+    /// * `id` - The Lightstreamer subscription ID
+    /// * `spec` - Description of the subscription
+    /// * `listener_factory` - Builds the listener on every resubscription
+    pub fn track(&mut self, id: usize, spec: SubscriptionSpec, listener_factory: ListenerFactory) {
+        self.subscriptions.push(TrackedSubscription {
+            id,
+            spec,
+            listener_factory,
+        });
+    }
+
+    /// Unsubscribes and stops tracking the subscription with the given ID
+    pub async fn unsubscribe(&mut self, id: usize) {
+        LightstreamerClient::unsubscribe(self.sender.clone(), id).await;
+        self.subscriptions.retain(|s| s.id != id);
+    }
+
+    /// Moves account-scoped subscriptions to a new account ID
+    ///
+    /// Every subscription containing a TRADE/ACCOUNT item for the current account is
+    /// unsubscribed and recreated with the items rewritten for `new_account_id`.
+    /// Other subscriptions keep their IDs and listeners.
+    ///
+    /// The account ID only changes once every subscription has been recreated. On an
+    /// error, the subscriptions not yet recreated stay bound to the current account and
+    /// calling again retries them.
+    ///
+    /// # Returns
+    /// * The number of subscriptions that were recreated
+    pub async fn resubscribe_for_account(
+        &mut self,
+        new_account_id: &str,
+    ) -> Result<usize, AppError> {
+        if self.account_id == new_account_id {
+            debug!("Streaming already bound to account {}", new_account_id);
+            return Ok(0);
+        }
+
+        let mut recreated = 0;
+        for tracked in self.subscriptions.iter_mut() {
+            if !tracked.spec.is_account_scoped(&self.account_id) {
+                continue;
+            }
+            LightstreamerClient::unsubscribe(self.sender.clone(), tracked.id).await;
+
+            let spec = tracked.spec.rescoped(&self.account_id, new_account_id);
+            let id = send_subscription(&self.sender, &spec, &tracked.listener_factory).await?;
+            debug!(
+                "Resubscribed {:?} as {:?} with id {}",
+                tracked.spec.items, spec.items, id
+            );
+            tracked.id = id;
+            tracked.spec = spec;
+            recreated += 1;
+        }
+
+        info!(
+            "Moved {} streaming subscriptions from account {} to {}",
+            recreated, self.account_id, new_account_id
+        );
+        self.account_id = new_account_id.to_string();
+        Ok(recreated)
+    }
+
+    /// Switches the session account and moves account-scoped subscriptions with it
+    ///
+    /// # Arguments
+    /// * `auth` - Authenticator used to perform the REST account switch
+    /// * `session` - The current session
+    /// * `account_id` - The ID of the account to switch to
+    /// * `default_account` - Whether to set this account as the default (optional)
+    ///
+    /// # Returns
+    /// * The new session for the switched account
+    pub async fn switch_account<A: IgAuthenticator>(
+        &mut self,
+        auth: &A,
+        session: &IgSession,
+        account_id: &str,
+        default_account: Option<bool>,
+    ) -> Result<IgSession, AppError> {
+        let new_session = auth
+            .switch_account(session, account_id, default_account)
+            .await?;
+        self.resubscribe_for_account(&new_session.account_id)
+            .await?;
+        Ok(new_session)
+    }
+}
+
+/// Builds a subscription from the spec and waits for the ID assigned by the client
+async fn send_subscription(
+    sender: &Sender<SubscriptionRequest>,
+    spec: &SubscriptionSpec,
+    listener_factory: &ListenerFactory,
+) -> Result<usize, AppError> {
+    let subscription = spec.build(listener_factory())?;
+    LightstreamerClient::subscribe_get_id(sender.clone(), subscription)
+        .await
+        .map_err(|e| AppError::WebSocketError(e.to_string()))
+}
@@ -0,0 +1,66 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// File written aside and renamed over its path once complete
+///
+/// The content goes to a hidden temporary file next to the path, renamed by
+/// [`AtomicFile::commit`]. A crash or an error never leaves a truncated file: the path
+/// keeps its previous content until the rename, and a file dropped without being
+/// committed is removed.
+pub struct AtomicFile {
+    path: PathBuf,
+    temporary: PathBuf,
+    writer: Option<BufWriter<File>>,
+    committed: bool,
+}
+
+impl AtomicFile {
+    /// Creates the temporary file of `path`
+    ///
+    /// The directory of `path` must exist.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temporary = path.with_file_name(format!(".{file_name}.tmp"));
+        let writer = BufWriter::new(File::create(&temporary)?);
+        Ok(Self {
+            path: path.to_path_buf(),
+            temporary,
+            writer: Some(writer),
+            committed: false,
+        })
+    }
+
+    /// Returns the writer of the temporary file
+    pub fn writer(&mut self) -> &mut BufWriter<File> {
+        self.writer
+            .as_mut()
+            .expect("the writer is only taken on commit or drop")
+    }
+
+    /// Flushes the temporary file and renames it over the path
+    pub fn commit(mut self) -> io::Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        std::fs::rename(&self.temporary, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.writer.take();
+            let _ = std::fs::remove_file(&self.temporary);
+        }
+    }
+}
+
+/// Replaces the content of `path` with `bytes` through an [`AtomicFile`]
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    file.writer().write_all(bytes)?;
+    file.commit()
+}
@@ -0,0 +1,107 @@
+// src/utils/currency.rs
+//
+// Currency conversion utilities based on the exchange rates published in market details
+
+use crate::application::models::market::MarketDetails;
+use crate::error::AppError;
+use std::collections::HashMap;
+
+/// Converts amounts between instrument currencies and the account base currency
+///
+/// Rates are collected from the `currencies` of [`MarketDetails`] (see
+/// `Currency::rate_to_base`) or set explicitly, so every service converting P&L,
+/// margins or sizes uses the same figures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurrencyConverter {
+    base_currency: String,
+    rates: HashMap<String, f64>,
+}
+
+impl CurrencyConverter {
+    /// Creates a converter for the given account base currency
+    ///
+    /// # Arguments
+    ///
+    /// * `base_currency` - ISO code of the account currency, e.g. "GBP"
+    pub fn new(base_currency: &str) -> Self {
+        Self {
+            base_currency: base_currency.to_string(),
+            rates: HashMap::from([(base_currency.to_string(), 1.0)]),
+        }
+    }
+
+    /// Creates a converter and loads the rates of every market
+    pub fn from_market_details<'a>(
+        base_currency: &str,
+        markets: impl IntoIterator<Item = &'a MarketDetails>,
+    ) -> Self {
+        let mut converter = Self::new(base_currency);
+        for details in markets {
+            converter.add_market_details(details);
+        }
+        converter
+    }
+
+    /// Returns the account base currency
+    pub fn base_currency(&self) -> &str {
+        &self.base_currency
+    }
+
+    /// Sets the rate converting `currency` into the base currency
+    pub fn with_rate(mut self, currency: &str, rate: f64) -> Self {
+        self.set_rate(currency, rate);
+        self
+    }
+
+    /// Sets the rate converting `currency` into the base currency
+    ///
+    /// The rate of the base currency itself is always 1.0 and cannot be overridden.
+    pub fn set_rate(&mut self, currency: &str, rate: f64) {
+        if currency != self.base_currency && rate > 0.0 {
+            self.rates.insert(currency.to_string(), rate);
+        }
+    }
+
+    /// Loads the exchange rates published in the market details
+    pub fn add_market_details(&mut self, details: &MarketDetails) {
+        for currency in details.instrument.currencies.iter().flatten() {
+            if let Some(rate) = currency.rate_to_base() {
+                self.set_rate(&currency.code, rate);
+            }
+        }
+    }
+
+    /// Returns the rate converting `currency` into the base currency, if known
+    pub fn rate(&self, currency: &str) -> Option<f64> {
+        self.rates.get(currency).copied()
+    }
+
+    /// Converts an amount in `currency` into the base currency
+    ///
+    /// # Returns
+    ///
+    /// * `Result<f64, AppError>` - The converted amount, or `AppError::InvalidInput` if no
+    ///   rate is known for `currency`
+    pub fn to_base(&self, amount: f64, currency: &str) -> Result<f64, AppError> {
+        Ok(amount * self.required_rate(currency)?)
+    }
+
+    /// Converts an amount in the base currency into `currency`
+    pub fn from_base(&self, amount: f64, currency: &str) -> Result<f64, AppError> {
+        Ok(amount / self.required_rate(currency)?)
+    }
+
+    /// Converts an amount between two currencies through the base currency
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Result<f64, AppError> {
+        self.from_base(self.to_base(amount, from)?, to)
+    }
+
+    fn required_rate(&self, currency: &str) -> Result<f64, AppError> {
+        self.rate(currency).ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "no exchange rate from {currency} to {}",
+                self.base_currency
+            ))
+        })
+    }
+}
@@ -0,0 +1,115 @@
+// src/utils/decimal.rs
+//
+// Decimal accessors for prices, sizes and balances, enabled with the `decimal` feature
+
+use crate::application::models::account::{AccountBalance, PositionDetails};
+use crate::application::models::market::{MarketData, MarketSnapshot, PricePoint};
+use crate::application::models::order::{CreateOrderRequest, CreateWorkingOrderRequest};
+use crate::utils::money::Money;
+pub use rust_decimal::Decimal;
+
+/// Converts an `f64` into a `Decimal` using its shortest decimal representation
+///
+/// `0.1_f64` becomes exactly `0.1`, so sums and differences of converted values are free
+/// of binary rounding artifacts. Returns `None` for NaN and infinite values.
+///
+/// # Examples
+///
+/// ```
+/// use ig_client::utils::decimal::{Decimal, to_decimal};
+/// use std::str::FromStr;
+///
+/// let sum = to_decimal(0.1).unwrap() + to_decimal(0.2).unwrap();
+/// assert_eq!(sum, Decimal::from_str("0.3").unwrap());
+/// ```
+pub fn to_decimal(value: f64) -> Option<Decimal> {
+    if !value.is_finite() {
+        return None;
+    }
+    value.to_string().parse().ok()
+}
+
+/// Implements `<field>_decimal()` accessors returning `Option<Decimal>`
+macro_rules! decimal_accessors {
+    ($t:ty { $($field:ident => $method:ident),+ $(,)? }) => {
+        impl $t {
+            $(
+                #[doc = concat!("Returns `", stringify!($field), "` as a `Decimal`")]
+                pub fn $method(&self) -> Option<Decimal> {
+                    DecimalField::to_decimal_field(&self.$field)
+                }
+            )+
+        }
+    };
+}
+
+/// Conversion of plain and optional `f64` fields
+trait DecimalField {
+    fn to_decimal_field(&self) -> Option<Decimal>;
+}
+
+impl DecimalField for f64 {
+    fn to_decimal_field(&self) -> Option<Decimal> {
+        to_decimal(*self)
+    }
+}
+
+impl DecimalField for Option<f64> {
+    fn to_decimal_field(&self) -> Option<Decimal> {
+        self.and_then(to_decimal)
+    }
+}
+
+impl DecimalField for Money {
+    fn to_decimal_field(&self) -> Option<Decimal> {
+        to_decimal(self.amount)
+    }
+}
+
+decimal_accessors!(MarketSnapshot {
+    bid => bid_decimal,
+    offer => offer_decimal,
+    high => high_decimal,
+    low => low_decimal,
+    net_change => net_change_decimal,
+});
+
+decimal_accessors!(MarketData {
+    bid => bid_decimal,
+    offer => offer_decimal,
+    net_change => net_change_decimal,
+});
+
+decimal_accessors!(PricePoint {
+    bid => bid_decimal,
+    ask => ask_decimal,
+    last_traded => last_traded_decimal,
+});
+
+decimal_accessors!(CreateOrderRequest {
+    size => size_decimal,
+    level => level_decimal,
+    stop_level => stop_level_decimal,
+    limit_level => limit_level_decimal,
+});
+
+decimal_accessors!(CreateWorkingOrderRequest {
+    size => size_decimal,
+    level => level_decimal,
+    stop_level => stop_level_decimal,
+    limit_level => limit_level_decimal,
+});
+
+decimal_accessors!(PositionDetails {
+    size => size_decimal,
+    level => level_decimal,
+    stop_level => stop_level_decimal,
+    limit_level => limit_level_decimal,
+});
+
+decimal_accessors!(AccountBalance {
+    balance => balance_decimal,
+    deposit => deposit_decimal,
+    profit_loss => profit_loss_decimal,
+    available => available_decimal,
+});
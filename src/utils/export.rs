@@ -0,0 +1,480 @@
+// src/utils/export.rs
+//
+// Export utilities writing market snapshots and transactions as CSV or
+// newline-delimited JSON, and transactions as Apache Parquet
+
+use crate::application::models::market::{MarketData, MarketDetails};
+use crate::application::models::transaction::EnrichedTransaction;
+use crate::error::AppError;
+use crate::utils::parsing::parse_amount;
+use serde_json::{Map, Value};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Output format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// One JSON object per line
+    NdJson,
+}
+
+impl FromStr for ExportFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "ndjson" | "jsonl" | "json" => Ok(ExportFormat::NdJson),
+            other => Err(AppError::InvalidInput(format!(
+                "unknown export format: {other}"
+            ))),
+        }
+    }
+}
+
+/// Column that can be exported for a market
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketColumn {
+    /// EPIC of the market
+    Epic,
+    /// Instrument name
+    InstrumentName,
+    /// Instrument type
+    InstrumentType,
+    /// Expiry
+    Expiry,
+    /// Market status
+    MarketStatus,
+    /// Bid price
+    Bid,
+    /// Offer price
+    Offer,
+    /// Session high (market details only)
+    High,
+    /// Session low (market details only)
+    Low,
+    /// Net change since the previous close
+    NetChange,
+    /// Percentage change since the previous close
+    PercentageChange,
+    /// Time of the last update
+    UpdateTime,
+}
+
+impl MarketColumn {
+    /// Every column, in default export order
+    pub const ALL: [MarketColumn; 12] = [
+        MarketColumn::Epic,
+        MarketColumn::InstrumentName,
+        MarketColumn::InstrumentType,
+        MarketColumn::Expiry,
+        MarketColumn::MarketStatus,
+        MarketColumn::Bid,
+        MarketColumn::Offer,
+        MarketColumn::High,
+        MarketColumn::Low,
+        MarketColumn::NetChange,
+        MarketColumn::PercentageChange,
+        MarketColumn::UpdateTime,
+    ];
+
+    /// Returns the column name used as CSV header and JSON key
+    pub fn name(&self) -> &'static str {
+        match self {
+            MarketColumn::Epic => "epic",
+            MarketColumn::InstrumentName => "instrumentName",
+            MarketColumn::InstrumentType => "instrumentType",
+            MarketColumn::Expiry => "expiry",
+            MarketColumn::MarketStatus => "marketStatus",
+            MarketColumn::Bid => "bid",
+            MarketColumn::Offer => "offer",
+            MarketColumn::High => "high",
+            MarketColumn::Low => "low",
+            MarketColumn::NetChange => "netChange",
+            MarketColumn::PercentageChange => "percentageChange",
+            MarketColumn::UpdateTime => "updateTime",
+        }
+    }
+}
+
+impl Display for MarketColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for MarketColumn {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MarketColumn::ALL
+            .into_iter()
+            .find(|column| column.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| AppError::InvalidInput(format!("unknown market column: {s}")))
+    }
+}
+
+/// A market that can be exported column by column
+pub trait MarketRow {
+    /// Returns the value of the column, `Value::Null` if unavailable
+    fn column_value(&self, column: MarketColumn) -> Value;
+}
+
+fn optional<T: Into<Value>>(value: Option<T>) -> Value {
+    value.map(Into::into).unwrap_or(Value::Null)
+}
+
+fn to_value<T: serde::Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+impl MarketRow for MarketData {
+    fn column_value(&self, column: MarketColumn) -> Value {
+        match column {
+            MarketColumn::Epic => self.epic.clone().into(),
+            MarketColumn::InstrumentName => self.instrument_name.clone().into(),
+            MarketColumn::InstrumentType => to_value(&self.instrument_type),
+            MarketColumn::Expiry => self.expiry.clone().into(),
+            MarketColumn::MarketStatus => self.market_status.clone().into(),
+            MarketColumn::Bid => optional(self.bid),
+            MarketColumn::Offer => optional(self.offer),
+            MarketColumn::High | MarketColumn::Low => Value::Null,
+            MarketColumn::NetChange => optional(self.net_change),
+            MarketColumn::PercentageChange => optional(self.percentage_change),
+            MarketColumn::UpdateTime => optional(self.update_time.clone()),
+        }
+    }
+}
+
+impl MarketRow for MarketDetails {
+    fn column_value(&self, column: MarketColumn) -> Value {
+        let snapshot = &self.snapshot;
+        match column {
+            MarketColumn::Epic => self.instrument.epic.clone().into(),
+            MarketColumn::InstrumentName => self.instrument.name.clone().into(),
+            MarketColumn::InstrumentType => to_value(&self.instrument.instrument_type),
+            MarketColumn::Expiry => self.instrument.expiry.clone().into(),
+            MarketColumn::MarketStatus => snapshot.market_status.clone().into(),
+            MarketColumn::Bid => optional(snapshot.bid),
+            MarketColumn::Offer => optional(snapshot.offer),
+            MarketColumn::High => optional(snapshot.high),
+            MarketColumn::Low => optional(snapshot.low),
+            MarketColumn::NetChange => optional(snapshot.net_change),
+            MarketColumn::PercentageChange => optional(snapshot.percentage_change),
+            MarketColumn::UpdateTime => optional(snapshot.update_time.clone()),
+        }
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, a quote or a line break
+pub fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Formats a JSON value as a CSV field, empty for `null`
+pub(crate) fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => escape_csv(s),
+        other => escape_csv(&other.to_string()),
+    }
+}
+
+/// Writes markets to `writer` in the given format
+///
+/// # Arguments
+///
+/// * `writer` - Destination of the export
+/// * `format` - CSV (with a header row) or newline-delimited JSON
+/// * `rows` - Markets to export
+/// * `columns` - Columns to export, in order; every column if empty
+pub fn write_markets<W: Write, R: MarketRow>(
+    writer: &mut W,
+    format: ExportFormat,
+    rows: &[R],
+    columns: &[MarketColumn],
+) -> Result<(), AppError> {
+    let columns = if columns.is_empty() {
+        &MarketColumn::ALL[..]
+    } else {
+        columns
+    };
+
+    match format {
+        ExportFormat::Csv => {
+            let header: Vec<&str> = columns.iter().map(MarketColumn::name).collect();
+            writeln!(writer, "{}", header.join(","))?;
+            for row in rows {
+                let fields: Vec<String> = columns
+                    .iter()
+                    .map(|column| csv_field(&row.column_value(*column)))
+                    .collect();
+                writeln!(writer, "{}", fields.join(","))?;
+            }
+        }
+        ExportFormat::NdJson => {
+            for row in rows {
+                let object: Map<String, Value> = columns
+                    .iter()
+                    .map(|column| (column.name().to_string(), row.column_value(*column)))
+                    .collect();
+                writeln!(writer, "{}", Value::Object(object))?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes markets to a file, replacing it if it exists
+///
+/// See [`write_markets`] for the arguments.
+pub fn export_markets<R: MarketRow>(
+    path: impl AsRef<Path>,
+    format: ExportFormat,
+    rows: &[R],
+    columns: &[MarketColumn],
+) -> Result<(), AppError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_markets(&mut writer, format, rows, columns)
+}
+
+/// Columns of a transaction export, in order
+///
+/// Sizes, levels, strikes and profits are exported as numbers, empty when IG reports
+/// none; the category is the [`TransactionCategory`] of the transaction.
+///
+/// [`TransactionCategory`]: crate::application::models::transaction::TransactionCategory
+pub const TRANSACTION_COLUMNS: [&str; 17] = [
+    "dateUtc",
+    "openDateUtc",
+    "reference",
+    "transactionType",
+    "category",
+    "instrumentName",
+    "underlying",
+    "strike",
+    "optionType",
+    "expiry",
+    "period",
+    "size",
+    "openLevel",
+    "closeLevel",
+    "profitAndLoss",
+    "currency",
+    "cashTransaction",
+];
+
+fn text(value: &str) -> Value {
+    if value.is_empty() || value == "-" {
+        Value::Null
+    } else {
+        value.into()
+    }
+}
+
+fn amount(value: &str) -> Value {
+    optional(parse_amount(value))
+}
+
+/// Values of a transaction, in the order of [`TRANSACTION_COLUMNS`]
+fn transaction_values(enriched: &EnrichedTransaction) -> [Value; 17] {
+    let transaction = &enriched.transaction;
+    let instrument = &enriched.instrument;
+    [
+        text(&transaction.date_utc),
+        text(&transaction.open_date_utc),
+        text(&transaction.reference),
+        text(&transaction.transaction_type),
+        to_value(&enriched.category),
+        text(&transaction.instrument_name),
+        text(&instrument.asset_name),
+        optional(instrument.strike.as_deref().and_then(parse_amount)),
+        optional(instrument.option_type.clone()),
+        optional(enriched.expiry.map(|expiry| expiry.to_string())),
+        text(&transaction.period),
+        amount(&transaction.size),
+        amount(&transaction.open_level),
+        amount(&transaction.close_level),
+        amount(&transaction.profit_and_loss),
+        text(&transaction.currency),
+        transaction.cash_transaction.into(),
+    ]
+}
+
+/// Writes enriched transactions to `writer` in the given format
+///
+/// CSV exports have a header row with [`TRANSACTION_COLUMNS`]; JSON exports write one
+/// object per line with the same keys.
+pub fn write_transactions<W: Write>(
+    writer: &mut W,
+    format: ExportFormat,
+    transactions: &[EnrichedTransaction],
+) -> Result<(), AppError> {
+    if format == ExportFormat::Csv {
+        writeln!(writer, "{}", TRANSACTION_COLUMNS.join(","))?;
+    }
+    for transaction in transactions {
+        let values = transaction_values(transaction);
+        match format {
+            ExportFormat::Csv => {
+                let fields: Vec<String> = values.iter().map(csv_field).collect();
+                writeln!(writer, "{}", fields.join(","))?;
+            }
+            ExportFormat::NdJson => {
+                let object: Map<String, Value> = TRANSACTION_COLUMNS
+                    .iter()
+                    .map(|column| column.to_string())
+                    .zip(values)
+                    .collect();
+                writeln!(writer, "{}", Value::Object(object))?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes enriched transactions to a file, replacing it if it exists
+pub fn export_transactions(
+    path: impl AsRef<Path>,
+    format: ExportFormat,
+    transactions: &[EnrichedTransaction],
+) -> Result<(), AppError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_transactions(&mut writer, format, transactions)
+}
+
+/// Parquet schema of a transaction export, one field per [`TRANSACTION_COLUMNS`]
+#[cfg(feature = "parquet")]
+const TRANSACTION_SCHEMA: &str = "
+message transaction {
+    OPTIONAL INT64 dateUtc (TIMESTAMP(MILLIS, true));
+    OPTIONAL INT64 openDateUtc (TIMESTAMP(MILLIS, true));
+    OPTIONAL BYTE_ARRAY reference (UTF8);
+    OPTIONAL BYTE_ARRAY transactionType (UTF8);
+    OPTIONAL BYTE_ARRAY category (UTF8);
+    OPTIONAL BYTE_ARRAY instrumentName (UTF8);
+    OPTIONAL BYTE_ARRAY underlying (UTF8);
+    OPTIONAL DOUBLE strike;
+    OPTIONAL BYTE_ARRAY optionType (UTF8);
+    OPTIONAL INT32 expiry (DATE);
+    OPTIONAL BYTE_ARRAY period (UTF8);
+    OPTIONAL DOUBLE size;
+    OPTIONAL DOUBLE openLevel;
+    OPTIONAL DOUBLE closeLevel;
+    OPTIONAL DOUBLE profitAndLoss;
+    OPTIONAL BYTE_ARRAY currency (UTF8);
+    OPTIONAL BOOLEAN cashTransaction;
+}";
+
+#[cfg(feature = "parquet")]
+pub(crate) fn parquet_error(error: parquet::errors::ParquetError) -> AppError {
+    AppError::SerializationError(format!("parquet: {error}"))
+}
+
+/// Writes the present values of an optional column
+#[cfg(feature = "parquet")]
+pub(crate) fn write_column<T: parquet::data_type::DataType>(
+    writer: &mut parquet::column::writer::ColumnWriterImpl<'_, T>,
+    values: Vec<Option<T::T>>,
+) -> Result<(), AppError> {
+    let levels: Vec<i16> = values.iter().map(|value| value.is_some() as i16).collect();
+    let values: Vec<T::T> = values.into_iter().flatten().collect();
+    writer
+        .write_batch(&values, Some(&levels), None)
+        .map_err(parquet_error)?;
+    Ok(())
+}
+
+/// Writes enriched transactions to `writer` as an Apache Parquet file
+///
+/// The file holds one row group with a column per [`TRANSACTION_COLUMNS`]. Dates are
+/// UTC timestamps in milliseconds, expiries are dates, and missing values are nulls,
+/// so the file loads as typed columns in pandas, polars or BI tools.
+#[cfg(feature = "parquet")]
+pub fn write_transactions_parquet<W: Write + Send>(
+    writer: W,
+    transactions: &[EnrichedTransaction],
+) -> Result<(), AppError> {
+    use chrono::{NaiveDate, NaiveDateTime};
+    use parquet::column::writer::ColumnWriter;
+    use parquet::data_type::ByteArray;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::sync::Arc;
+
+    let schema = Arc::new(parse_message_type(TRANSACTION_SCHEMA).map_err(parquet_error)?);
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut file = SerializedFileWriter::new(writer, schema, properties).map_err(parquet_error)?;
+    let rows: Vec<[Value; 17]> = transactions.iter().map(transaction_values).collect();
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid date");
+
+    let mut group = file.next_row_group().map_err(parquet_error)?;
+    let mut index = 0;
+    while let Some(mut column) = group.next_column().map_err(parquet_error)? {
+        let values = rows.iter().map(|row| &row[index]);
+        match column.untyped() {
+            ColumnWriter::Int64ColumnWriter(writer) => write_column(
+                writer,
+                values
+                    .map(|value| {
+                        let date = value.as_str()?;
+                        NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%.f")
+                            .ok()
+                            .map(|date| date.and_utc().timestamp_millis())
+                    })
+                    .collect(),
+            )?,
+            ColumnWriter::Int32ColumnWriter(writer) => write_column(
+                writer,
+                values
+                    .map(|value| {
+                        let date = NaiveDate::parse_from_str(value.as_str()?, "%Y-%m-%d").ok()?;
+                        Some((date - epoch).num_days() as i32)
+                    })
+                    .collect(),
+            )?,
+            ColumnWriter::DoubleColumnWriter(writer) => {
+                write_column(writer, values.map(Value::as_f64).collect())?
+            }
+            ColumnWriter::BoolColumnWriter(writer) => {
+                write_column(writer, values.map(Value::as_bool).collect())?
+            }
+            ColumnWriter::ByteArrayColumnWriter(writer) => write_column(
+                writer,
+                values
+                    .map(|value| value.as_str().map(ByteArray::from))
+                    .collect(),
+            )?,
+            _ => unreachable!("transaction schema has no other column type"),
+        }
+        column.close().map_err(parquet_error)?;
+        index += 1;
+    }
+    group.close().map_err(parquet_error)?;
+    let mut writer = file.into_inner().map_err(parquet_error)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes enriched transactions to a Parquet file, replacing it if it exists
+///
+/// See [`write_transactions_parquet`].
+#[cfg(feature = "parquet")]
+pub fn export_transactions_parquet(
+    path: impl AsRef<Path>,
+    transactions: &[EnrichedTransaction],
+) -> Result<(), AppError> {
+    write_transactions_parquet(BufWriter::new(File::create(path)?), transactions)
+}
@@ -3,7 +3,10 @@
 // Financial calculation utilities for the IG client
 
 use crate::application::models::account::Position;
+use crate::application::models::market::{DealingRules, MarketDetails};
 use crate::application::models::order::Direction;
+use crate::error::AppError;
+use crate::utils::money::Money;
 
 /// Calculate the Profit and Loss (P&L) for a position based on current market prices
 ///
@@ -34,6 +37,14 @@ pub fn calculate_pnl(position: &Position) -> Option<f64> {
     Some(price_diff * position.position.size)
 }
 
+/// Calculate the P&L of a position in its currency
+///
+/// Same figure as [`calculate_pnl`], tagged with the currency of the position so it
+/// cannot be added to amounts in another currency by mistake.
+pub fn calculate_pnl_money(position: &Position) -> Option<Money> {
+    calculate_pnl(position).map(|pnl| Money::new(pnl, &position.position.currency))
+}
+
 /// Calculate the percentage return for a position
 ///
 /// # Arguments
@@ -54,3 +65,115 @@ pub fn calculate_percentage_return(position: &Position) -> Option<f64> {
 
     Some((pnl / initial_value) * 100.0)
 }
+
+/// Calculate the margin required to open a position
+///
+/// Percentage margins are applied per deposit band: the part of `size` that falls within
+/// each band of `marginDepositBands` is charged that band's margin. Without bands the
+/// instrument `marginFactor` is used. A `POINTS` margin factor is charged per contract,
+/// independently of the level. Levels quoted in pips are divided by the scaling factor
+/// of the market snapshot.
+///
+/// # Arguments
+///
+/// * `details` - Market details of the instrument
+/// * `size` - Proposed deal size, in contracts
+/// * `level` - Proposed opening level, as quoted by IG
+/// * `exchange_rate` - Rate converting the instrument currency into the account currency
+///   (1.0 if both are the same)
+///
+/// # Returns
+///
+/// * `Result<f64, AppError>` - The required margin in the account currency, or
+///   `AppError::InvalidInput` if the inputs or the margin information are invalid
+pub fn calculate_margin(
+    details: &MarketDetails,
+    size: f64,
+    level: f64,
+    exchange_rate: f64,
+) -> Result<f64, AppError> {
+    if size <= 0.0 || level <= 0.0 || exchange_rate <= 0.0 {
+        return Err(AppError::InvalidInput(format!(
+            "size, level and exchange rate must be positive (size {size}, level {level}, rate {exchange_rate})"
+        )));
+    }
+
+    let instrument = &details.instrument;
+    let contract_size = instrument.contract_size.parse::<f64>().unwrap_or(1.0);
+    let margin_factor = instrument.margin_factor;
+
+    if instrument.margin_factor_unit.as_deref() == Some("POINTS") {
+        let points = margin_factor.ok_or_else(|| {
+            AppError::InvalidInput(format!("no margin factor for {}", instrument.epic))
+        })?;
+        return Ok(size * contract_size * points * exchange_rate);
+    }
+
+    let notional_per_contract = contract_size * level / details.snapshot.price_scale();
+    let margin = match instrument.margin_deposit_bands.as_deref() {
+        Some(bands) if !bands.is_empty() => bands
+            .iter()
+            .map(|band| {
+                let upper = band.max.unwrap_or(f64::INFINITY).min(size);
+                let portion = (upper - band.min).max(0.0);
+                portion * notional_per_contract * band.margin / 100.0
+            })
+            .sum(),
+        _ => {
+            let factor = margin_factor.ok_or_else(|| {
+                AppError::InvalidInput(format!("no margin information for {}", instrument.epic))
+            })?;
+            size * notional_per_contract * factor / 100.0
+        }
+    };
+
+    Ok(margin * exchange_rate)
+}
+
+/// Round a deal size to the dealing rules of an instrument
+///
+/// The size is floored to the precision of the minimum deal size (two decimals when the
+/// instrument has none) and capped at the maximum deal size. A size below the minimum
+/// deal size is never raised to it.
+///
+/// # Arguments
+///
+/// * `size` - Requested deal size, in contracts
+/// * `rules` - Dealing rules of the instrument
+///
+/// # Returns
+///
+/// * `Ok(f64)` - A size IG accepts for the instrument
+/// * `Err(AppError::InvalidInput)` - If the floored size is below the minimum deal size
+pub fn round_size_to_rules(size: f64, rules: &DealingRules) -> Result<f64, AppError> {
+    let min = rules.min_deal_size.value.filter(|min| *min > 0.0);
+    let mut rounded = floor_size(size, min.map_or(2, size_decimals));
+    if let Some(min) = min
+        && rounded < min
+    {
+        return Err(AppError::InvalidInput(format!(
+            "size {size} is below the minimum deal size of {min}"
+        )));
+    }
+    if let Some(max) = rules.max_deal_size.filter(|max| *max > 0.0) {
+        rounded = rounded.min(max);
+    }
+    Ok(rounded)
+}
+
+/// Floors a size to `decimals` decimals
+pub(crate) fn floor_size(size: f64, decimals: i32) -> f64 {
+    let factor = 10f64.powi(decimals);
+    // Nudge before flooring so that 0.3 / 0.1 style ratios do not lose a step
+    ((size * factor) + 1e-9).floor() / factor
+}
+
+/// Number of decimals of a minimum deal size, used as the size step
+pub(crate) fn size_decimals(min_deal_size: f64) -> i32 {
+    (0..8)
+        .find(|decimals| {
+            let scaled = min_deal_size * 10f64.powi(*decimals);
+            (scaled - scaled.round()).abs() < 1e-9
+        })
+        .unwrap_or(8)
+}
@@ -0,0 +1,239 @@
+// src/utils/ledger.rs
+//
+// Chronological ledger merging account activities and transactions, exported as CSV or
+// newline-delimited JSON for accounting tools
+
+use crate::application::models::account::{AccountTransaction, Activity};
+use crate::application::models::order::Direction;
+use crate::application::models::transaction::StoreTransaction;
+use crate::error::AppError;
+use crate::utils::export::{ExportFormat, csv_field};
+use crate::utils::parsing::parse_amount;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Columns of a ledger export, in order
+pub const LEDGER_COLUMNS: [&str; 13] = [
+    "dateUtc",
+    "source",
+    "type",
+    "epic",
+    "instrument",
+    "reference",
+    "direction",
+    "size",
+    "price",
+    "currency",
+    "pnl",
+    "fees",
+    "description",
+];
+
+/// Origin of a ledger entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LedgerSource {
+    /// Account activity: orders, positions and amendments
+    Activity,
+    /// Account transaction: realized profit and loss, cash movements and charges
+    Transaction,
+}
+
+/// One row of the ledger
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntry {
+    /// Date and time of the entry, in UTC
+    pub date_utc: DateTime<Utc>,
+    /// Origin of the entry
+    pub source: LedgerSource,
+    /// Activity type or transaction type as reported by IG
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    /// EPIC of the market; transactions do not carry one
+    pub epic: Option<String>,
+    /// Name of the instrument
+    pub instrument: Option<String>,
+    /// Deal ID of an activity or reference of a transaction
+    pub reference: Option<String>,
+    /// Direction of the deal
+    pub direction: Option<Direction>,
+    /// Size of the deal
+    pub size: Option<f64>,
+    /// Deal level, or closing level of a transaction
+    pub price: Option<f64>,
+    /// Currency of the amounts
+    pub currency: Option<String>,
+    /// Realized profit or loss
+    pub pnl: Option<f64>,
+    /// Charges and commissions
+    pub fees: Option<f64>,
+    /// Description of the activity
+    pub description: Option<String>,
+}
+
+/// Parses a date of the API, with or without fractional seconds
+fn parse_utc(date: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .map(|date| date.and_utc())
+}
+
+impl LedgerEntry {
+    /// Builds the entry of an activity
+    ///
+    /// The date of the activity is read as UTC. Size, direction and level come from
+    /// the details, only returned by detailed activity requests.
+    ///
+    /// # Returns
+    /// The entry, or `AppError::InvalidInput` if the date cannot be parsed
+    pub fn from_activity(activity: &Activity) -> Result<Self, AppError> {
+        let date_utc = parse_utc(&activity.date).ok_or_else(|| {
+            AppError::InvalidInput(format!("invalid activity date: {}", activity.date))
+        })?;
+        let details = activity.details.as_ref();
+        Ok(Self {
+            date_utc,
+            source: LedgerSource::Activity,
+            entry_type: serde_json::to_value(activity.activity_type)?
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            epic: activity.epic.clone(),
+            instrument: details.and_then(|details| details.market_name.clone()),
+            reference: activity.deal_id.clone(),
+            direction: details.and_then(|details| details.direction.clone()),
+            size: details.and_then(|details| details.size),
+            price: details
+                .and_then(|details| details.level)
+                .or_else(|| activity.level.as_deref().and_then(parse_amount)),
+            currency: details
+                .and_then(|details| details.currency.clone())
+                .or_else(|| activity.currency.clone()),
+            pnl: None,
+            fees: None,
+            description: activity.description.clone(),
+        })
+    }
+
+    /// Builds the entry of a transaction
+    ///
+    /// The amount is reported as fees for charges, as classified by
+    /// [`StoreTransaction`], and as profit or loss otherwise.
+    ///
+    /// # Returns
+    /// The entry, or `AppError::InvalidInput` if the date cannot be parsed
+    pub fn from_transaction(transaction: &AccountTransaction) -> Result<Self, AppError> {
+        let date_utc = parse_utc(&transaction.date_utc).ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "invalid transaction date: {}",
+                transaction.date_utc
+            ))
+        })?;
+        let amount = parse_amount(&transaction.profit_and_loss);
+        let is_fee = StoreTransaction::from(transaction).is_fee;
+        let size = parse_amount(&transaction.size);
+        Ok(Self {
+            date_utc,
+            source: LedgerSource::Transaction,
+            entry_type: transaction.transaction_type.clone(),
+            epic: None,
+            instrument: Some(transaction.instrument_name.clone()),
+            reference: Some(transaction.reference.clone()),
+            direction: size.filter(|size| *size != 0.0).map(|size| {
+                if size > 0.0 {
+                    Direction::Buy
+                } else {
+                    Direction::Sell
+                }
+            }),
+            size: size.map(f64::abs),
+            price: parse_amount(&transaction.close_level).filter(|level| *level != 0.0),
+            currency: Some(transaction.currency.clone()),
+            pnl: if is_fee { None } else { amount },
+            fees: if is_fee { amount } else { None },
+            description: None,
+        })
+    }
+}
+
+/// Activities and transactions merged in chronological order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ledger {
+    /// Entries sorted by date; activities come first at equal dates
+    pub entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+    /// Merges activities and transactions into a ledger
+    ///
+    /// # Returns
+    /// The ledger, or `AppError::InvalidInput` if a date cannot be parsed
+    pub fn merge(
+        activities: &[Activity],
+        transactions: &[AccountTransaction],
+    ) -> Result<Self, AppError> {
+        let mut entries = activities
+            .iter()
+            .map(LedgerEntry::from_activity)
+            .chain(transactions.iter().map(LedgerEntry::from_transaction))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.date_utc);
+        Ok(Self { entries })
+    }
+
+    /// Total realized profit or loss
+    pub fn total_pnl(&self) -> f64 {
+        self.entries.iter().filter_map(|entry| entry.pnl).sum()
+    }
+
+    /// Total charges and commissions
+    pub fn total_fees(&self) -> f64 {
+        self.entries.iter().filter_map(|entry| entry.fees).sum()
+    }
+}
+
+/// Writes a ledger to `writer` in the given format
+///
+/// CSV exports have a header row with [`LEDGER_COLUMNS`]; JSON exports write one object
+/// per line with the same keys.
+pub fn write_ledger<W: Write>(
+    writer: &mut W,
+    format: ExportFormat,
+    ledger: &Ledger,
+) -> Result<(), AppError> {
+    if format == ExportFormat::Csv {
+        writeln!(writer, "{}", LEDGER_COLUMNS.join(","))?;
+    }
+    for entry in &ledger.entries {
+        let Value::Object(object) = serde_json::to_value(entry)? else {
+            unreachable!("ledger entries serialize to objects");
+        };
+        match format {
+            ExportFormat::Csv => {
+                let fields: Vec<String> = LEDGER_COLUMNS
+                    .iter()
+                    .map(|column| csv_field(object.get(*column).unwrap_or(&Value::Null)))
+                    .collect();
+                writeln!(writer, "{}", fields.join(","))?;
+            }
+            ExportFormat::NdJson => writeln!(writer, "{}", Value::Object(object))?,
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes a ledger to a file, replacing it if it exists
+pub fn export_ledger(
+    path: impl AsRef<Path>,
+    format: ExportFormat,
+    ledger: &Ledger,
+) -> Result<(), AppError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_ledger(&mut writer, format, ledger)
+}
@@ -0,0 +1,124 @@
+// src/utils/market_hours.rs
+//
+// Approximate trading hours by instrument class, to skip polling when markets are closed
+
+use crate::application::models::market::MarketData;
+use crate::presentation::InstrumentType;
+use chrono::{DateTime, Datelike, Duration, DurationRound, Timelike, Utc, Weekday};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Broad class of instruments sharing similar trading hours
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketClass {
+    /// Currency pairs, traded around the clock from Sunday evening to Friday evening
+    Forex,
+    /// Index CFDs and spread bets, quoted nearly around the clock on weekdays
+    Indices,
+    /// Commodities, quoted nearly around the clock on weekdays
+    Commodities,
+    /// Shares, traded during exchange hours on weekdays
+    Shares,
+    /// Any other instrument, assumed to trade on weekdays
+    Other,
+}
+
+impl From<InstrumentType> for MarketClass {
+    fn from(instrument_type: InstrumentType) -> Self {
+        match instrument_type {
+            InstrumentType::Currencies
+            | InstrumentType::BungeeCurrencies
+            | InstrumentType::KnockoutsCurrencies
+            | InstrumentType::OptCurrencies => MarketClass::Forex,
+            InstrumentType::Indices
+            | InstrumentType::BungeeIndices
+            | InstrumentType::KnockoutsIndices
+            | InstrumentType::OptIndices => MarketClass::Indices,
+            InstrumentType::Commodities
+            | InstrumentType::BungeeCommodities
+            | InstrumentType::KnockoutsCommodities
+            | InstrumentType::OptCommodities => MarketClass::Commodities,
+            InstrumentType::Shares
+            | InstrumentType::KnockoutsShares
+            | InstrumentType::OptShares => MarketClass::Shares,
+            _ => MarketClass::Other,
+        }
+    }
+}
+
+/// Returns the number of minutes elapsed since Monday 00:00 UTC
+fn minute_of_week(time: &DateTime<Utc>) -> u32 {
+    time.weekday().num_days_from_monday() * MINUTES_PER_DAY + time.hour() * 60 + time.minute()
+}
+
+/// Builds a minute-of-week value
+const fn at(day: Weekday, hour: u32, minute: u32) -> u32 {
+    day as u32 * MINUTES_PER_DAY + hour * 60 + minute
+}
+
+/// Returns true for Saturdays and Sundays in UTC
+pub fn is_weekend(time: &DateTime<Utc>) -> bool {
+    matches!(time.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Returns whether instruments of the class are likely in session at `time`
+///
+/// The schedules are UTC approximations of IG's usual hours and ignore holidays and
+/// daylight saving shifts; use the snapshot `marketStatus` (see [`should_poll`]) or the
+/// instrument opening hours when exact hours matter.
+///
+/// * Forex: Sunday 21:00 to Friday 22:00
+/// * Indices and commodities: Sunday 23:00 to Friday 21:00
+/// * Shares: weekdays 07:00 to 21:00, covering European and US sessions
+/// * Other: weekdays
+pub fn is_likely_open(class: MarketClass, time: &DateTime<Utc>) -> bool {
+    let minute = minute_of_week(time);
+    let weekly = |open: u32, close: u32| minute >= open || minute < close;
+    match class {
+        MarketClass::Forex => weekly(at(Weekday::Sun, 21, 0), at(Weekday::Fri, 22, 0)),
+        MarketClass::Indices | MarketClass::Commodities => {
+            weekly(at(Weekday::Sun, 23, 0), at(Weekday::Fri, 21, 0))
+        }
+        MarketClass::Shares => {
+            let minute_of_day = minute % MINUTES_PER_DAY;
+            !is_weekend(time) && (7 * 60..21 * 60).contains(&minute_of_day)
+        }
+        MarketClass::Other => !is_weekend(time),
+    }
+}
+
+/// Returns the first minute at or after `time` when the class is likely in session
+///
+/// Returns `time` itself if the class is already in session.
+pub fn next_likely_open(class: MarketClass, time: &DateTime<Utc>) -> DateTime<Utc> {
+    if is_likely_open(class, time) {
+        return *time;
+    }
+    let mut candidate = time.duration_trunc(Duration::minutes(1)).unwrap_or(*time);
+    // Every schedule opens at least once a week
+    for _ in 0..7 * MINUTES_PER_DAY {
+        candidate += Duration::minutes(1);
+        if is_likely_open(class, &candidate) {
+            return candidate;
+        }
+    }
+    candidate
+}
+
+/// Decides whether a market is worth polling at `time`
+///
+/// The last known snapshot status takes precedence when it says the market is
+/// `TRADEABLE`; otherwise the class schedule decides, so a market seen closed is
+/// polled again once its usual session starts.
+pub fn should_poll(class: MarketClass, last_status: Option<&str>, time: &DateTime<Utc>) -> bool {
+    last_status == Some("TRADEABLE") || is_likely_open(class, time)
+}
+
+/// Decides whether a market from a search or navigation result is worth polling at `time`
+pub fn should_poll_market(market: &MarketData, time: &DateTime<Utc>) -> bool {
+    should_poll(
+        MarketClass::from(market.instrument_type),
+        Some(&market.market_status),
+        time,
+    )
+}
@@ -0,0 +1,44 @@
+// src/utils/metrics.rs
+//
+// Thin metrics layer: counters and histograms forwarded to the `metrics` facade when
+// the `metrics` feature is enabled, and compiled to nothing otherwise
+
+/// Increments the counter `name` by `value`
+///
+/// With the `metrics` feature, the counter is reported through the `metrics` facade, so
+/// any installed recorder (Prometheus, StatsD...) exports it. Without it, this is a
+/// no-op and services only keep their own in-process statistics.
+pub fn increment_counter(name: &'static str, value: u64, labels: &[(&'static str, String)]) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(name, to_labels(labels)).increment(value);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (name, value, labels);
+}
+
+/// Records `value` in the histogram `name`
+///
+/// See [`increment_counter`] for where the value goes.
+pub fn record_histogram(name: &'static str, value: f64, labels: &[(&'static str, String)]) {
+    #[cfg(feature = "metrics")]
+    metrics::histogram!(name, to_labels(labels)).record(value);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (name, value, labels);
+}
+
+/// Sets the gauge `name` to `value`
+///
+/// See [`increment_counter`] for where the value goes.
+pub fn set_gauge(name: &'static str, value: f64, labels: &[(&'static str, String)]) {
+    #[cfg(feature = "metrics")]
+    metrics::gauge!(name, to_labels(labels)).set(value);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (name, value, labels);
+}
+
+#[cfg(feature = "metrics")]
+fn to_labels(labels: &[(&'static str, String)]) -> Vec<metrics::Label> {
+    labels
+        .iter()
+        .map(|(key, value)| metrics::Label::new(*key, value.clone()))
+        .collect()
+}
@@ -1,10 +1,35 @@
+/// Module containing file writes that never leave a truncated file
+pub mod atomic_file;
+/// Module containing currency conversion utilities
+pub mod currency;
+/// Module containing `rust_decimal` accessors for prices, sizes and balances
+#[cfg(feature = "decimal")]
+pub mod decimal;
 /// Module containing display formatting utilities for JSON serialization
 pub mod display;
+/// Module containing CSV and newline-delimited JSON export utilities
+pub mod export;
 /// Module containing financial calculation utilities
 pub mod finance;
+/// Module containing a chronological ledger of activities and transactions
+pub mod ledger;
 /// Module containing logging utilities
 pub mod logger;
+/// Module containing approximate trading hours by instrument class
+pub mod market_hours;
+/// Module containing counters and histograms reported through the `metrics` facade
+pub mod metrics;
+/// Module containing amounts carrying their currency
+pub mod money;
 /// Module containing parsing utilities for instrument names and other data
 pub mod parsing;
+/// Module containing equity curve and trading statistics from the transaction history
+pub mod performance;
+/// Module containing tasks run on an interval until shut down
+pub mod periodic;
 /// Module containing rate limiting functionality to manage API request frequency
 pub mod rate_limiter;
+/// Module containing realized results aggregated per tax year
+pub mod tax_report;
+/// Module containing FIFO and LIFO matching of fills into round trips
+pub mod trade_matching;
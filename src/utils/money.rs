@@ -0,0 +1,150 @@
+// src/utils/money.rs
+//
+// Amounts carrying their currency, with arithmetic refusing to mix currencies
+
+use crate::application::models::account::{AccountTransaction, Position};
+use crate::error::AppError;
+use crate::utils::currency::CurrencyConverter;
+use crate::utils::parsing::parse_amount;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::ops::Neg;
+
+/// Amount of money in a currency
+///
+/// Sums and differences are checked: combining amounts in different currencies is an
+/// error rather than a silently wrong figure. Convert one side first with
+/// [`Money::convert`].
+///
+/// # Examples
+///
+/// ```
+/// use ig_client::utils::money::Money;
+///
+/// let total = Money::new(10.0, "EUR").checked_add(&Money::new(2.5, "EUR")).unwrap();
+/// assert_eq!(total, Money::new(12.5, "EUR"));
+/// assert!(total.checked_add(&Money::new(1.0, "USD")).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Money {
+    /// Amount, in `currency`
+    pub amount: f64,
+    /// Currency code, e.g. `EUR`
+    pub currency: String,
+}
+
+impl Money {
+    /// Creates an amount in `currency`
+    pub fn new(amount: f64, currency: &str) -> Self {
+        Self {
+            amount,
+            currency: currency.to_string(),
+        }
+    }
+
+    /// Creates a zero amount in `currency`
+    pub fn zero(currency: &str) -> Self {
+        Self::new(0.0, currency)
+    }
+
+    /// Adds an amount in the same currency
+    ///
+    /// # Returns
+    /// The sum, or `AppError::InvalidInput` if the currencies differ
+    pub fn checked_add(&self, other: &Money) -> Result<Money, AppError> {
+        self.same_currency(other)?;
+        Ok(Money::new(self.amount + other.amount, &self.currency))
+    }
+
+    /// Subtracts an amount in the same currency
+    ///
+    /// # Returns
+    /// The difference, or `AppError::InvalidInput` if the currencies differ
+    pub fn checked_sub(&self, other: &Money) -> Result<Money, AppError> {
+        self.same_currency(other)?;
+        Ok(Money::new(self.amount - other.amount, &self.currency))
+    }
+
+    /// Multiplies the amount by a plain number, keeping the currency
+    pub fn scale(&self, factor: f64) -> Money {
+        Money::new(self.amount * factor, &self.currency)
+    }
+
+    /// Sums amounts in `currency`
+    ///
+    /// # Returns
+    /// The sum, zero when empty, or `AppError::InvalidInput` if an amount is in another
+    /// currency
+    pub fn sum<'a>(
+        currency: &str,
+        amounts: impl IntoIterator<Item = &'a Money>,
+    ) -> Result<Money, AppError> {
+        amounts
+            .into_iter()
+            .try_fold(Money::zero(currency), |total, amount| {
+                total.checked_add(amount)
+            })
+    }
+
+    /// Converts the amount into `currency` with the rates of `converter`
+    pub fn convert(
+        &self,
+        converter: &CurrencyConverter,
+        currency: &str,
+    ) -> Result<Money, AppError> {
+        let amount = converter.convert(self.amount, &self.currency, currency)?;
+        Ok(Money::new(amount, currency))
+    }
+
+    /// Converts the amount into the base currency of `converter`
+    pub fn to_base(&self, converter: &CurrencyConverter) -> Result<Money, AppError> {
+        self.convert(converter, converter.base_currency())
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), AppError> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(AppError::InvalidInput(format!(
+                "cannot combine {} and {} amounts",
+                self.currency, other.currency
+            )))
+        }
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money::new(-self.amount, &self.currency)
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.2} {}", self.amount, self.currency)
+    }
+}
+
+impl AccountTransaction {
+    /// Returns the profit or loss of the transaction in its currency
+    ///
+    /// # Returns
+    /// The amount, or `None` if it cannot be parsed
+    pub fn pnl_money(&self) -> Option<Money> {
+        parse_amount(&self.profit_and_loss).map(|amount| Money::new(amount, &self.currency))
+    }
+}
+
+impl Position {
+    /// Returns the profit or loss at the given quote in the currency of the position
+    ///
+    /// See [`Position::unrealized_pnl`].
+    pub fn unrealized_pnl_money(&self, current_bid: f64, current_offer: f64) -> Money {
+        Money::new(
+            self.unrealized_pnl(current_bid, current_offer),
+            &self.position.currency,
+        )
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::NaiveDate;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -199,6 +200,275 @@ pub fn parse_instrument_name(instrument_name: &str) -> ParsedOptionInfo {
     }
 }
 
+/// Computes a similarity score between two instrument names
+///
+/// Both names are normalized (accents removed, lowercased, punctuation stripped) and
+/// compared with an equal blend of the normalized Levenshtein similarity and the
+/// token overlap, so that reordered words still score well.
+///
+/// # Returns
+/// A score between 0.0 (nothing in common) and 1.0 (identical after normalization)
+///
+/// # Examples
+///
+/// ```
+/// use ig_client::utils::parsing::name_similarity;
+///
+/// assert_eq!(name_similarity("FTSE 100", "ftse 100"), 1.0);
+/// assert!(name_similarity("FTSE 100 daily", "FTSE 100") > name_similarity("FTSE 100 daily", "Germany 40"));
+/// ```
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let a = normalize_for_matching(a);
+    let b = normalize_for_matching(b);
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let a_chars: Vec<char> = a.chars().collect();
+    let b_chars: Vec<char> = b.chars().collect();
+    let max_len = a_chars.len().max(b_chars.len()) as f64;
+    let edit_similarity = 1.0 - levenshtein(&a_chars, &b_chars) as f64 / max_len;
+
+    let a_tokens: std::collections::HashSet<&str> = a.split_whitespace().collect();
+    let b_tokens: std::collections::HashSet<&str> = b.split_whitespace().collect();
+    let shared = a_tokens.intersection(&b_tokens).count() as f64;
+    let token_similarity = shared / a_tokens.union(&b_tokens).count() as f64;
+
+    (edit_similarity + token_similarity) / 2.0
+}
+
+fn normalize_for_matching(text: &str) -> String {
+    normalize_text(text)
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            current[j + 1] = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Parses an IG expiry string into a date
+///
+/// Supports day expiries such as `22-MAY-25` and month expiries such as `DEC-25`,
+/// which map to the first day of the month. Undated markets (`-`, `DFB`) return `None`.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use ig_client::utils::parsing::parse_expiry;
+///
+/// assert_eq!(parse_expiry("22-MAY-25"), NaiveDate::from_ymd_opt(2025, 5, 22));
+/// assert_eq!(parse_expiry("DEC-25"), NaiveDate::from_ymd_opt(2025, 12, 1));
+/// assert_eq!(parse_expiry("DFB"), None);
+/// ```
+pub fn parse_expiry(expiry: &str) -> Option<NaiveDate> {
+    let expiry = expiry.trim();
+    NaiveDate::parse_from_str(expiry, "%d-%b-%y")
+        .or_else(|_| NaiveDate::parse_from_str(&format!("01-{expiry}"), "%d-%b-%y"))
+        .ok()
+}
+
+/// Parses an amount such as `E-12.50` or `£1,234.00`, skipping the currency prefix
+///
+/// # Examples
+///
+/// ```
+/// use ig_client::utils::parsing::parse_amount;
+///
+/// assert_eq!(parse_amount("E-12.50"), Some(-12.5));
+/// assert_eq!(parse_amount("£1,234.00"), Some(1234.0));
+/// assert_eq!(parse_amount("-"), None);
+/// ```
+pub fn parse_amount(amount: &str) -> Option<f64> {
+    let number: String = amount
+        .trim_start_matches(|c: char| !(c.is_ascii_digit() || c == '-' || c == '+' || c == '.'))
+        .chars()
+        .filter(|c| *c != ',')
+        .collect();
+    number.parse().ok()
+}
+
+/// Parses the exchange rate hinted in the instrument name of a transaction
+///
+/// IG notes the rate applied to convert a transaction into the account currency in
+/// its instrument name, e.g. `Tipo de cambio 0.93` on Spanish accounts or
+/// `Exchange rate 1.0823` on English ones. A decimal comma is accepted.
+///
+/// # Examples
+///
+/// ```
+/// use ig_client::utils::parsing::parse_exchange_rate;
+///
+/// assert_eq!(parse_exchange_rate("US 500 Tipo de cambio 0.93"), Some(0.93));
+/// assert_eq!(parse_exchange_rate("Taux de change 1,0823"), Some(1.0823));
+/// assert_eq!(parse_exchange_rate("Germany 40"), None);
+/// ```
+pub fn parse_exchange_rate(instrument_name: &str) -> Option<f64> {
+    lazy_static::lazy_static! {
+        static ref EXCHANGE_RATE_PATTERN: Regex = Regex::new(
+            r"(?i)\b(?:tipo de cambio|exchange rate|fx rate|taux de change|wechselkurs|tasso di cambio)\s*:?\s*(\d+(?:[.,]\d+)?)"
+        )
+        .unwrap();
+    }
+    let normalized = normalize_text(instrument_name);
+    let rate: f64 = EXCHANGE_RATE_PATTERN
+        .captures(&normalized)?
+        .get(1)?
+        .as_str()
+        .replace(',', ".")
+        .parse()
+        .ok()?;
+    (rate > 0.0).then_some(rate)
+}
+
+/// Kind of administrative entry booked on an account instead of a deal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AdminEntry {
+    /// Charges, commissions and admin fees
+    Fee,
+    /// Overnight funding, interest and rollover adjustments
+    Funding,
+    /// Dividend adjustments
+    Dividend,
+    /// Transfers between accounts
+    Transfer,
+}
+
+/// Detects an administrative entry from the instrument name of a transaction
+///
+/// IG books fees, funding and dividend adjustments as cash transactions whose
+/// instrument name describes the entry, e.g. `Daily Admin Fee`.
+///
+/// # Examples
+///
+/// ```
+/// use ig_client::utils::parsing::{AdminEntry, parse_admin_entry};
+///
+/// assert_eq!(parse_admin_entry("Daily Admin Fee"), Some(AdminEntry::Fee));
+/// assert_eq!(parse_admin_entry("Funding Adjustment US 500"), Some(AdminEntry::Funding));
+/// assert_eq!(parse_admin_entry("Germany 40"), None);
+/// ```
+pub fn parse_admin_entry(instrument_name: &str) -> Option<AdminEntry> {
+    const KEYWORDS: [(&str, AdminEntry); 12] = [
+        ("dividend", AdminEntry::Dividend),
+        ("transfer", AdminEntry::Transfer),
+        ("funding", AdminEntry::Funding),
+        ("interest", AdminEntry::Funding),
+        ("overnight", AdminEntry::Funding),
+        ("rollover", AdminEntry::Funding),
+        ("financing", AdminEntry::Funding),
+        ("fee", AdminEntry::Fee),
+        ("commission", AdminEntry::Fee),
+        ("charge", AdminEntry::Fee),
+        ("admin", AdminEntry::Fee),
+        ("premium", AdminEntry::Fee),
+    ];
+    let name = normalize_text(instrument_name).to_lowercase();
+    let words: Vec<&str> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    KEYWORDS.iter().find_map(|(keyword, entry)| {
+        words
+            .iter()
+            .any(|word| word.starts_with(keyword))
+            .then_some(*entry)
+    })
+}
+
+/// Structure to represent the parts of an option EPIC
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ParsedOptionEpic {
+    /// Underlying code without the `OTC` prefix and series digits (e.g., "DAX")
+    ///
+    /// Daily and weekly series may keep a one letter prefix, as in "DDAX".
+    pub underlying_code: String,
+    /// Strike price of the option when encoded in the EPIC (e.g., "21100")
+    pub strike: Option<String>,
+    /// Type of the option when encoded in the EPIC: CALL or PUT
+    pub option_type: Option<String>,
+}
+
+impl ParsedOptionEpic {
+    /// Returns true if the EPIC of a non-option market refers to the same underlying
+    ///
+    /// Compares the third EPIC segment, e.g. `DAX` in `IX.D.DAX.DAILY.IP`.
+    pub fn matches_underlying_epic(&self, epic: &str) -> bool {
+        epic.split('.')
+            .nth(2)
+            .is_some_and(|code| !code.is_empty() && self.underlying_code.ends_with(code))
+    }
+}
+
+/// Parses an option EPIC such as `OP.D.OTCDAX1.021100P.IP`
+///
+/// Returns `None` for EPICs that are not options (`OP.` or `DO.` prefixes).
+///
+/// # Examples
+///
+/// ```
+/// use ig_client::utils::parsing::parse_option_epic;
+///
+/// let parsed = parse_option_epic("OP.D.OTCDAX1.021100P.IP").unwrap();
+/// assert_eq!(parsed.underlying_code, "DAX");
+/// assert_eq!(parsed.strike, Some("21100".to_string()));
+/// assert_eq!(parsed.option_type, Some("PUT".to_string()));
+/// assert!(parsed.matches_underlying_epic("IX.D.DAX.DAILY.IP"));
+///
+/// assert_eq!(parse_option_epic("IX.D.DAX.DAILY.IP"), None);
+/// ```
+pub fn parse_option_epic(epic: &str) -> Option<ParsedOptionEpic> {
+    let parts: Vec<&str> = epic.split('.').collect();
+    if parts.len() < 4 || !matches!(parts[0], "OP" | "DO") {
+        return None;
+    }
+
+    let code = parts[2].strip_prefix("OTC").unwrap_or(parts[2]);
+    let underlying_code = code.trim_end_matches(|c: char| c.is_ascii_digit());
+    if underlying_code.is_empty() {
+        return None;
+    }
+
+    let series = parts[3];
+    let option_type = match series.chars().last() {
+        Some('C') => Some("CALL".to_string()),
+        Some('P') => Some("PUT".to_string()),
+        _ => None,
+    };
+    let strike = option_type.as_ref().and_then(|_| {
+        let digits = &series[..series.len() - 1];
+        (!digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+            .then(|| digits.trim_start_matches('0').to_string())
+    });
+
+    Some(ParsedOptionEpic {
+        underlying_code: underlying_code.to_string(),
+        option_type: strike.as_ref().and(option_type),
+        strike,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,193 @@
+// src/utils/performance.rs
+//
+// Equity curve and trading statistics computed from the transaction history
+
+use crate::application::models::account::AccountTransaction;
+use crate::application::models::transaction::TransactionCategory;
+use crate::error::AppError;
+use crate::utils::parsing::parse_amount;
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// Largest fall of the equity from a peak
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drawdown {
+    /// Time of the peak
+    pub peak_time: DateTime<Utc>,
+    /// Equity at the peak
+    pub peak_equity: f64,
+    /// Time of the lowest equity after the peak
+    pub trough_time: DateTime<Utc>,
+    /// Lowest equity after the peak
+    pub trough_equity: f64,
+}
+
+impl Drawdown {
+    /// Fall of the equity, in the account currency
+    pub fn amount(&self) -> f64 {
+        self.peak_equity - self.trough_equity
+    }
+
+    /// Fall of the equity as a percentage of the peak, `None` if the peak is not positive
+    pub fn percent(&self) -> Option<f64> {
+        (self.peak_equity > 0.0).then(|| self.amount() / self.peak_equity * 100.0)
+    }
+}
+
+/// Returns the largest fall of an equity curve from a previous peak
+///
+/// # Returns
+/// The drawdown, or `None` if the equity never fell below a previous peak
+pub fn max_drawdown(curve: &[(DateTime<Utc>, f64)]) -> Option<Drawdown> {
+    let (&(mut peak_time, mut peak_equity), rest) = curve.split_first()?;
+    let mut max: Option<Drawdown> = None;
+    for &(time, equity) in rest {
+        if equity > peak_equity {
+            (peak_time, peak_equity) = (time, equity);
+        } else if peak_equity - equity > max.map_or(0.0, |max| max.amount()) {
+            max = Some(Drawdown {
+                peak_time,
+                peak_equity,
+                trough_time: time,
+                trough_equity: equity,
+            });
+        }
+    }
+    max
+}
+
+/// Trading statistics of a transaction history
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerformanceReport {
+    /// Equity after each transaction, oldest first
+    pub equity_curve: Vec<(DateTime<Utc>, f64)>,
+    /// Number of closed trades
+    pub trades: usize,
+    /// Number of trades with a profit
+    pub wins: usize,
+    /// Number of trades with a loss
+    pub losses: usize,
+    /// Sum of the profits of winning trades
+    pub gross_profit: f64,
+    /// Sum of the losses of losing trades, as a positive amount
+    pub gross_loss: f64,
+    /// Sum of fees, funding and dividends
+    pub costs: f64,
+    /// Largest profit of a trade
+    pub largest_win: f64,
+    /// Largest loss of a trade, as a positive amount
+    pub largest_loss: f64,
+    /// Largest fall of the equity curve
+    pub max_drawdown: Option<Drawdown>,
+}
+
+impl PerformanceReport {
+    /// Computes the statistics of a transaction history
+    ///
+    /// Transactions are sorted by date. Deals count as trades; fees, funding and
+    /// dividends move the equity curve without being trades; deposits, withdrawals and
+    /// transfers are left out so they do not show as gains or drawdowns.
+    ///
+    /// # Arguments
+    /// * `transactions` - Transaction history, in any order
+    /// * `starting_equity` - Equity before the first transaction
+    ///
+    /// # Returns
+    /// The report, or `AppError::InvalidInput` if a date cannot be parsed
+    pub fn from_transactions(
+        transactions: &[AccountTransaction],
+        starting_equity: f64,
+    ) -> Result<Self, AppError> {
+        let mut entries = Vec::with_capacity(transactions.len());
+        for transaction in transactions {
+            let category = TransactionCategory::of(transaction);
+            if category.is_external_flow() {
+                continue;
+            }
+            let date = NaiveDateTime::parse_from_str(&transaction.date_utc, "%Y-%m-%dT%H:%M:%S%.f")
+                .map_err(|_| {
+                    AppError::InvalidInput(format!(
+                        "invalid transaction date: {}",
+                        transaction.date_utc
+                    ))
+                })?
+                .and_utc();
+            let amount = parse_amount(&transaction.profit_and_loss).unwrap_or_default();
+            entries.push((date, category, amount));
+        }
+        entries.sort_by_key(|(date, _, _)| *date);
+
+        let mut report = Self {
+            equity_curve: Vec::with_capacity(entries.len()),
+            trades: 0,
+            wins: 0,
+            losses: 0,
+            gross_profit: 0.0,
+            gross_loss: 0.0,
+            costs: 0.0,
+            largest_win: 0.0,
+            largest_loss: 0.0,
+            max_drawdown: None,
+        };
+        let mut equity = starting_equity;
+        for (date, category, amount) in entries {
+            equity += amount;
+            report.equity_curve.push((date, equity));
+            if category != TransactionCategory::Trade {
+                report.costs += amount;
+                continue;
+            }
+            report.trades += 1;
+            if amount > 0.0 {
+                report.wins += 1;
+                report.gross_profit += amount;
+                report.largest_win = report.largest_win.max(amount);
+            } else if amount < 0.0 {
+                report.losses += 1;
+                report.gross_loss -= amount;
+                report.largest_loss = report.largest_loss.max(-amount);
+            }
+        }
+        let mut curve = Vec::with_capacity(report.equity_curve.len() + 1);
+        if let Some((first, _)) = report.equity_curve.first() {
+            curve.push((*first, starting_equity));
+        }
+        curve.extend_from_slice(&report.equity_curve);
+        report.max_drawdown = max_drawdown(&curve);
+        Ok(report)
+    }
+
+    /// Profit or loss of the trades, before costs
+    pub fn net_trading_pnl(&self) -> f64 {
+        self.gross_profit - self.gross_loss
+    }
+
+    /// Profit or loss of the trades after costs
+    pub fn net_pnl(&self) -> f64 {
+        self.net_trading_pnl() + self.costs
+    }
+
+    /// Share of winning trades, from 0 to 1, `None` without trades
+    pub fn win_rate(&self) -> Option<f64> {
+        (self.trades > 0).then(|| self.wins as f64 / self.trades as f64)
+    }
+
+    /// Gross profit divided by gross loss, `None` without losses
+    pub fn profit_factor(&self) -> Option<f64> {
+        (self.gross_loss > 0.0).then(|| self.gross_profit / self.gross_loss)
+    }
+
+    /// Average profit or loss per trade, `None` without trades
+    pub fn average_trade(&self) -> Option<f64> {
+        (self.trades > 0).then(|| self.net_trading_pnl() / self.trades as f64)
+    }
+
+    /// Average profit of winning trades, `None` without wins
+    pub fn average_win(&self) -> Option<f64> {
+        (self.wins > 0).then(|| self.gross_profit / self.wins as f64)
+    }
+
+    /// Average loss of losing trades as a positive amount, `None` without losses
+    pub fn average_loss(&self) -> Option<f64> {
+        (self.losses > 0).then(|| self.gross_loss / self.losses as f64)
+    }
+}
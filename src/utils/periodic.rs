@@ -0,0 +1,122 @@
+use crate::error::AppError;
+use crate::utils::rate_limiter::RateLimiter;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+/// Token stopping the periodic tasks it is given to
+///
+/// Clones share the same state, so the token kept by the caller stops the tasks run
+/// with its clones.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownToken {
+    state: Arc<ShutdownState>,
+}
+
+#[derive(Debug, Default)]
+struct ShutdownState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl ShutdownToken {
+    /// Creates a token not cancelled yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops the tasks once their current run is over
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+
+    /// Returns true once [`ShutdownToken::cancel`] was called
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes once the token is cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.state.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Task run on an interval until its shutdown token is cancelled
+///
+/// Failed runs are logged and retried on the next tick; a rate limit error also
+/// notifies the rate limiter set with [`PeriodicTask::with_rate_limiter`].
+pub struct PeriodicTask<'a> {
+    action: &'a str,
+    interval: Duration,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    wake: Option<&'a Notify>,
+}
+
+impl<'a> PeriodicTask<'a> {
+    /// Creates a task running every `interval`, `action` naming it in the logs
+    pub fn new(action: &'a str, interval: Duration) -> Self {
+        Self {
+            action,
+            interval,
+            rate_limiter: None,
+            wake: None,
+        }
+    }
+
+    /// Notifies `rate_limiter` when a run fails with `AppError::RateLimitExceeded`
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Starts the next run as soon as `wake` is notified, without waiting the interval
+    pub fn with_wake(mut self, wake: &'a Notify) -> Self {
+        self.wake = Some(wake);
+        self
+    }
+
+    /// Runs `tick` now and then on every interval, until `shutdown` is cancelled
+    ///
+    /// A run in progress when the token is cancelled is completed first.
+    pub async fn run<F, Fut>(self, shutdown: &ShutdownToken, mut tick: F)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<(), AppError>>,
+    {
+        info!("Starting to {} every {:?}", self.action, self.interval);
+        while !shutdown.is_cancelled() {
+            match tick().await {
+                Ok(()) => {}
+                Err(AppError::RateLimitExceeded) => {
+                    warn!("Rate limit exceeded, failed to {}", self.action);
+                    if let Some(rate_limiter) = &self.rate_limiter {
+                        rate_limiter.notify_rate_limit_exceeded().await;
+                    }
+                }
+                Err(e) => warn!("Failed to {}: {}", self.action, e),
+            }
+            let wake = async {
+                match self.wake {
+                    Some(wake) => wake.notified().await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(self.interval) => {}
+                _ = wake => {}
+                _ = shutdown.cancelled() => {}
+            }
+        }
+        info!("Shut down the task to {}", self.action);
+    }
+}
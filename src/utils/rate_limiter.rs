@@ -2,8 +2,12 @@
 // This module provides utilities to prevent hitting IG Markets API rate limits
 
 use crate::constants::{BASE_DELAY_MS, SAFETY_BUFFER_MS};
+use crate::error::AppError;
+use crate::utils::atomic_file::write_atomic;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
@@ -66,11 +70,155 @@ impl RateLimitType {
     }
 }
 
+/// Algorithm used by a [`RateLimiter`] to space requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RateLimitStrategy {
+    /// Keeps the timestamp of every request of the time window and waits once the
+    /// window is full, which matches how IG counts requests
+    #[default]
+    SlidingWindow,
+    /// Keeps a small bucket of tokens, a tenth of the limit, refilled evenly over the
+    /// time window with the rest of the limit; each request takes a token and waits
+    /// for one when the bucket is empty, spreading bursts out instead of letting them
+    /// use the whole window at once. A full bucket plus the refill of a window never
+    /// exceeds the limit, so no time window admits more requests than IG allows.
+    TokenBucket,
+}
+
+/// Requests of the time window of a sliding window, with the points they used
+#[derive(Debug, Default)]
+struct RequestHistory {
+    /// Time and points of each request, oldest first
+    requests: VecDeque<(Instant, usize)>,
+    /// Sum of the points of the requests
+    points: usize,
+}
+
+impl RequestHistory {
+    fn push(&mut self, at: Instant, points: usize) {
+        self.requests.push_back((at, points));
+        self.points += points;
+    }
+
+    /// Adds a request made at `at`, keeping the requests ordered
+    fn insert(&mut self, at: Instant, points: usize) {
+        let index = self.requests.partition_point(|(request, _)| *request <= at);
+        self.requests.insert(index, (at, points));
+        self.points += points;
+    }
+
+    /// Removes the requests made `window` or longer before `now`
+    fn expire(&mut self, now: Instant, window: Duration) {
+        while let Some(&(oldest, points)) = self.requests.front() {
+            if now.duration_since(oldest) >= window {
+                self.requests.pop_front();
+                self.points -= points;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.requests.clear();
+        self.points = 0;
+    }
+}
+
+/// Requests counted by a [`RateLimiter`], depending on its strategy
+#[derive(Debug)]
+enum LimiterState {
+    /// Requests of the time window
+    Window(RequestHistory),
+    /// Tokens left, negative when requests are waiting for a refill, and time of the
+    /// last refill, in the future during a cooldown
+    Bucket { tokens: f64, refilled_at: Instant },
+}
+
+/// Tokens held by a full bucket for `limit` requests per window
+fn bucket_capacity(limit: usize) -> usize {
+    (limit / 10).max(1)
+}
+
+/// Tokens refilled over a window for `limit` requests per window
+///
+/// The burst of a full bucket plus the refill stay within the limit. With a limit of
+/// one, the single token is refilled over the whole window, and a request needs a
+/// whole token, so a window still admits one request.
+fn bucket_refill(limit: usize) -> usize {
+    if limit <= 1 {
+        return 1;
+    }
+    limit - bucket_capacity(limit)
+}
+
+impl LimiterState {
+    fn new(strategy: RateLimitStrategy, limit: usize) -> Self {
+        match strategy {
+            RateLimitStrategy::SlidingWindow => Self::Window(RequestHistory::default()),
+            RateLimitStrategy::TokenBucket => Self::Bucket {
+                tokens: bucket_capacity(limit) as f64,
+                refilled_at: Instant::now(),
+            },
+        }
+    }
+}
+
+/// Saved state of a [`RateLimiter`], see [`RateLimiter::save_state`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimiterSnapshot {
+    /// Type of rate limit of the limiter
+    pub limit_type: RateLimitType,
+    /// Algorithm of the limiter
+    pub strategy: RateLimitStrategy,
+    /// Time of the snapshot
+    pub saved_at: DateTime<Utc>,
+    /// Requests of the time window, oldest first, with
+    /// [`RateLimitStrategy::SlidingWindow`]
+    #[serde(default)]
+    pub requests: Vec<DateTime<Utc>>,
+    /// Points used by each of `requests`, 1 for the requests missing
+    #[serde(default)]
+    pub points: Vec<usize>,
+    /// Tokens left with [`RateLimitStrategy::TokenBucket`]
+    #[serde(default)]
+    pub tokens: Option<f64>,
+    /// Time of the last refill of the bucket, in the future during a cooldown
+    #[serde(default)]
+    pub refilled_at: Option<DateTime<Utc>>,
+}
+
+/// Converts an instant of the monotonic clock to the time of day
+fn to_wall_clock(instant: Instant, now: Instant, now_utc: DateTime<Utc>) -> DateTime<Utc> {
+    if instant <= now {
+        now_utc - chrono::Duration::from_std(now - instant).unwrap_or_default()
+    } else {
+        now_utc + chrono::Duration::from_std(instant - now).unwrap_or_default()
+    }
+}
+
+/// Converts a time of day to an instant of the monotonic clock
+///
+/// Times before the start of the monotonic clock, e.g. before a reboot, become `now`;
+/// [`RateLimiter::restore`] drops or refills for them beforehand.
+fn to_instant(time: DateTime<Utc>, now: Instant, now_utc: DateTime<Utc>) -> Instant {
+    match (now_utc - time).to_std() {
+        Ok(elapsed) => now.checked_sub(elapsed).unwrap_or(now),
+        Err(_) => now + (time - now_utc).to_std().unwrap_or_default(),
+    }
+}
+
 /// Advanced rate limiter for API calls that maintains a request history
+///
+/// Requests are spaced with a sliding window by default, or with a token bucket
+/// selected by [`RateLimiter::with_strategy`].
 #[derive(Debug)]
 pub struct RateLimiter {
-    /// History of request timestamps
-    request_history: Mutex<VecDeque<Instant>>,
+    /// Requests made in the time window
+    state: Mutex<LimiterState>,
+    /// Algorithm spacing the requests
+    strategy: RateLimitStrategy,
     /// Type of rate limit to enforce
     limit_type: RateLimitType,
     /// Whether to apply a safety margin to the rate limit
@@ -80,8 +228,10 @@ pub struct RateLimiter {
 impl RateLimiter {
     /// Creates a new rate limiter with the specified limit type
     pub fn new(limit_type: RateLimitType) -> Self {
+        let strategy = RateLimitStrategy::default();
         RateLimiter {
-            request_history: Mutex::new(VecDeque::new()),
+            state: Mutex::new(LimiterState::new(strategy, limit_type.request_limit())),
+            strategy,
             limit_type,
             safety_margin: 1.0,
         }
@@ -95,10 +245,31 @@ impl RateLimiter {
     ///   (e.g., 0.8 means use 80% of the actual limit)
     pub fn with_safety_margin(&mut self, safety_margin: f64) -> Self {
         let safety_margin = safety_margin.clamp(0.1, 1.0);
-        Self {
-            request_history: Mutex::new(VecDeque::new()),
+        let limiter = Self {
+            state: Mutex::new(LimiterState::Window(RequestHistory::default())),
+            strategy: self.strategy,
             limit_type: self.limit_type,
             safety_margin,
+        };
+        limiter.reset_state()
+    }
+
+    /// Sets the algorithm spacing the requests, clearing the requests counted so far
+    ///
+    /// # Arguments
+    ///
+    /// * `strategy` - [`RateLimitStrategy::SlidingWindow`] by default
+    pub fn with_strategy(mut self, strategy: RateLimitStrategy) -> Self {
+        self.strategy = strategy;
+        self.reset_state()
+    }
+
+    /// Replaces the state with an empty one for the current strategy and limit
+    fn reset_state(self) -> Self {
+        let state = LimiterState::new(self.strategy, self.effective_limit());
+        Self {
+            state: Mutex::new(state),
+            ..self
         }
     }
 
@@ -107,63 +278,119 @@ impl RateLimiter {
         self.limit_type
     }
 
+    /// Returns the algorithm spacing the requests
+    pub fn strategy(&self) -> RateLimitStrategy {
+        self.strategy
+    }
+
     /// Returns the effective request limit (after applying safety margin)
     pub fn effective_limit(&self) -> usize {
         let raw_limit = self.limit_type.request_limit();
         (raw_limit as f64 * self.safety_margin).floor() as usize
     }
 
-    /// Removes expired requests from the history
-    async fn cleanup_history(&self, now: Instant) {
-        let mut history = self.request_history.lock().await;
-        let window_duration = Duration::from_millis(self.limit_type.time_window_ms());
+    /// Returns the time window of the limit
+    fn window(&self) -> Duration {
+        Duration::from_millis(self.limit_type.time_window_ms())
+    }
 
-        // Remove requests that are older than the time window
-        while let Some(oldest) = history.front() {
-            if now.duration_since(*oldest) >= window_duration {
-                history.pop_front();
-            } else {
-                break;
+    /// Tokens held by a full bucket
+    fn capacity(&self) -> f64 {
+        bucket_capacity(self.effective_limit()) as f64
+    }
+
+    /// Tokens added to the bucket per millisecond
+    fn refill_rate(&self) -> f64 {
+        let refill = bucket_refill(self.effective_limit());
+        refill as f64 / self.limit_type.time_window_ms().max(1) as f64
+    }
+
+    /// Removes expired requests from the history, or refills the bucket
+    fn refresh(&self, state: &mut LimiterState, now: Instant) {
+        match state {
+            // Remove requests that are older than the time window
+            LimiterState::Window(history) => history.expire(now, self.window()),
+            LimiterState::Bucket {
+                tokens,
+                refilled_at,
+            } => {
+                let elapsed_ms = now.saturating_duration_since(*refilled_at).as_secs_f64() * 1000.0;
+                *tokens = (*tokens + elapsed_ms * self.refill_rate()).min(self.capacity());
+                *refilled_at = (*refilled_at).max(now);
+            }
+        }
+    }
+
+    /// Returns the number of requests counted in `state`
+    fn request_count(&self, state: &LimiterState) -> usize {
+        match state {
+            LimiterState::Window(history) => history.points,
+            LimiterState::Bucket { tokens, .. } => {
+                (self.capacity() - tokens).ceil().max(0.0) as usize
             }
         }
     }
 
     /// Gets the current number of requests in the time window
+    ///
+    /// Points counted by [`RateLimiter::consume`] count as one request each. With
+    /// [`RateLimitStrategy::TokenBucket`], this is the number of tokens missing from
+    /// the bucket.
     pub async fn current_request_count(&self) -> usize {
-        let history = self.request_history.lock().await;
-        history.len()
+        let mut state = self.state.lock().await;
+        self.refresh(&mut state, Instant::now());
+        self.request_count(&state)
     }
 
     /// Gets the time until the next request can be made (in milliseconds)
     /// Returns 0 if a request can be made immediately
     pub async fn time_until_next_request_ms(&self) -> u64 {
         let now = Instant::now();
-        self.cleanup_history(now).await;
 
         // Use async lock to avoid blocking the thread
-        let history = self.request_history.lock().await;
+        let mut state = self.state.lock().await;
+        self.refresh(&mut state, now);
+        match &*state {
+            LimiterState::Window(history) => self.window_wait_ms(history, now),
+            // The next request needs a whole token
+            LimiterState::Bucket {
+                tokens,
+                refilled_at,
+            } => self.bucket_wait_ms(tokens - 1.0, *refilled_at, now),
+        }
+    }
+
+    /// Time to wait before the next request of a sliding window
+    fn window_wait_ms(&self, history: &RequestHistory, now: Instant) -> u64 {
         let effective_limit = self.effective_limit();
 
         // Be more conservative: leave a safety margin for concurrent requests
         // This is especially important in recursive or concurrent contexts
         let usage_threshold = effective_limit.saturating_sub(2);
 
-        if history.len() < usage_threshold {
+        if history.points < usage_threshold {
             // We're well below the limit, no need to wait
             return 0;
         }
 
         // If we're close to the limit but haven't reached it, add a small delay
         // to prevent multiple concurrent requests from exceeding the limit
-        if history.len() < effective_limit {
+        if history.points < effective_limit {
             // Add a small delay proportional to how close we are to the limit
-            let proximity_factor = (history.len() as f64) / (effective_limit as f64);
+            let proximity_factor = (history.points as f64) / (effective_limit as f64);
             return (BASE_DELAY_MS as f64 * proximity_factor * proximity_factor).round() as u64;
         }
 
-        // We're at the limit, need to wait for the oldest request to expire
-        if let Some(oldest) = history.front() {
-            let window_duration = Duration::from_millis(self.limit_type.time_window_ms());
+        // We're at the limit, need to wait for the oldest requests to expire,
+        // until they free the points used beyond the limit
+        let excess = history.points.saturating_sub(effective_limit).max(1);
+        let mut freed = 0;
+        for (oldest, points) in &history.requests {
+            freed += points;
+            if freed < excess {
+                continue;
+            }
+            let window_duration = self.window();
             let time_since_oldest = now.duration_since(*oldest);
 
             if time_since_oldest < window_duration {
@@ -172,16 +399,117 @@ impl RateLimiter {
                 // Add a buffer for extra safety
                 return wait_time.as_millis() as u64 + SAFETY_BUFFER_MS;
             }
+            break;
         }
 
         0 // Should never reach here after cleanup, but just in case
     }
 
+    /// Time to wait until the bucket holds no debt, i.e. until the requests that
+    /// already took a token may be sent
+    fn bucket_wait_ms(&self, tokens: f64, refilled_at: Instant, now: Instant) -> u64 {
+        if tokens >= 0.0 {
+            return 0;
+        }
+        let cooldown = refilled_at.saturating_duration_since(now).as_millis() as u64;
+        cooldown + (-tokens / self.refill_rate()).ceil() as u64
+    }
+
     /// Records a new request in the history
+    #[cfg(test)]
     async fn record_request(&self) {
         let now = Instant::now();
-        let mut history = self.request_history.lock().await;
-        history.push_back(now);
+        let mut state = self.state.lock().await;
+        self.take(&mut state, now, 1);
+    }
+
+    /// Counts a request made at `now` using `points` of the limit
+    fn take(&self, state: &mut LimiterState, now: Instant, points: usize) {
+        self.refresh(state, now);
+        match state {
+            LimiterState::Window(history) => history.push(now, points),
+            LimiterState::Bucket { tokens, .. } => *tokens -= points as f64,
+        }
+    }
+
+    /// Counts `points` against the limit without waiting
+    ///
+    /// For limits counted in data points rather than requests, such as the weekly
+    /// [`RateLimitType::HistoricalPrice`] allowance, call it with the points of each
+    /// response. They are only known once the response arrived, so this never waits;
+    /// the next call to [`RateLimiter::wait`] waits for the budget instead.
+    pub async fn consume(&self, points: usize) {
+        if points == 0 {
+            return;
+        }
+        let now = Instant::now();
+        let mut state = self.state.lock().await;
+        self.take(&mut state, now, points);
+
+        let used = self.request_count(&state);
+        if used >= self.effective_limit() {
+            warn!(
+                "Rate limiter ({:?}): {}/{} points used in window, limit reached",
+                self.limit_type,
+                used,
+                self.effective_limit()
+            );
+        } else {
+            debug!(
+                "Rate limiter ({:?}): {} points consumed ({}/{} used)",
+                self.limit_type,
+                points,
+                used,
+                self.effective_limit()
+            );
+        }
+    }
+
+    /// Aligns the count with the usage reported by IG
+    ///
+    /// IG reports the points of a limit used by every client of the account or API
+    /// key, e.g. in the allowance of historical price responses. When more than
+    /// counted here, the difference is counted as used now and expires when IG resets
+    /// the allowance, in `resets_in`. The count is never lowered.
+    ///
+    /// # Arguments
+    /// * `used` - Points of the limit used according to IG
+    /// * `resets_in` - Time until IG resets the allowance
+    pub async fn sync_usage(&self, used: usize, resets_in: Duration) {
+        let now = Instant::now();
+        let mut state = self.state.lock().await;
+        self.refresh(&mut state, now);
+        let counted = self.request_count(&state);
+        if used <= counted {
+            return;
+        }
+
+        let missing = used - counted;
+        match &mut *state {
+            LimiterState::Window(history) => {
+                // Expires `resets_in` from now, or now when the window is shorter
+                let at = (now + resets_in.min(self.window()))
+                    .checked_sub(self.window())
+                    .unwrap_or(now);
+                history.insert(at, missing);
+            }
+            LimiterState::Bucket { tokens, .. } => *tokens -= missing as f64,
+        }
+        debug!(
+            "Rate limiter ({:?}): {} points used according to IG, {} counted",
+            self.limit_type, used, counted
+        );
+    }
+
+    /// Time to wait before sending the request just counted in `state`
+    fn wait_ms(&self, state: &LimiterState, now: Instant) -> u64 {
+        match state {
+            LimiterState::Window(history) => self.window_wait_ms(history, now),
+            LimiterState::Bucket {
+                tokens,
+                refilled_at,
+            } => self.bucket_wait_ms(*tokens, *refilled_at, now),
+        }
     }
 
     /// Notifies the rate limiter that a rate limit error has been encountered
@@ -189,16 +517,26 @@ impl RateLimiter {
     pub async fn notify_rate_limit_exceeded(&self) {
         // Add multiple "fake" requests to the history to force a cooldown
         let now = Instant::now();
-        let mut history = self.request_history.lock().await;
+        let mut state = self.state.lock().await;
+        let limit = self.effective_limit();
 
-        // Clear the history and add enough requests to reach the limit
-        // This ensures we'll enforce a full cooldown period
-        history.clear();
+        match &mut *state {
+            LimiterState::Window(history) => {
+                // Clear the history and add enough requests to reach the limit
+                // This ensures we'll enforce a full cooldown period
+                history.clear();
 
-        // Add enough requests to reach the limit
-        let limit = self.effective_limit();
-        for _ in 0..limit {
-            history.push_back(now);
+                // Add a request using the whole limit
+                history.push(now, limit);
+            }
+            LimiterState::Bucket {
+                tokens,
+                refilled_at,
+            } => {
+                // Empty the bucket and stop refilling it for a whole window
+                *tokens = 0.0;
+                *refilled_at = now + self.window();
+            }
         }
 
         warn!(
@@ -210,19 +548,23 @@ impl RateLimiter {
     /// Waits if necessary to respect the rate limit
     /// This method is thread-safe and can be called from multiple threads concurrently
     pub async fn wait(&self) {
-        // Register the request BEFORE waiting
-        // This is crucial to prevent multiple concurrent requests from exceeding the rate limit
-        self.record_request().await;
+        let now = Instant::now();
+        let (wait_time, request_count) = {
+            let mut state = self.state.lock().await;
+            // Register the request BEFORE waiting
+            // This is crucial to prevent multiple concurrent requests from exceeding the rate limit
+            self.take(&mut state, now, 1);
 
-        // Now calculate the wait time based on the updated history
-        let wait_time = self.time_until_next_request_ms().await;
+            // Now calculate the wait time based on the updated history
+            (self.wait_ms(&state, now), self.request_count(&state))
+        };
 
         if wait_time > 0 {
             info!(
                 "Rate limiter ({:?}): waiting for {}ms ({}/{} requests used in window)",
                 self.limit_type,
                 wait_time,
-                self.current_request_count().await,
+                request_count,
                 self.effective_limit()
             );
             sleep(Duration::from_millis(wait_time)).await;
@@ -230,19 +572,149 @@ impl RateLimiter {
             debug!(
                 "Rate limiter ({:?}): no wait needed ({}/{} requests used)",
                 self.limit_type,
-                self.current_request_count().await,
+                request_count,
                 self.effective_limit()
             );
         }
     }
 
+    /// Returns the requests counted so far, to be restored by [`RateLimiter::restore`]
+    pub async fn snapshot(&self) -> RateLimiterSnapshot {
+        let now = Instant::now();
+        let now_utc = Utc::now();
+        let mut state = self.state.lock().await;
+        self.refresh(&mut state, now);
+
+        let mut snapshot = RateLimiterSnapshot {
+            limit_type: self.limit_type,
+            strategy: self.strategy,
+            saved_at: now_utc,
+            requests: Vec::new(),
+            points: Vec::new(),
+            tokens: None,
+            refilled_at: None,
+        };
+        match &*state {
+            LimiterState::Window(history) => {
+                for (request, points) in &history.requests {
+                    snapshot
+                        .requests
+                        .push(to_wall_clock(*request, now, now_utc));
+                    snapshot.points.push(*points);
+                }
+            }
+            LimiterState::Bucket {
+                tokens,
+                refilled_at,
+            } => {
+                snapshot.tokens = Some(*tokens);
+                snapshot.refilled_at = Some(to_wall_clock(*refilled_at, now, now_utc));
+            }
+        }
+        snapshot
+    }
+
+    /// Replaces the requests counted so far with those of `snapshot`
+    ///
+    /// Requests that left the time window since the snapshot are dropped and the
+    /// bucket is refilled for the time elapsed.
+    ///
+    /// # Returns
+    /// * `Err(AppError::InvalidInput)` - If the snapshot is of another limit type or
+    ///   strategy
+    pub async fn restore(&self, snapshot: &RateLimiterSnapshot) -> Result<(), AppError> {
+        if snapshot.limit_type != self.limit_type || snapshot.strategy != self.strategy {
+            return Err(AppError::InvalidInput(format!(
+                "rate limiter snapshot of {:?} ({:?}) cannot be restored into {:?} ({:?})",
+                snapshot.limit_type, snapshot.strategy, self.limit_type, self.strategy
+            )));
+        }
+        let now = Instant::now();
+        let now_utc = Utc::now();
+        let mut state = self.state.lock().await;
+        *state = match self.strategy {
+            RateLimitStrategy::SlidingWindow => {
+                // Dropped by their time of day, as older requests may predate the
+                // monotonic clock and would count as made now
+                let window = chrono::Duration::from_std(self.window()).unwrap_or_default();
+                let mut requests: Vec<(Instant, usize)> = snapshot
+                    .requests
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, request)| now_utc - **request < window)
+                    .map(|(i, request)| {
+                        let points = snapshot.points.get(i).copied().unwrap_or(1);
+                        (to_instant(*request, now, now_utc), points)
+                    })
+                    .collect();
+                requests.sort_by_key(|(request, _)| *request);
+                let mut history = RequestHistory::default();
+                for (request, points) in requests {
+                    history.push(request, points);
+                }
+                LimiterState::Window(history)
+            }
+            RateLimitStrategy::TokenBucket => {
+                let capacity = self.capacity();
+                let tokens = snapshot.tokens.unwrap_or(capacity).min(capacity);
+                let refilled_at = snapshot.refilled_at.unwrap_or(snapshot.saved_at);
+                // Refilled by the time of day elapsed, which the monotonic clock may
+                // not cover
+                let elapsed_ms = (now_utc - refilled_at).num_milliseconds().max(0) as f64;
+                LimiterState::Bucket {
+                    tokens: (tokens + elapsed_ms * self.refill_rate()).min(capacity),
+                    refilled_at: to_instant(refilled_at.max(now_utc), now, now_utc),
+                }
+            }
+        };
+        self.refresh(&mut state, now);
+        debug!(
+            "Rate limiter ({:?}): restored {} requests saved at {}",
+            self.limit_type,
+            self.request_count(&state),
+            snapshot.saved_at
+        );
+        Ok(())
+    }
+
+    /// Saves the requests counted so far to a JSON file at `path`
+    ///
+    /// Restored with [`RateLimiter::load_state`] at startup, it keeps a restarted
+    /// process from spending again a budget it already used, such as the weekly
+    /// historical price allowance.
+    pub async fn save_state(&self, path: &Path) -> Result<(), AppError> {
+        let snapshot = self.snapshot().await;
+        if let Some(directory) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(directory)?;
+        }
+        write_atomic(path, &serde_json::to_vec_pretty(&snapshot)?)?;
+        Ok(())
+    }
+
+    /// Restores the requests saved by [`RateLimiter::save_state`] at `path`
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the state was restored
+    /// * `Ok(false)` - If there is no file at `path`, e.g. on the first start
+    /// * `Err(AppError::InvalidInput)` - If the file holds the state of another limiter
+    pub async fn load_state(&self, path: &Path) -> Result<bool, AppError> {
+        let content = match std::fs::read(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        let snapshot: RateLimiterSnapshot = serde_json::from_slice(&content)?;
+        self.restore(&snapshot).await?;
+        Ok(true)
+    }
+
     /// Gets statistics about the current rate limit usage
     pub async fn get_stats(&self) -> RateLimiterStats {
         let now = Instant::now();
-        self.cleanup_history(now).await;
+        let mut state = self.state.lock().await;
+        self.refresh(&mut state, now);
 
-        let history = self.request_history.lock().await;
-        let count = history.len();
+        let count = self.request_count(&state);
         let limit = self.effective_limit();
         let usage_percent = if limit > 0 {
             (count as f64 / limit as f64) * 100.0
@@ -252,6 +724,7 @@ impl RateLimiter {
 
         RateLimiterStats {
             limit_type: self.limit_type,
+            strategy: self.strategy,
             request_count: count,
             effective_limit: limit,
             usage_percent,
@@ -264,6 +737,8 @@ impl RateLimiter {
 pub struct RateLimiterStats {
     /// Type of rate limit
     pub limit_type: RateLimitType,
+    /// Algorithm spacing the requests
+    pub strategy: RateLimitStrategy,
     /// Current number of requests in the time window
     pub request_count: usize,
     /// Effective limit (raw limit * safety margin)
@@ -286,7 +761,151 @@ impl std::fmt::Display for RateLimiterStats {
     }
 }
 
+/// Rate limiters of one account used through one application (API key)
+///
+/// IG counts the per-account limits and the historical price allowance for each
+/// account, and the per-app limits for each API key, so sessions of different accounts
+/// or keys must not share them.
+#[derive(Debug, Clone)]
+pub struct SessionRateLimiters {
+    /// Non-trading requests of the account (30 per minute)
+    pub account_non_trading: Arc<RateLimiter>,
+    /// Trading requests of the account (100 per minute)
+    pub account_trading: Arc<RateLimiter>,
+    /// Non-trading requests of the application (60 per minute)
+    pub app_non_trading: Arc<RateLimiter>,
+    /// Historical price data points of the account (10,000 per week)
+    pub historical_price: Arc<RateLimiter>,
+}
+
+impl SessionRateLimiters {
+    /// Returns the limiter of the given type, if sessions track it
+    pub fn for_type(&self, limit_type: RateLimitType) -> Option<&Arc<RateLimiter>> {
+        match limit_type {
+            RateLimitType::NonTradingAccount => Some(&self.account_non_trading),
+            RateLimitType::TradingAccount => Some(&self.account_trading),
+            RateLimitType::NonTradingApp => Some(&self.app_non_trading),
+            RateLimitType::HistoricalPrice => Some(&self.historical_price),
+            RateLimitType::OnePerSecond => None,
+        }
+    }
+}
+
+/// Limiters of the per-account limits
+#[derive(Debug, Clone)]
+struct AccountLimiters {
+    non_trading: Arc<RateLimiter>,
+    trading: Arc<RateLimiter>,
+    historical_price: Arc<RateLimiter>,
+}
+
+/// Rate limiters of every account and application used by a process
+///
+/// Limiters are created on first use and shared by every session of the same
+/// account or API key, so refreshed or switched sessions keep counting against the
+/// same limits. An empty API key is not shared: each use gets a limiter of its own.
+#[derive(Debug)]
+pub struct RateLimiterRegistry {
+    /// Limiters of the non-trading requests by API key
+    apps: std::sync::Mutex<HashMap<String, Arc<RateLimiter>>>,
+    /// Limiters by account ID
+    accounts: std::sync::Mutex<HashMap<String, AccountLimiters>>,
+    /// Safety margin of the limiters created
+    safety_margin: f64,
+    /// Algorithm of the limiters created
+    strategy: RateLimitStrategy,
+}
+
+impl Default for RateLimiterRegistry {
+    fn default() -> Self {
+        Self {
+            apps: std::sync::Mutex::new(HashMap::new()),
+            accounts: std::sync::Mutex::new(HashMap::new()),
+            safety_margin: 0.8,
+            strategy: RateLimitStrategy::default(),
+        }
+    }
+}
+
+impl RateLimiterRegistry {
+    /// Creates an empty registry using 80% of each limit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the safety margin of the limiters created from now on
+    pub fn with_safety_margin(mut self, safety_margin: f64) -> Self {
+        self.safety_margin = safety_margin;
+        self
+    }
+
+    /// Sets the algorithm of the limiters created from now on
+    pub fn with_strategy(mut self, strategy: RateLimitStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    fn create(&self, limit_type: RateLimitType) -> Arc<RateLimiter> {
+        let mut limiter = RateLimiter::new(limit_type);
+        Arc::new(
+            limiter
+                .with_safety_margin(self.safety_margin)
+                .with_strategy(self.strategy),
+        )
+    }
+
+    /// Returns the limiter of the non-trading requests of an application, used
+    /// before a session exists
+    pub fn app_non_trading(&self, api_key: &str) -> Arc<RateLimiter> {
+        if api_key.is_empty() {
+            return self.create(RateLimitType::NonTradingApp);
+        }
+        self.apps
+            .lock()
+            .unwrap()
+            .entry(api_key.to_string())
+            .or_insert_with(|| self.create(RateLimitType::NonTradingApp))
+            .clone()
+    }
+
+    /// Returns the limiters of `account_id` used through `api_key`
+    pub fn for_account(&self, api_key: &str, account_id: &str) -> SessionRateLimiters {
+        let account = self
+            .accounts
+            .lock()
+            .unwrap()
+            .entry(account_id.to_string())
+            .or_insert_with(|| AccountLimiters {
+                non_trading: self.create(RateLimitType::NonTradingAccount),
+                trading: self.create(RateLimitType::TradingAccount),
+                historical_price: self.create(RateLimitType::HistoricalPrice),
+            })
+            .clone();
+        SessionRateLimiters {
+            account_non_trading: account.non_trading,
+            account_trading: account.trading,
+            app_non_trading: self.app_non_trading(api_key),
+            historical_price: account.historical_price,
+        }
+    }
+}
+
+/// Registry of the rate limiters given to sessions by default
+pub fn rate_limiter_registry() -> Arc<RateLimiterRegistry> {
+    static INSTANCE: once_cell::sync::Lazy<Arc<RateLimiterRegistry>> =
+        once_cell::sync::Lazy::new(|| Arc::new(RateLimiterRegistry::new()));
+
+    INSTANCE.clone()
+}
+
 /// Global rate limiter for non-trading account requests (30 per minute)
+///
+/// Shared by the whole process; sessions use their own limiters instead, see
+/// [`RateLimiterRegistry`].
+#[deprecated(
+    since = "0.1.20",
+    note = "use the limiters of the session, see `IgSession::rate_limiters`"
+)]
 pub fn account_non_trading_limiter() -> Arc<RateLimiter> {
     static INSTANCE: once_cell::sync::Lazy<Arc<RateLimiter>> = once_cell::sync::Lazy::new(|| {
         let mut limiter = RateLimiter::new(RateLimitType::NonTradingAccount);
@@ -297,6 +916,13 @@ pub fn account_non_trading_limiter() -> Arc<RateLimiter> {
 }
 
 /// Global rate limiter for trading account requests (100 per minute)
+///
+/// Shared by the whole process; sessions use their own limiters instead, see
+/// [`RateLimiterRegistry`].
+#[deprecated(
+    since = "0.1.20",
+    note = "use the limiters of the session, see `IgSession::rate_limiters`"
+)]
 pub fn account_trading_limiter() -> Arc<RateLimiter> {
     static INSTANCE: once_cell::sync::Lazy<Arc<RateLimiter>> = once_cell::sync::Lazy::new(|| {
         let mut limiter = RateLimiter::new(RateLimitType::TradingAccount);
@@ -307,6 +933,13 @@ pub fn account_trading_limiter() -> Arc<RateLimiter> {
 }
 
 /// Global rate limiter for non-trading app requests (60 per minute)
+///
+/// Shared by the whole process; sessions use their own limiters instead, see
+/// [`RateLimiterRegistry`].
+#[deprecated(
+    since = "0.1.20",
+    note = "use the limiters of the session, see `IgSession::rate_limiters`"
+)]
 pub fn app_non_trading_limiter() -> Arc<RateLimiter> {
     static INSTANCE: once_cell::sync::Lazy<Arc<RateLimiter>> = once_cell::sync::Lazy::new(|| {
         let mut limiter = RateLimiter::new(RateLimitType::NonTradingApp);
@@ -317,6 +950,13 @@ pub fn app_non_trading_limiter() -> Arc<RateLimiter> {
 }
 
 /// Global rate limiter for historical price data requests (10,000 points per week)
+///
+/// Shared by the whole process; sessions use their own limiters instead, see
+/// [`RateLimiterRegistry`].
+#[deprecated(
+    since = "0.1.20",
+    note = "use the limiters of the session, see `IgSession::rate_limiters`"
+)]
 pub fn historical_price_limiter() -> Arc<RateLimiter> {
     static INSTANCE: once_cell::sync::Lazy<Arc<RateLimiter>> = once_cell::sync::Lazy::new(|| {
         let mut limiter = RateLimiter::new(RateLimitType::HistoricalPrice);
@@ -326,6 +966,16 @@ pub fn historical_price_limiter() -> Arc<RateLimiter> {
     INSTANCE.clone()
 }
 
+/// Waits on `rate_limiter`, if any
+///
+/// For the services taking an optional limiter of their own: their requests already
+/// wait on the limiters of the session, so none is needed by default.
+pub async fn wait_if_set(rate_limiter: Option<&RateLimiter>) {
+    if let Some(rate_limiter) = rate_limiter {
+        rate_limiter.wait().await;
+    }
+}
+
 /// Creates a rate limiter with the specified type
 pub fn create_rate_limiter(
     limit_type: RateLimitType,
@@ -349,7 +999,12 @@ pub fn one_per_second_limiter() -> Arc<RateLimiter> {
 }
 
 /// Default global rate limiter (uses the most conservative limit: non-trading account)
+#[deprecated(
+    since = "0.1.20",
+    note = "use the limiters of the session, see `IgSession::rate_limiters`"
+)]
 pub fn global_rate_limiter() -> Arc<RateLimiter> {
+    #[allow(deprecated)]
     account_non_trading_limiter()
 }
 
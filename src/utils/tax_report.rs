@@ -0,0 +1,249 @@
+// src/utils/tax_report.rs
+//
+// Realized profit and loss, fees and funding charges aggregated per tax year
+
+use crate::application::models::transaction::{EnrichedTransaction, TransactionCategory};
+use crate::error::AppError;
+use crate::storage::transaction_store::transaction_date;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// First day of the tax years of a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaxYearStart {
+    /// Tax years are calendar years
+    Calendar,
+    /// UK tax years, starting on 6 April
+    Uk,
+    /// Tax years starting on a custom day of the year, built by [`TaxYearStart::custom`]
+    Custom(MonthDay),
+}
+
+/// Day of the year existing every year, so never 29 February
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonthDay {
+    month: u32,
+    day: u32,
+}
+
+impl MonthDay {
+    /// Returns the month, from 1 to 12
+    pub fn month(&self) -> u32 {
+        self.month
+    }
+
+    /// Returns the day of the month
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+}
+
+impl TaxYearStart {
+    /// Tax years starting every year on `day` of `month`
+    ///
+    /// # Returns
+    /// The start, or `AppError::InvalidInput` if the day does not exist every year
+    pub fn custom(month: u32, day: u32) -> Result<Self, AppError> {
+        // 2001 is not a leap year, so 29 February is rejected
+        NaiveDate::from_ymd_opt(2001, month, day)
+            .map(|_| TaxYearStart::Custom(MonthDay { month, day }))
+            .ok_or_else(|| AppError::InvalidInput(format!("invalid tax year start: {month}-{day}")))
+    }
+
+    fn month_day(&self) -> (u32, u32) {
+        match self {
+            TaxYearStart::Calendar => (1, 1),
+            TaxYearStart::Uk => (4, 6),
+            TaxYearStart::Custom(month_day) => (month_day.month, month_day.day),
+        }
+    }
+
+    fn start_in(&self, year: i32) -> NaiveDate {
+        let (month, day) = self.month_day();
+        NaiveDate::from_ymd_opt(year, month, day).expect("tax year start validated")
+    }
+
+    /// Returns the tax year containing `date`
+    pub fn tax_year_of(&self, date: NaiveDate) -> TaxYear {
+        let this_year = self.start_in(date.year());
+        let year = if date >= this_year {
+            date.year()
+        } else {
+            date.year() - 1
+        };
+        TaxYear {
+            start: self.start_in(year),
+            end: self.start_in(year + 1),
+        }
+    }
+}
+
+/// Tax year, from its first day included to the first day of the next one excluded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaxYear {
+    /// First day of the tax year
+    pub start: NaiveDate,
+    /// First day of the next tax year
+    pub end: NaiveDate,
+}
+
+impl TaxYear {
+    /// Returns true if `date` falls within the tax year
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        date >= self.start && date < self.end
+    }
+}
+
+impl Display for TaxYear {
+    /// `2025` for calendar years, `2024/25` for years straddling two calendar years
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.start.month() == 1 && self.start.day() == 1 {
+            write!(f, "{}", self.start.year())
+        } else {
+            write!(
+                f,
+                "{}/{:02}",
+                self.start.year(),
+                (self.start.year() + 1) % 100
+            )
+        }
+    }
+}
+
+/// Transaction counted in a tax report
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxDetail {
+    /// Date of the transaction, in UTC
+    pub date: DateTime<Utc>,
+    /// Tax year of the transaction
+    pub tax_year: TaxYear,
+    /// Category the amount is reported under
+    pub category: TransactionCategory,
+    /// Name of the instrument
+    pub instrument: String,
+    /// Reference of the transaction
+    pub reference: String,
+    /// Amount, negative for losses and charges
+    pub amount: f64,
+}
+
+/// Totals of one tax year
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxYearSummary {
+    /// Tax year of the totals
+    pub tax_year: TaxYear,
+    /// Realized profit or loss of the deals
+    pub realized_pnl: f64,
+    /// Charges and commissions, negative when paid
+    pub fees: f64,
+    /// Overnight funding and interest, negative when paid
+    pub funding: f64,
+    /// Dividend adjustments
+    pub dividends: f64,
+    /// Number of deals
+    pub trades: usize,
+}
+
+impl TaxYearSummary {
+    fn new(tax_year: TaxYear) -> Self {
+        Self {
+            tax_year,
+            realized_pnl: 0.0,
+            fees: 0.0,
+            funding: 0.0,
+            dividends: 0.0,
+            trades: 0,
+        }
+    }
+
+    /// Realized profit or loss after fees, funding and dividends
+    pub fn net(&self) -> f64 {
+        self.realized_pnl + self.fees + self.funding + self.dividends
+    }
+}
+
+/// Realized results aggregated per tax year, with the transactions behind them
+///
+/// Deals count as realized profit or loss, and fees, funding and dividends are kept
+/// apart; deposits, withdrawals, transfers and unclassified entries are left out.
+/// Amounts are in the currency of the transactions, which should share one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxReport {
+    /// First day of the tax years
+    pub year_start: TaxYearStart,
+    /// Totals per tax year, oldest first
+    pub years: Vec<TaxYearSummary>,
+    /// Transactions counted, oldest first
+    pub details: Vec<TaxDetail>,
+}
+
+impl TaxReport {
+    /// Builds the report of a transaction history
+    ///
+    /// # Returns
+    /// The report, or `AppError::InvalidInput` if a date cannot be parsed
+    pub fn from_transactions(
+        transactions: &[EnrichedTransaction],
+        year_start: TaxYearStart,
+    ) -> Result<Self, AppError> {
+        let mut details = Vec::new();
+        for transaction in transactions {
+            let category = transaction.category;
+            if !matches!(
+                category,
+                TransactionCategory::Trade
+                    | TransactionCategory::Fee
+                    | TransactionCategory::Funding
+                    | TransactionCategory::Dividend
+            ) {
+                continue;
+            }
+            let date = transaction_date(&transaction.transaction)?;
+            details.push(TaxDetail {
+                date,
+                tax_year: year_start.tax_year_of(date.date_naive()),
+                category,
+                instrument: transaction.transaction.instrument_name.clone(),
+                reference: transaction.transaction.reference.clone(),
+                amount: transaction.pnl().unwrap_or_default(),
+            });
+        }
+        details.sort_by_key(|detail| detail.date);
+
+        let mut years: BTreeMap<TaxYear, TaxYearSummary> = BTreeMap::new();
+        for detail in &details {
+            let summary = years
+                .entry(detail.tax_year)
+                .or_insert_with(|| TaxYearSummary::new(detail.tax_year));
+            match detail.category {
+                TransactionCategory::Trade => {
+                    summary.realized_pnl += detail.amount;
+                    summary.trades += 1;
+                }
+                TransactionCategory::Fee => summary.fees += detail.amount,
+                TransactionCategory::Funding => summary.funding += detail.amount,
+                _ => summary.dividends += detail.amount,
+            }
+        }
+        Ok(Self {
+            year_start,
+            years: years.into_values().collect(),
+            details,
+        })
+    }
+
+    /// Returns the totals of the tax year containing `date`
+    pub fn year(&self, date: NaiveDate) -> Option<&TaxYearSummary> {
+        self.years
+            .iter()
+            .find(|summary| summary.tax_year.contains(date))
+    }
+
+    /// Returns the transactions counted in a tax year
+    pub fn details_of(&self, tax_year: TaxYear) -> impl Iterator<Item = &TaxDetail> {
+        self.details
+            .iter()
+            .filter(move |detail| detail.tax_year == tax_year)
+    }
+}
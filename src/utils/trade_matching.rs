@@ -0,0 +1,275 @@
+// src/utils/trade_matching.rs
+//
+// Pairing of opening and closing fills per instrument into round trips, first in first
+// out or last in first out
+
+use crate::application::models::order::Direction;
+use crate::application::models::transaction::{EnrichedTransaction, TransactionCategory};
+use crate::utils::parsing::parse_amount;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// Sizes below this are considered fully matched, absorbing floating-point residue
+const SIZE_EPSILON: f64 = 1e-9;
+
+/// Order in which open lots are closed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchingMethod {
+    /// The oldest open lot is closed first
+    #[default]
+    Fifo,
+    /// The most recent open lot is closed first
+    Lifo,
+}
+
+/// Execution of a size on an instrument
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    /// Time of the execution
+    pub date: DateTime<Utc>,
+    /// Instrument the fill is on; fills are only matched on the same instrument
+    pub instrument: String,
+    /// Size bought, negative when sold
+    pub size: f64,
+    /// Execution level
+    pub price: f64,
+    /// Charges paid for the fill, as a positive amount
+    pub fees: f64,
+    /// Reference of the deal
+    pub reference: String,
+}
+
+/// Opening and closing of a size on one instrument
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundTrip {
+    /// Instrument of the trade
+    pub instrument: String,
+    /// Direction of the opening fill
+    pub direction: Direction,
+    /// Size opened and closed
+    pub size: f64,
+    /// Time of the opening fill
+    pub open_date: DateTime<Utc>,
+    /// Level of the opening fill
+    pub open_price: f64,
+    /// Reference of the opening fill
+    pub open_reference: String,
+    /// Time of the closing fill
+    pub close_date: DateTime<Utc>,
+    /// Level of the closing fill
+    pub close_price: f64,
+    /// Reference of the closing fill
+    pub close_reference: String,
+    /// Profit or loss before fees: points won times the size
+    pub pnl: f64,
+    /// Share of the fees of both fills for this size
+    pub fees: f64,
+}
+
+impl RoundTrip {
+    /// Profit or loss after fees
+    pub fn net_pnl(&self) -> f64 {
+        self.pnl - self.fees
+    }
+
+    /// Time between opening and closing
+    pub fn holding_period(&self) -> Duration {
+        self.close_date - self.open_date
+    }
+}
+
+/// Size of an opening fill not closed yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenLot {
+    /// Remaining size, negative for a short
+    pub size: f64,
+    /// The opening fill; its fees are shared pro rata as the lot closes
+    pub fill: Fill,
+}
+
+/// Round trips and open lots of a fill history
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatchResult {
+    /// Closed trades, in the order they closed
+    pub round_trips: Vec<RoundTrip>,
+    /// Lots still open, per instrument, oldest first
+    pub open_lots: BTreeMap<String, Vec<OpenLot>>,
+}
+
+impl MatchResult {
+    /// Realized profit or loss after fees, per instrument
+    pub fn pnl_by_instrument(&self) -> BTreeMap<String, f64> {
+        let mut totals = BTreeMap::new();
+        for trip in &self.round_trips {
+            *totals.entry(trip.instrument.clone()).or_default() += trip.net_pnl();
+        }
+        totals
+    }
+
+    /// Realized profit or loss after fees
+    pub fn total_pnl(&self) -> f64 {
+        self.round_trips.iter().map(RoundTrip::net_pnl).sum()
+    }
+}
+
+/// Pairs fills into round trips, one instrument at a time
+///
+/// A fill in the direction of the open lots of its instrument opens a new lot; an
+/// opposite fill closes lots in the order of the [`MatchingMethod`], and opens a lot
+/// the other way with any size left. Fees are shared between round trips in proportion
+/// to the size they take from each fill.
+#[derive(Debug, Default)]
+pub struct TradeMatcher {
+    method: MatchingMethod,
+    lots: HashMap<String, VecDeque<OpenLot>>,
+}
+
+impl TradeMatcher {
+    /// Creates a matcher with no open lot
+    pub fn new(method: MatchingMethod) -> Self {
+        Self {
+            method,
+            lots: HashMap::new(),
+        }
+    }
+
+    /// Matches one fill against the open lots of its instrument
+    ///
+    /// # Returns
+    /// The round trips closed by the fill
+    pub fn push(&mut self, fill: Fill) -> Vec<RoundTrip> {
+        let mut trips = Vec::new();
+        if fill.size.abs() < SIZE_EPSILON {
+            return trips;
+        }
+        let lots = self.lots.entry(fill.instrument.clone()).or_default();
+        let mut remaining = fill.size;
+        while remaining.abs() >= SIZE_EPSILON {
+            let lot = match self.method {
+                MatchingMethod::Fifo => lots.front_mut(),
+                MatchingMethod::Lifo => lots.back_mut(),
+            };
+            let Some(lot) = lot.filter(|lot| lot.size.signum() != remaining.signum()) else {
+                break;
+            };
+            let size = remaining.abs().min(lot.size.abs());
+            let sign = lot.size.signum();
+            let open = &lot.fill;
+            trips.push(RoundTrip {
+                instrument: fill.instrument.clone(),
+                direction: if sign > 0.0 {
+                    Direction::Buy
+                } else {
+                    Direction::Sell
+                },
+                size,
+                open_date: open.date,
+                open_price: open.price,
+                open_reference: open.reference.clone(),
+                close_date: fill.date,
+                close_price: fill.price,
+                close_reference: fill.reference.clone(),
+                pnl: (fill.price - open.price) * size * sign,
+                fees: open.fees * size / open.size.abs() + fill.fees * size / fill.size.abs(),
+            });
+            lot.size -= size * sign;
+            remaining += size * sign;
+            if lot.size.abs() < SIZE_EPSILON {
+                match self.method {
+                    MatchingMethod::Fifo => lots.pop_front(),
+                    MatchingMethod::Lifo => lots.pop_back(),
+                };
+            }
+        }
+        if remaining.abs() >= SIZE_EPSILON {
+            lots.push_back(OpenLot {
+                size: remaining,
+                fill,
+            });
+        }
+        trips
+    }
+
+    /// Returns the lots still open, per instrument, oldest first
+    pub fn open_lots(&self) -> BTreeMap<String, Vec<OpenLot>> {
+        self.lots
+            .iter()
+            .filter(|(_, lots)| !lots.is_empty())
+            .map(|(instrument, lots)| (instrument.clone(), lots.iter().cloned().collect()))
+            .collect()
+    }
+}
+
+/// Matches fills in date order
+pub fn match_fills(fills: &[Fill], method: MatchingMethod) -> MatchResult {
+    let mut fills = fills.to_vec();
+    fills.sort_by_key(|fill| fill.date);
+    let mut matcher = TradeMatcher::new(method);
+    let round_trips = fills
+        .into_iter()
+        .flat_map(|fill| matcher.push(fill))
+        .collect();
+    MatchResult {
+        round_trips,
+        open_lots: matcher.open_lots(),
+    }
+}
+
+fn parse_utc(date: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .map(|date| date.and_utc())
+}
+
+/// Rebuilds the fills of IG deal transactions
+///
+/// IG reports each closed deal as one transaction carrying both levels, so every deal
+/// gives an opening fill at its open date and level and a closing fill at its date and
+/// close level. The size of the transaction is the size of the position, negative for
+/// shorts. Fees booked with the reference of a deal are added to its closing fill;
+/// other transactions, and deals whose dates, levels or size cannot be parsed, are
+/// left out.
+pub fn fills_from_transactions(transactions: &[EnrichedTransaction]) -> Vec<Fill> {
+    let mut fees: HashMap<&str, f64> = HashMap::new();
+    for transaction in transactions {
+        if transaction.category == TransactionCategory::Fee {
+            *fees
+                .entry(transaction.transaction.reference.as_str())
+                .or_default() -= transaction.pnl().unwrap_or_default();
+        }
+    }
+
+    let mut fills = Vec::new();
+    for transaction in transactions {
+        if transaction.category != TransactionCategory::Trade {
+            continue;
+        }
+        let raw = &transaction.transaction;
+        let (Some(open_date), Some(close_date), Some(open), Some(close), Some(size)) = (
+            parse_utc(&raw.open_date_utc),
+            parse_utc(&raw.date_utc),
+            parse_amount(&raw.open_level),
+            parse_amount(&raw.close_level),
+            parse_amount(&raw.size),
+        ) else {
+            continue;
+        };
+        fills.push(Fill {
+            date: open_date,
+            instrument: raw.instrument_name.clone(),
+            size,
+            price: open,
+            fees: 0.0,
+            reference: raw.reference.clone(),
+        });
+        fills.push(Fill {
+            date: close_date,
+            instrument: raw.instrument_name.clone(),
+            size: -size,
+            price: close,
+            fees: fees.remove(raw.reference.as_str()).unwrap_or_default(),
+            reference: raw.reference.clone(),
+        });
+    }
+    fills
+}
@@ -29,9 +29,7 @@ fn test_get_accounts() {
     // Test get accounts
     rt.block_on(async {
         // Wait to respect the rate limit (non-trading requests per account)
-        ig_client::utils::rate_limiter::account_non_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().account_non_trading.wait().await;
         info!("Getting accounts");
 
         let result = account_service
@@ -88,9 +86,7 @@ fn test_get_account_activity() {
     // Test get account activity
     rt.block_on(async {
         // Wait to respect the rate limit (non-trading requests per account)
-        ig_client::utils::rate_limiter::account_non_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().account_non_trading.wait().await;
         // Use a date range for the last 7 days
         use chrono::{Duration, Utc};
 
@@ -148,9 +144,7 @@ fn test_get_transaction_history() {
     // Test get transaction history
     rt.block_on(async {
         // Wait to respect the rate limit (non-trading requests per account)
-        ig_client::utils::rate_limiter::account_non_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().account_non_trading.wait().await;
         // Use a date range for the last 30 days
         use chrono::{Duration, Utc};
 
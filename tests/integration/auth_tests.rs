@@ -42,7 +42,10 @@ fn test_account_switch() {
     // Wait to respect the rate limit
     let rt = Runtime::new().expect("Failed to create runtime");
     rt.block_on(async {
-        rate_limiter::global_rate_limiter().wait().await;
+        rate_limiter::rate_limiter_registry()
+            .app_non_trading(&config.credentials.api_key)
+            .wait()
+            .await;
     });
 
     // Create test configuration and authenticator
@@ -5,4 +5,6 @@ mod market_tests;
 mod order_tests;
 mod position_tests;
 mod rate_limiter_tests;
+mod store_tests;
+mod transaction_store_tests;
 mod working_order_tests;
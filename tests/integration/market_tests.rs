@@ -1,6 +1,7 @@
 // Integration tests for market endpoints
 
 use crate::common;
+use ig_client::application::models::market::Resolution;
 use ig_client::utils::logger::setup_logger;
 use ig_client::{
     application::services::MarketService, application::services::market_service::MarketServiceImpl,
@@ -28,9 +29,7 @@ fn test_search_markets() {
     // Test search markets
     rt.block_on(async {
         // Wait to respect the rate limit (non-trading requests per application)
-        ig_client::utils::rate_limiter::app_non_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().app_non_trading.wait().await;
         // Search for a common market term
         let search_term = "Germany 40";
         info!("Searching for markets with term: {}", search_term);
@@ -79,9 +78,7 @@ fn test_get_market_details() {
     // Test get market details
     rt.block_on(async {
         // Wait to respect the rate limit (non-trading requests per application)
-        ig_client::utils::rate_limiter::app_non_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().app_non_trading.wait().await;
         // Use an open market
         let epic = "DO.D.OTCDDAX.143.IP"; // Open market provided by the user
         info!("Getting market details for: {}", epic);
@@ -137,9 +134,7 @@ fn test_get_multiple_market_details() {
     // Test get multiple market details
     rt.block_on(async {
         // Wait to respect the rate limit (non-trading requests per application)
-        ig_client::utils::rate_limiter::app_non_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().app_non_trading.wait().await;
         // Use open markets
         let epics = vec![
             "DO.D.OTCDDAX.129.IP".to_string(),
@@ -202,9 +197,7 @@ fn test_get_historical_prices() {
     // Test get historical prices
     rt.block_on(async {
         // Wait to respect the rate limit (historical price data)
-        ig_client::utils::rate_limiter::historical_price_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().historical_price.wait().await;
 
         // Calculate dates for last week (Monday to Friday)
         use chrono::{Datelike, Duration, Utc};
@@ -248,7 +241,7 @@ fn test_get_historical_prices() {
             ("DO.D.OTCDDAX.1.IP", from_date.as_str(), to_date.as_str()),
         ];
 
-        let resolution = "DAY";
+        let resolution = Resolution::Day;
         let mut success = false;
 
         for (epic, from, to) in markets_to_try {
@@ -316,9 +309,7 @@ fn test_get_market_navigation() {
     // Test get market navigation
     rt.block_on(async {
         // Wait to respect the rate limit (non-trading requests per application)
-        ig_client::utils::rate_limiter::app_non_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().app_non_trading.wait().await;
         info!("Getting top-level market navigation nodes");
 
         let result = market_service
@@ -372,9 +363,7 @@ fn test_get_market_navigation_node() {
     // Test get market navigation node
     rt.block_on(async {
         // Wait to respect the rate limit (non-trading requests per application)
-        ig_client::utils::rate_limiter::app_non_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().app_non_trading.wait().await;
         // First get the top-level nodes to find a node ID to use
         let top_level = market_service
             .get_market_navigation(&session)
@@ -1,12 +1,10 @@
 // Integration tests for order service endpoints
 
 use crate::common;
-use ig_client::application::models::order::TimeInForce;
+use ig_client::application::models::order_builder::OrderBuilder;
 use ig_client::utils::logger::setup_logger;
 use ig_client::{
-    application::models::order::{
-        ClosePositionRequest, CreateOrderRequest, Direction, UpdatePositionRequest,
-    },
+    application::models::order::{ClosePositionRequest, Direction, UpdatePositionRequest},
     application::services::OrderService,
     application::services::order_service::OrderServiceImpl,
 };
@@ -22,7 +20,7 @@ fn test_create_and_close_position() {
     let client = common::create_test_client(config.clone());
 
     // Create order service
-    let order_service = OrderServiceImpl::new(config, client);
+    let order_service = OrderServiceImpl::new(config, client).with_market_defaults();
 
     // Get a session
     let session = common::login_with_account_switch();
@@ -33,9 +31,7 @@ fn test_create_and_close_position() {
     // Test create and close position
     rt.block_on(async {
         // Wait to respect the rate limit (trading requests per account)
-        ig_client::utils::rate_limiter::account_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().account_trading.wait().await;
         info!("Creating a test position");
 
         // Get current market price to set a reasonable limit price
@@ -89,19 +85,10 @@ fn test_create_and_close_position() {
         );
 
         // Create a small test position using a limit order
-        let mut create_order = CreateOrderRequest::limit(
-            epic.to_string(),
-            Direction::Buy,
-            0.2, // Very small size to minimize risk
-            limit_price,
-        )
-        .with_reference(format!("test_{}", chrono::Utc::now().timestamp()));
-
-        // Set required fields
-        create_order.expiry = Some("JUL-25".to_string()); // Use actual expiry date for options
-        create_order.guaranteed_stop = Some(false); // Specify whether to use a guaranteed stop
-        create_order.currency_code = Some("EUR".to_string()); // Set the currency code for the order
-        create_order.time_in_force = TimeInForce::FillOrKill; // Use fill or kill
+        // Very small fill-or-kill size to minimize risk, the expiry is taken from the market
+        let create_order = OrderBuilder::limit(epic, Direction::Buy, 0.2, limit_price, "EUR")
+            .with_reference(&format!("test_{}", chrono::Utc::now().timestamp()))
+            .build();
 
         // Create the position
         let create_result = order_service.create_order(&session, &create_order).await;
@@ -177,15 +164,9 @@ fn test_create_and_close_position() {
                 );
 
                 // Close the position using a limit order
-                let close_request = ClosePositionRequest::limit(
-                    deal_id.clone(),
-                    match create_order.direction {
-                        Direction::Buy => Direction::Sell,
-                        Direction::Sell => Direction::Buy,
-                    },
-                    create_order.size,
-                    close_price,
-                );
+                let close_request = ClosePositionRequest::for_deal(&deal_id, &create_order.direction, create_order.size)
+                    .with_market(epic, "EUR")
+                    .with_level(close_price);
 
                 info!("Closing position with deal ID: {}", deal_id);
 
@@ -230,7 +211,8 @@ fn test_closed_market_serialization() {
     let client = common::create_test_client(config.clone());
 
     // Create order service
-    let order_service = OrderServiceImpl::new(config.clone(), client.clone());
+    let order_service =
+        OrderServiceImpl::new(config.clone(), client.clone()).with_market_defaults();
 
     // Get a session
     let session = common::login_with_account_switch();
@@ -241,9 +223,7 @@ fn test_closed_market_serialization() {
     // Test creating an order in a closed market
     rt.block_on(async {
         // Wait to respect the rate limit
-        ig_client::utils::rate_limiter::account_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().account_trading.wait().await;
 
         info!("Testing order creation with a known closed market");
 
@@ -251,19 +231,10 @@ fn test_closed_market_serialization() {
         let closed_epic = "DO.D.OTCDDAX.71.IP";
 
         // Create a test order for the closed market
-        let mut create_order = CreateOrderRequest::limit(
-            closed_epic.to_string(),
-            Direction::Buy,
-            0.2,   // Small size
-            100.0, // Arbitrary price
-        )
-        .with_reference(format!("test_closed_{}", chrono::Utc::now().timestamp()));
-
-        // Set required fields
-        create_order.expiry = Some("JUL-25".to_string());
-        create_order.guaranteed_stop = Some(false);
-        create_order.currency_code = Some("EUR".to_string());
-        create_order.time_in_force = ig_client::application::models::order::TimeInForce::FillOrKill;
+        // Small size at an arbitrary price
+        let create_order = OrderBuilder::limit(closed_epic, Direction::Buy, 0.2, 100.0, "EUR")
+            .with_reference(&format!("test_closed_{}", chrono::Utc::now().timestamp()))
+            .build();
 
         // Attempt to create the position (should be rejected due to closed market)
         let create_result = order_service.create_order(&session, &create_order).await;
@@ -323,7 +294,8 @@ fn test_update_position() {
     let client = common::create_test_client(config.clone());
 
     // Create order service and account service
-    let order_service = OrderServiceImpl::new(config.clone(), client.clone());
+    let order_service =
+        OrderServiceImpl::new(config.clone(), client.clone()).with_market_defaults();
     let account_service =
         ig_client::application::services::account_service::AccountServiceImpl::new(config, client);
 
@@ -336,9 +308,7 @@ fn test_update_position() {
     // Test update position
     rt.block_on(async {
         // Wait to respect the rate limit (trading requests per account)
-        ig_client::utils::rate_limiter::account_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().account_trading.wait().await;
         // First get all positions to find one to update
         use ig_client::application::services::AccountService;
 
@@ -402,21 +372,10 @@ fn test_update_position() {
                 current_price, limit_price
             );
 
-            // Create a small test position using a limit order
-            let mut create_order = CreateOrderRequest::limit(
-                epic.to_string(),
-                Direction::Buy,
-                0.1, // Very small size to minimize risk
-                limit_price,
-            )
-            .with_reference(format!("test_{}", chrono::Utc::now().timestamp()));
-
-            // Set required fields
-            create_order.expiry = Some("JUL-25".to_string()); // Use actual expiry date for options
-            create_order.guaranteed_stop = Some(false); // Specify whether to use a guaranteed stop
-            create_order.currency_code = Some("EUR".to_string()); // Set the currency code for the order
-            create_order.time_in_force =
-                ig_client::application::models::order::TimeInForce::FillOrKill; // Use fill or kill
+            // Create a small fill-or-kill test position, the expiry is taken from the market
+            let create_order = OrderBuilder::limit(epic, Direction::Buy, 0.1, limit_price, "EUR")
+                .with_reference(&format!("test_{}", chrono::Utc::now().timestamp()))
+                .build();
 
             // Create the position
             let create_result = order_service.create_order(&session, &create_order).await;
@@ -487,6 +446,7 @@ fn test_update_position() {
                         limit_level: Some(limit_level),
                         trailing_stop: Some(false),
                         trailing_stop_distance: None,
+                        trailing_stop_increment: None,
                     };
 
                     info!("Updating position with deal ID: {}", deal_id);
@@ -528,15 +488,13 @@ fn test_update_position() {
                             );
 
                             // Close the position using a limit order
-                            let close_request = ClosePositionRequest::limit(
-                                deal_id.clone(),
-                                match create_order.direction {
-                                    Direction::Buy => Direction::Sell,
-                                    Direction::Sell => Direction::Buy,
-                                },
+                            let close_request = ClosePositionRequest::for_deal(
+                                &deal_id,
+                                &create_order.direction,
                                 create_order.size,
-                                close_price,
-                            );
+                            )
+                            .with_market(epic, "EUR")
+                            .with_level(close_price);
 
                             info!("Closing position with deal ID: {}", deal_id);
 
@@ -578,15 +536,13 @@ fn test_update_position() {
                             );
 
                             // Try to close the position anyway to clean up
-                            let close_request = ClosePositionRequest::limit(
-                                deal_id.clone(),
-                                match create_order.direction {
-                                    Direction::Buy => Direction::Sell,
-                                    Direction::Sell => Direction::Buy,
-                                },
+                            let close_request = ClosePositionRequest::for_deal(
+                                &deal_id,
+                                &create_order.direction,
                                 create_order.size,
-                                close_price,
-                            );
+                            )
+                            .with_market(epic, "EUR")
+                            .with_level(close_price);
 
                             info!("Closing position with deal ID: {}", deal_id);
 
@@ -624,6 +580,7 @@ fn test_update_position() {
             limit_level: Some(limit_level),
             trailing_stop: Some(false),
             trailing_stop_distance: None,
+            trailing_stop_increment: None,
         };
 
         info!("  Setting stop level: {}", stop_level);
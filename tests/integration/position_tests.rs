@@ -27,9 +27,7 @@ fn test_get_positions() {
     // Test get positions
     rt.block_on(async {
         // Wait to respect the rate limit (non-trading requests per account)
-        ig_client::utils::rate_limiter::account_non_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().account_non_trading.wait().await;
         info!("Getting open positions");
 
         let result = account_service
@@ -101,9 +99,7 @@ fn test_get_working_orders() {
     // Test get working orders
     rt.block_on(async {
         // Wait to respect the rate limit (non-trading requests per account)
-        ig_client::utils::rate_limiter::account_non_trading_limiter()
-            .wait()
-            .await;
+        session.rate_limiters().account_non_trading.wait().await;
         info!("Getting working orders");
 
         let result = account_service
@@ -0,0 +1,133 @@
+// Integration tests for the PostgreSQL storage backend
+
+use crate::common;
+use chrono::{Duration, TimeZone, Utc};
+use ig_client::application::models::market::Resolution;
+use ig_client::storage::Store;
+use ig_client::storage::order_journal::{JournalEntry, JournalEntryKind};
+use ig_client::storage::pg_store::{PG_STORE_SCHEMA_VERSION, PgStore};
+use ig_client::storage::store::{Candle, StoreQuery, Tick};
+use ig_client::utils::logger::setup_logger;
+use serde_json::json;
+use tokio::runtime::Runtime;
+use tracing::info;
+
+#[test]
+#[ignore]
+fn test_pg_store_candles_and_ticks() {
+    setup_logger();
+    let config = common::create_test_config();
+    let rt = Runtime::new().expect("Failed to create runtime");
+
+    rt.block_on(async {
+        let store = PgStore::connect(&config)
+            .await
+            .expect("Failed to connect to the database");
+        // Migrating again applies nothing
+        assert_eq!(store.migrate().await.unwrap(), 0);
+        assert_eq!(
+            store.schema_version().await.unwrap(),
+            Some(PG_STORE_SCHEMA_VERSION)
+        );
+
+        let epic = format!("IT.{}.EPIC", std::process::id());
+        let start = Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap();
+        let candle = |minute: i64, close: f64| Candle {
+            epic: epic.clone(),
+            resolution: Resolution::Minute,
+            timestamp: start + Duration::minutes(minute),
+            open: 100.0,
+            high: 110.0,
+            low: 90.0,
+            close,
+            volume: None,
+        };
+        store
+            .put_candles(&[candle(1, 101.0), candle(0, 100.0)])
+            .await
+            .unwrap();
+        store.put_candles(&[candle(1, 105.0)]).await.unwrap();
+        // Replaying a write leaves the stored candle as it is
+        let replayed = store.put_candles(&[candle(1, 105.0)]).await.unwrap();
+        assert_eq!(replayed.unchanged, 1);
+        let candles = store
+            .query_candles(Resolution::Minute, &StoreQuery::new().for_epic(&epic))
+            .await
+            .unwrap();
+        info!("Stored candles: {:?}", candles);
+        assert_eq!(candles, [candle(0, 100.0), candle(1, 105.0)]);
+
+        let tick = Tick {
+            epic: epic.clone(),
+            timestamp: start,
+            bid: Some(18000.5),
+            offer: Some(18001.5),
+        };
+        let report = store.put_ticks(std::slice::from_ref(&tick)).await.unwrap();
+        assert_eq!(report.inserted, 1);
+        let replayed = store.put_ticks(std::slice::from_ref(&tick)).await.unwrap();
+        assert_eq!(replayed.unchanged, 1);
+        let moved = Tick {
+            offer: Some(18002.0),
+            ..tick.clone()
+        };
+        let report = store.put_ticks(std::slice::from_ref(&moved)).await.unwrap();
+        assert_eq!(report.updated, 1);
+        assert_eq!(store.get_tick(&epic, start).await.unwrap(), Some(moved));
+    });
+}
+
+#[test]
+#[ignore]
+fn test_pg_store_deals() {
+    setup_logger();
+    let config = common::create_test_config();
+    let rt = Runtime::new().expect("Failed to create runtime");
+
+    rt.block_on(async {
+        let store = PgStore::connect(&config)
+            .await
+            .expect("Failed to connect to the database");
+        assert!(store.schema_version().await.unwrap() >= Some(4));
+
+        let epic = format!("IT.{}.EPIC", std::process::id());
+        let correlation_id = format!("IT{}", std::process::id());
+        let reference = format!("REF{}", std::process::id());
+        let start = Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap();
+        let mut request = JournalEntry::new(
+            &correlation_id,
+            JournalEntryKind::Request,
+            "create_order",
+            json!({ "epic": epic, "direction": "BUY", "size": 1.0 }),
+        )
+        .with_epic(Some(&epic));
+        request.timestamp = start;
+        let mut confirmation = JournalEntry::new(
+            &correlation_id,
+            JournalEntryKind::Confirmation,
+            "create_order",
+            json!({ "dealReference": reference, "dealStatus": "ACCEPTED", "level": 100.5 }),
+        )
+        .with_deal_reference(Some(&reference));
+        confirmation.timestamp = start + Duration::seconds(1);
+        store.record_orders(&[request]).await.unwrap();
+        store.record_orders(&[confirmation]).await.unwrap();
+
+        let deals = store
+            .query_deals(
+                &StoreQuery::new()
+                    .for_epic(&epic)
+                    .between(start, start + Duration::days(1)),
+            )
+            .await
+            .unwrap();
+        info!("Stored deals: {:?}", deals);
+        assert_eq!(deals.len(), 1);
+        assert!(deals[0].is_filled());
+        assert_eq!(deals[0].level, Some(100.5));
+        assert_eq!(
+            store.get_deal(&reference).await.unwrap(),
+            Some(deals[0].clone())
+        );
+    });
+}
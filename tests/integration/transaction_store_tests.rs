@@ -0,0 +1,77 @@
+// Integration tests for the PostgreSQL transaction store
+
+use crate::common;
+use chrono::{TimeZone, Utc};
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::storage::transaction_store::{PgTransactionStore, SyncCheckpoint, TransactionStore};
+use ig_client::utils::logger::setup_logger;
+use serde_json::json;
+use tokio::runtime::Runtime;
+use tracing::info;
+
+fn transaction(reference: &str, close_level: &str) -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": "2025-07-02",
+        "dateUtc": "2025-07-02T15:00:00",
+        "openDateUtc": "2025-07-01T09:00:00",
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": "E10.00",
+        "transactionType": "DEAL",
+        "reference": reference,
+        "openLevel": "18000",
+        "closeLevel": close_level,
+        "size": "-1",
+        "currency": "E",
+        "cashTransaction": false
+    }))
+    .unwrap()
+}
+
+#[test]
+#[ignore]
+fn test_pg_transaction_store_upsert() {
+    setup_logger();
+    let config = common::create_test_config();
+    let rt = Runtime::new().expect("Failed to create runtime");
+
+    rt.block_on(async {
+        let pool = config
+            .pg_pool()
+            .await
+            .expect("Failed to connect to the database");
+        let store = PgTransactionStore::new(pool, &format!("IT{}", std::process::id()));
+        store.migrate().await.expect("Failed to migrate");
+        // Migrating again applies nothing
+        assert_eq!(store.migrate().await.unwrap(), 0);
+        assert!(store.schema_version().await.unwrap() >= Some(2));
+
+        let reference = format!("IT{}", std::process::id());
+        let first = transaction(&reference, "17990");
+        assert_eq!(store.upsert(std::slice::from_ref(&first)).await.unwrap(), 1);
+        assert_eq!(store.upsert(&[first]).await.unwrap(), 0);
+        let corrected = transaction(&reference, "17992");
+        assert_eq!(store.upsert(&[corrected]).await.unwrap(), 1);
+
+        let stored: Vec<_> = store
+            .load()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|transaction| transaction.reference == reference)
+            .collect();
+        info!("Stored transactions: {:?}", stored);
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].close_level, "17992");
+
+        let checkpoint = SyncCheckpoint::new(Utc.with_ymd_and_hms(2025, 7, 2, 15, 0, 0).unwrap());
+        store.save_checkpoint(&checkpoint).await.unwrap();
+        assert_eq!(store.checkpoint().await.unwrap(), Some(checkpoint));
+        // Other accounts keep their own checkpoint
+        let other = PgTransactionStore::new(store.pool().clone(), "OTHER_ACCOUNT");
+        assert_ne!(
+            other.checkpoint().await.unwrap(),
+            store.checkpoint().await.unwrap()
+        );
+    });
+}
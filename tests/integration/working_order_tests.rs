@@ -3,8 +3,7 @@
 use crate::common;
 use ig_client::utils::logger::setup_logger;
 use ig_client::{
-    application::models::order::Direction, application::models::order::TimeInForce,
-    application::models::working_order::CreateWorkingOrderRequest,
+    application::models::order::Direction, application::models::order_builder::WorkingOrderBuilder,
     application::services::MarketService, application::services::OrderService,
     application::services::market_service::MarketServiceImpl,
     application::services::order_service::OrderServiceImpl,
@@ -67,23 +66,13 @@ pub fn test_working_orders() {
         );
 
         // Create a working order (limit order to buy when price drops)
-        let mut working_order = CreateWorkingOrderRequest::limit(
-            epic.to_string(),
-            Direction::Buy,
-            0.1, // Very small size to minimize risk
-            limit_price,
-        )
-            .with_reference(format!("test_wo_{}", chrono::Utc::now().timestamp()));
-
-        // Use GoodTillCancelled to avoid date format issues
-        working_order.time_in_force = TimeInForce::GoodTillCancelled;
-
-        // Set required fields for the API
-        working_order.guaranteed_stop = false; // This is now a required field, not optional
-        working_order.currency_code = Some("EUR".to_string());
-
-        // Set expiry field (required by the API)
-        working_order.expiry = "DFB".to_string(); // Daily Forward Bet
+        // Very small size to minimize risk, good till cancelled to avoid date format issues
+        let working_order =
+            WorkingOrderBuilder::limit(epic, Direction::Buy, 0.1, limit_price)
+                .with_currency("EUR")
+                .with_expiry("DFB")
+                .with_reference(&format!("test_wo_{}", chrono::Utc::now().timestamp()))
+                .build();
 
         // Create the working order
         info!("Creating working order for: {}", epic);
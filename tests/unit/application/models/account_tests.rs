@@ -2,8 +2,12 @@
 
 #[cfg(test)]
 mod tests {
-    use ig_client::application::models::account::{Position, Positions};
-    use ig_client::application::models::order::Direction;
+    use ig_client::application::models::account::{
+        ActionType, Activity, DealLifecycle, DealStage, ExposureReport, Position, PositionMargin,
+        Positions, UnderlyingGroup, WorkingOrders,
+    };
+    use ig_client::application::models::order::{CreateOrderRequest, DealFilter, Direction};
+    use ig_client::utils::currency::CurrencyConverter;
 
     use std::fs;
 
@@ -217,4 +221,414 @@ mod tests {
         assert_eq!(result.limit_level, Some(70.0)); // Takes from details1
         assert_eq!(result.stop_level, Some(50.0)); // Takes from details2
     }
+
+    #[test]
+    fn test_position_unrealized_pnl() {
+        // Short 1 contract at 62.2, closed at the offer
+        let mut position = load_test_position();
+        assert!((position.unrealized_pnl(50.0, 52.2) - 10.0).abs() < 1e-9);
+
+        position.position.direction = Direction::Buy;
+        position.position.size = 2.0;
+        position.position.contract_size = 5.0;
+        assert!((position.unrealized_pnl(60.2, 61.0) + 20.0).abs() < 1e-9);
+
+        let converter = CurrencyConverter::new("GBP").with_rate("EUR", 0.5);
+        let pnl = position.unrealized_pnl_in(60.2, 61.0, &converter).unwrap();
+        assert!((pnl + 10.0).abs() < 1e-9);
+        assert!(
+            position
+                .unrealized_pnl_in(60.2, 61.0, &CurrencyConverter::new("USD"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_position_unrealized_pnl_applies_scaling_factor() {
+        // Long 1 EUR/USD contract of 100000 at 1.0850, quoted in pips
+        let mut position = load_test_position();
+        position.market.epic = "CS.D.EURUSD.CFD.IP".to_string();
+        position.market.scaling_factor = 10000;
+        position.position.direction = Direction::Buy;
+        position.position.level = 10850.0;
+        position.position.contract_size = 100000.0;
+        position.position.currency = "USD".to_string();
+
+        // Ten pips are worth 100 USD
+        assert!((position.unrealized_pnl(10860.0, 10861.0) - 100.0).abs() < 1e-9);
+        assert_eq!(position.market.price_scale(), 10000.0);
+
+        position.market.scaling_factor = 0;
+        assert_eq!(position.market.price_scale(), 1.0);
+    }
+
+    #[test]
+    fn test_position_attached_orders_update() {
+        // Short position closing at the offer of 68.2, without stop or limit
+        let mut position = load_test_position();
+
+        let update = position
+            .attached_orders_update(Some(80.0), Some(50.0))
+            .unwrap();
+        assert_eq!(update.stop_level, Some(80.0));
+        assert_eq!(update.limit_level, Some(50.0));
+
+        assert!(position.attached_orders_update(Some(60.0), None).is_err());
+        assert!(position.attached_orders_update(None, Some(70.0)).is_err());
+
+        // Levels left out keep their current value
+        position.position.stop_level = Some(90.0);
+        position.position.limit_level = Some(40.0);
+        let update = position.attached_orders_update(None, Some(45.0)).unwrap();
+        assert_eq!(update.stop_level, Some(90.0));
+        assert_eq!(update.limit_level, Some(45.0));
+    }
+
+    #[test]
+    fn test_positions_exposure_after() {
+        // Two shorts of 1 on the weekly DAX put and a long of 3 on the DAX
+        let put = load_test_position();
+        let mut dax = create_position_with_epic("IX.D.DAX.DAILY.IP", 3.0, None);
+        dax.position.direction = Direction::Buy;
+        let positions = Positions {
+            positions: vec![put.clone(), put.clone(), dax],
+        };
+        let order = |direction: Direction, size: f64| {
+            CreateOrderRequest::market(put.market.epic.clone(), direction, size, "EUR".to_string())
+        };
+
+        let on_put = DealFilter::all().with_epic(&put.market.epic);
+        let exposure = positions.exposure_after(&on_put, &order(Direction::Buy, 3.0));
+        assert_eq!(exposure.current(), -2.0);
+        assert_eq!(exposure.projected(), 1.0);
+        assert_eq!(exposure.projected_direction(), Some(Direction::Buy));
+        assert!(exposure.is_reducing());
+        assert!(exposure.flips());
+        assert_eq!(exposure.projected_notional(), Some(65.2));
+
+        let exposure = positions.exposure_after(&on_put, &order(Direction::Buy, 2.0));
+        assert_eq!(exposure.projected_direction(), None);
+
+        // The underlying aggregates the put and the DAX, whatever the filter direction
+        let on_dax = DealFilter::all()
+            .with_underlying("DAX")
+            .with_direction(Direction::Buy);
+        let exposure = positions.exposure_after(&on_dax, &order(Direction::Sell, 1.0));
+        assert_eq!(exposure.long_size, 3.0);
+        assert_eq!(exposure.short_size, 2.0);
+        assert_eq!(exposure.projected(), 0.0);
+        assert!(!exposure.flips());
+
+        // An order on another market does not change the exposure of the put
+        let other = CreateOrderRequest::market(
+            "CS.D.EURUSD.CFD.IP".to_string(),
+            Direction::Buy,
+            5.0,
+            "EUR".to_string(),
+        );
+        let exposure = positions.exposure_after(&on_put, &other);
+        assert_eq!(exposure.order_size, 0.0);
+        assert_eq!(exposure.projected(), exposure.current());
+    }
+
+    fn working_order(
+        deal_id: &str,
+        epic: &str,
+        direction: &str,
+        size: f64,
+        good_till_date_iso: Option<&str>,
+        deal_reference: Option<&str>,
+    ) -> serde_json::Value {
+        serde_json::json!({
+            "workingOrderData": {
+                "dealId": deal_id,
+                "direction": direction,
+                "epic": epic,
+                "orderSize": size,
+                "orderLevel": 18000.0,
+                "timeInForce": if good_till_date_iso.is_some() { "GOOD_TILL_DATE" } else { "GOOD_TILL_CANCELLED" },
+                "goodTillDate": null,
+                "goodTillDateISO": good_till_date_iso,
+                "createdDate": "2025/05/22 09:00:00:000",
+                "createdDateUTC": "2025-05-22T08:00:00",
+                "guaranteedStop": false,
+                "orderType": "LIMIT",
+                "stopDistance": null,
+                "limitDistance": null,
+                "currencyCode": "EUR",
+                "dma": false,
+                "limitedRiskPremium": null,
+                "dealReference": deal_reference
+            },
+            "marketData": {
+                "instrumentName": "Germany 40",
+                "exchangeId": "XETRA",
+                "expiry": "DFB",
+                "marketStatus": "TRADEABLE",
+                "epic": epic,
+                "instrumentType": "INDICES",
+                "lotSize": 1.0,
+                "high": 18100.0,
+                "low": 17900.0,
+                "percentageChange": 0.1,
+                "netChange": 10.0,
+                "bid": 18000.0,
+                "offer": 18001.0,
+                "updateTime": "09:00:00",
+                "updateTimeUTC": "08:00:00",
+                "delayTime": 0,
+                "streamingPricesAvailable": true,
+                "scalingFactor": 1
+            }
+        })
+    }
+
+    #[test]
+    fn test_working_orders_filters() {
+        use chrono::{TimeZone, Utc};
+
+        let orders: WorkingOrders = serde_json::from_value(serde_json::json!({
+            "workingOrders": [
+                working_order("DEAL1", "IX.D.DAX.DAILY.IP", "BUY", 1.0, None, Some("grid-1")),
+                working_order("DEAL2", "IX.D.DAX.DAILY.IP", "SELL", 2.0, Some("2025-06-01T12:00"), Some("grid-2")),
+                working_order("DEAL3", "IX.D.FTSE.DAILY.IP", "BUY", 3.0, Some("2025-07-01T12:00"), None),
+            ]
+        }))
+        .unwrap();
+        let deal_ids = |orders: &WorkingOrders| -> Vec<String> {
+            orders
+                .working_orders
+                .iter()
+                .map(|order| order.working_order_data.deal_id.clone())
+                .collect()
+        };
+
+        assert_eq!(
+            deal_ids(&orders.by_epic("IX.D.DAX.DAILY.IP")),
+            vec!["DEAL1", "DEAL2"]
+        );
+        assert_eq!(
+            deal_ids(&orders.by_direction(&Direction::Buy)),
+            vec!["DEAL1", "DEAL3"]
+        );
+        assert_eq!(
+            deal_ids(&orders.expiring_before(Utc.with_ymd_and_hms(2025, 6, 15, 0, 0, 0).unwrap())),
+            vec!["DEAL2"]
+        );
+        assert_eq!(
+            deal_ids(&orders.with_reference("grid-")),
+            vec!["DEAL1", "DEAL2"]
+        );
+        assert_eq!(
+            deal_ids(
+                &orders
+                    .by_epic("IX.D.DAX.DAILY.IP")
+                    .by_direction(&Direction::Sell)
+            ),
+            vec!["DEAL2"]
+        );
+        assert_eq!(orders.total_size(&Direction::Buy), 4.0);
+        assert_eq!(orders.totals_by_direction(), (4.0, 2.0));
+        assert_eq!(
+            orders.working_orders[2].working_order_data.expiry(),
+            Some(Utc.with_ymd_and_hms(2025, 7, 1, 12, 0, 0).unwrap())
+        );
+        assert_eq!(orders.working_orders[0].working_order_data.expiry(), None);
+    }
+
+    fn detailed_activity(date: &str, deal_id: &str, actions: &[(&str, &str)]) -> Activity {
+        let actions: Vec<serde_json::Value> = actions
+            .iter()
+            .map(|(action_type, affected)| {
+                serde_json::json!({ "actionType": action_type, "affectedDealId": affected })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "date": date,
+            "dealId": deal_id,
+            "epic": "IX.D.DAX.DAILY.IP",
+            "type": "POSITION",
+            "status": "ACCEPTED",
+            "details": { "actions": actions, "size": 1.0 }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_activity_details_and_actions() {
+        let summary: Activity = serde_json::from_value(serde_json::json!({
+            "date": "2025-07-01T09:00:00",
+            "type": "POSITION",
+            "details": "Position opened"
+        }))
+        .unwrap();
+        assert!(summary.details.is_none());
+        assert!(summary.actions().is_empty());
+
+        let activity = detailed_activity(
+            "2025-07-01T09:00:00",
+            "DEAL9",
+            &[("POSITION_OPENED", "DEAL1"), ("SOMETHING_NEW", "DEAL2")],
+        );
+        let actions = activity.actions();
+        assert_eq!(actions[0].action_type, ActionType::PositionOpened);
+        assert_eq!(actions[1].action_type, ActionType::Unknown);
+        assert!(activity.involves_deal("DEAL1"));
+        assert!(activity.involves_deal("DEAL9"));
+        assert!(!activity.involves_deal("DEAL3"));
+
+        let mut position = load_test_position();
+        position.position.deal_id = "DEAL1".to_string();
+        let positions = Positions {
+            positions: vec![position],
+        };
+        let orders = WorkingOrders {
+            working_orders: vec![],
+        };
+        assert!(actions[0].affected_position(&positions).is_some());
+        assert!(actions[0].affected_working_order(&orders).is_none());
+        assert!(ActionType::LimitOrderFilled.affects_working_order());
+        assert!(!ActionType::StopLimitAmended.affects_working_order());
+    }
+
+    #[test]
+    fn test_deal_lifecycle_from_activities() {
+        let activities = vec![
+            detailed_activity(
+                "2025-07-03T09:00:00",
+                "DEAL1",
+                &[("POSITION_CLOSED", "DEAL1")],
+            ),
+            detailed_activity(
+                "2025-07-01T09:00:00",
+                "DEAL1",
+                &[("POSITION_OPENED", "DEAL1")],
+            ),
+            detailed_activity(
+                "2025-07-02T09:00:00",
+                "DEAL1",
+                &[("STOP_LIMIT_AMENDED", "DEAL1")],
+            ),
+            detailed_activity(
+                "2025-07-02T10:00:00",
+                "DEAL2",
+                &[("POSITION_OPENED", "DEAL2")],
+            ),
+        ];
+
+        let life = DealLifecycle::from_activities("DEAL1", &activities);
+        let stages: Vec<DealStage> = life.events.iter().map(|event| event.stage()).collect();
+        assert_eq!(
+            stages,
+            vec![DealStage::Opened, DealStage::Amended, DealStage::Closed]
+        );
+        assert_eq!(life.opened().unwrap().date, "2025-07-01T09:00:00");
+        assert_eq!(life.amendments().count(), 1);
+        assert_eq!(life.partial_closes().count(), 0);
+        assert!(life.is_closed());
+
+        let open = DealLifecycle::from_activities("DEAL2", &activities);
+        assert_eq!(open.events.len(), 1);
+        assert!(!open.is_closed());
+    }
+
+    #[test]
+    fn test_group_by_underlying() {
+        let weekly_put = load_test_position();
+        let mut daily_call = load_test_position();
+        daily_call.market.instrument_name = "Daily Germany 40 24000 CALL (E1)".to_string();
+        daily_call.position.direction = Direction::Buy;
+        daily_call.position.size = 2.0;
+        let mut us500 = load_test_position();
+        us500.market.instrument_name = "US 500 (Mini)".to_string();
+        let positions = Positions {
+            positions: vec![weekly_put, daily_call, us500],
+        };
+        let orders: WorkingOrders = serde_json::from_value(serde_json::json!({
+            "workingOrders": [working_order("DEAL1", "IX.D.DAX.DAILY.IP", "SELL", 3.0, None, None)]
+        }))
+        .unwrap();
+
+        let groups = UnderlyingGroup::group(&positions, &orders);
+        let names: Vec<&str> = groups
+            .iter()
+            .map(|group| group.underlying.as_str())
+            .collect();
+        assert_eq!(names, vec!["Germany 40", "US 500"]);
+
+        let dax = &groups[0];
+        assert_eq!(dax.positions.len(), 2);
+        assert_eq!(dax.working_orders.len(), 1);
+        assert_eq!(dax.long_size(), 2.0);
+        assert_eq!(dax.short_size(), 1.0);
+        assert_eq!(dax.net_size(), 1.0);
+        assert_eq!(dax.pending_size(&Direction::Sell), 3.0);
+        // The put sold at 62.2 closes at the offer (68.2), the calls bought at 62.2
+        // close at the bid (62.2)
+        assert!((dax.unrealized_pnl() + 6.0).abs() < 1e-9);
+        assert_eq!(groups[1].positions.len(), 1);
+    }
+
+    #[test]
+    fn test_exposure_report_by_currency() {
+        // Mid price of the test position is 65.2
+        let eur_short = load_test_position();
+        let mut eur_long = load_test_position();
+        eur_long.position.deal_id = "EURLONG".to_string();
+        eur_long.position.direction = Direction::Buy;
+        eur_long.position.size = 3.0;
+        let mut usd_long = load_test_position();
+        usd_long.position.deal_id = "USDLONG".to_string();
+        usd_long.position.direction = Direction::Buy;
+        usd_long.position.currency = "USD".to_string();
+        let positions = Positions {
+            positions: vec![eur_short, eur_long, usd_long],
+        };
+        let converter = CurrencyConverter::new("EUR").with_rate("USD", 0.5);
+        let margins = vec![PositionMargin {
+            deal_id: "USDLONG".to_string(),
+            epic: "OP.D.OTCDAXWK.23650P.IP".to_string(),
+            margin: 10.0,
+        }];
+
+        let report = ExposureReport::new(&positions, &converter, &margins).unwrap();
+        assert_eq!(report.base_currency, "EUR");
+        assert_eq!(report.currencies[0].currency, "EUR");
+
+        let eur = report.currency("EUR").unwrap();
+        assert_eq!(eur.positions, 2);
+        assert!((eur.long_notional - 195.6).abs() < 1e-9);
+        assert!((eur.short_notional - 65.2).abs() < 1e-9);
+        assert!((eur.net_in_base - 130.4).abs() < 1e-9);
+        assert_eq!(eur.margin, 0.0);
+
+        let usd = report.currency("USD").unwrap();
+        assert!((usd.gross_in_base - 32.6).abs() < 1e-9);
+        assert_eq!(usd.margin, 10.0);
+        assert_eq!(report.total_margin(), 10.0);
+        assert!((report.concentration("USD").unwrap() - 32.6 / 293.4 * 100.0).abs() < 1e-9);
+        assert_eq!(report.concentration("GBP"), Some(0.0));
+
+        assert!(ExposureReport::new(&positions, &CurrencyConverter::new("EUR"), &[]).is_err());
+    }
+
+    #[test]
+    fn test_exposure_report_applies_scaling_factor() {
+        // Long 1 EUR/USD contract of 100000 at a mid of 1.0850, quoted in pips
+        let mut position = load_test_position();
+        position.market.scaling_factor = 10000;
+        position.market.bid = 10849.5;
+        position.market.offer = 10850.5;
+        position.position.direction = Direction::Buy;
+        position.position.contract_size = 100000.0;
+        position.position.currency = "USD".to_string();
+        let positions = Positions {
+            positions: vec![position],
+        };
+        let converter = CurrencyConverter::new("USD");
+
+        let report = ExposureReport::new(&positions, &converter, &[]).unwrap();
+        let usd = report.currency("USD").unwrap();
+        assert!((usd.long_notional - 108500.0).abs() < 1e-6);
+    }
 }
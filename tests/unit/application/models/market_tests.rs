@@ -757,4 +757,431 @@ mod tests {
         assert_eq!(parsed_json["bid"], 1.18);
         assert_eq!(parsed_json["offer"], 1.181);
     }
+
+    #[test]
+    fn test_resolution_display_and_from_str() {
+        use ig_client::application::models::market::Resolution;
+        use std::str::FromStr;
+
+        for resolution in Resolution::ALL {
+            let name = resolution.to_string();
+            assert_eq!(Resolution::from_str(&name).unwrap(), resolution);
+        }
+        assert_eq!(Resolution::Minute5.to_string(), "MINUTE_5");
+        assert_eq!(Resolution::from_str("hour_4").unwrap(), Resolution::Hour4);
+        assert!(Resolution::from_str("MINUTE_7").is_err());
+        assert_eq!(
+            serde_json::to_string(&Resolution::Minute30).unwrap(),
+            "\"MINUTE_30\""
+        );
+        assert_eq!(
+            serde_json::from_str::<Resolution>("\"WEEK\"").unwrap(),
+            Resolution::Week
+        );
+    }
+
+    #[test]
+    fn test_resolution_align() {
+        use chrono::{TimeZone, Utc};
+        use ig_client::application::models::market::Resolution;
+
+        // Wednesday 2025-01-15 10:37:42 UTC
+        let time = Utc.with_ymd_and_hms(2025, 1, 15, 10, 37, 42).unwrap();
+        assert_eq!(
+            Resolution::Minute15.align(time),
+            Utc.with_ymd_and_hms(2025, 1, 15, 10, 30, 0).unwrap()
+        );
+        assert_eq!(
+            Resolution::Hour4.align(time),
+            Utc.with_ymd_and_hms(2025, 1, 15, 8, 0, 0).unwrap()
+        );
+        assert_eq!(
+            Resolution::Day.align(time),
+            Utc.with_ymd_and_hms(2025, 1, 15, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            Resolution::Week.align(time),
+            Utc.with_ymd_and_hms(2025, 1, 13, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            Resolution::Month.align(time),
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            Resolution::Month.next_boundary(Utc.with_ymd_and_hms(2025, 12, 3, 0, 0, 0).unwrap()),
+            Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            Resolution::Minute5.next_boundary(time),
+            Utc.with_ymd_and_hms(2025, 1, 15, 10, 40, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_opening_hours_deserialization_and_is_open_at() {
+        use chrono::{FixedOffset, NaiveTime, TimeZone};
+        use ig_client::application::models::market::OpeningHours;
+
+        let hours: OpeningHours = serde_json::from_str(
+            r#"{"marketTimes": [{"openTime": "08:00", "closeTime": "16:30"}, {"openTime": "22:00", "closeTime": "02:00"}]}"#,
+        )
+        .unwrap();
+
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert!(hours.is_open_at(at(8, 0)));
+        assert!(hours.is_open_at(at(16, 29)));
+        assert!(!hours.is_open_at(at(16, 30)));
+        assert!(hours.is_open_at(at(23, 15)));
+        assert!(hours.is_open_at(at(1, 59)));
+        assert!(!hours.is_open_at(at(5, 0)));
+        assert_eq!(hours.next_open(at(17, 0)), Some(at(22, 0)));
+        assert_eq!(hours.next_open(at(23, 0)), Some(at(8, 0)));
+
+        let json = serde_json::to_value(&hours).unwrap();
+        assert_eq!(json["marketTimes"][0]["closeTime"], "16:30");
+
+        let tz = FixedOffset::east_opt(3600).unwrap();
+        let time = tz.with_ymd_and_hms(2025, 5, 22, 9, 0, 0).unwrap();
+        let details_json = r#"{
+            "instrument": {
+                "epic": "IX.D.DAX.DAILY.IP",
+                "name": "Germany 40",
+                "expiry": "DFB",
+                "contractSize": "1",
+                "valueOfOnePip": "1.00",
+                "openingHours": {"marketTimes": [{"openTime": "08:00", "closeTime": "08:30"}]},
+                "specialInfo": ["DEFAULT KNOCK OUT LEVEL DISTANCE"]
+            },
+            "snapshot": {"marketStatus": "TRADEABLE"},
+            "dealingRules": {
+                "minStepDistance": {"unit": "POINTS", "value": 1.0},
+                "minDealSize": {"unit": "POINTS", "value": 0.1},
+                "minControlledRiskStopDistance": {"unit": "POINTS", "value": 1.0},
+                "minNormalStopOrLimitDistance": {"unit": "POINTS", "value": 1.0},
+                "maxStopOrLimitDistance": {"unit": "POINTS", "value": 1.0},
+                "controlledRiskSpacing": {"unit": "POINTS", "value": 1.0},
+                "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+                "trailingStopsPreference": "NOT_AVAILABLE"
+            }
+        }"#;
+        let details: MarketDetails = serde_json::from_str(details_json).unwrap();
+        assert_eq!(details.instrument.is_open_at(&time), Some(false));
+        assert!(!details.is_open_at(&time));
+        assert_eq!(details.instrument.special_info.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_is_open_at_checks_weekday_and_special_dates() {
+        use chrono::{NaiveDate, TimeZone, Utc};
+        use ig_client::application::models::market::{Instrument, SpecialDate};
+
+        let instrument: Instrument = serde_json::from_value(serde_json::json!({
+            "epic": "CS.D.EURUSD.CFD.IP",
+            "name": "EUR/USD",
+            "expiry": "-",
+            "contractSize": "1",
+            "valueOfOnePip": "1.00",
+            "openingHours": {"marketTimes": [{"openTime": "22:00", "closeTime": "21:00"}]},
+            "specialInfo": ["Market closed on 25/12/2025 for Christmas", "Early close 31/12/25"]
+        }))
+        .unwrap();
+
+        let date = |d| NaiveDate::from_ymd_opt(2025, 12, d).unwrap();
+        assert_eq!(
+            instrument.special_dates(),
+            vec![
+                SpecialDate {
+                    date: date(25),
+                    closed: true,
+                    info: "Market closed on 25/12/2025 for Christmas".to_string(),
+                },
+                SpecialDate {
+                    date: date(31),
+                    closed: false,
+                    info: "Early close 31/12/25".to_string(),
+                },
+            ]
+        );
+
+        let at = |d, h| Utc.with_ymd_and_hms(2025, 12, d, h, 0, 0).unwrap();
+        // Friday session, and its part after midnight on Saturday
+        assert_eq!(instrument.is_open_at(&at(19, 23)), Some(true));
+        assert_eq!(instrument.is_open_at(&at(20, 1)), Some(true));
+        // Sessions opened on Saturday and Sunday
+        assert_eq!(instrument.is_open_at(&at(20, 23)), Some(false));
+        assert_eq!(instrument.is_open_at(&at(22, 1)), Some(false));
+        // Christmas closure, then the session opened on the 25th
+        assert_eq!(instrument.is_open_at(&at(24, 23)), Some(true));
+        assert_eq!(instrument.is_open_at(&at(25, 12)), Some(true));
+        assert_eq!(instrument.is_open_at(&at(25, 23)), Some(false));
+        assert_eq!(instrument.is_open_at(&at(26, 12)), Some(false));
+        assert_eq!(instrument.is_open_at(&at(31, 12)), Some(true));
+    }
+
+    #[test]
+    fn test_market_details_diff() {
+        use ig_client::application::models::market::MarketDetailsDiff;
+        use serde_json::json;
+
+        let base = json!({
+            "instrument": {
+                "epic": "IX.D.DAX.DAILY.IP",
+                "name": "Germany 40",
+                "expiry": "DFB",
+                "contractSize": "1",
+                "valueOfOnePip": "1.00",
+                "marginFactor": 5.0,
+                "marginFactorUnit": "PERCENTAGE"
+            },
+            "snapshot": {"marketStatus": "TRADEABLE", "bid": 100.0, "offer": 101.0},
+            "dealingRules": {
+                "minStepDistance": {"unit": "POINTS", "value": 1.0},
+                "minDealSize": {"unit": "POINTS", "value": 0.5},
+                "minControlledRiskStopDistance": {"unit": "POINTS", "value": 1.0},
+                "minNormalStopOrLimitDistance": {"unit": "POINTS", "value": 1.0},
+                "maxStopOrLimitDistance": {"unit": "POINTS", "value": 1.0},
+                "controlledRiskSpacing": {"unit": "POINTS", "value": 1.0},
+                "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+                "trailingStopsPreference": "NOT_AVAILABLE"
+            }
+        });
+        let old: MarketDetails = serde_json::from_value(base.clone()).unwrap();
+
+        assert!(old.diff(&old).is_empty());
+
+        let mut prices_only = base.clone();
+        prices_only["snapshot"]["bid"] = json!(102.0);
+        let diff = old.diff(&serde_json::from_value(prices_only).unwrap());
+        assert_eq!(diff.snapshot.len(), 1);
+        assert_eq!(diff.snapshot[0].field, "bid");
+        assert_eq!(diff.snapshot[0].old, json!(100.0));
+        assert!(!diff.has_structural_changes());
+
+        let mut changed = base;
+        changed["instrument"]["marginFactor"] = json!(10.0);
+        changed["dealingRules"]["minDealSize"]["value"] = json!(1.0);
+        changed["snapshot"]["marketStatus"] = json!("CLOSED");
+        let new: MarketDetails = serde_json::from_value(changed).unwrap();
+        let diff = MarketDetailsDiff::diff(&old, &new);
+        assert_eq!(diff.epic, "IX.D.DAX.DAILY.IP");
+        assert_eq!(diff.dealing_rules.len(), 1);
+        assert_eq!(diff.dealing_rules[0].field, "minDealSize");
+        assert_eq!(diff.dealing_rules[0].new["value"], json!(1.0));
+        assert_eq!(diff.margin.len(), 1);
+        assert_eq!(diff.margin[0].field, "marginFactor");
+        assert_eq!(diff.snapshot[0].field, "marketStatus");
+        assert!(diff.has_structural_changes());
+    }
+
+    #[test]
+    fn test_knockout_info() {
+        use ig_client::application::models::market::KnockoutInfo;
+        use ig_client::application::models::order::Direction;
+        use serde_json::json;
+
+        let details: MarketDetails = serde_json::from_value(json!({
+            "instrument": {
+                "epic": "KO.D.DAX.123.IP",
+                "name": "Germany 40 Turbo Long 18,000",
+                "expiry": "-",
+                "contractSize": "1",
+                "valueOfOnePip": "1.00",
+                "instrumentType": "KNOCKOUTS_INDICES",
+                "specialInfo": [
+                    "DEFAULT KNOCK OUT LEVEL DISTANCE",
+                    "Knock-out level: 18,000.5",
+                    "Knock-out premium: 1.2 points",
+                    "Minimum knock-out distance: 50"
+                ]
+            },
+            "snapshot": {"marketStatus": "TRADEABLE", "bid": 18200.5, "offer": 18201.5},
+            "dealingRules": {
+                "minStepDistance": {"unit": "POINTS", "value": 1.0},
+                "minDealSize": {"unit": "POINTS", "value": 0.5},
+                "minControlledRiskStopDistance": {"unit": "POINTS", "value": 1.0},
+                "minNormalStopOrLimitDistance": {"unit": "POINTS", "value": 1.0},
+                "maxStopOrLimitDistance": {"unit": "POINTS", "value": 1.0},
+                "controlledRiskSpacing": {"unit": "POINTS", "value": 1.0},
+                "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+                "trailingStopsPreference": "NOT_AVAILABLE"
+            }
+        }))
+        .unwrap();
+
+        assert!(details.instrument.is_knockout());
+        let info = details.instrument.knockout_info().unwrap();
+        assert_eq!(
+            info,
+            KnockoutInfo {
+                knockout_level: Some(18000.5),
+                premium: Some(1.2),
+                min_knockout_distance: Some(50.0),
+            }
+        );
+        assert_eq!(details.distance_to_knockout(&Direction::Buy), Some(200.0));
+        assert_eq!(
+            info.distance_to_knockout(17900.5, &Direction::Buy),
+            Some(-100.0)
+        );
+        assert_eq!(
+            info.distance_to_knockout(17900.5, &Direction::Sell),
+            Some(100.0)
+        );
+        assert_eq!(
+            info.distance_to_knockout_pct(20000.5, &Direction::Sell),
+            Some(-2000.0 / 20000.5 * 100.0)
+        );
+
+        assert_eq!(
+            KnockoutInfo::from_special_info(&["DEFAULT KNOCK OUT LEVEL DISTANCE".to_string()]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_validate_trailing_stop() {
+        let mut rules: DealingRules = serde_json::from_str(
+            r#"
+        {
+            "minStepDistance": {"unit": "POINTS", "value": 1.0},
+            "minDealSize": {"unit": "POINTS", "value": 0.1},
+            "minControlledRiskStopDistance": {"unit": "POINTS", "value": 10.0},
+            "minNormalStopOrLimitDistance": {"unit": "PERCENTAGE", "value": 0.1},
+            "maxStopOrLimitDistance": {"unit": "POINTS", "value": 500.0},
+            "controlledRiskSpacing": {"unit": "POINTS", "value": 0.0},
+            "marketOrderPreference": "AVAILABLE_DEFAULT_ON",
+            "trailingStopsPreference": "AVAILABLE"
+        }
+        "#,
+        )
+        .unwrap();
+
+        assert!(rules.trailing_stops_available());
+        assert_eq!(
+            rules
+                .min_normal_stop_or_limit_distance
+                .to_points(Some(20000.0)),
+            Some(20.0)
+        );
+        assert_eq!(
+            rules.min_normal_stop_or_limit_distance.to_points(None),
+            None
+        );
+
+        assert!(
+            rules
+                .validate_trailing_stop(25.0, 1.0, Some(20000.0))
+                .is_ok()
+        );
+        // Below 0.1% of the price
+        assert!(
+            rules
+                .validate_trailing_stop(15.0, 1.0, Some(20000.0))
+                .is_err()
+        );
+        // The percentage rule cannot be checked without a price
+        assert!(rules.validate_trailing_stop(15.0, 1.0, None).is_ok());
+        assert!(
+            rules
+                .validate_trailing_stop(600.0, 1.0, Some(20000.0))
+                .is_err()
+        );
+        assert!(
+            rules
+                .validate_trailing_stop(25.0, 0.5, Some(20000.0))
+                .is_err()
+        );
+        assert!(
+            rules
+                .validate_trailing_stop(25.0, 0.0, Some(20000.0))
+                .is_err()
+        );
+
+        rules.trailing_stops_preference = "NOT_AVAILABLE".to_string();
+        assert!(!rules.trailing_stops_available());
+        assert!(
+            rules
+                .validate_trailing_stop(25.0, 1.0, Some(20000.0))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_stop_limit() {
+        use ig_client::application::models::order::Direction;
+        use ig_client::application::models::working_order::CreateWorkingOrderRequest;
+        use serde_json::json;
+
+        let details: MarketDetails = serde_json::from_value(json!({
+            "instrument": {
+                "epic": "IX.D.DAX.DAILY.IP",
+                "name": "Germany 40",
+                "expiry": "DFB",
+                "contractSize": "1",
+                "valueOfOnePip": "1.00"
+            },
+            "snapshot": {"marketStatus": "TRADEABLE", "bid": 18000.0, "offer": 18001.0},
+            "dealingRules": {
+                "minStepDistance": {"unit": "POINTS", "value": 1.0},
+                "minDealSize": {"unit": "POINTS", "value": 0.5},
+                "minControlledRiskStopDistance": {"unit": "POINTS", "value": 1.0},
+                "minNormalStopOrLimitDistance": {"unit": "POINTS", "value": 10.0},
+                "maxStopOrLimitDistance": {"unit": "POINTS", "value": 50.0},
+                "controlledRiskSpacing": {"unit": "POINTS", "value": 1.0},
+                "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+                "trailingStopsPreference": "NOT_AVAILABLE"
+            }
+        }))
+        .unwrap();
+        let order = |direction: Direction, stop: f64, limit: f64| {
+            CreateWorkingOrderRequest::stop_limit(
+                "IX.D.DAX.DAILY.IP".to_string(),
+                direction,
+                1.0,
+                stop,
+                limit,
+            )
+        };
+
+        assert!(
+            details
+                .validate_stop_limit(&order(Direction::Buy, 18020.0, 18030.0))
+                .is_ok()
+        );
+        assert!(
+            details
+                .validate_stop_limit(&order(Direction::Sell, 17980.0, 17950.0))
+                .is_ok()
+        );
+        // The trigger of a buy must be above the offer, by the minimum distance
+        assert!(
+            details
+                .validate_stop_limit(&order(Direction::Buy, 17990.0, 18000.0))
+                .is_err()
+        );
+        assert!(
+            details
+                .validate_stop_limit(&order(Direction::Buy, 18005.0, 18010.0))
+                .is_err()
+        );
+        // The limit may not be further than the maximum distance from the trigger
+        assert!(
+            details
+                .validate_stop_limit(&order(Direction::Buy, 18020.0, 18100.0))
+                .is_err()
+        );
+        // The limit must leave room to fill once triggered
+        assert!(
+            details
+                .validate_stop_limit(&order(Direction::Sell, 17980.0, 17990.0))
+                .is_err()
+        );
+        let stop = CreateWorkingOrderRequest::stop(
+            "IX.D.DAX.DAILY.IP".to_string(),
+            Direction::Buy,
+            1.0,
+            18020.0,
+        );
+        assert!(details.validate_stop_limit(&stop).is_err());
+    }
 }
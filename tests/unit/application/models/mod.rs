@@ -1,5 +1,6 @@
 mod account_tests;
 mod market_tests;
+mod order_builder_tests;
 mod order_tests;
 mod transaction_tests;
 mod working_order_tests;
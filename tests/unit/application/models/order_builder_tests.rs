@@ -0,0 +1,147 @@
+use ig_client::application::models::market::DealingRules;
+use ig_client::application::models::order::{Direction, OrderType, TimeInForce};
+use ig_client::application::models::order_builder::{OrderBuilder, WorkingOrderBuilder};
+use serde_json::json;
+
+#[test]
+fn test_market_order_builder() {
+    let order = OrderBuilder::market("IX.D.DAX.DAILY.IP", Direction::Sell, 2.0, "EUR")
+        .with_stop_level(18500.0)
+        .with_stop_distance(40.0)
+        .with_limit_level(17800.0)
+        .with_reference("REF1")
+        .build();
+
+    assert_eq!(order.order_type, OrderType::Market);
+    assert_eq!(order.time_in_force, TimeInForce::ExecuteAndEliminate);
+    assert_eq!(order.level, None);
+    // The last stop wins, never a level and a distance together
+    assert_eq!(order.stop_level, None);
+    assert_eq!(order.stop_distance, Some(40.0));
+    assert_eq!(order.limit_level, Some(17800.0));
+    assert_eq!(order.limit_distance, None);
+    assert!(order.force_open);
+    assert_eq!(order.deal_reference.as_deref(), Some("REF1"));
+    assert_eq!(order.expiry, "-");
+}
+
+#[test]
+fn test_limit_order_builder() {
+    let order = OrderBuilder::limit("OP.D.OTCDAX1.021100P.IP", Direction::Buy, 0.5, 12.5, "EUR")
+        .with_expiry("JUL-25")
+        .build();
+
+    assert_eq!(order.order_type, OrderType::Limit);
+    assert_eq!(order.time_in_force, TimeInForce::FillOrKill);
+    assert_eq!(order.level, Some(12.5));
+    assert_eq!(order.expiry, "JUL-25");
+    assert!(!order.force_open);
+    assert!(!order.guaranteed_stop);
+
+    let order = OrderBuilder::limit("IX.D.DAX.DAILY.IP", Direction::Buy, 1.0, 18000.0, "EUR")
+        .execute_and_eliminate()
+        .guaranteed_stop()
+        .build();
+    assert_eq!(order.time_in_force, TimeInForce::ExecuteAndEliminate);
+    // A guaranteed stop without a stop is dropped
+    assert!(!order.guaranteed_stop);
+}
+
+#[test]
+fn test_order_builder_trailing_stop() {
+    let order = OrderBuilder::market("IX.D.DAX.DAILY.IP", Direction::Buy, 1.0, "EUR")
+        .guaranteed_stop()
+        .with_trailing_stop(30.0, 5.0)
+        .build();
+
+    assert_eq!(order.trailing_stop, Some(true));
+    assert_eq!(order.stop_distance, Some(30.0));
+    assert_eq!(order.trailing_stop_increment, Some(5.0));
+    assert!(!order.guaranteed_stop);
+
+    let order = OrderBuilder::market("IX.D.DAX.DAILY.IP", Direction::Buy, 1.0, "EUR")
+        .with_trailing_stop(30.0, 5.0)
+        .with_stop_level(17900.0)
+        .build();
+    assert_eq!(order.trailing_stop, None);
+    assert_eq!(order.stop_level, Some(17900.0));
+}
+
+#[test]
+fn test_working_order_builder() {
+    let order = WorkingOrderBuilder::stop("IX.D.DAX.DAILY.IP", Direction::Buy, 1.0, 18200.0)
+        .with_currency("EUR")
+        .with_limit_distance(100.0)
+        .build();
+
+    assert_eq!(order.order_type, OrderType::Stop);
+    assert_eq!(order.time_in_force, TimeInForce::GoodTillCancelled);
+    assert_eq!(order.good_till_date, None);
+    assert_eq!(order.limit_distance, Some(100.0));
+    assert_eq!(order.currency_code.as_deref(), Some("EUR"));
+
+    let order = WorkingOrderBuilder::limit("IX.D.DAX.DAILY.IP", Direction::Sell, 1.0, 18500.0)
+        .with_stop_level(18600.0)
+        .guaranteed_stop()
+        .good_till_date("2025/12/31 23:59:59")
+        .build();
+
+    assert_eq!(order.order_type, OrderType::Limit);
+    assert_eq!(order.time_in_force, TimeInForce::GoodTillDate);
+    assert_eq!(order.good_till_date.as_deref(), Some("2025/12/31 23:59:59"));
+    assert_eq!(order.stop_level, Some(18600.0));
+    assert!(order.guaranteed_stop);
+}
+
+#[test]
+fn test_builders_round_size_to_dealing_rules() {
+    let step = json!({ "unit": "POINTS", "value": 1.0 });
+    let rules: DealingRules = serde_json::from_value(json!({
+        "minStepDistance": step,
+        "minDealSize": { "unit": "POINTS", "value": 0.5 },
+        "minControlledRiskStopDistance": step,
+        "minNormalStopOrLimitDistance": step,
+        "maxStopOrLimitDistance": step,
+        "controlledRiskSpacing": step,
+        "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+        "trailingStopsPreference": "AVAILABLE",
+        "maxDealSize": 50.0
+    }))
+    .unwrap();
+
+    let order = OrderBuilder::market("IX.D.DAX.DAILY.IP", Direction::Buy, 1.234, "EUR")
+        .round_size_to(&rules)
+        .unwrap()
+        .build();
+    assert_eq!(order.size, 1.2);
+
+    let working_order =
+        WorkingOrderBuilder::stop("IX.D.DAX.DAILY.IP", Direction::Buy, 0.7, 18500.0)
+            .round_size_to(&rules)
+            .unwrap()
+            .build();
+    assert_eq!(working_order.size, 0.7);
+
+    let too_small = WorkingOrderBuilder::stop("IX.D.DAX.DAILY.IP", Direction::Buy, 0.1, 18500.0)
+        .round_size_to(&rules);
+    assert!(too_small.is_err());
+}
+
+#[test]
+fn test_stop_limit_working_order_builder() {
+    let order = WorkingOrderBuilder::stop_limit(
+        "IX.D.DAX.DAILY.IP",
+        Direction::Sell,
+        1.0,
+        17900.0,
+        17890.0,
+    )
+    .good_till_date("2025/12/31 23:59:59")
+    .with_stop_distance(50.0)
+    .build();
+    assert_eq!(order.order_type, OrderType::StopLimit);
+    assert_eq!(order.level, 17900.0);
+    assert_eq!(order.limit_price, Some(17890.0));
+    assert_eq!(order.time_in_force, TimeInForce::GoodTillDate);
+    assert_eq!(order.stop_distance, Some(50.0));
+}
@@ -1,6 +1,8 @@
+use ig_client::application::models::account::Position;
 use ig_client::application::models::order::{
-    ClosePositionRequest, CreateOrderRequest, CreateWorkingOrderRequest, Direction, OrderType,
-    Status, TimeInForce,
+    ClosePositionRequest, CreateOrderRequest, CreateWorkingOrderRequest, DealFilter, Direction,
+    OrderConfirmation, OrderType, PositionMode, RejectReason, Status, TimeInForce,
+    UpdatePositionRequest,
 };
 use serde::Deserialize;
 use serde_json::json;
@@ -11,23 +13,24 @@ fn test_create_order_request_market() {
     let direction = Direction::Buy;
     let size = 1.0;
 
-    let order = CreateOrderRequest::market(epic.to_string(), direction.clone(), size);
+    let order =
+        CreateOrderRequest::market(epic.to_string(), direction.clone(), size, "EUR".to_string());
 
     assert_eq!(order.epic, epic);
     assert_eq!(order.direction, direction);
     assert_eq!(order.size, size);
     assert_eq!(order.order_type, OrderType::Market);
-    assert_eq!(order.time_in_force, TimeInForce::FillOrKill);
+    assert_eq!(order.time_in_force, TimeInForce::ExecuteAndEliminate);
     assert!(order.level.is_none());
-    assert!(order.guaranteed_stop.is_none());
+    assert!(!order.guaranteed_stop);
     assert!(order.stop_level.is_none());
     assert!(order.stop_distance.is_none());
     assert!(order.limit_level.is_none());
     assert!(order.limit_distance.is_none());
-    // quote_id field no longer exists in CreateOrderRequest
-    assert!(order.currency_code.is_none());
-    assert_eq!(order.force_open, Some(true)); // Updated: force_open is now Some(true) by default
-    assert!(order.expiry.is_none());
+    assert!(order.quote_id.is_none());
+    assert_eq!(order.currency_code, "EUR");
+    assert!(!order.force_open);
+    assert_eq!(order.expiry, "-");
     assert!(order.deal_reference.is_none());
 }
 
@@ -38,7 +41,13 @@ fn test_create_order_request_limit() {
     let size = 2.0;
     let level = 1.2345;
 
-    let order = CreateOrderRequest::limit(epic.to_string(), direction.clone(), size, level);
+    let order = CreateOrderRequest::limit(
+        epic.to_string(),
+        direction.clone(),
+        size,
+        level,
+        "EUR".to_string(),
+    );
 
     assert_eq!(order.epic, epic);
     assert_eq!(order.direction, direction);
@@ -46,15 +55,15 @@ fn test_create_order_request_limit() {
     assert_eq!(order.order_type, OrderType::Limit);
     assert_eq!(order.time_in_force, TimeInForce::GoodTillCancelled);
     assert_eq!(order.level, Some(level));
-    assert!(order.guaranteed_stop.is_none());
+    assert!(!order.guaranteed_stop);
     assert!(order.stop_level.is_none());
     assert!(order.stop_distance.is_none());
     assert!(order.limit_level.is_none());
     assert!(order.limit_distance.is_none());
-    // quote_id field no longer exists in CreateOrderRequest
-    assert!(order.currency_code.is_none());
-    assert_eq!(order.force_open, Some(true)); // Updated: force_open is now Some(true) by default
-    assert!(order.expiry.is_none());
+    assert!(order.quote_id.is_none());
+    assert_eq!(order.currency_code, "EUR");
+    assert!(order.force_open);
+    assert_eq!(order.expiry, "-");
     assert!(order.deal_reference.is_none());
 }
 
@@ -65,8 +74,8 @@ fn test_create_order_request_with_stop_loss() {
     let size = 1.0;
     let stop_level = 1.2000;
 
-    let order =
-        CreateOrderRequest::market(epic.to_string(), direction, size).with_stop_loss(stop_level);
+    let order = CreateOrderRequest::market(epic.to_string(), direction, size, "EUR".to_string())
+        .with_stop_loss(stop_level);
 
     assert_eq!(order.stop_level, Some(stop_level));
 }
@@ -78,12 +87,53 @@ fn test_create_order_request_with_take_profit() {
     let size = 1.0;
     let limit_level = 1.3000;
 
-    let order =
-        CreateOrderRequest::market(epic.to_string(), direction, size).with_take_profit(limit_level);
+    let order = CreateOrderRequest::market(epic.to_string(), direction, size, "EUR".to_string())
+        .with_take_profit(limit_level);
 
     assert_eq!(order.limit_level, Some(limit_level));
 }
 
+#[test]
+fn test_create_order_request_with_trailing_stop() {
+    let order = CreateOrderRequest::market(
+        "CS.D.EURUSD.TODAY.IP".to_string(),
+        Direction::Buy,
+        1.0,
+        "EUR".to_string(),
+    )
+    .with_stop_loss(1.1000)
+    .with_trailing_stop(20.0, 5.0);
+
+    let json = serde_json::to_value(&order).unwrap();
+    assert_eq!(json["trailingStop"], true);
+    assert_eq!(json["stopDistance"], 20.0);
+    assert_eq!(json["trailingStopIncrement"], 5.0);
+    assert!(json.get("stopLevel").is_none());
+    assert_eq!(json["guaranteedStop"], false);
+}
+
+#[test]
+fn test_update_position_request_trailing_stop() {
+    let update = UpdatePositionRequest::trailing_stop(1.1000, 20.0, 5.0).with_take_profit(1.2000);
+    assert_eq!(
+        serde_json::to_value(&update).unwrap(),
+        json!({
+            "stopLevel": 1.1000,
+            "limitLevel": 1.2000,
+            "trailingStop": true,
+            "trailingStopDistance": 20.0,
+            "trailingStopIncrement": 5.0
+        })
+    );
+
+    let update = UpdatePositionRequest::levels(Some(1.1000), None);
+    assert_eq!(
+        serde_json::to_value(&update).unwrap(),
+        json!({"stopLevel": 1.1000, "trailingStop": false})
+    );
+    assert_eq!(Direction::Buy.opposite(), Direction::Sell);
+}
+
 #[test]
 fn test_create_order_request_with_reference() {
     let epic = "CS.D.EURUSD.TODAY.IP";
@@ -91,126 +141,78 @@ fn test_create_order_request_with_reference() {
     let size = 1.0;
     let reference = "test-reference-123";
 
-    let order = CreateOrderRequest::market(epic.to_string(), direction, size)
+    let order = CreateOrderRequest::market(epic.to_string(), direction, size, "EUR".to_string())
         .with_reference(reference.to_string());
 
     assert_eq!(order.deal_reference, Some(reference.to_string()));
 }
 
 #[test]
-fn test_create_order_request_sell_option_to_market() {
-    let epic = "CC.D.LCO.UME.IP".to_string();
-    let size = 1.0;
-    let expiry = Some("DEC-25".to_string());
-    let deal_reference = Some("test-deal-ref".to_string());
-    let currency_code = Some("USD".to_string());
-
-    let order = CreateOrderRequest::sell_option_to_market(
-        &epic,
-        &size,
-        &expiry,
-        &deal_reference,
-        &currency_code,
-    );
-
-    assert_eq!(order.epic, epic);
-    assert_eq!(order.direction, Direction::Sell);
-    // Check that size is rounded correctly
-    assert_eq!(order.size, 1.0); // Rounded from 1.0 * 100.0 / 100.0
-    assert_eq!(order.order_type, OrderType::Limit); // Corrected from Market to Limit
-    assert_eq!(order.time_in_force, TimeInForce::FillOrKill);
-    assert!(order.level.is_some()); // Check level is set
-    assert_eq!(order.level, Some(0.0)); // Updated: default level value is 0.0
-    assert_eq!(order.guaranteed_stop, Some(false));
-    assert!(order.stop_level.is_none());
-    assert!(order.stop_distance.is_none());
-    assert!(order.limit_level.is_none());
-    assert!(order.limit_distance.is_none());
-    assert_eq!(order.expiry, expiry);
-    assert_eq!(order.deal_reference, deal_reference);
-    assert_eq!(order.force_open, Some(true));
-    assert_eq!(order.currency_code, currency_code);
-}
-
-#[test]
-fn test_create_order_request_buy_option_to_market() {
-    let epic = "CC.D.LCO.UME.IP";
-    let size = 2.5;
-    let expiry = "DEC-25";
-    let deal_id = "test-deal-123";
-    let currency = "USD";
-
-    let request = CreateOrderRequest::buy_option_to_market(
-        &epic.to_string(),
-        &size,
-        &Some(expiry.to_string()),
-        &Some(deal_id.to_string()),
-        &Some(currency.to_string()),
-    );
+fn test_close_position_request_for_deal() {
+    let request = ClosePositionRequest::for_deal("test-deal-123", &Direction::Buy, 2.0)
+        .with_market("CS.D.EURUSD.TODAY.IP", "EUR")
+        .with_size(1.0);
+
+    assert_eq!(request.deal_id.as_deref(), Some("test-deal-123"));
+    assert_eq!(request.direction, Direction::Sell);
+    assert_eq!(request.size, 1.0);
+    assert_eq!(request.epic, "CS.D.EURUSD.TODAY.IP");
+    assert_eq!(request.currency_code, "EUR");
+    assert_eq!(request.order_type, OrderType::Market);
+    assert_eq!(request.time_in_force, TimeInForce::ExecuteAndEliminate);
+    assert!(!request.force_open);
 
-    assert_eq!(request.epic, epic);
-    assert_eq!(request.direction, Direction::Buy);
-    assert_eq!(request.size, 2.5);
-    assert_eq!(request.order_type, OrderType::Limit); // Updated: order_type is now Limit
+    let request = request.with_level(1.2345);
+    assert_eq!(request.order_type, OrderType::Limit);
     assert_eq!(request.time_in_force, TimeInForce::FillOrKill);
-    assert_eq!(request.expiry, Some(expiry.to_string()));
-    assert_eq!(request.deal_reference, Some(deal_id.to_string()));
-    assert_eq!(request.currency_code, Some(currency.to_string()));
+    assert_eq!(request.level, Some(1.2345));
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_close_position_request_market() {
     let deal_id = "test-deal-123";
     let direction = Direction::Buy;
     let size = 1.0;
 
-    let request = ClosePositionRequest::market(deal_id.to_string(), direction.clone(), size);
+    let request = ClosePositionRequest::market(
+        deal_id.to_string(),
+        direction.clone(),
+        size,
+        "CS.D.EURUSD.TODAY.IP".to_string(),
+        "EUR".to_string(),
+    );
 
     assert_eq!(request.deal_id, Some(deal_id.to_string()));
     assert_eq!(request.direction, direction);
     assert_eq!(request.size, size);
     assert_eq!(request.order_type, OrderType::Market);
-    // time_in_force is now an enum, not an Option<TimeInForce>
+    assert_eq!(request.time_in_force, TimeInForce::ExecuteAndEliminate);
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_close_position_request_limit() {
     let deal_id = "test-deal-123";
     let direction = Direction::Sell;
     let size = 2.0;
     let level = 1.2345;
 
-    let request = ClosePositionRequest::limit(deal_id.to_string(), direction.clone(), size, level);
-
-    assert_eq!(request.deal_id, Some(deal_id.to_string()));
-    assert_eq!(request.direction, direction);
-    assert_eq!(request.size, size);
-    assert_eq!(request.order_type, OrderType::Limit);
-    assert_eq!(request.time_in_force, TimeInForce::FillOrKill); // Updated: time_in_force is now FillOrKill
-    assert_eq!(request.level, Some(level));
-}
-
-#[test]
-fn test_close_position_request_close_option_to_market_by_epic() {
-    let epic = "CC.D.LCO.UME.IP";
-    let direction = Direction::Sell;
-    let size = 1.0;
-    let expiry = "DEC-25";
-
-    let request = ClosePositionRequest::close_option_to_market_by_epic(
-        epic.to_string(),
-        expiry.to_string(),
+    let request = ClosePositionRequest::limit(
+        deal_id.to_string(),
         direction.clone(),
         size,
+        level,
+        "CS.D.EURUSD.TODAY.IP".to_string(),
+        "EUR".to_string(),
     );
 
-    assert_eq!(request.epic, Some(epic.to_string()));
-    assert_eq!(request.expiry, Some(expiry.to_string()));
+    assert_eq!(request.deal_id, Some(deal_id.to_string()));
     assert_eq!(request.direction, direction);
     assert_eq!(request.size, size);
-    assert_eq!(request.order_type, OrderType::Limit); // Updated: order_type is now Limit
-    assert!(request.deal_id.is_none());
-    // time_in_force is now an enum, not an Option<TimeInForce>
+    assert_eq!(request.order_type, OrderType::Limit);
+    assert_eq!(request.time_in_force, TimeInForce::FillOrKill);
+    assert_eq!(request.level, Some(level));
 }
 
 #[test]
@@ -354,3 +356,108 @@ fn test_deserialize_nullable_status() {
     let result: TestStatus = serde_json::from_value(json_with_null).unwrap();
     assert_eq!(result.status, Status::Rejected);
 }
+
+#[test]
+fn test_deal_filter_matches() {
+    assert!(DealFilter::all().matches("CS.D.EURUSD.TODAY.IP", &Direction::Sell));
+
+    let dax = DealFilter::all().with_underlying("DAX");
+    assert!(dax.matches("IX.D.DAX.DAILY.IP", &Direction::Buy));
+    assert!(dax.matches("OP.D.OTCDAX1.021100P.IP", &Direction::Sell));
+    assert!(dax.matches("DO.D.OTCDDAX.1.IP", &Direction::Sell));
+    assert!(dax.matches("OP.D.OTCDAXWK.23650P.IP", &Direction::Sell));
+    assert!(!dax.matches("CS.D.EURUSD.TODAY.IP", &Direction::Buy));
+    assert!(!dax.matches("IX.D.MDAX.DAILY.IP", &Direction::Buy));
+    assert!(!dax.matches("OP.D.OTCTECDAX.5000C.IP", &Direction::Buy));
+    let ftse = DealFilter::all().with_underlying("FTSE");
+    assert!(ftse.matches("IX.D.FTSE.DAILY.IP", &Direction::Buy));
+    assert!(!ftse.matches("IX.D.FTSE250.DAILY.IP", &Direction::Buy));
+
+    let filter = DealFilter::all()
+        .with_epic("IX.D.DAX.DAILY.IP")
+        .with_direction(Direction::Buy);
+    assert!(filter.matches("IX.D.DAX.DAILY.IP", &Direction::Buy));
+    assert!(!filter.matches("IX.D.DAX.DAILY.IP", &Direction::Sell));
+    assert!(!filter.matches("IX.D.FTSE.DAILY.IP", &Direction::Buy));
+}
+
+#[test]
+fn test_reject_reason() {
+    assert_eq!(
+        RejectReason::from_code("MARKET_CLOSED"),
+        RejectReason::MarketClosed
+    );
+    assert_eq!(
+        RejectReason::from_code("PARTIALY_CLOSED_POSITION_NOT_DELETED"),
+        RejectReason::PartiallyClosedPositionNotDeleted
+    );
+    assert_eq!(
+        RejectReason::from_code("SOMETHING_NEW"),
+        RejectReason::Other
+    );
+    assert!(RejectReason::LevelToleranceError.is_retryable());
+    assert!(!RejectReason::InsufficientFunds.is_retryable());
+
+    let confirmation = |deal_status: &str, reason: &str| -> OrderConfirmation {
+        serde_json::from_value(json!({
+            "date": "2025-05-22T09:00:00.000",
+            "status": null,
+            "reason": reason,
+            "dealStatus": deal_status,
+            "dealReference": "REF1"
+        }))
+        .unwrap()
+    };
+    assert_eq!(
+        confirmation("REJECTED", "ATTACHED_ORDER_LEVEL_ERROR").reject_reason(),
+        Some(RejectReason::AttachedOrderLevelError)
+    );
+    assert_eq!(confirmation("ACCEPTED", "SUCCESS").reject_reason(), None);
+}
+
+#[test]
+fn test_position_mode_force_open() {
+    let plain = CreateOrderRequest::limit(
+        "IX.D.DAX.DAILY.IP".to_string(),
+        Direction::Sell,
+        1.0,
+        18000.0,
+        "EUR".to_string(),
+    );
+    let with_stop = plain.clone().with_stop_loss(18100.0);
+
+    assert!(!PositionMode::Netting.force_open(&plain));
+    assert!(PositionMode::Netting.force_open(&with_stop));
+    assert!(PositionMode::Hedging.force_open(&plain));
+    assert!(PositionMode::Hedging.force_open(&with_stop));
+}
+
+#[test]
+fn test_position_mode_validate_close() {
+    let mut close = ClosePositionRequest::for_deal("DEAL123", &Direction::Buy, 1.0)
+        .with_market("IX.D.DAX.DAILY.IP", "EUR");
+    assert!(PositionMode::Hedging.validate_close(&close).is_ok());
+
+    close.deal_id = None;
+    assert!(PositionMode::Netting.validate_close(&close).is_ok());
+    assert!(PositionMode::Hedging.validate_close(&close).is_err());
+}
+
+#[test]
+fn test_position_mode_detect() {
+    let long: Position = serde_json::from_str(
+        &std::fs::read_to_string("tests/unit/application/models/position.json").unwrap(),
+    )
+    .unwrap();
+    assert_eq!(PositionMode::detect(&[long.clone(), long.clone()]), None);
+
+    let mut short = long.clone();
+    short.position.direction = Direction::Buy;
+    assert_eq!(
+        PositionMode::detect(&[long.clone(), short.clone()]),
+        Some(PositionMode::Hedging)
+    );
+
+    short.market.epic = "IX.D.DAX.DAILY.IP".to_string();
+    assert_eq!(PositionMode::detect(&[long, short]), None);
+}
@@ -1,5 +1,8 @@
 use ig_client::application::models::account::AccountTransaction;
-use ig_client::application::models::transaction::{StoreTransaction, TransactionList};
+use ig_client::application::models::transaction::{
+    EnrichedTransaction, RateSource, StoreTransaction, TransactionCategory, TransactionList,
+};
+use ig_client::utils::currency::CurrencyConverter;
 
 // Sample JSON for a simple transaction
 fn sample_raw_transaction_json() -> &'static str {
@@ -263,3 +266,128 @@ fn test_transaction_list_as_ref() {
     let deserialized: StoreTransaction = serde_json::from_str(&json).unwrap();
     assert_eq!(deserialized.reference, "ABCD1234");
 }
+
+fn cash_transaction(
+    transaction_type: &str,
+    instrument_name: &str,
+    amount: &str,
+) -> AccountTransaction {
+    AccountTransaction {
+        date: "2025-05-15T10:30:00".to_string(),
+        date_utc: "2025-05-15T10:30:00".to_string(),
+        open_date_utc: "".to_string(),
+        instrument_name: instrument_name.to_string(),
+        period: "-".to_string(),
+        profit_and_loss: amount.to_string(),
+        transaction_type: transaction_type.to_string(),
+        reference: "REF".to_string(),
+        open_level: "0".to_string(),
+        close_level: "0".to_string(),
+        size: "-".to_string(),
+        currency: "E".to_string(),
+        cash_transaction: transaction_type != "DEAL",
+    }
+}
+
+#[test]
+fn test_transaction_category() {
+    let transactions = vec![
+        cash_transaction("DEAL", "Germany 40", "E100.00"),
+        cash_transaction("WITH", "Daily Admin Fee", "E-0.50"),
+        cash_transaction("WITH", "Funding Adjustment US 500", "E-1.25"),
+        cash_transaction("DEPO", "Dividend Adjustment BP", "E2.00"),
+        cash_transaction("DEPO", "Card Payment", "E1,000.00"),
+        cash_transaction("WITH", "Bank Payment", "E-300.00"),
+        cash_transaction("DEPO", "Transfer from CFD account", "E50.00"),
+    ];
+    let categories: Vec<TransactionCategory> =
+        transactions.iter().map(TransactionCategory::of).collect();
+    assert_eq!(
+        categories,
+        vec![
+            TransactionCategory::Trade,
+            TransactionCategory::Fee,
+            TransactionCategory::Funding,
+            TransactionCategory::Dividend,
+            TransactionCategory::Deposit,
+            TransactionCategory::Withdrawal,
+            TransactionCategory::Transfer,
+        ]
+    );
+    assert!(TransactionCategory::Deposit.is_external_flow());
+    assert!(!TransactionCategory::Fee.is_external_flow());
+
+    let totals = TransactionCategory::totals(&transactions);
+    assert_eq!(totals[&TransactionCategory::Trade], 100.0);
+    assert_eq!(totals[&TransactionCategory::Deposit], 1000.0);
+    assert_eq!(totals[&TransactionCategory::Fee], -0.5);
+    assert!(!totals.contains_key(&TransactionCategory::Other));
+}
+
+#[test]
+fn test_enriched_transaction() {
+    let mut option = cash_transaction("DEAL", "US Tech 100 19200 CALL ($1)", "E-12.50");
+    option.period = "20-JUN-25".to_string();
+    let future = cash_transaction("DEAL", "Germany 40", "E100.00");
+    let fee = cash_transaction("WITH", "Daily Admin Fee", "E-0.50");
+
+    let enriched = EnrichedTransaction::enrich(&[option, future, fee]);
+    assert_eq!(enriched[0].underlying(), "US Tech 100");
+    assert_eq!(enriched[0].strike(), Some(19200.0));
+    assert_eq!(enriched[0].instrument.option_type.as_deref(), Some("CALL"));
+    assert!(enriched[0].is_option());
+    assert_eq!(
+        enriched[0].expiry,
+        chrono::NaiveDate::from_ymd_opt(2025, 6, 20)
+    );
+    assert_eq!(enriched[0].pnl(), Some(-12.5));
+
+    assert_eq!(enriched[1].underlying(), "Germany 40");
+    assert_eq!(enriched[1].strike(), None);
+    assert!(!enriched[1].is_option());
+    assert_eq!(enriched[1].expiry, None);
+    assert_eq!(enriched[2].category, TransactionCategory::Fee);
+
+    let json = serde_json::to_string(&enriched[0]).unwrap();
+    let back: EnrichedTransaction = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.instrument, enriched[0].instrument);
+}
+
+#[test]
+fn test_enriched_transaction_base_pnl() {
+    let converter = CurrencyConverter::new("GBP").with_rate("E", 0.85);
+    let mut hinted = EnrichedTransaction::new(cash_transaction(
+        "WITH",
+        "Conversion US 500 Tipo de cambio 0.80",
+        "E-10.00",
+    ));
+    assert_eq!(hinted.exchange_rate_hint(), Some(0.8));
+    let base = hinted.normalize(&converter).unwrap().clone();
+    assert_eq!(base.amount, -8.0);
+    assert_eq!(base.currency, "GBP");
+    assert_eq!(base.source, RateSource::InstrumentName);
+    assert_eq!(hinted.base_pnl, Some(base));
+
+    let plain = EnrichedTransaction::new(cash_transaction("DEAL", "Germany 40", "E100.00"));
+    let base = plain.to_base(&converter).unwrap();
+    assert_eq!((base.amount, base.rate), (85.0, 0.85));
+    assert_eq!(base.source, RateSource::Converter);
+
+    let mut same = cash_transaction("DEAL", "FTSE 100", "£20.00");
+    same.currency = "GBP".to_string();
+    let base = EnrichedTransaction::new(same).to_base(&converter).unwrap();
+    assert_eq!((base.amount, base.source), (20.0, RateSource::Base));
+
+    // No hint and no rate for the currency
+    let unknown = EnrichedTransaction::new(cash_transaction("DEAL", "Germany 40", "E1.00"));
+    assert!(unknown.to_base(&CurrencyConverter::new("GBP")).is_err());
+
+    let json = serde_json::to_value(&hinted).unwrap();
+    assert_eq!(json["basePnl"]["source"], "INSTRUMENT_NAME");
+    assert!(
+        serde_json::to_value(&plain)
+            .unwrap()
+            .get("basePnl")
+            .is_none()
+    );
+}
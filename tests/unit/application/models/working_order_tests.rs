@@ -148,4 +148,66 @@ mod tests {
 
         assert_eq!(response.deal_reference, deal_reference);
     }
+
+    #[test]
+    fn test_create_working_order_request_stop_limit() {
+        let request = CreateWorkingOrderRequest::stop_limit(
+            "IX.D.DAX.DAILY.IP".to_string(),
+            Direction::Buy,
+            1.0,
+            18500.0,
+            18510.0,
+        );
+        assert_eq!(request.order_type, OrderType::StopLimit);
+        assert_eq!(request.level, 18500.0);
+        assert_eq!(request.limit_price, Some(18510.0));
+        assert!(request.validate_levels().is_ok());
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["type"], "STOP_LIMIT");
+        assert_eq!(json["level"], 18500.0);
+        assert_eq!(json["limitPrice"], 18510.0);
+        let limit = CreateWorkingOrderRequest::limit("E".to_string(), Direction::Buy, 1.0, 1.0);
+        assert!(
+            serde_json::to_value(&limit)
+                .unwrap()
+                .get("limitPrice")
+                .is_none()
+        );
+
+        let parsed: CreateWorkingOrderRequest = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.order_type, OrderType::StopLimit);
+        assert_eq!(parsed.limit_price, Some(18510.0));
+        let legacy: OrderType = serde_json::from_str("\"STOPLIMIT\"").unwrap();
+        assert_eq!(legacy, OrderType::StopLimit);
+    }
+
+    #[test]
+    fn test_validate_stop_limit_levels() {
+        // A buy limit below its trigger, or a sell limit above it, can never fill
+        let buy = CreateWorkingOrderRequest::stop_limit(
+            "E".to_string(),
+            Direction::Buy,
+            1.0,
+            100.0,
+            99.0,
+        );
+        assert!(buy.validate_levels().is_err());
+        let sell = CreateWorkingOrderRequest::stop_limit(
+            "E".to_string(),
+            Direction::Sell,
+            1.0,
+            100.0,
+            99.0,
+        );
+        assert!(sell.validate_levels().is_ok());
+
+        let mut missing = sell.clone();
+        missing.limit_price = None;
+        assert!(missing.validate_levels().is_err());
+        let mut stop = CreateWorkingOrderRequest::stop("E".to_string(), Direction::Buy, 1.0, 100.0);
+        assert!(stop.validate_levels().is_ok());
+        stop.limit_price = Some(101.0);
+        assert!(stop.validate_levels().is_err());
+    }
 }
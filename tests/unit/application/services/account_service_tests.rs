@@ -1,7 +1,9 @@
 use async_trait::async_trait;
+use futures::TryStreamExt;
 use ig_client::application::models::account::{
-    Account, AccountActivity, AccountBalance, AccountInfo, PageData, Positions, TransactionHistory,
-    TransactionMetadata, WorkingOrders,
+    Account, AccountActivity, AccountBalance, AccountInfo, AccountStatus, AccountType,
+    ActivityQuery, PageData, Positions, TransactionHistory, TransactionMetadata, TransactionType,
+    WorkingOrders,
 };
 use ig_client::application::services::AccountService;
 use ig_client::application::services::account_service::AccountServiceImpl;
@@ -9,9 +11,11 @@ use ig_client::config::Config;
 use ig_client::error::AppError;
 use ig_client::session::interface::IgSession;
 use ig_client::transport::http_client::IgHttpClient;
+use ig_client::utils::currency::CurrencyConverter;
 use reqwest::Method;
 use serde::{Serialize, de::DeserializeOwned};
-use std::sync::Arc;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
 
 // Mock HTTP client for testing service methods without actual network calls
 struct MockHttpClient {
@@ -22,6 +26,7 @@ struct MockHttpClient {
 enum ResponseType {
     AccountInfo,
     Positions,
+    OnePosition,
     WorkingOrders,
     AccountActivity,
     TransactionHistory,
@@ -66,6 +71,13 @@ impl MockHttpClient {
                 }"#;
                 serde_json::from_str(json).map_err(|e| AppError::SerializationError(e.to_string()))
             }
+            ResponseType::OnePosition => {
+                let json = format!(
+                    r#"{{"positions": [{}]}}"#,
+                    include_str!("../models/position.json")
+                );
+                serde_json::from_str(&json).map_err(|e| AppError::SerializationError(e.to_string()))
+            }
             ResponseType::WorkingOrders => {
                 // For WorkingOrders, create a JSON string directly
                 let json = r#"{
@@ -143,15 +155,10 @@ fn test_account_info_structure() {
             account_id: "ACCOUNT-123".to_string(),
             account_name: "Test Account".to_string(),
             currency: "EUR".to_string(),
-            balance: AccountBalance {
-                balance: 1000.0,
-                deposit: 1000.0,
-                profit_loss: 0.0,
-                available: 1000.0,
-            },
-            account_type: "CFD".to_string(),
+            balance: AccountBalance::new("EUR", 1000.0, 1000.0, 0.0, 1000.0),
+            account_type: AccountType::Cfd,
             preferred: true,
-            status: "ENABLED".to_string(),
+            status: AccountStatus::Enabled,
         }],
     };
 
@@ -159,10 +166,44 @@ fn test_account_info_structure() {
     assert_eq!(account_info.accounts[0].account_id, "ACCOUNT-123");
     assert_eq!(account_info.accounts[0].account_name, "Test Account");
     assert_eq!(account_info.accounts[0].currency, "EUR");
-    assert_eq!(account_info.accounts[0].balance.balance, 1000.0);
-    assert_eq!(account_info.accounts[0].account_type, "CFD");
+    assert_eq!(account_info.accounts[0].balance.balance.amount, 1000.0);
+    assert_eq!(account_info.accounts[0].account_type, AccountType::Cfd);
     assert!(account_info.accounts[0].preferred);
-    assert_eq!(account_info.accounts[0].status, "ENABLED");
+    assert_eq!(account_info.accounts[0].status, AccountStatus::Enabled);
+}
+
+#[test]
+fn test_account_type_and_status_codes() {
+    let accounts: AccountInfo = serde_json::from_value(json!({
+        "accounts": [
+            {
+                "accountId": "A1", "accountName": "Spread bet", "accountType": "SPREADBET",
+                "balance": { "balance": 0.0, "deposit": 0.0, "profitLoss": 0.0, "available": 0.0 },
+                "currency": "GBP", "status": "SUSPENDED_FROM_DEALING", "preferred": false
+            },
+            {
+                "accountId": "A2", "accountName": "New", "accountType": "CRYPTO",
+                "balance": { "balance": 0.0, "deposit": 0.0, "profitLoss": 0.0, "available": 0.0 },
+                "currency": "GBP", "status": "CLOSING", "preferred": false
+            }
+        ]
+    }))
+    .unwrap();
+
+    assert_eq!(accounts.accounts[0].account_type, AccountType::Spreadbet);
+    assert_eq!(
+        accounts.accounts[0].status,
+        AccountStatus::SuspendedFromDealing
+    );
+    assert_eq!(
+        accounts.accounts[1].account_type,
+        AccountType::Other("CRYPTO".to_string())
+    );
+    assert_eq!(accounts.accounts[1].status.to_string(), "CLOSING");
+    assert_eq!(
+        serde_json::to_value(AccountType::Physical).unwrap(),
+        "PHYSICAL"
+    );
 }
 
 #[test]
@@ -261,6 +302,57 @@ async fn test_account_service_get_positions() {
     assert!(result.is_ok());
 }
 
+#[tokio::test]
+async fn test_account_service_find_position_by_reference() {
+    let mock_client = Arc::new(MockHttpClient::new("positions", ResponseType::OnePosition));
+    let service = AccountServiceImpl::new(Arc::new(Config::default()), mock_client);
+    let session = IgSession::new(
+        "test_cst".to_string(),
+        "test_token".to_string(),
+        "test_account".to_string(),
+    );
+
+    let position = service
+        .find_position_by_reference(&session, "RZ0RQ1JZ5VN38JC")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(position.position.deal_id, "DIAAAAT9SU2UMBB");
+
+    let missing = service
+        .find_position_by_reference(&session, "UNKNOWN")
+        .await
+        .unwrap();
+    assert!(missing.is_none());
+}
+
+#[tokio::test]
+async fn test_account_service_positions_with_pnl() {
+    let mock_client = Arc::new(MockHttpClient::new("positions", ResponseType::OnePosition));
+    let service = AccountServiceImpl::new(Arc::new(Config::default()), mock_client);
+    let session = IgSession::new(
+        "test_cst".to_string(),
+        "test_token".to_string(),
+        "test_account".to_string(),
+    );
+
+    // Short at 62.2 with an offer at 68.2, converted from EUR at 0.5
+    let converter = CurrencyConverter::new("GBP").with_rate("EUR", 0.5);
+    let positions = service
+        .positions_with_pnl(&session, &converter)
+        .await
+        .unwrap();
+    let pnl = positions.positions[0].pnl.unwrap();
+    assert!((pnl + 3.0).abs() < 1e-9);
+
+    // Without a rate the P&L stays empty
+    let positions = service
+        .positions_with_pnl(&session, &CurrencyConverter::new("USD"))
+        .await
+        .unwrap();
+    assert_eq!(positions.positions[0].pnl, None);
+}
+
 #[tokio::test]
 async fn test_account_service_get_working_orders() {
     // Create a mock client that expects the correct path
@@ -358,3 +450,262 @@ async fn test_account_service_get_transactions() {
         .await;
     assert!(result.is_ok());
 }
+
+// Mock HTTP client serving canned JSON by path and recording the paths requested
+struct PagedHttpClient {
+    pages: Vec<(String, Value)>,
+    paths: Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl IgHttpClient for PagedHttpClient {
+    async fn request<T: Serialize + std::marker::Send + std::marker::Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        self.paths.lock().unwrap().push(path.to_string());
+        let (_, page) = self
+            .pages
+            .iter()
+            .find(|(page_path, _)| page_path == path)
+            .ok_or(AppError::NotFound)?;
+        Ok(serde_json::from_value(page.clone())?)
+    }
+
+    async fn request_no_auth<
+        T: Serialize + std::marker::Send + std::marker::Sync,
+        R: DeserializeOwned,
+    >(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        unimplemented!()
+    }
+}
+
+fn activity_json(deal_id: &str) -> Value {
+    json!({
+        "date": "2025-07-01T10:00:00",
+        "dealId": deal_id,
+        "epic": "IX.D.DAX.DAILY.IP",
+        "type": "POSITION",
+        "status": "ACCEPTED",
+    })
+}
+
+#[test]
+fn test_activity_query_string() {
+    let query = ActivityQuery::new("2025-07-01T00:00:00")
+        .with_to("2025-07-02T00:00:00")
+        .with_filter("type==POSITION;epic==IX.D.DAX.DAILY.IP")
+        .detailed()
+        .with_page_size(50);
+    assert_eq!(
+        query.to_query_string(),
+        "from=2025-07-01T00:00:00&to=2025-07-02T00:00:00&detailed=true\
+         &filter=type%3D%3DPOSITION%3Bepic%3D%3DIX.D.DAX.DAILY.IP&pageSize=50"
+    );
+}
+
+#[tokio::test]
+async fn test_account_service_stream_activity_follows_cursor() {
+    let first = "history/activity?from=2025-07-01T00:00:00&filter=type%3D%3DPOSITION&pageSize=2";
+    let second = "history/activity?version=3&from=2025-07-01T00:00:00&pageSize=2&cursor=abc";
+    let client = Arc::new(PagedHttpClient {
+        pages: vec![
+            (
+                first.to_string(),
+                json!({
+                    "activities": [activity_json("DEAL1"), activity_json("DEAL2")],
+                    "metadata": { "paging": { "size": 2, "next": format!("/{second}") } }
+                }),
+            ),
+            (
+                second.to_string(),
+                json!({
+                    "activities": [activity_json("DEAL3")],
+                    "metadata": { "paging": { "size": 1, "next": null } }
+                }),
+            ),
+        ],
+        paths: Mutex::new(Vec::new()),
+    });
+    let service = AccountServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let session = IgSession::new(
+        "test_cst".to_string(),
+        "test_token".to_string(),
+        "test_account".to_string(),
+    );
+    let query = ActivityQuery::new("2025-07-01T00:00:00")
+        .with_filter("type==POSITION")
+        .with_page_size(2);
+
+    let activities: Vec<_> = service
+        .stream_activity(&session, &query)
+        .try_collect()
+        .await
+        .unwrap();
+
+    let deal_ids: Vec<_> = activities
+        .iter()
+        .filter_map(|activity| activity.deal_id.as_deref())
+        .collect();
+    assert_eq!(deal_ids, vec!["DEAL1", "DEAL2", "DEAL3"]);
+    assert_eq!(*client.paths.lock().unwrap(), vec![first, second]);
+}
+
+fn transaction_json(reference: &str) -> Value {
+    json!({
+        "date": "2025-07-01",
+        "dateUtc": "2025-07-01T10:00:00",
+        "openDateUtc": "2025-07-01T09:00:00",
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": "E10.00",
+        "transactionType": "DEAL",
+        "reference": reference,
+        "openLevel": "18000",
+        "closeLevel": "18010",
+        "size": "+1",
+        "currency": "E",
+        "cashTransaction": false
+    })
+}
+
+fn transaction_page(references: &[&str], page_number: i32, total_pages: i32) -> Value {
+    json!({
+        "transactions": references.iter().map(|r| transaction_json(r)).collect::<Vec<_>>(),
+        "metadata": {
+            "pageData": { "pageNumber": page_number, "pageSize": 50, "totalPages": total_pages },
+            "size": references.len()
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_account_service_stream_transactions_walks_pages() {
+    let path = |page: u32| {
+        format!(
+            "history/transactions?from=2025-07-01T00:00:00&to=2025-07-31T00:00:00\
+             &type=ALL_DEAL&pageSize=50&pageNumber={page}"
+        )
+    };
+    let client = Arc::new(PagedHttpClient {
+        pages: vec![
+            (path(1), transaction_page(&["TX1", "TX2"], 1, 2)),
+            (path(2), transaction_page(&["TX3"], 2, 2)),
+        ],
+        paths: Mutex::new(Vec::new()),
+    });
+    let service = AccountServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let session = IgSession::new(
+        "test_cst".to_string(),
+        "test_token".to_string(),
+        "test_account".to_string(),
+    );
+
+    let transactions: Vec<_> = service
+        .stream_transactions(
+            &session,
+            "2025-07-01T00:00:00",
+            "2025-07-31T00:00:00",
+            TransactionType::AllDeal,
+        )
+        .try_collect()
+        .await
+        .unwrap();
+
+    let references: Vec<_> = transactions.iter().map(|t| t.reference.as_str()).collect();
+    assert_eq!(references, vec!["TX1", "TX2", "TX3"]);
+    assert_eq!(*client.paths.lock().unwrap(), vec![path(1), path(2)]);
+}
+
+#[tokio::test]
+async fn test_account_service_get_margin_summary() {
+    // A short of 1 on a weekly DAX put, closing at the offer of 68.2, on a 10% margin
+    let position: Value = serde_json::from_str(
+        &std::fs::read_to_string("tests/unit/application/models/position.json").unwrap(),
+    )
+    .unwrap();
+    let epic = "OP.D.OTCDAXWK.23650P.IP";
+    let client = Arc::new(PagedHttpClient {
+        pages: vec![
+            (
+                "accounts".to_string(),
+                json!({ "accounts": [{
+                    "accountId": "ACC123", "accountName": "CFD", "accountType": "CFD",
+                    "balance": {
+                        "balance": 10000.0, "deposit": 10.0, "profitLoss": -6.0,
+                        "available": 9984.0
+                    },
+                    "currency": "EUR", "status": "ENABLED", "preferred": true
+                }]}),
+            ),
+            ("positions".to_string(), json!({ "positions": [position] })),
+            (
+                format!("markets/{epic}"),
+                json!({
+                    "instrument": {
+                        "epic": epic,
+                        "name": "Weekly Germany 40 23650 PUT",
+                        "expiry": "04-JUL-25",
+                        "contractSize": "1",
+                        "valueOfOnePip": "1.00",
+                        "marginFactor": 10.0,
+                        "marginFactorUnit": "PERCENTAGE"
+                    },
+                    "snapshot": { "marketStatus": "TRADEABLE", "bid": 62.2, "offer": 68.2 },
+                    "dealingRules": {
+                        "minStepDistance": { "unit": "POINTS", "value": 1.0 },
+                        "minDealSize": { "unit": "POINTS", "value": 1.0 },
+                        "minControlledRiskStopDistance": { "unit": "POINTS", "value": 1.0 },
+                        "minNormalStopOrLimitDistance": { "unit": "POINTS", "value": 1.0 },
+                        "maxStopOrLimitDistance": { "unit": "POINTS", "value": 100.0 },
+                        "controlledRiskSpacing": { "unit": "POINTS", "value": 1.0 },
+                        "marketOrderPreference": "AVAILABLE_DEFAULT_ON",
+                        "trailingStopsPreference": "AVAILABLE"
+                    }
+                }),
+            ),
+        ],
+        paths: Mutex::new(Vec::new()),
+    });
+    let service = AccountServiceImpl::new(Arc::new(Config::default()), client);
+    let session = IgSession::new(
+        "test_cst".to_string(),
+        "test_token".to_string(),
+        "ACC123".to_string(),
+    );
+
+    let summary = service
+        .get_margin_summary(&session, &CurrencyConverter::new("EUR"))
+        .await
+        .unwrap();
+
+    assert_eq!(summary.equity, 9994.0);
+    assert!((summary.used_margin - 6.82).abs() < 1e-9);
+    assert!((summary.free_margin - 9987.18).abs() < 1e-9);
+    assert!((summary.margin_level.unwrap() - 9994.0 / 6.82 * 100.0).abs() < 1e-6);
+    assert_eq!(summary.positions.len(), 1);
+    assert_eq!(summary.positions[0].deal_id, "DIAAAAT9SU2UMBB");
+
+    // A session on an account that is not listed
+    let other = IgSession::new(
+        "test_cst".to_string(),
+        "test_token".to_string(),
+        "OTHER".to_string(),
+    );
+    assert!(matches!(
+        service
+            .get_margin_summary(&other, &CurrencyConverter::new("EUR"))
+            .await,
+        Err(AppError::NotFound)
+    ));
+}
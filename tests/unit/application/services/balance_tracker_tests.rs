@@ -0,0 +1,102 @@
+use ig_client::application::services::account_service::AccountServiceImpl;
+use ig_client::application::services::balance_tracker::BalanceTracker;
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::storage::balance_history::MemoryBalanceStore;
+use ig_client::transport::http_client::IgHttpClient;
+use ig_client::utils::rate_limiter::{RateLimitType, create_rate_limiter};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+// Mock HTTP client returning the accounts with the current balance
+struct AccountsHttpClient {
+    balance: Mutex<f64>,
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for AccountsHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        assert_eq!(path, "accounts");
+        let balance = *self.balance.lock().unwrap();
+        Ok(serde_json::from_value(json!({
+            "accounts": [{
+                "accountId": "ACC123",
+                "accountName": "Demo",
+                "accountType": "CFD",
+                "balance": {
+                    "balance": balance,
+                    "deposit": 200.0,
+                    "profitLoss": -25.0,
+                    "available": balance
+                },
+                "currency": "EUR",
+                "status": "ENABLED",
+                "preferred": true
+            }]
+        }))?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Mock HTTP client does not support unauthenticated requests");
+    }
+}
+
+#[tokio::test]
+async fn test_balance_tracker_records_snapshots() {
+    let client = Arc::new(AccountsHttpClient {
+        balance: Mutex::new(1000.0),
+    });
+    let service = AccountServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let tracker = BalanceTracker::new(service, MemoryBalanceStore::new())
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None));
+    let session = IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    );
+
+    let from = chrono::Utc::now();
+    let snapshot = tracker.record(&session).await.unwrap();
+    assert_eq!(snapshot.account_id, "ACC123");
+    assert_eq!(snapshot.currency, "EUR");
+    assert_eq!(snapshot.equity(), 975.0);
+
+    *client.balance.lock().unwrap() = 900.0;
+    tracker.record(&session).await.unwrap();
+
+    let history = tracker
+        .history(
+            "ACC123",
+            from,
+            chrono::Utc::now() + chrono::Duration::seconds(1),
+        )
+        .unwrap();
+    assert_eq!(history.snapshots.len(), 2);
+    assert_eq!(history.max_drawdown().unwrap().amount(), 100.0);
+
+    let other = IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC999".to_string(),
+    );
+    assert!(matches!(
+        tracker.record(&other).await,
+        Err(AppError::NotFound)
+    ));
+}
@@ -0,0 +1,197 @@
+use chrono::Duration;
+use ig_client::application::models::market::{MarketData, MarketNode};
+use ig_client::application::services::catalog_refresher::{
+    CatalogRefreshReport, InstrumentCatalogRefresher,
+};
+use ig_client::application::services::market_service::MarketServiceImpl;
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::storage::Store;
+use ig_client::storage::instrument_catalog::CatalogQuery;
+use ig_client::storage::memory_store::MemoryStore;
+use ig_client::transport::http_client::IgHttpClient;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+// Mock HTTP client serving the details of any requested EPIC, in euros
+struct MockHttpClient {
+    paths: Mutex<Vec<String>>,
+}
+
+fn details(epic: &str) -> Value {
+    let step = json!({ "unit": "POINTS", "value": 1.0 });
+    json!({
+        "instrument": {
+            "epic": epic,
+            "name": format!("Market {epic}"),
+            "expiry": "-",
+            "contractSize": "1",
+            "valueOfOnePip": "1.00",
+            "currencies": [{ "code": "EUR", "isDefault": true }]
+        },
+        "snapshot": { "marketStatus": "TRADEABLE", "bid": 99.0, "offer": 101.0 },
+        "dealingRules": {
+            "minStepDistance": step,
+            "minDealSize": step,
+            "minControlledRiskStopDistance": step,
+            "minNormalStopOrLimitDistance": step,
+            "maxStopOrLimitDistance": step,
+            "controlledRiskSpacing": step,
+            "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+            "trailingStopsPreference": "NOT_AVAILABLE"
+        }
+    })
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for MockHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        self.paths.lock().unwrap().push(path.to_string());
+        let epics = path
+            .strip_prefix("markets?epics=")
+            .and_then(|rest| rest.strip_suffix("&filter=ALL"))
+            .ok_or(AppError::NotFound)?;
+        let markets: Vec<Value> = epics.split(',').map(details).collect();
+        Ok(serde_json::from_value(json!({ "marketDetails": markets }))?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Mock HTTP client does not support unauthenticated requests");
+    }
+}
+
+fn market(epic: &str, status: &str) -> MarketData {
+    serde_json::from_value(json!({
+        "epic": epic,
+        "instrumentName": format!("Market {epic}"),
+        "instrumentType": "INDICES",
+        "expiry": "-",
+        "marketStatus": status
+    }))
+    .unwrap()
+}
+
+fn tree(markets: Vec<MarketData>) -> Vec<MarketNode> {
+    vec![MarketNode {
+        id: "indices".to_string(),
+        name: "Indices".to_string(),
+        children: vec![],
+        markets,
+    }]
+}
+
+fn setup() -> (
+    MarketServiceImpl<MockHttpClient>,
+    Arc<MockHttpClient>,
+    IgSession,
+) {
+    let client = Arc::new(MockHttpClient {
+        paths: Mutex::new(Vec::new()),
+    });
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let session = IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    );
+    (service, client, session)
+}
+
+#[tokio::test]
+async fn test_catalog_refresh_is_incremental() {
+    let (service, client, session) = setup();
+    let store = MemoryStore::new();
+    let refresher = InstrumentCatalogRefresher::new(&service, store);
+
+    let first = tree(vec![market("DAX", "TRADEABLE"), market("CAC", "TRADEABLE")]);
+    let report = refresher.refresh(&session, &first).await.unwrap();
+    assert_eq!(
+        report,
+        CatalogRefreshReport {
+            listed: 2,
+            added: 2,
+            details_fetched: 2,
+            ..Default::default()
+        }
+    );
+    let dax = refresher
+        .store()
+        .get_instrument("DAX")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(dax.currency.as_deref(), Some("EUR"));
+    assert!(dax.dealing_rules.is_some());
+    assert_eq!(dax.path, ["Indices"]);
+    let listed_at = dax.listed_at;
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+    // Nothing changed, so nothing is requested
+    let report = refresher.refresh(&session, &first).await.unwrap();
+    assert_eq!(
+        (report.added, report.updated, report.details_fetched),
+        (0, 0, 0)
+    );
+    assert_eq!(client.paths.lock().unwrap().len(), 1);
+
+    let second = tree(vec![market("DAX", "EDITS_ONLY")]);
+    let report = refresher.refresh(&session, &second).await.unwrap();
+    assert_eq!((report.updated, report.unlisted), (1, 1));
+    let dax = refresher
+        .store()
+        .get_instrument("DAX")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(dax.market_status, "EDITS_ONLY");
+    // A rewritten entry keeps the time it was first listed
+    assert_eq!(dax.listed_at, listed_at);
+    // Details survive a change of listing, and unlisted markets stay in the catalog
+    assert_eq!(dax.currency.as_deref(), Some("EUR"));
+    let euro = refresher
+        .store()
+        .search_instruments(&CatalogQuery::new().in_currency("EUR"))
+        .await
+        .unwrap();
+    assert_eq!(euro.len(), 2);
+}
+
+#[tokio::test]
+async fn test_catalog_refresh_spreads_details() {
+    let (service, _client, session) = setup();
+    let store = MemoryStore::new();
+    let refresher = InstrumentCatalogRefresher::new(&service, store)
+        .with_max_details(1)
+        .with_details_max_age(Duration::days(1));
+    let markets = tree(vec![market("DAX", "TRADEABLE"), market("CAC", "TRADEABLE")]);
+
+    let first = refresher.refresh(&session, &markets).await.unwrap();
+    assert_eq!((first.added, first.details_fetched), (2, 1));
+    let second = refresher.refresh(&session, &markets).await.unwrap();
+    assert_eq!((second.added, second.details_fetched), (0, 1));
+    let third = refresher.refresh(&session, &markets).await.unwrap();
+    assert_eq!(third.details_fetched, 0);
+    let found = refresher
+        .store()
+        .search_instruments(&CatalogQuery::new().with_text("market cac"))
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert!(found[0].details_at.is_some());
+}
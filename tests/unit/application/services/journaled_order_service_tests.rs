@@ -0,0 +1,209 @@
+use ig_client::application::models::order::{CreateOrderRequest, Direction, UpdatePositionRequest};
+use ig_client::application::services::OrderService;
+use ig_client::application::services::journaled_order_service::JournaledOrderService;
+use ig_client::application::services::order_service::OrderServiceImpl;
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::storage::order_journal::{
+    FileOrderJournal, JournalEntryKind, JournalQuery, MemoryOrderJournal, OrderJournal,
+};
+use ig_client::transport::http_client::IgHttpClient;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::sync::Arc;
+
+const EPIC: &str = "IX.D.DAX.DAILY.IP";
+
+// Mock HTTP client accepting orders and amendments, failing anything else
+struct DealingHttpClient;
+
+#[async_trait::async_trait]
+impl IgHttpClient for DealingHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        let response = match (method, path) {
+            (Method::GET, "positions/DIAAAAT9SU2UMBB") => {
+                serde_json::from_str(include_str!("../models/position.json"))?
+            }
+            (Method::POST, "positions/otc") => json!({ "dealReference": "ORDER1" }),
+            (Method::DELETE, "positions/otc") => json!({ "dealReference": "CLOSE1" }),
+            (Method::PUT, "positions/otc/DEAL1") => json!({ "dealReference": "AMEND1" }),
+            (Method::GET, "confirms/ORDER1" | "confirms/CLOSE1") => json!({
+                "date": "2025-05-22T09:00:00.000",
+                "status": "OPEN",
+                "reason": "SUCCESS",
+                "dealStatus": "ACCEPTED",
+                "epic": EPIC,
+                "expiry": "-",
+                "dealReference": &path["confirms/".len()..],
+                "dealId": "DEAL1",
+                "level": 18200.0,
+                "size": 1.0,
+                "direction": "BUY",
+                "guaranteedStop": false,
+                "trailingStop": false
+            }),
+            _ => return Err(AppError::NotFound),
+        };
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        unimplemented!()
+    }
+}
+
+fn order_service() -> OrderServiceImpl<DealingHttpClient> {
+    OrderServiceImpl::new(Arc::new(Config::default()), Arc::new(DealingHttpClient))
+}
+
+fn market_order() -> CreateOrderRequest {
+    CreateOrderRequest::market(EPIC.to_string(), Direction::Buy, 1.0, "EUR".to_string())
+}
+
+fn test_session() -> IgSession {
+    IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    )
+}
+
+#[tokio::test]
+async fn test_journal_correlates_order_response_and_confirmation() {
+    let service = JournaledOrderService::new(order_service(), MemoryOrderJournal::new());
+    let session = test_session();
+
+    let response = service
+        .create_order(&session, &market_order())
+        .await
+        .unwrap();
+    service
+        .get_order_confirmation(&session, &response.deal_reference)
+        .await
+        .unwrap();
+    let update = UpdatePositionRequest::levels(Some(18100.0), None);
+    service
+        .update_position(&session, "DEAL1", &update)
+        .await
+        .unwrap();
+
+    let entries = service.journal().query(&JournalQuery::all()).unwrap();
+    let kinds: Vec<JournalEntryKind> = entries.iter().map(|entry| entry.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            JournalEntryKind::Request,
+            JournalEntryKind::Response,
+            JournalEntryKind::Confirmation,
+            JournalEntryKind::Amendment,
+            JournalEntryKind::Response,
+        ]
+    );
+    let order_id = &entries[0].correlation_id;
+    assert_eq!(&entries[1].correlation_id, order_id);
+    assert_eq!(&entries[2].correlation_id, order_id);
+    assert_ne!(&entries[3].correlation_id, order_id);
+    assert_eq!(entries[0].payload["epic"], EPIC);
+    assert_eq!(entries[1].deal_reference.as_deref(), Some("ORDER1"));
+    assert_eq!(entries[2].deal_id.as_deref(), Some("DEAL1"));
+    assert_eq!(entries[3].payload["stopLevel"], 18100.0);
+
+    let trail = service
+        .journal()
+        .query(&JournalQuery::all().with_deal_id("DEAL1"))
+        .unwrap();
+    assert_eq!(trail.len(), 3);
+}
+
+#[tokio::test]
+async fn test_journal_records_both_orders_of_a_reversal() {
+    let service = JournaledOrderService::new(order_service(), MemoryOrderJournal::new());
+
+    let reversal = service
+        .reverse_position(&test_session(), "DIAAAAT9SU2UMBB")
+        .await
+        .unwrap();
+    assert!(reversal.is_complete());
+
+    let requests = service
+        .journal()
+        .query(&JournalQuery::all().with_kind(JournalEntryKind::Request))
+        .unwrap();
+    let operations: Vec<&str> = requests
+        .iter()
+        .map(|entry| entry.operation.as_str())
+        .collect();
+    assert_eq!(operations, vec!["close_position", "create_order"]);
+    assert_eq!(requests[1].payload["direction"], "BUY");
+
+    let confirmations = service
+        .journal()
+        .query(&JournalQuery::all().with_kind(JournalEntryKind::Confirmation))
+        .unwrap();
+    assert_eq!(confirmations.len(), 2);
+}
+
+#[tokio::test]
+async fn test_journal_records_errors() {
+    let service = JournaledOrderService::new(order_service(), MemoryOrderJournal::new());
+
+    let result = service
+        .delete_working_order(&test_session(), "UNKNOWN")
+        .await;
+    assert!(result.is_err());
+
+    let errors = service
+        .journal()
+        .query(&JournalQuery::all().with_kind(JournalEntryKind::Error))
+        .unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].operation, "delete_working_order");
+    assert_eq!(errors[0].deal_id.as_deref(), Some("UNKNOWN"));
+}
+
+#[tokio::test]
+async fn test_file_journal_links_confirmations_across_restarts() {
+    let path = std::env::temp_dir().join(format!("ig_journal_{}.ndjson", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let session = test_session();
+
+    let service = JournaledOrderService::new(order_service(), FileOrderJournal::open(&path));
+    service
+        .create_order(&session, &market_order())
+        .await
+        .unwrap();
+    drop(service);
+
+    let restarted = JournaledOrderService::new(order_service(), FileOrderJournal::open(&path));
+    restarted
+        .get_order_confirmation(&session, "ORDER1")
+        .await
+        .unwrap();
+
+    let entries = restarted
+        .journal()
+        .query(&JournalQuery::all().with_epic(EPIC))
+        .unwrap();
+    assert_eq!(entries.len(), 3);
+    assert!(
+        entries
+            .iter()
+            .all(|entry| entry.correlation_id == entries[0].correlation_id)
+    );
+    let _ = std::fs::remove_file(&path);
+}
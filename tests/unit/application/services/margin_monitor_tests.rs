@@ -0,0 +1,100 @@
+use ig_client::application::models::account::{AccountBalance, Position};
+use ig_client::application::services::margin_monitor::{
+    MarginAlert, MarginMonitor, MarginThresholds,
+};
+use ig_client::storage::balance_history::BalanceSnapshot;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+fn snapshot(deposit: f64, available: f64) -> BalanceSnapshot {
+    BalanceSnapshot::new(
+        "ACC123",
+        "EUR",
+        AccountBalance::new("EUR", 1000.0, deposit, 0.0, available),
+    )
+}
+
+// Short position at 62.2 quoted 68.2 at the offer, losing 6 per unit
+fn position(deal_id: &str, size: f64) -> Position {
+    let mut position: Value = serde_json::from_str(
+        &std::fs::read_to_string("tests/unit/application/models/position.json").unwrap(),
+    )
+    .unwrap();
+    position["position"]["dealId"] = json!(deal_id);
+    position["position"]["size"] = json!(size);
+    serde_json::from_value(position).unwrap()
+}
+
+#[test]
+fn test_balance_alerts_fire_once_per_breach() {
+    let fired = Arc::new(Mutex::new(Vec::new()));
+    let log = fired.clone();
+    let monitor = MarginMonitor::new(
+        MarginThresholds::new()
+            .with_max_margin_usage(50.0)
+            .with_min_available(200.0),
+    )
+    .on_alert(move |alert| log.lock().unwrap().push(alert.clone()));
+    let mut alerts = monitor.subscribe();
+
+    assert!(monitor.check_balance(&snapshot(400.0, 600.0)).is_empty());
+
+    let breached = monitor.check_balance(&snapshot(600.0, 150.0));
+    assert_eq!(breached.len(), 2);
+    assert_eq!(
+        breached[0],
+        MarginAlert::MarginUsage {
+            account_id: "ACC123".to_string(),
+            usage: 60.0,
+            max: 50.0,
+        }
+    );
+    assert!(matches!(
+        breached[1],
+        MarginAlert::AvailableFunds { available, min, .. } if available == 150.0 && min == 200.0
+    ));
+    assert_eq!(*fired.lock().unwrap(), breached);
+    assert_eq!(alerts.try_recv().unwrap(), breached[0]);
+    assert_eq!(
+        breached[0].to_string(),
+        "margin usage of ACC123 is 60.00%, above the maximum 50%"
+    );
+
+    // Still breached: nothing new until the values recover
+    assert!(monitor.check_balance(&snapshot(700.0, 100.0)).is_empty());
+    assert!(monitor.check_balance(&snapshot(100.0, 900.0)).is_empty());
+    assert_eq!(monitor.check_balance(&snapshot(600.0, 900.0)).len(), 1);
+    assert_eq!(fired.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn test_position_loss_alerts() {
+    let monitor = MarginMonitor::new(MarginThresholds::new().with_max_position_loss(15.0));
+    assert!(monitor.check_balance(&snapshot(900.0, 0.0)).is_empty());
+
+    let positions = vec![position("SMALL", 1.0), position("LARGE", 2.0)];
+    assert!(monitor.check_positions(&positions).is_empty());
+
+    let positions = vec![position("SMALL", 1.0), position("LARGE", 3.0)];
+    let alerts = monitor.check_positions(&positions);
+    assert_eq!(alerts.len(), 1);
+    match &alerts[0] {
+        MarginAlert::PositionLoss {
+            deal_id,
+            epic,
+            loss,
+            max,
+        } => {
+            assert_eq!(deal_id, "LARGE");
+            assert_eq!(epic, "OP.D.OTCDAXWK.23650P.IP");
+            assert!((loss - 18.0).abs() < 1e-9);
+            assert_eq!(*max, 15.0);
+        }
+        other => panic!("unexpected alert {other:?}"),
+    }
+    assert!(monitor.check_positions(&positions).is_empty());
+
+    // Closing the position clears the breach, so a new deal with the same ID fires again
+    assert!(monitor.check_positions(&positions[..1]).is_empty());
+    assert_eq!(monitor.check_positions(&positions).len(), 1);
+}
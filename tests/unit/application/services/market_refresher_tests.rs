@@ -0,0 +1,170 @@
+use ig_client::application::services::market_refresher::{MarketChange, MarketRefresher};
+use ig_client::application::services::market_service::MarketServiceImpl;
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use ig_client::utils::rate_limiter::{RateLimitType, create_rate_limiter};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Mock HTTP client returning one scripted snapshot of REF.A per request:
+// (status, bid, offer)
+struct MockHttpClient {
+    snapshots: Mutex<Vec<(&'static str, f64, f64)>>,
+}
+
+fn details(status: &str, bid: f64, offer: f64) -> Value {
+    let step = json!({ "unit": "POINTS", "value": 1.0 });
+    json!({
+        "instrument": {
+            "epic": "REF.A",
+            "name": "Refreshed market",
+            "expiry": "-",
+            "contractSize": "1",
+            "valueOfOnePip": "1.00"
+        },
+        "snapshot": { "marketStatus": status, "bid": bid, "offer": offer },
+        "dealingRules": {
+            "minStepDistance": step,
+            "minDealSize": step,
+            "minControlledRiskStopDistance": step,
+            "minNormalStopOrLimitDistance": step,
+            "maxStopOrLimitDistance": step,
+            "controlledRiskSpacing": step,
+            "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+            "trailingStopsPreference": "NOT_AVAILABLE"
+        }
+    })
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for MockHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        assert_eq!(path, "markets?epics=REF.A&filter=ALL");
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let (status, bid, offer) = if snapshots.len() > 1 {
+            snapshots.remove(0)
+        } else {
+            snapshots[0]
+        };
+        Ok(serde_json::from_value(
+            json!({ "marketDetails": [details(status, bid, offer)] }),
+        )?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Mock HTTP client does not support unauthenticated requests");
+    }
+}
+
+fn setup(
+    snapshots: Vec<(&'static str, f64, f64)>,
+) -> (MarketServiceImpl<MockHttpClient>, IgSession) {
+    let client = Arc::new(MockHttpClient {
+        snapshots: Mutex::new(snapshots),
+    });
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client);
+    let session = IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    );
+    (service, session)
+}
+
+#[tokio::test]
+async fn test_refresh_reports_price_and_status_changes() {
+    let (service, session) = setup(vec![
+        ("TRADEABLE", 99.0, 101.0),
+        ("TRADEABLE", 99.5, 101.5),
+        ("TRADEABLE", 100.0, 102.0),
+        ("EDITS_ONLY", 100.0, 102.0),
+    ]);
+    let mut refresher = MarketRefresher::new(&service, vec!["REF.A".to_string()])
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None))
+        .with_price_threshold(0.75);
+
+    // Baseline
+    assert!(refresher.refresh(&session).await.unwrap().is_empty());
+    // 0.5% move, below the threshold
+    assert!(refresher.refresh(&session).await.unwrap().is_empty());
+    // 1% move measured from the last reported price
+    let changes = refresher.refresh(&session).await.unwrap();
+    assert_eq!(
+        changes,
+        vec![MarketChange::PriceMoved {
+            epic: "REF.A".to_string(),
+            previous: 100.0,
+            current: 101.0,
+            change_pct: 1.0,
+        }]
+    );
+    let changes = refresher.refresh(&session).await.unwrap();
+    assert_eq!(
+        changes,
+        vec![MarketChange::StatusChanged {
+            epic: "REF.A".to_string(),
+            previous: "TRADEABLE".to_string(),
+            current: "EDITS_ONLY".to_string(),
+        }]
+    );
+    assert_eq!(
+        refresher.snapshot("REF.A").unwrap().market_status,
+        "EDITS_ONLY"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_snapshot_is_the_latest_fetch_until_stale() {
+    let (service, session) = setup(vec![("TRADEABLE", 99.0, 101.0), ("TRADEABLE", 99.5, 101.5)]);
+    let mut refresher = MarketRefresher::new(&service, vec!["REF.A".to_string()])
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None))
+        .with_price_threshold(1.0)
+        .with_max_age(Duration::from_secs(90));
+
+    assert!(refresher.snapshot("REF.A").is_none());
+    refresher.refresh(&session).await.unwrap();
+    refresher.refresh(&session).await.unwrap();
+    // The move is below the threshold, but the snapshot has the fetched price
+    assert_eq!(refresher.snapshot("REF.A").unwrap().bid, Some(99.5));
+
+    tokio::time::advance(Duration::from_secs(91)).await;
+    assert!(refresher.snapshot("REF.A").is_none());
+}
+
+#[tokio::test]
+async fn test_run_sends_changes_until_receiver_dropped() {
+    let (service, session) = setup(vec![("TRADEABLE", 99.0, 101.0), ("CLOSED", 109.0, 111.0)]);
+    let mut refresher = MarketRefresher::new(&service, vec!["REF.A".to_string()])
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None))
+        .with_interval(Duration::from_millis(10));
+
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(8);
+    let consumer = async move {
+        let first = receiver.recv().await.unwrap();
+        let second = receiver.recv().await.unwrap();
+        (first, second)
+    };
+    let ((first, second), ()) = tokio::join!(consumer, refresher.run(&session, sender));
+
+    assert!(matches!(first, MarketChange::StatusChanged { .. }));
+    assert_eq!(second.epic(), "REF.A");
+    assert!(matches!(second, MarketChange::PriceMoved { change_pct, .. } if change_pct == 10.0));
+}
@@ -0,0 +1,170 @@
+use ig_client::application::services::market_scanner::MarketScanner;
+use ig_client::application::services::market_service::MarketServiceImpl;
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use ig_client::utils::rate_limiter::{RateLimitType, create_rate_limiter};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+// Mock HTTP client serving market snapshots and a navigation subtree:
+// N -> [N1] + market SCAN.B; N1 -> market SCAN.C
+struct MockHttpClient {
+    paths: Mutex<Vec<String>>,
+}
+
+fn snapshot(epic: &str, status: &str, bid: f64, offer: f64, change: f64) -> Value {
+    json!({
+        "epic": epic,
+        "instrumentName": format!("Market {epic}"),
+        "instrumentType": "INDICES",
+        "expiry": "-",
+        "marketStatus": status,
+        "bid": bid,
+        "offer": offer,
+        "percentageChange": change
+    })
+}
+
+fn details(market: &Value) -> Value {
+    let step = json!({ "unit": "POINTS", "value": 1.0 });
+    json!({
+        "instrument": {
+            "epic": market["epic"],
+            "name": market["instrumentName"],
+            "expiry": "-",
+            "contractSize": "1",
+            "valueOfOnePip": "1.00",
+            "instrumentType": "INDICES"
+        },
+        "snapshot": {
+            "marketStatus": market["marketStatus"],
+            "bid": market["bid"],
+            "offer": market["offer"],
+            "percentageChange": market["percentageChange"]
+        },
+        "dealingRules": {
+            "minStepDistance": step,
+            "minDealSize": step,
+            "minControlledRiskStopDistance": step,
+            "minNormalStopOrLimitDistance": step,
+            "maxStopOrLimitDistance": step,
+            "controlledRiskSpacing": step,
+            "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+            "trailingStopsPreference": "NOT_AVAILABLE"
+        }
+    })
+}
+
+fn markets() -> Vec<Value> {
+    vec![
+        snapshot("SCAN.A", "TRADEABLE", 100.0, 101.0, 0.5),
+        snapshot("SCAN.B", "TRADEABLE", 100.0, 100.5, -2.5),
+        snapshot("SCAN.C", "TRADEABLE", 100.0, 100.2, 1.5),
+        snapshot("SCAN.D", "CLOSED", 100.0, 100.1, 4.0),
+    ]
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for MockHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        self.paths.lock().unwrap().push(path.to_string());
+        let all = markets();
+        let response = match path {
+            "marketnavigation/N" => json!({
+                "nodes": [{ "id": "N1", "name": "Europe" }],
+                "markets": [all[1]]
+            }),
+            "marketnavigation/N1" => json!({ "nodes": null, "markets": [all[2]] }),
+            _ if path.starts_with("markets?epics=") => {
+                let requested = path
+                    .trim_start_matches("markets?epics=")
+                    .trim_end_matches("&filter=ALL");
+                let details: Vec<Value> = all
+                    .iter()
+                    .filter(|m| requested.split(',').any(|e| m["epic"] == e))
+                    .map(details)
+                    .collect();
+                json!({ "marketDetails": details })
+            }
+            _ => return Err(AppError::NotFound),
+        };
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Mock HTTP client does not support unauthenticated requests");
+    }
+}
+
+fn setup() -> (
+    Arc<MockHttpClient>,
+    MarketServiceImpl<MockHttpClient>,
+    IgSession,
+) {
+    let client = Arc::new(MockHttpClient {
+        paths: Mutex::new(Vec::new()),
+    });
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let session = IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    );
+    (client, service, session)
+}
+
+#[tokio::test]
+async fn test_scan_epics_filters_and_ranks() {
+    let (client, service, session) = setup();
+    let epics: Vec<String> = ["SCAN.A", "SCAN.B", "SCAN.C", "SCAN.D"]
+        .iter()
+        .map(|e| e.to_string())
+        .collect();
+
+    let scanner = MarketScanner::new(&service)
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None))
+        .with_status("TRADEABLE")
+        .with_max_spread(0.6)
+        .with_min_percentage_change(1.0);
+    let results = scanner.scan_epics(&session, &epics).await.unwrap();
+
+    let kept: Vec<&str> = results.iter().map(|r| r.market.epic.as_str()).collect();
+    assert_eq!(kept, vec!["SCAN.B", "SCAN.C"]);
+    assert_eq!(results[0].score, 2.5);
+    assert_eq!(client.paths.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_scan_node_with_custom_ranking() {
+    let (client, service, session) = setup();
+
+    let scanner = MarketScanner::new(&service)
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None))
+        .rank_by(|market| -market.spread().unwrap_or(f64::MAX))
+        .with_limit(1);
+    let results = scanner.scan_node(&session, "N").await.unwrap();
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].market.epic, "SCAN.C");
+    assert_eq!(
+        *client.paths.lock().unwrap(),
+        vec!["marketnavigation/N", "marketnavigation/N1"]
+    );
+}
@@ -1,7 +1,11 @@
+use chrono::NaiveDate;
+use futures::{StreamExt, TryStreamExt};
 use ig_client::application::models::market::{
-    HistoricalPricesResponse, MarketData, MarketNavigationNode, MarketNavigationResponse,
-    MarketSearchResult,
+    DealabilityIssue, HistoricalPricesQuery, HistoricalPricesResponse, MarketData, MarketDetails,
+    MarketNavigationNode, MarketNavigationResponse, MarketSearchFilter, MarketSearchResult,
+    Resolution,
 };
+use ig_client::application::models::order::{Direction, OrderType};
 use ig_client::application::services::MarketService;
 use ig_client::application::services::market_service::MarketServiceImpl;
 use ig_client::config::Config;
@@ -9,10 +13,11 @@ use ig_client::error::AppError;
 use ig_client::presentation::InstrumentType;
 use ig_client::session::interface::IgSession;
 use ig_client::transport::http_client::IgHttpClient;
-use ig_client::utils::rate_limiter::RateLimitType;
+use ig_client::utils::rate_limiter::{RateLimitType, RateLimiterRegistry};
 use reqwest::Method;
 use serde::de::DeserializeOwned;
-use std::sync::Arc;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
 
 // Mock HTTP client for testing service methods without actual network calls
 struct MockHttpClient {}
@@ -44,6 +49,58 @@ impl IgHttpClient for MockHttpClient {
     }
 }
 
+// Mock HTTP client that records requested paths and answers with a canned JSON value
+struct RecordingHttpClient {
+    paths: Mutex<Vec<String>>,
+    response: Box<dyn Fn(&str) -> Value + Send + Sync>,
+}
+
+impl RecordingHttpClient {
+    fn new(response: impl Fn(&str) -> Value + Send + Sync + 'static) -> Self {
+        Self {
+            paths: Mutex::new(Vec::new()),
+            response: Box::new(response),
+        }
+    }
+
+    fn paths(&self) -> Vec<String> {
+        self.paths.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for RecordingHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        self.paths.lock().unwrap().push(path.to_string());
+        Ok(serde_json::from_value((self.response)(path))?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Recording HTTP client does not support unauthenticated requests");
+    }
+}
+
+fn test_session() -> IgSession {
+    IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    )
+}
+
 #[test]
 fn test_market_data_display() {
     // Create a MarketData instance
@@ -166,6 +223,7 @@ fn test_historical_prices_response() {
     // Create a HistoricalPrice instance
     let historical_price = ig_client::application::models::market::HistoricalPrice {
         snapshot_time: "2023-01-01T00:00:00".to_string(),
+        snapshot_time_utc: None,
         open_price: price_point.clone(),
         high_price: price_point.clone(),
         low_price: price_point.clone(),
@@ -185,6 +243,7 @@ fn test_historical_prices_response() {
         prices: vec![historical_price],
         instrument_type: InstrumentType::Currencies,
         allowance: Some(price_allowance),
+        metadata: None,
     };
 
     // Verify structure was created correctly
@@ -291,3 +350,620 @@ fn test_market_navigation_response() {
     assert_eq!(nav_response.markets.len(), 1);
     assert_eq!(nav_response.markets[0].epic, "EPIC123");
 }
+
+#[tokio::test]
+async fn test_get_markets_chunks_epics() {
+    let client = Arc::new(RecordingHttpClient::new(|_| json!({ "marketDetails": [] })));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+
+    let epics: Vec<String> = (0..120).map(|i| format!("EPIC{i}")).collect();
+    let result = service.get_markets(&test_session(), &epics).await.unwrap();
+    assert!(result.is_empty());
+
+    let paths = client.paths();
+    assert_eq!(paths.len(), 3);
+    assert!(paths[0].starts_with("markets?epics=EPIC0,EPIC1,"));
+    assert!(paths[0].ends_with("EPIC49&filter=ALL"));
+    assert!(paths[1].starts_with("markets?epics=EPIC50,"));
+    assert!(paths[2].ends_with("EPIC119&filter=ALL"));
+}
+
+#[tokio::test]
+async fn test_get_markets_keeps_input_order() {
+    let client = Arc::new(RecordingHttpClient::new(|path| {
+        let epics = path
+            .trim_start_matches("markets?epics=")
+            .trim_end_matches("&filter=ALL");
+        let details: Vec<Value> = epics
+            .split(',')
+            .rev()
+            .map(|epic| market_details_json(epic, epic, "-", 1.0, 1.1))
+            .collect();
+        json!({ "marketDetails": details })
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+
+    let epics: Vec<String> = (0..60).map(|i| format!("EPIC{i}")).collect();
+    let result = service.get_markets(&test_session(), &epics).await.unwrap();
+    let returned: Vec<String> = result.into_iter().map(|d| d.instrument.epic).collect();
+    assert_eq!(returned, epics);
+
+    // A single batch keeps the same order
+    let result = service
+        .get_multiple_market_details(&test_session(), &epics[..3])
+        .await
+        .unwrap();
+    let returned: Vec<String> = result.into_iter().map(|d| d.instrument.epic).collect();
+    assert_eq!(returned, epics[..3]);
+    assert_eq!(client.paths().len(), 3);
+}
+
+#[tokio::test]
+async fn test_get_markets_empty_epics() {
+    let client = Arc::new(RecordingHttpClient::new(|_| json!({ "marketDetails": [] })));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+
+    let result = service.get_markets(&test_session(), &[]).await.unwrap();
+    assert!(result.is_empty());
+    assert!(client.paths().is_empty());
+}
+
+fn price_page(page_number: i64, total_pages: i64) -> Value {
+    json!({
+        "prices": [{
+            "snapshotTime": "2025/01/02 10:00:00",
+            "snapshotTimeUTC": "2025-01-02T09:00:00",
+            "openPrice": { "bid": 1.0, "ask": 1.1, "lastTraded": null },
+            "closePrice": { "bid": 1.0, "ask": 1.1, "lastTraded": null },
+            "highPrice": { "bid": 1.0, "ask": 1.1, "lastTraded": null },
+            "lowPrice": { "bid": 1.0, "ask": 1.1, "lastTraded": null },
+            "lastTradedVolume": 10
+        }],
+        "instrumentType": "CURRENCIES",
+        "metadata": {
+            "allowance": {
+                "remainingAllowance": 9990,
+                "totalAllowance": 10000,
+                "allowanceExpiry": 600000
+            },
+            "size": 1,
+            "pageData": { "pageSize": 1, "pageNumber": page_number, "totalPages": total_pages }
+        }
+    })
+}
+
+#[test]
+fn test_historical_prices_query_string() {
+    let query = HistoricalPricesQuery::new(Resolution::Minute)
+        .with_range("2025-01-01T00:00:00", "2025-01-02T00:00:00")
+        .with_max(10)
+        .with_page(20, 2);
+    assert_eq!(
+        query.to_query_string(),
+        "resolution=MINUTE&from=2025-01-01T00:00:00&to=2025-01-02T00:00:00&max=10&pageSize=20&pageNumber=2"
+    );
+    assert_eq!(HistoricalPricesQuery::default().to_query_string(), "");
+}
+
+#[tokio::test]
+async fn test_get_historical_prices_query_metadata() {
+    let client = Arc::new(RecordingHttpClient::new(|_| price_page(1, 1)));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+
+    let query = HistoricalPricesQuery::new(Resolution::Hour).with_max(1);
+    let result = service
+        .get_historical_prices_query(&test_session(), "CS.D.EURUSD.TODAY.IP", &query)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client.paths(),
+        vec!["prices/CS.D.EURUSD.TODAY.IP?resolution=HOUR&max=1".to_string()]
+    );
+    assert_eq!(result.prices.len(), 1);
+    assert_eq!(
+        result.prices[0].snapshot_time_utc.as_deref(),
+        Some("2025-01-02T09:00:00")
+    );
+    assert_eq!(result.allowance().unwrap().remaining_allowance, 9990);
+    assert_eq!(result.page_data().unwrap().total_pages, 1);
+    assert!(!result.has_more_pages());
+}
+
+#[tokio::test]
+async fn test_get_all_historical_prices_walks_pages() {
+    let client = Arc::new(RecordingHttpClient::new(|path| {
+        let page = path.rsplit("pageNumber=").next().unwrap().parse().unwrap();
+        price_page(page, 3)
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+
+    let query = HistoricalPricesQuery::new(Resolution::Day).with_page(1, 1);
+    let result = service
+        .get_all_historical_prices(&test_session(), "CS.D.EURUSD.TODAY.IP", &query)
+        .await
+        .unwrap();
+
+    assert_eq!(result.prices.len(), 3);
+    assert_eq!(client.paths().len(), 3);
+    assert!(client.paths()[2].ends_with("pageNumber=3"));
+    assert_eq!(result.page_data().unwrap().page_number, 3);
+}
+
+#[tokio::test]
+async fn test_get_all_historical_prices_stops_at_the_announced_last_page() {
+    // Every page announces one more page after it
+    let client = Arc::new(RecordingHttpClient::new(|path| {
+        let page = path.rsplit("pageNumber=").next().unwrap().parse().unwrap();
+        price_page(page, page + 1)
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+
+    let query = HistoricalPricesQuery::new(Resolution::Day).with_page(1, 1);
+    let result = service
+        .get_all_historical_prices(&test_session(), "CS.D.EURUSD.TODAY.IP", &query)
+        .await
+        .unwrap();
+
+    assert_eq!(result.prices.len(), 2);
+    assert_eq!(client.paths().len(), 2);
+}
+
+#[tokio::test]
+async fn test_get_all_historical_prices_stops_on_an_empty_page() {
+    let client = Arc::new(RecordingHttpClient::new(|path| {
+        let page = path.rsplit("pageNumber=").next().unwrap().parse().unwrap();
+        let mut response = price_page(page, 5);
+        if page == 2 {
+            response["prices"] = json!([]);
+        }
+        response
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+
+    let query = HistoricalPricesQuery::new(Resolution::Day).with_page(1, 1);
+    let result = service
+        .get_all_historical_prices(&test_session(), "CS.D.EURUSD.TODAY.IP", &query)
+        .await
+        .unwrap();
+
+    assert_eq!(result.prices.len(), 1);
+    assert_eq!(client.paths().len(), 2);
+}
+
+#[tokio::test]
+async fn test_historical_prices_consume_price_allowance() {
+    let client = Arc::new(RecordingHttpClient::new(|path| {
+        let page_number = path.rsplit("pageNumber=").next().unwrap().parse().unwrap();
+        let mut page = price_page(page_number, 3);
+        page["metadata"]["allowance"]["remainingAllowance"] = json!(10000);
+        page
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client);
+    let session = test_session()
+        .with_rate_limiters(RateLimiterRegistry::new().for_account("PRICES", "ACC123"));
+
+    let query = HistoricalPricesQuery::new(Resolution::Day).with_page(1, 1);
+    service
+        .get_all_historical_prices(&session, "CS.D.EURUSD.TODAY.IP", &query)
+        .await
+        .unwrap();
+
+    // One point per price returned, not per request
+    let limiters = session.rate_limiters();
+    assert_eq!(limiters.historical_price.current_request_count().await, 3);
+    assert_eq!(limiters.app_non_trading.current_request_count().await, 0);
+}
+
+#[tokio::test]
+async fn test_historical_prices_sync_price_allowance() {
+    let client = Arc::new(RecordingHttpClient::new(|_| {
+        let mut page = price_page(1, 1);
+        page["metadata"]["allowance"]["remainingAllowance"] = json!(2000);
+        page
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client);
+    let session = test_session()
+        .with_rate_limiters(RateLimiterRegistry::new().for_account("PRICES_SYNC", "ACC123"));
+
+    let query = HistoricalPricesQuery::new(Resolution::Day);
+    service
+        .get_historical_prices_query(&session, "CS.D.EURUSD.TODAY.IP", &query)
+        .await
+        .unwrap();
+
+    // Points used by other clients of the API key count too, and with 80% of the
+    // allowance already used the next request waits for the reset
+    let limiter = &session.rate_limiters().historical_price;
+    assert_eq!(limiter.current_request_count().await, 8000);
+    let wait = limiter.time_until_next_request_ms().await;
+    assert!(wait > 500_000_000, "waiting {wait}ms");
+}
+
+#[tokio::test]
+async fn test_stream_prices_pages_lazily() {
+    let client = Arc::new(RecordingHttpClient::new(|path| {
+        let page = path.rsplit("pageNumber=").next().unwrap().parse().unwrap();
+        price_page(page, 3)
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let session = test_session();
+
+    let first: Vec<_> = service
+        .stream_prices(
+            &session,
+            "CS.D.EURUSD.TODAY.IP",
+            Resolution::Hour,
+            "2025-01-01T00:00:00",
+            "2025-01-02T00:00:00",
+        )
+        .take(2)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(first.len(), 2);
+    assert_eq!(client.paths().len(), 2);
+
+    let all: Vec<_> = service
+        .stream_prices(
+            &session,
+            "CS.D.EURUSD.TODAY.IP",
+            Resolution::Hour,
+            "2025-01-01T00:00:00",
+            "2025-01-02T00:00:00",
+        )
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(all.len(), 3);
+    assert_eq!(client.paths().len(), 5);
+    assert!(client.paths()[4].contains("resolution=HOUR"));
+    assert!(client.paths()[4].ends_with("pageNumber=3"));
+}
+
+fn step(value: f64) -> Value {
+    json!({ "unit": "POINTS", "value": value })
+}
+
+fn market_details_json(epic: &str, name: &str, expiry: &str, bid: f64, offer: f64) -> Value {
+    json!({
+        "instrument": {
+            "epic": epic,
+            "name": name,
+            "expiry": expiry,
+            "contractSize": "1",
+            "valueOfOnePip": "1.00",
+            "instrumentType": "OPT_INDICES",
+            "currencies": [{
+                "code": "EUR",
+                "symbol": "E",
+                "baseExchangeRate": 1.0,
+                "exchangeRate": 1.0,
+                "isDefault": true
+            }]
+        },
+        "snapshot": {
+            "marketStatus": "TRADEABLE",
+            "bid": bid,
+            "offer": offer
+        },
+        "dealingRules": {
+            "minStepDistance": step(1.0),
+            "minDealSize": step(0.1),
+            "minControlledRiskStopDistance": step(10.0),
+            "minNormalStopOrLimitDistance": step(5.0),
+            "maxStopOrLimitDistance": step(75.0),
+            "controlledRiskSpacing": step(5.0),
+            "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+            "trailingStopsPreference": "NOT_AVAILABLE"
+        }
+    })
+}
+
+fn search_market_json(epic: &str, name: &str, instrument_type: &str, expiry: &str) -> Value {
+    json!({
+        "epic": epic,
+        "instrumentName": name,
+        "instrumentType": instrument_type,
+        "expiry": expiry,
+        "marketStatus": "TRADEABLE"
+    })
+}
+
+#[tokio::test]
+async fn test_get_option_chain() {
+    let client = Arc::new(RecordingHttpClient::new(|path| {
+        if path.starts_with("markets?searchTerm=") {
+            json!({ "markets": [
+                search_market_json("DO.D.OTCDDAX.1.IP", "Daily Germany 40 25050 PUT", "OPT_INDICES", "22-MAY-25"),
+                search_market_json("DO.D.OTCDDAX.2.IP", "Daily Germany 40 25050 CALL", "OPT_INDICES", "22-MAY-25"),
+                search_market_json("DO.D.OTCDDAX.3.IP", "Daily Germany 40 24950 CALL", "OPT_INDICES", "22-MAY-25"),
+                search_market_json("DO.D.OTCDDAX.4.IP", "Daily Germany 40 24950 CALL", "OPT_INDICES", "23-MAY-25"),
+                search_market_json("IX.D.DAX.DAILY.IP", "Germany 40", "INDICES", "DFB")
+            ]})
+        } else {
+            json!({ "marketDetails": [
+                market_details_json("DO.D.OTCDDAX.1.IP", "Daily Germany 40 25050 PUT", "22-MAY-25", 40.0, 42.0),
+                market_details_json("DO.D.OTCDDAX.2.IP", "Daily Germany 40 25050 CALL", "22-MAY-25", 30.0, 31.5),
+                market_details_json("DO.D.OTCDDAX.3.IP", "Daily Germany 40 24950 CALL", "22-MAY-25", 80.0, 82.0)
+            ]})
+        }
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+
+    let chain = service
+        .get_option_chain(&test_session(), "Daily Germany 40", "22-MAY-25")
+        .await
+        .unwrap();
+
+    let paths = client.paths();
+    assert_eq!(paths.len(), 2);
+    assert_eq!(
+        paths[1],
+        "markets?epics=DO.D.OTCDDAX.1.IP,DO.D.OTCDDAX.2.IP,DO.D.OTCDDAX.3.IP&filter=ALL"
+    );
+
+    assert_eq!(chain.strikes.len(), 2);
+    assert_eq!(chain.strikes[0].strike, 24950.0);
+    assert!(chain.strikes[0].put.is_none());
+    let strike = chain.get(25050.0).unwrap();
+    assert_eq!(strike.call.as_ref().unwrap().epic, "DO.D.OTCDDAX.2.IP");
+    assert_eq!(strike.put.as_ref().unwrap().bid, Some(40.0));
+    assert_eq!(chain.epics().len(), 3);
+}
+
+#[tokio::test]
+async fn test_resolve_epic() {
+    let client = Arc::new(RecordingHttpClient::new(|_| {
+        json!({ "markets": [
+            search_market_json("IX.D.FTSE.CASH.IP", "FTSE 100 Cash", "INDICES", "-"),
+            search_market_json("IX.D.FTSE.DAILY.IP", "FTSE 100 Daily", "INDICES", "DFB"),
+            search_market_json("IX.D.FTSE.FWM1.IP", "FTSE 100 Jun 25", "INDICES", "JUN-25")
+        ]})
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client);
+
+    let best = service
+        .resolve_epic(&test_session(), "FTSE 100 daily")
+        .await
+        .unwrap();
+
+    assert_eq!(best.epic, "IX.D.FTSE.DAILY.IP");
+    assert_eq!(best.confidence, 1.0);
+}
+
+#[tokio::test]
+async fn test_resolve_underlying() {
+    let client = Arc::new(RecordingHttpClient::new(|path| {
+        if path.starts_with("markets?searchTerm=") {
+            json!({ "markets": [
+                search_market_json("OP.D.OTCDAX1.021100C.IP", "Germany 40 21100 CALL", "OPT_INDICES", "DEC-25"),
+                search_market_json("IX.D.DAX.MONTH1.IP", "Germany 40 Dec", "INDICES", "DEC-25"),
+                search_market_json("IX.D.DAX.DAILY.IP", "Germany 40", "INDICES", "DFB"),
+                search_market_json("IX.D.DAXVOL.DAILY.IP", "Germany 40 Volatility", "INDICES", "DFB")
+            ]})
+        } else {
+            market_details_json(
+                "OP.D.OTCDAX1.021100P.IP",
+                "Germany 40 21100 PUT",
+                "DEC-25",
+                10.0,
+                11.0,
+            )
+        }
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+
+    let underlying = service
+        .resolve_underlying(&test_session(), "OP.D.OTCDAX1.021100P.IP")
+        .await
+        .unwrap();
+
+    assert_eq!(underlying.epic, "IX.D.DAX.DAILY.IP");
+    assert_eq!(
+        client.paths(),
+        vec![
+            "markets/OP.D.OTCDAX1.021100P.IP",
+            "markets?searchTerm=Germany 40"
+        ]
+    );
+
+    let result = service
+        .resolve_underlying(&test_session(), "IX.D.DAX.DAILY.IP")
+        .await;
+    assert!(matches!(result, Err(AppError::InvalidInput(_))));
+}
+
+#[tokio::test]
+async fn test_resolve_epic_not_found() {
+    let client = Arc::new(RecordingHttpClient::new(|_| json!({ "markets": [] })));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client);
+
+    let result = service.resolve_epic(&test_session(), "Nothing").await;
+    assert!(matches!(result, Err(AppError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_search_markets_filtered() {
+    let client = Arc::new(RecordingHttpClient::new(|_| {
+        let mut closed = search_market_json("IX.D.DAX.IFMM.IP", "Germany 40 Cash", "INDICES", "-");
+        closed["marketStatus"] = json!("CLOSED");
+        json!({ "markets": [
+            search_market_json("IX.D.DAX.DAILY.IP", "Germany 40", "INDICES", "DFB"),
+            search_market_json("IX.D.DAX.MONTH1.IP", "Germany 40 Jun", "INDICES", "JUN-25"),
+            search_market_json("DO.D.OTCDDAX.1.IP", "Daily Germany 40 25050 PUT", "OPT_INDICES", "22-MAY-25"),
+            closed
+        ]})
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client);
+    let session = test_session();
+
+    let filter = MarketSearchFilter::new()
+        .with_instrument_type(InstrumentType::Indices)
+        .tradeable_only();
+    let result = service
+        .search_markets_filtered(&session, "Germany 40", &filter)
+        .await
+        .unwrap();
+    let epics: Vec<&str> = result.markets.iter().map(|m| m.epic.as_str()).collect();
+    assert_eq!(epics, vec!["IX.D.DAX.DAILY.IP", "IX.D.DAX.MONTH1.IP"]);
+
+    let filter = MarketSearchFilter::new().with_expiry(false);
+    let result = service
+        .search_markets_filtered(&session, "Germany 40", &filter)
+        .await
+        .unwrap();
+    let epics: Vec<&str> = result.markets.iter().map(|m| m.epic.as_str()).collect();
+    assert_eq!(epics, vec!["IX.D.DAX.DAILY.IP", "IX.D.DAX.IFMM.IP"]);
+
+    let result = service
+        .search_markets_filtered(&session, "Germany 40", &MarketSearchFilter::new())
+        .await
+        .unwrap();
+    assert_eq!(result.markets.len(), 4);
+}
+
+#[tokio::test]
+async fn test_get_market_details_bulk_keeps_errors_per_epic() {
+    let client = Arc::new(RecordingHttpClient::new(|path| {
+        let epics = path
+            .trim_start_matches("markets?epics=")
+            .trim_end_matches("&filter=ALL");
+        if epics.split(',').any(|epic| epic == "BAD.EPIC.50") {
+            return json!(null);
+        }
+        let details: Vec<Value> = epics
+            .split(',')
+            .filter(|epic| *epic != "MISSING.EPIC")
+            .map(|epic| market_details_json(epic, epic, "-", 1.0, 1.1))
+            .collect();
+        json!({ "marketDetails": details })
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+
+    let mut epics = vec!["MISSING.EPIC".to_string()];
+    epics.extend((0..49).map(|i| format!("GOOD.EPIC.{i}")));
+    epics.push("BAD.EPIC.50".to_string());
+    epics.push("GOOD.EPIC.0".to_string());
+
+    let results = service
+        .get_market_details_bulk(&test_session(), &epics)
+        .await;
+
+    assert_eq!(client.paths().len(), 2);
+    assert_eq!(results.len(), 51);
+    assert_eq!(
+        results["GOOD.EPIC.7"].as_ref().unwrap().instrument.epic,
+        "GOOD.EPIC.7"
+    );
+    assert!(matches!(
+        results["BAD.EPIC.50"].as_ref().unwrap_err().as_ref(),
+        AppError::Json(_)
+    ));
+    assert!(matches!(
+        results["MISSING.EPIC"].as_ref().unwrap_err().as_ref(),
+        AppError::NotFound
+    ));
+}
+
+#[tokio::test]
+async fn test_check_dealable() {
+    let client = Arc::new(RecordingHttpClient::new(|path| {
+        let mut details =
+            market_details_json("IX.D.DAX.DAILY.IP", "Germany 40", "DFB", 100.0, 101.0);
+        if path.ends_with("CLOSED.IP") {
+            details["snapshot"]["marketStatus"] = json!("EDITS_ONLY");
+            details["snapshot"]["offer"] = Value::Null;
+            details["dealingRules"]["marketOrderPreference"] = json!("NOT_AVAILABLE");
+        }
+        details
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let session = test_session();
+
+    let check = service
+        .check_dealable(&session, "IX.D.DAX.DAILY.IP", 1.0, Direction::Buy)
+        .await
+        .unwrap();
+    assert!(check.is_dealable());
+    assert!(check.ensure_dealable(&OrderType::Market).is_ok());
+    assert_eq!(client.paths(), vec!["markets/IX.D.DAX.DAILY.IP"]);
+
+    let check = service
+        .check_dealable(&session, "IX.D.DAX.CLOSED.IP", 0.05, Direction::Buy)
+        .await
+        .unwrap();
+    assert!(!check.is_dealable());
+    assert_eq!(
+        check.issues,
+        vec![
+            DealabilityIssue::MarketNotTradeable("EDITS_ONLY".to_string()),
+            DealabilityIssue::NoPrice(Direction::Buy),
+            DealabilityIssue::BelowMinDealSize {
+                size: 0.05,
+                min: 0.1
+            },
+            DealabilityIssue::MarketOrdersNotAvailable,
+        ]
+    );
+    match check.ensure_dealable(&OrderType::Limit) {
+        Err(AppError::InvalidInput(message)) => {
+            assert!(message.contains("EDITS_ONLY"));
+            assert!(!message.contains("market orders"));
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn test_dealability_market_orders_only_block_market_type() {
+    let mut details = market_details_json("IX.D.DAX.DAILY.IP", "Germany 40", "DFB", 100.0, 101.0);
+    details["dealingRules"]["marketOrderPreference"] = json!("NOT_AVAILABLE");
+    details["dealingRules"]["maxDealSize"] = json!(5.0);
+    let details: MarketDetails = serde_json::from_value(details).unwrap();
+
+    let check = details.check_dealable(2.0, &Direction::Sell);
+    assert!(!check.is_dealable());
+    assert!(check.is_dealable_for(&OrderType::Limit));
+    assert!(!check.is_dealable_for(&OrderType::Market));
+
+    let check = details.check_dealable(6.0, &Direction::Sell);
+    assert!(!check.is_dealable_for(&OrderType::Limit));
+}
+
+#[tokio::test]
+async fn test_get_expiry_calendar() {
+    let client = Arc::new(RecordingHttpClient::new(|_| {
+        json!({ "markets": [
+            search_market_json("DO.D.OTCDDAX.2.IP", "Germany 40 25050 CALL", "OPT_INDICES", "23-MAY-25"),
+            search_market_json("DO.D.OTCDDAX.1.IP", "Germany 40 25050 PUT", "OPT_INDICES", "22-MAY-25"),
+            search_market_json("DO.D.OTCDDAX.3.IP", "Germany 40 24950 CALL", "OPT_INDICES", "22-MAY-25"),
+            search_market_json("IX.D.DAX.MONTH2.IP", "Germany 40 Sep", "INDICES", "SEP-25"),
+            search_market_json("IX.D.DAX.DAILY.IP", "Germany 40", "INDICES", "DFB"),
+            search_market_json("IX.D.OTHER.IP", "Wall Street Germany 40", "INDICES", "JUN-25")
+        ]})
+    }));
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client);
+
+    let calendar = service
+        .get_expiry_calendar(&test_session(), "Germany 40")
+        .await
+        .unwrap();
+
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    assert_eq!(
+        calendar.dates(),
+        vec![date(2025, 5, 22), date(2025, 5, 23), date(2025, 9, 1)]
+    );
+    assert_eq!(calendar.expiries[0].label, "22-MAY-25");
+    assert_eq!(calendar.expiries[0].epics.len(), 2);
+
+    let today = date(2025, 5, 22);
+    assert_eq!(calendar.next(today).unwrap().days_to_expiry(today), 0);
+    assert_eq!(calendar.upcoming(date(2025, 5, 23)).count(), 2);
+    assert_eq!(
+        calendar.next_with_min_days(today, 7).unwrap().label,
+        "SEP-25"
+    );
+    assert!(calendar.next(date(2025, 9, 2)).is_none());
+}
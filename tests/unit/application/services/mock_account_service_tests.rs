@@ -0,0 +1,308 @@
+use futures::StreamExt;
+use ig_client::application::models::account::{
+    Account, AccountBalance, AccountTransaction, Activity, ActivityQuery, Position, ReconciledDeal,
+    TransactionType, WorkingOrder,
+};
+use ig_client::application::services::AccountService;
+use ig_client::application::services::mock_account_service::MockAccountService;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::utils::currency::CurrencyConverter;
+use serde_json::json;
+
+fn session() -> IgSession {
+    IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    )
+}
+
+fn account() -> Account {
+    serde_json::from_value(json!({
+        "accountId": "ACC123",
+        "accountName": "Demo",
+        "accountType": "CFD",
+        "balance": { "balance": 1000.0, "deposit": 100.0, "profitLoss": -6.0, "available": 894.0 },
+        "currency": "EUR",
+        "status": "ENABLED",
+        "preferred": true
+    }))
+    .unwrap()
+}
+
+fn position() -> Position {
+    serde_json::from_str(
+        &std::fs::read_to_string("tests/unit/application/models/position.json").unwrap(),
+    )
+    .unwrap()
+}
+
+fn activity(date: &str) -> Activity {
+    serde_json::from_value(json!({
+        "date": date,
+        "dealId": "DEAL1",
+        "type": "POSITION",
+        "details": { "actions": [], "size": 1.0 }
+    }))
+    .unwrap()
+}
+
+fn transaction(date_utc: &str, transaction_type: &str, pnl: &str) -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": "2025-07-01",
+        "dateUtc": date_utc,
+        "openDateUtc": date_utc,
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": pnl,
+        "transactionType": transaction_type,
+        "reference": "REF",
+        "openLevel": "0",
+        "closeLevel": "0",
+        "size": "+1",
+        "currency": "EUR",
+        "cashTransaction": false
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_mock_account_service_serves_fixtures() {
+    let service = MockAccountService::new()
+        .with_account(account())
+        .with_positions(vec![position()])
+        .with_margin("DIAAAAT9SU2UMBB", 50.0);
+    let session = session();
+
+    let accounts = service.get_accounts(&session).await.unwrap().accounts;
+    assert_eq!(accounts[0].balance.balance.amount, 1000.0);
+
+    let converter = CurrencyConverter::new("EUR");
+    let positions = service
+        .positions_with_pnl(&session, &converter)
+        .await
+        .unwrap();
+    assert_eq!(positions.positions[0].pnl, Some(-6.0));
+    assert!(
+        service
+            .find_position_by_reference(&session, "RZ0RQ1JZ5VN38JC")
+            .await
+            .unwrap()
+            .is_some()
+    );
+
+    let summary = service
+        .get_margin_summary(&session, &converter)
+        .await
+        .unwrap();
+    assert_eq!(summary.used_margin, 50.0);
+    assert_eq!(summary.equity, 994.0);
+
+    service
+        .set_balance("ACC123", AccountBalance::new("EUR", 500.0, 0.0, 0.0, 500.0))
+        .unwrap();
+    service.set_positions(Vec::new());
+    let accounts = service.get_accounts(&session).await.unwrap().accounts;
+    assert_eq!(accounts[0].balance.balance.amount, 500.0);
+    assert!(
+        service
+            .get_positions(&session)
+            .await
+            .unwrap()
+            .positions
+            .is_empty()
+    );
+    assert!(matches!(
+        service.set_balance("OTHER", accounts[0].balance.clone()),
+        Err(AppError::NotFound)
+    ));
+
+    let other = IgSession::new("CST".to_string(), "XST".to_string(), "OTHER".to_string());
+    assert!(matches!(
+        service.get_margin_summary(&other, &converter).await,
+        Err(AppError::NotFound)
+    ));
+    assert_eq!(service.calls(), 7);
+}
+
+#[tokio::test]
+async fn test_mock_account_service_histories() {
+    let service = MockAccountService::new()
+        .with_activities(vec![
+            activity("2025-07-01T09:00:00"),
+            activity("2025-07-02T09:00:00"),
+        ])
+        .with_transactions(vec![
+            transaction("2025-07-01T10:00:00", "DEAL", "E10.00"),
+            transaction("2025-07-01T11:00:00", "DEPO", "E500.00"),
+            transaction("2025-07-01T12:00:00", "DEAL", "E-5.00"),
+            transaction("2025-07-03T12:00:00", "DEAL", "E1.00"),
+        ]);
+    let session = session();
+
+    let summary = service
+        .get_activity(&session, "2025-07-01T00:00:00Z", "2025-07-01T23:59:59Z")
+        .await
+        .unwrap();
+    assert_eq!(summary.activities.len(), 1);
+    assert!(summary.activities[0].details.is_none());
+    let detailed = service
+        .get_activity_with_details(&session, "2025-07-01T00:00:00", "2025-07-03T00:00:00")
+        .await
+        .unwrap();
+    assert_eq!(detailed.activities.len(), 2);
+    assert!(detailed.activities[0].details.is_some());
+    let streamed: Vec<_> = service
+        .stream_activity(&session, &ActivityQuery::new("2025-07-02T00:00:00"))
+        .collect()
+        .await;
+    assert_eq!(streamed.len(), 1);
+
+    let page = service
+        .get_transactions(&session, "2025-07-01T00:00:00", "2025-07-02T00:00:00", 2, 2)
+        .await
+        .unwrap();
+    assert_eq!(page.transactions.len(), 1);
+    assert_eq!(page.metadata.size, 3);
+    assert_eq!(page.metadata.page_data.total_pages, 2);
+    assert!(!page.metadata.page_data.has_more_pages());
+
+    let deals: Vec<_> = service
+        .stream_transactions(
+            &session,
+            "2025-07-01T00:00:00",
+            "2025-07-31T00:00:00",
+            TransactionType::AllDeal,
+        )
+        .collect()
+        .await;
+    assert_eq!(deals.len(), 3);
+}
+
+#[tokio::test]
+async fn test_mock_account_service_queued_failures() {
+    let service = MockAccountService::new().with_account(account());
+    let session = session();
+    service.fail_next(AppError::RateLimitExceeded);
+    service.fail_next(AppError::Unauthorized);
+
+    assert!(matches!(
+        service.get_positions(&session).await,
+        Err(AppError::RateLimitExceeded)
+    ));
+    let streamed: Vec<_> = service
+        .stream_transactions(&session, "2025-07-01", "2025-07-02", TransactionType::All)
+        .collect()
+        .await;
+    assert!(matches!(streamed[..], [Err(AppError::Unauthorized)]));
+    assert!(service.get_accounts(&session).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_reconcile_deal_references() {
+    let order: WorkingOrder = serde_json::from_value(json!({
+        "workingOrderData": {
+            "dealId": "ORDER1",
+            "direction": "BUY",
+            "epic": "IX.D.DAX.DAILY.IP",
+            "orderSize": 1.0,
+            "orderLevel": 18000.0,
+            "timeInForce": "GOOD_TILL_CANCELLED",
+            "createdDate": "2025/07/01 09:00:00:000",
+            "createdDateUTC": "2025-07-01T08:00:00",
+            "guaranteedStop": false,
+            "orderType": "LIMIT",
+            "currencyCode": "EUR",
+            "dma": false,
+            "dealReference": "REF_PENDING"
+        },
+        "marketData": {
+            "instrumentName": "Germany 40",
+            "exchangeId": "XETRA",
+            "expiry": "DFB",
+            "marketStatus": "TRADEABLE",
+            "epic": "IX.D.DAX.DAILY.IP",
+            "instrumentType": "INDICES",
+            "lotSize": 1.0,
+            "high": 18100.0,
+            "low": 17900.0,
+            "percentageChange": 0.1,
+            "netChange": 10.0,
+            "bid": 18000.0,
+            "offer": 18001.0,
+            "updateTime": "09:00:00",
+            "updateTimeUTC": "08:00:00",
+            "delayTime": 0,
+            "streamingPricesAvailable": true,
+            "scalingFactor": 1
+        }
+    }))
+    .unwrap();
+    let activities: Vec<Activity> = serde_json::from_value(json!([
+        {
+            "date": "2025-07-01T09:00:00",
+            "dealId": "DEAL2",
+            "dealReference": "REF_CLOSED",
+            "type": "POSITION",
+            "status": "ACCEPTED",
+            "details": { "actions": [{ "actionType": "POSITION_OPENED", "affectedDealId": "DEAL2" }] }
+        },
+        {
+            "date": "2025-07-01T10:00:00",
+            "dealId": "DEAL3",
+            "dealReference": "CLOSING_REF",
+            "type": "POSITION",
+            "status": "ACCEPTED",
+            "details": { "actions": [{ "actionType": "POSITION_CLOSED", "affectedDealId": "DEAL2" }] }
+        },
+        {
+            "date": "2025-07-01T11:00:00",
+            "dealReference": "REF_REJECTED",
+            "type": "POSITION",
+            "status": "REJECTED"
+        }
+    ]))
+    .unwrap();
+    let service = MockAccountService::new()
+        .with_positions(vec![position()])
+        .with_working_orders(vec![order])
+        .with_activities(activities);
+
+    let references: Vec<String> = [
+        "RZ0RQ1JZ5VN38JC",
+        "REF_PENDING",
+        "REF_CLOSED",
+        "REF_REJECTED",
+        "REF_LOST",
+    ]
+    .iter()
+    .map(|reference| reference.to_string())
+    .collect();
+    let reconciliation = service
+        .reconcile(&session(), &references, "2025-07-01T00:00:00")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        reconciliation.open().collect::<Vec<_>>(),
+        ["RZ0RQ1JZ5VN38JC"]
+    );
+    assert!(matches!(
+        reconciliation.get("REF_PENDING"),
+        Some(ReconciledDeal::Pending(order)) if order.working_order_data.deal_id == "ORDER1"
+    ));
+    match reconciliation.get("REF_CLOSED") {
+        Some(ReconciledDeal::Closed { deal_id, closed_at }) => {
+            assert_eq!(deal_id.as_deref(), Some("DEAL2"));
+            assert_eq!(closed_at.as_deref(), Some("2025-07-01T10:00:00"));
+        }
+        other => panic!("unexpected state {other:?}"),
+    }
+    assert!(matches!(
+        reconciliation.get("REF_REJECTED"),
+        Some(ReconciledDeal::Rejected)
+    ));
+    assert_eq!(reconciliation.unknown().collect::<Vec<_>>(), ["REF_LOST"]);
+    assert_eq!(reconciliation.closed().count(), 1);
+}
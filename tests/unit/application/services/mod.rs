@@ -1,9 +1,32 @@
 mod account_listener_tests;
 mod account_service_tests;
+mod balance_tracker_tests;
+mod catalog_refresher_tests;
 mod chart_listener_tests;
+mod journaled_order_service_tests;
+mod margin_monitor_tests;
 mod market_listener_tests;
+mod market_refresher_tests;
+mod market_scanner_tests;
 mod market_service_tests;
+mod mock_account_service_tests;
+mod navigation_crawler_tests;
+mod order_metrics_tests;
+mod order_scheduler_tests;
 mod order_service_tests;
+mod order_tracker_tests;
+mod paper_order_service_tests;
+mod portfolio_service_tests;
+mod positions_cache_tests;
+mod price_backfill_tests;
 mod price_listener_tests;
+mod retention_compactor_tests;
+mod risk_guard_tests;
+mod snapshot_recorder_tests;
+mod tick_recorder_tests;
+mod trailing_stop_manager_tests;
+mod transaction_fetcher_tests;
+mod transaction_sync_tests;
+mod watchlist_service_tests;
 
 mod account_service_impl_tests;
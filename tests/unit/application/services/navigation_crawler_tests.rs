@@ -0,0 +1,270 @@
+use ig_client::application::services::market_service::MarketServiceImpl;
+use ig_client::application::services::navigation_cache::NavigationCache;
+use ig_client::application::services::navigation_crawler::{
+    CrawlCheckpoint, CrawlStatus, NavigationCrawler,
+};
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::presentation::extract_markets_from_hierarchy;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use ig_client::utils::rate_limiter::{RateLimitType, create_rate_limiter};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+// Mock HTTP client serving a small navigation tree:
+// root -> [A, B]; A -> [A1] + market EPIC.A; A1 -> market EPIC.A1; B -> market EPIC.B
+struct MockHttpClient {
+    paths: Mutex<Vec<String>>,
+    rate_limited: Mutex<Vec<String>>,
+}
+
+impl MockHttpClient {
+    fn new() -> Self {
+        Self {
+            paths: Mutex::new(Vec::new()),
+            rate_limited: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn paths(&self) -> Vec<String> {
+        self.paths.lock().unwrap().clone()
+    }
+}
+
+fn market(epic: &str) -> Value {
+    json!({
+        "epic": epic,
+        "instrumentName": format!("Market {epic}"),
+        "instrumentType": "INDICES",
+        "expiry": "-",
+        "marketStatus": "TRADEABLE"
+    })
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for MockHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        {
+            let mut limited = self.rate_limited.lock().unwrap();
+            if let Some(pos) = limited.iter().position(|p| p == path) {
+                limited.remove(pos);
+                return Err(AppError::RateLimitExceeded);
+            }
+        }
+        self.paths.lock().unwrap().push(path.to_string());
+        let response = match path {
+            "marketnavigation" => json!({
+                "nodes": [{ "id": "A", "name": "Indices" }, { "id": "B", "name": "Shares" }],
+                "markets": null
+            }),
+            "marketnavigation/A" => json!({
+                "nodes": [{ "id": "A1", "name": "Europe" }],
+                "markets": [market("EPIC.A")]
+            }),
+            "marketnavigation/A1" => json!({ "nodes": null, "markets": [market("EPIC.A1")] }),
+            "marketnavigation/B" => json!({ "nodes": [], "markets": [market("EPIC.B")] }),
+            _ => return Err(AppError::NotFound),
+        };
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Mock HTTP client should not be called without authentication");
+    }
+}
+
+fn setup() -> (
+    Arc<MockHttpClient>,
+    MarketServiceImpl<MockHttpClient>,
+    IgSession,
+) {
+    let client = Arc::new(MockHttpClient::new());
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let session = IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    );
+    (client, service, session)
+}
+
+fn limiter() -> Arc<ig_client::utils::rate_limiter::RateLimiter> {
+    create_rate_limiter(RateLimitType::NonTradingApp, None)
+}
+
+#[tokio::test]
+async fn test_crawl_full_tree_breadth_first() {
+    let (client, service, session) = setup();
+    let crawler = NavigationCrawler::new(&service).with_rate_limiter(limiter());
+
+    let tree = crawler.crawl(&session).await.unwrap();
+
+    assert_eq!(
+        client.paths(),
+        vec![
+            "marketnavigation",
+            "marketnavigation/A",
+            "marketnavigation/B",
+            "marketnavigation/A1"
+        ]
+    );
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree[0].id, "A");
+    assert_eq!(tree[0].children[0].id, "A1");
+    let mut epics: Vec<String> = extract_markets_from_hierarchy(&tree)
+        .into_iter()
+        .map(|m| m.epic)
+        .collect();
+    epics.sort();
+    assert_eq!(epics, vec!["EPIC.A", "EPIC.A1", "EPIC.B"]);
+}
+
+#[tokio::test]
+async fn test_crawl_with_filter() {
+    let (client, service, session) = setup();
+    let crawler = NavigationCrawler::new(&service)
+        .with_rate_limiter(limiter())
+        .with_filter(|node, _depth| node.name != "Shares");
+
+    let tree = crawler.crawl(&session).await.unwrap();
+
+    assert!(!client.paths().contains(&"marketnavigation/B".to_string()));
+    assert_eq!(tree.len(), 1);
+    assert_eq!(tree[0].name, "Indices");
+}
+
+#[tokio::test]
+async fn test_crawl_resumes_from_checkpoint() {
+    let (client, service, session) = setup();
+    let crawler = NavigationCrawler::new(&service)
+        .with_rate_limiter(limiter())
+        .with_max_requests(2);
+
+    let mut checkpoint = CrawlCheckpoint::new();
+    let status = crawler.run(&session, &mut checkpoint).await.unwrap();
+    assert_eq!(status, CrawlStatus::Paused { pending: 2 });
+
+    // Round-trip the checkpoint through JSON as a persisted crawl would
+    let json = serde_json::to_string(&checkpoint).unwrap();
+    let mut checkpoint: CrawlCheckpoint = serde_json::from_str(&json).unwrap();
+
+    let status = crawler.run(&session, &mut checkpoint).await.unwrap();
+    assert_eq!(status, CrawlStatus::Complete);
+    assert_eq!(client.paths().len(), 4);
+    assert_eq!(checkpoint.requests, 4);
+    assert_eq!(extract_markets_from_hierarchy(&checkpoint.tree()).len(), 3);
+}
+
+#[tokio::test]
+async fn test_crawl_resume_fetches_each_pending_node_once() {
+    let (client, service, session) = setup();
+    let crawler = NavigationCrawler::new(&service)
+        .with_rate_limiter(limiter())
+        .with_max_requests(2);
+
+    let mut checkpoint = CrawlCheckpoint::new();
+    crawler.run(&session, &mut checkpoint).await.unwrap();
+    // A checkpoint listing a pending node twice, and a node that was already crawled
+    let pending = checkpoint.pending[0].clone();
+    checkpoint.pending.push_back(pending);
+    let mut crawled = checkpoint.pending[0].clone();
+    crawled.id = Some("A".to_string());
+    checkpoint.pending.push_back(crawled);
+
+    let crawler = NavigationCrawler::new(&service).with_rate_limiter(limiter());
+    let status = crawler.run(&session, &mut checkpoint).await.unwrap();
+    assert_eq!(status, CrawlStatus::Complete);
+    assert_eq!(client.paths().len(), 4);
+    assert_eq!(checkpoint.requests, 4);
+    assert_eq!(extract_markets_from_hierarchy(&checkpoint.tree()).len(), 3);
+}
+
+#[tokio::test]
+async fn test_crawl_pauses_on_rate_limit() {
+    let (client, service, session) = setup();
+    client
+        .rate_limited
+        .lock()
+        .unwrap()
+        .push("marketnavigation/B".to_string());
+    let crawler = NavigationCrawler::new(&service).with_rate_limiter(limiter());
+
+    let mut checkpoint = CrawlCheckpoint::new();
+    let status = crawler.run(&session, &mut checkpoint).await.unwrap();
+    assert!(matches!(status, CrawlStatus::Paused { .. }));
+    assert_eq!(checkpoint.pending[0].id.as_deref(), Some("B"));
+}
+
+#[tokio::test]
+async fn test_checkpoint_hierarchy_queries() {
+    let (_client, service, session) = setup();
+    let crawler = NavigationCrawler::new(&service).with_rate_limiter(limiter());
+
+    let mut checkpoint = CrawlCheckpoint::new();
+    crawler.run(&session, &mut checkpoint).await.unwrap();
+
+    assert_eq!(checkpoint.node("A1").unwrap().name, "Europe");
+    let children: Vec<&str> = checkpoint
+        .children("A")
+        .iter()
+        .map(|node| node.name.as_str())
+        .collect();
+    assert_eq!(children, vec!["Europe"]);
+    let epics: Vec<&str> = checkpoint
+        .markets_under("A")
+        .iter()
+        .map(|market| market.epic.as_str())
+        .collect();
+    assert_eq!(epics, vec!["EPIC.A", "EPIC.A1"]);
+    assert_eq!(checkpoint.find_markets(|m| m.epic.ends_with('B')).len(), 1);
+}
+
+#[tokio::test]
+async fn test_navigation_cache_serves_fresh_tree_and_resumes_partial_crawl() {
+    let (client, service, session) = setup();
+    let path = std::env::temp_dir().join(format!("ig_nav_cache_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let cache = NavigationCache::new(&path);
+
+    // A paused crawl is saved and reported as rate limited
+    let limited = NavigationCrawler::new(&service)
+        .with_rate_limiter(limiter())
+        .with_max_requests(2);
+    let result = cache.get_or_crawl(&limited, &session).await;
+    assert!(matches!(result, Err(AppError::RateLimitExceeded)));
+    assert!(!cache.load().unwrap().unwrap().checkpoint.is_complete());
+
+    // The next call resumes it instead of starting over
+    let crawler = NavigationCrawler::new(&service).with_rate_limiter(limiter());
+    let cached = cache.get_or_crawl(&crawler, &session).await.unwrap();
+    assert!(cached.checkpoint.is_complete());
+    assert_eq!(client.paths().len(), 4);
+
+    // A fresh cache is served without any request
+    let tree = cache.tree(&crawler, &session).await.unwrap();
+    assert_eq!(extract_markets_from_hierarchy(&tree).len(), 3);
+    assert_eq!(client.paths().len(), 4);
+
+    // An expired cache is crawled again
+    let expired = NavigationCache::new(&path).with_max_age(chrono::Duration::zero());
+    expired.get_or_crawl(&crawler, &session).await.unwrap();
+    assert_eq!(client.paths().len(), 8);
+
+    std::fs::remove_file(&path).unwrap();
+}
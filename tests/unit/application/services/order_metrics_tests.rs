@@ -0,0 +1,188 @@
+use ig_client::application::models::order::{ClosePositionRequest, CreateOrderRequest, Direction};
+use ig_client::application::services::OrderService;
+use ig_client::application::services::order_metrics::MeteredOrderService;
+use ig_client::application::services::order_service::OrderServiceImpl;
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const EPIC: &str = "IX.D.DAX.DAILY.IP";
+
+// Mock HTTP client accepting closes and the first order, rejecting the following ones
+struct DealingHttpClient {
+    orders: Mutex<usize>,
+}
+
+fn confirmation(deal_reference: &str, deal_status: &str, reason: &str) -> Value {
+    json!({
+        "date": "2025-05-22T09:00:00.000",
+        "status": "OPEN",
+        "reason": reason,
+        "dealStatus": deal_status,
+        "epic": EPIC,
+        "expiry": "-",
+        "dealReference": deal_reference,
+        "dealId": "DEAL1",
+        "level": 18200.0,
+        "size": 1.0,
+        "direction": "BUY",
+        "guaranteedStop": false,
+        "trailingStop": false
+    })
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for DealingHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        let response = match (method, path) {
+            (Method::POST, "positions/otc") => {
+                let mut orders = self.orders.lock().unwrap();
+                *orders += 1;
+                json!({ "dealReference": format!("ORDER{}", *orders) })
+            }
+            (Method::GET, "positions/DIAAAAT9SU2UMBB") => {
+                serde_json::from_str(include_str!("../models/position.json"))?
+            }
+            (Method::DELETE, "positions/otc") => json!({ "dealReference": "CLOSE1" }),
+            (Method::GET, "confirms/ORDER1") => confirmation("ORDER1", "ACCEPTED", "SUCCESS"),
+            (Method::GET, "confirms/CLOSE1") => confirmation("CLOSE1", "ACCEPTED", "SUCCESS"),
+            (Method::GET, path) if path.starts_with("confirms/") => confirmation(
+                path.trim_start_matches("confirms/"),
+                "REJECTED",
+                "INSUFFICIENT_FUNDS",
+            ),
+            _ => return Err(AppError::NotFound),
+        };
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        unimplemented!()
+    }
+}
+
+fn test_session() -> IgSession {
+    IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    )
+}
+
+#[tokio::test]
+async fn test_metered_order_service_counts_deals() {
+    let client = Arc::new(DealingHttpClient {
+        orders: Mutex::new(0),
+    });
+    let service =
+        MeteredOrderService::new(OrderServiceImpl::new(Arc::new(Config::default()), client));
+    let session = test_session();
+    let order =
+        CreateOrderRequest::market(EPIC.to_string(), Direction::Buy, 1.0, "EUR".to_string());
+
+    assert_eq!(service.snapshot().reject_rate(), None);
+    for _ in 0..3 {
+        let response = service.create_order(&session, &order).await.unwrap();
+        service
+            .get_order_confirmation(&session, &response.deal_reference)
+            .await
+            .unwrap();
+    }
+    // A confirmation read twice is only counted once
+    service
+        .get_order_confirmation(&session, "ORDER2")
+        .await
+        .unwrap();
+    let close =
+        ClosePositionRequest::for_deal("DEAL1", &Direction::Buy, 1.0).with_market(EPIC, "EUR");
+    service.close_position(&session, &close).await.unwrap();
+    assert!(service.find_working_order(&session, "X").await.is_err());
+    assert!(service.delete_working_order(&session, "X").await.is_err());
+
+    let snapshot = service.snapshot();
+    assert_eq!(snapshot.submitted, 3);
+    assert_eq!(snapshot.accepted, 1);
+    assert_eq!(snapshot.rejected, 2);
+    assert_eq!(snapshot.closed, 1);
+    assert_eq!(snapshot.errors, 1);
+    assert_eq!(snapshot.rejection_reasons["INSUFFICIENT_FUNDS"], 2);
+    assert!(snapshot.average_confirmation_latency.is_some());
+    assert!((snapshot.reject_rate().unwrap() - 2.0 / 3.0).abs() < 1e-9);
+
+    service.reset();
+    assert_eq!(service.snapshot().submitted, 0);
+}
+
+#[tokio::test]
+async fn test_metered_order_service_counts_both_orders_of_a_reversal() {
+    let client = Arc::new(DealingHttpClient {
+        orders: Mutex::new(0),
+    });
+    let service =
+        MeteredOrderService::new(OrderServiceImpl::new(Arc::new(Config::default()), client));
+
+    let reversal = service
+        .reverse_position(&test_session(), "DIAAAAT9SU2UMBB")
+        .await
+        .unwrap();
+    assert!(reversal.is_complete());
+
+    let snapshot = service.snapshot();
+    assert_eq!(snapshot.closed, 1);
+    assert_eq!(snapshot.submitted, 1);
+    assert_eq!(snapshot.accepted, 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_metered_order_service_forgets_unconfirmed_deals() {
+    let client = Arc::new(DealingHttpClient {
+        orders: Mutex::new(0),
+    });
+    let service = MeteredOrderService::new(
+        OrderServiceImpl::new(Arc::new(Config::default()), client)
+            .with_confirmation_timeout(Duration::from_secs(5)),
+    );
+    let session = test_session();
+    let order =
+        CreateOrderRequest::market(EPIC.to_string(), Direction::Buy, 1.0, "EUR".to_string());
+
+    service.create_order(&session, &order).await.unwrap();
+    tokio::time::advance(Duration::from_secs(6)).await;
+    service.create_order(&session, &order).await.unwrap();
+    // ORDER1 was evicted when ORDER2 was submitted
+    service
+        .get_order_confirmation(&session, "ORDER1")
+        .await
+        .unwrap();
+    tokio::time::advance(Duration::from_secs(6)).await;
+    // ORDER2 is past the timeout when its confirmation is read
+    service
+        .get_order_confirmation(&session, "ORDER2")
+        .await
+        .unwrap();
+
+    let snapshot = service.snapshot();
+    assert_eq!(snapshot.submitted, 2);
+    assert_eq!(snapshot.accepted, 0);
+    assert_eq!(snapshot.rejected, 0);
+    assert_eq!(snapshot.average_confirmation_latency, None);
+}
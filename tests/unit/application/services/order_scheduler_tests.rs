@@ -0,0 +1,247 @@
+use chrono::{Duration, TimeZone, Utc};
+use ig_client::application::models::order::{CreateOrderRequest, Direction};
+use ig_client::application::models::working_order::CreateWorkingOrderRequest;
+use ig_client::application::services::market_service::MarketServiceImpl;
+use ig_client::application::services::order_scheduler::{
+    OrderScheduler, ScheduleTrigger, ScheduledRequest,
+};
+use ig_client::application::services::order_service::OrderServiceImpl;
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+const EPIC: &str = "IX.D.DAX.DAILY.IP";
+
+// Mock HTTP client serving one market whose status can be changed, and accepting orders
+struct SchedulerHttpClient {
+    market_status: Mutex<String>,
+    requests: Mutex<Vec<(Method, String)>>,
+}
+
+impl SchedulerHttpClient {
+    fn requests(&self) -> Vec<(Method, String)> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for SchedulerHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        self.requests
+            .lock()
+            .unwrap()
+            .push((method.clone(), path.to_string()));
+        let response = match (method, path) {
+            (Method::GET, path) if path == format!("markets/{EPIC}") => json!({
+                "instrument": {
+                    "epic": EPIC,
+                    "name": "Germany 40",
+                    "expiry": "DFB",
+                    "contractSize": "1",
+                    "valueOfOnePip": "1.00"
+                },
+                "snapshot": {
+                    "marketStatus": self.market_status.lock().unwrap().clone(),
+                    "bid": 18000.0,
+                    "offer": 18001.0
+                },
+                "dealingRules": {
+                    "minStepDistance": { "unit": "POINTS", "value": 1.0 },
+                    "minDealSize": { "unit": "POINTS", "value": 0.5 },
+                    "minControlledRiskStopDistance": { "unit": "POINTS", "value": 1.0 },
+                    "minNormalStopOrLimitDistance": { "unit": "POINTS", "value": 1.0 },
+                    "maxStopOrLimitDistance": { "unit": "POINTS", "value": 100.0 },
+                    "controlledRiskSpacing": { "unit": "POINTS", "value": 1.0 },
+                    "marketOrderPreference": "AVAILABLE_DEFAULT_ON",
+                    "trailingStopsPreference": "AVAILABLE"
+                }
+            }),
+            (Method::POST, "positions/otc") => json!({ "dealReference": "ORDER1" }),
+            (Method::POST, "workingorders/otc") => json!({ "dealReference": "WORKING1" }),
+            _ => return Err(AppError::NotFound),
+        };
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        unimplemented!()
+    }
+}
+
+fn services(
+    market_status: &str,
+) -> (
+    Arc<SchedulerHttpClient>,
+    OrderServiceImpl<SchedulerHttpClient>,
+    MarketServiceImpl<SchedulerHttpClient>,
+) {
+    let config = Arc::new(Config::default());
+    let client = Arc::new(SchedulerHttpClient {
+        market_status: Mutex::new(market_status.to_string()),
+        requests: Mutex::new(Vec::new()),
+    });
+    let orders = OrderServiceImpl::new(config.clone(), client.clone());
+    let markets = MarketServiceImpl::new(config, client.clone());
+    (client, orders, markets)
+}
+
+fn market_order() -> ScheduledRequest {
+    ScheduledRequest::Order(CreateOrderRequest::market(
+        EPIC.to_string(),
+        Direction::Buy,
+        1.0,
+        "EUR".to_string(),
+    ))
+}
+
+fn test_session() -> IgSession {
+    IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    )
+}
+
+#[tokio::test]
+async fn test_scheduler_submits_orders_when_their_time_comes() {
+    let (client, orders, markets) = services("TRADEABLE");
+    let mut scheduler = OrderScheduler::new(&orders, &markets);
+    let open = Utc.with_ymd_and_hms(2025, 7, 1, 7, 0, 0).unwrap();
+    let id = scheduler
+        .schedule(market_order(), ScheduleTrigger::At(open))
+        .unwrap();
+    let working_order = ScheduledRequest::WorkingOrder(CreateWorkingOrderRequest::limit(
+        EPIC.to_string(),
+        Direction::Buy,
+        1.0,
+        17900.0,
+    ));
+    scheduler
+        .schedule(
+            working_order,
+            ScheduleTrigger::At(open + Duration::hours(1)),
+        )
+        .unwrap();
+
+    let session = test_session();
+    let executions = scheduler
+        .run_due_at(&session, open - Duration::seconds(1))
+        .await
+        .unwrap();
+    assert!(executions.is_empty());
+    assert!(client.requests().is_empty());
+
+    let executions = scheduler.run_due_at(&session, open).await.unwrap();
+    assert_eq!(executions.len(), 1);
+    assert_eq!(executions[0].order.id, id);
+    assert_eq!(executions[0].deal_reference.as_deref(), Some("ORDER1"));
+    assert_eq!(scheduler.pending().len(), 1);
+
+    let executions = scheduler
+        .run_due_at(&session, open + Duration::hours(2))
+        .await
+        .unwrap();
+    assert_eq!(executions[0].deal_reference.as_deref(), Some("WORKING1"));
+    assert!(scheduler.pending().is_empty());
+    let paths: Vec<String> = client.requests().into_iter().map(|(_, p)| p).collect();
+    assert_eq!(paths, vec!["positions/otc", "workingorders/otc"]);
+}
+
+#[tokio::test]
+async fn test_scheduler_waits_for_market_open() {
+    let (client, orders, markets) = services("CLOSED");
+    let mut scheduler = OrderScheduler::new(&orders, &markets);
+    scheduler
+        .schedule(
+            market_order(),
+            ScheduleTrigger::MarketOpen { not_before: None },
+        )
+        .unwrap();
+    scheduler
+        .schedule(
+            market_order(),
+            ScheduleTrigger::MarketOpen { not_before: None },
+        )
+        .unwrap();
+
+    let session = test_session();
+    let now = Utc::now();
+    assert!(
+        scheduler
+            .run_due_at(&session, now)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    // The market status is fetched once for both orders
+    assert_eq!(client.requests().len(), 1);
+
+    *client.market_status.lock().unwrap() = "TRADEABLE".to_string();
+    let executions = scheduler.run_due_at(&session, now).await.unwrap();
+    assert_eq!(executions.len(), 2);
+    assert!(executions.iter().all(|e| e.error.is_none()));
+    assert!(scheduler.pending().is_empty());
+}
+
+#[tokio::test]
+async fn test_scheduler_reloads_pending_orders() {
+    let path = std::env::temp_dir().join(format!("ig_schedule_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let (_, orders, markets) = services("TRADEABLE");
+    let tomorrow = Utc::now() + Duration::days(1);
+
+    let mut scheduler = OrderScheduler::new(&orders, &markets)
+        .with_persistence(&path)
+        .unwrap();
+    let first = scheduler
+        .schedule(market_order(), ScheduleTrigger::At(tomorrow))
+        .unwrap();
+    let second = scheduler
+        .schedule(
+            market_order(),
+            ScheduleTrigger::MarketOpen {
+                not_before: Some(tomorrow),
+            },
+        )
+        .unwrap();
+    scheduler.cancel(&first).unwrap();
+    drop(scheduler);
+    let temporary = format!(".ig_schedule_{}.json.tmp", std::process::id());
+    assert!(!path.with_file_name(temporary).exists());
+
+    let mut restarted = OrderScheduler::new(&orders, &markets)
+        .with_persistence(&path)
+        .unwrap();
+    assert_eq!(restarted.pending().len(), 1);
+    assert_eq!(restarted.pending()[0].id, second);
+    assert_eq!(
+        restarted.pending()[0].trigger,
+        ScheduleTrigger::MarketOpen {
+            not_before: Some(tomorrow)
+        }
+    );
+    // Identifiers keep increasing across restarts
+    let third = restarted
+        .schedule(market_order(), ScheduleTrigger::At(tomorrow))
+        .unwrap();
+    assert_eq!(third, "SCHED-3");
+    let _ = std::fs::remove_file(&path);
+}
@@ -1,9 +1,15 @@
+use ig_client::application::models::account::{Position, WorkingOrder};
 use ig_client::application::models::order::{
-    ClosePositionRequest, CreateOrderRequest, Direction, OrderConfirmation, OrderType, Status,
-    TimeInForce, UpdatePositionRequest,
+    BulkOrderOutcome, ClosePositionRequest, CreateOrderRequest, DealFilter, Direction,
+    OrderConfirmation, OrderType, PartialCloseSize, PositionMode, Status, TimeInForce,
+    UpdatePositionRequest,
 };
 use ig_client::application::models::working_order::{
-    CreateWorkingOrderRequest, CreateWorkingOrderResponse,
+    CreateWorkingOrderRequest, CreateWorkingOrderResponse, UpdateWorkingOrderRequest,
+};
+use ig_client::application::services::OrderService;
+use ig_client::application::services::order_idempotency::{
+    FileIdempotencyStore, MemoryIdempotencyStore, OrderIdempotencyStore,
 };
 use ig_client::application::services::order_service::OrderServiceImpl;
 use ig_client::config::Config;
@@ -13,7 +19,8 @@ use ig_client::transport::http_client::IgHttpClient;
 use ig_client::utils::rate_limiter::RateLimitType;
 use reqwest::Method;
 use serde::de::DeserializeOwned;
-use std::sync::Arc;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
 
 // Mock HTTP client for testing service methods without actual network calls
 struct MockHttpClient {}
@@ -45,18 +52,102 @@ impl IgHttpClient for MockHttpClient {
     }
 }
 
+/// Request seen by the recording client: method, path, JSON body and API version
+type RecordedRequest = (Method, String, Option<Value>, String);
+
+type Responder = Box<dyn Fn(&Method, &str) -> Value + Send + Sync>;
+
+// Mock HTTP client that records requests and answers with a canned JSON value,
+// `null` standing for a 404
+struct RecordingHttpClient {
+    requests: Mutex<Vec<RecordedRequest>>,
+    response: Responder,
+}
+
+impl RecordingHttpClient {
+    fn new(response: impl Fn(&Method, &str) -> Value + Send + Sync + 'static) -> Self {
+        Self {
+            requests: Mutex::new(Vec::new()),
+            response: Box::new(response),
+        }
+    }
+
+    fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for RecordingHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        _session: &IgSession,
+        body: Option<&T>,
+        version: &str,
+    ) -> Result<R, AppError> {
+        let body = body.map(serde_json::to_value).transpose()?;
+        let response = (self.response)(&method, path);
+        self.requests
+            .lock()
+            .unwrap()
+            .push((method, path.to_string(), body, version.to_string()));
+        if response.is_null() {
+            return Err(AppError::NotFound);
+        }
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Recording HTTP client does not support unauthenticated requests");
+    }
+}
+
+fn test_session() -> IgSession {
+    IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    )
+}
+
+fn recording_service(
+    response: impl Fn(&Method, &str) -> Value + Send + Sync + 'static,
+) -> (
+    Arc<RecordingHttpClient>,
+    OrderServiceImpl<RecordingHttpClient>,
+) {
+    let client = Arc::new(RecordingHttpClient::new(response));
+    let service = OrderServiceImpl::new(Arc::new(Config::default()), client.clone());
+    (client, service)
+}
+
 #[test]
 fn test_create_order_request_market() {
     // Test the market constructor of CreateOrderRequest
-    let order =
-        CreateOrderRequest::market("OP.D.OTCDAX1.021100P.IP".to_string(), Direction::Buy, 1.0);
+    let order = CreateOrderRequest::market(
+        "OP.D.OTCDAX1.021100P.IP".to_string(),
+        Direction::Buy,
+        1.0,
+        "EUR".to_string(),
+    );
 
     // Verify that the fields were set correctly
     assert_eq!(order.epic, "OP.D.OTCDAX1.021100P.IP");
     assert!(matches!(order.direction, Direction::Buy));
     assert_eq!(order.size, 1.0);
     assert!(matches!(order.order_type, OrderType::Market));
-    assert!(matches!(order.time_in_force, TimeInForce::FillOrKill));
+    assert!(matches!(
+        order.time_in_force,
+        TimeInForce::ExecuteAndEliminate
+    ));
 }
 
 #[test]
@@ -67,6 +158,7 @@ fn test_create_order_request_limit() {
         Direction::Sell,
         1.0,
         1.2345,
+        "EUR".to_string(),
     );
 
     // Verify that the fields were set correctly
@@ -84,9 +176,13 @@ fn test_create_order_request_limit() {
 #[test]
 fn test_create_order_request_with_reference() {
     // Test the with_reference method
-    let order =
-        CreateOrderRequest::market("OP.D.OTCDAX1.021100P.IP".to_string(), Direction::Buy, 1.0)
-            .with_reference("TEST_REF".to_string());
+    let order = CreateOrderRequest::market(
+        "OP.D.OTCDAX1.021100P.IP".to_string(),
+        Direction::Buy,
+        1.0,
+        "EUR".to_string(),
+    )
+    .with_reference("TEST_REF".to_string());
 
     // Verify deal_reference field is set correctly
     assert_eq!(order.deal_reference, Some("TEST_REF".to_string()));
@@ -95,9 +191,13 @@ fn test_create_order_request_with_reference() {
 #[test]
 fn test_create_order_request_with_stop_loss() {
     // Test the with_stop_loss method
-    let order =
-        CreateOrderRequest::market("OP.D.OTCDAX1.021100P.IP".to_string(), Direction::Buy, 1.0)
-            .with_stop_loss(1.2000);
+    let order = CreateOrderRequest::market(
+        "OP.D.OTCDAX1.021100P.IP".to_string(),
+        Direction::Buy,
+        1.0,
+        "EUR".to_string(),
+    )
+    .with_stop_loss(1.2000);
 
     // Verify stop_level field is set correctly
     assert_eq!(order.stop_level, Some(1.2000));
@@ -106,24 +206,38 @@ fn test_create_order_request_with_stop_loss() {
 #[test]
 fn test_create_order_request_with_take_profit() {
     // Test the with_take_profit method
-    let order =
-        CreateOrderRequest::market("OP.D.OTCDAX1.021100P.IP".to_string(), Direction::Buy, 1.0)
-            .with_take_profit(1.3000);
+    let order = CreateOrderRequest::market(
+        "OP.D.OTCDAX1.021100P.IP".to_string(),
+        Direction::Buy,
+        1.0,
+        "EUR".to_string(),
+    )
+    .with_take_profit(1.3000);
 
     // Verify limit_level field is set correctly
     assert_eq!(order.limit_level, Some(1.3000));
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_close_position_request_market() {
     // Test the market constructor of ClosePositionRequest
-    let request = ClosePositionRequest::market("DEAL123".to_string(), Direction::Sell, 1.0);
+    let request = ClosePositionRequest::market(
+        "DEAL123".to_string(),
+        Direction::Sell,
+        1.0,
+        "OP.D.OTCDAX1.021100P.IP".to_string(),
+        "EUR".to_string(),
+    );
 
     // Verify that the fields were set correctly
     assert!(matches!(request.direction, Direction::Sell));
     assert_eq!(request.size, 1.0);
     assert!(matches!(request.order_type, OrderType::Market));
-    assert!(matches!(request.time_in_force, TimeInForce::FillOrKill));
+    assert!(matches!(
+        request.time_in_force,
+        TimeInForce::ExecuteAndEliminate
+    ));
     assert_eq!(request.level, None);
 }
 
@@ -135,6 +249,7 @@ fn test_update_position_request() {
         limit_level: Some(1.3000),
         trailing_stop: Some(false),
         trailing_stop_distance: Some(0.01),
+        trailing_stop_increment: None,
     };
 
     // Verify that the fields were set correctly
@@ -242,3 +357,876 @@ fn test_order_confirmation() {
     assert_eq!(confirmation.size, Some(1.0));
     assert!(matches!(confirmation.direction, Some(Direction::Buy)));
 }
+
+#[tokio::test]
+async fn test_delete_working_order() {
+    let (client, service) = recording_service(|_, _| json!({ "dealReference": "DEL123" }));
+
+    let response = service
+        .delete_working_order(&test_session(), "DIAAAABBBCCC")
+        .await
+        .unwrap();
+
+    assert_eq!(response.deal_reference, "DEL123");
+    assert_eq!(
+        client.requests(),
+        vec![(
+            Method::DELETE,
+            "workingorders/otc/DIAAAABBBCCC".to_string(),
+            None,
+            "2".to_string()
+        )]
+    );
+}
+
+#[tokio::test]
+async fn test_update_working_order() {
+    let (client, service) = recording_service(|_, _| json!({ "dealReference": "UPD123" }));
+    let update =
+        UpdateWorkingOrderRequest::new(101.5, OrderType::Limit, TimeInForce::GoodTillCancelled)
+            .with_stop_distance(20.0)
+            .with_limit_distance(40.0)
+            .expires_at("2025/06/01 12:00".to_string());
+
+    let response = service
+        .update_working_order(&test_session(), "DIAAAABBBCCC", &update)
+        .await
+        .unwrap();
+
+    assert_eq!(response.deal_reference, "UPD123");
+    let requests = client.requests();
+    assert_eq!(requests.len(), 1);
+    let (method, path, body, version) = &requests[0];
+    assert_eq!(method, Method::PUT);
+    assert_eq!(path, "workingorders/otc/DIAAAABBBCCC");
+    assert_eq!(version, "2");
+    assert_eq!(
+        body.as_ref().unwrap(),
+        &json!({
+            "level": 101.5,
+            "type": "LIMIT",
+            "stopDistance": 20.0,
+            "limitDistance": 40.0,
+            "timeInForce": "GOOD_TILL_DATE",
+            "goodTillDate": "2025/06/01 12:00"
+        })
+    );
+}
+
+fn working_order_json(deal_id: &str) -> Value {
+    json!({
+        "workingOrderData": {
+            "dealId": deal_id,
+            "direction": "BUY",
+            "epic": "IX.D.DAX.DAILY.IP",
+            "orderSize": 1.0,
+            "orderLevel": 18000.0,
+            "timeInForce": "GOOD_TILL_CANCELLED",
+            "goodTillDate": null,
+            "goodTillDateISO": null,
+            "createdDate": "2025/05/22 09:00:00:000",
+            "createdDateUTC": "2025-05-22T08:00:00",
+            "guaranteedStop": false,
+            "orderType": "LIMIT",
+            "stopDistance": null,
+            "limitDistance": null,
+            "currencyCode": "EUR",
+            "dma": false,
+            "limitedRiskPremium": null
+        },
+        "marketData": {
+            "instrumentName": "Germany 40",
+            "exchangeId": "XETRA",
+            "expiry": "DFB",
+            "marketStatus": "TRADEABLE",
+            "epic": "IX.D.DAX.DAILY.IP",
+            "instrumentType": "INDICES",
+            "lotSize": 1.0,
+            "high": 18300.0,
+            "low": 18100.0,
+            "percentageChange": 0.1,
+            "netChange": 20.0,
+            "bid": 18200.0,
+            "offer": 18201.0,
+            "updateTime": "09:00:00",
+            "updateTimeUTC": "08:00:00",
+            "delayTime": 0,
+            "streamingPricesAvailable": true,
+            "scalingFactor": 1
+        }
+    })
+}
+
+#[test]
+fn test_update_working_order_keeps_type_and_duration() {
+    let order: WorkingOrder = serde_json::from_value(working_order_json("DIAAAABBBCCC")).unwrap();
+
+    let update = UpdateWorkingOrderRequest::for_order(&order.working_order_data, 18050.0);
+
+    assert_eq!(
+        serde_json::to_value(&update).unwrap(),
+        json!({
+            "level": 18050.0,
+            "type": "LIMIT",
+            "timeInForce": "GOOD_TILL_CANCELLED"
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_find_working_order() {
+    let (client, service) = recording_service(
+        |_, _| json!({ "workingOrders": [working_order_json("DEAL1"), working_order_json("DEAL2")] }),
+    );
+
+    let order = service
+        .find_working_order(&test_session(), "DEAL2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(order.working_order_data.deal_id, "DEAL2");
+
+    let missing = service
+        .find_working_order(&test_session(), "DEAL3")
+        .await
+        .unwrap();
+    assert!(missing.is_none());
+    assert_eq!(client.requests()[0].1, "workingorders");
+}
+
+#[tokio::test]
+async fn test_close_position_sends_delete() {
+    let (client, service) = recording_service(|_, _| json!({ "dealReference": "CLOSE123" }));
+    let close = ClosePositionRequest::for_deal("DIAAAABBBCCC", &Direction::Buy, 1.0)
+        .with_market("IX.D.DAX.DAILY.IP", "EUR");
+
+    let response = service
+        .close_position(&test_session(), &close)
+        .await
+        .unwrap();
+
+    assert_eq!(response.deal_reference, "CLOSE123");
+    let requests = client.requests();
+    let (method, path, body, version) = &requests[0];
+    assert_eq!(method, Method::DELETE);
+    assert_eq!(path, "positions/otc");
+    assert_eq!(version, "1");
+    assert_eq!(body.as_ref().unwrap()["dealId"], "DIAAAABBBCCC");
+    assert_eq!(body.as_ref().unwrap()["forceOpen"], false);
+
+    let mut forced = close.clone();
+    forced.force_open = true;
+    let result = service.close_position(&test_session(), &forced).await;
+    assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    assert_eq!(client.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_position_mode_sets_force_open_and_guards_closes() {
+    let (client, service) = recording_service(|_, _| json!({ "dealReference": "REF123" }));
+    let service = service.with_position_mode(PositionMode::Hedging);
+    assert_eq!(service.position_mode(), Some(PositionMode::Hedging));
+    let order = CreateOrderRequest::market(
+        "IX.D.DAX.DAILY.IP".to_string(),
+        Direction::Sell,
+        1.0,
+        "EUR".to_string(),
+    );
+
+    service.create_order(&test_session(), &order).await.unwrap();
+    assert_eq!(client.requests()[0].2.as_ref().unwrap()["forceOpen"], true);
+
+    let mut close = ClosePositionRequest::for_deal("DIAAAABBBCCC", &Direction::Sell, 1.0)
+        .with_market("IX.D.DAX.DAILY.IP", "EUR");
+    close.deal_id = None;
+    let result = service.close_position(&test_session(), &close).await;
+    assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    assert_eq!(client.requests().len(), 1);
+
+    // A netting account sends plain orders without forceOpen so they net
+    let (client, service) = recording_service(|_, _| json!({ "dealReference": "REF123" }));
+    let service = service.with_position_mode(PositionMode::Netting);
+    let mut forced = order.clone();
+    forced.force_open = true;
+    service
+        .create_order(&test_session(), &forced)
+        .await
+        .unwrap();
+    service
+        .close_position(&test_session(), &close)
+        .await
+        .unwrap();
+    assert_eq!(client.requests()[0].2.as_ref().unwrap()["forceOpen"], false);
+}
+
+fn confirmation_json(deal_reference: &str) -> Value {
+    json!({
+        "date": "2025-05-22T09:00:00.000",
+        "status": "OPEN",
+        "reason": "SUCCESS",
+        "dealStatus": "ACCEPTED",
+        "epic": "IX.D.DAX.DAILY.IP",
+        "expiry": "-",
+        "dealReference": deal_reference,
+        "dealId": "DIAAAABBBCCC",
+        "level": 18200.0,
+        "size": 1.0,
+        "direction": "BUY",
+        "stopLevel": null,
+        "limitLevel": null,
+        "stopDistance": null,
+        "limitDistance": null,
+        "guaranteedStop": false,
+        "trailingStop": false
+    })
+}
+
+#[tokio::test]
+async fn test_set_attached_orders_updates_and_confirms() {
+    let position: Value = serde_json::from_str(
+        &std::fs::read_to_string("tests/unit/application/models/position.json").unwrap(),
+    )
+    .unwrap();
+    let (client, service) = recording_service(move |method, path| match (method, path) {
+        (&Method::GET, "positions/DIAAAAT9SU2UMBB") => position.clone(),
+        (&Method::PUT, _) => json!({ "dealReference": "AMEND1" }),
+        (&Method::GET, "confirms/AMEND1") => confirmation_json("AMEND1"),
+        _ => Value::Null,
+    });
+
+    let confirmation = service
+        .set_attached_orders(&test_session(), "DIAAAAT9SU2UMBB", Some(80.0), Some(50.0))
+        .await
+        .unwrap();
+    assert_eq!(confirmation.deal_reference, "AMEND1");
+
+    let requests = client.requests();
+    let paths: Vec<(Method, String)> = requests
+        .iter()
+        .map(|r| (r.0.clone(), r.1.clone()))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            (Method::GET, "positions/DIAAAAT9SU2UMBB".to_string()),
+            (Method::PUT, "positions/otc/DIAAAAT9SU2UMBB".to_string()),
+            (Method::GET, "confirms/AMEND1".to_string()),
+        ]
+    );
+    let body = requests[1].2.as_ref().unwrap();
+    assert_eq!(body["stopLevel"], 80.0);
+    assert_eq!(body["limitLevel"], 50.0);
+
+    // A stop on the winning side is refused before anything is sent
+    let result = service
+        .set_attached_orders(&test_session(), "DIAAAAT9SU2UMBB", Some(60.0), None)
+        .await;
+    assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    assert_eq!(client.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn test_reverse_position_closes_then_opens_opposite() {
+    let position: Value = serde_json::from_str(
+        &std::fs::read_to_string("tests/unit/application/models/position.json").unwrap(),
+    )
+    .unwrap();
+    let (client, service) = recording_service(move |method, path| match (method, path) {
+        (&Method::GET, "positions/DIAAAAT9SU2UMBB") => position.clone(),
+        (&Method::DELETE, "positions/otc") => json!({ "dealReference": "CLOSE1" }),
+        (&Method::POST, "positions/otc") => json!({ "dealReference": "OPEN1" }),
+        (&Method::GET, "confirms/CLOSE1") => confirmation_json("CLOSE1"),
+        (&Method::GET, "confirms/OPEN1") => confirmation_json("OPEN1"),
+        _ => Value::Null,
+    });
+
+    let reversal = service
+        .reverse_position(&test_session(), "DIAAAAT9SU2UMBB")
+        .await
+        .unwrap();
+    assert!(reversal.is_complete());
+    assert_eq!(reversal.open.unwrap().unwrap().deal_reference, "OPEN1");
+
+    let requests = client.requests();
+    let paths: Vec<(Method, String)> = requests
+        .iter()
+        .map(|r| (r.0.clone(), r.1.clone()))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            (Method::GET, "positions/DIAAAAT9SU2UMBB".to_string()),
+            (Method::DELETE, "positions/otc".to_string()),
+            (Method::GET, "confirms/CLOSE1".to_string()),
+            (Method::POST, "positions/otc".to_string()),
+            (Method::GET, "confirms/OPEN1".to_string()),
+        ]
+    );
+    let close = requests[1].2.as_ref().unwrap();
+    assert_eq!(close["dealId"], "DIAAAAT9SU2UMBB");
+    assert_eq!(close["direction"], "BUY");
+    let open = requests[3].2.as_ref().unwrap();
+    assert_eq!(open["epic"], "OP.D.OTCDAXWK.23650P.IP");
+    assert_eq!(open["direction"], "BUY");
+    assert_eq!(open["size"], 1.0);
+    assert_eq!(open["expiry"], "04-JUL-25");
+}
+
+#[tokio::test]
+async fn test_reverse_position_stops_when_close_is_rejected() {
+    let position: Value = serde_json::from_str(
+        &std::fs::read_to_string("tests/unit/application/models/position.json").unwrap(),
+    )
+    .unwrap();
+    let (client, service) = recording_service(move |method, path| match (method, path) {
+        (&Method::GET, "positions/DIAAAAT9SU2UMBB") => position.clone(),
+        (&Method::DELETE, "positions/otc") => json!({ "dealReference": "CLOSE1" }),
+        (&Method::GET, "confirms/CLOSE1") => {
+            let mut confirmation = confirmation_json("CLOSE1");
+            confirmation["dealStatus"] = json!("REJECTED");
+            confirmation["reason"] = json!("MARKET_CLOSED_WITH_EDITS");
+            confirmation
+        }
+        _ => Value::Null,
+    });
+
+    let reversal = service
+        .reverse_position(&test_session(), "DIAAAAT9SU2UMBB")
+        .await
+        .unwrap();
+    assert!(!reversal.is_complete());
+    assert!(reversal.open.is_none());
+    assert_eq!(client.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn test_reverse_position_keeps_the_close_when_the_open_fails() {
+    let position: Value = serde_json::from_str(
+        &std::fs::read_to_string("tests/unit/application/models/position.json").unwrap(),
+    )
+    .unwrap();
+    let (_client, service) = recording_service(move |method, path| match (method, path) {
+        (&Method::GET, "positions/DIAAAAT9SU2UMBB") => position.clone(),
+        (&Method::DELETE, "positions/otc") => json!({ "dealReference": "CLOSE1" }),
+        (&Method::GET, "confirms/CLOSE1") => confirmation_json("CLOSE1"),
+        // The opening order is refused by the API
+        _ => Value::Null,
+    });
+
+    let reversal = service
+        .reverse_position(&test_session(), "DIAAAAT9SU2UMBB")
+        .await
+        .unwrap();
+    assert!(reversal.close.is_accepted());
+    assert!(reversal.is_flat());
+    assert!(matches!(reversal.open, Some(Err(_))));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_await_confirmation_retries_not_found() {
+    let calls = Arc::new(Mutex::new(0));
+    let counter = calls.clone();
+    let (client, service) = recording_service(move |_, _| {
+        let mut calls = counter.lock().unwrap();
+        *calls += 1;
+        if *calls < 3 {
+            Value::Null
+        } else {
+            confirmation_json("REF123")
+        }
+    });
+
+    let confirmation = service
+        .await_confirmation(&test_session(), "REF123", std::time::Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    assert_eq!(confirmation.deal_id.as_deref(), Some("DIAAAABBBCCC"));
+    assert_eq!(client.requests().len(), 3);
+    assert_eq!(client.requests()[0].1, "confirms/REF123");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_await_confirmation_times_out() {
+    let (client, service) = recording_service(|_, _| Value::Null);
+
+    let result = service
+        .await_confirmation(&test_session(), "REF123", std::time::Duration::from_secs(1))
+        .await;
+
+    assert!(matches!(result, Err(AppError::Timeout(_))));
+    // 100 + 200 + 400 ms, then the remaining 300 ms, then one last poll
+    assert_eq!(client.requests().len(), 5);
+}
+
+#[test]
+fn test_trade_fields_confirmation() {
+    use ig_client::presentation::trade::TradeFields;
+
+    let fields = TradeFields {
+        confirms: Some(confirmation_json("REF123").to_string()),
+        ..Default::default()
+    };
+    let confirmation = fields.confirmation().unwrap();
+    assert_eq!(confirmation.deal_reference, "REF123");
+    assert!(matches!(confirmation.status, Status::Open));
+    assert!(TradeFields::default().confirmation().is_none());
+}
+
+#[tokio::test]
+async fn test_create_orders_reports_each_order() {
+    let submitted = Arc::new(Mutex::new(0));
+    let counter = submitted.clone();
+    let (client, service) = recording_service(move |method, path| {
+        if *method == Method::POST {
+            let mut submitted = counter.lock().unwrap();
+            *submitted += 1;
+            return match *submitted {
+                2 => Value::Null,
+                n => json!({ "dealReference": format!("REF{n}") }),
+            };
+        }
+        match path {
+            "confirms/REF1" => confirmation_json("REF1"),
+            _ => {
+                let mut rejected = confirmation_json("REF3");
+                rejected["dealStatus"] = json!("REJECTED");
+                rejected["reason"] = json!("INSUFFICIENT_FUNDS");
+                rejected
+            }
+        }
+    });
+    let orders: Vec<CreateOrderRequest> = [
+        "OP.D.OTCDAX1.18000C.IP",
+        "OP.D.OTCDAX1.18100C.IP",
+        "OP.D.OTCDAX1.18200C.IP",
+    ]
+    .iter()
+    .map(|epic| {
+        CreateOrderRequest::market(epic.to_string(), Direction::Buy, 1.0, "EUR".to_string())
+    })
+    .collect();
+
+    let report = service
+        .create_orders(&test_session(), &orders)
+        .await
+        .unwrap();
+
+    assert_eq!(report.results.len(), 3);
+    assert!(!report.all_accepted());
+    let accepted: Vec<_> = report.accepted().collect();
+    assert_eq!(accepted.len(), 1);
+    assert_eq!(accepted[0].index, 0);
+    assert_eq!(accepted[0].deal_reference.as_deref(), Some("REF1"));
+
+    let errored: Vec<_> = report.errored().collect();
+    assert_eq!(errored.len(), 1);
+    assert_eq!(errored[0].epic, "OP.D.OTCDAX1.18100C.IP");
+    assert!(errored[0].deal_reference.is_none());
+
+    let rejected: Vec<_> = report.rejected().collect();
+    assert_eq!(rejected[0].index, 2);
+    match &rejected[0].outcome {
+        BulkOrderOutcome::Rejected(confirmation) => {
+            assert_eq!(confirmation.reason.as_deref(), Some("INSUFFICIENT_FUNDS"))
+        }
+        outcome => panic!("unexpected outcome {outcome:?}"),
+    }
+
+    let paths: Vec<String> = client.requests().into_iter().map(|r| r.1).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "positions/otc",
+            "confirms/REF1",
+            "positions/otc",
+            "positions/otc",
+            "confirms/REF3"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_create_order_with_idempotency_store_does_not_resubmit() {
+    // Submissions never get an answer, only the ALREADY order reached IG
+    let (client, service) = recording_service(|method, path| match (method, path) {
+        (&Method::GET, "confirms/ALREADY") => confirmation_json("ALREADY"),
+        _ => Value::Null,
+    });
+    let service = service
+        .with_idempotency_store(Arc::new(MemoryIdempotencyStore::new()))
+        .with_confirmation_timeout(std::time::Duration::from_millis(50));
+    let session = test_session();
+    let order = |reference: &str| {
+        CreateOrderRequest::market(
+            "IX.D.DAX.DAILY.IP".to_string(),
+            Direction::Buy,
+            1.0,
+            "EUR".to_string(),
+        )
+        .with_reference(reference.to_string())
+    };
+
+    assert!(
+        service
+            .create_order(&session, &order("ALREADY"))
+            .await
+            .is_err()
+    );
+    let response = service
+        .create_order(&session, &order("ALREADY"))
+        .await
+        .unwrap();
+    assert_eq!(response.deal_reference, "ALREADY");
+
+    assert!(
+        service
+            .create_order(&session, &order("LOST"))
+            .await
+            .is_err()
+    );
+    assert!(
+        service
+            .create_order(&session, &order("LOST"))
+            .await
+            .is_err()
+    );
+
+    // The confirmation of LOST is polled until the timeout before resubmitting
+    let mut requests: Vec<(Method, String)> =
+        client.requests().into_iter().map(|r| (r.0, r.1)).collect();
+    let polls = requests
+        .iter()
+        .filter(|request| request.1 == "confirms/LOST")
+        .count();
+    assert!(polls >= 2, "polled {polls} times");
+    requests.dedup();
+    assert_eq!(
+        requests,
+        vec![
+            (Method::POST, "positions/otc".to_string()),
+            (Method::GET, "confirms/ALREADY".to_string()),
+            (Method::POST, "positions/otc".to_string()),
+            (Method::GET, "confirms/LOST".to_string()),
+            (Method::POST, "positions/otc".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_file_idempotency_store_persists_references() {
+    let path = std::env::temp_dir().join(format!("ig_idempotency_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let store = FileIdempotencyStore::open(&path).unwrap();
+    store.record("REF1").unwrap();
+    store.record("REF2").unwrap();
+    store.remove("REF1").unwrap();
+
+    let reopened = FileIdempotencyStore::open(&path).unwrap();
+    assert!(!reopened.contains("REF1"));
+    assert!(reopened.contains("REF2"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_idempotency_stores_expire_references() {
+    let store = MemoryIdempotencyStore::new();
+    store.record("REF1").unwrap();
+    assert!(store.contains("REF1"));
+    let store = MemoryIdempotencyStore::new().with_ttl(std::time::Duration::ZERO);
+    store.record("REF1").unwrap();
+    assert!(!store.contains("REF1"));
+
+    let path = std::env::temp_dir().join(format!("ig_idempotency_ttl_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let store = FileIdempotencyStore::open(&path)
+        .unwrap()
+        .with_ttl(std::time::Duration::ZERO);
+    store.record("REF1").unwrap();
+    store.record("REF2").unwrap();
+
+    // Expired references are dropped from the file on the next change
+    let reopened = FileIdempotencyStore::open(&path).unwrap();
+    assert!(!reopened.contains("REF1"));
+    assert!(reopened.contains("REF2"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_partial_close_size_resolve() {
+    assert_eq!(
+        PartialCloseSize::Fraction(0.5)
+            .resolve(3.0, Some(0.1))
+            .unwrap(),
+        (1.5, 1.5)
+    );
+    // Rounded down to the 0.1 step
+    assert_eq!(
+        PartialCloseSize::Size(1.37)
+            .resolve(3.0, Some(0.5))
+            .unwrap(),
+        (1.3, 1.7)
+    );
+    // Leaves at least the minimum deal size open
+    assert_eq!(
+        PartialCloseSize::Size(2.8).resolve(3.0, Some(0.5)).unwrap(),
+        (2.5, 0.5)
+    );
+    assert_eq!(
+        PartialCloseSize::Fraction(1.0)
+            .resolve(3.0, Some(1.0))
+            .unwrap(),
+        (3.0, 0.0)
+    );
+    assert!(PartialCloseSize::Size(0.5).resolve(3.0, Some(1.0)).is_err());
+    assert!(PartialCloseSize::Size(1.0).resolve(1.5, Some(1.0)).is_err());
+    assert!(
+        PartialCloseSize::Fraction(1.5)
+            .resolve(3.0, Some(1.0))
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_partial_close() {
+    let (client, service) = recording_service(|method, path| match (method, path) {
+        (&Method::GET, "markets/OP.D.OTCDAXWK.23650P.IP") => json!({
+            "instrument": {
+                "epic": "OP.D.OTCDAXWK.23650P.IP",
+                "name": "Weekly Germany 40 23650 PUT",
+                "expiry": "04-JUL-25",
+                "contractSize": "1",
+                "valueOfOnePip": "1.00"
+            },
+            "snapshot": { "marketStatus": "TRADEABLE", "bid": 62.2, "offer": 68.2 },
+            "dealingRules": {
+                "minStepDistance": { "unit": "POINTS", "value": 1.0 },
+                "minDealSize": { "unit": "POINTS", "value": 0.2 },
+                "minControlledRiskStopDistance": { "unit": "POINTS", "value": 1.0 },
+                "minNormalStopOrLimitDistance": { "unit": "POINTS", "value": 1.0 },
+                "maxStopOrLimitDistance": { "unit": "POINTS", "value": 100.0 },
+                "controlledRiskSpacing": { "unit": "POINTS", "value": 1.0 },
+                "marketOrderPreference": "AVAILABLE_DEFAULT_ON",
+                "trailingStopsPreference": "NOT_AVAILABLE"
+            }
+        }),
+        (&Method::GET, _) => confirmation_json("CLOSE1"),
+        _ => json!({ "dealReference": "CLOSE1" }),
+    });
+    let position: Position = serde_json::from_str(include_str!("../models/position.json")).unwrap();
+
+    let result = service
+        .partial_close(&test_session(), &position, PartialCloseSize::Fraction(0.25))
+        .await
+        .unwrap();
+
+    assert_eq!(result.closed_size, 0.2);
+    assert_eq!(result.remaining_size, 0.8);
+    assert_eq!(result.confirmation.deal_reference, "CLOSE1");
+
+    let requests = client.requests();
+    assert_eq!(requests[1].0, Method::DELETE);
+    let body = requests[1].2.as_ref().unwrap();
+    assert_eq!(body["dealId"], "DIAAAAT9SU2UMBB");
+    assert_eq!(body["direction"], "BUY");
+    assert_eq!(body["size"], 0.2);
+    assert_eq!(requests[2].1, "confirms/CLOSE1");
+}
+
+/// Market details of a weekly DAX option with EUR as default currency
+fn option_market_details() -> Value {
+    json!({
+        "instrument": {
+            "epic": "OP.D.OTCDAXWK.23650P.IP",
+            "name": "Weekly Germany 40 23650 PUT",
+            "expiry": "04-JUL-25",
+            "contractSize": "1",
+            "valueOfOnePip": "1.00",
+            "currencies": [
+                { "code": "USD", "isDefault": false },
+                { "code": "EUR", "isDefault": true }
+            ]
+        },
+        "snapshot": { "marketStatus": "TRADEABLE", "bid": 62.2, "offer": 68.2 },
+        "dealingRules": {
+            "minStepDistance": { "unit": "POINTS", "value": 1.0 },
+            "minDealSize": { "unit": "POINTS", "value": 0.2 },
+            "minControlledRiskStopDistance": { "unit": "POINTS", "value": 1.0 },
+            "minNormalStopOrLimitDistance": { "unit": "POINTS", "value": 1.0 },
+            "maxStopOrLimitDistance": { "unit": "POINTS", "value": 100.0 },
+            "controlledRiskSpacing": { "unit": "POINTS", "value": 1.0 },
+            "marketOrderPreference": "AVAILABLE_DEFAULT_ON",
+            "trailingStopsPreference": "NOT_AVAILABLE"
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_create_order_resolves_market_defaults() {
+    let (client, service) = recording_service(|method, path| match (method, path) {
+        (&Method::GET, "markets/OP.D.OTCDAXWK.23650P.IP") => option_market_details(),
+        _ => json!({ "dealReference": "REF1" }),
+    });
+    let service = service.with_market_defaults();
+    let session = test_session();
+    let order = CreateOrderRequest::market(
+        "OP.D.OTCDAXWK.23650P.IP".to_string(),
+        Direction::Buy,
+        1.0,
+        String::new(),
+    );
+    service.create_order(&session, &order).await.unwrap();
+    let working_order = CreateWorkingOrderRequest::limit(
+        "OP.D.OTCDAXWK.23650P.IP".to_string(),
+        Direction::Buy,
+        1.0,
+        50.0,
+    )
+    .with_expiry("-".to_string());
+    service
+        .create_working_order(&session, &working_order)
+        .await
+        .unwrap();
+
+    // The market details are fetched once for both orders
+    let requests = client.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].1, "markets/OP.D.OTCDAXWK.23650P.IP");
+    let body = requests[1].2.as_ref().unwrap();
+    assert_eq!(body["expiry"], "04-JUL-25");
+    assert_eq!(body["currencyCode"], "EUR");
+    let body = requests[2].2.as_ref().unwrap();
+    assert_eq!(body["expiry"], "04-JUL-25");
+    assert_eq!(body["currencyCode"], "EUR");
+}
+
+#[tokio::test]
+async fn test_create_working_order_rejects_stop_limit() {
+    let (client, service) = recording_service(|_, _| json!({ "dealReference": "REF1" }));
+    let order = CreateWorkingOrderRequest::stop_limit(
+        "IX.D.DAX.DAILY.IP".to_string(),
+        Direction::Buy,
+        1.0,
+        18000.0,
+        18010.0,
+    );
+    let result = service.create_working_order(&test_session(), &order).await;
+    assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    assert!(client.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_market_defaults_expire() {
+    let (client, service) = recording_service(|method, path| match (method, path) {
+        (&Method::GET, "markets/OP.D.OTCDAXWK.23650P.IP") => option_market_details(),
+        _ => json!({ "dealReference": "REF1" }),
+    });
+    let service = service.with_market_defaults_ttl(std::time::Duration::from_millis(50));
+    let order = CreateOrderRequest::market(
+        "OP.D.OTCDAXWK.23650P.IP".to_string(),
+        Direction::Buy,
+        1.0,
+        String::new(),
+    );
+
+    service.create_order(&test_session(), &order).await.unwrap();
+    service.create_order(&test_session(), &order).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(80)).await;
+    service.create_order(&test_session(), &order).await.unwrap();
+
+    // Fetched for the first order, then again once expired
+    let fetches = client
+        .requests()
+        .iter()
+        .filter(|request| request.1 == "markets/OP.D.OTCDAXWK.23650P.IP")
+        .count();
+    assert_eq!(fetches, 2);
+}
+
+#[tokio::test]
+async fn test_create_order_keeps_given_currency_and_expiry() {
+    let (client, service) = recording_service(|_, _| json!({ "dealReference": "REF1" }));
+    let service = service.with_market_defaults();
+    let mut order = CreateOrderRequest::market(
+        "IX.D.DAX.DAILY.IP".to_string(),
+        Direction::Buy,
+        1.0,
+        "EUR".to_string(),
+    );
+    order.expiry = "DFB".to_string();
+
+    service.create_order(&test_session(), &order).await.unwrap();
+
+    let requests = client.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].1, "positions/otc");
+}
+
+#[tokio::test]
+async fn test_close_all_positions_with_filter() {
+    let (client, service) = recording_service(|method, _| {
+        if *method == Method::GET {
+            let option: Value =
+                serde_json::from_str(include_str!("../models/position.json")).unwrap();
+            let mut forex = option.clone();
+            forex["market"]["epic"] = json!("CS.D.EURUSD.TODAY.IP");
+            forex["position"]["dealId"] = json!("DEALFX");
+            json!({ "positions": [option, forex] })
+        } else {
+            json!({ "dealReference": "CLOSEREF" })
+        }
+    });
+
+    let summary = service
+        .close_all_positions(&test_session(), &DealFilter::all().with_underlying("DAX"))
+        .await
+        .unwrap();
+
+    assert!(summary.is_complete());
+    assert_eq!(summary.results.len(), 1);
+    assert_eq!(summary.results[0].deal_id, "DIAAAAT9SU2UMBB");
+    assert_eq!(
+        summary.results[0].deal_reference.as_deref(),
+        Some("CLOSEREF")
+    );
+
+    let requests = client.requests();
+    assert_eq!(requests.len(), 2);
+    let body = requests[1].2.as_ref().unwrap();
+    assert_eq!(body["dealId"], "DIAAAAT9SU2UMBB");
+    assert_eq!(body["direction"], "BUY");
+}
+
+#[tokio::test]
+async fn test_cancel_all_working_orders_reports_failures() {
+    let (client, service) = recording_service(|method, path| match (method, path) {
+        (&Method::GET, _) => {
+            json!({ "workingOrders": [working_order_json("DEAL1"), working_order_json("DEAL2")] })
+        }
+        (_, "workingorders/otc/DEAL2") => Value::Null,
+        _ => json!({ "dealReference": "DELREF" }),
+    });
+
+    let summary = service
+        .cancel_all_working_orders(&test_session(), &DealFilter::all())
+        .await
+        .unwrap();
+
+    assert!(!summary.is_complete());
+    assert_eq!(summary.succeeded().next().unwrap().deal_id, "DEAL1");
+    let failed: Vec<_> = summary.failed().collect();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].deal_id, "DEAL2");
+    assert!(failed[0].deal_reference.is_none());
+    assert_eq!(client.requests().len(), 3);
+
+    let summary = service
+        .cancel_all_working_orders(
+            &test_session(),
+            &DealFilter::all().with_direction(Direction::Sell),
+        )
+        .await
+        .unwrap();
+    assert!(summary.results.is_empty());
+}
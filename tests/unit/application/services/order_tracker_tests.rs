@@ -0,0 +1,126 @@
+use ig_client::application::models::order::{OrderConfirmation, Status};
+use ig_client::application::services::order_tracker::{OrderEvent, OrderState, OrderTracker};
+use ig_client::presentation::trade::{OpenPositionUpdate, TradeFields, WorkingOrderUpdate};
+use serde_json::json;
+
+fn confirmation(deal_reference: &str, deal_id: &str, deal_status: &str) -> OrderConfirmation {
+    serde_json::from_value(json!({
+        "date": "2025-05-22T09:00:00.000",
+        "status": "OPEN",
+        "reason": "SUCCESS",
+        "dealStatus": deal_status,
+        "epic": "IX.D.DAX.DAILY.IP",
+        "dealReference": deal_reference,
+        "dealId": deal_id
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_order_tracker_market_order_lifecycle() {
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(16);
+    let mut tracker = OrderTracker::new().with_sender(sender);
+
+    tracker.submitted("REF1", Some("IX.D.DAX.DAILY.IP"));
+    let event = tracker
+        .on_confirmation(&confirmation("REF1", "DEAL1", "ACCEPTED"))
+        .unwrap();
+    assert_eq!(event.state, OrderState::Accepted);
+
+    // The fill arrives on the stream with the position deal ID only
+    let events = tracker.on_trade_update(&TradeFields {
+        opu: Some(OpenPositionUpdate {
+            deal_id: Some("DEAL1".to_string()),
+            status: Some(Status::Open),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    assert_eq!(
+        events,
+        vec![OrderEvent {
+            deal_reference: "REF1".to_string(),
+            deal_id: Some("DEAL1".to_string()),
+            previous: OrderState::Accepted,
+            state: OrderState::Filled,
+        }]
+    );
+
+    // A late duplicate confirmation does not move the order back
+    assert!(
+        tracker
+            .on_confirmation(&confirmation("REF1", "DEAL1", "ACCEPTED"))
+            .is_none()
+    );
+
+    tracker.on_position_update(&OpenPositionUpdate {
+        deal_id: Some("DEAL1".to_string()),
+        status: Some(Status::Deleted),
+        ..Default::default()
+    });
+    let order = tracker.get("REF1").unwrap();
+    assert_eq!(
+        order.history,
+        vec![
+            OrderState::Submitted,
+            OrderState::Accepted,
+            OrderState::Filled,
+            OrderState::Closed
+        ]
+    );
+
+    let mut states = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        states.push(event.state);
+    }
+    assert_eq!(states, order.history);
+
+    tracker.prune_terminal();
+    assert!(tracker.get("REF1").is_none());
+    assert!(tracker.get_by_deal_id("DEAL1").is_none());
+}
+
+#[test]
+fn test_order_tracker_working_order_fill_and_rejection() {
+    let mut tracker = OrderTracker::new();
+    tracker.submitted("WO1", None);
+    tracker.submitted("REJ", None);
+
+    tracker.on_working_order_update(&WorkingOrderUpdate {
+        deal_reference: Some("WO1".to_string()),
+        deal_id: Some("WODEAL".to_string()),
+        status: Some(Status::Open),
+        ..Default::default()
+    });
+    assert_eq!(tracker.get("WO1").unwrap().state, OrderState::Working);
+
+    // The working order is deleted as it fills, then the position shows up
+    tracker.on_working_order_update(&WorkingOrderUpdate {
+        deal_id: Some("WODEAL".to_string()),
+        status: Some(Status::Deleted),
+        ..Default::default()
+    });
+    assert_eq!(tracker.get("WO1").unwrap().state, OrderState::Cancelled);
+    tracker.on_position_update(&OpenPositionUpdate {
+        deal_id: Some("POSDEAL".to_string()),
+        deal_id_origin: Some("WODEAL".to_string()),
+        status: Some(Status::Open),
+        ..Default::default()
+    });
+    let order = tracker.get_by_deal_id("POSDEAL").unwrap();
+    assert_eq!(order.deal_reference, "WO1");
+    assert_eq!(order.state, OrderState::Filled);
+
+    tracker.on_confirmation(&confirmation("REJ", "", "REJECTED"));
+    assert_eq!(tracker.get("REJ").unwrap().state, OrderState::Rejected);
+
+    // Updates of untracked deals are ignored
+    assert!(
+        tracker
+            .on_position_update(&OpenPositionUpdate {
+                deal_id: Some("OTHER".to_string()),
+                ..Default::default()
+            })
+            .is_none()
+    );
+}
@@ -0,0 +1,318 @@
+use ig_client::application::models::order::{
+    ClosePositionRequest, CreateOrderRequest, DealFilter, Direction, OrderType, PositionMode,
+    Status,
+};
+use ig_client::application::models::working_order::CreateWorkingOrderRequest;
+use ig_client::application::services::OrderService;
+use ig_client::application::services::market_service::MarketServiceImpl;
+use ig_client::application::services::paper_order_service::PaperOrderService;
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const EPIC: &str = "IX.D.DAX.DAILY.IP";
+
+// Mock HTTP client serving a DAX snapshot at 100 / 101, moved up one point by every
+// further request, and nothing else
+#[derive(Default)]
+struct SnapshotHttpClient {
+    requests: AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for SnapshotHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        assert_eq!(method, Method::GET, "paper orders must never reach IG");
+        if path != format!("markets/{EPIC}") {
+            return Err(AppError::NotFound);
+        }
+        let moved = self.requests.fetch_add(1, Ordering::SeqCst) as f64;
+        let step = json!({ "unit": "POINTS", "value": 1.0 });
+        Ok(serde_json::from_value(json!({
+            "instrument": {
+                "epic": EPIC,
+                "name": "Germany 40",
+                "expiry": "DFB",
+                "contractSize": "1",
+                "valueOfOnePip": "1.00"
+            },
+            "snapshot": {
+                "marketStatus": "TRADEABLE",
+                "bid": 100.0 + moved,
+                "offer": 101.0 + moved
+            },
+            "dealingRules": {
+                "minStepDistance": step,
+                "minDealSize": step,
+                "minControlledRiskStopDistance": step,
+                "minNormalStopOrLimitDistance": step,
+                "maxStopOrLimitDistance": step,
+                "controlledRiskSpacing": step,
+                "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+                "trailingStopsPreference": "NOT_AVAILABLE"
+            }
+        }))?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Snapshot HTTP client does not support unauthenticated requests");
+    }
+}
+
+fn paper_service() -> PaperOrderService<MarketServiceImpl<SnapshotHttpClient>> {
+    PaperOrderService::new(MarketServiceImpl::new(
+        Arc::new(Config::default()),
+        Arc::new(SnapshotHttpClient::default()),
+    ))
+}
+
+fn test_session() -> IgSession {
+    IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    )
+}
+
+fn market_order(direction: Direction, size: f64) -> CreateOrderRequest {
+    CreateOrderRequest::market(EPIC.to_string(), direction, size, "EUR".to_string())
+}
+
+#[tokio::test]
+async fn test_paper_market_orders_pay_the_spread_and_net() {
+    let service = paper_service();
+    let session = test_session();
+
+    // Bought at the offer of the snapshot
+    let response = service
+        .create_order(&session, &market_order(Direction::Buy, 2.0))
+        .await
+        .unwrap();
+    let confirmation = service
+        .get_order_confirmation(&session, &response.deal_reference)
+        .await
+        .unwrap();
+    assert!(confirmation.is_accepted());
+    assert_eq!(confirmation.level, Some(101.0));
+    assert_eq!(service.positions()[0].level, 101.0);
+
+    // A sell without force_open reduces the long, sold at the streamed bid
+    service.on_price(EPIC, 110.0, 111.0);
+    let mut order = market_order(Direction::Sell, 1.0);
+    order.force_open = false;
+    service.create_order(&session, &order).await.unwrap();
+    assert_eq!(service.positions()[0].size, 1.0);
+    assert!((service.realized_pnl() - 9.0).abs() < 1e-9);
+
+    let close =
+        ClosePositionRequest::for_deal(&service.positions()[0].deal_id, &Direction::Buy, 1.0)
+            .with_market(EPIC, "EUR");
+    let response = service.close_position(&session, &close).await.unwrap();
+    let confirmation = service
+        .get_order_confirmation(&session, &response.deal_reference)
+        .await
+        .unwrap();
+    assert_eq!(confirmation.status, Status::FullyClosed);
+    assert!(service.positions().is_empty());
+    assert!((service.realized_pnl() - 18.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_paper_orders_refetch_the_snapshot_until_prices_stream() {
+    let service = paper_service();
+    let session = test_session();
+
+    // Without a streamed quote every order fills at a fresh snapshot
+    for expected in [101.0, 102.0] {
+        let response = service
+            .create_order(&session, &market_order(Direction::Buy, 1.0))
+            .await
+            .unwrap();
+        let confirmation = service
+            .get_order_confirmation(&session, &response.deal_reference)
+            .await
+            .unwrap();
+        assert_eq!(confirmation.level, Some(expected));
+    }
+    assert_eq!(service.quote(EPIC), Some((101.0, 102.0)));
+
+    // Once prices stream the snapshot is no longer fetched
+    service.on_price(EPIC, 90.0, 91.0);
+    let response = service
+        .create_order(&session, &market_order(Direction::Buy, 1.0))
+        .await
+        .unwrap();
+    let confirmation = service
+        .get_order_confirmation(&session, &response.deal_reference)
+        .await
+        .unwrap();
+    assert_eq!(confirmation.level, Some(91.0));
+}
+
+#[tokio::test]
+async fn test_paper_hedging_mode_keeps_opposite_positions() {
+    let service = paper_service().with_position_mode(PositionMode::Hedging);
+    let session = test_session();
+
+    service
+        .create_order(&session, &market_order(Direction::Buy, 1.0))
+        .await
+        .unwrap();
+    service
+        .create_order(&session, &market_order(Direction::Sell, 1.0))
+        .await
+        .unwrap();
+    assert_eq!(service.positions().len(), 2);
+
+    let mut close =
+        ClosePositionRequest::for_deal(&service.positions()[0].deal_id, &Direction::Buy, 1.0)
+            .with_market(EPIC, "EUR");
+    close.deal_id = None;
+    assert!(service.close_position(&session, &close).await.is_err());
+    assert_eq!(service.positions().len(), 2);
+}
+
+#[tokio::test]
+async fn test_paper_reverse_position() {
+    let service = paper_service();
+    let session = test_session();
+    service
+        .create_order(&session, &market_order(Direction::Buy, 2.0))
+        .await
+        .unwrap();
+    let deal_id = service.positions()[0].deal_id.clone();
+
+    let reversal = service.reverse_position(&session, &deal_id).await.unwrap();
+    assert!(reversal.is_complete());
+    let positions = service.positions();
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0].direction, Direction::Sell);
+    assert_eq!(positions[0].size, 2.0);
+    assert_ne!(positions[0].deal_id, deal_id);
+
+    assert!(matches!(
+        service.reverse_position(&session, &deal_id).await,
+        Err(AppError::NotFound)
+    ));
+}
+
+#[tokio::test]
+async fn test_paper_stop_and_unfilled_limit_order() {
+    let service = paper_service();
+    let session = test_session();
+
+    let mut order = market_order(Direction::Buy, 1.0);
+    order.stop_distance = Some(10.0);
+    service.create_order(&session, &order).await.unwrap();
+    assert_eq!(service.positions()[0].stop_level, Some(91.0));
+
+    assert!(service.on_price(EPIC, 95.0, 96.0).is_empty());
+    let confirmations = service.on_price(EPIC, 90.0, 91.0);
+    assert_eq!(confirmations.len(), 1);
+    assert!(service.positions().is_empty());
+    assert!((service.realized_pnl() + 11.0).abs() < 1e-9);
+
+    // A fill-or-kill limit below the offer is rejected
+    let order = CreateOrderRequest::limit(
+        EPIC.to_string(),
+        Direction::Buy,
+        1.0,
+        80.0,
+        "EUR".to_string(),
+    );
+    let response = service.create_order(&session, &order).await.unwrap();
+    let confirmation = service
+        .get_order_confirmation(&session, &response.deal_reference)
+        .await
+        .unwrap();
+    assert!(!confirmation.is_accepted());
+    assert!(service.positions().is_empty());
+}
+
+#[tokio::test]
+async fn test_paper_working_orders() {
+    let service = paper_service();
+    let session = test_session();
+
+    let limit = CreateWorkingOrderRequest::limit(EPIC.to_string(), Direction::Buy, 1.0, 95.0);
+    service
+        .create_working_order(&session, &limit)
+        .await
+        .unwrap();
+    let stop = CreateWorkingOrderRequest::stop(EPIC.to_string(), Direction::Buy, 1.0, 120.0);
+    service.create_working_order(&session, &stop).await.unwrap();
+    assert_eq!(
+        service
+            .get_working_orders(&session)
+            .await
+            .unwrap()
+            .working_orders
+            .len(),
+        2
+    );
+
+    assert!(service.on_price(EPIC, 96.0, 97.0).is_empty());
+    let confirmations = service.on_price(EPIC, 93.0, 94.0);
+    assert_eq!(confirmations.len(), 1);
+    assert_eq!(confirmations[0].level, Some(94.0));
+    assert_eq!(service.positions().len(), 1);
+
+    let summary = service
+        .cancel_all_working_orders(&session, &DealFilter::all())
+        .await
+        .unwrap();
+    assert!(summary.is_complete());
+    assert_eq!(summary.succeeded().count(), 1);
+    assert!(service.on_price(EPIC, 130.0, 131.0).is_empty());
+}
+
+#[tokio::test]
+async fn test_paper_stop_limit_rests_as_limit_when_gapping() {
+    let service = paper_service();
+    let session = test_session();
+
+    let order =
+        CreateWorkingOrderRequest::stop_limit(EPIC.to_string(), Direction::Buy, 1.0, 110.0, 112.0);
+    service
+        .create_working_order(&session, &order)
+        .await
+        .unwrap();
+
+    // The price gaps through the trigger and the limit: the order rests at 112
+    assert!(service.on_price(EPIC, 114.0, 115.0).is_empty());
+    let working_orders = service
+        .get_working_orders(&session)
+        .await
+        .unwrap()
+        .working_orders;
+    assert_eq!(working_orders.len(), 1);
+    assert_eq!(
+        working_orders[0].working_order_data.order_type,
+        OrderType::Limit
+    );
+    assert_eq!(working_orders[0].working_order_data.order_level, 112.0);
+
+    let confirmations = service.on_price(EPIC, 110.5, 111.5);
+    assert_eq!(confirmations.len(), 1);
+    assert_eq!(confirmations[0].level, Some(111.5));
+    assert_eq!(service.positions().len(), 1);
+}
@@ -0,0 +1,127 @@
+use ig_client::application::services::account_service::AccountServiceImpl;
+use ig_client::application::services::portfolio_service::PortfolioService;
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use ig_client::utils::currency::CurrencyConverter;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+// Mock HTTP client answering for the account of the session: ACC1 in EUR, ACC2 in USD
+struct AccountsHttpClient;
+
+fn account_json(account_id: &str, currency: &str, balance: f64) -> Value {
+    json!({
+        "accountId": account_id,
+        "accountName": account_id,
+        "accountType": "CFD",
+        "balance": {
+            "balance": balance,
+            "deposit": 0.0,
+            "profitLoss": 10.0,
+            "available": balance
+        },
+        "currency": currency,
+        "status": "ENABLED",
+        "preferred": account_id == "ACC1"
+    })
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for AccountsHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        path: &str,
+        session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        let response = match path {
+            "accounts" => json!({
+                "accounts": [
+                    account_json("ACC1", "EUR", 1000.0),
+                    account_json("ACC2", "USD", 2000.0),
+                ]
+            }),
+            "positions" if session.account_id == "ACC1" => {
+                let position: Value =
+                    serde_json::from_str(include_str!("../models/position.json")).unwrap();
+                json!({ "positions": [position] })
+            }
+            "positions" => json!({ "positions": [] }),
+            "workingorders" => json!({ "workingOrders": [] }),
+            _ => panic!("Unexpected path {path}"),
+        };
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Mock HTTP client does not support unauthenticated requests");
+    }
+}
+
+fn session(account_id: &str) -> IgSession {
+    IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        account_id.to_string(),
+    )
+}
+
+#[tokio::test]
+async fn test_portfolio_report_combines_accounts() {
+    let service =
+        AccountServiceImpl::new(Arc::new(Config::default()), Arc::new(AccountsHttpClient));
+    let portfolio =
+        PortfolioService::new(service, CurrencyConverter::new("EUR").with_rate("USD", 0.5));
+
+    let report = portfolio
+        .report(&[session("ACC1"), session("ACC2")])
+        .await
+        .unwrap();
+    assert_eq!(report.base_currency, "EUR");
+    assert_eq!(report.accounts.len(), 2);
+    assert_eq!(report.accounts["ACC1"].balance, 1000.0);
+    assert_eq!(report.accounts["ACC2"].balance, 1000.0);
+    assert_eq!(
+        report.accounts["ACC2"].account.balance.balance.amount,
+        2000.0
+    );
+    assert_eq!(report.total_balance(), 2000.0);
+    assert_eq!(report.total_profit_loss(), 15.0);
+    assert_eq!(report.total_equity(), 2015.0);
+    assert_eq!(report.total_available(), 2000.0);
+
+    let positions: Vec<&str> = report
+        .positions()
+        .map(|(account_id, _)| account_id)
+        .collect();
+    assert_eq!(positions, vec!["ACC1"]);
+    assert_eq!(report.working_orders().count(), 0);
+}
+
+#[tokio::test]
+async fn test_portfolio_report_requires_rates() {
+    let service =
+        AccountServiceImpl::new(Arc::new(Config::default()), Arc::new(AccountsHttpClient));
+    let portfolio = PortfolioService::new(service, CurrencyConverter::new("EUR"));
+
+    assert!(matches!(
+        portfolio.report(&[session("ACC1"), session("ACC2")]).await,
+        Err(AppError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        portfolio.report(&[session("ACC3")]).await,
+        Err(AppError::NotFound)
+    ));
+}
@@ -0,0 +1,145 @@
+use ig_client::application::services::account_service::AccountServiceImpl;
+use ig_client::application::services::positions_cache::{PositionEvent, PositionsCache};
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::presentation::trade::OpenPositionUpdate;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use ig_client::utils::periodic::ShutdownToken;
+use ig_client::utils::rate_limiter::{RateLimitType, create_rate_limiter};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Mock HTTP client serving the current list of positions and counting requests
+struct PositionsHttpClient {
+    positions: Mutex<Vec<Value>>,
+    requests: AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for PositionsHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        assert_eq!(path, "positions");
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        let positions = self.positions.lock().unwrap().clone();
+        Ok(serde_json::from_value(json!({ "positions": positions }))?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Mock HTTP client does not support unauthenticated requests");
+    }
+}
+
+fn position(deal_id: &str, size: f64) -> Value {
+    let mut position: Value = serde_json::from_str(
+        &std::fs::read_to_string("tests/unit/application/models/position.json").unwrap(),
+    )
+    .unwrap();
+    position["position"]["dealId"] = json!(deal_id);
+    position["position"]["size"] = json!(size);
+    position
+}
+
+fn setup(
+    positions: Vec<Value>,
+) -> (
+    Arc<PositionsHttpClient>,
+    PositionsCache<AccountServiceImpl<PositionsHttpClient>>,
+    IgSession,
+) {
+    let client = Arc::new(PositionsHttpClient {
+        positions: Mutex::new(positions),
+        requests: AtomicUsize::new(0),
+    });
+    let service = AccountServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let cache = PositionsCache::new(service)
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None));
+    let session = IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    );
+    (client, cache, session)
+}
+
+#[tokio::test]
+async fn test_positions_cache_reports_changes() {
+    let (client, cache, session) = setup(vec![position("DEAL1", 1.0), position("DEAL2", 1.0)]);
+    let mut events = cache.subscribe();
+    assert!(cache.current().positions.is_empty());
+
+    assert!(cache.refresh(&session).await.unwrap().is_empty());
+    assert_eq!(cache.current().positions.len(), 2);
+    assert!(cache.last_refresh().is_some());
+
+    *client.positions.lock().unwrap() = vec![position("DEAL1", 2.0), position("DEAL3", 1.0)];
+    let changes = cache.refresh(&session).await.unwrap();
+    assert_eq!(changes.len(), 3);
+    assert!(matches!(
+        &changes[0],
+        PositionEvent::Updated { previous, current }
+            if previous.position.size == 1.0 && current.position.size == 2.0
+    ));
+    assert!(matches!(&changes[1], PositionEvent::Opened(p) if p.position.deal_id == "DEAL3"));
+    assert!(matches!(&changes[2], PositionEvent::Closed(p) if p.position.deal_id == "DEAL2"));
+
+    let received: Vec<String> = (0..3)
+        .map(|_| events.try_recv().unwrap().deal_id().to_string())
+        .collect();
+    assert_eq!(received, vec!["DEAL1", "DEAL3", "DEAL2"]);
+    assert_eq!(cache.get("DEAL1").unwrap().position.size, 2.0);
+    assert!(cache.get("DEAL2").is_none());
+
+    // Nothing changed, nothing reported
+    assert!(cache.refresh(&session).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_positions_cache_refreshes_on_position_update() {
+    let (client, cache, session) = setup(vec![position("DEAL1", 1.0)]);
+    let cache = Arc::new(cache.with_interval(Duration::from_secs(3600)));
+    let mut events = cache.subscribe();
+    let shutdown = ShutdownToken::new();
+    let runner = {
+        let cache = cache.clone();
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move { cache.run(&session, &shutdown).await })
+    };
+
+    while cache.last_refresh().is_none() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(client.requests.load(Ordering::SeqCst), 1);
+
+    *client.positions.lock().unwrap() = vec![position("DEAL1", 1.0), position("DEAL2", 1.0)];
+    cache.on_position_update(&OpenPositionUpdate::default());
+    let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.deal_id(), "DEAL2");
+    assert_eq!(client.requests.load(Ordering::SeqCst), 2);
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), runner)
+        .await
+        .unwrap()
+        .unwrap();
+}
@@ -0,0 +1,248 @@
+use chrono::{TimeZone, Utc};
+use ig_client::application::models::market::Resolution;
+use ig_client::application::services::market_service::MarketServiceImpl;
+use ig_client::application::services::price_backfill::{
+    BackfillCheckpoint, BackfillStatus, PriceBackfill,
+};
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use ig_client::utils::rate_limiter::{RateLimitType, RateLimiterRegistry, create_rate_limiter};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+// Mock HTTP client returning two candles per request and a decreasing allowance, until
+// the allowance is spent
+struct MockHttpClient {
+    paths: Mutex<Vec<String>>,
+    allowance: Mutex<i64>,
+}
+
+impl MockHttpClient {
+    fn paths(&self) -> Vec<String> {
+        self.paths.lock().unwrap().clone()
+    }
+}
+
+fn candle(time: &str) -> Value {
+    let point = json!({ "bid": 100.0, "ask": 101.0, "lastTraded": null });
+    json!({
+        "snapshotTime": time,
+        "snapshotTimeUTC": time,
+        "openPrice": point,
+        "highPrice": point,
+        "lowPrice": point,
+        "closePrice": point,
+        "lastTradedVolume": 10
+    })
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for MockHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        self.paths.lock().unwrap().push(path.to_string());
+        let mut allowance = self.allowance.lock().unwrap();
+        if *allowance <= 0 {
+            return Err(AppError::HistoricalAllowanceExceeded);
+        }
+        *allowance -= 2;
+        Ok(serde_json::from_value(json!({
+            "prices": [candle("2025/01/01 00:00:00"), candle("2025/01/01 01:00:00")],
+            "instrumentType": "INDICES",
+            "metadata": {
+                "allowance": {
+                    "remainingAllowance": *allowance,
+                    "totalAllowance": 10000,
+                    "allowanceExpiry": 3600
+                },
+                "size": 2,
+                "pageData": { "pageSize": 20, "pageNumber": 1, "totalPages": 1 }
+            }
+        }))?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Mock HTTP client does not support unauthenticated requests");
+    }
+}
+
+fn setup(
+    allowance: i64,
+) -> (
+    Arc<MockHttpClient>,
+    MarketServiceImpl<MockHttpClient>,
+    IgSession,
+) {
+    let client = Arc::new(MockHttpClient {
+        paths: Mutex::new(Vec::new()),
+        allowance: Mutex::new(allowance),
+    });
+    let service = MarketServiceImpl::new(Arc::new(Config::default()), client.clone());
+    // Limiters of its own, allowing the whole allowance the backfill pauses on
+    let session = IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    )
+    .with_rate_limiters(
+        RateLimiterRegistry::new()
+            .with_safety_margin(1.0)
+            .for_account("BACKFILL", "ACC123"),
+    );
+    (client, service, session)
+}
+
+fn checkpoint() -> BackfillCheckpoint {
+    BackfillCheckpoint::new(
+        &["EPIC.A".to_string(), "EPIC.B".to_string()],
+        &[Resolution::Hour],
+        Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+        Utc.with_ymd_and_hms(2025, 1, 1, 4, 0, 0).unwrap(),
+    )
+}
+
+fn failing_start() -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap()
+}
+
+fn checkpoint_with_one_task() -> BackfillCheckpoint {
+    BackfillCheckpoint::new(
+        &["EPIC.C".to_string()],
+        &[Resolution::Day],
+        failing_start(),
+        Utc.with_ymd_and_hms(2025, 1, 10, 0, 0, 0).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_backfill_downloads_every_window() {
+    let (client, service, session) = setup(10_000);
+    let backfill = PriceBackfill::new(&service)
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingApp, None))
+        .with_window_points(2);
+
+    let mut checkpoint = checkpoint();
+    let mut received = Vec::new();
+    let status = backfill
+        .run(&session, &mut checkpoint, |task, prices| {
+            received.push((task.epic.clone(), prices.len()));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(status, BackfillStatus::Complete);
+    assert_eq!(
+        client.paths()[..2],
+        [
+            "prices/EPIC.A?resolution=HOUR&from=2025-01-01T00:00:00&to=2025-01-01T01:59:59&pageNumber=1",
+            "prices/EPIC.A?resolution=HOUR&from=2025-01-01T02:00:00&to=2025-01-01T04:00:00&pageNumber=1",
+        ]
+    );
+    assert_eq!(received.len(), 4);
+    assert_eq!(received[2], ("EPIC.B".to_string(), 2));
+    assert_eq!(checkpoint.tasks[1].points, 4);
+    assert_eq!(checkpoint.remaining_allowance, Some(10_000 - 8));
+}
+
+#[tokio::test]
+async fn test_backfill_pauses_once_allowance_spent() {
+    let (client, service, session) = setup(0);
+    let backfill = PriceBackfill::new(&service).with_min_allowance(-1);
+
+    let mut checkpoint = checkpoint();
+    checkpoint.allowance_resets_at = Some(Utc::now() - chrono::Duration::hours(1));
+    let status = backfill
+        .run(&session, &mut checkpoint, |_, _| Ok(()))
+        .await
+        .unwrap();
+
+    // Not retried, and the next run waits for the allowance to reset
+    assert_eq!(status, BackfillStatus::Paused { pending: 2 });
+    assert_eq!(client.paths().len(), 1);
+    assert_eq!(checkpoint.remaining_allowance, Some(0));
+    assert!(checkpoint.is_allowance_exhausted(0, Utc::now() + chrono::Duration::days(6)));
+}
+
+#[tokio::test]
+async fn test_backfill_pauses_when_points_budget_spent() {
+    let (client, service, session) = setup(10_000);
+    session
+        .rate_limiters()
+        .historical_price
+        .consume(10_000)
+        .await;
+    let backfill = PriceBackfill::new(&service);
+
+    let mut checkpoint = checkpoint();
+    let status = backfill
+        .run(&session, &mut checkpoint, |_, _| Ok(()))
+        .await
+        .unwrap();
+    assert_eq!(status, BackfillStatus::Paused { pending: 2 });
+    assert!(client.paths().is_empty());
+}
+
+#[tokio::test]
+async fn test_backfill_pauses_on_allowance_and_resumes() {
+    let (client, service, session) = setup(5);
+    let backfill = PriceBackfill::new(&service)
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingApp, None))
+        .with_window_points(2)
+        .with_min_allowance(1);
+
+    let mut checkpoint = checkpoint();
+    let status = backfill
+        .run(&session, &mut checkpoint, |_, _| Ok(()))
+        .await
+        .unwrap();
+    assert_eq!(status, BackfillStatus::Paused { pending: 1 });
+    assert_eq!(client.paths().len(), 2);
+    assert_eq!(checkpoint.remaining_allowance, Some(1));
+
+    // Round-trip the checkpoint as a persisted backfill would, once the allowance reset
+    let json = serde_json::to_string(&checkpoint).unwrap();
+    let mut checkpoint: BackfillCheckpoint = serde_json::from_str(&json).unwrap();
+    checkpoint.allowance_resets_at = Some(Utc::now() - chrono::Duration::seconds(1));
+    *client.allowance.lock().unwrap() = 10_000;
+    // The points counted by the limiters expired with the allowance
+    let session = session.with_rate_limiters(
+        RateLimiterRegistry::new()
+            .with_safety_margin(1.0)
+            .for_account("BACKFILL", "ACC123"),
+    );
+
+    let status = backfill
+        .run(&session, &mut checkpoint, |_, _| Ok(()))
+        .await
+        .unwrap();
+    assert_eq!(status, BackfillStatus::Complete);
+    assert_eq!(client.paths().len(), 4);
+    assert!(checkpoint.tasks.iter().all(|task| task.points == 4));
+
+    // A failing sink keeps the window pending
+    let mut failing = checkpoint_with_one_task();
+    let result = backfill
+        .run(&session, &mut failing, |_, _| {
+            Err(AppError::InvalidInput("disk full".to_string()))
+        })
+        .await;
+    assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    assert_eq!(failing.tasks[0].cursor, failing_start());
+}
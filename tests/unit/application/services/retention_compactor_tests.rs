@@ -0,0 +1,160 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use ig_client::application::models::market::Resolution;
+use ig_client::application::services::retention_compactor::{CompactionReport, RetentionCompactor};
+use ig_client::error::AppError;
+use ig_client::storage::Store;
+use ig_client::storage::memory_store::MemoryStore;
+use ig_client::storage::retention::RetentionPolicy;
+use ig_client::storage::store::{Candle, StoreQuery, Tick};
+
+const DAX: &str = "IX.D.DAX.DAILY.IP";
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 7, 31, 12, 0, 30).unwrap()
+}
+
+fn tick(age_minutes: i64, bid: f64) -> Tick {
+    Tick {
+        epic: DAX.to_string(),
+        timestamp: now() - Duration::minutes(age_minutes),
+        bid: Some(bid),
+        offer: Some(bid + 2.0),
+    }
+}
+
+fn candle(resolution: Resolution, timestamp: DateTime<Utc>, close: f64) -> Candle {
+    Candle {
+        epic: DAX.to_string(),
+        resolution,
+        timestamp,
+        open: close,
+        high: close,
+        low: close,
+        close,
+        volume: None,
+    }
+}
+
+#[tokio::test]
+async fn test_compact_ticks_to_candles() {
+    let store = MemoryStore::new();
+    store
+        .put_ticks(&[tick(90, 100.0), tick(89, 104.0), tick(5, 110.0)])
+        .await
+        .unwrap();
+    // The minute of the oldest tick is already stored and is kept
+    let fetched = candle(
+        Resolution::Minute,
+        Resolution::Minute.align(tick(90, 0.0).timestamp),
+        50.0,
+    );
+    store
+        .put_candles(std::slice::from_ref(&fetched))
+        .await
+        .unwrap();
+
+    let policy = RetentionPolicy::new().compact_ticks(Duration::hours(1), Resolution::Minute);
+    let compactor = RetentionCompactor::new(store, policy);
+    let report = compactor.compact_at(now()).await.unwrap();
+    assert_eq!(
+        report,
+        CompactionReport {
+            candles_written: 1,
+            candles_deleted: 0,
+            ticks_deleted: 2,
+        }
+    );
+
+    let store = compactor.store();
+    let ticks = store.query_ticks(&StoreQuery::new()).await.unwrap();
+    assert_eq!(ticks, [tick(5, 110.0)]);
+    let candles = store
+        .query_candles(Resolution::Minute, &StoreQuery::new())
+        .await
+        .unwrap();
+    assert_eq!(candles.len(), 2);
+    assert_eq!(candles[0], fetched);
+    assert_eq!(candles[1].close, 105.0);
+    assert_eq!(candles[1].volume, Some(1));
+
+    // Running again finds nothing to do
+    assert_eq!(
+        compactor.compact_at(now()).await.unwrap(),
+        CompactionReport::default()
+    );
+}
+
+#[tokio::test]
+async fn test_compact_candles_cascade() {
+    let store = MemoryStore::new();
+    let day = Utc.with_ymd_and_hms(2025, 6, 1, 9, 0, 0).unwrap();
+    let minutes: Vec<Candle> = (0..90)
+        .map(|minute| {
+            candle(
+                Resolution::Minute,
+                day + Duration::minutes(minute),
+                minute as f64,
+            )
+        })
+        .collect();
+    store.put_candles(&minutes).await.unwrap();
+    let recent = candle(Resolution::Minute, now() - Duration::minutes(1), 1.0);
+    store
+        .put_candles(std::slice::from_ref(&recent))
+        .await
+        .unwrap();
+
+    let policy = RetentionPolicy::new()
+        .compact_candles(Resolution::Minute, Duration::days(7), Resolution::Hour)
+        .keep_candles(Resolution::Hour, Duration::days(365));
+    let compactor = RetentionCompactor::new(store, policy);
+    let report = compactor.compact_at(now()).await.unwrap();
+    assert_eq!(report.candles_written, 2);
+    assert_eq!(report.candles_deleted, 90);
+
+    let store = compactor.store();
+    let hours = store
+        .query_candles(Resolution::Hour, &StoreQuery::new())
+        .await
+        .unwrap();
+    assert_eq!(hours.len(), 2);
+    assert_eq!((hours[0].open, hours[0].close), (0.0, 59.0));
+    assert_eq!((hours[1].open, hours[1].close), (60.0, 89.0));
+    assert_eq!(
+        store
+            .query_candles(Resolution::Minute, &StoreQuery::new())
+            .await
+            .unwrap(),
+        [recent]
+    );
+
+    // A year later the recent minute is compacted too, then every hour is pruned
+    let report = compactor
+        .compact_at(now() + Duration::days(366))
+        .await
+        .unwrap();
+    assert_eq!(report.candles_written, 1);
+    assert_eq!(report.candles_deleted, 4);
+    assert!(
+        store
+            .query_candles(Resolution::Hour, &StoreQuery::new())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_compact_rejects_finer_target() {
+    let store = MemoryStore::new();
+    let policy = RetentionPolicy::new().compact_candles(
+        Resolution::Hour,
+        Duration::days(1),
+        Resolution::Minute,
+    );
+    let compactor = RetentionCompactor::new(store, policy);
+    assert!(matches!(
+        compactor.compact_at(now()).await,
+        Err(AppError::InvalidInput(_))
+    ));
+}
@@ -0,0 +1,265 @@
+use ig_client::application::models::order::{CreateOrderRequest, Direction};
+use ig_client::application::services::OrderService;
+use ig_client::application::services::account_service::AccountServiceImpl;
+use ig_client::application::services::order_service::OrderServiceImpl;
+use ig_client::application::services::risk_guard::{RiskGuard, RiskLimits, RiskViolation};
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const POSITION_EPIC: &str = "OP.D.OTCDAXWK.23650P.IP";
+
+// Mock HTTP client holding one open position and accepting every order
+struct AccountHttpClient {
+    orders: Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for AccountHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        let response = match (&method, path) {
+            (&Method::GET, "positions") => {
+                let position: Value =
+                    serde_json::from_str(include_str!("../models/position.json"))?;
+                json!({ "positions": [position] })
+            }
+            (&Method::GET, "positions/DIAAAAT9SU2UMBB") => {
+                serde_json::from_str(include_str!("../models/position.json"))?
+            }
+            (&Method::POST | &Method::DELETE, "positions/otc") => {
+                let mut orders = self.orders.lock().unwrap();
+                orders.push(format!("{method} {path}"));
+                json!({ "dealReference": format!("REF{}", orders.len()) })
+            }
+            (&Method::GET, confirm) if confirm.starts_with("confirms/") => json!({
+                "date": "2025-05-22T09:00:00.000",
+                "status": "OPEN",
+                "reason": "SUCCESS",
+                "dealStatus": "ACCEPTED",
+                "epic": POSITION_EPIC,
+                "expiry": "04-JUL-25",
+                "dealReference": &confirm["confirms/".len()..],
+                "dealId": "DIAAAABBBCCC",
+                "level": 65.2,
+                "size": 1.0,
+                "direction": "BUY",
+                "guaranteedStop": false,
+                "trailingStop": false
+            }),
+            _ => return Err(AppError::NotFound),
+        };
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Account HTTP client does not support unauthenticated requests");
+    }
+}
+
+type TestGuard =
+    RiskGuard<OrderServiceImpl<AccountHttpClient>, AccountServiceImpl<AccountHttpClient>>;
+
+fn guard(limits: RiskLimits) -> (Arc<AccountHttpClient>, TestGuard) {
+    let config = Arc::new(Config::default());
+    let client = Arc::new(AccountHttpClient {
+        orders: Mutex::new(Vec::new()),
+    });
+    let guard = RiskGuard::new(
+        OrderServiceImpl::new(config.clone(), client.clone()),
+        AccountServiceImpl::new(config, client.clone()),
+        limits,
+    );
+    (client, guard)
+}
+
+fn test_session() -> IgSession {
+    IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    )
+}
+
+fn market_order(epic: &str, size: f64) -> CreateOrderRequest {
+    CreateOrderRequest::market(epic.to_string(), Direction::Buy, size, "EUR".to_string())
+}
+
+fn violation(result: Result<impl std::fmt::Debug, AppError>) -> RiskViolation {
+    match result {
+        Err(AppError::RiskLimit(violation)) => violation,
+        other => panic!("expected a risk limit violation, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_risk_guard_max_open_positions() {
+    let (client, guard) = guard(RiskLimits::new().with_max_open_positions(1));
+    let session = test_session();
+
+    let result = guard
+        .create_order(&session, &market_order("IX.D.DAX.DAILY.IP", 1.0))
+        .await;
+    assert_eq!(
+        violation(result),
+        RiskViolation::MaxOpenPositions { open: 1, max: 1 }
+    );
+    assert!(client.orders.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_risk_guard_max_size_per_epic() {
+    let (client, guard) = guard(RiskLimits::new().with_max_size_per_epic(1.5));
+    let session = test_session();
+
+    // The open position already holds 1.0 on this EPIC
+    let result = guard
+        .create_order(&session, &market_order(POSITION_EPIC, 1.0))
+        .await;
+    assert_eq!(
+        violation(result),
+        RiskViolation::MaxSizePerEpic {
+            epic: POSITION_EPIC.to_string(),
+            size: 2.0,
+            max: 1.5,
+        }
+    );
+
+    let response = guard
+        .create_order(&session, &market_order("IX.D.DAX.DAILY.IP", 1.0))
+        .await
+        .unwrap();
+    assert_eq!(response.deal_reference, "REF1");
+    assert_eq!(client.orders.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_risk_guard_max_notional_exposure() {
+    let (_, guard) = guard(RiskLimits::new().with_max_notional_exposure(100.0));
+    let session = test_session();
+
+    // A market order on a market without position has no price
+    let result = guard
+        .create_order(&session, &market_order("IX.D.DAX.DAILY.IP", 1.0))
+        .await;
+    assert_eq!(
+        violation(result),
+        RiskViolation::UnknownPrice {
+            epic: "IX.D.DAX.DAILY.IP".to_string()
+        }
+    );
+
+    // The short position is worth 68.2 at the offer
+    let order = CreateOrderRequest::limit(
+        "IX.D.DAX.DAILY.IP".to_string(),
+        Direction::Buy,
+        1.0,
+        20.0,
+        "EUR".to_string(),
+    );
+    assert!(guard.create_order(&session, &order).await.is_ok());
+
+    let order = CreateOrderRequest::limit(
+        "IX.D.DAX.DAILY.IP".to_string(),
+        Direction::Buy,
+        2.0,
+        20.0,
+        "EUR".to_string(),
+    );
+    match violation(guard.create_order(&session, &order).await) {
+        RiskViolation::MaxNotionalExposure { exposure, max } => {
+            assert!((exposure - 108.2).abs() < 1e-9);
+            assert_eq!(max, 100.0);
+        }
+        other => panic!("unexpected violation {other:?}"),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_risk_guard_max_orders_per_minute() {
+    let (client, guard) = guard(RiskLimits::new().with_max_orders_per_minute(2));
+    let session = test_session();
+    let order = market_order("IX.D.DAX.DAILY.IP", 1.0);
+
+    assert!(guard.create_order(&session, &order).await.is_ok());
+    assert!(guard.create_order(&session, &order).await.is_ok());
+    assert_eq!(
+        violation(guard.create_order(&session, &order).await),
+        RiskViolation::MaxOrdersPerMinute { max: 2 }
+    );
+
+    tokio::time::advance(Duration::from_secs(60)).await;
+    assert!(guard.create_order(&session, &order).await.is_ok());
+    assert_eq!(client.orders.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_risk_guard_checks_the_opening_of_a_reversal_before_closing() {
+    let (client, guard) = guard(RiskLimits::new().with_max_size_per_epic(0.5));
+    let session = test_session();
+
+    let result = guard.reverse_position(&session, "DIAAAAT9SU2UMBB").await;
+    assert_eq!(
+        violation(result),
+        RiskViolation::MaxSizePerEpic {
+            epic: POSITION_EPIC.to_string(),
+            size: 1.0,
+            max: 0.5,
+        }
+    );
+    assert!(client.orders.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_risk_guard_leaves_the_reversed_position_out_of_the_limits() {
+    let (client, guard) = guard(
+        RiskLimits::new()
+            .with_max_open_positions(1)
+            .with_max_notional_exposure(70.0),
+    );
+    let session = test_session();
+
+    // The opposite order replaces the position, priced at its mid of 65.2
+    let reversal = guard
+        .reverse_position(&session, "DIAAAAT9SU2UMBB")
+        .await
+        .unwrap();
+    assert!(reversal.is_complete());
+    assert_eq!(
+        *client.orders.lock().unwrap(),
+        vec!["DELETE positions/otc", "POST positions/otc"]
+    );
+}
+
+#[test]
+fn test_risk_guard_confirms_within_the_timeout_of_the_wrapped_service() {
+    let config = Arc::new(Config::default());
+    let client = Arc::new(AccountHttpClient {
+        orders: Mutex::new(Vec::new()),
+    });
+    let guard = RiskGuard::new(
+        OrderServiceImpl::new(config.clone(), client.clone())
+            .with_confirmation_timeout(Duration::from_secs(30)),
+        AccountServiceImpl::new(config, client),
+        RiskLimits::default(),
+    );
+    assert_eq!(guard.confirmation_timeout(), Duration::from_secs(30));
+}
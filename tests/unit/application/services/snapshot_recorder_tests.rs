@@ -0,0 +1,179 @@
+use chrono::{Duration, Utc};
+use ig_client::application::services::account_service::AccountServiceImpl;
+use ig_client::application::services::snapshot_recorder::AccountSnapshotRecorder;
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::storage::account_snapshots::{AccountSnapshotStore, FileAccountSnapshotStore};
+use ig_client::transport::http_client::IgHttpClient;
+use ig_client::utils::rate_limiter::{RateLimitType, create_rate_limiter};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+// Mock HTTP client serving the accounts, the current positions and one working order
+struct AccountHttpClient {
+    positions: Mutex<Vec<Value>>,
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for AccountHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        path: &str,
+        _session: &IgSession,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        let response = match path {
+            "accounts" => json!({
+                "accounts": [{
+                    "accountId": "ACC123",
+                    "accountName": "Demo",
+                    "accountType": "CFD",
+                    "balance": {
+                        "balance": 1000.0,
+                        "deposit": 200.0,
+                        "profitLoss": -6.0,
+                        "available": 794.0
+                    },
+                    "currency": "EUR",
+                    "status": "ENABLED",
+                    "preferred": true
+                }]
+            }),
+            "positions" => json!({ "positions": *self.positions.lock().unwrap() }),
+            "workingorders" => json!({ "workingOrders": [working_order()] }),
+            _ => panic!("unexpected path {path}"),
+        };
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Mock HTTP client does not support unauthenticated requests");
+    }
+}
+
+fn position() -> Value {
+    serde_json::from_str(
+        &std::fs::read_to_string("tests/unit/application/models/position.json").unwrap(),
+    )
+    .unwrap()
+}
+
+fn working_order() -> Value {
+    json!({
+        "workingOrderData": {
+            "dealId": "ORDER1",
+            "direction": "BUY",
+            "epic": "IX.D.DAX.DAILY.IP",
+            "orderSize": 1.0,
+            "orderLevel": 18000.0,
+            "timeInForce": "GOOD_TILL_CANCELLED",
+            "goodTillDate": null,
+            "goodTillDateISO": null,
+            "createdDate": "2025/05/22 09:00:00:000",
+            "createdDateUTC": "2025-05-22T08:00:00",
+            "guaranteedStop": false,
+            "orderType": "LIMIT",
+            "stopDistance": null,
+            "limitDistance": null,
+            "currencyCode": "EUR",
+            "dma": false,
+            "limitedRiskPremium": null,
+            "dealReference": null
+        },
+        "marketData": {
+            "instrumentName": "Germany 40",
+            "exchangeId": "XETRA",
+            "expiry": "DFB",
+            "marketStatus": "TRADEABLE",
+            "epic": "IX.D.DAX.DAILY.IP",
+            "instrumentType": "INDICES",
+            "lotSize": 1.0,
+            "high": 18100.0,
+            "low": 17900.0,
+            "percentageChange": 0.1,
+            "netChange": 10.0,
+            "bid": 18000.0,
+            "offer": 18001.0,
+            "updateTime": "09:00:00",
+            "updateTimeUTC": "08:00:00",
+            "delayTime": 0,
+            "streamingPricesAvailable": true,
+            "scalingFactor": 1
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_snapshot_recorder_persists_account() {
+    let path = std::env::temp_dir().join(format!(
+        "ig_account_snapshots_{}.ndjson",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let client = Arc::new(AccountHttpClient {
+        positions: Mutex::new(vec![position()]),
+    });
+    let service = AccountServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let recorder = AccountSnapshotRecorder::new(service, FileAccountSnapshotStore::open(&path))
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None));
+    let session = IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    );
+
+    let from = Utc::now();
+    let first = recorder.record(&session).await.unwrap();
+    assert_eq!(first.account_id, "ACC123");
+    assert_eq!(first.positions.len(), 1);
+    assert!(first.find_position("DIAAAAT9SU2UMBB").is_some());
+    assert!(first.find_working_order("ORDER1").is_some());
+    assert_eq!(first.balance_snapshot().equity(), 994.0);
+
+    client.positions.lock().unwrap().clear();
+    let second = recorder.record(&session).await.unwrap();
+    assert!(second.positions.is_empty());
+
+    let to = Utc::now() + Duration::seconds(1);
+    let stored = recorder.store().query("ACC123", from, to).unwrap();
+    assert_eq!(stored.len(), 2);
+    assert_eq!(stored[0].positions.len(), 1);
+    assert_eq!(
+        stored[0].working_orders[0].working_order_data.epic,
+        "IX.D.DAX.DAILY.IP"
+    );
+    assert!(
+        recorder
+            .store()
+            .query("OTHER", from, to)
+            .unwrap()
+            .is_empty()
+    );
+
+    let latest = recorder
+        .store()
+        .latest_before("ACC123", to)
+        .unwrap()
+        .unwrap();
+    assert!(latest.positions.is_empty());
+    assert!(
+        recorder
+            .store()
+            .latest_before("ACC123", from)
+            .unwrap()
+            .is_none()
+    );
+
+    std::fs::remove_file(&path).unwrap();
+}
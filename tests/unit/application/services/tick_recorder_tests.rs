@@ -0,0 +1,273 @@
+use chrono::{DateTime, TimeZone, Utc};
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::application::models::market::Resolution;
+use ig_client::application::services::tick_recorder::{
+    TickRecorder, epic_of_item, tick_from_price,
+};
+use ig_client::error::AppError;
+use ig_client::presentation::PriceData;
+use ig_client::storage::Store;
+use ig_client::storage::batch_writer::BatchWriterConfig;
+use ig_client::storage::instrument_catalog::{CatalogEntry, CatalogQuery};
+use ig_client::storage::memory_store::MemoryStore;
+use ig_client::storage::order_journal::{DealRecord, JournalEntry};
+use ig_client::storage::store::{Candle, StoreQuery, Tick, WriteReport};
+use serde_json::json;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// Memory store recording the tick batches written, failing them while `failing` is set
+#[derive(Default)]
+struct TickBatches {
+    store: MemoryStore,
+    batches: Mutex<Vec<Vec<Tick>>>,
+    failing: AtomicBool,
+}
+
+#[async_trait::async_trait]
+impl Store for TickBatches {
+    async fn put_candles(&self, candles: &[Candle]) -> Result<WriteReport, AppError> {
+        self.store.put_candles(candles).await
+    }
+
+    async fn get_candle(
+        &self,
+        epic: &str,
+        resolution: Resolution,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Candle>, AppError> {
+        self.store.get_candle(epic, resolution, timestamp).await
+    }
+
+    async fn query_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<Vec<Candle>, AppError> {
+        self.store.query_candles(resolution, query).await
+    }
+
+    async fn delete_candles(
+        &self,
+        resolution: Resolution,
+        query: &StoreQuery,
+    ) -> Result<usize, AppError> {
+        self.store.delete_candles(resolution, query).await
+    }
+
+    async fn put_ticks(&self, ticks: &[Tick]) -> Result<WriteReport, AppError> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(AppError::Unexpected(
+                reqwest::StatusCode::SERVICE_UNAVAILABLE,
+            ));
+        }
+        self.batches.lock().unwrap().push(ticks.to_vec());
+        self.store.put_ticks(ticks).await
+    }
+
+    async fn get_tick(
+        &self,
+        epic: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Tick>, AppError> {
+        self.store.get_tick(epic, timestamp).await
+    }
+
+    async fn query_ticks(&self, query: &StoreQuery) -> Result<Vec<Tick>, AppError> {
+        self.store.query_ticks(query).await
+    }
+
+    async fn delete_ticks(&self, query: &StoreQuery) -> Result<usize, AppError> {
+        self.store.delete_ticks(query).await
+    }
+
+    async fn put_transactions(
+        &self,
+        transactions: &[AccountTransaction],
+    ) -> Result<WriteReport, AppError> {
+        self.store.put_transactions(transactions).await
+    }
+
+    async fn get_transactions(&self, reference: &str) -> Result<Vec<AccountTransaction>, AppError> {
+        self.store.get_transactions(reference).await
+    }
+
+    async fn query_transactions(
+        &self,
+        query: &StoreQuery,
+    ) -> Result<Vec<AccountTransaction>, AppError> {
+        self.store.query_transactions(query).await
+    }
+
+    async fn put_orders(&self, entries: &[JournalEntry]) -> Result<WriteReport, AppError> {
+        self.store.put_orders(entries).await
+    }
+
+    async fn get_orders(&self, correlation_id: &str) -> Result<Vec<JournalEntry>, AppError> {
+        self.store.get_orders(correlation_id).await
+    }
+
+    async fn query_orders(&self, query: &StoreQuery) -> Result<Vec<JournalEntry>, AppError> {
+        self.store.query_orders(query).await
+    }
+
+    async fn put_deals(&self, deals: &[DealRecord]) -> Result<WriteReport, AppError> {
+        self.store.put_deals(deals).await
+    }
+
+    async fn get_deal(&self, deal_reference: &str) -> Result<Option<DealRecord>, AppError> {
+        self.store.get_deal(deal_reference).await
+    }
+
+    async fn query_deals(&self, query: &StoreQuery) -> Result<Vec<DealRecord>, AppError> {
+        self.store.query_deals(query).await
+    }
+
+    async fn put_instruments(&self, entries: &[CatalogEntry]) -> Result<WriteReport, AppError> {
+        self.store.put_instruments(entries).await
+    }
+
+    async fn get_instrument(&self, epic: &str) -> Result<Option<CatalogEntry>, AppError> {
+        self.store.get_instrument(epic).await
+    }
+
+    async fn search_instruments(
+        &self,
+        query: &CatalogQuery,
+    ) -> Result<Vec<CatalogEntry>, AppError> {
+        self.store.search_instruments(query).await
+    }
+}
+
+fn price(epic: &str, millis: i64, bid: f64) -> PriceData {
+    serde_json::from_value(json!({
+        "item_name": format!("PRICE:ACC123:{epic}"),
+        "item_pos": 1,
+        "fields": {
+            "BIDPRICE1": bid.to_string(),
+            "ASKPRICE1": (bid + 1.0).to_string(),
+            "TIMESTAMP": millis.to_string()
+        },
+        "changed_fields": {},
+        "is_snapshot": false
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_tick_from_price() {
+    assert_eq!(
+        epic_of_item("PRICE:ACC123:IX.D.DAX.DAILY.IP"),
+        "IX.D.DAX.DAILY.IP"
+    );
+    assert_eq!(epic_of_item("IX.D.DAX.DAILY.IP"), "IX.D.DAX.DAILY.IP");
+
+    let received = Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap();
+    let tick = tick_from_price(
+        &price("IX.D.DAX.DAILY.IP", 1_751_360_400_250, 18000.5),
+        received,
+    )
+    .unwrap();
+    assert_eq!(tick.epic, "IX.D.DAX.DAILY.IP");
+    assert_eq!(tick.timestamp.timestamp_millis(), 1_751_360_400_250);
+    assert_eq!(tick.bid, Some(18000.5));
+    assert_eq!(tick.offer, Some(18001.5));
+
+    let mut quoteless = price("IX.D.DAX.DAILY.IP", 0, 1.0);
+    quoteless.fields = Default::default();
+    assert!(tick_from_price(&quoteless, received).is_none());
+}
+
+#[tokio::test]
+async fn test_tick_recorder_batches_per_epic() {
+    let mut recorder = TickRecorder::new(TickBatches::default())
+        .with_batch_size(3)
+        .with_flush_interval(Duration::from_secs(60))
+        .with_epics(["DAX", "FTSE"]);
+    assert!(recorder.record(&price("DAX", 1_000, 1.0)).await);
+    assert!(recorder.record(&price("FTSE", 1_000, 2.0)).await);
+    assert!(!recorder.record(&price("OTHER", 1_000, 3.0)).await);
+    // The third tick fills the batch, written one market at a time
+    assert!(recorder.record(&price("DAX", 2_000, 1.5)).await);
+    recorder.flush().await;
+    assert_eq!(recorder.pending(), 0);
+    assert_eq!(recorder.stats().written.inserted, 3);
+
+    let batches = recorder.store().batches.lock().unwrap().clone();
+    assert_eq!(batches.len(), 2);
+    assert!(batches[0].iter().all(|tick| tick.epic == "DAX"));
+    assert_eq!(batches[0].len(), 2);
+    assert_eq!(batches[1][0].epic, "FTSE");
+}
+
+#[tokio::test]
+async fn test_tick_recorder_retries_failed_batches() {
+    let mut recorder = TickRecorder::new(TickBatches::default()).with_writer_config(
+        BatchWriterConfig::new()
+            .with_batch_size(10)
+            .with_flush_interval(Duration::from_millis(10))
+            .with_retries(100, Duration::from_millis(5), Duration::from_millis(5)),
+    );
+    recorder.store().failing.store(true, Ordering::SeqCst);
+    recorder.record(&price("DAX", 1_000, 1.0)).await;
+    recorder.record(&price("DAX", 2_000, 2.0)).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(recorder.pending(), 2);
+    assert!(recorder.stats().failed_attempts > 0);
+
+    recorder.store().failing.store(false, Ordering::SeqCst);
+    recorder.flush().await;
+    assert_eq!(recorder.pending(), 0);
+    assert_eq!(recorder.stats().discarded, 0);
+    let batches = recorder.store().batches.lock().unwrap().clone();
+    let bids: Vec<Option<f64>> = batches[0].iter().map(|tick| tick.bid).collect();
+    assert_eq!(bids, [Some(1.0), Some(2.0)]);
+}
+
+#[tokio::test]
+async fn test_tick_recorder_drops_ticks_past_its_capacity() {
+    let mut recorder = TickRecorder::new(TickBatches::default())
+        .with_batch_size(2)
+        .with_flush_interval(Duration::from_millis(10))
+        .with_capacity(5);
+    recorder.store().failing.store(true, Ordering::SeqCst);
+    for i in 0..20 {
+        recorder.record(&price("DAX", i * 1_000, i as f64)).await;
+    }
+    // The queue and the batch being retried are all that is kept
+    let stats = recorder.stats();
+    assert!(recorder.pending() <= 7);
+    assert!(stats.dropped >= 13);
+    assert_eq!(stats.accepted + stats.dropped, 20);
+
+    recorder.store().failing.store(false, Ordering::SeqCst);
+    let stats = recorder.close().await;
+    assert_eq!(stats.written.total() as u64, stats.accepted);
+}
+
+#[tokio::test]
+async fn test_tick_recorder_run_flushes_on_end() {
+    let prices = futures::stream::iter((0..5).map(|i| price("DAX", i * 1_000, i as f64)));
+    let mut recorder = TickRecorder::new(TickBatches::default())
+        .with_batch_size(2)
+        .with_flush_interval(Duration::from_secs(60));
+    let stats = recorder.run(prices).await;
+    assert_eq!(stats.written.total(), 5);
+    assert_eq!(recorder.pending(), 0);
+    let sizes: Vec<usize> = recorder
+        .store()
+        .batches
+        .lock()
+        .unwrap()
+        .iter()
+        .map(Vec::len)
+        .collect();
+    assert_eq!(sizes, [2, 2, 1]);
+    let stored = recorder
+        .store()
+        .query_ticks(&StoreQuery::new())
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 5);
+}
@@ -0,0 +1,209 @@
+use ig_client::application::models::order::Direction;
+use ig_client::application::services::order_service::OrderServiceImpl;
+use ig_client::application::services::trailing_stop_manager::{
+    SyntheticTrailingStop, TrailingStopEvent, TrailingStopManager,
+};
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+// Mock HTTP client recording the method, path and body of every request, failing the
+// close requests of `failing_deal`
+struct RecordingHttpClient {
+    requests: Mutex<Vec<(Method, String, Option<Value>)>>,
+    failing_deal: Option<String>,
+}
+
+impl RecordingHttpClient {
+    fn new(failing_deal: Option<&str>) -> Arc<Self> {
+        Arc::new(Self {
+            requests: Mutex::new(Vec::new()),
+            failing_deal: failing_deal.map(str::to_string),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for RecordingHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        _session: &IgSession,
+        body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        let body = body.map(|body| serde_json::to_value(body).unwrap());
+        let failing = path == "positions/otc"
+            && body.as_ref().is_some_and(|body| {
+                self.failing_deal
+                    .as_deref()
+                    .is_some_and(|deal| body["dealId"] == deal)
+            });
+        self.requests
+            .lock()
+            .unwrap()
+            .push((method, path.to_string(), body));
+        if failing {
+            return Err(AppError::Unexpected(reqwest::StatusCode::BAD_GATEWAY));
+        }
+        Ok(serde_json::from_value(json!({"dealReference": "REF"}))?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        unimplemented!()
+    }
+}
+
+fn long_stop() -> SyntheticTrailingStop {
+    SyntheticTrailingStop::new(
+        "DEAL1".to_string(),
+        "IX.D.DAX.DAILY.IP".to_string(),
+        Direction::Buy,
+        2.0,
+        "EUR".to_string(),
+        20.0,
+        5.0,
+        18000.0,
+    )
+}
+
+#[test]
+fn test_synthetic_trailing_stop_follows_price_by_increments() {
+    let mut stop = long_stop();
+    assert_eq!(stop.stop_level, 17980.0);
+
+    // Not a whole increment yet
+    assert_eq!(stop.update(18004.0, 18005.0), TrailingStopEvent::Unchanged);
+    assert_eq!(
+        stop.update(18012.0, 18013.0),
+        TrailingStopEvent::Moved {
+            from: 17980.0,
+            to: 17990.0
+        }
+    );
+    // Never moves back
+    assert_eq!(stop.update(17995.0, 17996.0), TrailingStopEvent::Unchanged);
+    assert_eq!(
+        stop.update(17990.0, 17991.0),
+        TrailingStopEvent::Triggered {
+            stop_level: 17990.0,
+            price: 17990.0
+        }
+    );
+
+    let mut short = SyntheticTrailingStop::new(
+        "DEAL2".to_string(),
+        "IX.D.DAX.DAILY.IP".to_string(),
+        Direction::Sell,
+        1.0,
+        "EUR".to_string(),
+        20.0,
+        5.0,
+        18000.0,
+    );
+    assert_eq!(short.stop_level, 18020.0);
+    assert_eq!(
+        short.update(17989.0, 17990.0),
+        TrailingStopEvent::Moved {
+            from: 18020.0,
+            to: 18010.0
+        }
+    );
+    assert!(matches!(
+        short.update(18009.0, 18010.0),
+        TrailingStopEvent::Triggered { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_trailing_stop_manager_moves_and_closes() {
+    let client = RecordingHttpClient::new(None);
+    let service = OrderServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let session = IgSession::new("CST".to_string(), "TOKEN".to_string(), "ACC".to_string());
+
+    let mut manager = TrailingStopManager::new(&service).with_server_stops(true);
+    manager.track(long_stop().with_limit_level(Some(18100.0)));
+
+    let events = manager
+        .on_price(&session, "CS.D.EURUSD.TODAY.IP", 1.0, 1.1)
+        .await
+        .unwrap();
+    assert!(events.is_empty());
+
+    let events = manager
+        .on_price(&session, "IX.D.DAX.DAILY.IP", 18010.0, 18011.0)
+        .await
+        .unwrap();
+    assert_eq!(
+        events,
+        vec![(
+            "DEAL1".to_string(),
+            TrailingStopEvent::Moved {
+                from: 17980.0,
+                to: 17990.0
+            }
+        )]
+    );
+
+    let events = manager
+        .on_price(&session, "IX.D.DAX.DAILY.IP", 17985.0, 17986.0)
+        .await
+        .unwrap();
+    assert!(matches!(events[0].1, TrailingStopEvent::Triggered { .. }));
+    assert!(manager.get("DEAL1").is_none());
+
+    let requests = client.requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].0, Method::PUT);
+    assert_eq!(requests[0].1, "positions/otc/DEAL1");
+    assert_eq!(requests[0].2.as_ref().unwrap()["stopLevel"], 17990.0);
+    // The take profit is kept
+    assert_eq!(requests[0].2.as_ref().unwrap()["limitLevel"], 18100.0);
+    assert_eq!(requests[1].1, "positions/otc");
+    let close = requests[1].2.as_ref().unwrap();
+    assert_eq!(close["dealId"], "DEAL1");
+    assert_eq!(close["direction"], "SELL");
+    assert_eq!(close["size"], 2.0);
+}
+
+#[tokio::test]
+async fn test_trailing_stop_manager_keeps_stops_it_failed_to_close() {
+    let client = RecordingHttpClient::new(Some("DEAL1"));
+    let service = OrderServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let session = IgSession::new("CST".to_string(), "TOKEN".to_string(), "ACC".to_string());
+
+    let mut manager = TrailingStopManager::new(&service);
+    manager.track(long_stop());
+    let mut other = long_stop();
+    other.deal_id = "DEAL3".to_string();
+    manager.track(other);
+
+    // Both stops trigger; the failed close does not prevent the other one
+    let events = manager
+        .on_price(&session, "IX.D.DAX.DAILY.IP", 17970.0, 17971.0)
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert!(manager.get("DEAL1").is_some());
+    assert!(manager.get("DEAL3").is_none());
+    assert_eq!(client.requests.lock().unwrap().len(), 2);
+
+    // The next quote below the stop retries the close
+    manager
+        .on_price(&session, "IX.D.DAX.DAILY.IP", 17969.0, 17970.0)
+        .await
+        .unwrap();
+    assert_eq!(client.requests.lock().unwrap().len(), 3);
+}
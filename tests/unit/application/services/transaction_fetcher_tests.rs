@@ -0,0 +1,155 @@
+use chrono::{Duration, TimeZone, Utc};
+use futures::StreamExt;
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::application::services::mock_account_service::MockAccountService;
+use ig_client::application::services::transaction_fetcher::{
+    FetchProgress, TransactionFetcher, date_windows,
+};
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::utils::rate_limiter::{RateLimitType, create_rate_limiter};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+fn transaction(date_utc: &str, reference: &str) -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": &date_utc[..10],
+        "dateUtc": date_utc,
+        "openDateUtc": date_utc,
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": "E1.00",
+        "transactionType": "DEAL",
+        "reference": reference,
+        "openLevel": "0",
+        "closeLevel": "0",
+        "size": "+1",
+        "currency": "EUR",
+        "cashTransaction": false
+    }))
+    .unwrap()
+}
+
+fn session() -> IgSession {
+    IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    )
+}
+
+#[test]
+fn test_date_windows() {
+    let from = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2023, 12, 31, 0, 0, 0).unwrap();
+    let windows = date_windows(from, to, Duration::days(90));
+    assert_eq!(windows.len(), 5);
+    assert_eq!(
+        windows[0],
+        (from, from + Duration::days(90) - Duration::seconds(1))
+    );
+    assert_eq!(windows[1].0, windows[0].1 + Duration::seconds(1));
+    assert_eq!(windows[4].1, to);
+    assert!(date_windows(to, from, Duration::days(90)).is_empty());
+}
+
+#[tokio::test]
+async fn test_fetch_reports_progress() {
+    let service = MockAccountService::new().with_transactions(vec![
+        transaction("2024-01-15T10:00:00", "A"),
+        transaction("2024-02-20T10:00:00", "B"),
+        transaction("2024-02-21T10:00:00", "C"),
+    ]);
+    let progress: Arc<Mutex<Vec<FetchProgress>>> = Arc::default();
+    let seen = progress.clone();
+    let fetcher = TransactionFetcher::new(service)
+        .with_window_days(30)
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None))
+        .on_progress(move |progress| seen.lock().unwrap().push(progress.clone()));
+
+    let from = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+    let transactions = fetcher.fetch(&session(), from, to).await.unwrap();
+    assert_eq!(transactions.len(), 3);
+
+    let progress = progress.lock().unwrap();
+    assert_eq!(progress.len(), 2);
+    assert_eq!((progress[0].fetched, progress[0].total_fetched), (1, 1));
+    assert_eq!((progress[1].fetched, progress[1].total_fetched), (2, 3));
+    assert_eq!(progress[1].to, to);
+    assert_eq!(progress[0].fraction(), 0.5);
+    assert_eq!(progress[1].fraction(), 1.0);
+}
+
+#[tokio::test]
+async fn test_fetch_and_stream_return_boundary_transactions_once() {
+    // The first 30 day window ends where the second starts
+    let boundary = || {
+        MockAccountService::new().with_transactions(vec![transaction("2024-01-31T00:00:00", "A")])
+    };
+    let from = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+    let session = session();
+
+    let fetcher = TransactionFetcher::new(boundary())
+        .with_window_days(30)
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None));
+    assert_eq!(fetcher.fetch(&session, from, to).await.unwrap().len(), 1);
+
+    let fetcher = TransactionFetcher::new(boundary())
+        .with_window_days(30)
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None));
+    let streamed: Vec<_> = fetcher.stream(&session, from, to).collect().await;
+    assert_eq!(streamed.len(), 1);
+}
+
+#[tokio::test]
+async fn test_fetch_stops_on_error() {
+    let service = MockAccountService::new();
+    service.fail_next(AppError::RateLimitExceeded);
+    let fetcher = TransactionFetcher::new(service)
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None));
+    let from = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let result = fetcher
+        .fetch(&session(), from, from + Duration::days(365))
+        .await;
+    assert!(matches!(result, Err(AppError::RateLimitExceeded)));
+}
+
+#[tokio::test]
+async fn test_stream_enriches_across_windows() {
+    let service = MockAccountService::new().with_transactions(vec![
+        transaction("2024-01-15T10:00:00", "A"),
+        transaction("2024-02-20T10:00:00", "B"),
+    ]);
+    let fetcher = TransactionFetcher::new(service)
+        .with_window_days(30)
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None));
+    let session = session();
+    let from = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+
+    let transactions: Vec<_> = fetcher.stream(&session, from, to).collect().await;
+    let references: Vec<String> = transactions
+        .into_iter()
+        .map(|transaction| transaction.unwrap().transaction.reference)
+        .collect();
+    assert_eq!(references, ["A", "B"]);
+}
+
+#[tokio::test]
+async fn test_stream_ends_after_error() {
+    let service =
+        MockAccountService::new().with_transactions(vec![transaction("2024-02-20T10:00:00", "B")]);
+    service.fail_next(AppError::RateLimitExceeded);
+    let fetcher = TransactionFetcher::new(service)
+        .with_window_days(30)
+        .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None));
+    let session = session();
+    let from = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+
+    let items: Vec<_> = fetcher.stream(&session, from, to).collect().await;
+    assert_eq!(items.len(), 1);
+    assert!(matches!(items[0], Err(AppError::RateLimitExceeded)));
+}
@@ -0,0 +1,134 @@
+use chrono::{TimeZone, Utc};
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::application::services::mock_account_service::MockAccountService;
+use ig_client::application::services::transaction_sync::TransactionSync;
+use ig_client::session::interface::IgSession;
+use ig_client::storage::transaction_store::{
+    FileTransactionStore, MemoryTransactionStore, TransactionStore,
+};
+use ig_client::utils::rate_limiter::{RateLimitType, create_rate_limiter};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+fn transaction(date_utc: &str, reference: &str) -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": "2025-07-01",
+        "dateUtc": date_utc,
+        "openDateUtc": date_utc,
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": "E1.00",
+        "transactionType": "DEAL",
+        "reference": reference,
+        "openLevel": "0",
+        "closeLevel": "0",
+        "size": "+1",
+        "currency": "EUR",
+        "cashTransaction": false
+    }))
+    .unwrap()
+}
+
+fn session() -> IgSession {
+    IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    )
+}
+
+fn sync(
+    transactions: Vec<AccountTransaction>,
+    path: &std::path::Path,
+) -> TransactionSync<MockAccountService, FileTransactionStore> {
+    TransactionSync::new(
+        MockAccountService::new().with_transactions(transactions),
+        FileTransactionStore::open(path),
+        Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+    )
+    .with_window_days(10)
+    .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None))
+}
+
+#[tokio::test]
+async fn test_transaction_sync_resumes_from_checkpoint() {
+    let path = std::env::temp_dir().join(format!("ig_tx_sync_{}.ndjson", std::process::id()));
+    let checkpoint_path = FileTransactionStore::open(&path)
+        .checkpoint_path()
+        .to_path_buf();
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&checkpoint_path);
+    let session = session();
+    let to = Utc.with_ymd_and_hms(2025, 2, 15, 0, 0, 0).unwrap();
+
+    let first = sync(
+        vec![
+            transaction("2025-01-05T10:00:00", "T1"),
+            transaction("2025-01-20T10:00:00", "T2"),
+            transaction("2025-02-10T10:00:00", "T3"),
+        ],
+        &path,
+    );
+    let report = first.sync_until(&session, to).await.unwrap();
+    assert_eq!(report.windows, 5);
+    assert_eq!(report.stored, 3);
+    assert_eq!(
+        report.checkpoint.last_date_utc,
+        Utc.with_ymd_and_hms(2025, 2, 10, 10, 0, 0).unwrap()
+    );
+    assert_eq!(report.checkpoint.references, ["T3"]);
+
+    // A later run sees a transaction in the same second as the checkpoint and a newer one
+    let second = sync(
+        vec![
+            transaction("2025-01-05T10:00:00", "T1"),
+            transaction("2025-02-10T10:00:00", "T3"),
+            transaction("2025-02-10T10:00:00", "T4"),
+            transaction("2025-02-12T08:00:00", "T5"),
+        ],
+        &path,
+    );
+    let report = second.sync_until(&session, to).await.unwrap();
+    assert_eq!(report.windows, 1);
+    assert_eq!(report.fetched, 3);
+    assert_eq!(report.stored, 2);
+    let references: Vec<String> = second
+        .store()
+        .load()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|transaction| transaction.reference)
+        .collect();
+    assert_eq!(references, ["T1", "T2", "T3", "T4", "T5"]);
+    let checkpoint = second.store().checkpoint().await.unwrap().unwrap();
+    assert_eq!(checkpoint.references, ["T5"]);
+
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&checkpoint_path).unwrap();
+}
+
+#[tokio::test]
+async fn test_transaction_sync_skips_empty_windows() {
+    let windows: Arc<Mutex<Vec<usize>>> = Arc::default();
+    let seen = windows.clone();
+    let sync = TransactionSync::new(
+        MockAccountService::new(),
+        MemoryTransactionStore::new(),
+        Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+    )
+    .with_rate_limiter(create_rate_limiter(RateLimitType::NonTradingAccount, None))
+    .on_progress(move |progress| seen.lock().unwrap().push(progress.window));
+    let to = Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap();
+
+    let report = sync.sync_until(&session(), to).await.unwrap();
+    assert_eq!(report.windows, 2);
+    assert_eq!(*windows.lock().unwrap(), [1, 2]);
+    assert_eq!(report.stored, 0);
+    // Complete windows move the checkpoint; the last one is requested again
+    assert_eq!(
+        report.checkpoint.last_date_utc,
+        Utc.with_ymd_and_hms(2025, 1, 31, 0, 0, 0).unwrap()
+    );
+    assert_eq!(sync.sync_until(&session(), to).await.unwrap().windows, 1);
+}
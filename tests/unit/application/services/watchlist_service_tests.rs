@@ -0,0 +1,178 @@
+use ig_client::application::models::watchlist::CreateWatchlistStatus;
+use ig_client::application::services::WatchlistService;
+use ig_client::application::services::watchlist_service::WatchlistServiceImpl;
+use ig_client::config::Config;
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
+use ig_client::transport::http_client::IgHttpClient;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+// Mock HTTP client that records each call and answers based on method and path
+struct MockHttpClient {
+    calls: Mutex<Vec<(Method, String, Option<Value>)>>,
+}
+
+impl MockHttpClient {
+    fn new() -> Self {
+        Self {
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn calls(&self) -> Vec<(Method, String, Option<Value>)> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl IgHttpClient for MockHttpClient {
+    async fn request<T: serde::Serialize + Sync, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        _session: &IgSession,
+        body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        let body = body.map(|b| serde_json::to_value(b).unwrap());
+        self.calls
+            .lock()
+            .unwrap()
+            .push((method.clone(), path.to_string(), body));
+
+        let response = match (method, path) {
+            (Method::GET, "watchlists") => json!({
+                "watchlists": [{
+                    "id": "12345",
+                    "name": "My Watchlist",
+                    "editable": true,
+                    "deleteable": true,
+                    "defaultSystemWatchlist": false
+                }]
+            }),
+            (Method::POST, "watchlists") => json!({
+                "watchlistId": "67890",
+                "status": "SUCCESS_NOT_ALL_INSTRUMENTS_ADDED"
+            }),
+            (Method::GET, _) => json!({
+                "markets": [{
+                    "epic": "IX.D.FTSE.DAILY.IP",
+                    "instrumentName": "FTSE 100",
+                    "instrumentType": "INDICES",
+                    "expiry": "DFB",
+                    "marketStatus": "TRADEABLE",
+                    "bid": 8000.0,
+                    "offer": 8001.0
+                }]
+            }),
+            _ => json!({ "status": "SUCCESS" }),
+        };
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn request_no_auth<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        _method: Method,
+        _path: &str,
+        _body: Option<&T>,
+        _version: &str,
+    ) -> Result<R, AppError> {
+        panic!("Mock HTTP client should not be called without authentication");
+    }
+}
+
+fn setup() -> (
+    Arc<MockHttpClient>,
+    WatchlistServiceImpl<MockHttpClient>,
+    IgSession,
+) {
+    let client = Arc::new(MockHttpClient::new());
+    let service = WatchlistServiceImpl::new(Arc::new(Config::default()), client.clone());
+    let session = IgSession::new(
+        "CST123".to_string(),
+        "XST123".to_string(),
+        "ACC123".to_string(),
+    );
+    (client, service, session)
+}
+
+#[tokio::test]
+async fn test_get_watchlists() {
+    let (client, service, session) = setup();
+
+    let result = service.get_watchlists(&session).await.unwrap();
+
+    assert_eq!(result.watchlists.len(), 1);
+    assert_eq!(result.watchlists[0].id, "12345");
+    assert!(result.watchlists[0].editable);
+    assert!(!result.watchlists[0].default_system_watchlist);
+    assert_eq!(client.calls()[0].0, Method::GET);
+}
+
+#[tokio::test]
+async fn test_create_watchlist() {
+    let (client, service, session) = setup();
+    let epics = vec!["IX.D.FTSE.DAILY.IP".to_string()];
+
+    let result = service
+        .create_watchlist(&session, "Indices", &epics)
+        .await
+        .unwrap();
+
+    assert_eq!(result.watchlist_id, "67890");
+    assert_eq!(
+        result.status,
+        CreateWatchlistStatus::SuccessNotAllInstrumentsAdded
+    );
+    let (method, path, body) = &client.calls()[0];
+    assert_eq!(*method, Method::POST);
+    assert_eq!(path, "watchlists");
+    assert_eq!(
+        body.clone().unwrap(),
+        json!({ "name": "Indices", "epics": ["IX.D.FTSE.DAILY.IP"] })
+    );
+}
+
+#[tokio::test]
+async fn test_get_watchlist_markets() {
+    let (client, service, session) = setup();
+
+    let result = service.get_watchlist(&session, "12345").await.unwrap();
+
+    assert_eq!(result.epics(), vec!["IX.D.FTSE.DAILY.IP".to_string()]);
+    assert_eq!(client.calls()[0].1, "watchlists/12345");
+}
+
+#[tokio::test]
+async fn test_add_and_remove_epic() {
+    let (client, service, session) = setup();
+
+    let added = service
+        .add_to_watchlist(&session, "12345", "CS.D.EURUSD.TODAY.IP")
+        .await
+        .unwrap();
+    let removed = service
+        .remove_from_watchlist(&session, "12345", "CS.D.EURUSD.TODAY.IP")
+        .await
+        .unwrap();
+    let deleted = service.delete_watchlist(&session, "12345").await.unwrap();
+
+    assert!(added.is_success());
+    assert!(removed.is_success());
+    assert!(deleted.is_success());
+
+    let calls = client.calls();
+    assert_eq!(calls[0].0, Method::PUT);
+    assert_eq!(calls[0].1, "watchlists/12345");
+    assert_eq!(
+        calls[0].2.clone().unwrap(),
+        json!({ "epic": "CS.D.EURUSD.TODAY.IP" })
+    );
+    assert_eq!(calls[1].0, Method::DELETE);
+    assert_eq!(calls[1].1, "watchlists/12345/CS.D.EURUSD.TODAY.IP");
+    assert_eq!(calls[2].0, Method::DELETE);
+    assert_eq!(calls[2].1, "watchlists/12345");
+}
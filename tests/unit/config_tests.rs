@@ -207,6 +207,8 @@ fn test_pg_pool_invalid_url() {
         database: DatabaseConfig {
             url: "postgres://invalid_url_for_test".to_string(),
             max_connections: 5,
+            min_connections: 0,
+            acquire_timeout: 30,
         },
         sleep_hours: 1,
         page_size: 100,
@@ -221,3 +223,13 @@ fn test_pg_pool_invalid_url() {
     let _result = config.pg_pool();
     // The test passes if we can call the method without panicking
 }
+
+#[test]
+fn test_database_config_pool_defaults() {
+    let config: DatabaseConfig =
+        serde_json::from_str(r#"{"url": "postgres://localhost/ig", "max_connections": 10}"#)
+            .unwrap();
+    assert_eq!(config.max_connections, 10);
+    assert_eq!(config.min_connections, 0);
+    assert_eq!(config.acquire_timeout, 30);
+}
@@ -151,6 +151,16 @@ fn test_app_error_invalid_input() {
     assert_display_contains(&app_error, "invalid parameter");
 }
 
+#[test]
+fn test_app_error_incompatible_schema() {
+    let app_error = AppError::IncompatibleSchema {
+        found: 3,
+        supported: 2,
+    };
+    assert_display_contains(&app_error, "incompatible schema");
+    assert_display_contains(&app_error, "version 3");
+}
+
 #[test]
 fn test_app_error_deserialization() {
     let app_error = AppError::Deserialization("failed to deserialize".to_string());
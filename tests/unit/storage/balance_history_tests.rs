@@ -0,0 +1,85 @@
+use chrono::{Duration, TimeZone, Utc};
+use ig_client::application::models::account::AccountBalance;
+use ig_client::storage::balance_history::{
+    BalanceHistory, BalanceSnapshot, BalanceStore, FileBalanceStore, MemoryBalanceStore,
+};
+
+fn snapshot(hours: i64, balance: f64, profit_loss: f64) -> BalanceSnapshot {
+    let mut snapshot = BalanceSnapshot::new(
+        "ACC123",
+        "EUR",
+        AccountBalance::new("EUR", balance, 500.0, profit_loss, balance),
+    );
+    snapshot.timestamp =
+        Utc.with_ymd_and_hms(2025, 5, 1, 12, 0, 0).unwrap() + Duration::hours(hours);
+    snapshot
+}
+
+#[test]
+fn test_balance_history_analysis() {
+    let history = BalanceHistory::new(vec![
+        snapshot(6, 1000.0, 50.0),
+        snapshot(0, 1000.0, 0.0),
+        snapshot(24, 1000.0, -150.0),
+        snapshot(30, 1100.0, 0.0),
+        snapshot(48, 1300.0, 0.0),
+    ]);
+
+    let curve = history.equity_curve();
+    assert_eq!(
+        curve.iter().map(|(_, equity)| *equity).collect::<Vec<_>>(),
+        vec![1000.0, 1050.0, 850.0, 1100.0, 1300.0]
+    );
+
+    let drawdown = history.max_drawdown().unwrap();
+    assert_eq!(drawdown.peak_equity, 1050.0);
+    assert_eq!(drawdown.trough_equity, 850.0);
+    assert_eq!(drawdown.amount(), 200.0);
+    assert!((drawdown.percent().unwrap() - 19.047_619).abs() < 1e-6);
+
+    let days = history.daily_pnl();
+    assert_eq!(days.len(), 3);
+    assert_eq!(days[0].pnl(), 50.0);
+    assert_eq!(days[1].open_equity, 1050.0);
+    assert_eq!(days[1].pnl(), 50.0);
+    assert_eq!(days[2].pnl(), 200.0);
+
+    assert!(BalanceHistory::default().max_drawdown().is_none());
+    assert!(
+        BalanceHistory::new(vec![snapshot(0, 1000.0, 0.0), snapshot(1, 1200.0, 0.0)])
+            .max_drawdown()
+            .is_none()
+    );
+}
+
+#[test]
+fn test_balance_stores_query_range() {
+    let path = std::env::temp_dir().join(format!("ig_balances_{}.ndjson", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let file = FileBalanceStore::open(&path);
+    let memory = MemoryBalanceStore::new();
+    let mut other = snapshot(1, 10.0, 0.0);
+    other.account_id = "ACC456".to_string();
+
+    for store in [&file as &dyn BalanceStore, &memory] {
+        store.append(&snapshot(24, 1200.0, 0.0)).unwrap();
+        store.append(&snapshot(0, 1000.0, 0.0)).unwrap();
+        store.append(&other).unwrap();
+
+        let from = Utc.with_ymd_and_hms(2025, 5, 1, 0, 0, 0).unwrap();
+        let all = store
+            .query("ACC123", from, from + Duration::days(7))
+            .unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].balance.balance.amount, 1000.0);
+        assert_eq!(all[1], snapshot(24, 1200.0, 0.0));
+        assert_eq!(
+            store
+                .query("ACC123", from, from + Duration::hours(36))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,162 @@
+use chrono::{DateTime, Duration as ChronoDuration, TimeZone, Utc};
+use ig_client::error::AppError;
+use ig_client::storage::batch_writer::{BatchSink, BatchWriter, BatchWriterConfig, OverflowPolicy};
+use ig_client::storage::memory_store::MemoryStore;
+use ig_client::storage::store::{Tick, WriteReport};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap()
+}
+
+fn tick(second: i64) -> Tick {
+    Tick {
+        epic: "IX.D.DAX.DAILY.IP".to_string(),
+        timestamp: start() + ChronoDuration::seconds(second),
+        bid: Some(100.0),
+        offer: Some(101.0),
+    }
+}
+
+// Sink keeping the batches written, failing the first `failures` attempts
+#[derive(Default)]
+struct SinkState {
+    batches: Mutex<Vec<Vec<u32>>>,
+    failures: AtomicU32,
+    delay: Duration,
+}
+
+#[derive(Clone, Default)]
+struct Batches(Arc<SinkState>);
+
+#[async_trait::async_trait]
+impl BatchSink<u32> for Batches {
+    async fn write_batch(&self, batch: &[u32]) -> Result<WriteReport, AppError> {
+        tokio::time::sleep(self.0.delay).await;
+        if self
+            .0
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                left.checked_sub(1)
+            })
+            .is_ok()
+        {
+            return Err(AppError::Timeout("sink unavailable".to_string()));
+        }
+        self.0.batches.lock().unwrap().push(batch.to_vec());
+        Ok(WriteReport {
+            inserted: batch.len(),
+            ..WriteReport::default()
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_batch_writer_flushes_on_size_and_close() {
+    let store = MemoryStore::new();
+    let writer = BatchWriter::spawn(
+        store.clone(),
+        BatchWriterConfig::new()
+            .with_batch_size(2)
+            .with_flush_interval(Duration::from_secs(3600)),
+    );
+    for second in 0..3 {
+        assert!(writer.write(tick(second)).await);
+    }
+    // Replayed ticks are written again but counted as unchanged
+    assert!(writer.write(tick(0)).await);
+    writer.flush().await;
+    assert_eq!(store.tick_count(), 3);
+    assert!(writer.write(tick(3)).await);
+
+    let stats = writer.close().await;
+    assert_eq!(store.tick_count(), 4);
+    assert_eq!(stats.accepted, 5);
+    assert_eq!(stats.batches, 3);
+    assert_eq!(stats.written.inserted, 4);
+    assert_eq!(stats.written.unchanged, 1);
+}
+
+#[tokio::test]
+async fn test_batch_writer_flushes_on_interval() {
+    let store = MemoryStore::new();
+    let writer = BatchWriter::spawn(
+        store.clone(),
+        BatchWriterConfig::new().with_flush_interval(Duration::from_millis(20)),
+    );
+    writer.write(tick(0)).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(store.tick_count(), 1);
+    assert_eq!(writer.stats().batches, 1);
+}
+
+#[tokio::test]
+async fn test_batch_writer_retries_with_backoff() {
+    let sink = Batches(Arc::new(SinkState {
+        failures: AtomicU32::new(2),
+        ..SinkState::default()
+    }));
+    let writer = BatchWriter::spawn(
+        sink.clone(),
+        BatchWriterConfig::new().with_retries(
+            2,
+            Duration::from_millis(1),
+            Duration::from_millis(5),
+        ),
+    );
+    writer.write(1).await;
+    writer.flush().await;
+    assert_eq!(*sink.0.batches.lock().unwrap(), [vec![1]]);
+
+    // A batch failing past its retries is discarded
+    sink.0.failures.store(3, Ordering::SeqCst);
+    writer.write(2).await;
+    let stats = writer.close().await;
+    assert_eq!(stats.failed_attempts, 5);
+    assert_eq!(stats.discarded, 1);
+    assert_eq!(stats.batches, 1);
+}
+
+#[tokio::test]
+async fn test_batch_writer_drops_when_full() {
+    let sink = Batches(Arc::new(SinkState {
+        delay: Duration::from_millis(50),
+        ..SinkState::default()
+    }));
+    let writer = BatchWriter::spawn(
+        sink.clone(),
+        BatchWriterConfig::new()
+            .with_batch_size(1)
+            .with_capacity(1)
+            .with_overflow(OverflowPolicy::Drop),
+    );
+    let mut queued = 0;
+    for record in 0..10 {
+        if writer.write(record).await {
+            queued += 1;
+        }
+    }
+    let stats = writer.close().await;
+    assert!(queued < 10);
+    assert_eq!(stats.accepted, queued);
+    assert_eq!(stats.dropped, 10 - queued);
+    let written: usize = sink.0.batches.lock().unwrap().iter().map(Vec::len).sum();
+    assert_eq!(written as u64, queued);
+}
+
+#[tokio::test]
+async fn test_batch_writer_rejects_zero_flush_interval() {
+    let config = BatchWriterConfig::new().with_flush_interval(Duration::ZERO);
+    assert!(config.flush_interval > Duration::ZERO);
+
+    // Set directly, a zero interval still runs
+    let mut config = BatchWriterConfig::new();
+    config.flush_interval = Duration::ZERO;
+    let sink = Batches::default();
+    let writer = BatchWriter::spawn(sink.clone(), config);
+    writer.write(1).await;
+    let stats = writer.close().await;
+    assert_eq!(stats.written.inserted, 1);
+}
@@ -0,0 +1,88 @@
+use chrono::{Duration, TimeZone, Utc};
+use ig_client::application::models::market::{MarketData, MarketNode};
+use ig_client::presentation::InstrumentType;
+use ig_client::storage::instrument_catalog::{CatalogQuery, catalog_entries};
+use serde_json::json;
+
+fn market(epic: &str, name: &str, instrument_type: &str) -> MarketData {
+    serde_json::from_value(json!({
+        "epic": epic,
+        "instrumentName": name,
+        "instrumentType": instrument_type,
+        "expiry": "-",
+        "marketStatus": "TRADEABLE"
+    }))
+    .unwrap()
+}
+
+fn node(name: &str, children: Vec<MarketNode>, markets: Vec<MarketData>) -> MarketNode {
+    MarketNode {
+        id: name.to_lowercase(),
+        name: name.to_string(),
+        children,
+        markets,
+    }
+}
+
+#[test]
+fn test_catalog_entries_and_search() {
+    let dax = market("IX.D.DAX.DAILY.IP", "Germany 40", "INDICES");
+    let tree = vec![
+        node(
+            "Indices",
+            vec![node(
+                "Europe",
+                vec![],
+                vec![
+                    dax.clone(),
+                    market("IX.D.CAC.DAILY.IP", "France 40", "INDICES"),
+                ],
+            )],
+            vec![dax],
+        ),
+        node(
+            "Forex",
+            vec![],
+            vec![market("CS.D.EURUSD.TODAY.IP", "EUR/USD", "CURRENCIES")],
+        ),
+    ];
+    let listed_at = Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap();
+    let entries = catalog_entries(&tree, listed_at);
+    let epics: Vec<&str> = entries.iter().map(|entry| entry.epic.as_str()).collect();
+    assert_eq!(
+        epics,
+        [
+            "IX.D.DAX.DAILY.IP",
+            "IX.D.CAC.DAILY.IP",
+            "CS.D.EURUSD.TODAY.IP"
+        ]
+    );
+    // A market listed twice keeps its first path
+    assert_eq!(entries[0].path, ["Indices"]);
+    assert_eq!(entries[1].path, ["Indices", "Europe"]);
+    assert!(entries[0].details_stale(Duration::days(7), listed_at));
+
+    let europe = CatalogQuery::new()
+        .with_text("europe 40")
+        .select(entries.clone());
+    assert_eq!(europe.len(), 1);
+    assert_eq!(europe[0].name, "France 40");
+    let indices = CatalogQuery::new()
+        .of_type(InstrumentType::Indices)
+        .select(entries.clone());
+    let names: Vec<&str> = indices.iter().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(names, ["France 40", "Germany 40"]);
+    assert_eq!(
+        CatalogQuery::new()
+            .with_limit(1)
+            .select(entries.clone())
+            .len(),
+        1
+    );
+    assert!(
+        CatalogQuery::new()
+            .in_currency("EUR")
+            .select(entries)
+            .is_empty()
+    );
+}
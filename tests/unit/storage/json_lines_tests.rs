@@ -0,0 +1,36 @@
+use ig_client::storage::json_lines::{JsonLinesFile, MemoryRecords};
+
+#[test]
+fn test_json_lines_file_appends_and_filters() {
+    let path = std::env::temp_dir().join(format!("ig_json_lines_{}.ndjson", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let file: JsonLinesFile<(String, u32)> = JsonLinesFile::open(&path);
+    assert!(file.read().unwrap().is_empty());
+
+    file.append(&("a".to_string(), 1)).unwrap();
+    file.append_all(&[("b".to_string(), 2), ("c".to_string(), 3)])
+        .unwrap();
+    // Blank lines left by an editor are skipped
+    std::fs::write(
+        &path,
+        std::fs::read_to_string(&path)
+            .unwrap()
+            .replace('\n', "\n\n"),
+    )
+    .unwrap();
+
+    let reopened: JsonLinesFile<(String, u32)> = JsonLinesFile::open(&path);
+    assert_eq!(reopened.read().unwrap().len(), 3);
+    let odd = reopened.filter(|(_, n)| n % 2 == 1).unwrap();
+    assert_eq!(odd, [("a".to_string(), 1), ("c".to_string(), 3)]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_memory_records_keep_append_order() {
+    let records = MemoryRecords::new();
+    for n in [3, 1, 2] {
+        records.push(&n);
+    }
+    assert_eq!(records.filter(|n| *n > 1), [3, 2]);
+}
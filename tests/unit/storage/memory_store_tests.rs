@@ -0,0 +1,270 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::application::models::market::Resolution;
+use ig_client::storage::Store;
+use ig_client::storage::memory_store::MemoryStore;
+use ig_client::storage::order_journal::{JournalEntry, JournalEntryKind};
+use ig_client::storage::store::{Candle, StoreQuery, Tick, WriteReport};
+use serde_json::json;
+
+const DAX: &str = "IX.D.DAX.DAILY.IP";
+const FTSE: &str = "IX.D.FTSE.DAILY.IP";
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap()
+}
+
+fn candle(epic: &str, minute: i64, close: f64) -> Candle {
+    Candle {
+        epic: epic.to_string(),
+        resolution: Resolution::Minute,
+        timestamp: start() + Duration::minutes(minute),
+        open: 100.0,
+        high: 110.0,
+        low: 90.0,
+        close,
+        volume: None,
+    }
+}
+
+fn transaction(date_utc: &str, reference: &str) -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": "2025-07-01",
+        "dateUtc": date_utc,
+        "openDateUtc": date_utc,
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": "E1.00",
+        "transactionType": "DEAL",
+        "reference": reference,
+        "openLevel": "0",
+        "closeLevel": "0",
+        "size": "+1",
+        "currency": "EUR",
+        "cashTransaction": false
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_memory_store_candles_and_ticks() {
+    let store = MemoryStore::new();
+    store
+        .put_candles(&[
+            candle(FTSE, 0, 1.0),
+            candle(DAX, 1, 2.0),
+            candle(DAX, 0, 3.0),
+            candle(DAX, 2, 4.0),
+        ])
+        .await
+        .unwrap();
+    // Writing a stored key replaces it
+    store.put_candles(&[candle(DAX, 2, 5.0)]).await.unwrap();
+    assert_eq!(store.candle_count(), 4);
+
+    let all = store
+        .query_candles(Resolution::Minute, &StoreQuery::new())
+        .await
+        .unwrap();
+    let order: Vec<(&str, f64)> = all
+        .iter()
+        .map(|candle| (candle.epic.as_str(), candle.close))
+        .collect();
+    assert_eq!(order, [(DAX, 3.0), (FTSE, 1.0), (DAX, 2.0), (DAX, 5.0)]);
+    let limited = store
+        .query_candles(
+            Resolution::Minute,
+            &StoreQuery::new()
+                .for_epic(DAX)
+                .between(start() + Duration::minutes(1), start() + Duration::hours(1))
+                .with_limit(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(limited, [candle(DAX, 1, 2.0)]);
+    assert!(
+        store
+            .query_candles(Resolution::Hour, &StoreQuery::new())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    let latest = store
+        .latest_candles(DAX, Resolution::Minute, 2)
+        .await
+        .unwrap();
+    assert_eq!(latest, [candle(DAX, 1, 2.0), candle(DAX, 2, 5.0)]);
+    assert_eq!(
+        store
+            .get_candle(DAX, Resolution::Minute, start())
+            .await
+            .unwrap(),
+        Some(candle(DAX, 0, 3.0))
+    );
+
+    let deleted = store
+        .delete_candles(Resolution::Minute, &StoreQuery::new().for_epic(DAX))
+        .await
+        .unwrap();
+    assert_eq!(deleted, 3);
+    assert_eq!(store.candle_count(), 1);
+
+    let tick = Tick {
+        epic: DAX.to_string(),
+        timestamp: start(),
+        bid: Some(18000.0),
+        offer: None,
+    };
+    // Clones share the records
+    let shared = store.clone();
+    shared.put_ticks(std::slice::from_ref(&tick)).await.unwrap();
+    assert_eq!(store.get_tick(DAX, start()).await.unwrap(), Some(tick));
+    assert_eq!(
+        store
+            .delete_ticks(&StoreQuery::new().before(start()))
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(store.delete_ticks(&StoreQuery::new()).await.unwrap(), 1);
+    assert_eq!(store.tick_count(), 0);
+}
+
+#[tokio::test]
+async fn test_memory_store_transactions_and_orders() {
+    let store = MemoryStore::new();
+    store
+        .put_transactions(&[
+            transaction("2025-07-02T10:00:00", "D1"),
+            transaction("2025-07-01T10:00:00", "D1"),
+            transaction("2025-07-01T12:00:00", "D2"),
+        ])
+        .await
+        .unwrap();
+    // A bad date writes nothing of the batch
+    assert!(
+        store
+            .put_transactions(&[
+                transaction("2025-07-03T10:00:00", "D3"),
+                transaction("yesterday", "D4"),
+            ])
+            .await
+            .is_err()
+    );
+    let deal = store.get_transactions("D1").await.unwrap();
+    assert_eq!(deal[0].date_utc, "2025-07-01T10:00:00");
+    let july_first = store
+        .query_transactions(
+            &StoreQuery::new()
+                .for_epic("Germany 40")
+                .between(start() - Duration::hours(9), start() + Duration::hours(15)),
+        )
+        .await
+        .unwrap();
+    let references: Vec<&str> = july_first.iter().map(|tx| tx.reference.as_str()).collect();
+    assert_eq!(references, ["D1", "D2"]);
+    assert!(store.get_transactions("D3").await.unwrap().is_empty());
+
+    let mut request = JournalEntry::new(
+        "C1",
+        JournalEntryKind::Request,
+        "create_order",
+        json!({ "direction": "BUY", "size": 1.0 }),
+    )
+    .with_epic(Some(DAX));
+    request.timestamp = start();
+    let mut confirmation = JournalEntry::new(
+        "C1",
+        JournalEntryKind::Confirmation,
+        "create_order",
+        json!({ "dealReference": "REF1", "dealStatus": "ACCEPTED", "level": 18000.0 }),
+    )
+    .with_deal_reference(Some("REF1"));
+    confirmation.timestamp = start() + Duration::seconds(1);
+    store.record_orders(&[request.clone()]).await.unwrap();
+    store.record_orders(&[confirmation]).await.unwrap();
+
+    assert_eq!(store.get_orders("C1").await.unwrap().len(), 2);
+    // The confirmation has no EPIC, so a query on the market only returns the request
+    assert_eq!(
+        store
+            .query_orders(&StoreQuery::new().for_epic(DAX))
+            .await
+            .unwrap(),
+        [request]
+    );
+    let deals = store
+        .query_deals(
+            &StoreQuery::new()
+                .for_epic(DAX)
+                .between(start(), start() + Duration::days(1)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(deals.len(), 1);
+    assert!(deals[0].is_filled());
+    assert_eq!(deals[0].direction.as_deref(), Some("BUY"));
+    assert_eq!(
+        store.get_deal("REF1").await.unwrap(),
+        Some(deals[0].clone())
+    );
+}
+
+#[tokio::test]
+async fn test_memory_store_replayed_writes_report_conflicts() {
+    let store = MemoryStore::new();
+    let batch = [candle(DAX, 0, 100.0), candle(DAX, 1, 101.0)];
+    assert_eq!(
+        store.put_candles(&batch).await.unwrap(),
+        WriteReport {
+            inserted: 2,
+            updated: 0,
+            unchanged: 0,
+        }
+    );
+    // Replaying the batch writes nothing new
+    let replayed = store.put_candles(&batch).await.unwrap();
+    assert_eq!(replayed.unchanged, 2);
+    assert_eq!(replayed.conflicts(), 2);
+
+    let report = store
+        .put_candles(&[candle(DAX, 1, 105.0), candle(DAX, 2, 102.0)])
+        .await
+        .unwrap();
+    assert_eq!(
+        report,
+        WriteReport {
+            inserted: 1,
+            updated: 1,
+            unchanged: 0,
+        }
+    );
+    assert_eq!(
+        store
+            .query_candles(Resolution::Minute, &StoreQuery::new())
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+
+    let entry = transaction("2025-07-01T10:00:00", "D1");
+    store
+        .put_transactions(std::slice::from_ref(&entry))
+        .await
+        .unwrap();
+    let replayed = store
+        .put_transactions(std::slice::from_ref(&entry))
+        .await
+        .unwrap();
+    assert_eq!((replayed.inserted, replayed.unchanged), (0, 1));
+    assert_eq!(store.get_transactions("D1").await.unwrap().len(), 1);
+
+    // A charge booked in the same second under the reference of its deal is kept
+    let mut charge = entry;
+    charge.transaction_type = "CHARGE".to_string();
+    charge.profit_and_loss = "E-0.50".to_string();
+    let report = store.put_transactions(&[charge]).await.unwrap();
+    assert_eq!(report.inserted, 1);
+    assert_eq!(store.get_transactions("D1").await.unwrap().len(), 2);
+}
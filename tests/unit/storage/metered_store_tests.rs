@@ -0,0 +1,92 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::application::models::market::Resolution;
+use ig_client::storage::Store;
+use ig_client::storage::memory_store::MemoryStore;
+use ig_client::storage::metered_store::MeteredStore;
+use ig_client::storage::store::{StoreQuery, Tick};
+use serde_json::json;
+
+const DAX: &str = "IX.D.DAX.DAILY.IP";
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap()
+}
+
+fn tick(second: i64, bid: f64) -> Tick {
+    Tick {
+        epic: DAX.to_string(),
+        timestamp: start() + Duration::seconds(second),
+        bid: Some(bid),
+        offer: Some(bid + 1.0),
+    }
+}
+
+fn transaction(date_utc: &str) -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": "2025-07-01",
+        "dateUtc": date_utc,
+        "openDateUtc": date_utc,
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": "E1.00",
+        "transactionType": "DEAL",
+        "reference": "D1",
+        "openLevel": "0",
+        "closeLevel": "0",
+        "size": "+1",
+        "currency": "EUR",
+        "cashTransaction": false
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_metered_store_counts_writes() {
+    let store = MeteredStore::new(MemoryStore::new());
+    store
+        .put_ticks(&[tick(0, 100.0), tick(1, 101.0), tick(2, 102.0)])
+        .await
+        .unwrap();
+    store
+        .put_ticks(&[tick(2, 102.0), tick(3, 103.0)])
+        .await
+        .unwrap();
+    assert!(
+        store
+            .put_transactions(&[transaction("yesterday")])
+            .await
+            .is_err()
+    );
+    let deleted = store
+        .delete_ticks(&StoreQuery::new().before(start() + Duration::seconds(1)))
+        .await
+        .unwrap();
+    assert_eq!(deleted, 1);
+
+    let snapshot = store.snapshot();
+    let ticks = snapshot.table("ticks");
+    assert_eq!(ticks.batches, 2);
+    assert_eq!(ticks.records.inserted, 4);
+    assert_eq!(ticks.records.unchanged, 1);
+    assert_eq!(ticks.max_batch_size, 3);
+    assert_eq!(ticks.average_batch_size(), Some(2.5));
+    assert!(ticks.average_latency().is_some());
+    assert_eq!(snapshot.table("candles").batches, 0);
+    assert_eq!(snapshot.table("transactions").batches, 0);
+    assert_eq!(snapshot.deleted["ticks"], 1);
+    assert_eq!(snapshot.errors, 1);
+
+    // Reads go to the wrapped store
+    assert_eq!(store.inner().tick_count(), 3);
+    assert!(
+        store
+            .query_candles(Resolution::Minute, &StoreQuery::new())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    store.reset();
+    assert_eq!(store.snapshot(), Default::default());
+}
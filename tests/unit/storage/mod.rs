@@ -1 +1,17 @@
+mod balance_history_tests;
+mod batch_writer_tests;
+mod instrument_catalog_tests;
+mod json_lines_tests;
+mod memory_store_tests;
+mod metered_store_tests;
+mod order_journal_tests;
+#[cfg(feature = "parquet")]
+mod parquet_dataset_tests;
+mod price_series_tests;
+mod retention_tests;
+mod snapshot_tests;
+#[cfg(feature = "sqlite")]
+mod sqlite_store_tests;
 mod storage_utils_tests;
+mod store_tests;
+mod transaction_store_tests;
@@ -0,0 +1,133 @@
+use chrono::{Duration, Utc};
+use ig_client::storage::order_journal::{
+    JournalEntry, JournalEntryKind, JournalQuery, MemoryOrderJournal, OrderJournal, deal_records,
+};
+use serde_json::json;
+
+fn entry(correlation_id: &str, kind: JournalEntryKind, epic: &str) -> JournalEntry {
+    JournalEntry::new(correlation_id, kind, "create_order", json!({})).with_epic(Some(epic))
+}
+
+#[test]
+fn test_journal_query_filters() {
+    let journal = MemoryOrderJournal::new();
+    journal
+        .append(&entry("A", JournalEntryKind::Request, "IX.D.DAX.DAILY.IP"))
+        .unwrap();
+    journal
+        .append(
+            &entry("A", JournalEntryKind::Response, "IX.D.DAX.DAILY.IP")
+                .with_deal_reference(Some("REF1")),
+        )
+        .unwrap();
+    journal
+        .append(&entry(
+            "B",
+            JournalEntryKind::Request,
+            "CS.D.EURUSD.TODAY.IP",
+        ))
+        .unwrap();
+
+    assert_eq!(journal.query(&JournalQuery::all()).unwrap().len(), 3);
+    assert_eq!(
+        journal
+            .query(&JournalQuery::all().with_correlation_id("A"))
+            .unwrap()
+            .len(),
+        2
+    );
+    let responses = journal
+        .query(
+            &JournalQuery::all()
+                .with_epic("IX.D.DAX.DAILY.IP")
+                .with_kind(JournalEntryKind::Response),
+        )
+        .unwrap();
+    assert_eq!(responses.len(), 1);
+    assert_eq!(responses[0].deal_reference.as_deref(), Some("REF1"));
+    assert!(
+        journal
+            .query(&JournalQuery::all().with_deal_reference("REF2"))
+            .unwrap()
+            .is_empty()
+    );
+
+    let now = Utc::now();
+    let recent =
+        JournalQuery::all().between(now - Duration::minutes(1), now + Duration::minutes(1));
+    assert_eq!(journal.query(&recent).unwrap().len(), 3);
+    let later = JournalQuery::all().between(now + Duration::minutes(1), now + Duration::minutes(2));
+    assert!(journal.query(&later).unwrap().is_empty());
+}
+
+#[test]
+fn test_deal_records_link_request_and_confirmation() {
+    let start = Utc::now();
+    let mut request = JournalEntry::new(
+        "A",
+        JournalEntryKind::Request,
+        "create_order",
+        json!({ "epic": "IX.D.DAX.DAILY.IP", "direction": "BUY", "size": 2.0 }),
+    )
+    .with_epic(Some("IX.D.DAX.DAILY.IP"));
+    request.timestamp = start;
+    let mut response = JournalEntry::new(
+        "A",
+        JournalEntryKind::Response,
+        "create_order",
+        json!({ "dealReference": "REF1" }),
+    )
+    .with_deal_reference(Some("REF1"));
+    response.timestamp = start + Duration::milliseconds(100);
+    let mut confirmation = JournalEntry::new(
+        "A",
+        JournalEntryKind::Confirmation,
+        "create_order",
+        json!({
+            "dealReference": "REF1",
+            "dealStatus": "ACCEPTED",
+            "dealId": "DIAAA",
+            "level": 18500.5,
+            "size": 1.5,
+        }),
+    )
+    .with_deal_reference(Some("REF1"))
+    .with_deal_id(Some("DIAAA"));
+    confirmation.timestamp = start + Duration::milliseconds(300);
+    let mut rejected = JournalEntry::new(
+        "B",
+        JournalEntryKind::Confirmation,
+        "close_position",
+        json!({ "dealReference": "REF2", "dealStatus": "REJECTED", "reason": "MARKET_CLOSED" }),
+    )
+    .with_deal_reference(Some("REF2"));
+    rejected.timestamp = start + Duration::seconds(1);
+
+    // Order of the entries does not matter
+    let deals = deal_records(&[confirmation, rejected, response, request]);
+    assert_eq!(deals.len(), 2);
+
+    let filled = &deals[0];
+    assert_eq!(filled.deal_reference, "REF1");
+    assert_eq!(filled.correlation_id, "A");
+    assert_eq!(filled.timestamp, start + Duration::milliseconds(100));
+    assert_eq!(filled.requested_at, Some(start));
+    assert_eq!(
+        filled.confirmed_at,
+        Some(start + Duration::milliseconds(300))
+    );
+    assert_eq!(filled.epic.as_deref(), Some("IX.D.DAX.DAILY.IP"));
+    assert_eq!(filled.direction.as_deref(), Some("BUY"));
+    assert_eq!(filled.size, Some(1.5));
+    assert_eq!(filled.level, Some(18500.5));
+    assert_eq!(filled.deal_id.as_deref(), Some("DIAAA"));
+    assert!(filled.is_filled());
+
+    // A confirmation without its request still gives a deal
+    let rejected = &deals[1];
+    assert_eq!(rejected.operation, "close_position");
+    assert_eq!(rejected.timestamp, start + Duration::seconds(1));
+    assert_eq!(rejected.requested_at, None);
+    assert_eq!(rejected.reason.as_deref(), Some("MARKET_CLOSED"));
+    assert!(!rejected.is_filled());
+}
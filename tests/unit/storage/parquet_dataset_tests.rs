@@ -0,0 +1,171 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::application::models::market::Resolution;
+use ig_client::storage::parquet_dataset::{PARTITION_FILE_NAME, ParquetDatasetWriter};
+use ig_client::storage::store::{Candle, Tick};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::{Field, Row};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+const DAX: &str = "IX.D.DAX.DAILY.IP";
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 7, 1, 23, 58, 0).unwrap()
+}
+
+fn dataset_root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("ig_dataset_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    root
+}
+
+fn read_rows(path: &Path) -> Vec<Row> {
+    let reader = SerializedFileReader::new(std::fs::File::open(path).unwrap()).unwrap();
+    reader
+        .get_row_iter(None)
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect()
+}
+
+fn candle(epic: &str, minute: i64, close: f64) -> Candle {
+    Candle {
+        epic: epic.to_string(),
+        resolution: Resolution::Minute,
+        timestamp: start() + Duration::minutes(minute),
+        open: 100.0,
+        high: 110.0,
+        low: 90.0,
+        close,
+        volume: (minute == 0).then_some(5),
+    }
+}
+
+#[test]
+fn test_write_candles_partitions_by_epic_and_date() {
+    let root = dataset_root("candles");
+    let writer = ParquetDatasetWriter::new(&root);
+    let partitions = writer
+        .write_candles(&[
+            candle(DAX, 1, 101.0),
+            candle(DAX, 0, 100.0),
+            candle(DAX, 2, 102.0),
+            candle("CS.D.EURUSD.TODAY.IP", 0, 1.1),
+        ])
+        .unwrap();
+
+    let day = NaiveDate::from_ymd_opt(2025, 7, 1).unwrap();
+    let dax_day = writer
+        .candle_partition(DAX, Resolution::Minute, day)
+        .unwrap()
+        .join(PARTITION_FILE_NAME);
+    assert!(dax_day.ends_with(
+        "candles/resolution=MINUTE/epic=IX.D.DAX.DAILY.IP/date=2025-07-01/part-0.parquet"
+    ));
+    let rows: Vec<(PathBuf, usize)> = partitions
+        .iter()
+        .map(|partition| (partition.path.clone(), partition.rows))
+        .collect();
+    assert_eq!(rows.len(), 3);
+    assert!(rows.contains(&(dax_day.clone(), 2)));
+    assert!(
+        rows.contains(&(
+            writer
+                .candle_partition(DAX, Resolution::Minute, day.succ_opt().unwrap())
+                .unwrap()
+                .join(PARTITION_FILE_NAME),
+            1
+        ))
+    );
+
+    // Rows are sorted by time, partition values are only in the path
+    let rows = read_rows(&dax_day);
+    let fields: Vec<&Field> = rows[0].get_column_iter().map(|(_, field)| field).collect();
+    assert_eq!(rows[0].len(), 6);
+    assert_eq!(
+        fields[0],
+        &Field::TimestampMicros(start().timestamp_micros())
+    );
+    assert_eq!(fields[4], &Field::Double(100.0));
+    assert_eq!(fields[5], &Field::Long(5));
+    let fields: Vec<&Field> = rows[1].get_column_iter().map(|(_, field)| field).collect();
+    assert_eq!(fields[4], &Field::Double(101.0));
+    assert_eq!(fields[5], &Field::Null);
+
+    // Writing a partition again replaces its file
+    writer.write_candles(&[candle(DAX, 0, 99.0)]).unwrap();
+    assert_eq!(read_rows(&dax_day).len(), 1);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_write_ticks_and_transactions() {
+    let root = dataset_root("ticks");
+    let writer = ParquetDatasetWriter::new(&root);
+    let tick = |seconds: i64, bid: Option<f64>| Tick {
+        epic: DAX.to_string(),
+        timestamp: start() + Duration::seconds(seconds),
+        bid,
+        offer: Some(18001.0),
+    };
+    let partitions = writer
+        .write_ticks(&[tick(0, Some(18000.0)), tick(1, None)])
+        .unwrap();
+    assert_eq!(partitions.len(), 1);
+    assert_eq!(partitions[0].rows, 2);
+    let rows = read_rows(&partitions[0].path);
+    let fields: Vec<&Field> = rows[1].get_column_iter().map(|(_, field)| field).collect();
+    assert_eq!(fields[1], &Field::Null);
+    assert_eq!(fields[2], &Field::Double(18001.0));
+
+    let transaction = |date_utc: &str, reference: &str| -> AccountTransaction {
+        serde_json::from_value(json!({
+            "date": "2025-07-01",
+            "dateUtc": date_utc,
+            "openDateUtc": date_utc,
+            "instrumentName": "Germany 40",
+            "period": "-",
+            "profitAndLoss": "E1.00",
+            "transactionType": "DEAL",
+            "reference": reference,
+            "openLevel": "0",
+            "closeLevel": "0",
+            "size": "+1",
+            "currency": "EUR",
+            "cashTransaction": false
+        }))
+        .unwrap()
+    };
+    let partitions = writer
+        .write_transactions(&[
+            transaction("2025-07-02T10:00:00", "D2"),
+            transaction("2025-07-01T10:00:00", "D1"),
+        ])
+        .unwrap();
+    assert_eq!(partitions.len(), 2);
+    assert!(
+        partitions[0]
+            .path
+            .ends_with("transactions/date=2025-07-01/part-0.parquet")
+    );
+    assert_eq!(read_rows(&partitions[1].path).len(), 1);
+    assert!(
+        writer
+            .write_transactions(&[transaction("yesterday", "D3")])
+            .is_err()
+    );
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_write_candles_rejects_path_epics() {
+    let root = dataset_root("invalid");
+    let writer = ParquetDatasetWriter::new(&root);
+    assert!(
+        writer
+            .write_candles(&[candle("../escape", 0, 1.0)])
+            .is_err()
+    );
+    assert!(!root.exists());
+}
@@ -0,0 +1,154 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use futures::TryStreamExt;
+use ig_client::application::models::market::Resolution;
+use ig_client::storage::Store;
+use ig_client::storage::memory_store::MemoryStore;
+use ig_client::storage::price_series::{PriceEvent, PriceGap, SERIES_PAGE_SIZE, tick_stream};
+use ig_client::storage::store::{Candle, Tick};
+
+const DAX: &str = "IX.D.DAX.DAILY.IP";
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap()
+}
+
+fn candle(epic: &str, minute: i64) -> Candle {
+    Candle {
+        epic: epic.to_string(),
+        resolution: Resolution::Minute,
+        timestamp: start() + Duration::minutes(minute),
+        open: 1.0,
+        high: 1.0,
+        low: 1.0,
+        close: minute as f64,
+        volume: None,
+    }
+}
+
+fn tick(seconds: i64) -> Tick {
+    Tick {
+        epic: DAX.to_string(),
+        timestamp: start() + Duration::seconds(seconds),
+        bid: Some(100.0),
+        offer: Some(101.0),
+    }
+}
+
+#[tokio::test]
+async fn test_candles_stream_with_gaps() {
+    let store = MemoryStore::new();
+    store
+        .put_candles(&[
+            candle(DAX, 1),
+            candle(DAX, 2),
+            candle(DAX, 5),
+            candle("IX.D.FTSE.DAILY.IP", 3),
+        ])
+        .await
+        .unwrap();
+
+    // The range starts within minute 0, so minute 1 is the first expected candle
+    let events: Vec<PriceEvent<Candle>> = store
+        .candles(
+            DAX,
+            Resolution::Minute,
+            start() + Duration::seconds(30),
+            start() + Duration::minutes(8),
+        )
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(
+        events,
+        [
+            PriceEvent::Price(candle(DAX, 1)),
+            PriceEvent::Price(candle(DAX, 2)),
+            PriceEvent::Gap(PriceGap {
+                start: start() + Duration::minutes(3),
+                end: start() + Duration::minutes(5),
+                missing: Some(2),
+            }),
+            PriceEvent::Price(candle(DAX, 5)),
+            PriceEvent::Gap(PriceGap {
+                start: start() + Duration::minutes(6),
+                end: start() + Duration::minutes(8),
+                missing: Some(2),
+            }),
+        ]
+    );
+
+    // A range without candles is one gap
+    let events: Vec<PriceEvent<Candle>> = store
+        .candles(DAX, Resolution::Hour, start(), start() + Duration::hours(3))
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    let gap = events[0].gap().unwrap();
+    assert_eq!(gap.missing, Some(3));
+    assert_eq!(gap.duration(), Duration::hours(3));
+}
+
+#[tokio::test]
+async fn test_ticks_stream_with_gaps() {
+    let store = MemoryStore::new();
+    store
+        .put_ticks(&[tick(10), tick(20), tick(200)])
+        .await
+        .unwrap();
+
+    let events: Vec<PriceEvent<Tick>> = store
+        .ticks(DAX, start(), start() + Duration::seconds(230))
+        .try_collect()
+        .await
+        .unwrap();
+    let gaps: Vec<PriceGap> = events
+        .iter()
+        .filter_map(|event| event.gap().copied())
+        .collect();
+    assert_eq!(
+        gaps,
+        [PriceGap {
+            start: start() + Duration::seconds(20),
+            end: start() + Duration::seconds(200),
+            missing: None,
+        }]
+    );
+    let ticks: Vec<Tick> = events.into_iter().filter_map(PriceEvent::price).collect();
+    assert_eq!(ticks, [tick(10), tick(20), tick(200)]);
+
+    // A shorter threshold also reports the silences at both ends
+    let events: Vec<PriceEvent<Tick>> = tick_stream(
+        &store,
+        DAX,
+        start(),
+        start() + Duration::seconds(230),
+        Duration::seconds(5),
+    )
+    .try_collect()
+    .await
+    .unwrap();
+    assert_eq!(
+        events.iter().filter(|event| event.gap().is_some()).count(),
+        4
+    );
+}
+
+#[tokio::test]
+async fn test_ticks_stream_reads_pages() {
+    let store = MemoryStore::new();
+    let ticks: Vec<Tick> = (0..SERIES_PAGE_SIZE as i64 + 5).map(tick).collect();
+    store.put_ticks(&ticks).await.unwrap();
+
+    let events: Vec<PriceEvent<Tick>> = store
+        .ticks(
+            DAX,
+            start(),
+            start() + Duration::seconds(SERIES_PAGE_SIZE as i64 + 5),
+        )
+        .try_collect()
+        .await
+        .unwrap();
+    let streamed: Vec<Tick> = events.into_iter().filter_map(PriceEvent::price).collect();
+    assert_eq!(streamed, ticks);
+}
@@ -0,0 +1,47 @@
+use chrono::{Duration, TimeZone, Utc};
+use ig_client::application::models::market::Resolution;
+use ig_client::storage::retention::{RetentionPolicy, RetentionRule};
+
+#[test]
+fn test_retention_policy_rules() {
+    let policy = RetentionPolicy::new()
+        .compact_ticks(Duration::days(30), Resolution::Minute)
+        .keep_candles(Resolution::Second, Duration::days(1))
+        .compact_candles(Resolution::Minute, Duration::days(365), Resolution::Hour);
+    assert!(!policy.is_empty());
+    assert!(RetentionPolicy::new().is_empty());
+    assert_eq!(
+        policy.ticks,
+        Some(RetentionRule {
+            max_age: Duration::days(30),
+            compact_to: Some(Resolution::Minute),
+        })
+    );
+    // Rules are applied from the finest resolution
+    let resolutions: Vec<Resolution> = policy.candles.keys().copied().collect();
+    assert_eq!(resolutions, [Resolution::Second, Resolution::Minute]);
+
+    // A later rule for the same data replaces the previous one
+    let policy = policy.keep_ticks(Duration::days(7));
+    assert_eq!(policy.ticks.unwrap().compact_to, None);
+}
+
+#[test]
+fn test_retention_rule_cutoff() {
+    let now = Utc.with_ymd_and_hms(2025, 7, 31, 9, 42, 17).unwrap();
+    let prune = RetentionRule {
+        max_age: Duration::days(30),
+        compact_to: None,
+    };
+    assert_eq!(prune.cutoff(now), now - Duration::days(30));
+
+    // Compaction only covers whole periods of the target resolution
+    let compact = RetentionRule {
+        max_age: Duration::days(30),
+        compact_to: Some(Resolution::Hour),
+    };
+    assert_eq!(
+        compact.cutoff(now),
+        Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap()
+    );
+}
@@ -0,0 +1,249 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::application::models::market::Resolution;
+use ig_client::error::AppError;
+use ig_client::presentation::InstrumentType;
+use ig_client::storage::Store;
+use ig_client::storage::instrument_catalog::CatalogEntry;
+use ig_client::storage::memory_store::MemoryStore;
+use ig_client::storage::order_journal::{JournalEntry, JournalEntryKind};
+use ig_client::storage::snapshot::{
+    SNAPSHOT_PAGE_SIZE, SNAPSHOT_VERSION, SnapshotCounts, SnapshotSelection, read_snapshot_header,
+};
+use ig_client::storage::store::{Candle, StoreQuery, Tick};
+use serde_json::json;
+use std::path::PathBuf;
+
+const DAX: &str = "IX.D.DAX.DAILY.IP";
+const FTSE: &str = "IX.D.FTSE.DAILY.IP";
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap()
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("ig_snapshot_{}", std::process::id()))
+        .join(name)
+}
+
+fn candle(epic: &str, resolution: Resolution, hours: i64) -> Candle {
+    Candle {
+        epic: epic.to_string(),
+        resolution,
+        timestamp: start() + Duration::hours(hours),
+        open: 100.0,
+        high: 110.0,
+        low: 90.0,
+        close: 105.0,
+        volume: Some(3),
+    }
+}
+
+fn tick(epic: &str, hours: i64) -> Tick {
+    Tick {
+        epic: epic.to_string(),
+        timestamp: start() + Duration::hours(hours),
+        bid: Some(100.0),
+        offer: Some(101.0),
+    }
+}
+
+fn instrument(epic: &str) -> CatalogEntry {
+    CatalogEntry {
+        epic: epic.to_string(),
+        name: epic.to_string(),
+        instrument_type: InstrumentType::Indices,
+        expiry: "-".to_string(),
+        path: vec!["Indices".to_string()],
+        market_status: "TRADEABLE".to_string(),
+        currency: Some("EUR".to_string()),
+        dealing_rules: None,
+        listed_at: start(),
+        details_at: None,
+    }
+}
+
+fn transaction() -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": "2025-07-01",
+        "dateUtc": "2025-07-01T10:00:00",
+        "openDateUtc": "2025-07-01T10:00:00",
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": "E1.00",
+        "transactionType": "DEAL",
+        "reference": "D1",
+        "openLevel": "0",
+        "closeLevel": "0",
+        "size": "+1",
+        "currency": "EUR",
+        "cashTransaction": false
+    }))
+    .unwrap()
+}
+
+async fn filled_store() -> MemoryStore {
+    let store = MemoryStore::new();
+    store
+        .put_candles(&[
+            candle(DAX, Resolution::Hour, 0),
+            candle(DAX, Resolution::Hour, 1),
+            candle(DAX, Resolution::Day, 0),
+            candle(DAX, Resolution::Hour, 30),
+            candle(FTSE, Resolution::Hour, 0),
+        ])
+        .await
+        .unwrap();
+    store
+        .put_ticks(&[tick(DAX, 0), tick(FTSE, 0)])
+        .await
+        .unwrap();
+    store.put_transactions(&[transaction()]).await.unwrap();
+    store
+        .put_instruments(&[instrument(DAX), instrument(FTSE)])
+        .await
+        .unwrap();
+
+    let mut request = JournalEntry::new(
+        "C1",
+        JournalEntryKind::Request,
+        "create_order",
+        json!({ "direction": "BUY", "size": 1.0 }),
+    )
+    .with_epic(Some(DAX));
+    request.timestamp = start();
+    let mut confirmation = JournalEntry::new(
+        "C1",
+        JournalEntryKind::Confirmation,
+        "create_order",
+        json!({ "dealReference": "REF1", "dealStatus": "ACCEPTED", "level": 18000.0 }),
+    )
+    .with_deal_reference(Some("REF1"));
+    confirmation.timestamp = start() + Duration::seconds(1);
+    store.record_orders(&[request, confirmation]).await.unwrap();
+    store
+}
+
+#[tokio::test]
+async fn test_snapshot_of_selected_markets() {
+    let source = filled_store().await;
+    let path = snapshot_path("dax.jsonl");
+    let selection = SnapshotSelection::new()
+        .for_epic(DAX)
+        .between(start(), start() + Duration::days(1));
+    let counts = source.export_snapshot(&path, &selection).await.unwrap();
+    // The confirmation has no EPIC and follows its request; transactions are skipped
+    assert_eq!(
+        counts,
+        SnapshotCounts {
+            candles: 3,
+            ticks: 1,
+            transactions: 0,
+            orders: 2,
+            deals: 1,
+            instruments: 1,
+        }
+    );
+    let header = read_snapshot_header(&path).unwrap();
+    assert_eq!(header.version, SNAPSHOT_VERSION);
+    assert_eq!(header.selection, selection);
+
+    let target = MemoryStore::new();
+    let report = target.import_snapshot(&path).await.unwrap();
+    assert_eq!(report.inserted, counts.total());
+    assert_eq!(target.candle_count(), 3);
+    assert_eq!(target.get_orders("C1").await.unwrap().len(), 2);
+    assert!(target.get_deal("REF1").await.unwrap().unwrap().is_filled());
+    assert!(target.get_instrument(FTSE).await.unwrap().is_none());
+
+    // Importing again changes nothing
+    let replayed = target.import_snapshot(&path).await.unwrap();
+    assert_eq!(replayed.unchanged, counts.total());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_snapshot_of_whole_store() {
+    let source = filled_store().await;
+    let path = snapshot_path("all.jsonl");
+    let counts = source
+        .export_snapshot(&path, &SnapshotSelection::new())
+        .await
+        .unwrap();
+    assert_eq!(counts.total(), 13);
+    assert_eq!(counts.transactions, 1);
+
+    let target = MemoryStore::new();
+    target.import_snapshot(&path).await.unwrap();
+    for resolution in [Resolution::Hour, Resolution::Day] {
+        assert_eq!(
+            target
+                .query_candles(resolution, &StoreQuery::new())
+                .await
+                .unwrap(),
+            source
+                .query_candles(resolution, &StoreQuery::new())
+                .await
+                .unwrap()
+        );
+    }
+    assert_eq!(
+        target.get_transactions("D1").await.unwrap(),
+        [transaction()]
+    );
+    assert_eq!(target.tick_count(), 2);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_snapshot_pages_records_sharing_a_time() {
+    // Three markets tick at the same times, so pages end in the middle of a time
+    let source = MemoryStore::new();
+    let ticks: Vec<Tick> = (0..SNAPSHOT_PAGE_SIZE as i64)
+        .flat_map(|millis| {
+            [DAX, FTSE, "CS.D.EURUSD.CFD.IP"].map(|epic| Tick {
+                timestamp: start() + Duration::milliseconds(millis),
+                ..tick(epic, 0)
+            })
+        })
+        .collect();
+    source.put_ticks(&ticks).await.unwrap();
+
+    let path = snapshot_path("paged.jsonl");
+    let counts = source
+        .export_snapshot(&path, &SnapshotSelection::new())
+        .await
+        .unwrap();
+    assert_eq!(counts.ticks, ticks.len());
+
+    let target = MemoryStore::new();
+    target.import_snapshot(&path).await.unwrap();
+    assert_eq!(target.tick_count(), ticks.len());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_import_rejects_other_files() {
+    let path = snapshot_path("not_a_snapshot.jsonl");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "{\"format\":\"other\"}\n").unwrap();
+    let store = MemoryStore::new();
+    assert!(matches!(
+        store.import_snapshot(&path).await,
+        Err(AppError::InvalidInput(_))
+    ));
+
+    let newer = json!({
+        "format": "ig-client-store-snapshot",
+        "version": SNAPSHOT_VERSION + 1,
+        "createdAt": start(),
+        "selection": SnapshotSelection::new(),
+    });
+    std::fs::write(&path, format!("{newer}\n")).unwrap();
+    assert!(matches!(
+        read_snapshot_header(&path),
+        Err(AppError::InvalidInput(_))
+    ));
+    let _ = std::fs::remove_file(&path);
+}
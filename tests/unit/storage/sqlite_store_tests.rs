@@ -0,0 +1,389 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::application::models::market::Resolution;
+use ig_client::error::AppError;
+use ig_client::storage::Store;
+use ig_client::storage::order_journal::{JournalEntry, JournalEntryKind};
+use ig_client::storage::sqlite_store::{SQLITE_STORE_SCHEMA_VERSION, SqliteStore};
+use ig_client::storage::store::{Candle, StoreQuery, Tick, WriteReport};
+use serde_json::json;
+
+const DAX: &str = "IX.D.DAX.DAILY.IP";
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap()
+}
+
+fn candle(minute: i64, close: f64) -> Candle {
+    Candle {
+        epic: DAX.to_string(),
+        resolution: Resolution::Minute,
+        timestamp: start() + Duration::minutes(minute),
+        open: 100.0,
+        high: 110.0,
+        low: 90.0,
+        close,
+        volume: Some(5),
+    }
+}
+
+fn transaction(date_utc: &str, reference: &str) -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": "2025-07-01",
+        "dateUtc": date_utc,
+        "openDateUtc": date_utc,
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": "E1.00",
+        "transactionType": "DEAL",
+        "reference": reference,
+        "openLevel": "0",
+        "closeLevel": "0",
+        "size": "+1",
+        "currency": "EUR",
+        "cashTransaction": false
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_sqlite_candles_and_ticks() {
+    let store = SqliteStore::in_memory().await.unwrap();
+    store
+        .put_candles(&[candle(2, 101.0), candle(0, 100.0), candle(1, 99.0)])
+        .await
+        .unwrap();
+    // Writing a candle again replaces it
+    store.put_candles(&[candle(1, 105.0)]).await.unwrap();
+
+    let candles = store
+        .query_candles(Resolution::Minute, &StoreQuery::new().for_epic(DAX))
+        .await
+        .unwrap();
+    let closes: Vec<f64> = candles.iter().map(|candle| candle.close).collect();
+    assert_eq!(closes, [100.0, 105.0, 101.0]);
+    let limited = store
+        .query_candles(
+            Resolution::Minute,
+            &StoreQuery::new()
+                .between(start() + Duration::minutes(1), start() + Duration::hours(1))
+                .with_limit(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(limited, [candle(1, 105.0)]);
+    assert!(
+        store
+            .query_candles(Resolution::Hour, &StoreQuery::new())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        store
+            .get_candle(DAX, Resolution::Minute, start())
+            .await
+            .unwrap(),
+        Some(candle(0, 100.0))
+    );
+
+    let latest = store
+        .latest_candles(DAX, Resolution::Minute, 2)
+        .await
+        .unwrap();
+    assert_eq!(latest, [candle(1, 105.0), candle(2, 101.0)]);
+    let five = store
+        .query_candles_downsampled(Resolution::Minute, Resolution::Minute5, &StoreQuery::new())
+        .await
+        .unwrap();
+    assert_eq!(five.len(), 1);
+    assert_eq!((five[0].open, five[0].close), (100.0, 101.0));
+    assert_eq!(five[0].volume, Some(15));
+    assert!(
+        store
+            .query_candles_downsampled(Resolution::Hour, Resolution::Minute, &StoreQuery::new())
+            .await
+            .is_err()
+    );
+
+    let tick = Tick {
+        epic: DAX.to_string(),
+        timestamp: start() + Duration::microseconds(1_500),
+        bid: Some(18000.5),
+        offer: None,
+    };
+    store.put_ticks(std::slice::from_ref(&tick)).await.unwrap();
+    assert_eq!(
+        store.get_tick(DAX, tick.timestamp).await.unwrap(),
+        Some(tick)
+    );
+    assert!(store.get_tick(DAX, start()).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_sqlite_transactions_and_orders() {
+    let path = std::env::temp_dir().join(format!("ig_store_{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let store = SqliteStore::open(&path).await.unwrap();
+
+    let written = store
+        .put_transactions(&[
+            transaction("2025-07-02T10:00:00", "D1"),
+            transaction("2025-07-01T10:00:00", "D1"),
+            transaction("2025-07-01T12:00:00", "D2"),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(written.inserted, 3);
+    store
+        .put_transactions(&[transaction("2025-07-01T12:00:00", "D2")])
+        .await
+        .unwrap();
+    assert!(
+        store
+            .put_transactions(&[transaction("yesterday", "D3")])
+            .await
+            .is_err()
+    );
+    let deal = store.get_transactions("D1").await.unwrap();
+    assert_eq!(deal.len(), 2);
+    assert_eq!(deal[0].date_utc, "2025-07-01T10:00:00");
+    let july_first = store
+        .query_transactions(
+            &StoreQuery::new()
+                .for_epic("Germany 40")
+                .between(start(), start() + Duration::days(1)),
+        )
+        .await
+        .unwrap();
+    let references: Vec<&str> = july_first.iter().map(|tx| tx.reference.as_str()).collect();
+    assert_eq!(references, ["D1", "D2"]);
+
+    let request = JournalEntry::new("C1", JournalEntryKind::Request, "create_order", json!({}))
+        .with_epic(Some(DAX));
+    let response = JournalEntry::new("C1", JournalEntryKind::Response, "create_order", json!({}))
+        .with_deal_reference(Some("REF1"));
+    store
+        .put_orders(&[request.clone(), response])
+        .await
+        .unwrap();
+    assert_eq!(store.get_orders("C1").await.unwrap().len(), 2);
+    // The response has no EPIC, so a query on the market only returns the request
+    let orders = store
+        .query_orders(&StoreQuery::new().for_epic(DAX))
+        .await
+        .unwrap();
+    assert_eq!(orders, [request]);
+
+    drop(store);
+    let reopened = SqliteStore::open(&path).await.unwrap();
+    assert_eq!(
+        reopened
+            .query_transactions(&StoreQuery::new())
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+    drop(reopened);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+}
+
+#[tokio::test]
+async fn test_sqlite_record_orders_and_query_deals() {
+    let store = SqliteStore::in_memory().await.unwrap();
+    let at = |seconds: i64| start() + Duration::seconds(seconds);
+    let mut request = JournalEntry::new(
+        "C1",
+        JournalEntryKind::Request,
+        "create_order",
+        json!({ "epic": DAX, "direction": "SELL", "size": 1.0 }),
+    )
+    .with_epic(Some(DAX));
+    request.timestamp = at(0);
+    let mut response = JournalEntry::new(
+        "C1",
+        JournalEntryKind::Response,
+        "create_order",
+        json!({ "dealReference": "REF1" }),
+    )
+    .with_deal_reference(Some("REF1"));
+    response.timestamp = at(1);
+    store.record_orders(&[request, response]).await.unwrap();
+
+    let pending = store.get_deal("REF1").await.unwrap().unwrap();
+    assert_eq!(pending.epic.as_deref(), Some(DAX));
+    assert_eq!(pending.deal_status, None);
+
+    // The confirmation arrives in a later write and completes the stored deal
+    let mut confirmation = JournalEntry::new(
+        "C1",
+        JournalEntryKind::Confirmation,
+        "create_order",
+        json!({ "dealReference": "REF1", "dealStatus": "ACCEPTED", "level": 18400.0 }),
+    )
+    .with_deal_reference(Some("REF1"));
+    confirmation.timestamp = at(2);
+    assert_eq!(
+        store.record_orders(&[confirmation]).await.unwrap().inserted,
+        1
+    );
+    let filled = store.get_deal("REF1").await.unwrap().unwrap();
+    assert!(filled.is_filled());
+    assert_eq!(filled.level, Some(18400.0));
+    assert_eq!(filled.direction.as_deref(), Some("SELL"));
+
+    let mut other = JournalEntry::new(
+        "C2",
+        JournalEntryKind::Response,
+        "create_order",
+        json!({ "dealReference": "REF2" }),
+    )
+    .with_epic(Some("CS.D.EURUSD.TODAY.IP"))
+    .with_deal_reference(Some("REF2"));
+    other.timestamp = at(3);
+    let mut later = JournalEntry::new(
+        "C3",
+        JournalEntryKind::Response,
+        "create_order",
+        json!({ "dealReference": "REF3" }),
+    )
+    .with_epic(Some(DAX))
+    .with_deal_reference(Some("REF3"));
+    later.timestamp = at(3600);
+    store.record_orders(&[other, later]).await.unwrap();
+
+    let dax_deals = store
+        .query_deals(&StoreQuery::new().for_epic(DAX).between(at(0), at(60)))
+        .await
+        .unwrap();
+    assert_eq!(dax_deals, [filled]);
+    assert_eq!(
+        store.query_deals(&StoreQuery::new()).await.unwrap().len(),
+        3
+    );
+    assert!(store.get_deal("REF4").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_sqlite_replayed_writes_report_conflicts() {
+    let store = SqliteStore::in_memory().await.unwrap();
+    let batch = [candle(0, 100.0), candle(1, 101.0)];
+    assert_eq!(
+        store.put_candles(&batch).await.unwrap(),
+        WriteReport {
+            inserted: 2,
+            updated: 0,
+            unchanged: 0,
+        }
+    );
+    // Replaying the batch writes nothing new
+    let replayed = store.put_candles(&batch).await.unwrap();
+    assert_eq!(replayed.unchanged, 2);
+    assert_eq!(replayed.conflicts(), 2);
+
+    let report = store
+        .put_candles(&[candle(1, 105.0), candle(2, 102.0)])
+        .await
+        .unwrap();
+    assert_eq!(
+        report,
+        WriteReport {
+            inserted: 1,
+            updated: 1,
+            unchanged: 0,
+        }
+    );
+    assert_eq!(
+        store
+            .query_candles(Resolution::Minute, &StoreQuery::new())
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+
+    // A missing quote is compared as a value too
+    let mut tick = Tick {
+        epic: DAX.to_string(),
+        timestamp: start(),
+        bid: None,
+        offer: Some(18001.5),
+    };
+    store.put_ticks(std::slice::from_ref(&tick)).await.unwrap();
+    let replayed = store.put_ticks(std::slice::from_ref(&tick)).await.unwrap();
+    assert_eq!(replayed.unchanged, 1);
+    tick.bid = Some(18000.5);
+    let report = store.put_ticks(std::slice::from_ref(&tick)).await.unwrap();
+    assert_eq!(report.updated, 1);
+    assert_eq!(store.get_tick(DAX, start()).await.unwrap(), Some(tick));
+
+    let entry = transaction("2025-07-01T10:00:00", "D1");
+    store
+        .put_transactions(std::slice::from_ref(&entry))
+        .await
+        .unwrap();
+    let replayed = store
+        .put_transactions(std::slice::from_ref(&entry))
+        .await
+        .unwrap();
+    assert_eq!((replayed.inserted, replayed.unchanged), (0, 1));
+    assert_eq!(store.get_transactions("D1").await.unwrap().len(), 1);
+
+    // A charge booked in the same second under the reference of its deal is kept
+    let mut charge = entry;
+    charge.transaction_type = "CHARGE".to_string();
+    charge.profit_and_loss = "E-0.50".to_string();
+    let report = store.put_transactions(&[charge]).await.unwrap();
+    assert_eq!(report.inserted, 1);
+    assert_eq!(store.get_transactions("D1").await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_sqlite_schema_versioning() {
+    let path = std::env::temp_dir().join(format!("ig_schema_{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let store = SqliteStore::open(&path).await.unwrap();
+    assert_eq!(
+        store.schema_version().await.unwrap(),
+        Some(SQLITE_STORE_SCHEMA_VERSION)
+    );
+    // Opening again applies nothing
+    assert_eq!(store.migrate().await.unwrap(), 0);
+    store.put_candles(&[candle(0, 100.0)]).await.unwrap();
+
+    // A database created before versioning keeps its data
+    sqlx::raw_sql("PRAGMA user_version = 0")
+        .execute(store.pool())
+        .await
+        .unwrap();
+    assert_eq!(
+        store.migrate().await.unwrap() as i64,
+        SQLITE_STORE_SCHEMA_VERSION
+    );
+    assert!(
+        store
+            .get_candle(DAX, Resolution::Minute, start())
+            .await
+            .unwrap()
+            .is_some()
+    );
+
+    // A database written by a later version is not opened
+    sqlx::raw_sql(&format!(
+        "PRAGMA user_version = {}",
+        SQLITE_STORE_SCHEMA_VERSION + 1
+    ))
+    .execute(store.pool())
+    .await
+    .unwrap();
+    store.pool().close().await;
+    assert!(matches!(
+        SqliteStore::open(&path).await,
+        Err(AppError::IncompatibleSchema { found, supported })
+            if found == SQLITE_STORE_SCHEMA_VERSION + 1 && supported == SQLITE_STORE_SCHEMA_VERSION
+    ));
+    let _ = std::fs::remove_file(&path);
+}
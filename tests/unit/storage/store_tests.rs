@@ -0,0 +1,157 @@
+use chrono::{Duration, TimeZone, Utc};
+use ig_client::application::models::market::{HistoricalPrice, Resolution};
+use ig_client::error::AppError;
+use ig_client::presentation::ChartData;
+use ig_client::storage::store::{Candle, StoreQuery, Tick, candles_from_ticks, downsample};
+use serde_json::json;
+
+#[test]
+fn test_store_query_matches() {
+    let start = Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap();
+    let query = StoreQuery::new()
+        .for_epic("IX.D.DAX.DAILY.IP")
+        .between(start, start + Duration::days(1))
+        .with_limit(10);
+    assert_eq!(query.limit, Some(10));
+    assert!(query.matches("IX.D.DAX.DAILY.IP", start));
+    assert!(!query.matches("IX.D.FTSE.DAILY.IP", start));
+    assert!(!query.matches("IX.D.DAX.DAILY.IP", start - Duration::seconds(1)));
+    // The end of the range is excluded
+    assert!(!query.matches("IX.D.DAX.DAILY.IP", start + Duration::days(1)));
+    assert!(StoreQuery::new().matches("anything", start));
+}
+
+fn minute(epic: &str, minute: i64, open: f64, close: f64, volume: Option<i64>) -> Candle {
+    Candle {
+        epic: epic.to_string(),
+        resolution: Resolution::Minute,
+        timestamp: Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap() + Duration::minutes(minute),
+        open,
+        high: open.max(close) + 1.0,
+        low: open.min(close) - 1.0,
+        close,
+        volume,
+    }
+}
+
+#[test]
+fn test_downsample_candles() {
+    let candles = [
+        minute("DAX", 6, 12.0, 13.0, None),
+        minute("DAX", 0, 10.0, 11.0, Some(2)),
+        minute("DAX", 4, 11.0, 9.0, Some(3)),
+        minute("FTSE", 1, 5.0, 6.0, None),
+    ];
+    let five = downsample(&candles, Resolution::Minute5).unwrap();
+    assert_eq!(five.len(), 3);
+    let dax = &five[0];
+    assert_eq!(dax.epic, "DAX");
+    assert_eq!(dax.resolution, Resolution::Minute5);
+    assert_eq!(dax.timestamp, candles[1].timestamp);
+    assert_eq!(
+        (dax.open, dax.high, dax.low, dax.close),
+        (10.0, 12.0, 8.0, 9.0)
+    );
+    assert_eq!(dax.volume, Some(5));
+    assert_eq!(five[1].epic, "FTSE");
+    assert_eq!(
+        five[2].timestamp,
+        candles[1].timestamp + Duration::minutes(5)
+    );
+    assert_eq!(five[2].volume, None);
+
+    assert_eq!(downsample(&candles, Resolution::Hour).unwrap().len(), 2);
+    assert!(matches!(
+        downsample(&five, Resolution::Minute),
+        Err(AppError::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn test_candle_from_historical_and_chart() {
+    let price: HistoricalPrice = serde_json::from_value(json!({
+        "snapshotTime": "2025/07/01 10:00:00",
+        "snapshotTimeUTC": "2025-07-01T09:00:00",
+        "openPrice": {"bid": 100.0, "ask": 102.0, "lastTraded": null},
+        "highPrice": {"bid": 104.0, "ask": 106.0, "lastTraded": null},
+        "lowPrice": {"bid": 98.0, "ask": 100.0, "lastTraded": null},
+        "closePrice": {"bid": null, "ask": null, "lastTraded": 103.0},
+        "lastTradedVolume": 7
+    }))
+    .unwrap();
+    let candle = Candle::from_historical("DAX", Resolution::Hour, &price).unwrap();
+    assert_eq!(
+        candle.timestamp,
+        Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap()
+    );
+    assert_eq!(
+        (candle.open, candle.high, candle.low, candle.close),
+        (101.0, 105.0, 99.0, 103.0)
+    );
+    assert_eq!(candle.volume, Some(7));
+
+    // The local snapshotTime alone gives no candle, its offset being unknown
+    let mut local = price.clone();
+    local.snapshot_time_utc = None;
+    assert!(local.timestamp().is_none());
+    assert!(Candle::from_historical("DAX", Resolution::Hour, &local).is_none());
+
+    let chart: ChartData = serde_json::from_value(json!({
+        "item_name": "CHART:IX.D.DAX.DAILY.IP:1MINUTE",
+        "item_pos": 1,
+        "scale": "1MINUTE",
+        "fields": {
+            "UTM": "1751360400000",
+            "BID_OPEN": "100", "OFR_OPEN": "102",
+            "BID_HIGH": "104", "OFR_HIGH": "106",
+            "BID_LOW": "98", "OFR_LOW": "100",
+            "BID_CLOSE": "102", "OFR_CLOSE": "104"
+        },
+        "changed_fields": {},
+        "is_snapshot": false
+    }))
+    .unwrap();
+    let candle = Candle::from_chart(&chart).unwrap();
+    assert_eq!(candle.epic, "IX.D.DAX.DAILY.IP");
+    assert_eq!(candle.resolution, Resolution::Minute);
+    assert_eq!(
+        candle.timestamp,
+        Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap()
+    );
+    assert_eq!((candle.open, candle.close), (101.0, 103.0));
+    assert_eq!(candle.volume, None);
+}
+
+#[test]
+fn test_candles_from_ticks() {
+    let start = Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap();
+    let tick = |seconds: i64, bid: Option<f64>, offer: Option<f64>| Tick {
+        epic: "DAX".to_string(),
+        timestamp: start + Duration::seconds(seconds),
+        bid,
+        offer,
+    };
+    let candles = candles_from_ticks(
+        &[
+            tick(30, Some(12.0), Some(14.0)),
+            tick(0, Some(10.0), Some(12.0)),
+            tick(45, Some(8.0), None),
+            tick(50, None, None),
+            tick(61, None, Some(20.0)),
+        ],
+        Resolution::Minute,
+    );
+    assert_eq!(candles.len(), 2);
+    let first = &candles[0];
+    assert_eq!(first.timestamp, start);
+    assert_eq!(first.resolution, Resolution::Minute);
+    assert_eq!(
+        (first.open, first.high, first.low, first.close),
+        (11.0, 13.0, 8.0, 8.0)
+    );
+    // Ticks without a price are not counted
+    assert_eq!(first.volume, Some(3));
+    assert_eq!(candles[1].timestamp, start + Duration::minutes(1));
+    assert_eq!(candles[1].close, 20.0);
+    assert!(candles_from_ticks(&[], Resolution::Minute).is_empty());
+}
@@ -0,0 +1,88 @@
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::storage::transaction_store::{
+    FileTransactionStore, MemoryTransactionStore, TransactionStore,
+};
+use serde_json::json;
+
+fn transaction(date_utc: &str, reference: &str, pnl: &str) -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": "2025-07-01",
+        "dateUtc": date_utc,
+        "openDateUtc": date_utc,
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": pnl,
+        "transactionType": "DEAL",
+        "reference": reference,
+        "openLevel": "0",
+        "closeLevel": "0",
+        "size": "+1",
+        "currency": "EUR",
+        "cashTransaction": false
+    }))
+    .unwrap()
+}
+
+async fn assert_deduplicates(store: &dyn TransactionStore) {
+    let first = [
+        transaction("2025-07-01T10:00:00", "REF1", "E10.00"),
+        transaction("2025-07-01T11:00:00", "REF2", "E-4.00"),
+    ];
+    assert_eq!(store.append(&first).await.unwrap(), 2);
+
+    // Overlapping window: REF2 again, a charge sharing REF1 at another date, a charge
+    // booked in the same second as REF2, a new deal and the same new deal twice in the
+    // batch
+    let mut same_second_charge = transaction("2025-07-01T11:00:00", "REF2", "E-0.20");
+    same_second_charge.transaction_type = "CHARGE".to_string();
+    let overlapping = [
+        transaction("2025-07-01T11:00:00", "REF2", "E-4.00"),
+        transaction("2025-07-01T12:00:00", "REF1", "E-0.50"),
+        same_second_charge,
+        transaction("2025-07-01T13:00:00", "REF3", "E2.00"),
+        transaction("2025-07-01T13:00:00", "REF3", "E2.00"),
+    ];
+    assert_eq!(store.append(&overlapping).await.unwrap(), 3);
+    assert_eq!(store.append(&overlapping).await.unwrap(), 0);
+
+    let stored = store.load().await.unwrap();
+    let keys: Vec<(&str, &str)> = stored
+        .iter()
+        .map(|transaction| {
+            (
+                transaction.reference.as_str(),
+                transaction.date_utc.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        keys,
+        [
+            ("REF1", "2025-07-01T10:00:00"),
+            ("REF2", "2025-07-01T11:00:00"),
+            ("REF1", "2025-07-01T12:00:00"),
+            ("REF2", "2025-07-01T11:00:00"),
+            ("REF3", "2025-07-01T13:00:00"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_memory_transaction_store_deduplicates() {
+    assert_deduplicates(&MemoryTransactionStore::new()).await;
+}
+
+#[tokio::test]
+async fn test_file_transaction_store_deduplicates() {
+    let path = std::env::temp_dir().join(format!("ig_tx_store_{}.ndjson", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    assert_deduplicates(&FileTransactionStore::open(&path)).await;
+
+    // A reopened store learns the stored keys from the file
+    let reopened = FileTransactionStore::open(&path);
+    let again = [transaction("2025-07-01T10:00:00", "REF1", "E10.00")];
+    assert_eq!(reopened.append(&again).await.unwrap(), 0);
+    assert_eq!(reopened.load().await.unwrap().len(), 5);
+
+    std::fs::remove_file(&path).unwrap();
+}
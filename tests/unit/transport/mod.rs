@@ -1 +1,2 @@
 mod http_client_tests;
+mod streaming_tests;
@@ -0,0 +1,111 @@
+use ig_client::transport::streaming::{
+    ListenerFactory, StreamingMode, StreamingSubscriptions, SubscriptionSpec,
+    is_account_scoped_item, rescope_item,
+};
+use lightstreamer_rs::subscription::SubscriptionListener;
+use std::sync::Arc;
+use tokio::sync::mpsc::channel;
+
+struct NoopListener;
+
+impl SubscriptionListener for NoopListener {}
+
+#[test]
+fn test_is_account_scoped_item() {
+    assert!(is_account_scoped_item("TRADE:ABC123", "ABC123"));
+    assert!(is_account_scoped_item("ACCOUNT:ABC123", "ABC123"));
+    assert!(!is_account_scoped_item("TRADE:XYZ999", "ABC123"));
+    assert!(!is_account_scoped_item(
+        "MARKET:CS.D.EURUSD.TODAY.IP",
+        "ABC123"
+    ));
+    assert!(!is_account_scoped_item("CHART:ABC123:1MINUTE", "ABC123"));
+}
+
+#[test]
+fn test_rescope_item() {
+    assert_eq!(rescope_item("TRADE:OLD", "OLD", "NEW"), "TRADE:NEW");
+    assert_eq!(rescope_item("ACCOUNT:OLD", "OLD", "NEW"), "ACCOUNT:NEW");
+    assert_eq!(
+        rescope_item("MARKET:CS.D.EURUSD.TODAY.IP", "OLD", "NEW"),
+        "MARKET:CS.D.EURUSD.TODAY.IP"
+    );
+    assert_eq!(rescope_item("TRADE:OTHER", "OLD", "NEW"), "TRADE:OTHER");
+}
+
+#[test]
+fn test_subscription_spec_rescoped_keeps_market_items() {
+    let spec = SubscriptionSpec::new(
+        StreamingMode::Distinct,
+        vec![
+            "TRADE:OLD".to_string(),
+            "MARKET:IX.D.DAX.DAILY.IP".to_string(),
+        ],
+        vec!["CONFIRMS".to_string()],
+    );
+
+    assert!(spec.is_account_scoped("OLD"));
+    assert!(!spec.is_account_scoped("NEW"));
+
+    let rescoped = spec.rescoped("OLD", "NEW");
+    assert_eq!(
+        rescoped.items,
+        vec![
+            "TRADE:NEW".to_string(),
+            "MARKET:IX.D.DAX.DAILY.IP".to_string()
+        ]
+    );
+    assert_eq!(rescoped.mode, StreamingMode::Distinct);
+    assert_eq!(rescoped.fields, spec.fields);
+    assert!(rescoped.snapshot);
+}
+
+#[tokio::test]
+async fn test_resubscribe_for_account_updates_account_id() {
+    let (sender, _receiver) = channel(10);
+    let mut subscriptions = StreamingSubscriptions::new(sender, "OLD");
+
+    assert_eq!(
+        subscriptions.resubscribe_for_account("OLD").await.unwrap(),
+        0
+    );
+    assert_eq!(subscriptions.account_id(), "OLD");
+
+    assert_eq!(
+        subscriptions.resubscribe_for_account("NEW").await.unwrap(),
+        0
+    );
+    assert_eq!(subscriptions.account_id(), "NEW");
+    assert!(subscriptions.specs().is_empty());
+}
+
+#[tokio::test]
+async fn test_resubscribe_for_account_keeps_the_account_when_a_subscription_fails() {
+    // The client drops every request, so no subscription ID is ever returned
+    let (sender, mut receiver) = channel(10);
+    tokio::spawn(async move { while receiver.recv().await.is_some() {} });
+    let mut subscriptions = StreamingSubscriptions::new(sender, "OLD");
+    let factory: ListenerFactory = Arc::new(|| Box::new(NoopListener));
+    let spec = |item: &str| {
+        SubscriptionSpec::new(
+            StreamingMode::Distinct,
+            vec![item.to_string()],
+            vec!["CONFIRMS".to_string()],
+        )
+    };
+    subscriptions.track(1, spec("TRADE:OLD"), factory.clone());
+    subscriptions.track(2, spec("ACCOUNT:OLD"), factory);
+
+    assert!(subscriptions.resubscribe_for_account("NEW").await.is_err());
+    assert_eq!(subscriptions.account_id(), "OLD");
+    assert!(
+        subscriptions
+            .specs()
+            .iter()
+            .all(|spec| spec.is_account_scoped("OLD"))
+    );
+
+    // A retry sends the subscriptions again instead of finding the account switched
+    assert!(subscriptions.resubscribe_for_account("NEW").await.is_err());
+    assert_eq!(subscriptions.account_id(), "OLD");
+}
@@ -0,0 +1,23 @@
+use ig_client::utils::atomic_file::{AtomicFile, write_atomic};
+use std::io::Write;
+
+#[test]
+fn test_write_atomic_replaces_the_file() {
+    let directory = std::env::temp_dir().join(format!("ig_atomic_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("state.json");
+
+    write_atomic(&path, b"first").unwrap();
+    write_atomic(&path, b"second").unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+
+    // A file dropped before its commit leaves the previous content and no temporary file
+    let mut file = AtomicFile::create(&path).unwrap();
+    file.writer().write_all(b"partial").unwrap();
+    drop(file);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+    let entries: Vec<_> = std::fs::read_dir(&directory).unwrap().collect();
+    assert_eq!(entries.len(), 1);
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,69 @@
+use ig_client::application::models::market::MarketDetails;
+use ig_client::error::AppError;
+use ig_client::utils::currency::CurrencyConverter;
+use serde_json::{Value, json};
+
+fn create_test_market_details(currencies: Value) -> MarketDetails {
+    let step = json!({ "unit": "POINTS", "value": 1.0 });
+    serde_json::from_value(json!({
+        "instrument": {
+            "epic": "IX.D.DAX.DAILY.IP",
+            "name": "Germany 40",
+            "expiry": "DFB",
+            "contractSize": "1",
+            "valueOfOnePip": "1.00",
+            "currencies": currencies
+        },
+        "snapshot": { "marketStatus": "TRADEABLE" },
+        "dealingRules": {
+            "minStepDistance": step,
+            "minDealSize": step,
+            "minControlledRiskStopDistance": step,
+            "minNormalStopOrLimitDistance": step,
+            "maxStopOrLimitDistance": step,
+            "controlledRiskSpacing": step,
+            "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+            "trailingStopsPreference": "NOT_AVAILABLE"
+        }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_converter_uses_market_details_rates() {
+    let details = create_test_market_details(json!([
+        { "code": "EUR", "symbol": "E", "baseExchangeRate": 1.25, "exchangeRate": 0.8, "isDefault": true },
+        { "code": "USD", "symbol": "$", "baseExchangeRate": 1.25, "isDefault": false },
+        { "code": "GBP", "symbol": "£", "baseExchangeRate": 1.0, "exchangeRate": 2.0, "isDefault": false }
+    ]));
+    assert_eq!(
+        details
+            .instrument
+            .default_currency()
+            .map(|c| c.code.as_str()),
+        Some("EUR")
+    );
+
+    let converter = CurrencyConverter::from_market_details("GBP", [&details]);
+    assert_eq!(converter.base_currency(), "GBP");
+    assert_eq!(converter.rate("GBP"), Some(1.0));
+    assert_eq!(converter.rate("EUR"), Some(0.8));
+    assert_eq!(converter.rate("USD"), Some(0.8));
+
+    assert!((converter.to_base(100.0, "EUR").unwrap() - 80.0).abs() < 1e-9);
+    assert!((converter.from_base(80.0, "EUR").unwrap() - 100.0).abs() < 1e-9);
+    assert!((converter.convert(100.0, "EUR", "GBP").unwrap() - 80.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_converter_manual_rates_and_missing_currency() {
+    let converter = CurrencyConverter::new("EUR")
+        .with_rate("USD", 0.9)
+        .with_rate("EUR", 3.0);
+    assert_eq!(converter.rate("EUR"), Some(1.0));
+    assert!((converter.convert(10.0, "USD", "EUR").unwrap() - 9.0).abs() < 1e-9);
+    assert!(matches!(
+        converter.to_base(1.0, "JPY"),
+        Err(AppError::InvalidInput(_))
+    ));
+}
@@ -0,0 +1,44 @@
+use ig_client::application::models::market::PricePoint;
+use ig_client::application::models::order::{CreateOrderRequest, Direction};
+use ig_client::utils::decimal::{Decimal, to_decimal};
+use std::str::FromStr;
+
+#[test]
+fn test_to_decimal() {
+    assert_eq!(to_decimal(1.1), Some(Decimal::from_str("1.1").unwrap()));
+    assert_eq!(
+        to_decimal(-0.0001),
+        Some(Decimal::from_str("-0.0001").unwrap())
+    );
+    assert_eq!(to_decimal(f64::NAN), None);
+    assert_eq!(to_decimal(f64::INFINITY), None);
+}
+
+#[test]
+fn test_decimal_accessors() {
+    let order = CreateOrderRequest::limit(
+        "CS.D.EURUSD.TODAY.IP".to_string(),
+        Direction::Buy,
+        0.3,
+        1.0815,
+        "EUR".to_string(),
+    );
+    assert_eq!(
+        order.size_decimal(),
+        Some(Decimal::from_str("0.3").unwrap())
+    );
+    assert_eq!(
+        order.level_decimal(),
+        Some(Decimal::from_str("1.0815").unwrap())
+    );
+    assert_eq!(order.stop_level_decimal(), None);
+
+    let price = PricePoint {
+        bid: Some(1.1),
+        ask: Some(1.2),
+        last_traded: None,
+    };
+    let spread = price.ask_decimal().unwrap() - price.bid_decimal().unwrap();
+    assert_eq!(spread, Decimal::from_str("0.1").unwrap());
+    assert_eq!(price.last_traded_decimal(), None);
+}
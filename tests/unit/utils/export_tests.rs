@@ -0,0 +1,214 @@
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::application::models::market::{MarketData, MarketDetails};
+use ig_client::application::models::transaction::EnrichedTransaction;
+use ig_client::presentation::InstrumentType;
+use ig_client::utils::export::{
+    ExportFormat, MarketColumn, TRANSACTION_COLUMNS, escape_csv, export_markets, write_markets,
+    write_transactions,
+};
+use serde_json::{Value, json};
+
+fn create_test_market(epic: &str, name: &str, bid: Option<f64>) -> MarketData {
+    MarketData {
+        epic: epic.to_string(),
+        instrument_name: name.to_string(),
+        instrument_type: InstrumentType::Indices,
+        expiry: "DFB".to_string(),
+        high_limit_price: None,
+        low_limit_price: None,
+        market_status: "TRADEABLE".to_string(),
+        net_change: None,
+        percentage_change: None,
+        update_time: None,
+        update_time_utc: None,
+        bid,
+        offer: bid.map(|b| b + 1.0),
+    }
+}
+
+#[test]
+fn test_escape_csv() {
+    assert_eq!(escape_csv("plain"), "plain");
+    assert_eq!(escape_csv("a,b"), "\"a,b\"");
+    assert_eq!(escape_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+}
+
+#[test]
+fn test_write_markets_csv_with_selected_columns() {
+    let markets = vec![
+        create_test_market("IX.D.DAX.DAILY.IP", "Germany 40", Some(100.5)),
+        create_test_market("IX.D.FTSE.DAILY.IP", "FTSE 100, cash", None),
+    ];
+    let columns: Vec<MarketColumn> = ["epic", "instrumentName", "instrumentType", "bid"]
+        .iter()
+        .map(|c| c.parse().unwrap())
+        .collect();
+
+    let mut output = Vec::new();
+    write_markets(&mut output, ExportFormat::Csv, &markets, &columns).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "epic,instrumentName,instrumentType,bid\n\
+         IX.D.DAX.DAILY.IP,Germany 40,INDICES,100.5\n\
+         IX.D.FTSE.DAILY.IP,\"FTSE 100, cash\",INDICES,\n"
+    );
+}
+
+#[test]
+fn test_export_market_details_ndjson() {
+    let step = json!({ "unit": "POINTS", "value": 1.0 });
+    let details: MarketDetails = serde_json::from_value(json!({
+        "instrument": {
+            "epic": "IX.D.DAX.DAILY.IP",
+            "name": "Germany 40",
+            "expiry": "DFB",
+            "contractSize": "1",
+            "valueOfOnePip": "1.00"
+        },
+        "snapshot": { "marketStatus": "TRADEABLE", "bid": 100.0, "offer": 101.0, "high": 105.0 },
+        "dealingRules": {
+            "minStepDistance": step,
+            "minDealSize": step,
+            "minControlledRiskStopDistance": step,
+            "minNormalStopOrLimitDistance": step,
+            "maxStopOrLimitDistance": step,
+            "controlledRiskSpacing": step,
+            "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+            "trailingStopsPreference": "NOT_AVAILABLE"
+        }
+    }))
+    .unwrap();
+
+    let path = std::env::temp_dir().join(format!("ig_export_{}.ndjson", std::process::id()));
+    export_markets(
+        &path,
+        "jsonl".parse().unwrap(),
+        &[details.clone(), details],
+        &[MarketColumn::Epic, MarketColumn::High, MarketColumn::Low],
+    )
+    .unwrap();
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let lines: Vec<Value> = content
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[0],
+        json!({ "epic": "IX.D.DAX.DAILY.IP", "high": 105.0, "low": null })
+    );
+    assert!("parquet".parse::<ExportFormat>().is_err());
+}
+
+fn transactions() -> Vec<EnrichedTransaction> {
+    let transactions: Vec<AccountTransaction> = serde_json::from_value(json!([
+        {
+            "date": "2025-07-02",
+            "dateUtc": "2025-07-02T15:00:00",
+            "openDateUtc": "2025-07-01T09:00:00",
+            "instrumentName": "US 500 5500 CALL",
+            "period": "18-JUL-25",
+            "profitAndLoss": "E1,250.50",
+            "transactionType": "DEAL",
+            "reference": "D1",
+            "openLevel": "12.5",
+            "closeLevel": "25",
+            "size": "+1",
+            "currency": "E",
+            "cashTransaction": false
+        },
+        {
+            "date": "2025-07-03",
+            "dateUtc": "2025-07-03T08:00:00",
+            "openDateUtc": "2025-07-03T08:00:00",
+            "instrumentName": "Card Payment, Ref 42",
+            "period": "-",
+            "profitAndLoss": "E500.00",
+            "transactionType": "DEPO",
+            "reference": "P1",
+            "openLevel": "-",
+            "closeLevel": "-",
+            "size": "-",
+            "currency": "E",
+            "cashTransaction": true
+        }
+    ]))
+    .unwrap();
+    EnrichedTransaction::enrich(&transactions)
+}
+
+#[test]
+fn test_write_transactions_csv() {
+    let mut buffer = Vec::new();
+    write_transactions(&mut buffer, ExportFormat::Csv, &transactions()).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], TRANSACTION_COLUMNS.join(","));
+    assert_eq!(
+        lines[1],
+        "2025-07-02T15:00:00,2025-07-01T09:00:00,D1,DEAL,TRADE,US 500 5500 CALL,US 500,\
+         5500.0,CALL,2025-07-18,18-JUL-25,1.0,12.5,25.0,1250.5,E,false"
+    );
+    assert!(lines[2].contains(",DEPOSIT,\"Card Payment, Ref 42\","));
+    assert!(lines[2].ends_with(",,,,,500.0,E,true"));
+}
+
+#[test]
+fn test_write_transactions_ndjson() {
+    let mut buffer = Vec::new();
+    write_transactions(&mut buffer, ExportFormat::NdJson, &transactions()).unwrap();
+    let rows: Vec<Value> = String::from_utf8(buffer)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["profitAndLoss"], json!(1250.5));
+    assert_eq!(rows[0]["expiry"], json!("2025-07-18"));
+    assert_eq!(rows[1]["category"], json!("DEPOSIT"));
+    assert_eq!(rows[1]["size"], Value::Null);
+    assert_eq!(
+        rows[1].as_object().unwrap().len(),
+        TRANSACTION_COLUMNS.len()
+    );
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_export_transactions_parquet() {
+    use ig_client::utils::export::export_transactions_parquet;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+
+    let path = std::env::temp_dir().join(format!("ig_export_{}.parquet", std::process::id()));
+    export_transactions_parquet(&path, &transactions()).unwrap();
+    let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let schema = reader.metadata().file_metadata().schema_descr();
+    let names: Vec<&str> = schema
+        .columns()
+        .iter()
+        .map(|column| column.name())
+        .collect();
+    assert_eq!(names, TRANSACTION_COLUMNS);
+
+    let rows: Vec<_> = reader
+        .get_row_iter(None)
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+    assert_eq!(rows.len(), 2);
+    let fields: Vec<&Field> = rows[0].get_column_iter().map(|(_, field)| field).collect();
+    assert_eq!(fields[0], &Field::TimestampMillis(1_751_468_400_000));
+    assert_eq!(fields[4], &Field::Str("TRADE".to_string()));
+    assert_eq!(fields[9], &Field::Date(20_287));
+    assert_eq!(fields[14], &Field::Double(1250.5));
+    let fields: Vec<&Field> = rows[1].get_column_iter().map(|(_, field)| field).collect();
+    assert_eq!(fields[11], &Field::Null);
+    assert_eq!(fields[16], &Field::Bool(true));
+}
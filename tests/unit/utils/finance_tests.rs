@@ -1,6 +1,11 @@
 use ig_client::application::models::account::{Position, PositionDetails, PositionMarket};
+use ig_client::application::models::market::{DealingRules, MarketDetails};
 use ig_client::application::models::order::Direction;
-use ig_client::utils::finance::{calculate_percentage_return, calculate_pnl};
+use ig_client::error::AppError;
+use ig_client::utils::finance::{
+    calculate_margin, calculate_percentage_return, calculate_pnl, round_size_to_rules,
+};
+use serde_json::{Value, json};
 use tracing::info;
 
 #[test]
@@ -132,3 +137,126 @@ fn create_test_position(
         pnl: None,
     }
 }
+
+fn create_test_market_details(margin_factor: f64, unit: &str, bands: Value) -> MarketDetails {
+    let step = json!({ "unit": "POINTS", "value": 1.0 });
+    serde_json::from_value(json!({
+        "instrument": {
+            "epic": "IX.D.DAX.DAILY.IP",
+            "name": "Germany 40",
+            "expiry": "DFB",
+            "contractSize": "1",
+            "valueOfOnePip": "1.00",
+            "marginFactor": margin_factor,
+            "marginFactorUnit": unit,
+            "marginDepositBands": bands
+        },
+        "snapshot": { "marketStatus": "TRADEABLE" },
+        "dealingRules": {
+            "minStepDistance": step,
+            "minDealSize": step,
+            "minControlledRiskStopDistance": step,
+            "minNormalStopOrLimitDistance": step,
+            "maxStopOrLimitDistance": step,
+            "controlledRiskSpacing": step,
+            "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+            "trailingStopsPreference": "NOT_AVAILABLE"
+        }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_calculate_margin_with_deposit_bands() {
+    let details = create_test_market_details(
+        5.0,
+        "PERCENTAGE",
+        json!([
+            { "min": 0, "max": 10, "margin": 5, "currency": "EUR" },
+            { "min": 10, "max": 20, "margin": 10, "currency": "EUR" },
+            { "min": 20, "max": null, "margin": 20, "currency": "EUR" }
+        ]),
+    );
+
+    // Within the first band
+    let margin = calculate_margin(&details, 2.0, 20000.0, 1.0).unwrap();
+    assert!((margin - 2000.0).abs() < 1e-9);
+
+    // Spanning all bands: 10 @ 5%, 10 @ 10%, 5 @ 20%
+    let margin = calculate_margin(&details, 25.0, 100.0, 1.0).unwrap();
+    assert!((margin - (50.0 + 100.0 + 100.0)).abs() < 1e-9);
+
+    // Converted into the account currency
+    let margin = calculate_margin(&details, 2.0, 20000.0, 0.5).unwrap();
+    assert!((margin - 1000.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_calculate_margin_with_margin_factor() {
+    let details = create_test_market_details(10.0, "PERCENTAGE", Value::Null);
+    let margin = calculate_margin(&details, 3.0, 100.0, 1.0).unwrap();
+    assert!((margin - 30.0).abs() < 1e-9);
+
+    let details = create_test_market_details(250.0, "POINTS", Value::Null);
+    let margin = calculate_margin(&details, 2.0, 20000.0, 1.0).unwrap();
+    assert!((margin - 500.0).abs() < 1e-9);
+
+    assert!(matches!(
+        calculate_margin(&details, 0.0, 20000.0, 1.0),
+        Err(AppError::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn test_calculate_margin_applies_scaling_factor() {
+    // 1 EUR/USD contract of 100000 at 1.0850, quoted in pips, with a 3.33% margin
+    let mut details = create_test_market_details(3.33, "PERCENTAGE", Value::Null);
+    details.instrument.contract_size = "100000".to_string();
+    details.snapshot.scaling_factor = Some(10000);
+
+    let margin = calculate_margin(&details, 1.0, 10850.0, 1.0).unwrap();
+    assert!((margin - 3613.05).abs() < 1e-6);
+}
+
+fn create_test_dealing_rules(
+    min_deal_size: Option<f64>,
+    max_deal_size: Option<f64>,
+) -> DealingRules {
+    let step = json!({ "unit": "POINTS", "value": 1.0 });
+    serde_json::from_value(json!({
+        "minStepDistance": step,
+        "minDealSize": { "unit": "POINTS", "value": min_deal_size },
+        "minControlledRiskStopDistance": step,
+        "minNormalStopOrLimitDistance": step,
+        "maxStopOrLimitDistance": step,
+        "controlledRiskSpacing": step,
+        "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+        "trailingStopsPreference": "NOT_AVAILABLE",
+        "maxDealSize": max_deal_size
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_round_size_to_rules() {
+    // Floored to the precision of the minimum deal size
+    let rules = create_test_dealing_rules(Some(0.5), Some(100.0));
+    assert_eq!(round_size_to_rules(2.37, &rules).unwrap(), 2.3);
+    assert_eq!(round_size_to_rules(250.0, &rules).unwrap(), 100.0);
+    // Sizes below the minimum are rejected, not raised
+    assert!(matches!(
+        round_size_to_rules(0.3, &rules),
+        Err(AppError::InvalidInput(_))
+    ));
+
+    let rules = create_test_dealing_rules(Some(1.0), None);
+    assert_eq!(round_size_to_rules(3.99, &rules).unwrap(), 3.0);
+    assert!(round_size_to_rules(0.99, &rules).is_err());
+
+    let rules = create_test_dealing_rules(Some(0.04), None);
+    assert_eq!(round_size_to_rules(0.7, &rules).unwrap(), 0.7);
+
+    // Two decimals without a minimum deal size
+    let rules = create_test_dealing_rules(None, None);
+    assert_eq!(round_size_to_rules(1.23456, &rules).unwrap(), 1.23);
+}
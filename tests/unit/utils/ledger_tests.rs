@@ -0,0 +1,113 @@
+use ig_client::application::models::account::{AccountTransaction, Activity};
+use ig_client::application::models::order::Direction;
+use ig_client::utils::export::ExportFormat;
+use ig_client::utils::ledger::{Ledger, LedgerSource, write_ledger};
+use serde_json::json;
+
+fn activity() -> Activity {
+    serde_json::from_value(json!({
+        "date": "2025-07-01T09:00:00.000",
+        "dealId": "DEAL1",
+        "epic": "IX.D.DAX.DAILY.IP",
+        "type": "POSITION",
+        "status": "ACCEPTED",
+        "description": "Position opened: DEAL1",
+        "details": {
+            "marketName": "Germany 40",
+            "currency": "EUR",
+            "size": 1.0,
+            "direction": "BUY",
+            "level": 18000.0
+        }
+    }))
+    .unwrap()
+}
+
+fn transaction(
+    date_utc: &str,
+    transaction_type: &str,
+    amount: &str,
+    size: &str,
+) -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": "2025-07-01",
+        "dateUtc": date_utc,
+        "openDateUtc": "2025-07-01T09:00:00",
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": amount,
+        "transactionType": transaction_type,
+        "reference": "REF1",
+        "openLevel": "18000",
+        "closeLevel": "18010",
+        "size": size,
+        "currency": "E",
+        "cashTransaction": false
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_ledger_merges_chronologically() {
+    let ledger = Ledger::merge(
+        &[activity()],
+        &[
+            transaction("2025-07-02T08:00:00", "WITH", "E-0.50", "-"),
+            transaction("2025-07-01T10:00:00", "DEAL", "E1,010.00", "-1"),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(ledger.entries.len(), 3);
+    let opened = &ledger.entries[0];
+    assert_eq!(opened.source, LedgerSource::Activity);
+    assert_eq!(opened.entry_type, "POSITION");
+    assert_eq!(opened.epic.as_deref(), Some("IX.D.DAX.DAILY.IP"));
+    assert_eq!(opened.direction, Some(Direction::Buy));
+    assert_eq!(opened.size, Some(1.0));
+    assert_eq!(opened.price, Some(18000.0));
+
+    let closed = &ledger.entries[1];
+    assert_eq!(closed.source, LedgerSource::Transaction);
+    assert_eq!(closed.direction, Some(Direction::Sell));
+    assert_eq!(closed.size, Some(1.0));
+    assert_eq!(closed.price, Some(18010.0));
+    assert_eq!(closed.pnl, Some(1010.0));
+
+    let fee = &ledger.entries[2];
+    assert_eq!(fee.fees, Some(-0.5));
+    assert_eq!(fee.pnl, None);
+    assert_eq!(fee.size, None);
+
+    assert_eq!(ledger.total_pnl(), 1010.0);
+    assert_eq!(ledger.total_fees(), -0.5);
+}
+
+#[test]
+fn test_ledger_rejects_invalid_dates() {
+    assert!(Ledger::merge(&[], &[transaction("01/07/2025", "DEAL", "E1", "+1")]).is_err());
+}
+
+#[test]
+fn test_write_ledger_formats() {
+    let ledger = Ledger::merge(&[activity()], &[]).unwrap();
+
+    let mut csv = Vec::new();
+    write_ledger(&mut csv, ExportFormat::Csv, &ledger).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "dateUtc,source,type,epic,instrument,reference,direction,size,price,currency,pnl,fees,description"
+    );
+    assert_eq!(
+        lines[1],
+        "2025-07-01T09:00:00Z,ACTIVITY,POSITION,IX.D.DAX.DAILY.IP,Germany 40,DEAL1,BUY,1.0,18000.0,EUR,,,Position opened: DEAL1"
+    );
+
+    let mut ndjson = Vec::new();
+    write_ledger(&mut ndjson, ExportFormat::NdJson, &ledger).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&ndjson).unwrap();
+    assert_eq!(value["reference"], "DEAL1");
+    assert_eq!(value["pnl"], serde_json::Value::Null);
+}
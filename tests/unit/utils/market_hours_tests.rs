@@ -0,0 +1,61 @@
+use chrono::{DateTime, TimeZone, Utc};
+use ig_client::presentation::InstrumentType;
+use ig_client::utils::market_hours::{
+    MarketClass, is_likely_open, is_weekend, next_likely_open, should_poll,
+};
+
+// 2025-05-16 is a Friday
+fn utc(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 5, day, hour, minute, 0).unwrap()
+}
+
+#[test]
+fn test_weekly_schedules() {
+    assert!(!is_weekend(&utc(16, 23, 0)));
+    assert!(is_weekend(&utc(17, 12, 0)));
+
+    assert!(is_likely_open(MarketClass::Forex, &utc(16, 21, 59)));
+    assert!(!is_likely_open(MarketClass::Forex, &utc(16, 22, 0)));
+    assert!(!is_likely_open(MarketClass::Forex, &utc(18, 20, 59)));
+    assert!(is_likely_open(MarketClass::Forex, &utc(18, 21, 0)));
+    assert!(is_likely_open(MarketClass::Forex, &utc(14, 3, 0)));
+
+    assert!(!is_likely_open(MarketClass::Indices, &utc(16, 21, 30)));
+    assert!(is_likely_open(MarketClass::Indices, &utc(18, 23, 0)));
+
+    assert!(is_likely_open(MarketClass::Shares, &utc(15, 14, 0)));
+    assert!(!is_likely_open(MarketClass::Shares, &utc(15, 22, 0)));
+    assert!(!is_likely_open(MarketClass::Shares, &utc(17, 14, 0)));
+
+    assert!(!is_likely_open(MarketClass::Other, &utc(18, 14, 0)));
+}
+
+#[test]
+fn test_next_likely_open_and_should_poll() {
+    assert_eq!(
+        next_likely_open(MarketClass::Forex, &utc(17, 10, 30)),
+        utc(18, 21, 0)
+    );
+    assert_eq!(
+        next_likely_open(MarketClass::Shares, &utc(15, 22, 0)),
+        utc(16, 7, 0)
+    );
+    let open = utc(15, 12, 0);
+    assert_eq!(next_likely_open(MarketClass::Shares, &open), open);
+
+    assert_eq!(
+        MarketClass::from(InstrumentType::OptIndices),
+        MarketClass::Indices
+    );
+    assert!(should_poll(
+        MarketClass::Shares,
+        Some("TRADEABLE"),
+        &utc(17, 14, 0)
+    ));
+    assert!(!should_poll(
+        MarketClass::Shares,
+        Some("CLOSED"),
+        &utc(17, 14, 0)
+    ));
+    assert!(should_poll(MarketClass::Shares, None, &utc(15, 14, 0)));
+}
@@ -1,5 +1,17 @@
+mod atomic_file_tests;
+mod currency_tests;
+#[cfg(feature = "decimal")]
+mod decimal_tests;
 mod display_tests;
+mod export_tests;
 mod finance_tests;
+mod ledger_tests;
+mod market_hours_tests;
+mod money_tests;
 mod parsing_tests;
+mod performance_tests;
+mod periodic_tests;
 mod rate_limiter_tests;
+mod tax_report_tests;
 mod tools_tests;
+mod trade_matching_tests;
@@ -0,0 +1,85 @@
+use ig_client::application::models::account::{Account, AccountTransaction, Position};
+use ig_client::utils::currency::CurrencyConverter;
+use ig_client::utils::finance::calculate_pnl_money;
+use ig_client::utils::money::Money;
+use serde_json::json;
+
+#[test]
+fn test_money_checked_arithmetic() {
+    let eur = Money::new(10.0, "EUR");
+    assert_eq!(
+        eur.checked_sub(&Money::new(4.0, "EUR")).unwrap(),
+        Money::new(6.0, "EUR")
+    );
+    assert!(eur.checked_sub(&Money::new(4.0, "USD")).is_err());
+    assert_eq!(eur.scale(1.5), Money::new(15.0, "EUR"));
+    assert_eq!(-eur.clone(), Money::new(-10.0, "EUR"));
+    assert_eq!(eur.to_string(), "10.00 EUR");
+
+    let amounts = [Money::new(1.0, "EUR"), Money::new(2.0, "EUR")];
+    assert_eq!(Money::sum("EUR", &amounts).unwrap(), Money::new(3.0, "EUR"));
+    assert_eq!(Money::sum("USD", &[]).unwrap(), Money::zero("USD"));
+    assert!(Money::sum("USD", &amounts).is_err());
+}
+
+#[test]
+fn test_money_conversion() {
+    let converter = CurrencyConverter::new("EUR").with_rate("USD", 0.5);
+    let usd = Money::new(10.0, "USD");
+    assert_eq!(usd.to_base(&converter).unwrap(), Money::new(5.0, "EUR"));
+    assert_eq!(
+        Money::new(5.0, "EUR").convert(&converter, "USD").unwrap(),
+        usd
+    );
+    assert!(Money::new(1.0, "GBP").to_base(&converter).is_err());
+}
+
+#[test]
+fn test_money_accessors() {
+    let account: Account = serde_json::from_value(json!({
+        "accountId": "ACC123",
+        "accountName": "Demo",
+        "accountType": "CFD",
+        "balance": { "balance": 1000.0, "deposit": 200.0, "profitLoss": -25.0, "available": 775.0 },
+        "currency": "EUR",
+        "status": "ENABLED",
+        "preferred": true
+    }))
+    .unwrap();
+    let equity = account
+        .balance
+        .balance
+        .checked_add(&account.balance.profit_loss)
+        .unwrap();
+    assert_eq!(equity, Money::new(975.0, "EUR"));
+    assert_eq!(account.balance.deposit.amount, 200.0);
+    assert_eq!(account.balance.available.currency, "EUR");
+
+    let transaction: AccountTransaction = serde_json::from_value(json!({
+        "date": "2025-07-01",
+        "dateUtc": "2025-07-01T10:00:00",
+        "openDateUtc": "2025-07-01T09:00:00",
+        "instrumentName": "Germany 40",
+        "period": "-",
+        "profitAndLoss": "E-12.50",
+        "transactionType": "DEAL",
+        "reference": "REF1",
+        "openLevel": "18000",
+        "closeLevel": "17987.5",
+        "size": "+1",
+        "currency": "EUR",
+        "cashTransaction": false
+    }))
+    .unwrap();
+    assert_eq!(transaction.pnl_money(), Some(Money::new(-12.5, "EUR")));
+
+    let position: Position = serde_json::from_str(
+        &std::fs::read_to_string("tests/unit/application/models/position.json").unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        position.unrealized_pnl_money(62.2, 68.2),
+        Money::new(-6.0, "EUR")
+    );
+    assert_eq!(calculate_pnl_money(&position).unwrap().currency, "EUR");
+}
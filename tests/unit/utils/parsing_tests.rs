@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod tests {
-    use ig_client::utils::parsing::{ParsedOptionInfo, normalize_text, parse_instrument_name};
+    use ig_client::utils::parsing::{
+        ParsedOptionInfo, normalize_text, parse_exchange_rate, parse_instrument_name,
+    };
 
     #[test]
     fn test_normalize_text() {
@@ -103,4 +105,71 @@ mod tests {
         assert_eq!(info.strike, Some("18500".to_string()));
         assert_eq!(info.option_type, Some("CALL".to_string()));
     }
+
+    #[test]
+    fn test_name_similarity() {
+        use ig_client::utils::parsing::name_similarity;
+
+        assert_eq!(name_similarity("FTSE 100", "FTSE 100"), 1.0);
+        assert_eq!(name_similarity("Japón 225", "japan-225"), 1.0);
+        assert_eq!(name_similarity("", "FTSE 100"), 0.0);
+        assert!(name_similarity("100 FTSE", "FTSE 100") >= 0.5);
+
+        let daily = name_similarity("FTSE 100 daily", "FTSE 100 Daily Funded Bet");
+        let other = name_similarity("FTSE 100 daily", "Germany 40");
+        assert!(daily > other);
+        assert!((0.0..=1.0).contains(&daily));
+    }
+
+    #[test]
+    fn test_parse_expiry() {
+        use chrono::NaiveDate;
+        use ig_client::utils::parsing::parse_expiry;
+
+        assert_eq!(
+            parse_expiry("22-MAY-25"),
+            NaiveDate::from_ymd_opt(2025, 5, 22)
+        );
+        assert_eq!(
+            parse_expiry(" 01-jan-26 "),
+            NaiveDate::from_ymd_opt(2026, 1, 1)
+        );
+        assert_eq!(parse_expiry("DEC-25"), NaiveDate::from_ymd_opt(2025, 12, 1));
+        assert_eq!(parse_expiry("-"), None);
+        assert_eq!(parse_expiry("DFB"), None);
+        assert_eq!(parse_expiry("31-FEB-25"), None);
+    }
+
+    #[test]
+    fn test_parse_option_epic() {
+        use ig_client::utils::parsing::parse_option_epic;
+
+        let parsed = parse_option_epic("OP.D.OTCDAX1.021100P.IP").unwrap();
+        assert_eq!(parsed.underlying_code, "DAX");
+        assert_eq!(parsed.strike.as_deref(), Some("21100"));
+        assert_eq!(parsed.option_type.as_deref(), Some("PUT"));
+        assert!(parsed.matches_underlying_epic("IX.D.DAX.IFD.IP"));
+        assert!(!parsed.matches_underlying_epic("IX.D.FTSE.DAILY.IP"));
+
+        let daily = parse_option_epic("DO.D.OTCDDAX.1.IP").unwrap();
+        assert_eq!(daily.underlying_code, "DDAX");
+        assert_eq!(daily.strike, None);
+        assert_eq!(daily.option_type, None);
+        assert!(daily.matches_underlying_epic("IX.D.DAX.DAILY.IP"));
+
+        assert_eq!(parse_option_epic("CS.D.EURUSD.CFD.IP"), None);
+        assert_eq!(parse_option_epic("OP.D"), None);
+    }
+
+    #[test]
+    fn test_parse_exchange_rate() {
+        assert_eq!(parse_exchange_rate("Tipo de cambio 0.93"), Some(0.93));
+        assert_eq!(
+            parse_exchange_rate("US 500 (tipo de cambio: 0,9312)"),
+            Some(0.9312)
+        );
+        assert_eq!(parse_exchange_rate("FX Rate 1.25 Germany 40"), Some(1.25));
+        assert_eq!(parse_exchange_rate("Tipo de cambio 0"), None);
+        assert_eq!(parse_exchange_rate("US Tech 100 19200 CALL"), None);
+    }
 }
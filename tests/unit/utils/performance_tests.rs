@@ -0,0 +1,106 @@
+use chrono::{TimeZone, Utc};
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::utils::performance::{PerformanceReport, max_drawdown};
+
+fn transaction(
+    date_utc: &str,
+    transaction_type: &str,
+    name: &str,
+    amount: &str,
+) -> AccountTransaction {
+    AccountTransaction {
+        date: date_utc.to_string(),
+        date_utc: date_utc.to_string(),
+        open_date_utc: date_utc.to_string(),
+        instrument_name: name.to_string(),
+        period: "-".to_string(),
+        profit_and_loss: amount.to_string(),
+        transaction_type: transaction_type.to_string(),
+        reference: "REF".to_string(),
+        open_level: "0".to_string(),
+        close_level: "0".to_string(),
+        size: "+1".to_string(),
+        currency: "E".to_string(),
+        cash_transaction: transaction_type != "DEAL",
+    }
+}
+
+#[test]
+fn test_performance_report_statistics() {
+    let transactions = vec![
+        transaction("2025-05-03T10:00:00", "DEAL", "Germany 40", "E-150.00"),
+        transaction("2025-05-01T10:00:00", "DEAL", "Germany 40", "E100.00"),
+        transaction("2025-05-02T10:00:00", "DEPO", "Card Payment", "E5000.00"),
+        transaction("2025-05-02T11:00:00", "DEAL", "US 500", "E200.00"),
+        transaction("2025-05-04T10:00:00", "WITH", "Daily Admin Fee", "E-0.50"),
+        transaction("2025-05-05T10:00:00", "DEAL", "US 500", "E50.00"),
+    ];
+
+    let report = PerformanceReport::from_transactions(&transactions, 1000.0).unwrap();
+    let equity: Vec<f64> = report
+        .equity_curve
+        .iter()
+        .map(|(_, equity)| *equity)
+        .collect();
+    assert_eq!(equity, vec![1100.0, 1300.0, 1150.0, 1149.5, 1199.5]);
+    assert_eq!(report.trades, 4);
+    assert_eq!(report.wins, 3);
+    assert_eq!(report.losses, 1);
+    assert_eq!(report.win_rate(), Some(0.75));
+    assert_eq!(report.gross_profit, 350.0);
+    assert_eq!(report.gross_loss, 150.0);
+    assert!((report.profit_factor().unwrap() - 350.0 / 150.0).abs() < 1e-9);
+    assert_eq!(report.average_trade(), Some(50.0));
+    assert!((report.average_win().unwrap() - 350.0 / 3.0).abs() < 1e-9);
+    assert_eq!(report.average_loss(), Some(150.0));
+    assert_eq!(report.largest_win, 200.0);
+    assert_eq!(report.largest_loss, 150.0);
+    assert_eq!(report.costs, -0.5);
+    assert_eq!(report.net_pnl(), 199.5);
+
+    let drawdown = report.max_drawdown.unwrap();
+    assert_eq!(drawdown.peak_equity, 1300.0);
+    assert_eq!(drawdown.trough_equity, 1149.5);
+}
+
+#[test]
+fn test_performance_report_edge_cases() {
+    let empty = PerformanceReport::from_transactions(&[], 1000.0).unwrap();
+    assert_eq!(empty.trades, 0);
+    assert_eq!(empty.win_rate(), None);
+    assert_eq!(empty.profit_factor(), None);
+    assert!(empty.max_drawdown.is_none());
+
+    // A first losing trade is a drawdown from the starting equity
+    let losing = PerformanceReport::from_transactions(
+        &[transaction(
+            "2025-05-01T10:00:00",
+            "DEAL",
+            "Germany 40",
+            "E-10.00",
+        )],
+        1000.0,
+    )
+    .unwrap();
+    assert_eq!(losing.max_drawdown.unwrap().amount(), 10.0);
+    assert_eq!(losing.profit_factor(), Some(0.0));
+
+    assert!(
+        PerformanceReport::from_transactions(
+            &[transaction("01/05/2025", "DEAL", "Germany 40", "E1.00")],
+            0.0
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_max_drawdown_of_curve() {
+    let at = |day: u32| Utc.with_ymd_and_hms(2025, 5, day, 0, 0, 0).unwrap();
+    let drawdown =
+        max_drawdown(&[(at(1), 100.0), (at(2), 80.0), (at(3), 120.0), (at(4), 90.0)]).unwrap();
+    assert_eq!(drawdown.peak_time, at(3));
+    assert_eq!(drawdown.amount(), 30.0);
+    assert_eq!(drawdown.percent(), Some(25.0));
+    assert!(max_drawdown(&[(at(1), 100.0), (at(2), 110.0)]).is_none());
+}
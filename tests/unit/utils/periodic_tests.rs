@@ -0,0 +1,57 @@
+use ig_client::error::AppError;
+use ig_client::utils::periodic::{PeriodicTask, ShutdownToken};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+#[tokio::test]
+async fn test_periodic_task_retries_failures_until_shut_down() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let shutdown = ShutdownToken::new();
+    let task = {
+        let runs = runs.clone();
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            PeriodicTask::new("count", Duration::from_millis(5))
+                .run(&shutdown, || async {
+                    // Every other run fails, which does not stop the task
+                    match runs.fetch_add(1, Ordering::SeqCst) % 2 {
+                        0 => Err(AppError::RateLimitExceeded),
+                        _ => Ok(()),
+                    }
+                })
+                .await
+        })
+    };
+
+    while runs.load(Ordering::SeqCst) < 4 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), task)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(shutdown.is_cancelled());
+}
+
+#[tokio::test]
+async fn test_periodic_task_wakes_before_the_interval() {
+    let runs = AtomicUsize::new(0);
+    let wake = Notify::new();
+    let shutdown = ShutdownToken::new();
+    let task = PeriodicTask::new("count", Duration::from_secs(3600))
+        .with_wake(&wake)
+        .run(&shutdown, || async {
+            if runs.fetch_add(1, Ordering::SeqCst) == 1 {
+                shutdown.cancel();
+            }
+            Ok(())
+        });
+    wake.notify_one();
+    tokio::time::timeout(Duration::from_secs(5), task)
+        .await
+        .unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
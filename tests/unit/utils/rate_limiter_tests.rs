@@ -1,8 +1,13 @@
 // Tests for the rate limiter module
+#![allow(deprecated)]
+use ig_client::error::AppError;
+use ig_client::session::interface::IgSession;
 use ig_client::utils::rate_limiter::{
-    RateLimitType, RateLimiter, account_non_trading_limiter, account_trading_limiter,
-    app_non_trading_limiter, global_rate_limiter, historical_price_limiter,
+    RateLimitStrategy, RateLimitType, RateLimiter, RateLimiterRegistry, RateLimiterSnapshot,
+    account_non_trading_limiter, account_trading_limiter, app_non_trading_limiter,
+    global_rate_limiter, historical_price_limiter, wait_if_set,
 };
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio_test::block_on;
@@ -111,4 +116,347 @@ mod tests {
         let global2 = global_rate_limiter();
         assert!(Arc::ptr_eq(&global1, &global2));
     }
+
+    #[test]
+    fn test_rate_limiter_strategy() {
+        let limiter = RateLimiter::new(RateLimitType::NonTradingAccount);
+        assert_eq!(limiter.strategy(), RateLimitStrategy::SlidingWindow);
+
+        // The strategy survives a change of safety margin
+        let mut limiter = limiter.with_strategy(RateLimitStrategy::TokenBucket);
+        let limiter = limiter.with_safety_margin(0.5);
+        assert_eq!(limiter.strategy(), RateLimitStrategy::TokenBucket);
+        assert_eq!(limiter.effective_limit(), 15);
+    }
+
+    #[test]
+    fn test_token_bucket_allows_bursts_up_to_a_tenth_of_the_limit() {
+        let limiter = RateLimiter::new(RateLimitType::NonTradingApp)
+            .with_strategy(RateLimitStrategy::TokenBucket);
+
+        // A full bucket lets 6 requests through without waiting
+        let start = Instant::now();
+        for _ in 0..6 {
+            block_on(limiter.wait());
+        }
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        // The next one waits for a token, 54 of them refilled per minute
+        let wait = block_on(limiter.time_until_next_request_ms());
+        assert!((900..=1_112).contains(&wait), "waiting {wait}ms");
+
+        let stats = block_on(limiter.get_stats());
+        assert_eq!(stats.strategy, RateLimitStrategy::TokenBucket);
+        assert_eq!(stats.request_count, 6);
+        assert_eq!(stats.effective_limit, 60);
+    }
+
+    #[test]
+    fn test_token_bucket_refills_over_time() {
+        let limiter = RateLimiter::new(RateLimitType::OnePerSecond)
+            .with_strategy(RateLimitStrategy::TokenBucket);
+        block_on(limiter.wait());
+        assert!(block_on(limiter.time_until_next_request_ms()) > 900);
+
+        std::thread::sleep(Duration::from_millis(300));
+        let wait = block_on(limiter.time_until_next_request_ms());
+        assert!(wait <= 700, "waiting {wait}ms");
+
+        // The request that takes the missing token waits for it
+        let start = Instant::now();
+        block_on(limiter.wait());
+        assert!(start.elapsed() >= Duration::from_millis(wait.saturating_sub(50)));
+        assert_eq!(block_on(limiter.current_request_count()), 1);
+    }
+
+    #[test]
+    fn test_wait_if_set() {
+        // Nothing to wait on without a limiter
+        block_on(wait_if_set(None));
+
+        let limiter = RateLimiter::new(RateLimitType::NonTradingAccount);
+        block_on(wait_if_set(Some(&limiter)));
+        assert_eq!(block_on(limiter.current_request_count()), 1);
+    }
+
+    #[test]
+    fn test_token_bucket_cooldown_after_rate_limit_error() {
+        let limiter = RateLimiter::new(RateLimitType::TradingAccount)
+            .with_strategy(RateLimitStrategy::TokenBucket);
+        block_on(limiter.notify_rate_limit_exceeded());
+
+        // The next request waits for a whole window, then for its token
+        let wait = block_on(limiter.time_until_next_request_ms());
+        assert!((60_000..=60_700).contains(&wait), "waiting {wait}ms");
+        assert_eq!(block_on(limiter.current_request_count()), 10);
+    }
+
+    #[test]
+    fn test_token_bucket_worst_case_window() {
+        for limit_type in [
+            RateLimitType::NonTradingApp,
+            RateLimitType::TradingAccount,
+            RateLimitType::NonTradingAccount,
+            RateLimitType::OnePerSecond,
+        ] {
+            let limiter =
+                RateLimiter::new(limit_type).with_strategy(RateLimitStrategy::TokenBucket);
+            let limit = limiter.effective_limit();
+            let window = limit_type.time_window_ms();
+
+            // Starting from a full bucket, the requests admitted within one window,
+            // including every token refilled during it, never exceed the limit
+            block_on(limiter.consume(limit));
+            let wait = block_on(limiter.time_until_next_request_ms());
+            assert!(wait >= window, "{limit_type:?}: waiting {wait}ms");
+            // and the refill sustains nearly the whole limit
+            assert!(
+                wait <= window + window / 10,
+                "{limit_type:?}: waiting {wait}ms"
+            );
+        }
+    }
+
+    #[test]
+    fn test_registry_limiters_by_credential() {
+        let registry = RateLimiterRegistry::new().with_strategy(RateLimitStrategy::TokenBucket);
+        let first = registry.for_account("KEY1", "ACC1");
+        let again = registry.for_account("KEY1", "ACC1");
+        let other_account = registry.for_account("KEY1", "ACC2");
+        let other_key = registry.for_account("KEY2", "ACC1");
+
+        // Account limits and the price allowance follow the account, app limits the
+        // API key
+        assert!(Arc::ptr_eq(&first.account_trading, &again.account_trading));
+        assert!(Arc::ptr_eq(&first.app_non_trading, &again.app_non_trading));
+        assert!(!Arc::ptr_eq(
+            &first.account_non_trading,
+            &other_account.account_non_trading
+        ));
+        assert!(!Arc::ptr_eq(
+            &first.historical_price,
+            &other_account.historical_price
+        ));
+        assert!(Arc::ptr_eq(
+            &first.account_trading,
+            &other_key.account_trading
+        ));
+        assert!(Arc::ptr_eq(
+            &first.historical_price,
+            &other_key.historical_price
+        ));
+        assert!(!Arc::ptr_eq(
+            &first.app_non_trading,
+            &other_key.app_non_trading
+        ));
+        assert!(Arc::ptr_eq(
+            &registry.app_non_trading("KEY2"),
+            &other_key.app_non_trading
+        ));
+        // Without an API key, nothing tells two applications apart
+        assert!(!Arc::ptr_eq(
+            &registry.for_account("", "ACC1").app_non_trading,
+            &registry.for_account("", "ACC2").app_non_trading
+        ));
+
+        assert_eq!(first.account_trading.effective_limit(), 80);
+        assert_eq!(
+            first.historical_price.strategy(),
+            RateLimitStrategy::TokenBucket
+        );
+    }
+
+    #[test]
+    fn test_sessions_of_different_accounts_do_not_share_limits() {
+        let first = IgSession::new("CST".to_string(), "XST".to_string(), "LIM1".to_string());
+        let second = IgSession::new("CST".to_string(), "XST".to_string(), "LIM2".to_string());
+        block_on(first.rate_limiters().account_non_trading.wait());
+
+        let first_stats = block_on(first.rate_limiters().account_non_trading.get_stats());
+        let second_stats = block_on(second.rate_limiters().account_non_trading.get_stats());
+        assert_eq!(first_stats.request_count, 1);
+        assert_eq!(second_stats.request_count, 0);
+        assert!(!Arc::ptr_eq(
+            &first.rate_limiters().account_non_trading,
+            &account_non_trading_limiter()
+        ));
+    }
+
+    #[test]
+    fn test_sessions_share_the_app_limiter_of_their_api_key() {
+        let session = |account_id: &str| {
+            IgSession::new("CST".to_string(), "XST".to_string(), account_id.to_string())
+        };
+        assert!(!Arc::ptr_eq(
+            &session("KEYLESS1").rate_limiters().app_non_trading,
+            &session("KEYLESS2").rate_limiters().app_non_trading
+        ));
+
+        let first = session("APP1").with_api_key("SHARED_KEY");
+        let second = session("APP2").with_api_key("SHARED_KEY");
+        assert_eq!(first.api_key, "SHARED_KEY");
+        assert!(Arc::ptr_eq(
+            &first.rate_limiters().app_non_trading,
+            &second.rate_limiters().app_non_trading
+        ));
+        assert!(!Arc::ptr_eq(
+            &first.rate_limiters().historical_price,
+            &second.rate_limiters().historical_price
+        ));
+    }
+
+    fn state_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("ig_rate_limiter_{}", std::process::id()))
+            .join(name)
+    }
+
+    #[test]
+    fn test_rate_limiter_state_survives_restart() {
+        let path = state_path("historical.json");
+        let limiter = RateLimiter::new(RateLimitType::HistoricalPrice);
+        for _ in 0..3 {
+            block_on(limiter.wait());
+        }
+        block_on(limiter.save_state(&path)).unwrap();
+
+        let restarted = RateLimiter::new(RateLimitType::HistoricalPrice);
+        assert!(block_on(restarted.load_state(&path)).unwrap());
+        assert_eq!(block_on(restarted.current_request_count()), 3);
+
+        // A limiter of another kind refuses the state
+        let other = RateLimiter::new(RateLimitType::HistoricalPrice)
+            .with_strategy(RateLimitStrategy::TokenBucket);
+        assert!(matches!(
+            block_on(other.load_state(&path)),
+            Err(AppError::InvalidInput(_))
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        // Nothing to restore on the first start
+        assert!(!block_on(restarted.load_state(&state_path("missing.json"))).unwrap());
+    }
+
+    #[test]
+    fn test_token_bucket_state_keeps_cooldown() {
+        let path = state_path("trading.json");
+        let limiter = RateLimiter::new(RateLimitType::TradingAccount)
+            .with_strategy(RateLimitStrategy::TokenBucket);
+        block_on(limiter.notify_rate_limit_exceeded());
+        block_on(limiter.save_state(&path)).unwrap();
+
+        let restarted = RateLimiter::new(RateLimitType::TradingAccount)
+            .with_strategy(RateLimitStrategy::TokenBucket);
+        assert!(block_on(restarted.load_state(&path)).unwrap());
+        let wait = block_on(restarted.time_until_next_request_ms());
+        assert!(wait > 59_000, "waiting {wait}ms");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_restore_drops_requests_out_of_the_window() {
+        let now = chrono::Utc::now();
+        let snapshot = RateLimiterSnapshot {
+            limit_type: RateLimitType::NonTradingAccount,
+            strategy: RateLimitStrategy::SlidingWindow,
+            saved_at: now - chrono::Duration::seconds(30),
+            requests: vec![
+                now - chrono::Duration::seconds(90),
+                now - chrono::Duration::seconds(45),
+                now - chrono::Duration::seconds(31),
+            ],
+            points: vec![1, 2, 3],
+            tokens: None,
+            refilled_at: None,
+        };
+        let limiter = RateLimiter::new(RateLimitType::NonTradingAccount);
+        block_on(limiter.restore(&snapshot)).unwrap();
+        assert_eq!(block_on(limiter.current_request_count()), 5);
+
+        let saved = block_on(limiter.snapshot());
+        assert_eq!(saved.requests.len(), 2);
+        assert!(saved.requests[0] < saved.requests[1]);
+        assert_eq!(saved.points, [2, 3]);
+    }
+
+    #[test]
+    fn test_restore_drops_requests_older_than_the_monotonic_clock() {
+        // Requests older than the uptime, e.g. saved before a reboot, are dropped by
+        // their age rather than counted as made now
+        let now = chrono::Utc::now();
+        let snapshot = RateLimiterSnapshot {
+            limit_type: RateLimitType::HistoricalPrice,
+            strategy: RateLimitStrategy::SlidingWindow,
+            saved_at: now - chrono::Duration::days(7),
+            requests: vec![
+                now - chrono::Duration::days(3650),
+                now - chrono::Duration::days(1),
+            ],
+            points: vec![9_000, 100],
+            tokens: None,
+            refilled_at: None,
+        };
+        let limiter = RateLimiter::new(RateLimitType::HistoricalPrice);
+        block_on(limiter.restore(&snapshot)).unwrap();
+        assert_eq!(block_on(limiter.current_request_count()), 100);
+
+        // A bucket is refilled for the time elapsed since its last refill
+        let snapshot = RateLimiterSnapshot {
+            limit_type: RateLimitType::TradingAccount,
+            strategy: RateLimitStrategy::TokenBucket,
+            saved_at: now - chrono::Duration::days(3650),
+            requests: Vec::new(),
+            points: Vec::new(),
+            tokens: Some(-50.0),
+            refilled_at: Some(now - chrono::Duration::days(3650)),
+        };
+        let limiter = RateLimiter::new(RateLimitType::TradingAccount)
+            .with_strategy(RateLimitStrategy::TokenBucket);
+        block_on(limiter.restore(&snapshot)).unwrap();
+        assert_eq!(block_on(limiter.current_request_count()), 0);
+    }
+
+    #[test]
+    fn test_consume_counts_points() {
+        let limiter = RateLimiter::new(RateLimitType::HistoricalPrice);
+        block_on(limiter.consume(500));
+        block_on(limiter.consume(0));
+        block_on(limiter.wait());
+        assert_eq!(block_on(limiter.current_request_count()), 501);
+        assert_eq!(block_on(limiter.snapshot()).points, [500, 1]);
+        assert_eq!(block_on(limiter.time_until_next_request_ms()), 0);
+
+        // Once the weekly allowance is spent, the next request waits for it
+        block_on(limiter.consume(9_500));
+        let stats = block_on(limiter.get_stats());
+        assert_eq!(stats.request_count, 10_001);
+        assert!(stats.usage_percent > 100.0);
+        assert!(block_on(limiter.time_until_next_request_ms()) > 600_000_000);
+    }
+
+    #[test]
+    fn test_sync_usage_never_lowers_the_count() {
+        let limiter = RateLimiter::new(RateLimitType::HistoricalPrice);
+        block_on(limiter.consume(300));
+        block_on(limiter.sync_usage(100, Duration::from_secs(3_600)));
+        assert_eq!(block_on(limiter.current_request_count()), 300);
+
+        // Points used elsewhere expire when IG resets the allowance
+        block_on(limiter.sync_usage(500, Duration::from_secs(3_600)));
+        assert_eq!(block_on(limiter.current_request_count()), 500);
+        let snapshot = block_on(limiter.snapshot());
+        assert_eq!(snapshot.points, [200, 300]);
+        assert!(snapshot.requests[0] < snapshot.requests[1]);
+    }
+
+    #[test]
+    fn test_token_bucket_consumes_points() {
+        let limiter = RateLimiter::new(RateLimitType::NonTradingApp)
+            .with_strategy(RateLimitStrategy::TokenBucket);
+        block_on(limiter.consume(30));
+        assert_eq!(block_on(limiter.current_request_count()), 30);
+        // 24 tokens of debt plus the next token, 54 of them refilled per minute
+        let wait = block_on(limiter.time_until_next_request_ms());
+        assert!((27_000..=27_780).contains(&wait), "waiting {wait}ms");
+    }
 }
@@ -0,0 +1,110 @@
+use chrono::NaiveDate;
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::application::models::transaction::{EnrichedTransaction, TransactionCategory};
+use ig_client::utils::tax_report::{TaxReport, TaxYearStart};
+use serde_json::json;
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn transaction(date: &str, kind: &str, instrument: &str, pnl: &str) -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": &date[..10],
+        "dateUtc": date,
+        "openDateUtc": date,
+        "instrumentName": instrument,
+        "period": "-",
+        "profitAndLoss": pnl,
+        "transactionType": kind,
+        "reference": format!("R{}", &date[..10]),
+        "openLevel": "-",
+        "closeLevel": "-",
+        "size": "-",
+        "currency": "E",
+        "cashTransaction": kind != "DEAL"
+    }))
+    .unwrap()
+}
+
+fn history() -> Vec<EnrichedTransaction> {
+    EnrichedTransaction::enrich(&[
+        transaction("2024-04-05T16:00:00", "DEAL", "Germany 40", "E100.00"),
+        transaction("2024-04-06T09:00:00", "DEAL", "Germany 40", "E-40.00"),
+        transaction(
+            "2024-04-06T22:00:00",
+            "WITH",
+            "Funding Adjustment",
+            "E-2.50",
+        ),
+        transaction("2025-01-10T10:00:00", "WITH", "Daily Admin Fee", "E-1.00"),
+        transaction("2025-01-10T12:00:00", "DEPO", "Card Payment", "E500.00"),
+        transaction("2025-05-01T12:00:00", "DEAL", "US 500", "E30.00"),
+    ])
+}
+
+#[test]
+fn test_tax_year_boundaries() {
+    let uk = TaxYearStart::Uk.tax_year_of(date(2025, 4, 5));
+    assert_eq!(uk.start, date(2024, 4, 6));
+    assert_eq!(uk.end, date(2025, 4, 6));
+    assert_eq!(uk.to_string(), "2024/25");
+    assert_eq!(
+        TaxYearStart::Uk.tax_year_of(date(2025, 4, 6)).to_string(),
+        "2025/26"
+    );
+
+    let calendar = TaxYearStart::Calendar.tax_year_of(date(2025, 12, 31));
+    assert_eq!(calendar.start, date(2025, 1, 1));
+    assert_eq!(calendar.to_string(), "2025");
+
+    let custom = TaxYearStart::custom(7, 1).unwrap();
+    let TaxYearStart::Custom(month_day) = custom else {
+        panic!("expected a custom start");
+    };
+    assert_eq!((month_day.month(), month_day.day()), (7, 1));
+    assert_eq!(
+        custom.tax_year_of(date(2025, 6, 30)).start,
+        date(2024, 7, 1)
+    );
+    assert!(TaxYearStart::custom(2, 29).is_err());
+    assert!(TaxYearStart::custom(13, 1).is_err());
+}
+
+#[test]
+fn test_uk_report() {
+    let report = TaxReport::from_transactions(&history(), TaxYearStart::Uk).unwrap();
+    assert_eq!(report.years.len(), 3);
+    assert_eq!(report.years[0].tax_year.to_string(), "2023/24");
+    assert_eq!(report.years[0].realized_pnl, 100.0);
+
+    let year = report.year(date(2024, 12, 1)).unwrap();
+    assert_eq!(year.realized_pnl, -40.0);
+    assert_eq!(year.trades, 1);
+    assert_eq!(year.funding, -2.5);
+    assert_eq!(year.fees, -1.0);
+    assert_eq!(year.net(), -43.5);
+
+    // The deposit is left out of the details
+    assert_eq!(report.details.len(), 5);
+    let details: Vec<_> = report.details_of(year.tax_year).collect();
+    assert_eq!(details.len(), 3);
+    assert_eq!(details[1].category, TransactionCategory::Funding);
+    assert_eq!(details[1].amount, -2.5);
+}
+
+#[test]
+fn test_calendar_report() {
+    let report = TaxReport::from_transactions(&history(), TaxYearStart::Calendar).unwrap();
+    assert_eq!(report.years.len(), 2);
+    assert_eq!(report.years[0].realized_pnl, 60.0);
+    assert_eq!(report.years[0].trades, 2);
+    assert_eq!(report.years[1].net(), 29.0);
+}
+
+#[test]
+fn test_report_rejects_invalid_date() {
+    let transactions =
+        EnrichedTransaction::enrich(&[transaction("not a date", "DEAL", "Germany 40", "E1.00")]);
+    assert!(TaxReport::from_transactions(&transactions, TaxYearStart::Uk).is_err());
+}
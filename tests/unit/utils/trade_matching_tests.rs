@@ -0,0 +1,125 @@
+use chrono::{Duration, TimeZone, Utc};
+use ig_client::application::models::account::AccountTransaction;
+use ig_client::application::models::order::Direction;
+use ig_client::application::models::transaction::EnrichedTransaction;
+use ig_client::utils::trade_matching::{
+    Fill, MatchingMethod, fills_from_transactions, match_fills,
+};
+use serde_json::json;
+
+fn fill(day: u32, size: f64, price: f64, fees: f64, reference: &str) -> Fill {
+    Fill {
+        date: Utc.with_ymd_and_hms(2025, 7, day, 10, 0, 0).unwrap(),
+        instrument: "Germany 40".to_string(),
+        size,
+        price,
+        fees,
+        reference: reference.to_string(),
+    }
+}
+
+fn fills() -> Vec<Fill> {
+    vec![
+        fill(1, 2.0, 100.0, 2.0, "B1"),
+        fill(2, 1.0, 110.0, 0.0, "B2"),
+        fill(3, -2.0, 120.0, 1.0, "S1"),
+        // Closes the last long and opens a short of 1
+        fill(4, -2.0, 105.0, 0.0, "S2"),
+    ]
+}
+
+#[test]
+fn test_fifo_matching() {
+    let result = match_fills(&fills(), MatchingMethod::Fifo);
+    let trips = &result.round_trips;
+    assert_eq!(trips.len(), 2);
+    assert_eq!(trips[0].open_reference, "B1");
+    assert_eq!(trips[0].close_reference, "S1");
+    assert_eq!(trips[0].size, 2.0);
+    assert_eq!(trips[0].pnl, 40.0);
+    assert_eq!(trips[0].fees, 3.0);
+    assert_eq!(trips[0].net_pnl(), 37.0);
+    assert_eq!(trips[0].holding_period(), Duration::days(2));
+    assert_eq!(trips[1].open_reference, "B2");
+    assert_eq!(trips[1].pnl, -5.0);
+
+    let open = &result.open_lots["Germany 40"];
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].size, -1.0);
+    assert_eq!(open[0].fill.reference, "S2");
+    assert_eq!(result.total_pnl(), 32.0);
+}
+
+#[test]
+fn test_lifo_matching() {
+    let result = match_fills(&fills(), MatchingMethod::Lifo);
+    let trips = &result.round_trips;
+    assert_eq!(trips.len(), 3);
+    // S1 closes B2 first, then half of B1 with half of its fees
+    assert_eq!(
+        (trips[0].open_reference.as_str(), trips[0].size),
+        ("B2", 1.0)
+    );
+    assert_eq!(trips[0].pnl, 10.0);
+    assert_eq!(
+        (trips[1].open_reference.as_str(), trips[1].size),
+        ("B1", 1.0)
+    );
+    assert_eq!(trips[1].pnl, 20.0);
+    assert_eq!(trips[1].fees, 1.5);
+    assert_eq!(
+        (trips[2].open_reference.as_str(), trips[2].size),
+        ("B1", 1.0)
+    );
+    assert_eq!(trips[2].pnl, 5.0);
+    assert_eq!(trips[2].direction, Direction::Buy);
+    assert_eq!(result.pnl_by_instrument()["Germany 40"], 32.0);
+}
+
+fn transaction(
+    kind: &str,
+    instrument: &str,
+    reference: &str,
+    size: &str,
+    pnl: &str,
+) -> AccountTransaction {
+    serde_json::from_value(json!({
+        "date": "2025-07-02",
+        "dateUtc": "2025-07-02T15:00:00",
+        "openDateUtc": "2025-07-01T09:00:00",
+        "instrumentName": instrument,
+        "period": "-",
+        "profitAndLoss": pnl,
+        "transactionType": kind,
+        "reference": reference,
+        "openLevel": "18000",
+        "closeLevel": "17990",
+        "size": size,
+        "currency": "EUR",
+        "cashTransaction": kind != "DEAL"
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_fills_from_transactions() {
+    let transactions = EnrichedTransaction::enrich(&[
+        transaction("DEAL", "Germany 40", "D1", "-2", "E20.00"),
+        transaction("WITH", "Daily Admin Fee", "D1", "-", "E-1.50"),
+        transaction("DEPO", "Card Payment", "P1", "-", "E500.00"),
+    ]);
+    let fills = fills_from_transactions(&transactions);
+    assert_eq!(fills.len(), 2);
+    assert_eq!(fills[0].size, -2.0);
+    assert_eq!(fills[0].price, 18000.0);
+    assert_eq!(fills[1].size, 2.0);
+    assert_eq!(fills[1].fees, 1.5);
+
+    let result = match_fills(&fills, MatchingMethod::Fifo);
+    let trip = &result.round_trips[0];
+    assert_eq!(trip.direction, Direction::Sell);
+    assert_eq!(trip.pnl, 20.0);
+    assert_eq!(trip.net_pnl(), 18.5);
+    assert_eq!(trip.holding_period(), Duration::hours(30));
+    assert!(result.open_lots.is_empty());
+}